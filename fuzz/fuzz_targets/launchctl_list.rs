@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lsedr::features::service::parse_launchctl_list;
+use lsedr::shared::command::decode;
+
+fuzz_target!(|data: &[u8]| {
+    let output = decode(data);
+    let parsed = parse_launchctl_list(&output);
+    assert!(parsed.records.len() + parsed.rejected.len() <= output.lines().count());
+    for service in &parsed.records {
+        assert!(!service.name.is_empty() && !service.name.contains(char::is_control));
+        assert_eq!(service.state == "running", service.process_id.is_some());
+    }
+});
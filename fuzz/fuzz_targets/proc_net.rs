@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lsedr::features::network::parse_proc_net;
+use std::net::IpAddr;
+
+fuzz_target!(|data: &[u8]| {
+    // /proc/net tables are read with read_to_string, so only valid UTF-8 reaches the parser
+    let Ok(content) = std::str::from_utf8(data) else {
+        return;
+    };
+    for tcp in [true, false] {
+        let parsed = parse_proc_net(content, tcp);
+        assert!(parsed.records.len() + parsed.rejected.len() <= content.lines().count());
+        for socket in &parsed.records {
+            assert!(socket.local_address.parse::<IpAddr>().is_ok());
+            assert!(socket.remote_address.parse::<IpAddr>().is_ok());
+            assert!(tcp || socket.state == "NONE");
+        }
+    }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lsedr::features::service::parse_sc_query;
+use lsedr::shared::command::decode;
+
+fuzz_target!(|data: &[u8]| {
+    // The collector decodes whatever sc writes before parsing, so any bytes are possible
+    let output = decode(data);
+    let parsed = parse_sc_query(&output);
+    assert!(parsed.records.len() + parsed.rejected.len() <= output.lines().count());
+    for service in &parsed.records {
+        assert!(!service.name.is_empty() && !service.name.contains(char::is_control));
+        assert!(!service.display_name.is_empty() && !service.display_name.contains(char::is_control));
+        assert!(!service.state.is_empty());
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lsedr::features::persistence::parse_shell_link;
+
+fuzz_target!(|data: &[u8]| {
+    // Shortcuts in a Startup folder are written by whoever can write there
+    if let Some(link) = parse_shell_link(data) {
+        assert!(data.len() >= 0x4C);
+        if let Some(command) = link.command_line() {
+            assert!(!command.is_empty());
+        }
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lsedr::features::snmp_trap::parse_trap;
+
+fuzz_target!(|data: &[u8]| {
+    // Any host that can reach the trap port decides what the listener parses
+    if let Ok(trap) = parse_trap(data) {
+        assert!(!trap.trap_oid.is_empty());
+        if let Some(acknowledgement) = trap.acknowledgement(data) {
+            assert_eq!(acknowledgement.len(), data.len());
+        }
+    }
+});
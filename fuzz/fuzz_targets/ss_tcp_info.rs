@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lsedr::features::network::parse_ss_tcp_info;
+
+fuzz_target!(|data: &[u8]| {
+    // ss output is decoded to a String before it is parsed
+    let Ok(content) = std::str::from_utf8(data) else {
+        return;
+    };
+    let parsed = parse_ss_tcp_info(content);
+    assert!(parsed.records.len() + parsed.rejected.len() <= content.lines().count());
+    assert!(parsed.records.iter().all(|socket| socket.inode != 0));
+});
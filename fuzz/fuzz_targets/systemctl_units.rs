@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lsedr::features::service::parse_systemctl_units;
+use lsedr::shared::command::decode;
+
+fuzz_target!(|data: &[u8]| {
+    let output = decode(data);
+    let parsed = parse_systemctl_units(&output);
+    assert!(parsed.records.len() + parsed.rejected.len() <= output.lines().count());
+    for service in &parsed.records {
+        assert!(!service.name.is_empty() && !service.name.contains(char::is_control));
+        assert!(!service.display_name.is_empty() && !service.display_name.contains(char::is_control));
+        assert!(!service.state.is_empty());
+    }
+});
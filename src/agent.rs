@@ -0,0 +1,109 @@
+use crate::shared::storage::{StorageBackend, StorageDocument};
+use crate::shared::traits::AsyncDataCollector;
+use log::{error, info};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
+
+type CollectorTask = Box<dyn FnOnce(Arc<dyn StorageBackend>, watch::Receiver<bool>) -> JoinHandle<()> + Send>;
+
+// Runs collectors chosen by the embedding program, each on its own task, and stores what
+// they collect. The bundled `lsedr` binary adds detection rules, IOC matching and local
+// alerting on top; an embedding program that wants those wires them up itself.
+pub struct Agent {
+    storage: Arc<dyn StorageBackend>,
+    tasks: Vec<CollectorTask>,
+}
+
+impl Agent {
+    pub fn builder() -> AgentBuilder {
+        AgentBuilder::new()
+    }
+
+    // Runs every collector until `shutdown` completes, then lets each finish the
+    // collection it is in
+    pub async fn run<S: Future<Output = ()>>(self, shutdown: S) {
+        let (stop, stopped) = watch::channel(false);
+        let handles: Vec<JoinHandle<()>> = self
+            .tasks
+            .into_iter()
+            .map(|task| task(self.storage.clone(), stopped.clone()))
+            .collect();
+        shutdown.await;
+        stop.send(true).ok();
+        for handle in handles {
+            if let Err(e) = handle.await {
+                error!("Collector task failed: {}", e);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AgentBuilder {
+    storage: Option<Arc<dyn StorageBackend>>,
+    tasks: Vec<CollectorTask>,
+}
+
+impl AgentBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn storage(mut self, storage: Box<dyn StorageBackend>) -> Self {
+        self.storage = Some(Arc::from(storage));
+        self
+    }
+
+    // Collects every `period` and writes each event to `index`
+    pub fn collector<C, T>(mut self, index: &'static str, period: Duration, mut collector: C) -> Self
+    where
+        C: AsyncDataCollector<Vec<T>> + Send + 'static,
+        T: Serialize + Send + Sync + 'static,
+    {
+        self.tasks.push(Box::new(move |storage, mut stopped| {
+            info!("Collecting {} every {}s", index, period.as_secs());
+            tokio::spawn(async move {
+                let mut interval = time::interval(period);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {}
+                        _ = stopped.changed() => {}
+                    }
+                    if *stopped.borrow() {
+                        break;
+                    }
+                    let events = match AsyncDataCollector::<Vec<T>>::collect(&mut collector).await {
+                        Ok(events) if events.is_empty() => continue,
+                        Ok(events) => events,
+                        Err(e) => {
+                            error!("Error collecting {}: {}", index, e);
+                            continue;
+                        }
+                    };
+                    let result = match StorageDocument::many(index, &events) {
+                        Ok(documents) => storage.batch_store(documents).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = result {
+                        error!("Failed to store {} in {} storage: {}", index, storage.name(), e);
+                    }
+                }
+                info!("Stopped collecting {}", index);
+            })
+        }));
+        self
+    }
+
+    pub fn build(self) -> Result<Agent, String> {
+        Ok(Agent {
+            storage: self.storage.ok_or("storage is required")?,
+            tasks: self.tasks,
+        })
+    }
+}
@@ -0,0 +1,115 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(name = "lsedr", about = "SpathaX endpoint detection and response agent", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Run every enabled collector and ship events to the configured storage (default)
+    Run,
+    /// Load and validate the configuration, then exit
+    CheckConfig,
+    /// Collect and print events to stdout instead of the configured storage
+    Collect {
+        /// Run a single collection cycle and exit
+        #[arg(long)]
+        once: bool,
+        /// Output format of the printed documents
+        #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+        output: OutputFormat,
+    },
+    /// Check the autorun baseline and the autoruns present now for persistence and print
+    /// one audit report for this host
+    PersistenceAudit {
+        /// Write the report to the configured storage instead of printing it
+        #[arg(long)]
+        store: bool,
+        /// Output format of the printed report
+        #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+        output: OutputFormat,
+    },
+    /// Check that the storage backend and every enabled collector are healthy
+    Health,
+    /// Print the agent version and build target
+    Version,
+    /// Install the Elasticsearch ingest pipeline and ILM policies, then exit
+    EsSetup,
+    /// Run under the Windows service control manager or as a systemd service
+    Service {
+        /// Directory holding config/ and rules/; service managers start the agent elsewhere
+        #[arg(long)]
+        working_dir: Option<PathBuf>,
+    },
+    /// Register and start the agent as a Windows service or systemd unit, run from the
+    /// current directory
+    InstallService,
+    /// Stop and remove the Windows service or systemd unit
+    UninstallService,
+    /// Start, stop or show maintenance mode, which holds back alerts and heavy scans during
+    /// patch windows while events are still recorded
+    Maintenance {
+        #[command(subcommand)]
+        action: MaintenanceAction,
+    },
+    /// Move the agent's local state (baselines, spool, deferred queues, pseudonyms) to a
+    /// replacement machine through an encrypted archive
+    State {
+        #[command(subcommand)]
+        action: StateAction,
+    },
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum StateAction {
+    /// Write this machine's agent state to an encrypted archive; stop the agent first
+    Export {
+        /// Archive file to write
+        #[arg(long)]
+        output: PathBuf,
+        /// Environment variable holding the archive passphrase
+        #[arg(long, default_value = "LSEDR_STATE_KEY")]
+        key_env: String,
+    },
+    /// Restore an exported state on this machine; stop the agent first
+    Import {
+        /// Archive file to read
+        #[arg(long)]
+        input: PathBuf,
+        /// Environment variable holding the archive passphrase
+        #[arg(long, default_value = "LSEDR_STATE_KEY")]
+        key_env: String,
+        /// Overwrite the state this machine already has
+        #[arg(long)]
+        replace: bool,
+    },
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum MaintenanceAction {
+    /// Enter maintenance mode now; the running agent picks it up within seconds
+    Start {
+        /// How long maintenance lasts (default: maintenance.default_minutes)
+        #[arg(long)]
+        minutes: Option<u64>,
+        /// Recorded on every document stored during maintenance, e.g. a change ticket
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Leave a manually started maintenance mode early
+    Stop,
+    /// Show whether maintenance mode is active and why
+    Status,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// One {"index", "document"} JSON object per line
+    Json,
+    /// Indented JSON, one object per document
+    Pretty,
+}
@@ -0,0 +1,309 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector};
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::features::boot::models::{BootChange, BootEvent, BootEventBuilder};
+use crate::utils::load_config_section;
+use chrono::Utc;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BootConfig {
+    pub enabled: bool,
+    // EFI System Partition root; found from the mounts (Linux) or the boot manager (Windows) when unset
+    pub esp_path: Option<String>,
+    pub monitor_esp_files: bool,
+    pub monitor_boot_entries: bool,
+    // The ESP is rehashed in full on every scan, so scans are spaced out
+    pub scan_interval_secs: u64,
+}
+
+impl Default for BootConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            esp_path: None,
+            monitor_esp_files: true,
+            monitor_boot_entries: true,
+            scan_interval_secs: 3600,
+        }
+    }
+}
+
+impl BootConfig {
+    pub fn load() -> Self {
+        load_config_section("boot")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootEntry {
+    pub id: String,
+    pub description: Option<String>,
+    // Device path of the loader the entry starts
+    pub loader: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BootConfiguration {
+    pub entries: HashMap<String, BootEntry>,
+    pub order: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EfiFile {
+    size: u64,
+    sha256: String,
+}
+
+#[cfg(target_os = "linux")]
+use crate::features::boot::linux::{boot_configuration, esp_root};
+#[cfg(windows)]
+use crate::features::boot::win32::{boot_configuration, esp_root};
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn esp_root() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn boot_configuration() -> Option<BootConfiguration> {
+    None
+}
+
+// Boot-level persistence: every file on the EFI System Partition with its hash, and the
+// firmware boot entries with their order. The first scan is the baseline; later scans
+// report what was added, replaced or removed. File hashes are compared rather than
+// timestamps, which a bootkit installer can restore.
+pub struct BootCollector {
+    config: BootConfig,
+    esp: Option<PathBuf>,
+    files: Option<HashMap<String, EfiFile>>,
+    boot: Option<BootConfiguration>,
+    last_scan: Option<Instant>,
+    host: Arc<HostIdentityService>,
+}
+
+impl BootCollector {
+    pub fn new() -> Self {
+        Self::with_config(BootConfig::load())
+    }
+
+    pub fn with_config(config: BootConfig) -> Self {
+        let esp = config.esp_path.as_ref().map(PathBuf::from).or_else(esp_root);
+        match &esp {
+            Some(esp) => info!("Monitoring EFI System Partition at {}", esp.display()),
+            None => warn!("No EFI System Partition found, only boot entries are monitored"),
+        }
+
+        Self {
+            config,
+            esp,
+            files: None,
+            boot: None,
+            last_scan: None,
+            host: HostIdentityService::global(),
+        }
+    }
+
+    fn event(&self, change: BootChange) -> BootEventBuilder {
+        BootEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("boot"))
+            .change(change)
+    }
+
+    fn hash_file(path: &Path) -> Option<String> {
+        let mut file = fs::File::open(path).ok()?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher).ok()?;
+        Some(format!("{:x}", hasher.finalize()))
+    }
+
+    fn scan_directory(root: &Path, directory: &Path, files: &mut HashMap<String, EfiFile>) {
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug!("Failed to read {}: {}", directory.display(), e);
+                return;
+            }
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let path = entry.path();
+            if metadata.is_dir() {
+                Self::scan_directory(root, &path, files);
+                continue;
+            }
+            let Some(sha256) = Self::hash_file(&path) else {
+                continue;
+            };
+            // FAT is case-insensitive, so are the keys
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().to_lowercase();
+            files.insert(relative, EfiFile { size: metadata.len(), sha256 });
+        }
+    }
+
+    fn file_events(&mut self) -> Result<Vec<BootEvent>, CollectionError> {
+        let Some(esp) = self.esp.clone() else {
+            return Ok(Vec::new());
+        };
+        let mut current = HashMap::new();
+        Self::scan_directory(&esp, &esp, &mut current);
+        debug!("Hashed {} files on the EFI System Partition", current.len());
+
+        let mut events = Vec::new();
+        if let Some(previous) = self.files.take() {
+            for (path, file) in &current {
+                let builder = match previous.get(path) {
+                    None => self.event(BootChange::EfiFileAdded),
+                    Some(old) if old.sha256 != file.sha256 => {
+                        self.event(BootChange::EfiFileModified).previous_sha256(Some(old.sha256.clone()))
+                    }
+                    Some(_) => continue,
+                };
+                warn!("EFI System Partition file changed: {}", path);
+                events.push(
+                    builder
+                        .path(path.clone())
+                        .size(file.size)
+                        .sha256(Some(file.sha256.clone()))
+                        .build()
+                        .map_err(CollectionError::Parse)?,
+                );
+            }
+            for (path, file) in previous.iter().filter(|(path, _)| !current.contains_key(*path)) {
+                events.push(
+                    self.event(BootChange::EfiFileRemoved)
+                        .path(path.clone())
+                        .previous_sha256(Some(file.sha256.clone()))
+                        .build()
+                        .map_err(CollectionError::Parse)?,
+                );
+            }
+        }
+        self.files = Some(current);
+        Ok(events)
+    }
+
+    fn entry_event(&self, change: BootChange, entry: &BootEntry) -> BootEventBuilder {
+        self.event(change)
+            .entry_id(entry.id.clone())
+            .description(entry.description.clone())
+            .loader(entry.loader.clone())
+    }
+
+    fn boot_entry_events(&mut self) -> Result<Vec<BootEvent>, CollectionError> {
+        let Some(current) = boot_configuration() else {
+            return Ok(Vec::new());
+        };
+
+        let mut events = Vec::new();
+        if let Some(previous) = self.boot.take() {
+            for (id, entry) in &current.entries {
+                let builder = match previous.entries.get(id) {
+                    None => self.entry_event(BootChange::BootEntryAdded, entry),
+                    Some(old) if old != entry => {
+                        self.entry_event(BootChange::BootEntryModified, entry).previous_loader(old.loader.clone())
+                    }
+                    Some(_) => continue,
+                };
+                warn!("Boot entry {} changed: {:?}", id, entry.loader);
+                events.push(builder.build().map_err(CollectionError::Parse)?);
+            }
+            for entry in previous.entries.values().filter(|entry| !current.entries.contains_key(&entry.id)) {
+                events.push(
+                    self.entry_event(BootChange::BootEntryRemoved, entry)
+                        .build()
+                        .map_err(CollectionError::Parse)?,
+                );
+            }
+            if previous.order != current.order {
+                warn!("Boot order changed from {:?} to {:?}", previous.order, current.order);
+                events.push(
+                    self.event(BootChange::BootOrderChanged)
+                        .boot_order(current.order.clone())
+                        .previous_boot_order(previous.order.clone())
+                        .build()
+                        .map_err(CollectionError::Parse)?,
+                );
+            }
+        }
+        self.boot = Some(current);
+        Ok(events)
+    }
+
+    fn collect_events(&mut self) -> Result<Vec<BootEvent>, CollectionError> {
+        let interval = Duration::from_secs(self.config.scan_interval_secs);
+        if self.last_scan.is_some_and(|last_scan| last_scan.elapsed() < interval) {
+            return Ok(Vec::new());
+        }
+        self.last_scan = Some(Instant::now());
+
+        let mut events = Vec::new();
+        if self.config.monitor_esp_files {
+            events.extend(self.file_events()?);
+        }
+        if self.config.monitor_boot_entries {
+            events.extend(self.boot_entry_events()?);
+        }
+        info!("Collected {} boot configuration events", events.len());
+        Ok(events)
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if !cfg!(any(target_os = "linux", target_os = "windows")) {
+            return Err(CollectionError::SystemApi(
+                "Boot configuration monitoring is only supported on Linux and Windows".to_string()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DataCollector<Vec<BootEvent>> for BootCollector {
+    fn collect(&mut self) -> Result<Vec<BootEvent>, CollectionError> {
+        self.collect_events()
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<BootEvent>> for BootCollector {
+    async fn collect(&mut self) -> Result<Vec<BootEvent>, CollectionError> {
+        DataCollector::collect(self)
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+impl Default for BootCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
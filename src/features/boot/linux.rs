@@ -0,0 +1,71 @@
+use crate::features::boot::collector::{BootConfiguration, BootEntry};
+use crate::shared::command;
+use log::debug;
+use std::fs;
+use std::path::PathBuf;
+
+// Where distributions mount the EFI System Partition, in order of preference
+const ESP_MOUNT_POINTS: [&str; 3] = ["/boot/efi", "/efi", "/boot"];
+
+pub fn esp_root() -> Option<PathBuf> {
+    let mounts = fs::read_to_string("/proc/mounts").ok()?;
+    let vfat: Vec<&str> = mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount_point = fields.next()?;
+            (fields.next()? == "vfat").then_some(mount_point)
+        })
+        .collect();
+    ESP_MOUNT_POINTS
+        .iter()
+        .find(|candidate| vfat.contains(candidate))
+        .map(PathBuf::from)
+}
+
+// Firmware boot entries from efibootmgr, None on BIOS systems or without efibootmgr
+pub fn boot_configuration() -> Option<BootConfiguration> {
+    let output = match command::run("efibootmgr", ["-v"]) {
+        Ok(output) if output.success => output,
+        Ok(output) => {
+            debug!("efibootmgr failed: {}", output.stderr.trim());
+            return None;
+        }
+        Err(e) => {
+            debug!("Failed to run efibootmgr: {}", e);
+            return None;
+        }
+    };
+
+    let mut configuration = BootConfiguration::default();
+    for line in output.stdout.lines() {
+        if let Some(order) = line.strip_prefix("BootOrder:") {
+            configuration.order = order.trim().split(',').map(|id| id.trim().to_string()).collect();
+            continue;
+        }
+        // "Boot0001* ubuntu\tHD(1,GPT,...)/File(\EFI\ubuntu\shimx64.efi)"; newer efibootmgr
+        // separates description and device path with two spaces instead of a tab
+        let Some(rest) = line.strip_prefix("Boot") else {
+            continue;
+        };
+        if rest.len() < 5 || !rest[..4].chars().all(|c| c.is_ascii_hexdigit()) {
+            continue;
+        }
+        let id = rest[..4].to_string();
+        let rest = rest[4..].trim_start_matches('*').trim_start();
+        let (description, loader) = match rest.split_once('\t').or_else(|| rest.split_once("  ")) {
+            Some((description, loader)) => (description.trim(), Some(loader.trim().to_string())),
+            None => (rest.trim(), None),
+        };
+        configuration.entries.insert(
+            id.clone(),
+            BootEntry {
+                id,
+                description: Some(description.to_string()).filter(|description| !description.is_empty()),
+                loader: loader.filter(|loader| !loader.is_empty()),
+            },
+        );
+    }
+    Some(configuration)
+}
@@ -0,0 +1,219 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootChange {
+    EfiFileAdded,
+    EfiFileModified,
+    EfiFileRemoved,
+    BootEntryAdded,
+    BootEntryModified,
+    BootEntryRemoved,
+    BootOrderChanged,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub change: BootChange,
+    // File on the EFI System Partition, relative to its root
+    pub path: Option<String>,
+    pub size: Option<u64>,
+    pub sha256: Option<String>,
+    pub previous_sha256: Option<String>,
+    // Firmware boot entry (Boot#### or BCD identifier)
+    pub entry_id: Option<String>,
+    pub description: Option<String>,
+    pub loader: Option<String>,
+    pub previous_loader: Option<String>,
+    pub boot_order: Vec<String>,
+    pub previous_boot_order: Vec<String>,
+}
+
+impl Event for BootEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.change {
+            BootChange::EfiFileAdded => "efi_file_added",
+            BootChange::EfiFileModified => "efi_file_modified",
+            BootChange::EfiFileRemoved => "efi_file_removed",
+            BootChange::BootEntryAdded => "boot_entry_added",
+            BootChange::BootEntryModified => "boot_entry_modified",
+            BootChange::BootEntryRemoved => "boot_entry_removed",
+            BootChange::BootOrderChanged => "boot_order_changed",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        match self.change {
+            // A replaced boot loader is the bootkit case itself
+            BootChange::EfiFileModified | BootChange::BootEntryModified => Severity::High,
+            BootChange::EfiFileAdded | BootChange::BootEntryAdded | BootChange::BootOrderChanged => Severity::Medium,
+            BootChange::EfiFileRemoved | BootChange::BootEntryRemoved => Severity::Low,
+        }
+    }
+}
+
+impl Identifiable for BootEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for BootEvent {
+    fn validate(&self) -> Result<(), String> {
+        match self.change {
+            BootChange::EfiFileAdded | BootChange::EfiFileModified | BootChange::EfiFileRemoved
+                if self.path.is_none() =>
+            {
+                Err("EFI file events require a path".to_string())
+            }
+            BootChange::BootEntryAdded | BootChange::BootEntryModified | BootChange::BootEntryRemoved
+                if self.entry_id.is_none() =>
+            {
+                Err("Boot entry events require an entry id".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct BootEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    change: Option<BootChange>,
+    path: Option<String>,
+    size: Option<u64>,
+    sha256: Option<String>,
+    previous_sha256: Option<String>,
+    entry_id: Option<String>,
+    description: Option<String>,
+    loader: Option<String>,
+    previous_loader: Option<String>,
+    boot_order: Vec<String>,
+    previous_boot_order: Vec<String>,
+}
+
+impl BootEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn change(mut self, change: BootChange) -> Self {
+        self.change = Some(change);
+        self
+    }
+
+    pub fn path(mut self, path: String) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn sha256(mut self, sha256: Option<String>) -> Self {
+        self.sha256 = sha256;
+        self
+    }
+
+    pub fn previous_sha256(mut self, previous_sha256: Option<String>) -> Self {
+        self.previous_sha256 = previous_sha256;
+        self
+    }
+
+    pub fn entry_id(mut self, entry_id: String) -> Self {
+        self.entry_id = Some(entry_id);
+        self
+    }
+
+    pub fn description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn loader(mut self, loader: Option<String>) -> Self {
+        self.loader = loader;
+        self
+    }
+
+    pub fn previous_loader(mut self, previous_loader: Option<String>) -> Self {
+        self.previous_loader = previous_loader;
+        self
+    }
+
+    pub fn boot_order(mut self, boot_order: Vec<String>) -> Self {
+        self.boot_order = boot_order;
+        self
+    }
+
+    pub fn previous_boot_order(mut self, previous_boot_order: Vec<String>) -> Self {
+        self.previous_boot_order = previous_boot_order;
+        self
+    }
+
+    pub fn build(self) -> Result<BootEvent, String> {
+        let event = BootEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            change: self.change.ok_or("change is required")?,
+            path: self.path,
+            size: self.size,
+            sha256: self.sha256,
+            previous_sha256: self.previous_sha256,
+            entry_id: self.entry_id,
+            description: self.description,
+            loader: self.loader,
+            previous_loader: self.previous_loader,
+            boot_order: self.boot_order,
+            previous_boot_order: self.previous_boot_order,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::features::boot::collector::{BootConfiguration, BootEntry};
+use crate::shared::command;
+use log::debug;
+use std::path::PathBuf;
+
+fn bcdedit(args: &[&str]) -> Option<String> {
+    match command::run("bcdedit", args) {
+        Ok(output) if output.success => Some(output.stdout),
+        Ok(output) => {
+            debug!("bcdedit {} failed: {}", args.join(" "), output.stdout.trim());
+            None
+        }
+        Err(e) => {
+            debug!("Failed to run bcdedit: {}", e);
+            None
+        }
+    }
+}
+
+// Entries of bcdedit output as (field, values) lists. Multi-valued fields such as
+// displayorder continue on indented lines without a field name.
+fn entries(output: &str) -> Vec<Vec<(String, Vec<String>)>> {
+    let mut entries = Vec::new();
+    for block in output.replace("\r\n", "\n").split("\n\n") {
+        let mut fields: Vec<(String, Vec<String>)> = Vec::new();
+        for line in block.lines() {
+            if line.trim().is_empty() || line.starts_with('-') {
+                continue;
+            }
+            if line.starts_with(' ') {
+                if let Some((_, values)) = fields.last_mut() {
+                    values.push(line.trim().to_string());
+                }
+                continue;
+            }
+            match line.split_once(char::is_whitespace) {
+                Some((name, value)) if !value.trim().is_empty() => {
+                    fields.push((name.to_string(), vec![value.trim().to_string()]))
+                }
+                // The entry title ("Windows Boot Manager") has no value column
+                _ => {}
+            }
+        }
+        if !fields.is_empty() {
+            entries.push(fields);
+        }
+    }
+    entries
+}
+
+fn field<'a>(entry: &'a [(String, Vec<String>)], name: &str) -> Option<&'a Vec<String>> {
+    entry.iter().find(|(field, _)| field == name).map(|(_, values)| values)
+}
+
+// The ESP has no drive letter; the boot manager's device names its volume, which is
+// reachable through the GLOBALROOT namespace when running as SYSTEM
+pub fn esp_root() -> Option<PathBuf> {
+    let output = bcdedit(&["/enum", "{bootmgr}"])?;
+    entries(&output).iter().find_map(|entry| {
+        let device = field(entry, "device")?.first()?;
+        let volume = device.strip_prefix("partition=")?;
+        Some(PathBuf::from(format!(r"\\?\GLOBALROOT{}\", volume)))
+    })
+}
+
+// Firmware boot entries and their order; /v keeps GUIDs instead of aliases so the
+// display order and identifiers match
+pub fn boot_configuration() -> Option<BootConfiguration> {
+    let output = bcdedit(&["/enum", "firmware", "/v"])?;
+    let mut configuration = BootConfiguration::default();
+    for entry in entries(&output) {
+        // Element names are the same in every language except the identifier label
+        // ("Bezeichner", "識別子"), which always comes first
+        let Some(id) = entry.first().and_then(|(_, values)| values.first()).cloned() else {
+            continue;
+        };
+        // The firmware boot manager itself only carries the order
+        if let Some(order) = field(&entry, "displayorder") {
+            configuration.order = order.clone();
+            continue;
+        }
+        let device = field(&entry, "device").and_then(|values| values.first());
+        let path = field(&entry, "path").and_then(|values| values.first());
+        let loader = match (device, path) {
+            (Some(device), Some(path)) => Some(format!("{}{}", device, path)),
+            (Some(value), None) | (None, Some(value)) => Some(value.clone()),
+            (None, None) => None,
+        };
+        let description = field(&entry, "description").and_then(|values| values.first()).cloned();
+        configuration.entries.insert(id.clone(), BootEntry { id, description, loader });
+    }
+    Some(configuration)
+}
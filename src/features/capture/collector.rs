@@ -0,0 +1,300 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector};
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::process_resolver::ProcessResolver;
+use crate::shared::user_resolver::UserResolver;
+use crate::features::capture::models::{CaptureEvent, CaptureEventBuilder, CaptureKind};
+use crate::utils::load_config_section;
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureConfig {
+    // Off unless explicitly enabled, even though no content is ever read
+    pub enabled: bool,
+    pub monitor_clipboard: bool,
+    pub sample_interval_ms: u64,
+    // Clipboard opens plus writes per minute above which a process is reported
+    pub clipboard_accesses_per_minute: f64,
+    pub monitor_screen_capture: bool,
+    // Capability access manager entries recording screen capture API use
+    pub capabilities: Vec<String>,
+    // Executable names (case-insensitive) never reported
+    pub allowed_processes: Vec<String>,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            monitor_clipboard: true,
+            sample_interval_ms: 50,
+            clipboard_accesses_per_minute: 30.0,
+            monitor_screen_capture: true,
+            capabilities: vec![
+                String::from("graphicsCaptureProgrammatic"),
+                String::from("graphicsCaptureWithoutBorder"),
+            ],
+            allowed_processes: [
+                "explorer.exe", "rdpclip.exe", "textinputhost.exe", "ctfmon.exe", "snippingtool.exe",
+                "screenclippinghost.exe", "dwm.exe",
+            ]
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        }
+    }
+}
+
+impl CaptureConfig {
+    pub fn load() -> Self {
+        load_config_section("capture")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ClipboardCounter {
+    pub opens: u64,
+    pub writes: u64,
+}
+
+// Clipboard activity per PID since the last collection
+pub type ClipboardCounts = HashMap<u32, ClipboardCounter>;
+
+#[derive(Debug, Clone)]
+pub struct ScreenCaptureUsage {
+    pub user_sid: String,
+    pub capability: String,
+    // Executable path for desktop apps, package family name for store apps
+    pub application: String,
+    pub started: Option<DateTime<Utc>>,
+    pub stopped: Option<DateTime<Utc>>,
+}
+
+#[cfg(windows)]
+use crate::features::capture::win32::{sample_clipboard, screen_capture_usage};
+
+#[cfg(not(windows))]
+fn sample_clipboard(_counts: std::sync::Weak<Mutex<ClipboardCounts>>, _interval: Duration) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "no clipboard sampling on this platform"))
+}
+
+#[cfg(not(windows))]
+fn screen_capture_usage(_capabilities: &[String]) -> Vec<ScreenCaptureUsage> {
+    Vec::new()
+}
+
+// Metadata-only exfiltration indicators on Windows: processes hammering the clipboard and
+// processes using the screen capture APIs. Clipboard contents and captured frames are never
+// read, only which process did it, when and how often.
+pub struct CaptureCollector {
+    config: CaptureConfig,
+    clipboard: Arc<Mutex<ClipboardCounts>>,
+    window_started: Instant,
+    sampling: bool,
+    // Latest capture start seen per (user, capability, application)
+    capture_starts: HashMap<(String, String, String), DateTime<Utc>>,
+    capture_baselined: bool,
+    resolver: Arc<ProcessResolver>,
+    users: Arc<UserResolver>,
+    host: Arc<HostIdentityService>,
+}
+
+impl CaptureCollector {
+    pub fn new() -> Self {
+        Self::with_config(CaptureConfig::load())
+    }
+
+    pub fn with_config(config: CaptureConfig) -> Self {
+        let clipboard = Arc::new(Mutex::new(ClipboardCounts::new()));
+        let sampling = config.monitor_clipboard
+            && match sample_clipboard(Arc::downgrade(&clipboard), Duration::from_millis(config.sample_interval_ms.max(10))) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("Clipboard sampling unavailable: {}", e);
+                    false
+                }
+            };
+
+        Self {
+            config,
+            clipboard,
+            window_started: Instant::now(),
+            sampling,
+            capture_starts: HashMap::new(),
+            capture_baselined: false,
+            resolver: ProcessResolver::global(),
+            users: UserResolver::global(),
+            host: HostIdentityService::global(),
+        }
+    }
+
+    fn is_allowed(&self, name: &str) -> bool {
+        let name = Path::new(name)
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| name.to_lowercase());
+        self.config.allowed_processes.iter().any(|allowed| allowed.to_lowercase() == name)
+    }
+
+    fn event(&self, kind: CaptureKind, process_name: String) -> CaptureEventBuilder {
+        CaptureEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("capture"))
+            .kind(kind)
+            .process_name(process_name)
+    }
+
+    fn clipboard_events(&mut self) -> Result<Vec<CaptureEvent>, CollectionError> {
+        let counts = {
+            let mut counts = self
+                .clipboard
+                .lock()
+                .map_err(|_| CollectionError::SystemApi("Clipboard counters lock poisoned".to_string()))?;
+            std::mem::take(&mut *counts)
+        };
+        let window = self.window_started.elapsed();
+        self.window_started = Instant::now();
+        // A very short window (first collection) would turn a handful of pastes into a high rate
+        if window < Duration::from_secs(10) {
+            return Ok(Vec::new());
+        }
+        let minutes = window.as_secs_f64() / 60.0;
+        let own_pid = std::process::id();
+
+        let mut events = Vec::new();
+        for (pid, counter) in counts {
+            let rate = (counter.opens + counter.writes) as f64 / minutes;
+            if pid == own_pid || rate < self.config.clipboard_accesses_per_minute {
+                continue;
+            }
+            let details = self.resolver.resolve(pid);
+            let name = details.as_ref().map(|details| details.name.clone()).unwrap_or_else(|| String::from("unknown"));
+            if self.is_allowed(&name) {
+                continue;
+            }
+
+            warn!("{} (pid {}) accessed the clipboard {:.0} times per minute", name, pid, rate);
+            let mut builder = self
+                .event(CaptureKind::ClipboardAccess, name)
+                .process_id(pid)
+                .clipboard_opens(counter.opens)
+                .clipboard_writes(counter.writes)
+                .accesses_per_minute(rate)
+                .window_secs(window.as_secs());
+            if let Some(details) = details {
+                if let Some(path) = details.path {
+                    builder = builder.process_path(path);
+                }
+                if let Some(user) = details.user {
+                    builder = builder.user(user);
+                }
+            }
+            events.push(builder.build().map_err(CollectionError::Parse)?);
+        }
+        Ok(events)
+    }
+
+    // Reports every capture start newer than the one last seen. Starts recorded before the
+    // agent came up are the baseline, not new captures.
+    fn screen_capture_events(&mut self) -> Result<Vec<CaptureEvent>, CollectionError> {
+        let mut events = Vec::new();
+        for usage in screen_capture_usage(&self.config.capabilities) {
+            let Some(started) = usage.started else {
+                continue;
+            };
+            let key = (usage.user_sid.clone(), usage.capability.clone(), usage.application.clone());
+            let previous = self.capture_starts.insert(key, started);
+            if !self.capture_baselined || previous.is_some_and(|previous| previous >= started) {
+                continue;
+            }
+            if self.is_allowed(&usage.application) {
+                debug!("Ignoring screen capture by allowed application {}", usage.application);
+                continue;
+            }
+
+            let name = Path::new(&usage.application)
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| usage.application.clone());
+            warn!("{} used {} at {}", usage.application, usage.capability, started);
+            let mut builder = self
+                .event(CaptureKind::ScreenCapture, name)
+                .process_path(usage.application)
+                .user(self.users.display_id(&usage.user_sid))
+                .capability(usage.capability)
+                .capture_started(started);
+            if let Some(stopped) = usage.stopped.filter(|stopped| *stopped >= started) {
+                builder = builder.capture_stopped(stopped);
+            }
+            events.push(builder.build().map_err(CollectionError::Parse)?);
+        }
+        self.capture_baselined = true;
+        Ok(events)
+    }
+
+    fn collect_events(&mut self) -> Result<Vec<CaptureEvent>, CollectionError> {
+        let mut events = Vec::new();
+        if self.sampling {
+            events.extend(self.clipboard_events()?);
+        }
+        if self.config.monitor_screen_capture {
+            events.extend(self.screen_capture_events()?);
+        }
+        info!("Collected {} clipboard / screen capture events", events.len());
+        Ok(events)
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if !cfg!(target_os = "windows") {
+            return Err(CollectionError::SystemApi(
+                "Clipboard and screen capture monitoring is only supported on Windows".to_string()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DataCollector<Vec<CaptureEvent>> for CaptureCollector {
+    fn collect(&mut self) -> Result<Vec<CaptureEvent>, CollectionError> {
+        self.collect_events()
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<CaptureEvent>> for CaptureCollector {
+    async fn collect(&mut self) -> Result<Vec<CaptureEvent>, CollectionError> {
+        DataCollector::collect(self)
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+impl Default for CaptureCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
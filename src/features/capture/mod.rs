@@ -0,0 +1,7 @@
+mod collector;
+mod models;
+#[cfg(windows)]
+mod win32;
+
+pub use collector::{CaptureCollector, CaptureConfig};
+pub use models::{CaptureEvent, CaptureEventBuilder, CaptureKind};
@@ -0,0 +1,211 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureKind {
+    // A process opening or writing the clipboard far more often than a person would
+    ClipboardAccess,
+    // A process using the screen capture APIs
+    ScreenCapture,
+}
+
+// Who touched the clipboard or the screen and how often. Never the content itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub kind: CaptureKind,
+    pub process_id: Option<u32>,
+    pub process_name: String,
+    pub process_path: Option<String>,
+    pub user: Option<String>,
+    pub clipboard_opens: Option<u64>,
+    pub clipboard_writes: Option<u64>,
+    pub accesses_per_minute: Option<f64>,
+    pub window_secs: Option<u64>,
+    // Capability access manager entry the screen capture was recorded under
+    pub capability: Option<String>,
+    pub capture_started: Option<DateTime<Utc>>,
+    pub capture_stopped: Option<DateTime<Utc>>,
+}
+
+impl Event for CaptureEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.kind {
+            CaptureKind::ClipboardAccess => "clipboard_access",
+            CaptureKind::ScreenCapture => "screen_capture",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        match self.kind {
+            CaptureKind::ClipboardAccess => Severity::Medium,
+            CaptureKind::ScreenCapture => Severity::High,
+        }
+    }
+}
+
+impl Identifiable for CaptureEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for CaptureEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.process_name.is_empty() {
+            return Err("Process name cannot be empty".to_string());
+        }
+        if self.kind == CaptureKind::ClipboardAccess && self.accesses_per_minute.is_none() {
+            return Err("Clipboard access events require an access rate".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct CaptureEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    kind: Option<CaptureKind>,
+    process_id: Option<u32>,
+    process_name: Option<String>,
+    process_path: Option<String>,
+    user: Option<String>,
+    clipboard_opens: Option<u64>,
+    clipboard_writes: Option<u64>,
+    accesses_per_minute: Option<f64>,
+    window_secs: Option<u64>,
+    capability: Option<String>,
+    capture_started: Option<DateTime<Utc>>,
+    capture_stopped: Option<DateTime<Utc>>,
+}
+
+impl CaptureEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn kind(mut self, kind: CaptureKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    pub fn process_id(mut self, process_id: u32) -> Self {
+        self.process_id = Some(process_id);
+        self
+    }
+
+    pub fn process_name(mut self, process_name: String) -> Self {
+        self.process_name = Some(process_name);
+        self
+    }
+
+    pub fn process_path(mut self, process_path: String) -> Self {
+        self.process_path = Some(process_path);
+        self
+    }
+
+    pub fn user(mut self, user: String) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    pub fn clipboard_opens(mut self, clipboard_opens: u64) -> Self {
+        self.clipboard_opens = Some(clipboard_opens);
+        self
+    }
+
+    pub fn clipboard_writes(mut self, clipboard_writes: u64) -> Self {
+        self.clipboard_writes = Some(clipboard_writes);
+        self
+    }
+
+    pub fn accesses_per_minute(mut self, accesses_per_minute: f64) -> Self {
+        self.accesses_per_minute = Some(accesses_per_minute);
+        self
+    }
+
+    pub fn window_secs(mut self, window_secs: u64) -> Self {
+        self.window_secs = Some(window_secs);
+        self
+    }
+
+    pub fn capability(mut self, capability: String) -> Self {
+        self.capability = Some(capability);
+        self
+    }
+
+    pub fn capture_started(mut self, capture_started: DateTime<Utc>) -> Self {
+        self.capture_started = Some(capture_started);
+        self
+    }
+
+    pub fn capture_stopped(mut self, capture_stopped: DateTime<Utc>) -> Self {
+        self.capture_stopped = Some(capture_stopped);
+        self
+    }
+
+    pub fn build(self) -> Result<CaptureEvent, String> {
+        let event = CaptureEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            kind: self.kind.ok_or("kind is required")?,
+            process_id: self.process_id,
+            process_name: self.process_name.ok_or("process_name is required")?,
+            process_path: self.process_path,
+            user: self.user,
+            clipboard_opens: self.clipboard_opens,
+            clipboard_writes: self.clipboard_writes,
+            accesses_per_minute: self.accesses_per_minute,
+            window_secs: self.window_secs,
+            capability: self.capability,
+            capture_started: self.capture_started,
+            capture_stopped: self.capture_stopped,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
@@ -0,0 +1,137 @@
+use crate::features::capture::collector::{ClipboardCounts, ScreenCaptureUsage};
+use crate::shared::command;
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::io;
+use std::sync::{Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::System::DataExchange::{GetClipboardOwner, GetClipboardSequenceNumber, GetOpenClipboardWindow};
+use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
+
+const CONSENT_STORE: &str = r"Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore";
+
+// Seconds between 1601-01-01 and 1970-01-01
+const FILETIME_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+fn window_process(window: HWND) -> Option<u32> {
+    if window.0 == 0 {
+        return None;
+    }
+    let mut pid = 0u32;
+    unsafe {
+        GetWindowThreadProcessId(window, Some(&mut pid));
+    }
+    if pid == 0 { None } else { Some(pid) }
+}
+
+// Polls which window holds the clipboard open and who wrote it last. Clipboard opens last
+// milliseconds, so sampling undercounts, but a process scraping the clipboard in a loop is
+// hit in proportion to how often it does it. Only sees the clipboard of the session the
+// agent runs in; OpenClipboard(NULL) callers have no window and are invisible.
+pub fn sample_clipboard(counts: Weak<Mutex<ClipboardCounts>>, interval: Duration) -> io::Result<()> {
+    thread::Builder::new()
+        .name(String::from("clipboard-sampler"))
+        .spawn(move || {
+            let mut holder = None;
+            let mut sequence = unsafe { GetClipboardSequenceNumber() };
+            loop {
+                thread::sleep(interval);
+                let Some(shared) = counts.upgrade() else {
+                    return;
+                };
+
+                let open = window_process(unsafe { GetOpenClipboardWindow() });
+                let current = unsafe { GetClipboardSequenceNumber() };
+                let written = current != sequence;
+                sequence = current;
+                let writer = if written { window_process(unsafe { GetClipboardOwner() }) } else { None };
+
+                if let Ok(mut counts) = shared.lock() {
+                    // The same open seen on consecutive samples is counted once
+                    if let Some(pid) = open.filter(|pid| holder != Some(*pid)) {
+                        counts.entry(pid).or_default().opens += 1;
+                    }
+                    if let Some(pid) = writer {
+                        counts.entry(pid).or_default().writes += 1;
+                    }
+                };
+                holder = open;
+            }
+        })?;
+    Ok(())
+}
+
+fn filetime(value: &str) -> Option<DateTime<Utc>> {
+    let ticks = u64::from_str_radix(value.trim().trim_start_matches("0x"), 16).ok()?;
+    if ticks == 0 {
+        return None;
+    }
+    DateTime::from_timestamp((ticks / 10_000_000) as i64 - FILETIME_EPOCH_OFFSET, 0)
+}
+
+fn user_hives() -> Vec<String> {
+    let Ok(output) = command::run("reg", ["query", "HKU"]) else {
+        return Vec::new();
+    };
+    output
+        .stdout
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix(r"HKEY_USERS\"))
+        .filter(|sid| sid.starts_with("S-1-5-21-") && !sid.ends_with("_Classes"))
+        .map(str::to_string)
+        .collect()
+}
+
+// Applications the capability access manager recorded as using a capability, for every
+// loaded user hive. Windows only keeps the start and stop of the latest use.
+pub fn screen_capture_usage(capabilities: &[String]) -> Vec<ScreenCaptureUsage> {
+    let mut usage = Vec::new();
+    for sid in user_hives() {
+        for capability in capabilities {
+            let root = format!(r"HKEY_USERS\{}\{}\{}", sid, CONSENT_STORE, capability);
+            let output = match command::run("reg", ["query", root.as_str(), "/s"]) {
+                Ok(output) if output.success => output,
+                Ok(_) => continue,
+                Err(e) => {
+                    debug!("Failed to query {}: {}", root, e);
+                    continue;
+                }
+            };
+
+            let mut current: Option<ScreenCaptureUsage> = None;
+            for line in output.stdout.lines() {
+                if let Some(key) = line.strip_prefix(&root) {
+                    usage.extend(current.take().filter(|entry| entry.started.is_some()));
+                    // Desktop apps sit under NonPackaged with '\' in their path replaced by '#'
+                    let application = key.trim_start_matches('\\');
+                    let application = application.strip_prefix(r"NonPackaged\").unwrap_or(application);
+                    if application.is_empty() {
+                        continue;
+                    }
+                    current = Some(ScreenCaptureUsage {
+                        user_sid: sid.clone(),
+                        capability: capability.clone(),
+                        application: application.replace('#', "\\"),
+                        started: None,
+                        stopped: None,
+                    });
+                    continue;
+                }
+
+                let Some(entry) = current.as_mut() else {
+                    continue;
+                };
+                let mut fields = line.split_whitespace();
+                match (fields.next(), fields.next(), fields.next()) {
+                    (Some("LastUsedTimeStart"), Some("REG_QWORD"), Some(value)) => entry.started = filetime(value),
+                    (Some("LastUsedTimeStop"), Some("REG_QWORD"), Some(value)) => entry.stopped = filetime(value),
+                    _ => {}
+                }
+            }
+            usage.extend(current.take().filter(|entry| entry.started.is_some()));
+        }
+    }
+    usage
+}
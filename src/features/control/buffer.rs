@@ -0,0 +1,89 @@
+use crate::features::control::server::ControlConfig;
+use crate::shared::storage::StorageDocument;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+
+// One event as it was written to storage, enriched and pseudonymized
+#[derive(Debug, Clone)]
+pub struct BufferedEvent {
+    pub buffered_at: DateTime<Utc>,
+    pub document: StorageDocument,
+}
+
+// The newest events the agent stored, kept in memory for the control server so a console
+// can look at what a host just saw without querying the backend
+pub struct EventBuffer {
+    capacity: usize,
+    events: Mutex<VecDeque<BufferedEvent>>,
+}
+
+impl EventBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    // Holds `control.buffered_events` events while the control server is enabled, none otherwise
+    pub fn global() -> Arc<EventBuffer> {
+        static GLOBAL: OnceLock<Arc<EventBuffer>> = OnceLock::new();
+        GLOBAL
+            .get_or_init(|| {
+                let config = ControlConfig::load();
+                Arc::new(EventBuffer::new(if config.enabled { config.buffered_events } else { 0 }))
+            })
+            .clone()
+    }
+
+    pub fn push<T: Serialize>(&self, index: &str, events: &[T]) {
+        if self.capacity == 0 {
+            return;
+        }
+        // Only the newest `capacity` of a large batch would stay anyway
+        let skip = events.len().saturating_sub(self.capacity);
+        let buffered_at = Utc::now();
+        let documents: Vec<BufferedEvent> = events[skip..]
+            .iter()
+            .filter_map(|event| StorageDocument::new(index, event).ok())
+            .map(|document| BufferedEvent { buffered_at, document })
+            .collect();
+
+        if let Ok(mut events) = self.events.lock() {
+            for event in documents {
+                if events.len() == self.capacity {
+                    events.pop_front();
+                }
+                events.push_back(event);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.lock().map(|events| events.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The newest `limit` events, optionally only those written to `index`, oldest first;
+    // a limit of 0 returns everything buffered
+    pub fn recent(&self, limit: usize, index: Option<&str>) -> Vec<BufferedEvent> {
+        let Ok(events) = self.events.lock() else {
+            return Vec::new();
+        };
+        let limit = if limit == 0 { events.len() } else { limit };
+        let mut recent: Vec<BufferedEvent> = events
+            .iter()
+            .rev()
+            .filter(|event| index.is_none_or(|index| event.document.index == index))
+            .take(limit)
+            .cloned()
+            .collect();
+        recent.reverse();
+        recent
+    }
+}
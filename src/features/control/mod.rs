@@ -0,0 +1,8 @@
+mod buffer;
+mod proto;
+mod server;
+mod state;
+
+pub use buffer::{BufferedEvent, EventBuffer};
+pub use server::{ControlConfig, ControlServer, ControlTlsConfig};
+pub use state::{AgentControl, AgentStatus, CollectionTrigger, RunInfo};
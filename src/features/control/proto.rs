@@ -0,0 +1,214 @@
+use crate::features::control::buffer::BufferedEvent;
+use crate::features::control::state::AgentStatus;
+use crate::features::incident::{Incident, IncidentStatus};
+use crate::features::response::ActionOutput;
+use crate::shared::protobuf::{fields, Message};
+use chrono::{DateTime, Utc};
+
+// The spathax.control.v1 messages, encoded by hand; field numbers follow control.proto next
+// to this file, which the management console generates its client from
+
+fn unix_ms(time: Option<DateTime<Utc>>) -> i64 {
+    time.map(|time| time.timestamp_millis()).unwrap_or(0)
+}
+
+fn text(payload: &[u8]) -> Result<String, String> {
+    String::from_utf8(payload.to_vec()).map_err(|_| String::from("String field is not UTF-8"))
+}
+
+// AgentStatus
+pub fn agent_status(status: &AgentStatus) -> Vec<u8> {
+    let degraded = status.run.as_ref().map(|run| run.degraded.as_slice()).unwrap_or_default();
+    let collectors: Vec<Message> = status
+        .collectors
+        .iter()
+        .map(|collector| {
+            let message = Message::new()
+                .string(1, &collector.collector)
+                .varint(2, collector.runs)
+                .varint(3, collector.failures)
+                .varint(4, collector.events)
+                .int64(5, unix_ms(collector.last_run));
+            degraded
+                .iter()
+                .filter(|degradation| degradation.collector == collector.collector)
+                .fold(message, |message, degradation| message.string(6, &degradation.reason))
+        })
+        .collect();
+    let capabilities: Vec<Message> = status
+        .capabilities
+        .iter()
+        .map(|capability| {
+            Message::new()
+                .string(1, &capability.name)
+                .bool(2, capability.available)
+                .string(3, &capability.detail)
+        })
+        .collect();
+    let run = status.run.as_ref();
+    let config_source = run
+        .and_then(|run| run.config_source.as_ref())
+        .map(|path| path.display().to_string())
+        .unwrap_or_default();
+    let message = Message::new()
+        .string(1, env!("CARGO_PKG_VERSION"))
+        .string(2, &status.hostname)
+        .string(3, std::env::consts::OS)
+        .string(4, status.state)
+        .int64(5, status.started_at.timestamp_millis())
+        .varint(6, (Utc::now() - status.started_at).num_seconds().max(0) as u64)
+        .string(7, &config_source)
+        .string(8, run.and_then(|run| run.policy_profile.as_deref()).unwrap_or_default())
+        .string(9, run.map(|run| run.storage_backend.as_str()).unwrap_or_default())
+        .int64(10, unix_ms(run.map(|run| run.since)))
+        .varint(11, status.config_reloads)
+        .messages(12, &collectors)
+        .varint(13, status.buffered_events as u64)
+        .messages(14, &capabilities);
+    degraded
+        .iter()
+        .fold(message, |message, degradation| message.string(15, &degradation.to_string()))
+        .into_bytes()
+}
+
+// TriggerCollectionRequest: the interval keys of the collectors to run
+pub fn trigger_collection_request(request: &[u8]) -> Result<Vec<String>, String> {
+    fields(request)
+        .into_iter()
+        .filter(|(field, _, _)| *field == 1)
+        .map(|(_, _, payload)| text(payload))
+        .collect()
+}
+
+// TriggerCollectionResponse
+pub fn trigger_collection_response(collectors: &[String]) -> Vec<u8> {
+    collectors
+        .iter()
+        .fold(Message::new(), |message, collector| message.string(1, collector))
+        .into_bytes()
+}
+
+// ReloadConfigResponse
+pub fn reload_config_response(config_source: &str, policy_profile: &str) -> Vec<u8> {
+    Message::new().string(1, config_source).string(2, policy_profile).into_bytes()
+}
+
+// GetRecentEventsRequest: how many events, 0 for all, and the index to take them from
+pub fn recent_events_request(request: &[u8]) -> Result<(usize, Option<String>), String> {
+    let mut limit = 0;
+    let mut index = None;
+    for (field, value, payload) in fields(request) {
+        match field {
+            1 => limit = value as usize,
+            2 => index = Some(text(payload)?).filter(|index| !index.is_empty()),
+            _ => {}
+        }
+    }
+    Ok((limit, index))
+}
+
+// GetRecentEventsResponse
+pub fn recent_events_response(events: &[BufferedEvent]) -> Vec<u8> {
+    let events: Vec<Message> = events
+        .iter()
+        .map(|event| {
+            Message::new()
+                .string(1, &event.document.index)
+                .int64(2, event.buffered_at.timestamp_millis())
+                .string(3, &event.document.body.to_string())
+        })
+        .collect();
+    Message::new().messages(1, &events).into_bytes()
+}
+
+fn status(payload: &[u8]) -> Result<Option<IncidentStatus>, String> {
+    let status = text(payload)?;
+    if status.is_empty() {
+        return Ok(None);
+    }
+    status.parse().map(Some)
+}
+
+// ListIncidentsRequest: how many incidents, 0 for all, and the status to take them from
+pub fn list_incidents_request(request: &[u8]) -> Result<(usize, Option<IncidentStatus>), String> {
+    let mut limit = 0;
+    let mut wanted = None;
+    for (field, value, payload) in fields(request) {
+        match field {
+            1 => limit = value as usize,
+            2 => wanted = status(payload)?,
+            _ => {}
+        }
+    }
+    Ok((limit, wanted))
+}
+
+// Incident
+fn incident_message(incident: &Incident) -> Message {
+    let repeated = |message: Message, field: u32, values: &[String]| {
+        values.iter().fold(message, |message, value| message.string(field, value))
+    };
+    let message = Message::new()
+        .string(1, &incident.id)
+        .varint(2, incident.revision)
+        .string(3, &incident.status.to_string())
+        .string(4, &format!("{:?}", incident.severity))
+        .string(5, &incident.title)
+        .int64(6, incident.first_seen.timestamp_millis())
+        .int64(7, incident.last_seen.timestamp_millis());
+    let message = repeated(message, 8, &incident.alert_ids);
+    let message = repeated(message, 9, &incident.rule_ids);
+    repeated(message, 10, &incident.related_ids).string(11, incident.note.as_deref().unwrap_or_default())
+}
+
+pub fn incident(incident: &Incident) -> Vec<u8> {
+    incident_message(incident).into_bytes()
+}
+
+// ListIncidentsResponse
+pub fn list_incidents_response(incidents: &[Incident]) -> Vec<u8> {
+    let incidents: Vec<Message> = incidents.iter().map(incident_message).collect();
+    Message::new().messages(1, &incidents).into_bytes()
+}
+
+// UpdateIncidentRequest: the incident, its new status and the note to leave
+pub fn update_incident_request(request: &[u8]) -> Result<(String, IncidentStatus, Option<String>), String> {
+    let mut id = String::new();
+    let mut new_status = None;
+    let mut note = None;
+    for (field, _, payload) in fields(request) {
+        match field {
+            1 => id = text(payload)?,
+            2 => new_status = status(payload)?,
+            3 => note = Some(text(payload)?).filter(|note| !note.is_empty()),
+            _ => {}
+        }
+    }
+    if id.is_empty() {
+        return Err(String::from("incident_id is required"));
+    }
+    let new_status = new_status.ok_or_else(|| String::from("status is required"))?;
+    Ok((id, new_status, note))
+}
+
+// ExecuteActionRequest: the signed request in JSON. Invalid UTF-8 is kept as replacement
+// characters so the executor still denies and audits the call.
+pub fn execute_action_request(request: &[u8]) -> String {
+    fields(request)
+        .into_iter()
+        .find(|(field, _, _)| *field == 1)
+        .map(|(_, _, payload)| String::from_utf8_lossy(payload).into_owned())
+        .unwrap_or_default()
+}
+
+// ExecuteActionResponse
+pub fn execute_action_response(output: &ActionOutput) -> Vec<u8> {
+    let result = match output {
+        ActionOutput::FileRetrieved(file) => serde_json::to_string(file),
+        ActionOutput::PseudonymsResolved(entries) => serde_json::to_string(entries),
+    };
+    Message::new()
+        .string(1, output.action().as_str())
+        .string(2, &result.unwrap_or_default())
+        .into_bytes()
+}
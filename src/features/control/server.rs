@@ -0,0 +1,304 @@
+use crate::features::control::buffer::EventBuffer;
+use crate::features::control::proto;
+use crate::features::control::state::AgentControl;
+use crate::features::incident::IncidentTracker;
+use crate::features::response::ResponseActionExecutor;
+use crate::shared::error::ActionError;
+use crate::shared::config::AgentConfig;
+use crate::shared::protobuf::EncodedCodec;
+use crate::utils::load_config_section;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::future::ready;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::task::JoinHandle;
+use tonic::body::BoxBody;
+use tonic::codegen::{http, BoxFuture, Service};
+use tonic::server::{Grpc, NamedService, UnaryService};
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
+use tonic::Status;
+
+const SERVICE_NAME: &str = "spathax.control.v1.AgentControl";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlTlsConfig {
+    // PEM certificate chain and key the server presents; both or neither
+    pub cert_file: Option<String>,
+    pub key_file: Option<String>,
+    // PEM CA that client certificates must chain to; unset accepts clients without one
+    pub client_ca_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlConfig {
+    pub enabled: bool,
+    // Address the gRPC server listens on; 0.0.0.0 exposes it to the network, which should
+    // come with a token and TLS
+    pub listen: String,
+    // Required as `authorization: Bearer <token>` on every call when set
+    pub token: Option<String>,
+    pub tls: ControlTlsConfig,
+    // Newest stored events kept in memory for GetRecentEvents
+    pub buffered_events: usize,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: String::from("127.0.0.1:9185"),
+            token: None,
+            tls: ControlTlsConfig::default(),
+            buffered_events: 1000,
+        }
+    }
+}
+
+impl ControlConfig {
+    pub fn load() -> Self {
+        load_config_section("control")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Rpc {
+    GetStatus,
+    TriggerCollection,
+    ReloadConfig,
+    GetRecentEvents,
+    ListIncidents,
+    UpdateIncident,
+    ExecuteAction,
+}
+
+impl Rpc {
+    fn from_path(path: &str) -> Option<Self> {
+        let method = path.strip_prefix('/')?.strip_prefix(SERVICE_NAME)?.strip_prefix('/')?;
+        match method {
+            "GetStatus" => Some(Rpc::GetStatus),
+            "TriggerCollection" => Some(Rpc::TriggerCollection),
+            "ReloadConfig" => Some(Rpc::ReloadConfig),
+            "GetRecentEvents" => Some(Rpc::GetRecentEvents),
+            "ListIncidents" => Some(Rpc::ListIncidents),
+            "UpdateIncident" => Some(Rpc::UpdateIncident),
+            "ExecuteAction" => Some(Rpc::ExecuteAction),
+            _ => None,
+        }
+    }
+}
+
+fn action_status(error: ActionError) -> Status {
+    match error {
+        ActionError::InvalidRequest(_) => Status::invalid_argument(error.to_string()),
+        ActionError::PolicyViolation(_) => Status::permission_denied(error.to_string()),
+        ActionError::Integrity(_) => Status::failed_precondition(error.to_string()),
+        ActionError::Io(ref e) if e.kind() == std::io::ErrorKind::NotFound => Status::not_found(error.to_string()),
+        ActionError::Io(_) => Status::internal(error.to_string()),
+    }
+}
+
+// Authorization, file reads and auditing run off the async workers
+async fn execute_action(peer: String, request: Vec<u8>) -> Result<tonic::Response<Vec<u8>>, Status> {
+    let request = proto::execute_action_request(&request);
+    let executor = ResponseActionExecutor::global();
+    let result = tokio::task::spawn_blocking(move || executor.execute_json(&request))
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+    match result {
+        Ok(output) => {
+            info!("Control client {} ran {}", peer, output.action().as_str());
+            Ok(tonic::Response::new(proto::execute_action_response(&output)))
+        }
+        Err(e) => {
+            warn!("Control client {} was refused a response action: {}", peer, e);
+            Err(action_status(e))
+        }
+    }
+}
+
+// Reading and validating the configuration touches files and the registry
+async fn reload_config(peer: String) -> Result<tonic::Response<Vec<u8>>, Status> {
+    let loaded = tokio::task::spawn_blocking(AgentConfig::load)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+    match loaded {
+        Ok(config) => {
+            let source = config.source.as_ref().map(|path| path.display().to_string()).unwrap_or_default();
+            let profile = config.role.map(|role| role.to_string()).unwrap_or_default();
+            info!("Control client {} reloaded the configuration, restarting the collectors", peer);
+            AgentControl::global().request_reload(config);
+            Ok(tonic::Response::new(proto::reload_config_response(&source, &profile)))
+        }
+        Err(e) => {
+            warn!("Control client {} asked for a reload of an invalid configuration: {}", peer, e);
+            Err(Status::invalid_argument(e.to_string()))
+        }
+    }
+}
+
+// One call of an RPC, decoded and encoded by tonic's unary handling
+struct UnaryRpc(Rpc);
+
+impl UnaryService<Vec<u8>> for UnaryRpc {
+    type Response = Vec<u8>;
+    type Future = BoxFuture<tonic::Response<Vec<u8>>, Status>;
+
+    fn call(&mut self, request: tonic::Request<Vec<u8>>) -> Self::Future {
+        let peer = request.remote_addr().map(|addr| addr.to_string()).unwrap_or_else(|| String::from("unknown"));
+        let request = request.get_ref();
+        let control = AgentControl::global();
+        let response = match self.0 {
+            Rpc::GetStatus => Ok(proto::agent_status(&control.status())),
+            Rpc::TriggerCollection => match proto::trigger_collection_request(request) {
+                Ok(requested) => match control.trigger(&requested) {
+                    Ok(triggered) => {
+                        info!("Control client {} triggered a collection of {}", peer, triggered.join(", "));
+                        Ok(proto::trigger_collection_response(&triggered))
+                    }
+                    Err(e) => Err(Status::failed_precondition(e)),
+                },
+                Err(e) => Err(Status::invalid_argument(e)),
+            },
+            Rpc::ReloadConfig if !control.accepts_reloads() => {
+                Err(Status::unavailable("The agent is not running its collectors"))
+            }
+            Rpc::ReloadConfig => return Box::pin(reload_config(peer)),
+            Rpc::GetRecentEvents => proto::recent_events_request(request)
+                .map_err(Status::invalid_argument)
+                .map(|(limit, index)| proto::recent_events_response(&EventBuffer::global().recent(limit, index.as_deref()))),
+            Rpc::ListIncidents | Rpc::UpdateIncident if !IncidentTracker::global().enabled() => {
+                Err(Status::failed_precondition("Incidents are not enabled on this agent"))
+            }
+            Rpc::ListIncidents => proto::list_incidents_request(request)
+                .map_err(Status::invalid_argument)
+                .map(|(limit, status)| proto::list_incidents_response(&IncidentTracker::global().list(status, limit))),
+            Rpc::UpdateIncident => match proto::update_incident_request(request) {
+                Ok((id, status, note)) => match IncidentTracker::global().update(&id, status, note) {
+                    Ok(incident) => {
+                        info!("Control client {} set incident {} to {}", peer, incident.id, incident.status);
+                        Ok(proto::incident(&incident))
+                    }
+                    Err(e) => Err(Status::not_found(e)),
+                },
+                Err(e) => Err(Status::invalid_argument(e)),
+            },
+            Rpc::ExecuteAction => return Box::pin(execute_action(peer, request.clone())),
+        };
+        Box::pin(ready(response.map(tonic::Response::new)))
+    }
+}
+
+// Compares every byte, so the response time does not tell how much of a token was right
+fn same_token(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len() && given.iter().zip(expected).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// spathax.control.v1.AgentControl, see control.proto
+#[derive(Clone)]
+pub struct ControlServer {
+    token: Option<Arc<str>>,
+}
+
+impl ControlServer {
+    fn authorized(&self, headers: &http::HeaderMap) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        headers
+            .get(http::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| same_token(given.as_bytes(), token.as_bytes()))
+    }
+
+    fn tls(config: &ControlTlsConfig) -> Result<Option<ServerTlsConfig>, String> {
+        let (Some(cert_file), Some(key_file)) = (&config.cert_file, &config.key_file) else {
+            return Ok(None);
+        };
+        let read = |path: &str| std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e));
+        let mut tls = ServerTlsConfig::new().identity(Identity::from_pem(read(cert_file)?, read(key_file)?));
+        if let Some(client_ca_file) = &config.client_ca_file {
+            tls = tls.client_ca_root(Certificate::from_pem(read(client_ca_file)?));
+        }
+        Ok(Some(tls))
+    }
+
+    // Serves the control RPCs until the task is aborted. Must be called within the Tokio
+    // runtime.
+    pub fn serve(config: ControlConfig) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let addr: SocketAddr = match config.listen.parse() {
+                Ok(addr) => addr,
+                Err(e) => {
+                    warn!("Invalid control.listen {}: {}", config.listen, e);
+                    return;
+                }
+            };
+            let tls = match Self::tls(&config.tls) {
+                Ok(tls) => tls,
+                Err(e) => {
+                    warn!("Failed to load the control server TLS settings: {}", e);
+                    return;
+                }
+            };
+            // From the network, calls run response actions and reload the configuration
+            if !addr.ip().is_loopback() {
+                if config.token.is_none() {
+                    error!("Not serving agent control on {}: a non-loopback address needs control.token", addr);
+                    return;
+                }
+                if tls.is_none() {
+                    error!("Not serving agent control on {}: the token would be sent without TLS", addr);
+                    return;
+                }
+            }
+            let mut builder = Server::builder();
+            if let Some(tls) = tls {
+                match builder.tls_config(tls) {
+                    Ok(tls_builder) => builder = tls_builder,
+                    Err(e) => {
+                        warn!("Invalid control server TLS settings: {}", e);
+                        return;
+                    }
+                }
+            }
+
+            let service = ControlServer {
+                token: config.token.as_deref().map(Arc::from),
+            };
+            info!("Serving agent control on {}", addr);
+            if let Err(e) = builder.add_service(service).serve(addr).await {
+                warn!("Control server on {} stopped: {}", addr, e);
+            }
+        })
+    }
+}
+
+impl Service<http::Request<BoxBody>> for ControlServer {
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<BoxBody>) -> Self::Future {
+        let Some(rpc) = Rpc::from_path(request.uri().path()) else {
+            return Box::pin(ready(Ok(Status::unimplemented("Unknown method").into_http())));
+        };
+        if !self.authorized(request.headers()) {
+            return Box::pin(ready(Ok(Status::unauthenticated("Missing or wrong control token").into_http())));
+        }
+        Box::pin(async move { Ok(Grpc::new(EncodedCodec).unary(UnaryRpc(rpc), request).await) })
+    }
+}
+
+impl NamedService for ControlServer {
+    const NAME: &'static str = SERVICE_NAME;
+}
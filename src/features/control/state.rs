@@ -0,0 +1,228 @@
+use crate::features::control::buffer::EventBuffer;
+use crate::shared::capabilities::{Capabilities, Capability, Degradation};
+use crate::shared::config::AgentConfig;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::maintenance::MaintenanceMode;
+use crate::shared::metrics::{AgentMetrics, CollectorStats};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::watch;
+
+// Pending requests for an immediate collection; a burst beyond this collapses into one run
+const COLLECTION_REQUESTS: usize = 16;
+
+// What the collectors were last started with
+#[derive(Debug, Clone)]
+pub struct RunInfo {
+    pub config_source: Option<PathBuf>,
+    pub policy_profile: Option<String>,
+    pub storage_backend: String,
+    pub since: DateTime<Utc>,
+    // Collectors running with less than configured on this host
+    pub degraded: Vec<Degradation>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentStatus {
+    pub hostname: String,
+    // running, paused or maintenance
+    pub state: &'static str,
+    pub started_at: DateTime<Utc>,
+    pub run: Option<RunInfo>,
+    pub config_reloads: u64,
+    // Every running collector by its interval key, with what it has done so far
+    pub collectors: Vec<CollectorStats>,
+    pub buffered_events: usize,
+    pub capabilities: Vec<Capability>,
+}
+
+// Waits for requests to run one collector right away, see AgentControl::trigger
+pub struct CollectionTrigger {
+    control: Arc<AgentControl>,
+    key: &'static str,
+    requests: broadcast::Receiver<Arc<Vec<String>>>,
+}
+
+impl CollectionTrigger {
+    // Resolves once a collection of this collector has been requested
+    pub async fn requested(&mut self) {
+        loop {
+            match self.requests.recv().await {
+                Ok(collectors) if collectors.is_empty() || collectors.iter().any(|key| key == self.key) => return,
+                Ok(_) => {}
+                Err(RecvError::Lagged(_)) => return,
+                Err(RecvError::Closed) => std::future::pending().await,
+            }
+        }
+    }
+}
+
+impl Drop for CollectionTrigger {
+    fn drop(&mut self) {
+        self.control.unregister(self.key);
+    }
+}
+
+// The running agent as the control server sees and steers it: which collectors run, what
+// they were started with, and requests to collect now or to reload the configuration
+pub struct AgentControl {
+    started_at: DateTime<Utc>,
+    run: Mutex<Option<RunInfo>>,
+    paused: AtomicBool,
+    // Collector tasks waiting on a CollectionTrigger, by interval key
+    collectors: Mutex<BTreeMap<&'static str, usize>>,
+    collection_requests: broadcast::Sender<Arc<Vec<String>>>,
+    reload_requests: watch::Sender<Option<AgentConfig>>,
+    config_reloads: AtomicU64,
+}
+
+impl AgentControl {
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            run: Mutex::new(None),
+            paused: AtomicBool::new(false),
+            collectors: Mutex::new(BTreeMap::new()),
+            collection_requests: broadcast::channel(COLLECTION_REQUESTS).0,
+            reload_requests: watch::channel(None).0,
+            config_reloads: AtomicU64::new(0),
+        }
+    }
+
+    pub fn global() -> Arc<AgentControl> {
+        static GLOBAL: OnceLock<Arc<AgentControl>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(AgentControl::new())).clone()
+    }
+
+    // Called each time the collectors start, on the configuration they run with
+    pub fn running(&self, config: &AgentConfig, storage_backend: &str, degraded: &[Degradation]) {
+        if let Ok(mut run) = self.run.lock() {
+            *run = Some(RunInfo {
+                config_source: config.source.clone(),
+                policy_profile: config.role.map(|role| role.to_string()),
+                storage_backend: storage_backend.to_string(),
+                since: Utc::now(),
+                degraded: degraded.to_vec(),
+            });
+        }
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    // A collector task registers under its interval key for as long as it holds the trigger
+    pub fn subscribe(self: &Arc<Self>, key: &'static str) -> CollectionTrigger {
+        if let Ok(mut collectors) = self.collectors.lock() {
+            *collectors.entry(key).or_default() += 1;
+        }
+        CollectionTrigger {
+            control: self.clone(),
+            key,
+            requests: self.collection_requests.subscribe(),
+        }
+    }
+
+    fn unregister(&self, key: &'static str) {
+        if let Ok(mut collectors) = self.collectors.lock() {
+            if let Some(count) = collectors.get_mut(key) {
+                *count -= 1;
+                if *count == 0 {
+                    collectors.remove(key);
+                }
+            }
+        }
+    }
+
+    pub fn running_collectors(&self) -> Vec<String> {
+        self.collectors
+            .lock()
+            .map(|collectors| collectors.keys().map(|key| key.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    // Runs the named collectors, or all of them when none are named, without waiting for
+    // their next interval; returns the collectors asked to run
+    pub fn trigger(&self, collectors: &[String]) -> Result<Vec<String>, String> {
+        let running = self.running_collectors();
+        if running.is_empty() {
+            return Err(String::from("No collectors are running"));
+        }
+        let unknown: Vec<&str> = collectors
+            .iter()
+            .filter(|key| !running.contains(key))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return Err(format!("Collectors not running: {}", unknown.join(", ")));
+        }
+        // Sending only fails without subscribers, which were just found running
+        let _ = self.collection_requests.send(Arc::new(collectors.to_vec()));
+        Ok(if collectors.is_empty() { running } else { collectors.to_vec() })
+    }
+
+    // The agent loop watches this for configurations to restart on
+    pub fn reload_requests(&self) -> watch::Receiver<Option<AgentConfig>> {
+        self.reload_requests.subscribe()
+    }
+
+    pub fn accepts_reloads(&self) -> bool {
+        self.reload_requests.receiver_count() > 0
+    }
+
+    // Hands a loaded and validated configuration to the agent loop, which restarts storage
+    // and the collectors on it
+    pub fn request_reload(&self, config: AgentConfig) {
+        self.config_reloads.fetch_add(1, Ordering::Relaxed);
+        self.reload_requests.send_replace(Some(config));
+    }
+
+    pub fn status(&self) -> AgentStatus {
+        let stats: BTreeMap<String, CollectorStats> = AgentMetrics::global()
+            .collector_stats()
+            .into_iter()
+            .map(|stats| (stats.collector.clone(), stats))
+            .collect();
+        let collectors = self
+            .running_collectors()
+            .into_iter()
+            .map(|collector| {
+                stats.get(&collector).cloned().unwrap_or(CollectorStats {
+                    collector,
+                    runs: 0,
+                    failures: 0,
+                    events: 0,
+                    last_run: None,
+                })
+            })
+            .collect();
+
+        let state = if self.paused.load(Ordering::Relaxed) {
+            "paused"
+        } else if MaintenanceMode::global().current().is_some() {
+            "maintenance"
+        } else {
+            "running"
+        };
+        AgentStatus {
+            hostname: HostIdentityService::global().name(),
+            state,
+            started_at: self.started_at,
+            run: self.run.lock().ok().and_then(|run| run.clone()),
+            config_reloads: self.config_reloads.load(Ordering::Relaxed),
+            collectors,
+            buffered_events: EventBuffer::global().len(),
+            capabilities: Capabilities::global().capabilities.clone(),
+        }
+    }
+}
+
+impl Default for AgentControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,522 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector};
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::process_resolver::ProcessResolver;
+use crate::features::deception::decoys::{plant_username_hint, read_username_hint, DecoyCredentials};
+use crate::features::deception::models::{DecoyKind, DeceptionAction, DeceptionEvent, DeceptionEventBuilder};
+use crate::utils::load_config_section;
+use chrono::Utc;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use uuid::Uuid;
+
+#[cfg(target_os = "linux")]
+use crate::features::deception::inotify::{DecoyWatch, ACCESSED, MODIFIED, REMOVED};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecoyFileConfig {
+    // ${USERPROFILE} and ${HOME} are expanded
+    pub path: String,
+    pub kind: DecoyKind,
+    // Template for `custom` decoys; {username} {password} {host} {domain} {fqdn} are filled in
+    pub content: Option<String>,
+}
+
+impl Default for DecoyFileConfig {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            kind: DecoyKind::Custom,
+            content: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeceptionConfig {
+    pub enabled: bool,
+    pub username: String,
+    // Derived from the host name when empty, so every host carries a distinct decoy
+    pub password: Option<String>,
+    pub decoy_host: String,
+    pub domain: String,
+    pub files: Vec<DecoyFileConfig>,
+    // Windows: add the decoy host to mstsc's recent server list
+    pub plant_rdp_registry: bool,
+    pub watch_file_access: bool,
+    pub scan_process_command_lines: bool,
+}
+
+impl Default for DeceptionConfig {
+    fn default() -> Self {
+        let home = if cfg!(target_os = "windows") { "${USERPROFILE}" } else { "${HOME}" };
+        Self {
+            enabled: false,
+            username: String::from("svc_backup"),
+            password: None,
+            decoy_host: String::from("fs-backup-01"),
+            domain: String::from("corp.local"),
+            files: vec![
+                DecoyFileConfig {
+                    path: format!("{}/Documents/passwords.csv", home),
+                    kind: DecoyKind::BrowserPasswordExport,
+                    content: None,
+                },
+                DecoyFileConfig {
+                    path: format!("{}/Documents/fs-backup-01.rdp", home),
+                    kind: DecoyKind::RdpFile,
+                    content: None,
+                },
+            ],
+            plant_rdp_registry: true,
+            watch_file_access: true,
+            scan_process_command_lines: true,
+        }
+    }
+}
+
+impl DeceptionConfig {
+    pub fn load() -> Self {
+        load_config_section("deception")
+    }
+}
+
+struct PlantedFile {
+    path: PathBuf,
+    kind: DecoyKind,
+    content: String,
+    // Metadata as planted; decoys are never opened again so reads stay attributable
+    modified: Option<SystemTime>,
+    len: u64,
+    accessed: Option<SystemTime>,
+    #[cfg(target_os = "linux")]
+    watch: Option<i32>,
+}
+
+impl PlantedFile {
+    fn refresh_metadata(&mut self) {
+        let metadata = fs::metadata(&self.path).ok();
+        self.modified = metadata.as_ref().and_then(|metadata| metadata.modified().ok());
+        self.len = metadata.as_ref().map_or(0, |metadata| metadata.len());
+        self.accessed = metadata.as_ref().and_then(|metadata| metadata.accessed().ok());
+    }
+}
+
+// Plants decoy credentials (a browser password export, an .rdp file, an mstsc recent
+// server entry) and reports anything that reads, changes or removes them, or runs a
+// process whose command line carries the decoy user name, password or host. Removed
+// or altered decoys are reported and planted again.
+pub struct DeceptionCollector {
+    config: DeceptionConfig,
+    credentials: DecoyCredentials,
+    files: Vec<PlantedFile>,
+    registry_key: Option<String>,
+    pending: Vec<DeceptionEvent>,
+    #[cfg(target_os = "linux")]
+    watch: Option<DecoyWatch>,
+    sys: System,
+    // (pid, start time) of processes already reported for using a decoy
+    reported_processes: HashSet<(u32, u64)>,
+    resolver: Arc<ProcessResolver>,
+    host: Arc<HostIdentityService>,
+}
+
+impl DeceptionCollector {
+    pub fn new() -> Self {
+        Self::with_config(DeceptionConfig::load())
+    }
+
+    pub fn with_config(config: DeceptionConfig) -> Self {
+        let host = HostIdentityService::global();
+        let password = config.password.clone().filter(|password| !password.is_empty()).unwrap_or_else(|| {
+            let digest = Sha256::digest(format!("lsedr-decoy|{}", host.name()).as_bytes());
+            format!("Bk{}!", &hex::encode(digest)[..10])
+        });
+        let credentials = DecoyCredentials {
+            username: config.username.clone(),
+            password,
+            host: config.decoy_host.clone(),
+            domain: config.domain.clone(),
+        };
+
+        let mut collector = Self {
+            credentials,
+            files: Vec::new(),
+            registry_key: None,
+            pending: Vec::new(),
+            #[cfg(target_os = "linux")]
+            watch: None,
+            sys: System::new(),
+            reported_processes: HashSet::new(),
+            resolver: ProcessResolver::global(),
+            host,
+            config,
+        };
+
+        for decoy in collector.config.files.clone() {
+            collector.plant_file(&decoy);
+        }
+        if collector.config.plant_rdp_registry && cfg!(target_os = "windows") {
+            collector.plant_registry();
+        }
+        #[cfg(target_os = "linux")]
+        if collector.config.watch_file_access {
+            collector.start_watch();
+        }
+
+        collector
+    }
+
+    fn expand(path: &str) -> String {
+        let mut expanded = path.to_string();
+        for variable in ["USERPROFILE", "HOME"] {
+            let placeholder = format!("${{{}}}", variable);
+            if expanded.contains(&placeholder) {
+                if let Ok(value) = std::env::var(variable) {
+                    expanded = expanded.replace(&placeholder, &value.replace('\\', "/"));
+                }
+            }
+        }
+        expanded
+    }
+
+    fn event(&self, action: DeceptionAction, kind: DecoyKind, decoy: &str) -> DeceptionEventBuilder {
+        DeceptionEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("deception"))
+            .action(action)
+            .decoy_kind(kind)
+            .decoy(decoy.to_string())
+    }
+
+    fn queue(&mut self, event: Result<DeceptionEvent, String>) {
+        match event {
+            Ok(event) => self.pending.push(event),
+            Err(e) => error!("Failed to build deception event: {}", e),
+        }
+    }
+
+    fn write_decoy(path: &Path, content: &str) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)
+    }
+
+    fn plant_file(&mut self, decoy: &DecoyFileConfig) {
+        let path = PathBuf::from(Self::expand(&decoy.path));
+        if path.as_os_str().to_string_lossy().contains("${") {
+            warn!("Skipping decoy {}, path variables could not be expanded", decoy.path);
+            return;
+        }
+        let Some(content) = self.credentials.render(decoy.kind, decoy.content.as_deref()) else {
+            warn!("Skipping decoy {}, no content for kind {:?}", decoy.path, decoy.kind);
+            return;
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(existing) if existing == content => {}
+            // Never overwrite a real file that happens to sit at the configured path
+            Ok(_) => {
+                warn!("Skipping decoy {}, a different file already exists there", path.display());
+                return;
+            }
+            Err(_) => {
+                if let Err(e) = Self::write_decoy(&path, &content) {
+                    warn!("Failed to plant decoy {}: {}", path.display(), e);
+                    return;
+                }
+                info!("Planted decoy {}", path.display());
+                let event = self
+                    .event(DeceptionAction::Planted, decoy.kind, &path.to_string_lossy())
+                    .build();
+                self.queue(event);
+            }
+        }
+
+        let mut planted = PlantedFile {
+            path,
+            kind: decoy.kind,
+            content,
+            modified: None,
+            len: 0,
+            accessed: None,
+            #[cfg(target_os = "linux")]
+            watch: None,
+        };
+        planted.refresh_metadata();
+        self.files.push(planted);
+    }
+
+    fn plant_registry(&mut self) {
+        let key = self.credentials.rdp_registry_key();
+        let hint = self.credentials.qualified_username();
+        if read_username_hint(&key).as_deref() != Some(hint.as_str()) {
+            match plant_username_hint(&key, &hint) {
+                Ok(()) => {
+                    info!("Planted decoy RDP server entry {}", key);
+                    let event = self.event(DeceptionAction::Planted, DecoyKind::RdpRegistry, &key).build();
+                    self.queue(event);
+                }
+                Err(e) => {
+                    warn!("Failed to plant decoy RDP server entry: {}", e);
+                    return;
+                }
+            }
+        }
+        self.registry_key = Some(key);
+    }
+
+    #[cfg(target_os = "linux")]
+    fn start_watch(&mut self) {
+        let watch = match DecoyWatch::new() {
+            Ok(watch) => watch,
+            Err(e) => {
+                warn!("Decoy access watch unavailable: {}", e);
+                return;
+            }
+        };
+        for file in &mut self.files {
+            match watch.add(&file.path) {
+                Ok(wd) => file.watch = Some(wd),
+                Err(e) => warn!("Failed to watch decoy {}: {}", file.path.display(), e),
+            }
+        }
+        self.watch = Some(watch);
+    }
+
+    // inotify events folded into one count per decoy and action
+    #[cfg(target_os = "linux")]
+    fn watch_actions(&self) -> HashMap<(usize, DeceptionAction), u32> {
+        let mut actions = HashMap::new();
+        let Some(watch) = &self.watch else {
+            return actions;
+        };
+
+        for (wd, mask) in watch.drain() {
+            let Some(index) = self.files.iter().position(|file| file.watch == Some(wd)) else {
+                continue;
+            };
+            for (bits, action) in [
+                (ACCESSED, DeceptionAction::Accessed),
+                (MODIFIED, DeceptionAction::Modified),
+                (REMOVED, DeceptionAction::Deleted),
+            ] {
+                if mask & bits != 0 {
+                    *actions.entry((index, action)).or_insert(0) += 1;
+                }
+            }
+        }
+
+        actions
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn watch_actions(&self) -> HashMap<(usize, DeceptionAction), u32> {
+        HashMap::new()
+    }
+
+    // Writes a decoy back without our own write showing up as tampering
+    fn restore_file(&mut self, index: usize) {
+        #[cfg(target_os = "linux")]
+        if let (Some(watch), Some(wd)) = (&self.watch, self.files[index].watch.take()) {
+            watch.remove(wd);
+        }
+
+        let file = &mut self.files[index];
+        if let Err(e) = Self::write_decoy(&file.path, &file.content) {
+            warn!("Failed to restore decoy {}: {}", file.path.display(), e);
+        }
+        file.refresh_metadata();
+
+        #[cfg(target_os = "linux")]
+        if let Some(watch) = &self.watch {
+            self.files[index].watch = watch.add(&self.files[index].path).ok();
+        }
+    }
+
+    fn check_files(&mut self) {
+        let mut actions = self.watch_actions();
+
+        // Metadata only: opening the decoy here would look like an attacker reading it
+        for (index, file) in self.files.iter().enumerate() {
+            let Ok(metadata) = fs::metadata(&file.path) else {
+                actions.entry((index, DeceptionAction::Deleted)).or_insert(1);
+                continue;
+            };
+            if metadata.len() != file.len || metadata.modified().ok() != file.modified {
+                actions.entry((index, DeceptionAction::Modified)).or_insert(1);
+            }
+            // Access times are coarse (relatime, NTFS last-access policy) but are the
+            // only read signal where there is no inotify
+            let accessed = metadata.accessed().ok();
+            if accessed.is_some() && accessed != file.accessed {
+                actions.entry((index, DeceptionAction::Accessed)).or_insert(1);
+            }
+        }
+
+        let mut restore = HashSet::new();
+        for ((index, action), occurrences) in actions {
+            let file = &self.files[index];
+            warn!("Decoy {} {:?}", file.path.display(), action);
+            let event = self
+                .event(action, file.kind, &file.path.to_string_lossy())
+                .occurrences(occurrences)
+                .build();
+            self.queue(event);
+            if action != DeceptionAction::Accessed {
+                restore.insert(index);
+            }
+        }
+
+        for index in 0..self.files.len() {
+            if restore.contains(&index) {
+                self.restore_file(index);
+            } else {
+                self.files[index].accessed = fs::metadata(&self.files[index].path)
+                    .and_then(|metadata| metadata.accessed())
+                    .ok();
+            }
+        }
+    }
+
+    fn check_registry(&mut self) {
+        let Some(key) = self.registry_key.clone() else {
+            return;
+        };
+        let hint = self.credentials.qualified_username();
+        let action = match read_username_hint(&key) {
+            None => DeceptionAction::Deleted,
+            Some(current) if current != hint => DeceptionAction::Modified,
+            Some(_) => return,
+        };
+
+        warn!("Decoy RDP server entry {} {:?}", key, action);
+        let event = self.event(action, DecoyKind::RdpRegistry, &key).build();
+        self.queue(event);
+        if let Err(e) = plant_username_hint(&key, &hint) {
+            warn!("Failed to restore decoy RDP server entry: {}", e);
+        }
+    }
+
+    fn check_processes(&mut self) {
+        self.sys.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing().with_cmd(UpdateKind::OnlyIfNotSet),
+        );
+
+        let own_pid = std::process::id();
+        let indicators = self.credentials.indicators();
+        let mut matches = Vec::new();
+        let mut alive = HashSet::new();
+
+        for (pid, process) in self.sys.processes() {
+            let key = (pid.as_u32(), process.start_time());
+            alive.insert(key);
+            if key.0 == own_pid || self.reported_processes.contains(&key) {
+                continue;
+            }
+            let command_line = process
+                .cmd()
+                .iter()
+                .map(|argument| argument.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let lowered = command_line.to_lowercase();
+            if let Some(indicator) = indicators.iter().find(|indicator| lowered.contains(&indicator.to_lowercase())) {
+                matches.push((key, command_line, indicator.clone()));
+            }
+        }
+        self.reported_processes.retain(|key| alive.contains(key));
+
+        for ((pid, start_time), command_line, indicator) in matches {
+            self.reported_processes.insert((pid, start_time));
+            warn!("Process {} used decoy credential indicator {}", pid, indicator);
+
+            let decoy = self.credentials.fqdn();
+            let mut builder = self
+                .event(DeceptionAction::CredentialUsed, DecoyKind::Custom, &decoy)
+                .matched_indicator(indicator)
+                .process_id(pid)
+                .command_line(command_line);
+            if let Some(details) = self.resolver.resolve(pid) {
+                builder = builder.process_name(details.name);
+                if let Some(user) = details.user {
+                    builder = builder.user(user);
+                }
+            }
+            let event = builder.build();
+            self.queue(event);
+        }
+    }
+
+    fn collect_events(&mut self) -> Result<Vec<DeceptionEvent>, CollectionError> {
+        self.check_files();
+        if cfg!(target_os = "windows") {
+            self.check_registry();
+        }
+        if self.config.scan_process_command_lines {
+            self.check_processes();
+        }
+
+        let events = std::mem::take(&mut self.pending);
+        info!("Monitoring {} decoys, {} deception events", self.files.len() + self.registry_key.iter().count(), events.len());
+        Ok(events)
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if self.files.is_empty() && self.registry_key.is_none() {
+            return Err(CollectionError::SystemApi(
+                "No decoy could be planted".to_string()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DataCollector<Vec<DeceptionEvent>> for DeceptionCollector {
+    fn collect(&mut self) -> Result<Vec<DeceptionEvent>, CollectionError> {
+        self.collect_events()
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<DeceptionEvent>> for DeceptionCollector {
+    async fn collect(&mut self) -> Result<Vec<DeceptionEvent>, CollectionError> {
+        DataCollector::collect(self)
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+impl Default for DeceptionCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
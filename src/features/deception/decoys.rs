@@ -0,0 +1,89 @@
+use crate::features::deception::models::DecoyKind;
+use crate::shared::command;
+
+// Where mstsc keeps its "recent servers" list and the user name it last used for each
+pub const RDP_SERVERS_KEY: &str = r"HKCU\Software\Microsoft\Terminal Server Client\Servers";
+
+// The fake identity every decoy advertises
+#[derive(Debug, Clone)]
+pub struct DecoyCredentials {
+    pub username: String,
+    pub password: String,
+    pub host: String,
+    pub domain: String,
+}
+
+impl DecoyCredentials {
+    pub fn fqdn(&self) -> String {
+        format!("{}.{}", self.host, self.domain)
+    }
+
+    pub fn qualified_username(&self) -> String {
+        let netbios = self.domain.split('.').next().unwrap_or_default().to_uppercase();
+        format!("{}\\{}", netbios, self.username)
+    }
+
+    // Strings whose appearance anywhere outside the decoys means someone is using them
+    pub fn indicators(&self) -> Vec<String> {
+        vec![self.username.clone(), self.password.clone(), self.host.clone()]
+    }
+
+    pub fn render(&self, kind: DecoyKind, custom: Option<&str>) -> Option<String> {
+        match kind {
+            DecoyKind::BrowserPasswordExport => Some(format!(
+                "name,url,username,password,note\n\
+                 {fqdn},https://{fqdn}/login,{username},{password},\n\
+                 vpn.{domain},https://vpn.{domain}/,{username},{password},\n",
+                fqdn = self.fqdn(),
+                domain = self.domain,
+                username = self.username,
+                password = self.password,
+            )),
+            DecoyKind::RdpFile => Some(format!(
+                "screen mode id:i:2\r\n\
+                 full address:s:{}\r\n\
+                 username:s:{}\r\n\
+                 prompt for credentials:i:0\r\n\
+                 authentication level:i:2\r\n",
+                self.fqdn(),
+                self.qualified_username(),
+            )),
+            DecoyKind::Custom => custom.map(|template| {
+                template
+                    .replace("{username}", &self.username)
+                    .replace("{password}", &self.password)
+                    .replace("{host}", &self.host)
+                    .replace("{domain}", &self.domain)
+                    .replace("{fqdn}", &self.fqdn())
+            }),
+            DecoyKind::RdpRegistry => None,
+        }
+    }
+
+    pub fn rdp_registry_key(&self) -> String {
+        format!(r"{}\{}", RDP_SERVERS_KEY, self.fqdn())
+    }
+}
+
+// Current UsernameHint of a decoy RDP server entry, None when the entry is gone
+pub fn read_username_hint(key: &str) -> Option<String> {
+    let output = command::run("reg", ["query", key, "/v", "UsernameHint"]).ok()?;
+    if !output.success {
+        return None;
+    }
+    output
+        .stdout
+        .lines()
+        .find(|line| line.trim_start().starts_with("UsernameHint"))
+        .and_then(|line| line.split_once("REG_SZ"))
+        .map(|(_, value)| value.trim().to_string())
+}
+
+pub fn plant_username_hint(key: &str, username: &str) -> Result<(), String> {
+    let output = command::run("reg", ["add", key, "/v", "UsernameHint", "/t", "REG_SZ", "/d", username, "/f"])
+        .map_err(|e| format!("Failed to execute reg command: {}", e))?;
+    if !output.success {
+        return Err(format!("reg add failed: {}", output.stderr.trim()));
+    }
+    Ok(())
+}
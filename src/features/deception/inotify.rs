@@ -0,0 +1,91 @@
+// Direct inotify watches on the decoy files themselves. The notify crate does not ask
+// the kernel for open / read events, and those are exactly what a decoy is for.
+use log::warn;
+use std::ffi::CString;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub const ACCESSED: u32 = libc::IN_OPEN | libc::IN_ACCESS;
+pub const MODIFIED: u32 = libc::IN_MODIFY;
+pub const REMOVED: u32 = libc::IN_DELETE_SELF | libc::IN_MOVE_SELF;
+
+const EVENT_HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+
+pub struct DecoyWatch {
+    fd: Arc<OwnedFd>,
+    events: Mutex<Receiver<(i32, u32)>>,
+}
+
+impl DecoyWatch {
+    pub fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = Arc::new(unsafe { OwnedFd::from_raw_fd(fd) });
+
+        let (tx, rx) = channel();
+        let reader = fd.clone();
+        thread::Builder::new()
+            .name(String::from("decoy-inotify"))
+            .spawn(move || {
+                let mut buffer = vec![0u8; 16 * 1024];
+                loop {
+                    let read = unsafe {
+                        libc::read(reader.as_raw_fd(), buffer.as_mut_ptr() as *mut libc::c_void, buffer.len())
+                    };
+                    if read < 0 {
+                        let error = io::Error::last_os_error();
+                        if error.kind() == io::ErrorKind::Interrupted {
+                            continue;
+                        }
+                        warn!("Decoy inotify read failed, stopping: {}", error);
+                        return;
+                    }
+
+                    let read = read as usize;
+                    let mut offset = 0;
+                    while offset + EVENT_HEADER <= read {
+                        let event = unsafe {
+                            std::ptr::read_unaligned(buffer[offset..].as_ptr() as *const libc::inotify_event)
+                        };
+                        if tx.send((event.wd, event.mask)).is_err() {
+                            return;
+                        }
+                        offset += EVENT_HEADER + event.len as usize;
+                    }
+                }
+            })?;
+
+        Ok(Self { fd, events: Mutex::new(rx) })
+    }
+
+    pub fn add(&self, path: &Path) -> io::Result<i32> {
+        let path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let wd = unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), path.as_ptr(), ACCESSED | MODIFIED | REMOVED) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(wd)
+    }
+
+    pub fn remove(&self, wd: i32) {
+        unsafe {
+            libc::inotify_rm_watch(self.fd.as_raw_fd(), wd);
+        }
+    }
+
+    // (watch descriptor, event mask) pairs received since the last call
+    pub fn drain(&self) -> Vec<(i32, u32)> {
+        match self.events.lock() {
+            Ok(events) => events.try_iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+mod collector;
+mod decoys;
+mod models;
+#[cfg(target_os = "linux")]
+mod inotify;
+
+pub use collector::{DecoyFileConfig, DeceptionCollector, DeceptionConfig};
+pub use decoys::DecoyCredentials;
+pub use models::{DecoyKind, DeceptionAction, DeceptionEvent, DeceptionEventBuilder};
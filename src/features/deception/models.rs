@@ -0,0 +1,204 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecoyKind {
+    // CSV in the format browsers use for "export passwords"
+    BrowserPasswordExport,
+    RdpFile,
+    RdpRegistry,
+    Custom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeceptionAction {
+    Planted,
+    Accessed,
+    Modified,
+    Deleted,
+    CredentialUsed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeceptionEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub action: DeceptionAction,
+    pub decoy_kind: DecoyKind,
+    // File path or registry key of the decoy
+    pub decoy: String,
+    // Raw notifications folded into this event since the last collection
+    pub occurrences: u32,
+    pub matched_indicator: Option<String>,
+    pub process_id: Option<u32>,
+    pub process_name: Option<String>,
+    pub command_line: Option<String>,
+    pub user: Option<String>,
+}
+
+impl Event for DeceptionEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.action {
+            DeceptionAction::Planted => "decoy_planted",
+            DeceptionAction::Accessed => "decoy_accessed",
+            DeceptionAction::Modified => "decoy_modified",
+            DeceptionAction::Deleted => "decoy_deleted",
+            DeceptionAction::CredentialUsed => "decoy_credential_used",
+        }
+    }
+
+    // Nothing legitimate touches a decoy
+    fn severity(&self) -> Severity {
+        match self.action {
+            DeceptionAction::Planted => Severity::Low,
+            _ => Severity::Critical,
+        }
+    }
+}
+
+impl Identifiable for DeceptionEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for DeceptionEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.decoy.is_empty() {
+            return Err("Decoy cannot be empty".to_string());
+        }
+
+        if self.action == DeceptionAction::CredentialUsed && self.matched_indicator.is_none() {
+            return Err("Credential use events must carry the matched indicator".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct DeceptionEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    action: Option<DeceptionAction>,
+    decoy_kind: Option<DecoyKind>,
+    decoy: Option<String>,
+    occurrences: Option<u32>,
+    matched_indicator: Option<String>,
+    process_id: Option<u32>,
+    process_name: Option<String>,
+    command_line: Option<String>,
+    user: Option<String>,
+}
+
+impl DeceptionEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn action(mut self, action: DeceptionAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn decoy_kind(mut self, decoy_kind: DecoyKind) -> Self {
+        self.decoy_kind = Some(decoy_kind);
+        self
+    }
+
+    pub fn decoy(mut self, decoy: String) -> Self {
+        self.decoy = Some(decoy);
+        self
+    }
+
+    pub fn occurrences(mut self, occurrences: u32) -> Self {
+        self.occurrences = Some(occurrences);
+        self
+    }
+
+    pub fn matched_indicator(mut self, matched_indicator: String) -> Self {
+        self.matched_indicator = Some(matched_indicator);
+        self
+    }
+
+    pub fn process_id(mut self, process_id: u32) -> Self {
+        self.process_id = Some(process_id);
+        self
+    }
+
+    pub fn process_name(mut self, process_name: String) -> Self {
+        self.process_name = Some(process_name);
+        self
+    }
+
+    pub fn command_line(mut self, command_line: String) -> Self {
+        self.command_line = Some(command_line);
+        self
+    }
+
+    pub fn user(mut self, user: String) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    pub fn build(self) -> Result<DeceptionEvent, String> {
+        let event = DeceptionEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            action: self.action.ok_or("action is required")?,
+            decoy_kind: self.decoy_kind.ok_or("decoy_kind is required")?,
+            decoy: self.decoy.ok_or("decoy is required")?,
+            occurrences: self.occurrences.unwrap_or(1),
+            matched_indicator: self.matched_indicator,
+            process_id: self.process_id,
+            process_name: self.process_name,
+            command_line: self.command_line,
+            user: self.user,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
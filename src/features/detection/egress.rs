@@ -0,0 +1,299 @@
+use crate::features::detection::models::{Alert, AlertBuilder, DetectionSource};
+use crate::features::network::NetworkConnectionInformation;
+use crate::shared::baseline::Baseline;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::policy_profiles::HostRole;
+use crate::shared::traits::Severity;
+use crate::utils::load_config_section;
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use uuid::Uuid;
+
+// Policy applied to hosts whose role has none of its own, and to hosts without a profile
+pub const DEFAULT_EGRESS_POLICY: &str = "default";
+
+const RULE_ID: &str = "egress_policy_violation";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EgressRules {
+    // Destination networks such as 10.0.0.0/8, or single addresses
+    pub allowed_cidrs: Vec<String>,
+    // Destination host names, allowed at whatever addresses they resolve to; a name does not
+    // cover its subdomains
+    pub allowed_domains: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EgressConfig {
+    pub enabled: bool,
+    // Allowed destinations per policy profile (workstation, server, domain_controller, kiosk)
+    // or `default`
+    pub policies: BTreeMap<String, EgressRules>,
+    pub severity: Severity,
+    // How often allowed_domains are resolved again, for names whose addresses move
+    pub resolve_secs: u64,
+    // The same process talking to the same destination is reported once within this window
+    pub suppress_secs: u64,
+}
+
+impl Default for EgressConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            policies: BTreeMap::new(),
+            severity: Severity::High,
+            resolve_secs: 300,
+            suppress_secs: 3600,
+        }
+    }
+}
+
+impl EgressConfig {
+    pub fn load() -> Self {
+        load_config_section("egress")
+    }
+}
+
+// A CIDR block or a bare address, which stands for a block of one
+pub fn parse_network(value: &str) -> Option<(IpAddr, u32)> {
+    let value = value.trim();
+    let (address, prefix) = match value.split_once('/') {
+        Some((address, prefix)) => (address.parse::<IpAddr>().ok()?, Some(prefix.parse::<u32>().ok()?)),
+        None => (value.parse::<IpAddr>().ok()?, None),
+    };
+    let bits = if address.is_ipv4() { 32 } else { 128 };
+    match prefix {
+        Some(prefix) if prefix > bits => None,
+        prefix => Some((address, prefix.unwrap_or(bits))),
+    }
+}
+
+pub(crate) fn contains((network, prefix): (IpAddr, u32), address: IpAddr) -> bool {
+    match (network, address) {
+        (IpAddr::V4(network), IpAddr::V4(address)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(network) & mask == u32::from(address) & mask
+        }
+        (IpAddr::V6(network), IpAddr::V6(address)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(network) & mask == u128::from(address) & mask
+        }
+        _ => false,
+    }
+}
+
+struct Resolved {
+    at: Option<Instant>,
+    addresses: HashMap<String, Vec<IpAddr>>,
+}
+
+// Detective control for hosts that should only talk to known endpoints: outbound connections
+// to destinations outside the allow-list of the host's role raise an alert. Loopback traffic,
+// sockets without a remote end and connections accepted on a listening port are not egress.
+pub struct EgressPolicy {
+    config: EgressConfig,
+    policy: String,
+    networks: Vec<(IpAddr, u32)>,
+    domains: Vec<String>,
+    resolved: Mutex<Resolved>,
+    // Kept across restarts like IOC matches, so a long-lived connection is not reported again
+    // every time the agent starts
+    reported: Mutex<HashMap<String, DateTime<Utc>>>,
+    host: Arc<HostIdentityService>,
+}
+
+impl EgressPolicy {
+    const REPORTED_BASELINE: &'static str = "egress_reported";
+
+    // The policy of the given role, or the default one; None when neither is configured
+    pub fn for_role(config: EgressConfig, role: Option<HostRole>) -> Option<Self> {
+        let policy = role
+            .map(|role| role.name())
+            .filter(|name| config.policies.contains_key(*name))
+            .unwrap_or(DEFAULT_EGRESS_POLICY)
+            .to_string();
+        let Some(rules) = config.policies.get(&policy) else {
+            warn!(
+                "Egress monitoring is enabled but there is no policy for the {} profile nor a default one",
+                role.map(|role| role.name()).unwrap_or("none")
+            );
+            return None;
+        };
+
+        let networks: Vec<(IpAddr, u32)> = rules
+            .allowed_cidrs
+            .iter()
+            .filter_map(|cidr| {
+                let network = parse_network(cidr);
+                if network.is_none() {
+                    warn!("Ignoring invalid egress CIDR '{}'", cidr);
+                }
+                network
+            })
+            .collect();
+        let domains: Vec<String> = rules
+            .allowed_domains
+            .iter()
+            .map(|domain| domain.trim().trim_end_matches('.').to_lowercase())
+            .collect();
+        info!(
+            "Egress monitoring on the {} policy with {} allowed networks and {} allowed domains",
+            policy,
+            networks.len(),
+            domains.len()
+        );
+
+        Some(Self {
+            policy,
+            networks,
+            domains,
+            resolved: Mutex::new(Resolved {
+                at: None,
+                addresses: HashMap::new(),
+            }),
+            reported: Mutex::new(Baseline::global().load(Self::REPORTED_BASELINE).unwrap_or_default()),
+            host: HostIdentityService::global(),
+            config,
+        })
+    }
+
+    // Resolves allowed_domains once resolve_secs have passed; a name that fails to resolve
+    // keeps the addresses it had
+    async fn resolve_domains(&self) {
+        let due = self
+            .resolved
+            .lock()
+            .map(|resolved| resolved.at.is_none_or(|at| at.elapsed().as_secs() >= self.config.resolve_secs))
+            .unwrap_or(false);
+        if !due || self.domains.is_empty() {
+            return;
+        }
+
+        let mut addresses = HashMap::new();
+        for domain in &self.domains {
+            match tokio::net::lookup_host((domain.as_str(), 0)).await {
+                Ok(resolved) => {
+                    addresses.insert(domain.clone(), resolved.map(|socket| socket.ip()).collect());
+                }
+                Err(e) => debug!("Failed to resolve allowed egress domain {}: {}", domain, e),
+            }
+        }
+        if let Ok(mut resolved) = self.resolved.lock() {
+            resolved.addresses.extend(addresses);
+            resolved.at = Some(Instant::now());
+        }
+    }
+
+    // Inside an allowed network or at an address an allowed domain resolved to
+    fn allowed(&self, address: IpAddr, resolved: &HashMap<String, Vec<IpAddr>>) -> bool {
+        self.networks.iter().any(|network| contains(*network, address))
+            || resolved.values().any(|addresses| addresses.contains(&address))
+    }
+
+    pub async fn evaluate(&self, connections: &[NetworkConnectionInformation]) -> Vec<Alert> {
+        self.resolve_domains().await;
+        let Ok(resolved) = self.resolved.lock().map(|resolved| resolved.addresses.clone()) else {
+            return Vec::new();
+        };
+        let Ok(mut reported) = self.reported.lock() else {
+            return Vec::new();
+        };
+
+        let listening: HashSet<(&str, u16)> = connections
+            .iter()
+            .filter(|connection| connection.state == "LISTENING")
+            .map(|connection| (connection.protocol.as_str(), connection.local_port))
+            .collect();
+        let now = Utc::now();
+        let suppress_since = now - Duration::seconds(self.config.suppress_secs as i64);
+        let tracked = reported.len();
+        reported.retain(|_, reported_at| *reported_at > suppress_since);
+        let mut changed = reported.len() != tracked;
+
+        let mut alerts = Vec::new();
+        for connection in connections {
+            let Ok(remote) = connection.remote_address.parse::<IpAddr>() else {
+                continue;
+            };
+            if remote.is_unspecified()
+                || remote.is_loopback()
+                || connection.remote_port == 0
+                || listening.contains(&(connection.protocol.as_str(), connection.local_port))
+                || self.allowed(remote, &resolved)
+            {
+                continue;
+            }
+
+            let process = connection.process_path.as_deref().or(connection.process_name.as_deref());
+            let key = format!(
+                "{}|{}|{}|{}",
+                process.unwrap_or_default(),
+                connection.protocol,
+                connection.remote_address,
+                connection.remote_port
+            );
+            if reported.contains_key(&key) {
+                continue;
+            }
+            reported.insert(key, now);
+            changed = true;
+
+            let mut matched_fields = BTreeMap::from([
+                (String::from("remote_address"), connection.remote_address.clone()),
+                (String::from("remote_port"), connection.remote_port.to_string()),
+                (String::from("protocol"), connection.protocol.clone()),
+                (String::from("policy"), self.policy.clone()),
+            ]);
+            let optional = [
+                ("process_id", connection.process_id.map(|pid| pid.to_string())),
+                ("process_name", connection.process_name.clone()),
+                ("process_path", connection.process_path.clone()),
+                ("user", connection.user.clone()),
+            ];
+            for (field, value) in optional {
+                if let Some(value) = value {
+                    matched_fields.insert(field.to_string(), value);
+                }
+            }
+
+            let alert = AlertBuilder::new()
+                .id(Uuid::new_v4().to_string())
+                .timestamp(now)
+                .source(self.host.name())
+                .category(String::from("detection"))
+                .rule_id(String::from(RULE_ID))
+                .rule_name(String::from("Egress outside the allow-list"))
+                .description(Some(format!(
+                    "{} connected to {}:{}, which the {} egress policy does not allow",
+                    process.unwrap_or("An unknown process"),
+                    connection.remote_address,
+                    connection.remote_port,
+                    self.policy
+                )))
+                .severity(self.config.severity)
+                .detection_source(DetectionSource::Network)
+                .matched_fields(matched_fields)
+                .tags(vec![String::from("egress_policy")])
+                .build();
+            match alert {
+                Ok(alert) => {
+                    warn!("Egress policy violation: {}", alert.description.as_deref().unwrap_or_default());
+                    alerts.push(alert);
+                }
+                Err(e) => warn!("Dropping egress policy alert: {}", e),
+            }
+        }
+
+        if changed {
+            Baseline::global().save(Self::REPORTED_BASELINE, &*reported);
+        }
+        alerts
+    }
+}
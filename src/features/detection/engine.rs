@@ -0,0 +1,195 @@
+use crate::features::detection::models::{Alert, AlertBuilder, DetectionSource};
+use crate::features::detection::rules::{field_values, load_rules, CompiledRule, Rule};
+use crate::features::detection::sigma::load_sigma_rules;
+use crate::shared::host_identity::HostIdentityService;
+use crate::utils::load_config_section;
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+// Most event ids a threshold alert lists in related_ids, the latest ones
+const MAX_RELATED_IDS: usize = 100;
+
+// Match times of a threshold rule with the ids of the matched events
+type MatchWindow = VecDeque<(DateTime<Utc>, Option<String>)>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DetectionConfig {
+    pub enabled: bool,
+    // YAML files with a top-level `rules:` list
+    pub rule_files: Vec<String>,
+    // Rules defined directly in this section, same format
+    pub rules: Vec<Rule>,
+    // Directories searched recursively for Sigma rules (.yml / .yaml)
+    pub sigma_directories: Vec<String>,
+    // Cap on alerts one rule may raise per evaluated batch, so a noisy rule cannot flood the index
+    pub max_alerts_per_rule: usize,
+}
+
+impl Default for DetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rule_files: vec![String::from("config/rules.yaml")],
+            rules: Vec::new(),
+            sigma_directories: vec![String::from("rules")],
+            max_alerts_per_rule: 100,
+        }
+    }
+}
+
+impl DetectionConfig {
+    pub fn load() -> Self {
+        load_config_section("detection")
+    }
+}
+
+// Evaluates collected events against user-supplied rules. A rule either alerts on every
+// event that satisfies its match conditions, or, with a threshold, once when the number of
+// matches for a group reaches the count inside the window.
+pub struct RuleEngine {
+    config: DetectionConfig,
+    rules: Vec<CompiledRule>,
+    // Per (rule id, group key) for threshold rules
+    windows: Mutex<HashMap<(String, String), MatchWindow>>,
+    host: Arc<HostIdentityService>,
+}
+
+impl RuleEngine {
+    pub fn new() -> Self {
+        Self::with_config(DetectionConfig::load())
+    }
+
+    pub fn with_config(config: DetectionConfig) -> Self {
+        let mut rules = load_rules(&config.rule_files, &config.rules);
+        rules.extend(load_sigma_rules(&config.sigma_directories));
+        let rules: Vec<CompiledRule> = rules
+            .into_iter()
+            .filter(|rule| rule.enabled)
+            .filter_map(|rule| match CompiledRule::compile(rule) {
+                Ok(rule) => Some(rule),
+                Err(e) => {
+                    warn!("Skipping detection rule: {}", e);
+                    None
+                }
+            })
+            .collect();
+        info!("Detection engine loaded {} rules", rules.len());
+
+        Self {
+            config,
+            rules,
+            windows: Mutex::new(HashMap::new()),
+            host: HostIdentityService::global(),
+        }
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    fn alert(&self, rule: &Rule, event_id: Option<String>, matched_fields: BTreeMap<String, String>) -> AlertBuilder {
+        AlertBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("detection"))
+            .rule_id(rule.id.clone())
+            .rule_name(rule.name.clone().unwrap_or_else(|| rule.id.clone()))
+            .description(rule.description.clone())
+            .severity(rule.severity)
+            .detection_source(rule.source)
+            .event_id(event_id)
+            .matched_fields(matched_fields)
+            .tags(rule.tags.clone())
+    }
+
+    // Records a match for a threshold rule, returning the count and the ids of the counted
+    // events when it just reached the threshold
+    fn count_match(
+        &self,
+        rule: &CompiledRule,
+        group: &str,
+        event_id: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Option<(u64, Vec<String>)> {
+        let threshold = rule.rule.threshold.as_ref()?;
+        let mut windows = self.windows.lock().ok()?;
+        let window = windows.entry((rule.rule.id.clone(), group.to_string())).or_default();
+        let horizon = now - Duration::seconds(threshold.window_secs as i64);
+        while window.front().is_some_and(|(seen, _)| *seen < horizon) {
+            window.pop_front();
+        }
+        window.push_back((now, event_id));
+        let count = window.len() as u64;
+        if count < threshold.count.max(1) {
+            return None;
+        }
+        // Start counting afresh so a sustained burst alerts once per threshold, not per event
+        let counted: Vec<String> = window.drain(..).filter_map(|(_, event_id)| event_id).collect();
+        let related_ids = counted[counted.len().saturating_sub(MAX_RELATED_IDS)..].to_vec();
+        Some((count, related_ids))
+    }
+
+    pub fn evaluate<T: Serialize>(&self, source: DetectionSource, events: &[T]) -> Vec<Alert> {
+        let rules: Vec<&CompiledRule> = self.rules.iter().filter(|rule| rule.rule.source == source).collect();
+        if rules.is_empty() || events.is_empty() {
+            return Vec::new();
+        }
+        let documents: Vec<Value> = events.iter().filter_map(|event| serde_json::to_value(event).ok()).collect();
+        let now = Utc::now();
+
+        let mut alerts = Vec::new();
+        for rule in rules {
+            let mut raised = 0;
+            for document in &documents {
+                if raised >= self.config.max_alerts_per_rule {
+                    debug!("Rule {} hit its alert cap for this batch", rule.rule.id);
+                    break;
+                }
+                let Some(matched) = rule.evaluate(document) else {
+                    continue;
+                };
+                let event_id = document.get("id").and_then(Value::as_str).map(str::to_string);
+
+                let builder = match &rule.rule.threshold {
+                    Some(threshold) => {
+                        let group: BTreeMap<String, String> = threshold
+                            .group_by
+                            .iter()
+                            .map(|field| (field.clone(), field_values(document, field).join(",")))
+                            .collect();
+                        let key = group.values().cloned().collect::<Vec<_>>().join("|");
+                        let Some((count, related_ids)) = self.count_match(rule, &key, event_id.clone(), now) else {
+                            continue;
+                        };
+                        let fields = if group.is_empty() { matched.into_iter().collect() } else { group };
+                        self.alert(&rule.rule, event_id, fields).count(count).related_ids(related_ids)
+                    }
+                    None => self.alert(&rule.rule, event_id, matched.into_iter().collect()),
+                };
+
+                match builder.build() {
+                    Ok(alert) => {
+                        warn!("Detection rule {} ({:?}) matched: {:?}", alert.rule_id, alert.severity, alert.matched_fields);
+                        alerts.push(alert);
+                        raised += 1;
+                    }
+                    Err(e) => warn!("Dropping alert for rule {}: {}", rule.rule.id, e),
+                }
+            }
+        }
+        alerts
+    }
+}
+
+impl Default for RuleEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
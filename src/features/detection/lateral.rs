@@ -0,0 +1,319 @@
+use crate::features::detection::egress::{contains, parse_network};
+use crate::features::detection::models::{Alert, AlertBuilder, DetectionSource};
+use crate::features::network::NetworkConnectionInformation;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::traits::Severity;
+use crate::utils::load_config_section;
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+const RULE_ID: &str = "lateral_movement";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LateralMovementConfig {
+    pub enabled: bool,
+    // Destinations counted as internal; connections elsewhere are left to egress monitoring
+    pub internal_cidrs: Vec<String>,
+    // Connections one process opened within this many seconds are looked at together
+    pub window_secs: u64,
+    // Distinct internal hosts one process reaches within the window before it is a sweep
+    pub max_hosts: usize,
+    // Distinct ports one process reaches on a single internal host before it is a port scan
+    pub max_ports: usize,
+    // Remote administration and file sharing ports (SMB, RPC, RDP, WinRM, SSH), where far
+    // fewer hosts already look like spraying credentials or payloads
+    pub admin_ports: Vec<u16>,
+    pub max_admin_hosts: usize,
+    pub severity: Severity,
+    // The same process is reported once per pattern within this window
+    pub suppress_secs: u64,
+}
+
+impl Default for LateralMovementConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            internal_cidrs: vec![
+                String::from("10.0.0.0/8"),
+                String::from("172.16.0.0/12"),
+                String::from("192.168.0.0/16"),
+                String::from("fc00::/7"),
+            ],
+            window_secs: 300,
+            max_hosts: 20,
+            max_ports: 25,
+            admin_ports: vec![22, 135, 139, 445, 3389, 5985, 5986],
+            max_admin_hosts: 5,
+            severity: Severity::High,
+            suppress_secs: 3600,
+        }
+    }
+}
+
+impl LateralMovementConfig {
+    pub fn load() -> Self {
+        load_config_section("lateral_movement")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Pattern {
+    HostSweep,
+    PortScan,
+    AdminSpray,
+}
+
+impl Pattern {
+    fn name(&self) -> &'static str {
+        match self {
+            Pattern::HostSweep => "host_sweep",
+            Pattern::PortScan => "port_scan",
+            Pattern::AdminSpray => "admin_port_spray",
+        }
+    }
+
+    fn rule_name(&self) -> &'static str {
+        match self {
+            Pattern::HostSweep => "Internal host sweep",
+            Pattern::PortScan => "Internal port scan",
+            Pattern::AdminSpray => "Remote administration ports sprayed across hosts",
+        }
+    }
+}
+
+// A process as seen in the connection table; the PID keeps two instances of the same
+// program apart
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ProcessKey {
+    pid: Option<u32>,
+    image: String,
+}
+
+#[derive(Default)]
+struct ProcessActivity {
+    // Internal destination and when it was last seen
+    targets: HashMap<(IpAddr, u16), DateTime<Utc>>,
+    // Connection details for the alert
+    last: Option<NetworkConnectionInformation>,
+}
+
+#[derive(Default)]
+struct State {
+    processes: HashMap<ProcessKey, ProcessActivity>,
+    reported: HashMap<(ProcessKey, Pattern), DateTime<Utc>>,
+}
+
+// Recognizes this endpoint scanning its own network: one process connecting to many internal
+// hosts, to many ports of one host, or to the remote administration ports of several hosts
+// within a short window. Works on the connection table, so connections that open and close
+// between two collections are not seen; half-open attempts (SYN_SENT) are, and count.
+pub struct LateralMovementDetector {
+    config: LateralMovementConfig,
+    internal: Vec<(IpAddr, u32)>,
+    admin_ports: HashSet<u16>,
+    state: Mutex<State>,
+    host: Arc<HostIdentityService>,
+}
+
+impl LateralMovementDetector {
+    pub fn new(config: LateralMovementConfig) -> Self {
+        let internal = config
+            .internal_cidrs
+            .iter()
+            .filter_map(|cidr| {
+                let network = parse_network(cidr);
+                if network.is_none() {
+                    warn!("Ignoring invalid internal CIDR '{}'", cidr);
+                }
+                network
+            })
+            .collect();
+        Self {
+            internal,
+            admin_ports: config.admin_ports.iter().copied().collect(),
+            state: Mutex::new(State::default()),
+            host: HostIdentityService::global(),
+            config,
+        }
+    }
+
+    fn internal(&self, address: IpAddr) -> bool {
+        self.internal.iter().any(|network| contains(*network, address))
+    }
+
+    // The pattern a process's recent targets show, with the targets that make it up
+    fn classify(&self, targets: &HashMap<(IpAddr, u16), DateTime<Utc>>) -> Option<(Pattern, BTreeSet<(IpAddr, u16)>)> {
+        let admin: Vec<(IpAddr, u16)> = targets
+            .keys()
+            .filter(|(_, port)| self.admin_ports.contains(port))
+            .copied()
+            .collect();
+        let admin_hosts: HashSet<IpAddr> = admin.iter().map(|(address, _)| *address).collect();
+        if admin_hosts.len() >= self.config.max_admin_hosts {
+            return Some((Pattern::AdminSpray, admin.into_iter().collect()));
+        }
+
+        let mut ports: HashMap<IpAddr, BTreeSet<(IpAddr, u16)>> = HashMap::new();
+        for target in targets.keys() {
+            ports.entry(target.0).or_default().insert(*target);
+        }
+        if ports.len() >= self.config.max_hosts {
+            return Some((Pattern::HostSweep, targets.keys().copied().collect()));
+        }
+        ports
+            .into_values()
+            .max_by_key(BTreeSet::len)
+            .filter(|host| host.len() >= self.config.max_ports)
+            .map(|host| (Pattern::PortScan, host))
+    }
+
+    fn alert(
+        &self,
+        pattern: Pattern,
+        connection: &NetworkConnectionInformation,
+        targets: BTreeSet<(IpAddr, u16)>,
+        now: DateTime<Utc>,
+    ) -> Option<Alert> {
+        let hosts: BTreeSet<IpAddr> = targets.iter().map(|(address, _)| *address).collect();
+        let ports: BTreeSet<u16> = targets.iter().map(|(_, port)| *port).collect();
+        // Enough to see what was scanned without a field the size of a subnet
+        let sample: Vec<String> = targets
+            .iter()
+            .take(10)
+            .map(|(address, port)| format!("{}:{}", address, port))
+            .collect();
+
+        let mut matched_fields = BTreeMap::from([
+            (String::from("pattern"), pattern.name().to_string()),
+            (String::from("distinct_hosts"), hosts.len().to_string()),
+            (String::from("distinct_ports"), ports.len().to_string()),
+            (String::from("targets"), sample.join(", ")),
+            (String::from("window_secs"), self.config.window_secs.to_string()),
+        ]);
+        let optional = [
+            ("process_id", connection.process_id.map(|pid| pid.to_string())),
+            ("process_name", connection.process_name.clone()),
+            ("process_path", connection.process_path.clone()),
+            ("user", connection.user.clone()),
+        ];
+        for (field, value) in optional {
+            if let Some(value) = value {
+                matched_fields.insert(field.to_string(), value);
+            }
+        }
+
+        let process = connection
+            .process_path
+            .as_deref()
+            .or(connection.process_name.as_deref())
+            .unwrap_or("An unknown process");
+        let description = match pattern {
+            Pattern::PortScan => format!(
+                "{} connected to {} ports of {} within {} seconds",
+                process,
+                ports.len(),
+                hosts.iter().next().map(IpAddr::to_string).unwrap_or_default(),
+                self.config.window_secs
+            ),
+            _ => format!(
+                "{} connected to {} internal hosts on ports {} within {} seconds",
+                process,
+                hosts.len(),
+                ports.iter().map(u16::to_string).collect::<Vec<_>>().join(", "),
+                self.config.window_secs
+            ),
+        };
+
+        let alert = AlertBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(now)
+            .source(self.host.name())
+            .category(String::from("detection"))
+            .rule_id(String::from(RULE_ID))
+            .rule_name(pattern.rule_name().to_string())
+            .description(Some(description))
+            .severity(self.config.severity)
+            .detection_source(DetectionSource::Network)
+            .matched_fields(matched_fields)
+            .tags(vec![String::from("lateral_movement"), pattern.name().to_string()])
+            .build();
+        match alert {
+            Ok(alert) => {
+                warn!("Possible lateral movement: {}", alert.description.as_deref().unwrap_or_default());
+                Some(alert)
+            }
+            Err(e) => {
+                warn!("Dropping lateral movement alert: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn evaluate(&self, connections: &[NetworkConnectionInformation]) -> Vec<Alert> {
+        let Ok(mut state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let now = Utc::now();
+        let window_start = now - Duration::seconds(self.config.window_secs as i64);
+        let suppress_since = now - Duration::seconds(self.config.suppress_secs as i64);
+
+        let listening: HashSet<(&str, u16)> = connections
+            .iter()
+            .filter(|connection| connection.state == "LISTENING")
+            .map(|connection| (connection.protocol.as_str(), connection.local_port))
+            .collect();
+        let local: HashSet<&str> = connections.iter().map(|connection| connection.local_address.as_str()).collect();
+        for connection in connections {
+            let Ok(remote) = connection.remote_address.parse::<IpAddr>() else {
+                continue;
+            };
+            if connection.remote_port == 0
+                || local.contains(connection.remote_address.as_str())
+                || listening.contains(&(connection.protocol.as_str(), connection.local_port))
+                || !self.internal(remote)
+            {
+                continue;
+            }
+            let key = ProcessKey {
+                pid: connection.process_id,
+                image: connection
+                    .process_path
+                    .clone()
+                    .or_else(|| connection.process_name.clone())
+                    .unwrap_or_default(),
+            };
+            let activity = state.processes.entry(key).or_default();
+            activity.targets.insert((remote, connection.remote_port), now);
+            activity.last = Some(connection.clone());
+        }
+
+        state.processes.retain(|_, activity| {
+            activity.targets.retain(|_, seen| *seen > window_start);
+            !activity.targets.is_empty()
+        });
+        state.reported.retain(|_, reported_at| *reported_at > suppress_since);
+
+        let mut found = Vec::new();
+        for (key, activity) in &state.processes {
+            let (Some((pattern, targets)), Some(connection)) = (self.classify(&activity.targets), &activity.last) else {
+                continue;
+            };
+            if !state.reported.contains_key(&(key.clone(), pattern)) {
+                found.push((key.clone(), pattern, connection.clone(), targets));
+            }
+        }
+
+        let mut alerts = Vec::new();
+        for (key, pattern, connection, targets) in found {
+            state.reported.insert((key, pattern), now);
+            alerts.extend(self.alert(pattern, &connection, targets, now));
+        }
+        alerts
+    }
+}
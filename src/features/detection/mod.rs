@@ -0,0 +1,16 @@
+mod egress;
+mod engine;
+mod lateral;
+mod models;
+mod outbound_volume;
+mod rules;
+mod sigma;
+
+pub use egress::{parse_network, EgressConfig, EgressPolicy, EgressRules, DEFAULT_EGRESS_POLICY};
+pub(crate) use egress::contains;
+pub use engine::{DetectionConfig, RuleEngine};
+pub use lateral::{LateralMovementConfig, LateralMovementDetector};
+pub use models::{Alert, AlertBuilder, DetectionSource};
+pub use outbound_volume::{OutboundVolumeConfig, OutboundVolumeDetector};
+pub use rules::{load_rules, CompiledMatcher, FieldMatcher, Rule, Threshold};
+pub use sigma::load_sigma_rules;
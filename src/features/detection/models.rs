@@ -0,0 +1,201 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+// The event streams rules can be written against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectionSource {
+    File,
+    Registry,
+    Process,
+    Network,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    // Ids of the events that raised the alert: the triggering event, or every event counted
+    // towards a threshold
+    #[serde(default)]
+    pub related_ids: Vec<String>,
+    pub rule_id: String,
+    pub rule_name: String,
+    pub description: Option<String>,
+    pub severity: Severity,
+    pub detection_source: DetectionSource,
+    // Id of the triggering event, when the event type has one
+    pub event_id: Option<String>,
+    // Field values the rule matched on, or the group_by values for threshold rules
+    pub matched_fields: BTreeMap<String, String>,
+    // Number of matching events in the window, threshold rules only
+    pub count: Option<u64>,
+    pub tags: Vec<String>,
+}
+
+impl Event for Alert {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        &self.rule_id
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl Identifiable for Alert {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for Alert {
+    fn validate(&self) -> Result<(), String> {
+        if self.rule_id.is_empty() {
+            return Err("Alert requires a rule id".to_string());
+        }
+        if self.matched_fields.is_empty() {
+            return Err("Alert requires at least one matched field".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct AlertBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    related_ids: Vec<String>,
+    rule_id: Option<String>,
+    rule_name: Option<String>,
+    description: Option<String>,
+    severity: Option<Severity>,
+    detection_source: Option<DetectionSource>,
+    event_id: Option<String>,
+    matched_fields: BTreeMap<String, String>,
+    count: Option<u64>,
+    tags: Vec<String>,
+}
+
+impl AlertBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn related_ids(mut self, related_ids: Vec<String>) -> Self {
+        self.related_ids = related_ids;
+        self
+    }
+
+    pub fn rule_id(mut self, rule_id: String) -> Self {
+        self.rule_id = Some(rule_id);
+        self
+    }
+
+    pub fn rule_name(mut self, rule_name: String) -> Self {
+        self.rule_name = Some(rule_name);
+        self
+    }
+
+    pub fn description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    pub fn detection_source(mut self, detection_source: DetectionSource) -> Self {
+        self.detection_source = Some(detection_source);
+        self
+    }
+
+    pub fn event_id(mut self, event_id: Option<String>) -> Self {
+        self.event_id = event_id;
+        self
+    }
+
+    pub fn matched_fields(mut self, matched_fields: BTreeMap<String, String>) -> Self {
+        self.matched_fields = matched_fields;
+        self
+    }
+
+    pub fn count(mut self, count: u64) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    pub fn tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn build(self) -> Result<Alert, String> {
+        let rule_id = self.rule_id.ok_or("rule_id is required")?;
+        let alert = Alert {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            // An alert on one event is related to that event
+            related_ids: if self.related_ids.is_empty() {
+                self.event_id.iter().cloned().collect()
+            } else {
+                self.related_ids
+            },
+            rule_name: self.rule_name.unwrap_or_else(|| rule_id.clone()),
+            rule_id,
+            description: self.description,
+            severity: self.severity.ok_or("severity is required")?,
+            detection_source: self.detection_source.ok_or("detection_source is required")?,
+            event_id: self.event_id,
+            matched_fields: self.matched_fields,
+            count: self.count,
+            tags: self.tags,
+        };
+
+        alert.validate()?;
+        Ok(alert)
+    }
+}
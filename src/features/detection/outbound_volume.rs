@@ -0,0 +1,315 @@
+use crate::features::detection::egress::{contains, parse_network};
+use crate::features::detection::models::{Alert, AlertBuilder, DetectionSource};
+use crate::features::network::NetworkConnectionInformation;
+use crate::shared::baseline::Baseline;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::traits::Severity;
+use crate::utils::load_config_section;
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+const RULE_ID: &str = "outbound_volume_anomaly";
+// Weight of the newest window in a process's usual volume
+const SMOOTHING: f64 = 0.2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutboundVolumeConfig {
+    pub enabled: bool,
+    // Destinations that are not external; what is sent to them is not tracked
+    pub internal_cidrs: Vec<String>,
+    // What a process sends to one destination is summed over windows this long
+    pub window_secs: u64,
+    // A destination stays new for this long after the process first sent data to it
+    pub new_destination_hours: u64,
+    // How many times its usual volume to a single destination a process must send
+    pub factor: f64,
+    // Below this many bytes in a window nothing is reported, however unusual
+    pub min_bytes: u64,
+    // Destinations a process has not sent to for this many days are forgotten
+    pub retention_days: u64,
+    pub severity: Severity,
+}
+
+impl Default for OutboundVolumeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            internal_cidrs: vec![
+                String::from("10.0.0.0/8"),
+                String::from("172.16.0.0/12"),
+                String::from("192.168.0.0/16"),
+                String::from("fc00::/7"),
+            ],
+            window_secs: 3600,
+            new_destination_hours: 24,
+            factor: 10.0,
+            min_bytes: 100 * 1024 * 1024,
+            retention_days: 30,
+            severity: Severity::High,
+        }
+    }
+}
+
+impl OutboundVolumeConfig {
+    pub fn load() -> Self {
+        load_config_section("outbound_volume")
+    }
+}
+
+// What the baseline store keeps per process image
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProcessVolume {
+    // Moving average of the most the process sent to a single destination in a window
+    usual_bytes: f64,
+    // Windows that went into usual_bytes
+    windows: u64,
+    // External destinations with when the process first and last sent data to them
+    destinations: HashMap<String, (DateTime<Utc>, DateTime<Utc>)>,
+}
+
+struct WindowVolume {
+    bytes: u64,
+    // Latest connection, for the process and port in the alert
+    connection: NetworkConnectionInformation,
+}
+
+struct State {
+    processes: HashMap<String, ProcessVolume>,
+    // bytes_sent of every open socket at the last collection
+    sockets: HashMap<String, u64>,
+    // False until the counters of the sockets open at startup are known, whose earlier
+    // traffic must not land in the first window
+    primed: bool,
+    window_start: DateTime<Utc>,
+    window: HashMap<(String, String), WindowVolume>,
+    reported: HashSet<(String, String)>,
+}
+
+fn megabytes(bytes: f64) -> String {
+    format!("{:.1} MB", bytes / (1024.0 * 1024.0))
+}
+
+// Basic exfiltration detection on the per-socket byte counters: a process that sends an order
+// of magnitude more than it usually sends to any one destination, to an external destination
+// it had not sent to before, raises an alert. The usual volume of each process and the
+// destinations it talks to are kept in the baseline store, so they survive restarts.
+pub struct OutboundVolumeDetector {
+    config: OutboundVolumeConfig,
+    internal: Vec<(IpAddr, u32)>,
+    state: Mutex<State>,
+    host: Arc<HostIdentityService>,
+}
+
+impl OutboundVolumeDetector {
+    const BASELINE: &'static str = "outbound_volume";
+
+    pub fn new(config: OutboundVolumeConfig) -> Self {
+        let internal = config
+            .internal_cidrs
+            .iter()
+            .filter_map(|cidr| {
+                let network = parse_network(cidr);
+                if network.is_none() {
+                    warn!("Ignoring invalid internal CIDR '{}'", cidr);
+                }
+                network
+            })
+            .collect();
+        Self {
+            internal,
+            state: Mutex::new(State {
+                processes: Baseline::global().load(Self::BASELINE).unwrap_or_default(),
+                sockets: HashMap::new(),
+                primed: false,
+                window_start: Utc::now(),
+                window: HashMap::new(),
+                reported: HashSet::new(),
+            }),
+            host: HostIdentityService::global(),
+            config,
+        }
+    }
+
+    fn external(&self, address: IpAddr) -> bool {
+        !address.is_loopback()
+            && !address.is_unspecified()
+            && !self.internal.iter().any(|network| contains(*network, address))
+    }
+
+    // Folds the window into each process's usual volume and forgets stale destinations
+    fn close_window(&self, state: &mut State, now: DateTime<Utc>) {
+        let mut largest: HashMap<&str, u64> = HashMap::new();
+        for ((image, _), volume) in &state.window {
+            let entry = largest.entry(image.as_str()).or_default();
+            *entry = (*entry).max(volume.bytes);
+        }
+        for (image, bytes) in largest {
+            let process = state.processes.entry(image.to_string()).or_default();
+            process.usual_bytes = if process.windows == 0 {
+                bytes as f64
+            } else {
+                process.usual_bytes * (1.0 - SMOOTHING) + bytes as f64 * SMOOTHING
+            };
+            process.windows += 1;
+        }
+
+        let forget_before = now - Duration::days(self.config.retention_days as i64);
+        state.processes.retain(|_, process| {
+            process.destinations.retain(|_, (_, last_sent)| *last_sent > forget_before);
+            !process.destinations.is_empty()
+        });
+        state.window.clear();
+        state.reported.clear();
+        state.window_start = now;
+    }
+
+    fn alert(&self, image: &str, destination: &str, volume: &WindowVolume, process: &ProcessVolume, now: DateTime<Utc>) -> Option<Alert> {
+        let connection = &volume.connection;
+        let usual_bytes = process.usual_bytes;
+        // A process seen for the first time has nothing to compare with but min_bytes
+        let usual = if process.windows == 0 {
+            String::from("no earlier volume was on record for it")
+        } else {
+            format!("it usually sends at most {} to one destination", megabytes(usual_bytes))
+        };
+        let mut matched_fields = BTreeMap::from([
+            (String::from("destination"), destination.to_string()),
+            (String::from("remote_port"), connection.remote_port.to_string()),
+            (String::from("bytes_sent"), volume.bytes.to_string()),
+            (String::from("usual_bytes"), (usual_bytes as u64).to_string()),
+            (String::from("window_secs"), self.config.window_secs.to_string()),
+        ]);
+        let optional = [
+            ("process_id", connection.process_id.map(|pid| pid.to_string())),
+            ("process_name", connection.process_name.clone()),
+            ("process_path", connection.process_path.clone()),
+            ("user", connection.user.clone()),
+        ];
+        for (field, value) in optional {
+            if let Some(value) = value {
+                matched_fields.insert(field.to_string(), value);
+            }
+        }
+
+        let alert = AlertBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(now)
+            .source(self.host.name())
+            .category(String::from("detection"))
+            .rule_id(String::from(RULE_ID))
+            .rule_name(String::from("Unusual outbound data volume to a new destination"))
+            .description(Some(format!(
+                "{} sent {} to {}:{}, a destination new to it, within {} seconds; {}",
+                image,
+                megabytes(volume.bytes as f64),
+                destination,
+                connection.remote_port,
+                self.config.window_secs,
+                usual
+            )))
+            .severity(self.config.severity)
+            .detection_source(DetectionSource::Network)
+            .matched_fields(matched_fields)
+            .tags(vec![String::from("exfiltration")])
+            .build();
+        match alert {
+            Ok(alert) => {
+                warn!("Outbound volume anomaly: {}", alert.description.as_deref().unwrap_or_default());
+                Some(alert)
+            }
+            Err(e) => {
+                warn!("Dropping outbound volume alert: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn evaluate(&self, connections: &[NetworkConnectionInformation]) -> Vec<Alert> {
+        let Ok(mut state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let state = &mut *state;
+        let now = Utc::now();
+        let mut changed = false;
+        if now - state.window_start >= Duration::seconds(self.config.window_secs as i64) {
+            self.close_window(state, now);
+            changed = true;
+        }
+
+        let mut sockets = HashMap::new();
+        for connection in connections {
+            let (Some(sent), Ok(remote)) = (connection.bytes_sent, connection.remote_address.parse::<IpAddr>()) else {
+                continue;
+            };
+            let socket = format!(
+                "{}|{}:{}|{}:{}",
+                connection.process_id.unwrap_or_default(),
+                connection.local_address,
+                connection.local_port,
+                connection.remote_address,
+                connection.remote_port
+            );
+            // A counter that went down belongs to a new socket on the same ports
+            let delta = match state.sockets.get(&socket) {
+                Some(previous) if sent >= *previous => sent - previous,
+                _ if state.primed => sent,
+                _ => 0,
+            };
+            sockets.insert(socket, sent);
+            if delta == 0 || connection.remote_port == 0 || !self.external(remote) {
+                continue;
+            }
+
+            let image = connection
+                .process_path
+                .clone()
+                .or_else(|| connection.process_name.clone())
+                .unwrap_or_else(|| String::from("unknown"));
+            let destination = remote.to_string();
+            let process = state.processes.entry(image.clone()).or_default();
+            let times = process.destinations.entry(destination.clone()).or_insert_with(|| {
+                changed = true;
+                (now, now)
+            });
+            times.1 = now;
+            let volume = state.window.entry((image, destination)).or_insert_with(|| WindowVolume {
+                bytes: 0,
+                connection: connection.clone(),
+            });
+            volume.bytes += delta;
+            volume.connection = connection.clone();
+        }
+        state.sockets = sockets;
+        state.primed = true;
+
+        let new_since = now - Duration::hours(self.config.new_destination_hours as i64);
+        let mut alerts = Vec::new();
+        for ((image, destination), volume) in &state.window {
+            let key = (image.clone(), destination.clone());
+            let Some(process) = state.processes.get(image) else {
+                continue;
+            };
+            let threshold = (self.config.min_bytes as f64).max(process.usual_bytes * self.config.factor);
+            let new = process
+                .destinations
+                .get(destination)
+                .is_some_and(|(first_sent, _)| *first_sent > new_since);
+            if !new || (volume.bytes as f64) < threshold || state.reported.contains(&key) {
+                continue;
+            }
+            alerts.extend(self.alert(image, destination, volume, process, now));
+            state.reported.insert(key);
+        }
+
+        if changed {
+            Baseline::global().save(Self::BASELINE, &state.processes);
+        }
+        alerts
+    }
+}
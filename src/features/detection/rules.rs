@@ -0,0 +1,249 @@
+use crate::features::detection::models::DetectionSource;
+use crate::shared::traits::Severity;
+use log::{info, warn};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+
+// One field condition. Every comparison that is set has to hold; string comparisons
+// ignore case unless case_sensitive is set. Array fields match when any element does.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FieldMatcher {
+    // Dotted path into the serialized event, e.g. "process_name" or "signature_status"
+    pub field: String,
+    pub equals: Option<String>,
+    pub contains: Option<String>,
+    pub starts_with: Option<String>,
+    pub ends_with: Option<String>,
+    pub regex: Option<String>,
+    #[serde(rename = "in")]
+    pub any_of: Vec<String>,
+    pub gt: Option<f64>,
+    pub lt: Option<f64>,
+    // true: the field must be present and non-null, false: it must be absent or null
+    pub exists: Option<bool>,
+    pub case_sensitive: bool,
+    pub negate: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Threshold {
+    pub count: u64,
+    pub window_secs: u64,
+    // Matches are counted per distinct combination of these fields
+    pub group_by: Vec<String>,
+}
+
+impl Default for Threshold {
+    fn default() -> Self {
+        Self {
+            count: 10,
+            window_secs: 60,
+            group_by: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub severity: Severity,
+    pub source: DetectionSource,
+    #[serde(rename = "match", default)]
+    pub matchers: Vec<FieldMatcher>,
+    // Groups of conditions of which at least one must hold in full, on top of `match`
+    #[serde(rename = "match_any", default)]
+    pub alternatives: Vec<Vec<FieldMatcher>>,
+    pub threshold: Option<Threshold>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RuleFile {
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+// Rules from every file, then the inline ones. A file that fails to parse is skipped as a
+// whole so a typo never loads half a rule set.
+pub fn load_rules(files: &[String], inline: &[Rule]) -> Vec<Rule> {
+    let mut rules = Vec::new();
+    for path in files {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Failed to read rule file {}: {}", path, e);
+                continue;
+            }
+        };
+        match serde_yaml::from_str::<RuleFile>(&content) {
+            Ok(file) => {
+                info!("Loaded {} rules from {}", file.rules.len(), path);
+                rules.extend(file.rules);
+            }
+            Err(e) => warn!("Failed to parse rule file {}: {}", path, e),
+        }
+    }
+    rules.extend(inline.iter().cloned());
+    rules
+}
+
+// A FieldMatcher ready to run, also used outside the rules to pick events by field
+pub struct CompiledMatcher {
+    field: String,
+    equals: Option<String>,
+    contains: Option<String>,
+    starts_with: Option<String>,
+    ends_with: Option<String>,
+    regex: Option<Regex>,
+    any_of: Vec<String>,
+    gt: Option<f64>,
+    lt: Option<f64>,
+    exists: Option<bool>,
+    case_sensitive: bool,
+    negate: bool,
+}
+
+pub struct CompiledRule {
+    pub rule: Rule,
+    matchers: Vec<CompiledMatcher>,
+    alternatives: Vec<Vec<CompiledMatcher>>,
+}
+
+// Field values as strings; arrays are flattened one level, null and missing are none
+pub fn field_values(document: &Value, path: &str) -> Vec<String> {
+    let value = path.split('.').try_fold(document, |value, key| value.get(key));
+    let scalar = |value: &Value| match value {
+        Value::Null => None,
+        Value::String(text) => Some(text.clone()),
+        other => Some(other.to_string()),
+    };
+    match value {
+        Some(Value::Array(items)) => items.iter().filter_map(scalar).collect(),
+        Some(value) => scalar(value).into_iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+impl CompiledMatcher {
+    pub fn compile(matcher: &FieldMatcher) -> Result<Self, String> {
+        let fold = |value: &Option<String>| {
+            value.as_ref().map(|value| if matcher.case_sensitive { value.clone() } else { value.to_lowercase() })
+        };
+        let regex = match &matcher.regex {
+            Some(pattern) => Some(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(!matcher.case_sensitive)
+                    .build()
+                    .map_err(|e| format!("invalid regex for {}: {}", matcher.field, e))?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            field: matcher.field.clone(),
+            equals: fold(&matcher.equals),
+            contains: fold(&matcher.contains),
+            starts_with: fold(&matcher.starts_with),
+            ends_with: fold(&matcher.ends_with),
+            regex,
+            any_of: matcher
+                .any_of
+                .iter()
+                .map(|value| if matcher.case_sensitive { value.clone() } else { value.to_lowercase() })
+                .collect(),
+            gt: matcher.gt,
+            lt: matcher.lt,
+            exists: matcher.exists,
+            case_sensitive: matcher.case_sensitive,
+            negate: matcher.negate,
+        })
+    }
+
+    fn value_matches(&self, value: &str) -> bool {
+        let folded = if self.case_sensitive { value.to_string() } else { value.to_lowercase() };
+        let number = || value.parse::<f64>().ok();
+        self.equals.as_ref().map_or(true, |expected| folded == *expected)
+            && self.contains.as_ref().map_or(true, |needle| folded.contains(needle.as_str()))
+            && self.starts_with.as_ref().map_or(true, |prefix| folded.starts_with(prefix.as_str()))
+            && self.ends_with.as_ref().map_or(true, |suffix| folded.ends_with(suffix.as_str()))
+            && self.regex.as_ref().map_or(true, |regex| regex.is_match(value))
+            && (self.any_of.is_empty() || self.any_of.contains(&folded))
+            && self.gt.map_or(true, |bound| number().is_some_and(|number| number > bound))
+            && self.lt.map_or(true, |bound| number().is_some_and(|number| number < bound))
+    }
+
+    pub fn matches(&self, document: &Value) -> bool {
+        self.evaluate(document).is_some()
+    }
+
+    // The first matching value; negated and exists: false conditions match with an empty value
+    fn evaluate(&self, document: &Value) -> Option<String> {
+        let values = field_values(document, &self.field);
+        let matched = match self.exists {
+            Some(false) => values.is_empty().then(String::new),
+            Some(true) if values.is_empty() => None,
+            _ => values.into_iter().find(|value| self.value_matches(value)),
+        };
+        match (matched, self.negate) {
+            (Some(value), false) => Some(value),
+            (None, true) => Some(String::new()),
+            _ => None,
+        }
+    }
+}
+
+impl CompiledRule {
+    pub fn compile(rule: Rule) -> Result<Self, String> {
+        if rule.matchers.is_empty() && rule.alternatives.iter().all(Vec::is_empty) {
+            return Err(format!("rule {} has no match conditions", rule.id));
+        }
+        let compile_all = |matchers: &[FieldMatcher]| {
+            matchers
+                .iter()
+                .map(CompiledMatcher::compile)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("rule {}: {}", rule.id, e))
+        };
+        let matchers = compile_all(&rule.matchers)?;
+        let alternatives = rule
+            .alternatives
+            .iter()
+            .filter(|group| !group.is_empty())
+            .map(|group| compile_all(group))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { rule, matchers, alternatives })
+    }
+
+    fn evaluate_all(matchers: &[CompiledMatcher], document: &Value) -> Option<Vec<(String, String)>> {
+        matchers
+            .iter()
+            .map(|matcher| matcher.evaluate(document).map(|value| (matcher.field.clone(), value)))
+            .collect()
+    }
+
+    // (field, matched value) per condition when every condition holds, followed by
+    // those of the first alternative group that holds
+    pub fn evaluate(&self, document: &Value) -> Option<Vec<(String, String)>> {
+        let mut matched = Self::evaluate_all(&self.matchers, document)?;
+        if !self.alternatives.is_empty() {
+            let alternative = self
+                .alternatives
+                .iter()
+                .find_map(|group| Self::evaluate_all(group, document))?;
+            matched.extend(alternative);
+        }
+        Some(matched)
+    }
+}
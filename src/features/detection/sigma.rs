@@ -0,0 +1,702 @@
+use crate::features::detection::models::DetectionSource;
+use crate::features::detection::rules::{FieldMatcher, Rule, Threshold};
+use crate::shared::traits::Severity;
+use log::{debug, info, warn};
+use regex::Regex;
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// A condition that flattens to more alternatives than this is rejected rather than
+// evaluated slowly on every event
+const MAX_ALTERNATIVES: usize = 64;
+
+// Sigma field name, agent field, and whether the agent only has the file name of a path
+const PROCESS_FIELDS: &[(&str, &str, bool)] = &[
+    ("Image", "name", true),
+    ("CommandLine", "command", false),
+    ("User", "user", false),
+    ("ProcessId", "pid", false),
+];
+
+const FILE_FIELDS: &[(&str, &str, bool)] = &[
+    ("TargetFilename", "path", false),
+    ("Image", "process_name", true),
+    ("ProcessId", "process_id", false),
+    ("User", "user", false),
+];
+
+const REGISTRY_FIELDS: &[(&str, &str, bool)] = &[
+    ("TargetObject", "key_path", false),
+    ("Details", "new_data", false),
+    ("Image", "process_name", true),
+    ("ProcessId", "process_id", false),
+    ("User", "user", false),
+];
+
+const NETWORK_FIELDS: &[(&str, &str, bool)] = &[
+    ("DestinationIp", "remote_address", false),
+    ("DestinationPort", "remote_port", false),
+    ("SourceIp", "local_address", false),
+    ("SourcePort", "local_port", false),
+    ("Protocol", "protocol", false),
+    ("Image", "process_path", false),
+    ("ProcessId", "process_id", false),
+    ("User", "user", false),
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct LogSource {
+    category: Option<String>,
+    product: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SigmaRule {
+    title: String,
+    id: Option<String>,
+    status: Option<String>,
+    description: Option<String>,
+    level: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    logsource: LogSource,
+    detection: Mapping,
+}
+
+// Boolean condition over field matchers, before it is flattened into rule alternatives
+#[derive(Debug, Clone)]
+enum Expr {
+    Leaf(FieldMatcher),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+}
+
+// The agent event stream a logsource maps to, and the event type implied by its category
+fn source_for(logsource: &LogSource) -> Result<(DetectionSource, Option<&'static str>), String> {
+    let category = logsource.category.as_deref().unwrap_or_default();
+    let mapped = match category {
+        "process_creation" => (DetectionSource::Process, None),
+        "file_event" => (DetectionSource::File, Some("Created")),
+        "file_change" => (DetectionSource::File, Some("Modified")),
+        "file_delete" => (DetectionSource::File, Some("Deleted")),
+        "file_rename" => (DetectionSource::File, Some("Renamed")),
+        "file_access" => (DetectionSource::File, Some("Accessed")),
+        "registry_event" => (DetectionSource::Registry, None),
+        "registry_add" => (DetectionSource::Registry, Some("Created")),
+        "registry_set" => (DetectionSource::Registry, Some("Modified")),
+        "registry_delete" => (DetectionSource::Registry, Some("Deleted")),
+        "network_connection" => (DetectionSource::Network, None),
+        other => return Err(format!("unsupported logsource category '{}'", other)),
+    };
+    Ok(mapped)
+}
+
+fn fields_for(source: DetectionSource) -> &'static [(&'static str, &'static str, bool)] {
+    match source {
+        DetectionSource::Process => PROCESS_FIELDS,
+        DetectionSource::File => FILE_FIELDS,
+        DetectionSource::Registry => REGISTRY_FIELDS,
+        DetectionSource::Network => NETWORK_FIELDS,
+    }
+}
+
+fn map_field(source: DetectionSource, field: &str) -> Result<(&'static str, bool), String> {
+    fields_for(source)
+        .iter()
+        .find(|(sigma, _, _)| sigma.eq_ignore_ascii_case(field))
+        .map(|(_, agent, basename)| (*agent, *basename))
+        .ok_or_else(|| format!("field '{}' has no equivalent in {:?} events", field, source))
+}
+
+fn severity_for(level: Option<&str>) -> Severity {
+    match level.unwrap_or_default() {
+        "critical" => Severity::Critical,
+        "high" => Severity::High,
+        "medium" => Severity::Medium,
+        _ => Severity::Low,
+    }
+}
+
+fn scalar(value: &Value) -> Result<Option<String>, String> {
+    match value {
+        Value::Null => Ok(None),
+        Value::String(text) => Ok(Some(text.clone())),
+        Value::Number(number) => Ok(Some(number.to_string())),
+        Value::Bool(flag) => Ok(Some(flag.to_string())),
+        other => Err(format!("unsupported value {:?}", other)),
+    }
+}
+
+// Sigma wildcards (* and ?, escaped with a backslash) as a regex fragment
+fn wildcard_pattern(value: &str) -> String {
+    let mut pattern = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some('*') | Some('?') | Some('\\')) => {
+                pattern.push_str(&regex::escape(&chars.next().unwrap_or_default().to_string()));
+            }
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            other => pattern.push_str(&regex::escape(&other.to_string())),
+        }
+    }
+    pattern
+}
+
+// A value without wildcards as the literal text it stands for
+fn unescape_wildcards(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some('*') | Some('?') | Some('\\')) => text.push(chars.next().unwrap_or_default()),
+            other => text.push(other),
+        }
+    }
+    text
+}
+
+fn has_wildcards(value: &str) -> bool {
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '*' | '?' if !escaped => return true,
+            _ => escaped = false,
+        }
+    }
+    false
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Equals,
+    Contains,
+    StartsWith,
+    EndsWith,
+    Regex,
+}
+
+// A path value for a field where the agent only records the file name. Only comparisons
+// anchored at the end of the path survive, and they become a file name comparison.
+fn basename_value(value: &str, comparison: Comparison) -> Result<(String, Comparison), String> {
+    let Some(position) = value.rfind(['\\', '/']) else {
+        return Ok((value.to_string(), comparison));
+    };
+    match comparison {
+        Comparison::Equals | Comparison::EndsWith => Ok((value[position + 1..].to_string(), Comparison::Equals)),
+        _ => Err(format!("path comparison on '{}' needs the full image path", value)),
+    }
+}
+
+// One field condition for a set of values; several values of the same field are alternatives
+// unless the `all` modifier asks for every one of them
+fn field_condition(source: DetectionSource, key: &str, value: &Value) -> Result<Expr, String> {
+    let mut parts = key.split('|');
+    let (field, basename) = map_field(source, parts.next().unwrap_or_default())?;
+
+    let mut comparison = Comparison::Equals;
+    let mut all = false;
+    let mut case_sensitive = false;
+    let mut numeric: Option<&str> = None;
+    for modifier in parts {
+        match modifier {
+            "contains" => comparison = Comparison::Contains,
+            "startswith" => comparison = Comparison::StartsWith,
+            "endswith" => comparison = Comparison::EndsWith,
+            "re" => {
+                comparison = Comparison::Regex;
+                case_sensitive = true;
+            }
+            "i" => case_sensitive = false,
+            "all" => all = true,
+            "gt" | "gte" | "lt" | "lte" => numeric = Some(modifier),
+            other => return Err(format!("unsupported modifier '{}' on {}", other, key)),
+        }
+    }
+
+    let values = match value {
+        Value::Sequence(items) => items.iter().map(scalar).collect::<Result<Vec<_>, _>>()?,
+        other => vec![scalar(other)?],
+    };
+    if values.is_empty() {
+        return Err(format!("no values for {}", key));
+    }
+
+    // Several alternative values share one regex so they stay a single condition
+    let combine = values.len() > 1 && !all;
+    let mut conditions = Vec::new();
+    let mut patterns = Vec::new();
+    for value in values {
+        let Some(value) = value else {
+            conditions.push(Expr::Leaf(FieldMatcher {
+                field: field.to_string(),
+                exists: Some(false),
+                ..FieldMatcher::default()
+            }));
+            continue;
+        };
+
+        if let Some(operator) = numeric {
+            let bound: f64 = value.parse().map_err(|_| format!("{} needs a number, got '{}'", key, value))?;
+            let leaf = |gt: Option<f64>, lt: Option<f64>| {
+                Expr::Leaf(FieldMatcher {
+                    field: field.to_string(),
+                    gt,
+                    lt,
+                    ..FieldMatcher::default()
+                })
+            };
+            let equal = Expr::Leaf(FieldMatcher {
+                field: field.to_string(),
+                equals: Some(value.clone()),
+                ..FieldMatcher::default()
+            });
+            conditions.push(match operator {
+                "gt" => leaf(Some(bound), None),
+                "lt" => leaf(None, Some(bound)),
+                "gte" => Expr::Or(vec![leaf(Some(bound), None), equal]),
+                _ => Expr::Or(vec![leaf(None, Some(bound)), equal]),
+            });
+            continue;
+        }
+
+        let (value, comparison) = if basename && comparison != Comparison::Regex {
+            basename_value(&value, comparison)?
+        } else {
+            (value, comparison)
+        };
+
+        if comparison == Comparison::Regex || combine || has_wildcards(&value) {
+            let pattern = match comparison {
+                Comparison::Regex => value,
+                Comparison::Equals => format!("^{}$", wildcard_pattern(&value)),
+                Comparison::Contains => wildcard_pattern(&value),
+                Comparison::StartsWith => format!("^{}", wildcard_pattern(&value)),
+                Comparison::EndsWith => format!("{}$", wildcard_pattern(&value)),
+            };
+            Regex::new(&pattern).map_err(|e| format!("invalid pattern for {}: {}", key, e))?;
+            patterns.push(pattern);
+            continue;
+        }
+
+        let value = unescape_wildcards(&value);
+        let mut matcher = FieldMatcher {
+            field: field.to_string(),
+            case_sensitive,
+            ..FieldMatcher::default()
+        };
+        match comparison {
+            Comparison::Contains => matcher.contains = Some(value),
+            Comparison::StartsWith => matcher.starts_with = Some(value),
+            Comparison::EndsWith => matcher.ends_with = Some(value),
+            _ => matcher.equals = Some(value),
+        }
+        conditions.push(Expr::Leaf(matcher));
+    }
+
+    if !patterns.is_empty() {
+        let groups: Vec<Vec<String>> = if all {
+            patterns.into_iter().map(|pattern| vec![pattern]).collect()
+        } else {
+            vec![patterns]
+        };
+        for group in groups {
+            conditions.push(Expr::Leaf(FieldMatcher {
+                field: field.to_string(),
+                regex: Some(format!("(?:{})", group.join("|"))),
+                case_sensitive,
+                ..FieldMatcher::default()
+            }));
+        }
+    }
+
+    Ok(if conditions.len() == 1 {
+        conditions.remove(0)
+    } else if all {
+        Expr::And(conditions)
+    } else {
+        Expr::Or(conditions)
+    })
+}
+
+// A named selection: a map is a conjunction of field conditions, a list of maps is a disjunction
+fn selection(source: DetectionSource, name: &str, value: &Value) -> Result<Expr, String> {
+    match value {
+        Value::Mapping(fields) => {
+            let conditions = fields
+                .iter()
+                .map(|(key, value)| {
+                    let key = key.as_str().ok_or_else(|| format!("selection {} has a non-string key", name))?;
+                    field_condition(source, key, value)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Expr::And(conditions))
+        }
+        Value::Sequence(items) if items.iter().all(Value::is_mapping) => {
+            let alternatives = items
+                .iter()
+                .map(|item| selection(source, name, item))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Expr::Or(alternatives))
+        }
+        _ => Err(format!("selection {} uses keyword search, which is not supported", name)),
+    }
+}
+
+fn tokenize(condition: &str) -> Vec<String> {
+    condition
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+// Recursive descent over `not` > `and` > `or`, with `1 of x*` / `all of them` quantifiers
+struct ConditionParser<'a> {
+    tokens: Vec<String>,
+    position: usize,
+    selections: &'a BTreeMap<String, Expr>,
+}
+
+impl ConditionParser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut terms = vec![self.and()?];
+        while self.peek().is_some_and(|token| token.eq_ignore_ascii_case("or")) {
+            self.next();
+            terms.push(self.and()?);
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Expr::Or(terms) })
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut factors = vec![self.not()?];
+        while self.peek().is_some_and(|token| token.eq_ignore_ascii_case("and")) {
+            self.next();
+            factors.push(self.not()?);
+        }
+        Ok(if factors.len() == 1 { factors.remove(0) } else { Expr::And(factors) })
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.peek().is_some_and(|token| token.eq_ignore_ascii_case("not")) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self.next().ok_or("condition ends unexpectedly")?;
+        if token == "(" {
+            let expr = self.or()?;
+            if self.next().as_deref() != Some(")") {
+                return Err(String::from("unbalanced parentheses in condition"));
+            }
+            return Ok(expr);
+        }
+        if self.peek() == Some("of") {
+            self.next();
+            let target = self.next().ok_or("quantifier without a target")?;
+            let matched = self.matching(&target)?;
+            return match token.to_lowercase().as_str() {
+                "1" | "any" => Ok(Expr::Or(matched)),
+                "all" => Ok(Expr::And(matched)),
+                other => Err(format!("unsupported quantifier '{} of'", other)),
+            };
+        }
+        self.selections
+            .get(&token)
+            .cloned()
+            .ok_or_else(|| format!("condition references unknown selection '{}'", token))
+    }
+
+    // Selections named by a quantifier target: `them` or a name with * wildcards
+    fn matching(&self, target: &str) -> Result<Vec<Expr>, String> {
+        let pattern = if target == "them" {
+            None
+        } else {
+            Some(Regex::new(&format!("^{}$", wildcard_pattern(target))).map_err(|e| e.to_string())?)
+        };
+        let matched: Vec<Expr> = self
+            .selections
+            .iter()
+            .filter(|(name, _)| match &pattern {
+                Some(pattern) => pattern.is_match(name),
+                None => !name.starts_with('_'),
+            })
+            .map(|(_, expr)| expr.clone())
+            .collect();
+        if matched.is_empty() {
+            return Err(format!("'{}' matches no selection", target));
+        }
+        Ok(matched)
+    }
+}
+
+fn parse_condition(condition: &str, selections: &BTreeMap<String, Expr>) -> Result<Expr, String> {
+    let mut parser = ConditionParser {
+        tokens: tokenize(condition),
+        position: 0,
+        selections,
+    };
+    let expr = parser.or()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("unexpected '{}' in condition", token));
+    }
+    Ok(expr)
+}
+
+// Disjunctive normal form: alternatives of conditions that must all hold. Negation is
+// pushed down to the field conditions, which the rule engine can negate on their own.
+fn flatten(expr: &Expr, negated: bool) -> Result<Vec<Vec<FieldMatcher>>, String> {
+    let conjunction = |children: &[Expr]| -> Result<Vec<Vec<FieldMatcher>>, String> {
+        let mut product = vec![Vec::new()];
+        for child in children {
+            let alternatives = flatten(child, negated)?;
+            let mut next = Vec::new();
+            for prefix in &product {
+                for alternative in &alternatives {
+                    next.push([prefix.clone(), alternative.clone()].concat());
+                }
+            }
+            if next.len() > MAX_ALTERNATIVES {
+                return Err(String::from("condition is too complex"));
+            }
+            product = next;
+        }
+        Ok(product)
+    };
+    let disjunction = |children: &[Expr]| -> Result<Vec<Vec<FieldMatcher>>, String> {
+        let mut alternatives = Vec::new();
+        for child in children {
+            alternatives.extend(flatten(child, negated)?);
+        }
+        if alternatives.len() > MAX_ALTERNATIVES {
+            return Err(String::from("condition is too complex"));
+        }
+        Ok(alternatives)
+    };
+
+    match (expr, negated) {
+        (Expr::Leaf(matcher), _) => {
+            let mut matcher = matcher.clone();
+            matcher.negate ^= negated;
+            Ok(vec![vec![matcher]])
+        }
+        (Expr::And(children), false) | (Expr::Or(children), true) => conjunction(children),
+        (Expr::Or(children), false) | (Expr::And(children), true) => disjunction(children),
+        (Expr::Not(inner), _) => flatten(inner, !negated),
+    }
+}
+
+// Sigma timeframe such as 30s, 5m, 1h or 1d
+fn timeframe_secs(timeframe: &str) -> Result<u64, String> {
+    let timeframe = timeframe.trim();
+    let invalid = || format!("invalid timeframe '{}'", timeframe);
+    // The unit is the last character, which need not be a single byte
+    let (split, _) = timeframe.char_indices().last().ok_or_else(invalid)?;
+    let (number, unit) = timeframe.split_at(split);
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err(invalid()),
+    };
+    number.checked_mul(scale).ok_or_else(invalid)
+}
+
+// `count() [by field, ...] > N` aggregations become thresholds
+fn aggregation(
+    source: DetectionSource,
+    expression: &str,
+    timeframe: Option<&str>,
+) -> Result<Threshold, String> {
+    let pattern = Regex::new(r"^count\(\s*\)\s*(?:by\s+([\w.,\s]+?))?\s*(>=|>)\s*(\d+)$").map_err(|e| e.to_string())?;
+    let captures = pattern
+        .captures(expression.trim())
+        .ok_or_else(|| format!("unsupported aggregation '{}'", expression.trim()))?;
+    let count: u64 = captures[3].parse().map_err(|_| String::from("invalid aggregation count"))?;
+    let group_by = match captures.get(1) {
+        Some(fields) => fields
+            .as_str()
+            .split(',')
+            .map(|field| map_field(source, field.trim()).map(|(field, _)| field.to_string()))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+    let count = match &captures[2] {
+        ">" => count.checked_add(1).ok_or("invalid aggregation count")?,
+        _ => count,
+    };
+    Ok(Threshold {
+        count,
+        window_secs: timeframe.map(timeframe_secs).transpose()?.unwrap_or(Threshold::default().window_secs),
+        group_by,
+    })
+}
+
+fn translate(sigma: SigmaRule) -> Result<Rule, String> {
+    let (source, event_type) = source_for(&sigma.logsource)?;
+
+    let mut detection = sigma.detection;
+    let condition = detection.remove("condition").ok_or("detection has no condition")?;
+    let timeframe = detection.remove("timeframe");
+    let timeframe = timeframe.as_ref().and_then(Value::as_str);
+
+    let mut selections = BTreeMap::new();
+    for (name, value) in &detection {
+        let name = name.as_str().ok_or("selection with a non-string name")?;
+        selections.insert(name.to_string(), selection(source, name, value)?);
+    }
+
+    let (condition, threshold) = match condition {
+        Value::String(condition) => match condition.split_once('|') {
+            Some((expression, aggregate)) => (
+                parse_condition(expression, &selections)?,
+                Some(aggregation(source, aggregate, timeframe)?),
+            ),
+            None => (parse_condition(&condition, &selections)?, None),
+        },
+        // A list of conditions means any of them
+        Value::Sequence(conditions) => {
+            let conditions = conditions
+                .iter()
+                .map(|condition| {
+                    let condition = condition.as_str().ok_or("condition is not a string")?;
+                    if condition.contains('|') {
+                        return Err(String::from("aggregations in a condition list are not supported"));
+                    }
+                    parse_condition(condition, &selections)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            (Expr::Or(conditions), None)
+        }
+        _ => return Err(String::from("condition is not a string")),
+    };
+
+    let mut alternatives = flatten(&condition, false)?;
+    let mut matchers = Vec::new();
+    if let Some(event_type) = event_type {
+        matchers.push(FieldMatcher {
+            field: String::from("event_type"),
+            equals: Some(event_type.to_string()),
+            ..FieldMatcher::default()
+        });
+    }
+    if alternatives.len() == 1 {
+        matchers.extend(alternatives.remove(0));
+    }
+
+    Ok(Rule {
+        id: sigma.id.unwrap_or_else(|| sigma.title.clone()),
+        name: Some(sigma.title),
+        description: sigma.description,
+        severity: severity_for(sigma.level.as_deref()),
+        source,
+        matchers,
+        alternatives,
+        threshold,
+        tags: sigma.tags,
+        enabled: !matches!(sigma.status.as_deref(), Some("deprecated") | Some("unsupported")),
+    })
+}
+
+// Sigma rules written for another operating system never see matching events here
+fn for_this_host(logsource: &LogSource) -> bool {
+    match logsource.product.as_deref() {
+        Some("windows") => cfg!(windows),
+        Some("linux") => cfg!(target_os = "linux"),
+        Some("macos") => cfg!(target_os = "macos"),
+        _ => true,
+    }
+}
+
+fn load_file(path: &Path, rules: &mut Vec<Rule>) {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            warn!("Failed to read Sigma rule {}: {}", path.display(), e);
+            return;
+        }
+    };
+    // A file may hold several rules as separate YAML documents
+    for document in serde_yaml::Deserializer::from_str(&content) {
+        let sigma = match SigmaRule::deserialize(document) {
+            Ok(sigma) => sigma,
+            Err(e) => {
+                warn!("Skipping Sigma rule in {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if !for_this_host(&sigma.logsource) {
+            debug!("Skipping Sigma rule '{}' for another platform", sigma.title);
+            continue;
+        }
+        let title = sigma.title.clone();
+        match translate(sigma) {
+            Ok(rule) => rules.push(rule),
+            Err(e) => warn!("Skipping Sigma rule '{}' in {}: {}", title, path.display(), e),
+        }
+    }
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read Sigma rule directory {}: {}", directory.display(), e);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else if path.extension().is_some_and(|extension| extension == "yml" || extension == "yaml") {
+            files.push(path);
+        }
+    }
+}
+
+// Translates every Sigma rule under the directories, recursively. Rules using logsources,
+// fields, modifiers or conditions outside the supported subset are skipped with a warning.
+pub fn load_sigma_rules(directories: &[String]) -> Vec<Rule> {
+    let mut rules = Vec::new();
+    for directory in directories {
+        let directory = Path::new(directory);
+        if !directory.exists() {
+            debug!("Sigma rule directory {} does not exist", directory.display());
+            continue;
+        }
+        let mut files = Vec::new();
+        collect_files(directory, &mut files);
+        files.sort();
+        let before = rules.len();
+        for file in &files {
+            load_file(file, &mut rules);
+        }
+        info!("Loaded {} Sigma rules from {}", rules.len() - before, directory.display());
+    }
+    rules
+}
@@ -0,0 +1,458 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector, Severity};
+use crate::shared::baseline::Baseline;
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::user_resolver::UserResolver;
+use crate::features::directory::models::{DirectoryChange, DirectoryChangeEvent, DirectoryChangeEventBuilder};
+use crate::features::directory::parsers::{
+    common_name, generalized_time, parse_generalized_time, parse_sddl_dacl, replication_rights, DirectoryEntry,
+};
+use crate::utils::load_config_section;
+use chrono::{Duration as ChronoDuration, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DirectoryConfig {
+    pub enabled: bool,
+    // Queries read the whole directory partition they cover, so they are spaced out
+    pub query_interval_secs: u64,
+    // How far back account creation is looked at; keep it longer than the query interval
+    pub lookback_hours: u64,
+    pub privileged_accounts: bool,
+    pub group_policies: bool,
+    pub replication_rights: bool,
+    // Groups whose new members count as privileged accounts, by name. Accounts nested in a
+    // protected group are caught by adminCount instead.
+    pub privileged_groups: Vec<String>,
+    // Trustees besides the built-in ones that are expected to replicate the domain, by SID or
+    // account name, e.g. the Azure AD Connect account
+    pub allowed_replication_trustees: Vec<String>,
+    // Directory database of a Samba domain controller
+    pub samba_database: String,
+}
+
+impl Default for DirectoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            query_interval_secs: 900,
+            lookback_hours: 24,
+            privileged_accounts: true,
+            group_policies: true,
+            replication_rights: true,
+            privileged_groups: [
+                "Domain Admins",
+                "Enterprise Admins",
+                "Schema Admins",
+                "Administrators",
+                "Account Operators",
+                "Backup Operators",
+                "Server Operators",
+                "Print Operators",
+                "DnsAdmins",
+                "Group Policy Creator Owners",
+                "Key Admins",
+                "Enterprise Key Admins",
+            ]
+            .iter()
+            .map(|group| group.to_string())
+            .collect(),
+            allowed_replication_trustees: Vec::new(),
+            samba_database: String::from("/var/lib/samba/private/sam.ldb"),
+        }
+    }
+}
+
+impl DirectoryConfig {
+    pub fn load() -> Self {
+        load_config_section("directory")
+    }
+
+    // Settings that keep queries from running or changes from being seen, for the configuration check
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.enabled {
+            return problems;
+        }
+        if self.query_interval_secs == 0 {
+            problems.push(String::from("directory.query_interval_secs must be greater than 0"));
+        }
+        if self.lookback_hours.saturating_mul(3600) < self.query_interval_secs {
+            problems.push(format!(
+                "directory.lookback_hours ({}) is shorter than query_interval_secs ({}s), accounts created between queries are missed",
+                self.lookback_hours, self.query_interval_secs
+            ));
+        }
+        if self.privileged_accounts && self.privileged_groups.is_empty() {
+            problems.push(String::from(
+                "directory.privileged_groups is empty, only accounts with adminCount set are reported",
+            ));
+        }
+        problems
+    }
+}
+
+#[cfg(windows)]
+use crate::features::directory::win32::{domain_root, search};
+#[cfg(not(windows))]
+use crate::features::directory::samba::{domain_root, search};
+
+// The Default Domain Policy and Default Domain Controllers Policy apply to every account and
+// every domain controller
+const DEFAULT_GPOS: [&str; 2] = [
+    "{31b2f340-016d-11d2-945f-00c04fb984f9}",
+    "{6ac1786c-016f-11d2-945f-00c04fb984f9}",
+];
+
+// SDDL aliases of the trustees Windows grants replication rights to: Administrators, SYSTEM,
+// Enterprise Domain Controllers, Domain Admins, Enterprise Admins, Domain Controllers and
+// Enterprise Read-only Domain Controllers
+const EXPECTED_ALIASES: [&str; 7] = ["BA", "SY", "ED", "DA", "EA", "DD", "RO"];
+const EXPECTED_SIDS: [&str; 3] = ["S-1-5-32-544", "S-1-5-18", "S-1-5-9"];
+// Relative ids of Domain Admins, Domain Controllers, Enterprise Admins, Enterprise Read-only
+// Domain Controllers and Read-only Domain Controllers, for descriptors written with full SIDs
+const EXPECTED_RIDS: [&str; 5] = ["512", "516", "519", "498", "521"];
+
+const PRIVILEGED_ACCOUNTS_BASELINE: &str = "directory_privileged_accounts";
+const GROUP_POLICIES_BASELINE: &str = "directory_group_policies";
+const REPLICATION_RIGHTS_BASELINE: &str = "directory_replication_rights";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct GroupPolicy {
+    distinguished_name: String,
+    display_name: Option<String>,
+    when_created: Option<String>,
+    when_changed: Option<String>,
+    version: Option<u64>,
+}
+
+// Lightweight queries a domain controller runs against its own directory: accounts created
+// recently that landed in a privileged group, group policy objects created, edited or
+// removed, and trustees outside the built-in ones holding the rights to replicate secrets
+// from the domain (DCSync). Windows domain controllers are searched through ADSI, Samba ones
+// by reading their database with ldbsearch.
+pub struct DirectoryCollector {
+    config: DirectoryConfig,
+    host: Arc<HostIdentityService>,
+    // Accounts already reported, among those created within the lookback
+    reported_accounts: HashSet<String>,
+    // Group policy objects of the previous query by GUID; None until the first query
+    group_policies: Option<HashMap<String, GroupPolicy>>,
+    // Replication rights of the unexpected trustees of the previous query
+    replication: HashMap<String, BTreeSet<String>>,
+    last_query: Option<Instant>,
+}
+
+impl DirectoryCollector {
+    pub fn new() -> Self {
+        Self::with_config(DirectoryConfig::load())
+    }
+
+    pub fn with_config(config: DirectoryConfig) -> Self {
+        let baseline = Baseline::global();
+        Self {
+            config,
+            host: HostIdentityService::global(),
+            reported_accounts: baseline.load(PRIVILEGED_ACCOUNTS_BASELINE).unwrap_or_default(),
+            group_policies: baseline.load(GROUP_POLICIES_BASELINE),
+            replication: baseline.load(REPLICATION_RIGHTS_BASELINE).unwrap_or_default(),
+            last_query: None,
+        }
+    }
+
+    fn event(&self, change: DirectoryChange, distinguished_name: &str) -> DirectoryChangeEventBuilder {
+        DirectoryChangeEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("directory"))
+            .change(change)
+            .distinguished_name(distinguished_name.to_string())
+    }
+
+    fn privileged_groups(&self, entry: &DirectoryEntry) -> Vec<String> {
+        entry
+            .values("memberof")
+            .iter()
+            .map(|group| common_name(group))
+            .filter(|group| self.config.privileged_groups.iter().any(|name| name.eq_ignore_ascii_case(group)))
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn privileged_account_events(&mut self) -> Result<Vec<DirectoryChangeEvent>, CollectionError> {
+        let since = Utc::now() - ChronoDuration::hours(self.config.lookback_hours as i64);
+        let filter = format!("(&(objectCategory=person)(objectClass=user)(whenCreated>={}))", generalized_time(since));
+        let accounts = search(
+            &self.config,
+            &filter,
+            &["distinguishedName", "sAMAccountName", "whenCreated", "adminCount", "memberOf", "objectSid"],
+        )?;
+
+        let mut events = Vec::new();
+        let mut recent = HashSet::new();
+        for account in &accounts {
+            let Some(dn) = account.first("distinguishedname") else {
+                continue;
+            };
+            recent.insert(dn.to_string());
+            if self.reported_accounts.contains(dn) {
+                continue;
+            }
+            let groups = self.privileged_groups(account);
+            // adminCount is set by SDProp on members of protected groups, nested ones included
+            if groups.is_empty() && account.first("admincount") != Some("1") {
+                continue;
+            }
+            events.push(
+                self.event(DirectoryChange::PrivilegedAccountCreated, dn)
+                    .name(account.first("samaccountname").map(str::to_string))
+                    .changed_at(account.first("whencreated").and_then(parse_generalized_time))
+                    .groups(groups)
+                    .trustee_sid(account.first("objectsid").map(str::to_string))
+                    .severity(Severity::High)
+                    .build()
+                    .map_err(CollectionError::Parse)?,
+            );
+            self.reported_accounts.insert(dn.to_string());
+        }
+        // Accounts older than the lookback are not returned again
+        self.reported_accounts.retain(|dn| recent.contains(dn));
+        Baseline::global().save(PRIVILEGED_ACCOUNTS_BASELINE, &self.reported_accounts);
+        Ok(events)
+    }
+
+    fn group_policy_events(&mut self) -> Result<Vec<DirectoryChangeEvent>, CollectionError> {
+        let entries = search(
+            &self.config,
+            "(objectClass=groupPolicyContainer)",
+            &["distinguishedName", "name", "displayName", "whenCreated", "whenChanged", "versionNumber"],
+        )?;
+        let current: HashMap<String, GroupPolicy> = entries
+            .iter()
+            .filter_map(|entry| {
+                let distinguished_name = entry.first("distinguishedname")?.to_string();
+                let guid = entry.first("name").unwrap_or(common_name(&distinguished_name)).to_lowercase();
+                Some((
+                    guid,
+                    GroupPolicy {
+                        distinguished_name,
+                        display_name: entry.first("displayname").map(str::to_string),
+                        when_created: entry.first("whencreated").map(str::to_string),
+                        when_changed: entry.first("whenchanged").map(str::to_string),
+                        version: entry.first("versionnumber").and_then(|version| version.parse().ok()),
+                    },
+                ))
+            })
+            .collect();
+
+        let mut events = Vec::new();
+        let event = |change: DirectoryChange, guid: &str, policy: &GroupPolicy| {
+            let severity = match change {
+                DirectoryChange::GpoModified if DEFAULT_GPOS.contains(&guid) => Severity::High,
+                _ => Severity::Medium,
+            };
+            self.event(change, &policy.distinguished_name)
+                .name(policy.display_name.clone())
+                .changed_at(policy.when_changed.as_deref().and_then(parse_generalized_time))
+                .version(policy.version)
+                .severity(severity)
+                .build()
+                .map_err(CollectionError::Parse)
+        };
+        match &self.group_policies {
+            Some(previous) => {
+                for (guid, policy) in &current {
+                    match previous.get(guid) {
+                        None => events.push(event(DirectoryChange::GpoCreated, guid, policy)?),
+                        Some(known) if known.when_changed != policy.when_changed || known.version != policy.version => {
+                            events.push(event(DirectoryChange::GpoModified, guid, policy)?)
+                        }
+                        Some(_) => {}
+                    }
+                }
+                for (guid, policy) in previous.iter().filter(|(guid, _)| !current.contains_key(*guid)) {
+                    events.push(event(DirectoryChange::GpoDeleted, guid, policy)?);
+                }
+            }
+            // Nothing to compare with yet: policies changed within the lookback are reported
+            None => {
+                let since = Utc::now() - ChronoDuration::hours(self.config.lookback_hours as i64);
+                let recent = |time: &Option<String>| {
+                    time.as_deref().and_then(parse_generalized_time).is_some_and(|time| time >= since)
+                };
+                for (guid, policy) in &current {
+                    if recent(&policy.when_created) {
+                        events.push(event(DirectoryChange::GpoCreated, guid, policy)?);
+                    } else if recent(&policy.when_changed) {
+                        events.push(event(DirectoryChange::GpoModified, guid, policy)?);
+                    }
+                }
+            }
+        }
+
+        Baseline::global().save(GROUP_POLICIES_BASELINE, &current);
+        self.group_policies = Some(current);
+        Ok(events)
+    }
+
+    fn expected_trustee(&self, trustee: &str, name: &str) -> bool {
+        if EXPECTED_ALIASES.contains(&trustee) || EXPECTED_SIDS.contains(&trustee) {
+            return true;
+        }
+        if trustee.starts_with("S-1-5-21-") && trustee.rsplit('-').next().is_some_and(|rid| EXPECTED_RIDS.contains(&rid)) {
+            return true;
+        }
+        self.config
+            .allowed_replication_trustees
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(trustee) || allowed.eq_ignore_ascii_case(name))
+    }
+
+    fn replication_events(&mut self) -> Result<Vec<DirectoryChangeEvent>, CollectionError> {
+        let domain = domain_root(&self.config)?;
+        let domain_name = domain.first("distinguishedname").unwrap_or_default().to_string();
+        let sddl = domain
+            .first("ntsecuritydescriptor")
+            .ok_or_else(|| CollectionError::Parse(format!("no security descriptor read for {}", domain_name)))?;
+
+        let resolver = UserResolver::global();
+        // SDDL aliases such as AU are left as they are
+        let display = |trustee: &str| {
+            if trustee.starts_with("S-") {
+                resolver.display_id(trustee)
+            } else {
+                trustee.to_string()
+            }
+        };
+        let current: HashMap<String, BTreeSet<String>> = replication_rights(&parse_sddl_dacl(sddl))
+            .into_iter()
+            .filter(|(trustee, _)| !self.expected_trustee(trustee, &display(trustee)))
+            .collect();
+
+        let mut events = Vec::new();
+        let event = |change: DirectoryChange, trustee: &str, rights: Vec<String>, severity: Severity| {
+            self.event(change, &domain_name)
+                .name(Some(display(trustee)))
+                .trustee_sid(Some(trustee.to_string()))
+                .rights(rights)
+                .severity(severity)
+                .build()
+                .map_err(CollectionError::Parse)
+        };
+        for (trustee, rights) in &current {
+            let known = self.replication.get(trustee);
+            let granted: Vec<String> = rights
+                .iter()
+                .filter(|right| known.is_none_or(|known| !known.contains(*right)))
+                .cloned()
+                .collect();
+            if granted.is_empty() {
+                continue;
+            }
+            // Get-Changes-All is the one that returns password hashes
+            let secrets = granted
+                .iter()
+                .any(|right| right == "DS-Replication-Get-Changes-All" || right == "all extended rights");
+            let severity = if secrets { Severity::Critical } else { Severity::High };
+            events.push(event(DirectoryChange::ReplicationRightsGranted, trustee, granted, severity)?);
+        }
+        for (trustee, rights) in &self.replication {
+            let remaining = current.get(trustee);
+            let revoked: Vec<String> = rights
+                .iter()
+                .filter(|right| remaining.is_none_or(|remaining| !remaining.contains(*right)))
+                .cloned()
+                .collect();
+            if !revoked.is_empty() {
+                events.push(event(DirectoryChange::ReplicationRightsRevoked, trustee, revoked, Severity::Low)?);
+            }
+        }
+
+        Baseline::global().save(REPLICATION_RIGHTS_BASELINE, &current);
+        self.replication = current;
+        Ok(events)
+    }
+
+    fn collect_events(&mut self) -> Result<Vec<DirectoryChangeEvent>, CollectionError> {
+        let interval = Duration::from_secs(self.config.query_interval_secs);
+        if self.last_query.is_some_and(|last_query| last_query.elapsed() < interval) {
+            return Ok(Vec::new());
+        }
+        self.last_query = Some(Instant::now());
+
+        // A failing query leaves the others to run
+        let mut events = Vec::new();
+        if self.config.privileged_accounts {
+            match self.privileged_account_events() {
+                Ok(found) => events.extend(found),
+                Err(e) => warn!("Failed to query new privileged accounts: {}", e),
+            }
+        }
+        if self.config.group_policies {
+            match self.group_policy_events() {
+                Ok(found) => events.extend(found),
+                Err(e) => warn!("Failed to query group policy objects: {}", e),
+            }
+        }
+        if self.config.replication_rights {
+            match self.replication_events() {
+                Ok(found) => events.extend(found),
+                Err(e) => warn!("Failed to query domain replication rights: {}", e),
+            }
+        }
+        info!("Collected {} directory change events", events.len());
+        Ok(events)
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if !cfg!(windows) && !std::path::Path::new(&self.config.samba_database).exists() {
+            return Err(CollectionError::SystemApi(format!(
+                "Samba database {} not found, directory queries need a domain controller",
+                self.config.samba_database
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl DataCollector<Vec<DirectoryChangeEvent>> for DirectoryCollector {
+    fn collect(&mut self) -> Result<Vec<DirectoryChangeEvent>, CollectionError> {
+        self.collect_events()
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<DirectoryChangeEvent>> for DirectoryCollector {
+    async fn collect(&mut self) -> Result<Vec<DirectoryChangeEvent>, CollectionError> {
+        DataCollector::collect(self)
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+impl Default for DirectoryCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
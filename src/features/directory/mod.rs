@@ -0,0 +1,11 @@
+pub mod models;
+pub mod collector;
+pub mod parsers;
+#[cfg(windows)]
+mod win32;
+#[cfg(not(windows))]
+mod samba;
+
+pub use models::{DirectoryChange, DirectoryChangeEvent, DirectoryChangeEventBuilder};
+pub use collector::{DirectoryCollector, DirectoryConfig};
+pub use parsers::{parse_ldif, parse_sddl_dacl, replication_rights, AccessEntry, DirectoryEntry};
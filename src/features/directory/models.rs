@@ -0,0 +1,200 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectoryChange {
+    PrivilegedAccountCreated,
+    GpoCreated,
+    GpoModified,
+    GpoDeleted,
+    ReplicationRightsGranted,
+    ReplicationRightsRevoked,
+}
+
+impl DirectoryChange {
+    pub fn event_type(self) -> &'static str {
+        match self {
+            Self::PrivilegedAccountCreated => "privileged_account_created",
+            Self::GpoCreated => "gpo_created",
+            Self::GpoModified => "gpo_modified",
+            Self::GpoDeleted => "gpo_deleted",
+            Self::ReplicationRightsGranted => "replication_rights_granted",
+            Self::ReplicationRightsRevoked => "replication_rights_revoked",
+        }
+    }
+}
+
+// A change found by querying Active Directory from a domain controller: a new account in a
+// privileged group, a group policy object created, edited or removed, or a trustee gaining
+// or losing the rights to replicate the domain's secrets (DCSync)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryChangeEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub change: DirectoryChange,
+    pub distinguished_name: String,
+    // Account name, GPO display name or trustee
+    pub name: Option<String>,
+    // whenCreated of an account, whenChanged of a GPO
+    pub changed_at: Option<DateTime<Utc>>,
+    // Privileged groups the account is a member of
+    pub groups: Vec<String>,
+    pub trustee_sid: Option<String>,
+    // Replication rights granted or revoked
+    pub rights: Vec<String>,
+    // versionNumber of a GPO, which counts edits to its settings
+    pub version: Option<u64>,
+    pub severity: Severity,
+}
+
+impl Event for DirectoryChangeEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        self.change.event_type()
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl Identifiable for DirectoryChangeEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for DirectoryChangeEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.distinguished_name.is_empty() {
+            return Err("Distinguished name cannot be empty".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct DirectoryChangeEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    change: Option<DirectoryChange>,
+    distinguished_name: Option<String>,
+    name: Option<String>,
+    changed_at: Option<DateTime<Utc>>,
+    groups: Vec<String>,
+    trustee_sid: Option<String>,
+    rights: Vec<String>,
+    version: Option<u64>,
+    severity: Option<Severity>,
+}
+
+impl DirectoryChangeEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn change(mut self, change: DirectoryChange) -> Self {
+        self.change = Some(change);
+        self
+    }
+
+    pub fn distinguished_name(mut self, distinguished_name: String) -> Self {
+        self.distinguished_name = Some(distinguished_name);
+        self
+    }
+
+    pub fn name(mut self, name: Option<String>) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub fn changed_at(mut self, changed_at: Option<DateTime<Utc>>) -> Self {
+        self.changed_at = changed_at;
+        self
+    }
+
+    pub fn groups(mut self, groups: Vec<String>) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    pub fn trustee_sid(mut self, trustee_sid: Option<String>) -> Self {
+        self.trustee_sid = trustee_sid;
+        self
+    }
+
+    pub fn rights(mut self, rights: Vec<String>) -> Self {
+        self.rights = rights;
+        self
+    }
+
+    pub fn version(mut self, version: Option<u64>) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    pub fn build(self) -> Result<DirectoryChangeEvent, String> {
+        let event = DirectoryChangeEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.unwrap_or_else(Utc::now),
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            change: self.change.ok_or("change is required")?,
+            distinguished_name: self.distinguished_name.ok_or("distinguished_name is required")?,
+            name: self.name,
+            changed_at: self.changed_at,
+            groups: self.groups,
+            trustee_sid: self.trustee_sid,
+            rights: self.rights,
+            version: self.version,
+            severity: self.severity.unwrap_or(Severity::Medium),
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
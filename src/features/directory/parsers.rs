@@ -0,0 +1,213 @@
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+// One object returned by a directory search, attribute names lower-cased
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirectoryEntry {
+    pub attributes: HashMap<String, Vec<String>>,
+}
+
+impl DirectoryEntry {
+    pub fn first(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).and_then(|values| values.first()).map(String::as_str)
+    }
+
+    pub fn values(&self, name: &str) -> &[String] {
+        self.attributes.get(name).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+// The JSON array the Windows search script writes: one object per entry, every attribute an
+// array of strings
+pub fn parse_search_json(content: &str) -> Vec<DirectoryEntry> {
+    let entries = match serde_json::from_str::<Value>(content.trim()) {
+        Ok(Value::Array(entries)) => entries,
+        Ok(entry @ Value::Object(_)) => vec![entry],
+        _ => return Vec::new(),
+    };
+    entries
+        .iter()
+        .filter_map(Value::as_object)
+        .map(|entry| DirectoryEntry {
+            attributes: entry
+                .iter()
+                .map(|(name, values)| {
+                    let values = match values {
+                        Value::Array(values) => values.iter().filter_map(json_string).collect(),
+                        value => json_string(value).into_iter().collect(),
+                    };
+                    (name.to_lowercase(), values)
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+fn json_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+// LDIF as ldbsearch and ldapsearch print it: entries separated by blank lines, long lines
+// folded onto lines starting with a space, `name:: value` for base64 values and # comments.
+// The dn is kept as distinguishedname.
+pub fn parse_ldif(content: &str) -> Vec<DirectoryEntry> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix(' '), lines.last_mut()) {
+            (Some(continuation), Some(previous)) if !previous.is_empty() => previous.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut entries = Vec::new();
+    let mut entry = DirectoryEntry::default();
+    for line in lines {
+        if line.is_empty() {
+            if !entry.attributes.is_empty() {
+                entries.push(std::mem::take(&mut entry));
+            }
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = match value.strip_prefix(':') {
+            Some(encoded) => match base64::engine::general_purpose::STANDARD.decode(encoded.trim()) {
+                Ok(decoded) => String::from_utf8_lossy(&decoded).into_owned(),
+                Err(_) => continue,
+            },
+            None => value.trim_start().to_string(),
+        };
+        let name = match name.to_lowercase() {
+            name if name == "dn" => String::from("distinguishedname"),
+            name => name,
+        };
+        entry.attributes.entry(name).or_default().push(value);
+    }
+    if !entry.attributes.is_empty() {
+        entries.push(entry);
+    }
+    entries
+}
+
+// LDAP generalized time, 20240105101532.0Z
+pub fn parse_generalized_time(value: &str) -> Option<DateTime<Utc>> {
+    let digits = value.get(..14)?;
+    NaiveDateTime::parse_from_str(digits, "%Y%m%d%H%M%S")
+        .ok()
+        .map(|naive| naive.and_utc())
+}
+
+pub fn generalized_time(time: DateTime<Utc>) -> String {
+    format!("{}.0Z", time.format("%Y%m%d%H%M%S"))
+}
+
+// The relative name of a distinguished name's first component: CN=Domain Admins,CN=Users,...
+// gives Domain Admins
+pub fn common_name(dn: &str) -> &str {
+    let first = dn.split(',').next().unwrap_or(dn);
+    first.split_once('=').map_or(first, |(_, value)| value).trim()
+}
+
+// One access control entry of an SDDL DACL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessEntry {
+    // A, OA, D, OD, ...
+    pub kind: String,
+    pub flags: String,
+    pub rights: String,
+    pub object_type: Option<String>,
+    // A SID or an SDDL alias such as BA or DA
+    pub trustee: String,
+}
+
+impl AccessEntry {
+    fn allows(&self) -> bool {
+        matches!(self.kind.as_str(), "A" | "OA")
+    }
+
+    // Inherit-only entries apply to child objects, not to the object carrying them
+    fn applies_here(&self) -> bool {
+        !self.flags.contains("IO")
+    }
+
+    fn has_right(&self, code: &str, mask: u32) -> bool {
+        match self.rights.strip_prefix("0x").or_else(|| self.rights.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16).is_ok_and(|rights| rights & mask != 0),
+            None => self
+                .rights
+                .as_bytes()
+                .chunks(2)
+                .any(|pair| pair == code.as_bytes()),
+        }
+    }
+}
+
+// The entries of the discretionary ACL in an SDDL string; conditional and resource entries,
+// which carry more fields, are left out
+pub fn parse_sddl_dacl(sddl: &str) -> Vec<AccessEntry> {
+    let Some(start) = sddl.find("D:") else {
+        return Vec::new();
+    };
+    let dacl = &sddl[start + 2..];
+    let dacl = match dacl.find("S:") {
+        Some(end) if !dacl[..end].ends_with(';') => &dacl[..end],
+        _ => dacl,
+    };
+    dacl.split('(')
+        .skip(1)
+        .filter_map(|entry| entry.split_once(')').map(|(entry, _)| entry))
+        .filter_map(|entry| {
+            let fields: Vec<&str> = entry.split(';').collect();
+            if fields.len() != 6 {
+                return None;
+            }
+            Some(AccessEntry {
+                kind: fields[0].to_uppercase(),
+                flags: fields[1].to_uppercase(),
+                rights: fields[2].to_uppercase(),
+                object_type: Some(fields[3].to_lowercase()).filter(|guid| !guid.is_empty()),
+                trustee: fields[5].to_string(),
+            })
+        })
+        .collect()
+}
+
+// Extended rights that let a trustee replicate secrets from a domain controller (DCSync)
+pub const GET_CHANGES: &str = "1131f6aa-9c07-11d1-f79f-00c04fc2dcd2";
+pub const GET_CHANGES_ALL: &str = "1131f6ad-9c07-11d1-f79f-00c04fc2dcd2";
+pub const GET_CHANGES_FILTERED: &str = "89e95b76-444d-4c62-991a-0facbeda640c";
+
+// ADS_RIGHT_DS_CONTROL_ACCESS and GENERIC_ALL
+const CONTROL_ACCESS: u32 = 0x100;
+const GENERIC_ALL: u32 = 0x1000_0000;
+
+// Replication rights each trustee holds on the domain object: the named extended rights, or
+// "all extended rights" for control access to every right or full control
+pub fn replication_rights(entries: &[AccessEntry]) -> HashMap<String, BTreeSet<String>> {
+    let mut rights: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for entry in entries.iter().filter(|entry| entry.allows() && entry.applies_here()) {
+        let full_control = entry.has_right("GA", GENERIC_ALL);
+        let control_access = entry.has_right("CR", CONTROL_ACCESS);
+        let right = match entry.object_type.as_deref() {
+            _ if full_control => "all extended rights",
+            None if control_access => "all extended rights",
+            Some(GET_CHANGES) if control_access => "DS-Replication-Get-Changes",
+            Some(GET_CHANGES_ALL) if control_access => "DS-Replication-Get-Changes-All",
+            Some(GET_CHANGES_FILTERED) if control_access => "DS-Replication-Get-Changes-In-Filtered-Set",
+            _ => continue,
+        };
+        rights.entry(entry.trustee.clone()).or_default().insert(right.to_string());
+    }
+    rights
+}
@@ -0,0 +1,42 @@
+use crate::features::directory::collector::DirectoryConfig;
+use crate::features::directory::parsers::{parse_ldif, DirectoryEntry};
+use crate::shared::command;
+use crate::shared::error::CollectionError;
+use std::path::Path;
+
+// A Samba domain controller is read straight from its database with ldbsearch, which prints
+// LDIF with times in generalized time, SIDs as strings and security descriptors in SDDL
+fn ldbsearch(config: &DirectoryConfig, args: &[&str]) -> Result<Vec<DirectoryEntry>, CollectionError> {
+    if !Path::new(&config.samba_database).exists() {
+        return Err(CollectionError::SystemApi(format!(
+            "Samba database {} not found, is this a domain controller?",
+            config.samba_database
+        )));
+    }
+    let output = command::run("ldbsearch", [["-H", config.samba_database.as_str()].as_slice(), args].concat())?;
+    if !output.success {
+        return Err(CollectionError::SystemApi(format!("ldbsearch failed: {}", output.stderr.trim())));
+    }
+    Ok(parse_ldif(&output.stdout))
+}
+
+fn naming_context(config: &DirectoryConfig) -> Result<String, CollectionError> {
+    ldbsearch(config, &["-s", "base", "-b", "", "defaultNamingContext"])?
+        .iter()
+        .find_map(|entry| entry.first("defaultnamingcontext").map(str::to_string))
+        .ok_or_else(|| CollectionError::Parse(String::from("the root DSE has no defaultNamingContext")))
+}
+
+pub fn search(config: &DirectoryConfig, filter: &str, attributes: &[&str]) -> Result<Vec<DirectoryEntry>, CollectionError> {
+    let base = naming_context(config)?;
+    let args = [["-s", "sub", "-b", base.as_str(), filter].as_slice(), attributes].concat();
+    ldbsearch(config, &args)
+}
+
+pub fn domain_root(config: &DirectoryConfig) -> Result<DirectoryEntry, CollectionError> {
+    let base = naming_context(config)?;
+    ldbsearch(config, &["-s", "base", "-b", base.as_str(), "nTSecurityDescriptor"])?
+        .into_iter()
+        .next()
+        .ok_or_else(|| CollectionError::Parse(format!("domain object {} not found", base)))
+}
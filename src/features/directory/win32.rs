@@ -0,0 +1,60 @@
+use crate::features::directory::collector::DirectoryConfig;
+use crate::features::directory::parsers::{parse_search_json, DirectoryEntry};
+use crate::shared::command;
+use crate::shared::error::CollectionError;
+
+// A paged search of the domain this controller serves, one JSON object per entry with every
+// attribute as an array of strings: times in generalized time, objectSid as a SID string and
+// other binary values in base64
+const SEARCH: &str = r#"$ErrorActionPreference = 'Stop'
+$root = [ADSI]'LDAP://RootDSE'
+$searcher = New-Object System.DirectoryServices.DirectorySearcher([ADSI]('LDAP://' + $root.defaultNamingContext), '{filter}')
+$searcher.PageSize = 500
+'{attributes}'.Split(',') | ForEach-Object { [void]$searcher.PropertiesToLoad.Add($_) }
+$entries = @($searcher.FindAll() | ForEach-Object {
+    $properties = $_.Properties
+    $entry = @{}
+    foreach ($name in $properties.PropertyNames) {
+        $entry[$name] = @($properties[$name] | ForEach-Object {
+            if ($_ -is [datetime]) { $_.ToUniversalTime().ToString('yyyyMMddHHmmss.0Z') }
+            elseif ($_ -is [byte[]] -and $name -eq 'objectsid') { (New-Object System.Security.Principal.SecurityIdentifier($_, 0)).Value }
+            elseif ($_ -is [byte[]]) { [Convert]::ToBase64String($_) }
+            else { [string]$_ }
+        })
+    }
+    $entry
+})
+ConvertTo-Json -InputObject $entries -Compress -Depth 3"#;
+
+// The domain object with the access part of its security descriptor in SDDL
+const DOMAIN_ROOT: &str = r#"$ErrorActionPreference = 'Stop'
+$root = [ADSI]'LDAP://RootDSE'
+$domain = [ADSI]('LDAP://' + $root.defaultNamingContext)
+ConvertTo-Json -Compress -InputObject @(@{
+    distinguishedname = @([string]$root.defaultNamingContext)
+    ntsecuritydescriptor = @($domain.ObjectSecurity.GetSecurityDescriptorSddlForm('Access'))
+})"#;
+
+fn powershell(script: &str, what: &str) -> Result<Vec<DirectoryEntry>, CollectionError> {
+    let output = command::run("powershell", ["-NoProfile", "-NonInteractive", "-Command", script])?;
+    if !output.success {
+        return Err(CollectionError::SystemApi(format!("Failed to query {}: {}", what, output.stderr.trim())));
+    }
+    Ok(parse_search_json(&output.stdout))
+}
+
+pub fn search(_config: &DirectoryConfig, filter: &str, attributes: &[&str]) -> Result<Vec<DirectoryEntry>, CollectionError> {
+    // Filters are built by the collector, never from configuration, but a quote would still
+    // end the PowerShell string
+    let script = SEARCH
+        .replace("{filter}", &filter.replace('\'', "''"))
+        .replace("{attributes}", &attributes.join(","));
+    powershell(&script, "Active Directory")
+}
+
+pub fn domain_root(_config: &DirectoryConfig) -> Result<DirectoryEntry, CollectionError> {
+    powershell(DOMAIN_ROOT, "the domain security descriptor")?
+        .into_iter()
+        .next()
+        .ok_or_else(|| CollectionError::Parse(String::from("no domain object returned")))
+}
@@ -0,0 +1,213 @@
+use crate::features::exec_trace::models::ExecEventType;
+use crate::features::exec_trace::RawExecRecord;
+use std::collections::HashMap;
+
+pub(crate) const AUDIT_SYSCALL: u16 = 1300;
+pub(crate) const AUDIT_EXECVE: u16 = 1309;
+pub(crate) const AUDIT_EOE: u16 = 1320;
+// Multicast group that lets a second reader see audit records alongside auditd
+pub(crate) const AUDIT_NLGRP_READLOG: u32 = 1;
+
+// Partially assembled audit event; records of one event share a serial number
+#[derive(Default)]
+struct PendingEvent {
+    syscall: Option<HashMap<String, String>>,
+    argv: Vec<(usize, String)>,
+    has_execve: bool,
+}
+
+// Collects SYSCALL / EXECVE records per audit serial and turns each completed
+// event (terminated by EOE) into an exec or fork record.
+pub(crate) struct AuditAssembler {
+    pending: HashMap<u64, PendingEvent>,
+    max_pending: usize,
+}
+
+impl AuditAssembler {
+    pub(crate) fn new(max_pending: usize) -> Self {
+        Self {
+            pending: HashMap::new(),
+            max_pending,
+        }
+    }
+
+    pub(crate) fn push(&mut self, message_type: u16, text: &str) -> Option<RawExecRecord> {
+        let (serial, body) = parse_header(text)?;
+
+        match message_type {
+            AUDIT_SYSCALL => {
+                self.entry(serial).syscall = Some(parse_fields(body));
+                None
+            }
+            AUDIT_EXECVE => {
+                let pending = self.entry(serial);
+                pending.has_execve = true;
+                collect_arguments(body, &mut pending.argv);
+                None
+            }
+            AUDIT_EOE => {
+                let pending = self.pending.remove(&serial)?;
+                Self::complete(pending)
+            }
+            _ => None,
+        }
+    }
+
+    fn entry(&mut self, serial: u64) -> &mut PendingEvent {
+        // Events whose EOE was lost would otherwise accumulate forever
+        if self.pending.len() >= self.max_pending && !self.pending.contains_key(&serial) {
+            if let Some(oldest) = self.pending.keys().min().copied() {
+                self.pending.remove(&oldest);
+            }
+        }
+        self.pending.entry(serial).or_default()
+    }
+
+    fn complete(pending: PendingEvent) -> Option<RawExecRecord> {
+        let syscall = pending.syscall?;
+        if syscall.get("success").map(String::as_str) == Some("no") {
+            return None;
+        }
+
+        let arch = syscall.get("arch").map(String::as_str).unwrap_or_default();
+        let number: u32 = syscall.get("syscall")?.parse().ok()?;
+        let field = |name: &str| syscall.get(name).and_then(|value| value.parse::<u32>().ok());
+
+        let event_type = if pending.has_execve || is_exec_syscall(arch, number) {
+            ExecEventType::Exec
+        } else if is_fork_syscall(arch, number) {
+            ExecEventType::Fork
+        } else {
+            return None;
+        };
+
+        let mut argv = pending.argv;
+        argv.sort_by_key(|(index, _)| *index);
+
+        let record = match event_type {
+            // For fork-family calls the new PID is the syscall return value
+            ExecEventType::Fork => RawExecRecord {
+                event_type,
+                pid: field("exit")?,
+                parent_pid: field("pid"),
+                uid: field("uid"),
+                executable: syscall.get("exe").map(|value| decode_string(value)),
+                command_name: syscall.get("comm").map(|value| decode_string(value)),
+                argv: Vec::new(),
+                exit_code: None,
+            },
+            _ => RawExecRecord {
+                event_type,
+                pid: field("pid")?,
+                parent_pid: field("ppid"),
+                uid: field("uid"),
+                executable: syscall.get("exe").map(|value| decode_string(value)),
+                command_name: syscall.get("comm").map(|value| decode_string(value)),
+                argv: argv.into_iter().map(|(_, argument)| argument).collect(),
+                exit_code: None,
+            },
+        };
+
+        Some(record)
+    }
+}
+
+// "audit(1700000000.123:456): key=value ..." -> (456, "key=value ...")
+pub(crate) fn parse_header(text: &str) -> Option<(u64, &str)> {
+    let start = text.find("audit(")? + "audit(".len();
+    let end = start + text[start..].find(')')?;
+    let serial = text[start..end].split(':').nth(1)?.parse().ok()?;
+    let body = text[end + 1..].trim_start_matches(':').trim_start();
+    Some((serial, body))
+}
+
+// Splits space separated key=value pairs, keeping quoted values intact
+pub(crate) fn parse_fields(body: &str) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let mut rest = body.trim();
+
+    while !rest.is_empty() {
+        let Some(equals) = rest.find('=') else {
+            break;
+        };
+        let key = rest[..equals].trim().to_string();
+        rest = &rest[equals + 1..];
+
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            // Keep the quotes so decode_string can tell literal strings from hex
+            let end = quoted.find('"').map(|end| end + 2).unwrap_or(rest.len());
+            let value = rest[..end].to_string();
+            rest = &rest[end..];
+            value
+        } else {
+            let end = rest.find(' ').unwrap_or(rest.len());
+            let value = rest[..end].to_string();
+            rest = &rest[end..];
+            value
+        };
+
+        fields.insert(key, value);
+        rest = rest.trim_start();
+    }
+
+    fields
+}
+
+// Handles both `a0="ls"` and the split form `a1_len=9000 a1[0]=...` used for long arguments
+fn collect_arguments(body: &str, argv: &mut Vec<(usize, String)>) {
+    let mut chunks: HashMap<usize, Vec<(usize, String)>> = HashMap::new();
+
+    for (key, value) in parse_fields(body) {
+        let Some(rest) = key.strip_prefix('a') else {
+            continue;
+        };
+        if let Some((index, chunk)) = rest.split_once('[') {
+            let (Ok(index), Ok(chunk)) = (index.parse(), chunk.trim_end_matches(']').parse()) else {
+                continue;
+            };
+            chunks.entry(index).or_default().push((chunk, value));
+        } else if let Ok(index) = rest.parse::<usize>() {
+            argv.push((index, decode_string(&value)));
+        }
+    }
+
+    for (index, mut parts) in chunks {
+        parts.sort_by_key(|(chunk, _)| *chunk);
+        // Chunks are hex encoded independently; decode the joined payload once
+        let joined: String = parts.into_iter().map(|(_, part)| part.trim_matches('"').to_string()).collect();
+        argv.push((index, decode_string(&joined)));
+    }
+}
+
+// Audit quotes printable strings and hex encodes anything containing spaces,
+// quotes or control characters.
+pub(crate) fn decode_string(value: &str) -> String {
+    if let Some(quoted) = value.strip_prefix('"') {
+        return quoted.trim_end_matches('"').to_string();
+    }
+    if value == "(null)" || value.len() % 2 != 0 {
+        return value.to_string();
+    }
+    match hex::decode(value) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(_) => value.to_string(),
+    }
+}
+
+fn is_exec_syscall(arch: &str, number: u32) -> bool {
+    match arch {
+        "c000003e" => matches!(number, 59 | 322),
+        "c00000b7" => matches!(number, 221 | 281),
+        "40000003" => matches!(number, 11 | 358),
+        _ => false,
+    }
+}
+
+fn is_fork_syscall(arch: &str, number: u32) -> bool {
+    match arch {
+        "c000003e" => matches!(number, 56 | 57 | 58 | 435),
+        "c00000b7" => matches!(number, 220 | 435),
+        "40000003" => matches!(number, 2 | 120 | 190 | 435),
+        _ => false,
+    }
+}
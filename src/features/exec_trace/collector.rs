@@ -0,0 +1,383 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector};
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::user_resolver::UserResolver;
+use crate::features::exec_trace::models::{ExecEvent, ExecEventBuilder, ExecEventType, ExecTraceSource};
+use crate::utils::load_config_section;
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecTraceMode {
+    // Audit when execve rules are loaded (or can be installed), proc connector otherwise
+    Auto,
+    Audit,
+    ProcConnector,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecTraceConfig {
+    pub enabled: bool,
+    pub mode: ExecTraceMode,
+    // Load `auditctl` execve/fork rules at startup when using the audit source
+    pub install_audit_rules: bool,
+    pub include_fork: bool,
+    pub include_exit: bool,
+    pub max_args: usize,
+    pub max_arg_length: usize,
+    pub channel_capacity: usize,
+}
+
+impl Default for ExecTraceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mode: ExecTraceMode::Auto,
+            install_audit_rules: false,
+            include_fork: true,
+            include_exit: false,
+            max_args: 64,
+            max_arg_length: 1024,
+            channel_capacity: 4096,
+        }
+    }
+}
+
+impl ExecTraceConfig {
+    pub fn load() -> Self {
+        load_config_section("exec_trace")
+    }
+}
+
+// Captures process exec / fork (and optionally exit) events on Linux from the audit
+// netlink multicast group or the kernel process connector. A reader thread blocks on
+// the socket and hands events to `collect` through a bounded channel.
+pub struct ExecTraceCollector {
+    config: ExecTraceConfig,
+    tracer: ExecTraceSource,
+    event_receiver: Receiver<ExecEvent>,
+    dropped: Arc<AtomicU64>,
+    reader_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ExecTraceCollector {
+    pub fn new() -> Result<Self, CollectionError> {
+        Self::with_config(ExecTraceConfig::load())
+    }
+
+    pub fn with_config(config: ExecTraceConfig) -> Result<Self, CollectionError> {
+        if !cfg!(target_os = "linux") {
+            return Err(CollectionError::SystemApi(
+                "exec_trace is only available on Linux".to_string(),
+            ));
+        }
+
+        let (tx, rx) = channel(config.channel_capacity.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+        let (tracer, reader_thread) = Self::start_reader(&config, tx, dropped.clone())?;
+        info!("Tracing process execution via {:?}", tracer);
+
+        Ok(Self {
+            config,
+            tracer,
+            event_receiver: rx,
+            dropped,
+            reader_thread: Some(reader_thread),
+        })
+    }
+
+    pub fn tracer(&self) -> ExecTraceSource {
+        self.tracer
+    }
+
+    #[cfg(target_os = "linux")]
+    fn start_reader(
+        config: &ExecTraceConfig,
+        tx: Sender<ExecEvent>,
+        dropped: Arc<AtomicU64>,
+    ) -> Result<(ExecTraceSource, thread::JoinHandle<()>), CollectionError> {
+        use crate::features::exec_trace::audit::{AuditAssembler, AUDIT_NLGRP_READLOG};
+        use crate::features::exec_trace::netlink::NetlinkSocket;
+        use crate::features::exec_trace::proc_connector::{self, CN_IDX_PROC};
+        use std::time::Duration;
+
+        const NETLINK_AUDIT: i32 = 9;
+        const NETLINK_CONNECTOR: i32 = 11;
+
+        let open_audit = || {
+            NetlinkSocket::open(libc::SOCK_RAW, NETLINK_AUDIT, AUDIT_NLGRP_READLOG)
+                .map_err(|e| CollectionError::SystemApi(format!("Failed to join audit multicast group: {}", e)))
+        };
+        let open_connector = || -> Result<NetlinkSocket, CollectionError> {
+            let socket = NetlinkSocket::open(libc::SOCK_DGRAM, NETLINK_CONNECTOR, CN_IDX_PROC)
+                .map_err(|e| CollectionError::SystemApi(format!("Failed to open process connector: {}", e)))?;
+            socket
+                .send(&proc_connector::listen_message())
+                .map_err(|e| CollectionError::SystemApi(format!("Failed to subscribe to process events: {}", e)))?;
+            Ok(socket)
+        };
+
+        let (tracer, socket) = match config.mode {
+            ExecTraceMode::Audit => {
+                if config.install_audit_rules {
+                    Self::install_audit_rules();
+                }
+                (ExecTraceSource::Audit, open_audit()?)
+            }
+            ExecTraceMode::ProcConnector => (ExecTraceSource::ProcConnector, open_connector()?),
+            ExecTraceMode::Auto => {
+                let rules_ready = (config.install_audit_rules && Self::install_audit_rules())
+                    || Self::audit_rules_loaded();
+                match open_audit() {
+                    Ok(socket) if rules_ready => (ExecTraceSource::Audit, socket),
+                    Ok(_) => {
+                        info!("No execve audit rules loaded, using the process connector");
+                        (ExecTraceSource::ProcConnector, open_connector()?)
+                    }
+                    Err(e) => {
+                        info!("{}, using the process connector", e);
+                        (ExecTraceSource::ProcConnector, open_connector()?)
+                    }
+                }
+            }
+        };
+
+        socket
+            .set_read_timeout(Duration::from_secs(1))
+            .map_err(|e| CollectionError::SystemApi(e.to_string()))?;
+
+        let config = config.clone();
+        let handle = thread::Builder::new()
+            .name(String::from("exec-trace"))
+            .spawn(move || {
+                let host = HostIdentityService::global();
+                let users = UserResolver::global();
+                let mut assembler = AuditAssembler::new(1024);
+                let mut buffer = vec![0u8; 64 * 1024];
+
+                loop {
+                    if tx.is_closed() {
+                        return;
+                    }
+                    let received = match socket.recv(&mut buffer) {
+                        Ok(0) => continue,
+                        Ok(received) => received,
+                        Err(e) => {
+                            warn!("Exec trace socket failed, stopping: {}", e);
+                            return;
+                        }
+                    };
+
+                    let mut records = Vec::new();
+                    match tracer {
+                        ExecTraceSource::Audit => {
+                            for (message_type, payload) in crate::features::exec_trace::netlink::messages(&buffer[..received]) {
+                                let text = String::from_utf8_lossy(payload);
+                                if let Some(record) = assembler.push(message_type, text.trim_end_matches('\0')) {
+                                    records.push(record);
+                                }
+                            }
+                        }
+                        ExecTraceSource::ProcConnector => {
+                            for (_, payload) in crate::features::exec_trace::netlink::messages(&buffer[..received]) {
+                                if let Some(mut record) = proc_connector::parse_event(payload) {
+                                    if record.event_type != ExecEventType::Exit {
+                                        proc_connector::enrich_from_proc(&mut record);
+                                    }
+                                    records.push(record);
+                                }
+                            }
+                        }
+                    }
+
+                    for record in records {
+                        let Some(event) = Self::build_event(&config, tracer, record, &host, &users) else {
+                            continue;
+                        };
+                        match tx.try_send(event) {
+                            Ok(()) => {}
+                            Err(tokio::sync::mpsc::error::TrySendError::Full(_)) => {
+                                dropped.fetch_add(1, Ordering::Relaxed);
+                            }
+                            Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => return,
+                        }
+                    }
+                }
+            })
+            .map_err(CollectionError::Io)?;
+
+        Ok((tracer, handle))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn start_reader(
+        _config: &ExecTraceConfig,
+        _tx: Sender<ExecEvent>,
+        _dropped: Arc<AtomicU64>,
+    ) -> Result<(ExecTraceSource, thread::JoinHandle<()>), CollectionError> {
+        Err(CollectionError::SystemApi(
+            "exec_trace is only available on Linux".to_string(),
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn build_event(
+        config: &ExecTraceConfig,
+        tracer: ExecTraceSource,
+        record: crate::features::exec_trace::RawExecRecord,
+        host: &HostIdentityService,
+        users: &UserResolver,
+    ) -> Option<ExecEvent> {
+        match record.event_type {
+            ExecEventType::Fork if !config.include_fork => return None,
+            ExecEventType::Exit if !config.include_exit => return None,
+            _ => {}
+        }
+
+        let argv = record
+            .argv
+            .into_iter()
+            .take(config.max_args)
+            .map(|argument| Self::truncate(argument, config.max_arg_length))
+            .collect();
+
+        let mut builder = ExecEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(host.name())
+            .category(String::from("exec_trace"))
+            .event_type(record.event_type)
+            .tracer(tracer)
+            .pid(record.pid)
+            .argv(argv);
+
+        if let Some(parent_pid) = record.parent_pid {
+            builder = builder.parent_pid(parent_pid);
+        }
+        if let Some(uid) = record.uid {
+            builder = builder.uid(uid).user(users.display_id(&uid.to_string()));
+        }
+        if let Some(executable) = record.executable.filter(|value| !value.is_empty()) {
+            builder = builder.executable(executable);
+        }
+        if let Some(command_name) = record.command_name {
+            builder = builder.command_name(command_name);
+        }
+        if let Some(exit_code) = record.exit_code {
+            builder = builder.exit_code(exit_code);
+        }
+
+        builder.build().ok()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn truncate(mut value: String, max_length: usize) -> String {
+        if value.len() > max_length {
+            let mut end = max_length;
+            while !value.is_char_boundary(end) {
+                end -= 1;
+            }
+            value.truncate(end);
+        }
+        value
+    }
+
+    #[cfg(target_os = "linux")]
+    fn audit_rules_loaded() -> bool {
+        std::process::Command::new("auditctl")
+            .arg("-l")
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains("execve"))
+            .unwrap_or(false)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn install_audit_rules() -> bool {
+        let rules: &[&[&str]] = &[
+            &["-a", "always,exit", "-F", "arch=b64", "-S", "execve,execveat", "-k", "lsedr_exec"],
+            &["-a", "always,exit", "-F", "arch=b64", "-S", "fork,vfork,clone", "-k", "lsedr_fork"],
+        ];
+
+        let mut installed = true;
+        for rule in rules {
+            match std::process::Command::new("auditctl").args(*rule).output() {
+                // auditctl reports an already loaded rule as an error; that is fine
+                Ok(output) if output.status.success()
+                    || String::from_utf8_lossy(&output.stderr).contains("exists") => {}
+                Ok(output) => {
+                    warn!("auditctl {:?} failed: {}", rule, String::from_utf8_lossy(&output.stderr).trim());
+                    installed = false;
+                }
+                Err(e) => {
+                    warn!("Failed to run auditctl: {}", e);
+                    installed = false;
+                }
+            }
+        }
+        installed
+    }
+
+    fn drain(&mut self) -> Vec<ExecEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.event_receiver.try_recv() {
+            events.push(event);
+        }
+
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            warn!(
+                "Dropped {} exec events, consider raising exec_trace.channel_capacity (currently {})",
+                dropped, self.config.channel_capacity
+            );
+        }
+
+        info!("Collected {} exec trace events", events.len());
+        events
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        match &self.reader_thread {
+            Some(handle) if !handle.is_finished() => Ok(()),
+            _ => Err(CollectionError::SystemApi(
+                "Exec trace reader thread is not running".to_string(),
+            )),
+        }
+    }
+}
+
+impl DataCollector<Vec<ExecEvent>> for ExecTraceCollector {
+    fn collect(&mut self) -> Result<Vec<ExecEvent>, CollectionError> {
+        Ok(self.drain())
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<ExecEvent>> for ExecTraceCollector {
+    async fn collect(&mut self) -> Result<Vec<ExecEvent>, CollectionError> {
+        Ok(self.drain())
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
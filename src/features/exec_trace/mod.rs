@@ -0,0 +1,46 @@
+mod collector;
+mod models;
+#[cfg(target_os = "linux")]
+mod audit;
+#[cfg(target_os = "linux")]
+pub(crate) mod netlink;
+#[cfg(target_os = "linux")]
+mod proc_connector;
+
+pub use collector::{ExecTraceCollector, ExecTraceConfig, ExecTraceMode};
+pub use models::{ExecEvent, ExecEventBuilder, ExecEventType, ExecTraceSource};
+
+// Kernel-level view of one process transition before it is enriched into an ExecEvent
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone)]
+pub(crate) struct RawExecRecord {
+    pub event_type: ExecEventType,
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    pub uid: Option<u32>,
+    pub executable: Option<String>,
+    pub command_name: Option<String>,
+    pub argv: Vec<String>,
+    pub exit_code: Option<i32>,
+}
+
+#[cfg(target_os = "linux")]
+impl RawExecRecord {
+    pub(crate) fn new(event_type: ExecEventType, pid: u32) -> Self {
+        Self {
+            event_type,
+            pid,
+            parent_pid: None,
+            uid: None,
+            executable: None,
+            command_name: None,
+            argv: Vec::new(),
+            exit_code: None,
+        }
+    }
+
+    pub(crate) fn with_parent(mut self, parent_pid: u32) -> Self {
+        self.parent_pid = Some(parent_pid);
+        self
+    }
+}
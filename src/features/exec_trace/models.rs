@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecEventType {
+    Exec,
+    Fork,
+    Exit,
+}
+
+// Kernel interface an event was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecTraceSource {
+    Audit,
+    ProcConnector,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub event_type: ExecEventType,
+    pub tracer: ExecTraceSource,
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    pub uid: Option<u32>,
+    pub user: Option<String>,
+    pub executable: Option<String>,
+    pub command_name: Option<String>,
+    pub argv: Vec<String>,
+    pub exit_code: Option<i32>,
+}
+
+impl Event for ExecEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.event_type {
+            ExecEventType::Exec => "process_exec",
+            ExecEventType::Fork => "process_fork",
+            ExecEventType::Exit => "process_exit",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        match self.event_type {
+            // Root executions are what persistence and privilege abuse look like
+            ExecEventType::Exec if self.uid == Some(0) => Severity::Medium,
+            _ => Severity::Low,
+        }
+    }
+}
+
+impl Identifiable for ExecEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for ExecEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.pid == 0 {
+            return Err("PID cannot be zero".to_string());
+        }
+
+        if let Some(ref executable) = self.executable {
+            if executable.is_empty() {
+                return Err("Executable cannot be empty when provided".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct ExecEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    event_type: Option<ExecEventType>,
+    tracer: Option<ExecTraceSource>,
+    pid: Option<u32>,
+    parent_pid: Option<u32>,
+    uid: Option<u32>,
+    user: Option<String>,
+    executable: Option<String>,
+    command_name: Option<String>,
+    argv: Vec<String>,
+    exit_code: Option<i32>,
+}
+
+impl ExecEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn event_type(mut self, event_type: ExecEventType) -> Self {
+        self.event_type = Some(event_type);
+        self
+    }
+
+    pub fn tracer(mut self, tracer: ExecTraceSource) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+
+    pub fn pid(mut self, pid: u32) -> Self {
+        self.pid = Some(pid);
+        self
+    }
+
+    pub fn parent_pid(mut self, parent_pid: u32) -> Self {
+        self.parent_pid = Some(parent_pid);
+        self
+    }
+
+    pub fn uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    pub fn user(mut self, user: String) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    pub fn executable(mut self, executable: String) -> Self {
+        self.executable = Some(executable);
+        self
+    }
+
+    pub fn command_name(mut self, command_name: String) -> Self {
+        self.command_name = Some(command_name);
+        self
+    }
+
+    pub fn argv(mut self, argv: Vec<String>) -> Self {
+        self.argv = argv;
+        self
+    }
+
+    pub fn exit_code(mut self, exit_code: i32) -> Self {
+        self.exit_code = Some(exit_code);
+        self
+    }
+
+    pub fn build(self) -> Result<ExecEvent, String> {
+        let event = ExecEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            event_type: self.event_type.ok_or("event_type is required")?,
+            tracer: self.tracer.ok_or("tracer is required")?,
+            pid: self.pid.ok_or("pid is required")?,
+            parent_pid: self.parent_pid,
+            uid: self.uid,
+            user: self.user,
+            executable: self.executable,
+            command_name: self.command_name,
+            argv: self.argv,
+            exit_code: self.exit_code,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
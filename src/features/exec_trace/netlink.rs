@@ -0,0 +1,147 @@
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+
+pub(crate) const NLMSG_HDRLEN: usize = 16;
+pub(crate) const NLMSG_NOOP: u16 = 1;
+pub(crate) const NLMSG_DONE: u16 = 3;
+
+// Thin owner of an AF_NETLINK socket bound to a set of multicast groups
+pub(crate) struct NetlinkSocket {
+    fd: OwnedFd,
+}
+
+impl NetlinkSocket {
+    pub(crate) fn open(sock_type: i32, protocol: i32, groups: u32) -> io::Result<Self> {
+        let fd = unsafe { libc::socket(libc::AF_NETLINK, sock_type | libc::SOCK_CLOEXEC, protocol) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_pid = 0;
+        address.nl_groups = groups;
+
+        let bound = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self { fd })
+    }
+
+    // Bounded receive so reader threads can notice the collector going away
+    pub(crate) fn set_read_timeout(&self, timeout: Duration) -> io::Result<()> {
+        let value = libc::timeval {
+            tv_sec: timeout.as_secs() as libc::time_t,
+            tv_usec: timeout.subsec_micros() as libc::suseconds_t,
+        };
+        let result = unsafe {
+            libc::setsockopt(
+                self.fd.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &value as *const libc::timeval as *const libc::c_void,
+                mem::size_of::<libc::timeval>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn send(&self, message: &[u8]) -> io::Result<()> {
+        let mut kernel: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        kernel.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+
+        let sent = unsafe {
+            libc::sendto(
+                self.fd.as_raw_fd(),
+                message.as_ptr() as *const libc::c_void,
+                message.len(),
+                0,
+                &kernel as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    // Returns Ok(0) when the read timed out
+    pub(crate) fn recv(&self, buffer: &mut [u8]) -> io::Result<usize> {
+        let received = unsafe {
+            libc::recv(
+                self.fd.as_raw_fd(),
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+                0,
+            )
+        };
+        if received < 0 {
+            let error = io::Error::last_os_error();
+            return match error.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted => Ok(0),
+                _ => Err(error),
+            };
+        }
+        Ok(received as usize)
+    }
+}
+
+pub(crate) fn read_u16(buffer: &[u8], offset: usize) -> Option<u16> {
+    buffer
+        .get(offset..offset + 2)
+        .map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]))
+}
+
+pub(crate) fn read_u32(buffer: &[u8], offset: usize) -> Option<u32> {
+    buffer
+        .get(offset..offset + 4)
+        .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// Splits a receive buffer into (message type, payload) pairs
+pub(crate) fn messages(buffer: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut parsed = Vec::new();
+    let mut offset = 0;
+
+    while offset + NLMSG_HDRLEN <= buffer.len() {
+        let length = match read_u32(buffer, offset) {
+            Some(length) => length as usize,
+            None => break,
+        };
+        if length < NLMSG_HDRLEN || offset + length > buffer.len() {
+            break;
+        }
+        let message_type = read_u16(buffer, offset + 4).unwrap_or(NLMSG_NOOP);
+        parsed.push((message_type, &buffer[offset + NLMSG_HDRLEN..offset + length]));
+
+        // Messages are 4-byte aligned
+        offset += (length + 3) & !3;
+    }
+
+    parsed
+}
+
+pub(crate) fn header(length: usize, message_type: u16, flags: u16) -> Vec<u8> {
+    let mut header = Vec::with_capacity(NLMSG_HDRLEN);
+    header.extend_from_slice(&((NLMSG_HDRLEN + length) as u32).to_ne_bytes());
+    header.extend_from_slice(&message_type.to_ne_bytes());
+    header.extend_from_slice(&flags.to_ne_bytes());
+    header.extend_from_slice(&0u32.to_ne_bytes());
+    header.extend_from_slice(&std::process::id().to_ne_bytes());
+    header
+}
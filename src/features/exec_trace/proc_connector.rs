@@ -0,0 +1,97 @@
+use crate::features::exec_trace::models::ExecEventType;
+use crate::features::exec_trace::netlink::{self, read_u32, NLMSG_DONE};
+use crate::features::exec_trace::RawExecRecord;
+use std::fs;
+
+pub(crate) const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+
+const PROC_EVENT_FORK: u32 = 0x0000_0001;
+const PROC_EVENT_EXEC: u32 = 0x0000_0002;
+const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+// struct cn_msg: cb_id { idx, val }, seq, ack, len (u16), flags (u16)
+const CN_MSG_LEN: usize = 20;
+// struct proc_event: what, cpu, timestamp_ns (u64), then the event union
+const PROC_EVENT_DATA: usize = CN_MSG_LEN + 16;
+
+// Subscription request sent once after binding to the CN_IDX_PROC group
+pub(crate) fn listen_message() -> Vec<u8> {
+    let mut payload = Vec::with_capacity(CN_MSG_LEN + 4);
+    payload.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+    payload.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+    payload.extend_from_slice(&0u32.to_ne_bytes());
+    payload.extend_from_slice(&0u32.to_ne_bytes());
+    payload.extend_from_slice(&4u16.to_ne_bytes());
+    payload.extend_from_slice(&0u16.to_ne_bytes());
+    payload.extend_from_slice(&PROC_CN_MCAST_LISTEN.to_ne_bytes());
+
+    let mut message = netlink::header(payload.len(), NLMSG_DONE, 0);
+    message.extend_from_slice(&payload);
+    message
+}
+
+// Decodes one netlink connector payload. Thread creation and thread exits are
+// dropped, only whole-process transitions are reported.
+pub(crate) fn parse_event(payload: &[u8]) -> Option<RawExecRecord> {
+    if read_u32(payload, 0)? != CN_IDX_PROC {
+        return None;
+    }
+    let what = read_u32(payload, CN_MSG_LEN)?;
+    let data = |index: usize| read_u32(payload, PROC_EVENT_DATA + index * 4);
+
+    match what {
+        PROC_EVENT_FORK => {
+            let (parent_tgid, child_pid, child_tgid) = (data(1)?, data(2)?, data(3)?);
+            if child_pid != child_tgid {
+                return None;
+            }
+            Some(RawExecRecord::new(ExecEventType::Fork, child_tgid).with_parent(parent_tgid))
+        }
+        PROC_EVENT_EXEC => Some(RawExecRecord::new(ExecEventType::Exec, data(1)?)),
+        PROC_EVENT_EXIT => {
+            let (pid, tgid, exit_code) = (data(0)?, data(1)?, data(2)?);
+            if pid != tgid {
+                return None;
+            }
+            let mut record = RawExecRecord::new(ExecEventType::Exit, tgid);
+            record.exit_code = Some(exit_code as i32);
+            Some(record)
+        }
+        _ => None,
+    }
+}
+
+// The connector only reports PIDs; fill in the rest from /proc while the process is still there
+pub(crate) fn enrich_from_proc(record: &mut RawExecRecord) {
+    let base = format!("/proc/{}", record.pid);
+
+    if let Ok(status) = fs::read_to_string(format!("{}/status", base)) {
+        for line in status.lines() {
+            if let Some(value) = line.strip_prefix("PPid:") {
+                if record.parent_pid.is_none() {
+                    record.parent_pid = value.trim().parse().ok();
+                }
+            } else if let Some(value) = line.strip_prefix("Uid:") {
+                record.uid = value.split_whitespace().next().and_then(|uid| uid.parse().ok());
+            } else if let Some(value) = line.strip_prefix("Name:") {
+                record.command_name = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    if let Ok(executable) = fs::read_link(format!("{}/exe", base)) {
+        record.executable = Some(executable.to_string_lossy().into_owned());
+    }
+
+    if record.event_type == ExecEventType::Exec {
+        if let Ok(cmdline) = fs::read(format!("{}/cmdline", base)) {
+            record.argv = cmdline
+                .split(|byte| *byte == 0)
+                .filter(|argument| !argument.is_empty())
+                .map(|argument| String::from_utf8_lossy(argument).into_owned())
+                .collect();
+        }
+    }
+}
@@ -0,0 +1,141 @@
+#[cfg(target_os = "linux")]
+use crate::features::filesystem::linux::{open_holders, AttributionSource};
+#[cfg(windows)]
+use crate::features::filesystem::win32::AttributionSource;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::fs;
+#[cfg(not(any(target_os = "linux", windows)))]
+use std::io;
+use std::path::{Path, MAIN_SEPARATOR};
+use std::time::{Duration, Instant};
+
+// How long a write is remembered for the notify event it explains
+const RETENTION_SECS: u64 = 30;
+// Writes waiting for the next collection; more are dropped rather than stalling the kernel
+const MAX_PENDING: usize = 10_000;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+struct AttributionSource;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+impl AttributionSource {
+    fn start(_max_pending: usize) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "not supported on this platform"))
+    }
+
+    fn watch(&mut self, _root: &Path, _recursive: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn drain(&mut self) -> Vec<(String, u32)> {
+        Vec::new()
+    }
+}
+
+// Which process changed a file. notify only says that a path changed, so the kernel is asked
+// who changed it: fanotify on Linux and the Kernel-File ETW provider on Windows. Without the
+// rights those need, a file still held open on Linux is attributed to its holder.
+pub struct FileAttribution {
+    enabled: bool,
+    source: Option<AttributionSource>,
+    // Watched paths and their comparison keys; writes elsewhere on a marked mount are ignored
+    roots: HashMap<String, String>,
+    recent: HashMap<String, (u32, Instant)>,
+}
+
+impl FileAttribution {
+    pub fn new(enabled: bool) -> Self {
+        let source = if enabled {
+            match AttributionSource::start(MAX_PENDING) {
+                Ok(source) => {
+                    info!("Attributing file events to the process that made them");
+                    Some(source)
+                }
+                Err(e) => {
+                    warn!("File event process attribution unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Self {
+            enabled,
+            source,
+            roots: HashMap::new(),
+            recent: HashMap::new(),
+        }
+    }
+
+    // Paths as compared: Windows paths are case-insensitive and notify mixes separators
+    fn key(path: &str) -> String {
+        if cfg!(windows) {
+            path.trim_start_matches(r"\\?\").replace('/', "\\").to_lowercase()
+        } else {
+            path.to_string()
+        }
+    }
+
+    fn canonical_key(path: &str) -> String {
+        Self::key(&fs::canonicalize(path).map_or_else(|_| path.to_string(), |path| path.to_string_lossy().into_owned()))
+    }
+
+    pub fn watch(&mut self, root: &str, recursive: bool) {
+        if let Some(source) = self.source.as_mut() {
+            if let Err(e) = source.watch(Path::new(root), recursive) {
+                warn!("Failed to attribute file events under {}: {}", root, e);
+            }
+        }
+        self.roots.insert(root.to_string(), Self::canonical_key(root));
+    }
+
+    pub fn unwatch(&mut self, root: &str) {
+        self.roots.remove(root);
+    }
+
+    fn under_roots(&self, key: &str) -> bool {
+        self.roots.values().any(|root| {
+            key.strip_prefix(root.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(MAIN_SEPARATOR) || root.ends_with(MAIN_SEPARATOR))
+        })
+    }
+
+    fn refresh(&mut self) {
+        let Some(source) = self.source.as_mut() else {
+            return;
+        };
+        let now = Instant::now();
+        for (path, pid) in source.drain() {
+            let key = Self::key(&path);
+            if self.under_roots(&key) {
+                self.recent.insert(key, (pid, now));
+            }
+        }
+        let retention = Duration::from_secs(RETENTION_SECS);
+        self.recent.retain(|_, (_, seen)| seen.elapsed() < retention);
+    }
+
+    #[cfg(target_os = "linux")]
+    fn open_holder(path: &Path) -> Option<u32> {
+        open_holders(path).first().copied()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn open_holder(_path: &Path) -> Option<u32> {
+        None
+    }
+
+    // The process that last changed the file, if the kernel said so recently
+    pub fn process_of(&mut self, path: &Path) -> Option<u32> {
+        if !self.enabled {
+            return None;
+        }
+        self.refresh();
+        let path_str = path.to_string_lossy();
+        [Self::key(&path_str), Self::canonical_key(&path_str)]
+            .iter()
+            .find_map(|key| self.recent.get(key).map(|(pid, _)| *pid))
+            .or_else(|| Self::open_holder(path))
+    }
+}
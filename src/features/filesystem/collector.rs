@@ -1,119 +1,339 @@
 use crate::shared::traits::{AsyncDataCollector, DataCollector};
 use crate::shared::error::CollectionError;
+use crate::shared::process_resolver::ProcessResolver;
+use crate::shared::user_resolver::UserResolver;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::paths::expand_path;
+use crate::shared::matchers::ExtensionSet;
+use crate::shared::maintenance::MaintenanceMode;
+use crate::shared::baseline::Baseline;
 use crate::features::filesystem::models::{FileEvent, FileEventType, FileEventBuilder};
+use crate::features::filesystem::attribution::FileAttribution;
+use crate::features::filesystem::yara_scan::{YaraConfig, YaraMatch, YaraScanner};
+use crate::features::detection::{Alert, AlertBuilder, DetectionSource};
+use crate::shared::traits::Severity;
+use crate::utils::{config_generation, load_config_root};
 use log::{info, warn, debug};
-use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Config as NotifyConfig, Event, EventKind, PollWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use tokio::sync::mpsc::{channel, Receiver};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use chrono::Utc;
 use sha2::{Sha256, Digest};
 use std::fs;
 use serde::{Deserialize, Serialize};
-use sysinfo::{System, Pid, ProcessRefreshKind, ProcessesToUpdate};
 use uuid::Uuid;
 use std::sync::Arc;
 use tokio::runtime::Handle;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct MonitorConfig {
-    paths: Vec<String>,
-    settings: MonitorSettings,
+// How long the old name of a rename waits for its new name; the halves arrive back to back
+const RENAME_PAIR_SECS: u64 = 2;
+
+// The top-level `paths`, `settings` and `yara` of the monitor config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileSystemConfig {
+    pub paths: Vec<String>,
+    pub settings: FileSystemSettings,
+    // Scanning of created and modified files
+    pub yara: YaraConfig,
+}
+
+// Built-in profile for a bare binary: where droppers and persistence land on each OS
+impl Default for FileSystemConfig {
+    fn default() -> Self {
+        let paths: &[&str] = if cfg!(windows) {
+            &[
+                "${WINDIR}/System32",
+                "${WINDIR}/Temp",
+                "${USERPROFILE}/Downloads",
+                "${USERPROFILE}/Desktop",
+                "${USERPROFILE}/AppData/Local/Temp",
+                "${USERPROFILE}/AppData/Roaming/Microsoft/Windows/Start Menu/Programs/Startup",
+                "${PROGRAMDATA}/Microsoft/Windows/Start Menu/Programs/Startup",
+            ]
+        } else if cfg!(target_os = "macos") {
+            // Watched through FSEvents; /tmp is a link to /private/tmp
+            &[
+                "/private/tmp",
+                "/Applications",
+                "/usr/local/bin",
+                "/Library/LaunchAgents",
+                "/Library/LaunchDaemons",
+                "${HOME}/Library/LaunchAgents",
+                "${HOME}/Downloads",
+            ]
+        } else {
+            &[
+                "/tmp",
+                "/var/tmp",
+                "/dev/shm",
+                "/etc/cron.d",
+                "/usr/local/bin",
+                "${HOME}/Downloads",
+            ]
+        };
+        Self {
+            paths: paths.iter().map(|path| path.to_string()).collect(),
+            settings: FileSystemSettings::default(),
+            yara: YaraConfig::default(),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct MonitorSettings {
-    recursive: bool,
-    extensions: Vec<String>,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileSystemSettings {
+    pub recursive: bool,
+    // Matched without regard to case, so .EXE counts as .exe
+    pub extensions: Vec<String>,
+    // How often paths are expanded again so ${USERPROFILE} paths of users who logged on
+    // since the last pass, and new wildcard matches, are watched too
+    pub profile_refresh_secs: u64,
+    // Files whose last hash is remembered across restarts; a write that leaves the content
+    // unchanged is not reported. 0 disables.
+    pub max_known_hashes: usize,
+    // Files larger than this are reported without a hash; None hashes files of any size
+    pub max_hash_bytes: Option<u64>,
+    // Ask the kernel which process made each change (fanotify on Linux, ETW on Windows);
+    // both need administrator rights
+    pub process_attribution: bool,
+}
+
+impl Default for FileSystemSettings {
+    fn default() -> Self {
+        Self {
+            recursive: true,
+            // Executables, scripts and installers
+            extensions: [
+                ".exe", ".dll", ".sys", ".scr", ".com", ".msi", ".bat", ".cmd", ".ps1", ".psm1", ".vbs",
+                ".vbe", ".js", ".jse", ".wsf", ".hta", ".lnk", ".jar", ".sh", ".py", ".so", ".dylib", ".command",
+                ".pkg", ".plist",
+            ]
+            .iter()
+            .map(|extension| extension.to_string())
+            .collect(),
+            profile_refresh_secs: 60,
+            max_known_hashes: 50_000,
+            max_hash_bytes: None,
+            process_attribution: true,
+        }
+    }
+}
+
+impl FileSystemConfig {
+    pub fn load() -> Self {
+        load_config_root()
+    }
 }
 
 pub struct FileSystemCollector {
     event_receiver: Receiver<notify::Result<Event>>,
-    config: MonitorConfig,
-    sys: System,
-    _watcher: RecommendedWatcher,
-    hostname: String,
+    config: FileSystemConfig,
+    // settings.extensions, compiled whenever the config is
+    extensions: ExtensionSet,
+    // Installed configuration the settings were last read from, see follow_config
+    config_generation: u64,
+    resolver: Arc<ProcessResolver>,
+    users: Arc<UserResolver>,
+    // The OS notification backend, or polling when it cannot be started
+    watcher: Box<dyn Watcher + Send + Sync>,
+    // Expanded paths currently watched, and paths already reported as missing
+    watched: HashSet<String>,
+    missing: HashSet<String>,
+    last_path_refresh: Instant,
+    host: Arc<HostIdentityService>,
+    yara: Option<YaraScanner>,
+    // Alerts for YARA matches, picked up with take_alerts
+    alerts: Vec<Alert>,
+    // Last hash of every reported file, saved to the baseline store
+    known_hashes: HashMap<String, String>,
+    // Id of the Created event of files created since startup, which a rename links back to
+    created_events: HashMap<String, String>,
+    known_hashes_changed: bool,
+    last_state_save: Instant,
+    // Old names of renames waiting for their new name, with the backend's rename cookie
+    pending_renames: VecDeque<(Option<usize>, PathBuf, Instant)>,
+    // Cookies of renames already paired, so inotify's trailing combined event is not
+    // reported a second time
+    paired_renames: HashMap<usize, Instant>,
+    attribution: FileAttribution,
 }
 
+// How often changed file hashes are written to the baseline store
+const STATE_SAVE_SECS: u64 = 60;
+const KNOWN_HASHES_BASELINE: &str = "file_hashes";
+// Files whose Created event id is remembered; creations beyond it are not linked
+const MAX_CREATED_EVENTS: usize = 10_000;
+// How often the polling fallback rescans the watched paths
+const POLL_FALLBACK_SECS: u64 = 30;
+
 impl FileSystemCollector {
-    fn normalize_path(path: &str) -> String {
-        path.replace('\\', "/")
+    // Watches expanded paths that appeared since the last pass and forgets removed ones,
+    // so a profile deleted and created again is watched again
+    fn watch_new_paths(&mut self) {
+        self.last_path_refresh = Instant::now();
+        let recursive_mode = if self.config.settings.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+
+        let gone: Vec<String> = self.watched.iter().filter(|path| !Path::new(path).is_dir()).cloned().collect();
+        for path in gone {
+            let _ = self.watcher.unwatch(Path::new(&path));
+            self.attribution.unwatch(&path);
+            self.watched.remove(&path);
+            info!("Stopped watching removed path: {}", path);
+        }
+
+        let paths: Vec<String> = self.config.paths.iter().flat_map(|path| expand_path(path)).collect();
+        for path in paths {
+            if self.watched.contains(&path) {
+                continue;
+            }
+            match fs::metadata(&path) {
+                Ok(metadata) if metadata.is_dir() => match self.watcher.watch(Path::new(&path), recursive_mode) {
+                    Ok(_) => {
+                        info!("Successfully watching path: {}", path);
+                        self.attribution.watch(&path, self.config.settings.recursive);
+                        self.missing.remove(&path);
+                        self.watched.insert(path);
+                    }
+                    Err(e) => warn!("Failed to watch path {}: {}", path, e),
+                },
+                // Reported once; a user's Downloads only exists after their first logon
+                Ok(_) if self.missing.insert(path.clone()) => warn!("Path is not a directory: {}", path),
+                Err(_) if self.missing.insert(path.clone()) => {
+                    warn!("Path does not exist or is not accessible: {}", path)
+                }
+                _ => {}
+            }
+        }
     }
 
-    fn expand_env_vars(path: &str) -> String {
-        let mut result = path.to_string();
-        if path.contains("${USERPROFILE}") {
-            if let Ok(user_profile) = std::env::var("USERPROFILE") {
-                result = path.replace("${USERPROFILE}", &Self::normalize_path(&user_profile));
+    // Switches to new paths and settings: paths no longer configured stop being watched, new
+    // ones are watched, and extensions apply from the next event. A change of
+    // process_attribution waits for a restart.
+    pub fn apply_config(&mut self, config: FileSystemConfig) {
+        let recursive_changed = config.settings.recursive != self.config.settings.recursive;
+        if config.settings.process_attribution != self.config.settings.process_attribution {
+            info!("settings.process_attribution takes effect after a restart");
+        }
+        if config.yara != self.config.yara {
+            info!("yara takes effect after a restart");
+        }
+        self.extensions = ExtensionSet::new(&config.settings.extensions);
+        self.config = config;
+
+        let configured: HashSet<String> = self.config.paths.iter().flat_map(|path| expand_path(path)).collect();
+        let dropped: Vec<String> = self
+            .watched
+            .iter()
+            .filter(|path| recursive_changed || !configured.contains(*path))
+            .cloned()
+            .collect();
+        for path in dropped {
+            let _ = self.watcher.unwatch(Path::new(&path));
+            self.attribution.unwatch(&path);
+            self.watched.remove(&path);
+            if !configured.contains(&path) {
+                info!("Stopped watching path no longer configured: {}", path);
             }
         }
-        Self::normalize_path(&result)
+        self.missing.retain(|path| configured.contains(path));
+        self.watch_new_paths();
+    }
+
+    // Applies the filesystem section of a configuration installed since the last run
+    fn follow_config(&mut self) {
+        let generation = config_generation();
+        if generation == self.config_generation {
+            return;
+        }
+        self.config_generation = generation;
+        let config = FileSystemConfig::load();
+        if config != self.config {
+            info!("Applying the reloaded filesystem configuration");
+            self.apply_config(config);
+        }
+    }
+
+    fn refresh_paths(&mut self) {
+        let period = Duration::from_secs(self.config.settings.profile_refresh_secs.max(1));
+        if self.last_path_refresh.elapsed() >= period {
+            self.watch_new_paths();
+        }
     }
 
     pub fn new() -> Result<Self, CollectionError> {
-        let config_path = "config/monitor.yaml";
-        info!("Reading config from: {}", config_path);
-        let config_content = fs::read_to_string(config_path)
-            .map_err(|e| CollectionError::Parse(format!("Failed to read config: {}", e)))?;
-        
-        let mut config: MonitorConfig = serde_yaml::from_str(&config_content)
-            .map_err(|e| CollectionError::Parse(format!("Failed to parse config: {}", e)))?;
-        
+        Self::with_config(FileSystemConfig::load())
+    }
+
+    pub fn with_config(config: FileSystemConfig) -> Result<Self, CollectionError> {
         info!("Loaded config: {:?}", config);
         
-        for path in &mut config.paths {
-            let expanded_path = Self::expand_env_vars(path);
-            info!("Expanded path: {} -> {}", path, expanded_path);
-            *path = expanded_path;
-        }
-        
         let (tx, rx) = channel(100);
-        let tx_clone = tx.clone();
-        let runtime = Arc::new(tokio::runtime::Runtime::new().unwrap());
-        
-        let mut watcher = notify::recommended_watcher(move |res| {
-            let tx = tx_clone.clone();
-            let handle = runtime.handle().clone();
-            handle.spawn(async move {
-                if let Err(e) = tx.send(res).await {
-                    warn!("Failed to send event: {}", e);
-                }
-            });
-        })
-        .map_err(|e| CollectionError::SystemApi(e.to_string()))?;
 
-        for path in &config.paths {
-            let recursive_mode = if config.settings.recursive {
-                RecursiveMode::Recursive
-            } else {
-                RecursiveMode::NonRecursive
-            };
-            
-            info!("Attempting to watch path: {}", path);
-            if let Ok(metadata) = fs::metadata(path) {
-                if metadata.is_dir() {
-                    match watcher.watch(Path::new(path), recursive_mode) {
-                        Ok(_) => info!("Successfully watching path: {}", path),
-                        Err(e) => warn!("Failed to watch path {}: {}", path, e),
-                    }
-                } else {
-                    warn!("Path is not a directory: {}", path);
+        // Sent from the watcher thread in order, so both halves of a rename arrive in sequence
+        let handler = move |res| {
+            if let Err(e) = tx.blocking_send(res) {
+                warn!("Failed to send event: {}", e);
+            }
+        };
+        // Out of inotify instances or a backend the OS refuses: rescanning is slower and
+        // misses short-lived files, but still sees what lands and stays
+        let watcher: Box<dyn Watcher + Send + Sync> = match notify::recommended_watcher(handler.clone()) {
+            Ok(watcher) => Box::new(watcher),
+            Err(e) => {
+                warn!("File change notifications unavailable ({}), polling every {}s instead", e, POLL_FALLBACK_SECS);
+                let config = NotifyConfig::default().with_poll_interval(Duration::from_secs(POLL_FALLBACK_SECS));
+                Box::new(PollWatcher::new(handler, config).map_err(|e| CollectionError::SystemApi(e.to_string()))?)
+            }
+        };
+
+        let yara = if config.yara.enabled {
+            match YaraScanner::new(config.yara.clone()) {
+                Ok(scanner) => Some(scanner),
+                Err(e) => {
+                    warn!("YARA scanning unavailable: {}", e);
+                    None
                 }
-            } else {
-                warn!("Path does not exist or is not accessible: {}", path);
             }
-        }
+        } else {
+            None
+        };
 
-        Ok(Self {
+        let attribution = FileAttribution::new(config.settings.process_attribution);
+        let mut collector = Self {
             event_receiver: rx,
+            extensions: ExtensionSet::new(&config.settings.extensions),
             config,
-            sys: System::new(),
-            _watcher: watcher,
-            hostname: whoami::hostname(),
-        })
+            config_generation: config_generation(),
+            resolver: ProcessResolver::global(),
+            users: UserResolver::global(),
+            watcher,
+            watched: HashSet::new(),
+            missing: HashSet::new(),
+            last_path_refresh: Instant::now(),
+            host: HostIdentityService::global(),
+            yara,
+            alerts: Vec::new(),
+            known_hashes: Baseline::global().load(KNOWN_HASHES_BASELINE).unwrap_or_default(),
+            known_hashes_changed: false,
+            created_events: HashMap::new(),
+            last_state_save: Instant::now(),
+            pending_renames: VecDeque::new(),
+            paired_renames: HashMap::new(),
+            attribution,
+        };
+        collector.watch_new_paths();
+        Ok(collector)
     }
 
-    fn calculate_file_hash(path: &Path) -> Option<String> {
+    pub fn calculate_file_hash(path: &Path) -> Option<String> {
         if let Ok(mut file) = fs::File::open(path) {
             let mut hasher = Sha256::new();
             if std::io::copy(&mut file, &mut hasher).is_ok() {
@@ -140,77 +360,259 @@ impl FileSystemCollector {
     }
 
     fn should_monitor_file(&self, path: &Path) -> bool {
-        if let Some(extension) = path.extension() {
-            if let Some(ext_str) = extension.to_str() {
-                let ext = format!(".{}", ext_str);
-                let should_monitor = self.config.settings.extensions.contains(&ext);
-                debug!("Checking file {}: extension {} -> monitor: {}", 
-                    path.display(), ext, should_monitor);
-                return should_monitor;
+        let should_monitor = self.extensions.contains(path);
+        debug!("Checking file {} -> monitor: {}", path.display(), should_monitor);
+        should_monitor
+    }
+
+    pub fn take_alerts(&mut self) -> Vec<Alert> {
+        std::mem::take(&mut self.alerts)
+    }
+
+    fn yara_alert(&self, event: &FileEvent, matched: &YaraMatch) -> Option<Alert> {
+        // The scanned file, which is the new name after a rename
+        let path = event.new_path.as_ref().unwrap_or(&event.path);
+        let mut matched_fields = std::collections::BTreeMap::new();
+        matched_fields.insert(String::from("path"), path.clone());
+        matched_fields.insert(String::from("yara_rule"), format!("{}:{}", matched.namespace, matched.rule));
+        if let Some(hash) = event.hash.as_ref().filter(|hash| !hash.is_empty()) {
+            matched_fields.insert(String::from("hash"), hash.clone());
+        }
+        let mut tags = vec![String::from("yara")];
+        tags.extend(matched.tags.iter().cloned());
+
+        AlertBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("yara"))
+            .rule_id(format!("yara:{}:{}", matched.namespace, matched.rule))
+            .rule_name(matched.rule.clone())
+            .description(Some(format!("YARA rule {} matched {}", matched.rule, path)))
+            .severity(Severity::High)
+            .detection_source(DetectionSource::File)
+            .event_id(Some(event.id.clone()))
+            .matched_fields(matched_fields)
+            .tags(tags)
+            .build()
+            .map_err(|e| warn!("Dropping YARA alert for {}: {}", path, e))
+            .ok()
+    }
+
+    fn get_process_info(&self, pid: u32) -> Option<(u32, String)> {
+        self.resolver
+            .resolve(pid)
+            .map(|process| (process.pid, process.name))
+    }
+
+    // The old name of a rename with this cookie, or the latest one without a cookie on
+    // backends that report none
+    fn take_pending_rename(&mut self, tracker: Option<usize>) -> Option<PathBuf> {
+        let position = match tracker {
+            Some(_) => self.pending_renames.iter().position(|(pending, _, _)| *pending == tracker),
+            None => self.pending_renames.iter().rposition(|(pending, _, _)| pending.is_none()),
+        }?;
+        self.pending_renames.remove(position).map(|(_, path, _)| path)
+    }
+
+    // Old names whose new name never came were moved out of the watched paths, so the file
+    // is gone from here like a delete
+    fn expire_pending_renames(&mut self) {
+        let window = Duration::from_secs(RENAME_PAIR_SECS);
+        while let Some((_, path, _)) = self.pending_renames.front().filter(|(_, _, seen)| seen.elapsed() >= window) {
+            debug!("{} was moved out of the watched paths", path.display());
+            let path_str = path.to_string_lossy().to_string();
+            if self.known_hashes.remove(&path_str).is_some() {
+                self.known_hashes_changed = true;
             }
+            self.created_events.remove(&path_str);
+            self.pending_renames.pop_front();
         }
-        false
+        self.paired_renames.retain(|_, paired| paired.elapsed() < window);
     }
 
-    fn get_process_info(&mut self, pid: u32) -> Option<(u32, String)> {
-        let pid = Pid::from_u32(pid);
-        self.sys.refresh_processes_specifics(
-            ProcessesToUpdate::All,
-            true,
-            ProcessRefreshKind::everything(),
-        );
-        
-        self.sys.process(pid).map(|process| {
-            (pid.as_u32(), process.name().to_string_lossy().into_owned())
-        })
+    // The event type with the path it applies to and, for a rename, the new path; None for
+    // events not reported or the first half of a rename
+    fn classify(&mut self, event: Event) -> Option<(FileEventType, PathBuf, Option<PathBuf>)> {
+        let tracker = event.tracker();
+        let mut paths = event.paths.into_iter();
+        let path = paths.next()?;
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                self.pending_renames.push_back((tracker, path, Instant::now()));
+                None
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => match self.take_pending_rename(tracker) {
+                Some(from) => {
+                    if let Some(tracker) = tracker {
+                        self.paired_renames.insert(tracker, Instant::now());
+                    }
+                    Some((FileEventType::Renamed, from, Some(path)))
+                }
+                // Moved in from outside the watched paths
+                None => Some((FileEventType::Created, path, None)),
+            },
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                if tracker.is_some_and(|tracker| self.paired_renames.remove(&tracker).is_some()) {
+                    return None;
+                }
+                self.take_pending_rename(tracker);
+                let to = paths.next()?;
+                Some((FileEventType::Renamed, path, Some(to)))
+            }
+            EventKind::Create(_) => Some((FileEventType::Created, path, None)),
+            EventKind::Modify(_) => Some((FileEventType::Modified, path, None)),
+            EventKind::Remove(_) => Some((FileEventType::Deleted, path, None)),
+            _ => None,
+        }
     }
 
     async fn process_event(&mut self, event: Event) -> Option<FileEvent> {
-        let path = event.paths.first()?;
-        
-        debug!("Processing event for path: {}", path.display());
-        
-        if !path.is_dir() && !self.should_monitor_file(path) {
-            debug!("Skipping file: {}", path.display());
+        let (event_type, path, new_path) = self.classify(event)?;
+        // A renamed file is described by what it is now
+        let current = new_path.as_deref().unwrap_or(&path);
+
+        debug!("Processing event for path: {}", current.display());
+
+        // A rename counts when either name is monitored, e.g. payload.txt renamed to payload.exe
+        let monitored = current.is_dir()
+            || self.should_monitor_file(&path)
+            || new_path.as_deref().is_some_and(|new_path| self.should_monitor_file(new_path));
+        if !monitored {
+            debug!("Skipping file: {}", current.display());
             return None;
         }
-        
-        let event_type = match event.kind {
-            EventKind::Create(_) => FileEventType::Created,
-            EventKind::Modify(_) => FileEventType::Modified,
-            EventKind::Remove(_) => FileEventType::Deleted,
-            _ => return None,
-        };
 
-        debug!("Event type: {:?} for path: {}", event_type, path.display());
-
-        let (file_type, file_size) = self.get_file_info(path)?;
-        let file_hash = Self::calculate_file_hash(path);
-        let (process_id, process_name) = self.get_process_info(std::process::id())
-            .unwrap_or((0, "unknown".to_string()));
+        debug!("Event type: {:?} for path: {}", event_type, current.display());
 
         let path_str = path.to_string_lossy().to_string();
+        let new_path_str = new_path.as_deref().map(|new_path| new_path.to_string_lossy().to_string());
+        let mut related_ids = Vec::new();
+        match (&event_type, &new_path_str) {
+            (FileEventType::Deleted, _) => {
+                self.created_events.remove(&path_str);
+                if self.known_hashes.remove(&path_str).is_some() {
+                    self.known_hashes_changed = true;
+                }
+            }
+            // The content moves with the name, and so does the creation a rename links to
+            (FileEventType::Renamed, Some(new_path_str)) => {
+                if let Some(hash) = self.known_hashes.remove(&path_str) {
+                    self.known_hashes.insert(new_path_str.clone(), hash);
+                    self.known_hashes_changed = true;
+                }
+                if let Some(created) = self.created_events.remove(&path_str) {
+                    related_ids.push(created.clone());
+                    self.created_events.insert(new_path_str.clone(), created);
+                }
+            }
+            _ => {}
+        }
+
+        let (file_type, file_size) = self.get_file_info(current)?;
+        let file_hash = match self.config.settings.max_hash_bytes {
+            Some(max) if file_size > max => None,
+            _ => Self::calculate_file_hash(current),
+        };
+        let current_str = new_path_str.as_deref().unwrap_or(&path_str).to_string();
+        if file_type == "file"
+            && !matches!(event_type, FileEventType::Renamed)
+            && !self.remember_hash(&current_str, file_hash.as_deref())
+        {
+            debug!("Skipping unchanged content of {}", current_str);
+            return None;
+        }
+        // The new name is what the writer used last; a rename is reported under either
+        let attributed = [new_path.as_deref(), Some(path.as_path())]
+            .into_iter()
+            .flatten()
+            .find_map(|changed| self.attribution.process_of(changed));
+        let (process_id, process_name) = match attributed {
+            Some(pid) => self.get_process_info(pid).unwrap_or((pid, "unknown".to_string())),
+            None => (0, "unknown".to_string()),
+        };
+
+        let user = self.users
+            .display_process_owner(process_id)
+            .unwrap_or_else(|| "unknown".to_string());
 
-        let event = FileEventBuilder::new()
+        // Only content that just appeared, changed or took a new name is worth scanning, and
+        // patch windows rewrite too much of the disk to scan it all
+        let yara_matches = match (&self.yara, &event_type) {
+            (Some(scanner), FileEventType::Created | FileEventType::Modified | FileEventType::Renamed)
+                if file_type == "file" && !MaintenanceMode::global().skips_yara() =>
+            {
+                scanner.scan(current)
+            }
+            _ => Vec::new(),
+        };
+
+        let mut builder = FileEventBuilder::new()
             .id(Uuid::new_v4().to_string())
             .timestamp(Utc::now())
-            .source(self.hostname.clone())
+            .source(self.host.name())
             .category(String::from("filesystem"))
-            .event_type(event_type)
+            .related_ids(related_ids)
+            .event_type(event_type.clone())
             .path(path_str)
             .file_type(file_type)
             .file_size(file_size)
             .hash(file_hash.unwrap_or_default())
             .process_id(process_id)
             .process_name(process_name)
-            .build()
-            .ok()?;
+            .user(user)
+            .yara_matches(yara_matches.iter().map(|matched| format!("{}:{}", matched.namespace, matched.rule)).collect());
+        if let Some(new_path_str) = new_path_str {
+            builder = builder.new_path(new_path_str);
+        }
+        let event = builder.build().ok()?;
+        let room = self.created_events.len() < MAX_CREATED_EVENTS || self.created_events.contains_key(&current_str);
+        if matches!(event_type, FileEventType::Created) && room {
+            self.created_events.insert(current_str.clone(), event.id.clone());
+        }
+
+        for matched in &yara_matches {
+            warn!("YARA rule {} matched {}", matched.rule, current_str);
+            if let Some(alert) = self.yara_alert(&event, matched) {
+                self.alerts.push(alert);
+            }
+        }
 
         Some(event)
     }
 
+    // False when the file still has the content last reported, e.g. a write replayed after
+    // a restart or a touch that only changed timestamps
+    fn remember_hash(&mut self, path: &str, hash: Option<&str>) -> bool {
+        let (Some(hash), true) = (hash, self.config.settings.max_known_hashes > 0) else {
+            return true;
+        };
+        if self.known_hashes.get(path).is_some_and(|known| known == hash) {
+            return false;
+        }
+        if self.known_hashes.len() < self.config.settings.max_known_hashes || self.known_hashes.contains_key(path) {
+            self.known_hashes.insert(path.to_string(), hash.to_string());
+            self.known_hashes_changed = true;
+        }
+        true
+    }
+
+    fn save_state(&mut self) {
+        if self.known_hashes_changed {
+            Baseline::global().save(KNOWN_HASHES_BASELINE, &self.known_hashes);
+            self.known_hashes_changed = false;
+        }
+        self.last_state_save = Instant::now();
+    }
+
+    fn save_state_periodically(&mut self) {
+        if self.last_state_save.elapsed() >= Duration::from_secs(STATE_SAVE_SECS) {
+            self.save_state();
+        }
+    }
+
     fn internal_validate(&self) -> Result<(), CollectionError> {
-        for path in &self.config.paths {
+        for path in &self.watched {
             if !Path::new(path).exists() {
                 return Err(CollectionError::SystemApi(
                     format!("Monitored path does not exist: {}", path)
@@ -223,6 +625,8 @@ impl FileSystemCollector {
 
 impl DataCollector<Vec<FileEvent>> for FileSystemCollector {
     fn collect(&mut self) -> Result<Vec<FileEvent>, CollectionError> {
+        self.follow_config();
+        self.refresh_paths();
         let mut events = Vec::new();
         
         while let Ok(Ok(event)) = self.event_receiver.try_recv() {
@@ -232,6 +636,8 @@ impl DataCollector<Vec<FileEvent>> for FileSystemCollector {
             }
         }
 
+        self.expire_pending_renames();
+        self.save_state_periodically();
         info!("Collected {} filesystem events", events.len());
         Ok(events)
     }
@@ -248,6 +654,8 @@ impl DataCollector<Vec<FileEvent>> for FileSystemCollector {
 #[async_trait::async_trait]
 impl AsyncDataCollector<Vec<FileEvent>> for FileSystemCollector {
     async fn collect(&mut self) -> Result<Vec<FileEvent>, CollectionError> {
+        self.follow_config();
+        self.refresh_paths();
         let mut events = Vec::new();
         
         while let Ok(Ok(event)) = self.event_receiver.try_recv() {
@@ -257,6 +665,8 @@ impl AsyncDataCollector<Vec<FileEvent>> for FileSystemCollector {
             }
         }
 
+        self.expire_pending_renames();
+        self.save_state_periodically();
         info!("Collected {} filesystem events", events.len());
         Ok(events)
     }
@@ -270,6 +680,12 @@ impl AsyncDataCollector<Vec<FileEvent>> for FileSystemCollector {
     }
 }
 
+impl Drop for FileSystemCollector {
+    fn drop(&mut self) {
+        self.save_state();
+    }
+}
+
 impl Default for FileSystemCollector {
     fn default() -> Self {
         Self::new().expect("Failed to create default FileSystemCollector")
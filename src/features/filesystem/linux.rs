@@ -0,0 +1,142 @@
+// fanotify reports the pid of the writer along with an open descriptor of the file written.
+// Needs CAP_SYS_ADMIN. Without FAN_REPORT_FID only writes are reported; a file created empty
+// or renamed is attributed to a process still holding it open, if any.
+use log::{debug, warn};
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const WRITES: u64 = libc::FAN_MODIFY | libc::FAN_CLOSE_WRITE;
+
+const METADATA: usize = std::mem::size_of::<libc::fanotify_event_metadata>();
+
+pub struct AttributionSource {
+    fd: Arc<OwnedFd>,
+    writes: Mutex<Receiver<(String, u32)>>,
+}
+
+impl AttributionSource {
+    pub fn start(max_pending: usize) -> io::Result<Self> {
+        let fd = unsafe {
+            libc::fanotify_init(
+                libc::FAN_CLASS_NOTIF | libc::FAN_CLOEXEC,
+                (libc::O_RDONLY | libc::O_LARGEFILE | libc::O_CLOEXEC) as u32,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = Arc::new(unsafe { OwnedFd::from_raw_fd(fd) });
+
+        let (tx, rx) = sync_channel(max_pending);
+        let reader = fd.clone();
+        let own_pid = std::process::id() as i32;
+        thread::Builder::new()
+            .name(String::from("file-fanotify"))
+            .spawn(move || {
+                let mut buffer = vec![0u8; 64 * 1024];
+                loop {
+                    let read = unsafe {
+                        libc::read(reader.as_raw_fd(), buffer.as_mut_ptr() as *mut libc::c_void, buffer.len())
+                    };
+                    if read < 0 {
+                        let error = io::Error::last_os_error();
+                        if error.kind() == io::ErrorKind::Interrupted {
+                            continue;
+                        }
+                        warn!("fanotify read failed, stopping file attribution: {}", error);
+                        return;
+                    }
+
+                    let read = read as usize;
+                    let mut offset = 0;
+                    while offset + METADATA <= read {
+                        let event = unsafe {
+                            std::ptr::read_unaligned(buffer[offset..].as_ptr() as *const libc::fanotify_event_metadata)
+                        };
+                        if event.vers != libc::FANOTIFY_METADATA_VERSION || event.event_len == 0 {
+                            warn!("Unexpected fanotify metadata version {}, stopping file attribution", event.vers);
+                            return;
+                        }
+                        offset += event.event_len as usize;
+                        if event.fd == libc::FAN_NOFD {
+                            continue;
+                        }
+                        // The descriptor must be closed whatever happens to the event
+                        let file = unsafe { OwnedFd::from_raw_fd(event.fd) };
+                        if event.pid == own_pid {
+                            continue;
+                        }
+                        let Ok(path) = fs::read_link(format!("/proc/self/fd/{}", file.as_raw_fd())) else {
+                            continue;
+                        };
+                        // A full queue drops the write rather than stalling the kernel's
+                        if tx.try_send((path.to_string_lossy().into_owned(), event.pid as u32)).is_err() {
+                            debug!("File attribution queue full, dropping write to {}", path.display());
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self { fd, writes: Mutex::new(rx) })
+    }
+
+    // Marks the whole mount for a recursive watch, since fanotify has no recursive directory
+    // marks; writes outside the watched paths are filtered out when drained
+    pub fn watch(&mut self, root: &Path, recursive: bool) -> io::Result<()> {
+        let path = CString::new(root.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let (flags, mask) = if recursive {
+            (libc::FAN_MARK_ADD | libc::FAN_MARK_MOUNT, WRITES)
+        } else {
+            (libc::FAN_MARK_ADD, WRITES | libc::FAN_EVENT_ON_CHILD)
+        };
+        let marked = unsafe { libc::fanotify_mark(self.fd.as_raw_fd(), flags, mask, libc::AT_FDCWD, path.as_ptr()) };
+        if marked < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    // (path, pid) of every write since the last call
+    pub fn drain(&mut self) -> Vec<(String, u32)> {
+        match self.writes.lock() {
+            Ok(writes) => writes.try_iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+// Processes other than this one holding the file open right now
+pub fn open_holders(path: &Path) -> Vec<u32> {
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    let own_pid = std::process::id();
+    let mut holders = Vec::new();
+    for process in processes.flatten() {
+        let Some(pid) = process.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        if pid == own_pid {
+            continue;
+        }
+        let Ok(descriptors) = fs::read_dir(process.path().join("fd")) else {
+            continue;
+        };
+        if descriptors
+            .flatten()
+            .any(|descriptor| fs::read_link(descriptor.path()).is_ok_and(|target| target == path))
+        {
+            holders.push(pid);
+        }
+    }
+    holders
+}
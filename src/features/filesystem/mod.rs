@@ -1,5 +1,12 @@
 pub mod models;
 pub mod collector;
+pub mod yara_scan;
+mod attribution;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(windows)]
+mod win32;
 
 pub use models::{FileEvent, FileEventType, FileEventBuilder};
-pub use collector::FileSystemCollector;
+pub use collector::{FileSystemCollector, FileSystemConfig, FileSystemSettings};
+pub use yara_scan::{YaraConfig, YaraMatch, YaraScanner};
@@ -18,6 +18,9 @@ pub struct FileEvent {
     pub timestamp: DateTime<Utc>,
     pub source: String,
     pub category: String,
+    // For a rename, the id of the event that reported the file's creation, when it was seen
+    #[serde(default)]
+    pub related_ids: Vec<String>,
     pub event_type: FileEventType,
     pub path: String,
     pub new_path: Option<String>,
@@ -27,6 +30,10 @@ pub struct FileEvent {
     pub hash: Option<String>,
     pub process_id: Option<u32>,
     pub process_name: Option<String>,
+    pub user: Option<String>,
+    // YARA rules the file matched, as namespace:rule
+    #[serde(default)]
+    pub yara_matches: Vec<String>,
 }
 
 impl Event for FileEvent {
@@ -100,6 +107,7 @@ pub struct FileEventBuilder {
     timestamp: Option<DateTime<Utc>>,
     source: Option<String>,
     category: Option<String>,
+    related_ids: Vec<String>,
     event_type: Option<FileEventType>,
     path: Option<String>,
     new_path: Option<String>,
@@ -109,6 +117,8 @@ pub struct FileEventBuilder {
     hash: Option<String>,
     process_id: Option<u32>,
     process_name: Option<String>,
+    user: Option<String>,
+    yara_matches: Vec<String>,
 }
 
 impl FileEventBuilder {
@@ -136,6 +146,11 @@ impl FileEventBuilder {
         self
     }
 
+    pub fn related_ids(mut self, related_ids: Vec<String>) -> Self {
+        self.related_ids = related_ids;
+        self
+    }
+
     pub fn event_type(mut self, event_type: FileEventType) -> Self {
         self.event_type = Some(event_type);
         self
@@ -181,12 +196,23 @@ impl FileEventBuilder {
         self
     }
 
+    pub fn user(mut self, user: String) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    pub fn yara_matches(mut self, yara_matches: Vec<String>) -> Self {
+        self.yara_matches = yara_matches;
+        self
+    }
+
     pub fn build(self) -> Result<FileEvent, String> {
         let event = FileEvent {
             id: self.id.ok_or("id is required")?,
             timestamp: self.timestamp.ok_or("timestamp is required")?,
             source: self.source.ok_or("source is required")?,
             category: self.category.ok_or("category is required")?,
+            related_ids: self.related_ids,
             event_type: self.event_type.ok_or("event_type is required")?,
             path: self.path.ok_or("path is required")?,
             new_path: self.new_path,
@@ -196,6 +222,8 @@ impl FileEventBuilder {
             hash: self.hash,
             process_id: self.process_id,
             process_name: self.process_name,
+            user: self.user,
+            yara_matches: self.yara_matches,
         };
 
         event.validate()?;
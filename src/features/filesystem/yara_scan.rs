@@ -0,0 +1,132 @@
+use crate::shared::error::CollectionError;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct YaraConfig {
+    pub enabled: bool,
+    // Directory searched recursively for .yar / .yara rule files
+    pub rules_directory: String,
+    // Larger files are not scanned
+    pub max_file_size_bytes: u64,
+    pub timeout_secs: u16,
+}
+
+impl Default for YaraConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rules_directory: String::from("rules/yara"),
+            max_file_size_bytes: 32 * 1024 * 1024,
+            timeout_secs: 10,
+        }
+    }
+}
+
+impl YaraConfig {
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.enabled {
+            if self.rules_directory.trim().is_empty() {
+                problems.push(String::from("yara.rules_directory is empty"));
+            }
+            if self.timeout_secs == 0 {
+                problems.push(String::from("yara.timeout_secs must not be 0"));
+            }
+        }
+        problems
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct YaraMatch {
+    pub rule: String,
+    pub namespace: String,
+    pub tags: Vec<String>,
+}
+
+// Compiled YARA rules for scanning created and modified files. Needs the `yara` cargo
+// feature, which links libyara; without it the scanner reports itself unavailable.
+pub struct YaraScanner {
+    config: YaraConfig,
+    #[cfg(feature = "yara")]
+    rules: ::yara::Rules,
+}
+
+#[cfg(feature = "yara")]
+impl YaraScanner {
+    fn rule_files(directory: &Path, files: &mut Vec<std::path::PathBuf>) {
+        let Ok(entries) = std::fs::read_dir(directory) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                Self::rule_files(&path, files);
+            } else if path.extension().is_some_and(|extension| extension == "yar" || extension == "yara") {
+                files.push(path);
+            }
+        }
+    }
+
+    pub fn new(config: YaraConfig) -> Result<Self, CollectionError> {
+        let mut files = Vec::new();
+        Self::rule_files(Path::new(&config.rules_directory), &mut files);
+        files.sort();
+        if files.is_empty() {
+            return Err(CollectionError::SystemApi(format!("No YARA rules in {}", config.rules_directory)));
+        }
+
+        let mut compiler = ::yara::Compiler::new().map_err(|e| CollectionError::SystemApi(e.to_string()))?;
+        for file in &files {
+            // Each file gets its own namespace so rule names only need to be unique per file
+            let namespace = file.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+            compiler = compiler
+                .add_rules_file_with_namespace(file, &namespace)
+                .map_err(|e| CollectionError::Parse(format!("Failed to compile {}: {}", file.display(), e)))?;
+        }
+        let rules = compiler
+            .compile_rules()
+            .map_err(|e| CollectionError::Parse(format!("Failed to compile YARA rules: {}", e)))?;
+        log::info!("Loaded {} YARA rule files from {}", files.len(), config.rules_directory);
+
+        Ok(Self { config, rules })
+    }
+
+    pub fn scan(&self, path: &Path) -> Vec<YaraMatch> {
+        let size = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+        if size == 0 || size > self.config.max_file_size_bytes {
+            return Vec::new();
+        }
+        match self.rules.scan_file(path, i32::from(self.config.timeout_secs)) {
+            Ok(matches) => matches
+                .into_iter()
+                .map(|rule| YaraMatch {
+                    rule: rule.identifier.to_string(),
+                    namespace: rule.namespace.to_string(),
+                    tags: rule.tags.iter().map(|tag| tag.to_string()).collect(),
+                })
+                .collect(),
+            Err(e) => {
+                log::debug!("YARA scan of {} failed: {}", path.display(), e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "yara"))]
+impl YaraScanner {
+    pub fn new(config: YaraConfig) -> Result<Self, CollectionError> {
+        Err(CollectionError::SystemApi(format!(
+            "YARA rules in {} need an agent built with the yara feature",
+            config.rules_directory
+        )))
+    }
+
+    pub fn scan(&self, _path: &Path) -> Vec<YaraMatch> {
+        let _ = &self.config;
+        Vec::new()
+    }
+}
@@ -0,0 +1,353 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector};
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::features::honeypot::models::{HoneypotEvent, HoneypotEventBuilder};
+use crate::utils::load_config_section;
+use chrono::Utc;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{channel, error::TrySendError, Receiver, Sender};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecoyListenerConfig {
+    pub name: String,
+    pub bind_address: String,
+    pub port: u16,
+    // Sent as soon as a client connects (FTP/SSH/Telnet style services)
+    pub banner: Option<String>,
+    // Hex bytes sent after the client's first packet (RDP/SMB style services speak second)
+    pub response_hex: Option<String>,
+}
+
+impl Default for DecoyListenerConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            bind_address: String::from("0.0.0.0"),
+            port: 0,
+            banner: None,
+            response_hex: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HoneypotConfig {
+    pub enabled: bool,
+    pub listeners: Vec<DecoyListenerConfig>,
+    // How long to keep a connection open collecting payload
+    pub read_timeout_secs: u64,
+    pub max_payload_bytes: usize,
+    pub max_concurrent_connections: usize,
+    // Sources that are expected to touch the decoys, such as an internal vulnerability scanner
+    pub ignore_sources: Vec<String>,
+    pub channel_capacity: usize,
+}
+
+impl Default for HoneypotConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listeners: vec![
+                DecoyListenerConfig {
+                    name: String::from("rdp"),
+                    port: 3389,
+                    // X.224 Connection Confirm selecting standard RDP security
+                    response_hex: Some(String::from("030000130ed000001234000200080000000000")),
+                    ..DecoyListenerConfig::default()
+                },
+                DecoyListenerConfig {
+                    name: String::from("smb"),
+                    port: 445,
+                    ..DecoyListenerConfig::default()
+                },
+            ],
+            read_timeout_secs: 10,
+            max_payload_bytes: 4096,
+            max_concurrent_connections: 64,
+            ignore_sources: Vec::new(),
+            channel_capacity: 1024,
+        }
+    }
+}
+
+impl HoneypotConfig {
+    pub fn load() -> Self {
+        load_config_section("honeypot")
+    }
+}
+
+// What one decoy port needs to answer connections
+struct DecoyListener {
+    name: String,
+    port: u16,
+    banner: Option<Vec<u8>>,
+    response: Option<Vec<u8>>,
+}
+
+struct HoneypotContext {
+    read_timeout: Duration,
+    max_payload_bytes: usize,
+    ignore_sources: Vec<IpAddr>,
+    connections: Arc<Semaphore>,
+    host: Arc<HostIdentityService>,
+    sender: Sender<HoneypotEvent>,
+    dropped: AtomicU64,
+}
+
+impl HoneypotContext {
+    // Plays the decoy service just long enough to capture what the client sends
+    async fn engage(&self, listener: &DecoyListener, stream: &mut TcpStream) -> Vec<u8> {
+        let deadline = tokio::time::Instant::now() + self.read_timeout;
+        let mut payload = Vec::new();
+        let mut buffer = [0u8; 2048];
+
+        if let Some(banner) = &listener.banner {
+            if stream.write_all(banner).await.is_err() {
+                return payload;
+            }
+        }
+
+        let mut responded = false;
+        while payload.len() < self.max_payload_bytes {
+            let read = match tokio::time::timeout_at(deadline, stream.read(&mut buffer)).await {
+                Ok(Ok(0)) | Ok(Err(_)) | Err(_) => break,
+                Ok(Ok(read)) => read,
+            };
+            let keep = read.min(self.max_payload_bytes - payload.len());
+            payload.extend_from_slice(&buffer[..keep]);
+
+            if let (Some(response), false) = (&listener.response, responded) {
+                responded = true;
+                if stream.write_all(response).await.is_err() {
+                    break;
+                }
+            }
+        }
+
+        payload
+    }
+
+    async fn handle(self: Arc<Self>, listener: Arc<DecoyListener>, mut stream: TcpStream, peer: SocketAddr) {
+        let started = Instant::now();
+        // Past the connection limit, record the attempt without engaging
+        let payload = match self.connections.clone().try_acquire_owned() {
+            Ok(_permit) => self.engage(&listener, &mut stream).await,
+            Err(_) => Vec::new(),
+        };
+        drop(stream);
+
+        let event = HoneypotEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("honeypot"))
+            .listener(listener.name.clone())
+            .local_port(listener.port)
+            .remote_address(peer.ip().to_string())
+            .remote_port(peer.port())
+            .payload(payload)
+            .duration_ms(started.elapsed().as_millis() as u64)
+            .build();
+
+        match event {
+            Ok(event) => {
+                warn!(
+                    "Decoy {} port {} contacted by {}:{}",
+                    listener.name, listener.port, event.remote_address, event.remote_port
+                );
+                if let Err(TrySendError::Full(_)) = self.sender.try_send(event) {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Err(e) => error!("Failed to build honeypot event: {}", e),
+        }
+    }
+}
+
+// Decoy TCP listeners on ports no legitimate client uses. Every connection attempt is
+// recorded with its source address and whatever the client sent before timing out.
+pub struct HoneypotCollector {
+    context: Arc<HoneypotContext>,
+    event_receiver: Receiver<HoneypotEvent>,
+    listeners: Vec<JoinHandle<()>>,
+}
+
+impl HoneypotCollector {
+    pub fn new() -> Result<Self, CollectionError> {
+        Self::with_config(HoneypotConfig::load())
+    }
+
+    // Must be called from within a tokio runtime
+    pub fn with_config(config: HoneypotConfig) -> Result<Self, CollectionError> {
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|e| CollectionError::SystemApi(format!("Honeypot listeners need a tokio runtime: {}", e)))?;
+
+        let ignore_sources = config
+            .ignore_sources
+            .iter()
+            .filter_map(|source| match source.parse() {
+                Ok(address) => Some(address),
+                Err(_) => {
+                    warn!("Ignoring invalid honeypot ignore_sources entry {}", source);
+                    None
+                }
+            })
+            .collect();
+
+        let (tx, rx) = channel(config.channel_capacity.max(1));
+        let context = Arc::new(HoneypotContext {
+            read_timeout: Duration::from_secs(config.read_timeout_secs.max(1)),
+            max_payload_bytes: config.max_payload_bytes,
+            ignore_sources,
+            connections: Arc::new(Semaphore::new(config.max_concurrent_connections.max(1))),
+            host: HostIdentityService::global(),
+            sender: tx,
+            dropped: AtomicU64::new(0),
+        });
+
+        let mut listeners = Vec::new();
+        for listener_config in &config.listeners {
+            match Self::start_listener(&runtime, context.clone(), listener_config) {
+                Ok(handle) => listeners.push(handle),
+                Err(e) => warn!("Skipping decoy listener {}: {}", listener_config.name, e),
+            }
+        }
+        if listeners.is_empty() {
+            return Err(CollectionError::SystemApi(
+                "No honeypot listener could be started".to_string()
+            ));
+        }
+
+        Ok(Self {
+            context,
+            event_receiver: rx,
+            listeners,
+        })
+    }
+
+    fn start_listener(
+        runtime: &tokio::runtime::Handle,
+        context: Arc<HoneypotContext>,
+        config: &DecoyListenerConfig,
+    ) -> Result<JoinHandle<()>, CollectionError> {
+        if config.name.is_empty() || config.port == 0 {
+            return Err(CollectionError::Parse("listener needs a name and a port".to_string()));
+        }
+        let response = config
+            .response_hex
+            .as_deref()
+            .map(hex::decode)
+            .transpose()
+            .map_err(|e| CollectionError::Parse(format!("invalid response_hex: {}", e)))?;
+        let decoy = Arc::new(DecoyListener {
+            name: config.name.clone(),
+            port: config.port,
+            banner: config.banner.as_ref().map(|banner| banner.clone().into_bytes()),
+            response,
+        });
+
+        let address: IpAddr = config
+            .bind_address
+            .parse()
+            .map_err(|e| CollectionError::Parse(format!("invalid bind address {}: {}", config.bind_address, e)))?;
+        let std_listener = std::net::TcpListener::bind(SocketAddr::new(address, config.port))?;
+        std_listener.set_nonblocking(true)?;
+        let listener = {
+            let _guard = runtime.enter();
+            TcpListener::from_std(std_listener)?
+        };
+        info!("Decoy listener {} open on {}:{}", decoy.name, address, decoy.port);
+
+        Ok(runtime.spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        if context.ignore_sources.contains(&peer.ip()) {
+                            debug!("Ignoring decoy connection from {}", peer);
+                            continue;
+                        }
+                        tokio::spawn(context.clone().handle(decoy.clone(), stream, peer));
+                    }
+                    Err(e) => {
+                        warn!("Failed to accept decoy connection on {}: {}", decoy.name, e);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                    }
+                }
+            }
+        }))
+    }
+
+    fn drain(&mut self) -> Vec<HoneypotEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.event_receiver.try_recv() {
+            events.push(event);
+        }
+
+        let dropped = self.context.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            warn!("Dropped {} honeypot events, queue was full", dropped);
+        }
+        if !events.is_empty() {
+            warn!("Collected {} honeypot connection attempts", events.len());
+        }
+        events
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if self.listeners.iter().all(|listener| listener.is_finished()) {
+            return Err(CollectionError::SystemApi(
+                "No honeypot listener is running".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for HoneypotCollector {
+    fn drop(&mut self) {
+        for listener in &self.listeners {
+            listener.abort();
+        }
+    }
+}
+
+impl DataCollector<Vec<HoneypotEvent>> for HoneypotCollector {
+    fn collect(&mut self) -> Result<Vec<HoneypotEvent>, CollectionError> {
+        Ok(self.drain())
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<HoneypotEvent>> for HoneypotCollector {
+    async fn collect(&mut self) -> Result<Vec<HoneypotEvent>, CollectionError> {
+        Ok(self.drain())
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
@@ -0,0 +1,5 @@
+mod collector;
+mod models;
+
+pub use collector::{DecoyListenerConfig, HoneypotCollector, HoneypotConfig};
+pub use models::{HoneypotEvent, HoneypotEventBuilder};
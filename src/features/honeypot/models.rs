@@ -0,0 +1,164 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+// One connection attempt against a decoy port. Nothing legitimate talks to these
+// ports, so every event is Critical.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HoneypotEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub listener: String,
+    pub local_port: u16,
+    pub remote_address: String,
+    pub remote_port: u16,
+    pub payload_bytes: usize,
+    pub payload_hex: String,
+    // Printable ASCII of the payload, other bytes replaced by '.'
+    pub payload_preview: String,
+    pub duration_ms: u64,
+}
+
+impl Event for HoneypotEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        "honeypot_connection"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Critical
+    }
+}
+
+impl Identifiable for HoneypotEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for HoneypotEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.listener.is_empty() {
+            return Err("Listener name cannot be empty".to_string());
+        }
+
+        if self.remote_address.is_empty() {
+            return Err("Remote address cannot be empty".to_string());
+        }
+
+        if self.local_port == 0 {
+            return Err("Local port cannot be 0".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct HoneypotEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    listener: Option<String>,
+    local_port: Option<u16>,
+    remote_address: Option<String>,
+    remote_port: Option<u16>,
+    payload: Vec<u8>,
+    duration_ms: Option<u64>,
+}
+
+impl HoneypotEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn listener(mut self, listener: String) -> Self {
+        self.listener = Some(listener);
+        self
+    }
+
+    pub fn local_port(mut self, local_port: u16) -> Self {
+        self.local_port = Some(local_port);
+        self
+    }
+
+    pub fn remote_address(mut self, remote_address: String) -> Self {
+        self.remote_address = Some(remote_address);
+        self
+    }
+
+    pub fn remote_port(mut self, remote_port: u16) -> Self {
+        self.remote_port = Some(remote_port);
+        self
+    }
+
+    pub fn payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    pub fn duration_ms(mut self, duration_ms: u64) -> Self {
+        self.duration_ms = Some(duration_ms);
+        self
+    }
+
+    pub fn build(self) -> Result<HoneypotEvent, String> {
+        let payload_preview = self
+            .payload
+            .iter()
+            .map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' })
+            .collect();
+        let event = HoneypotEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            listener: self.listener.ok_or("listener is required")?,
+            local_port: self.local_port.ok_or("local_port is required")?,
+            remote_address: self.remote_address.ok_or("remote_address is required")?,
+            remote_port: self.remote_port.ok_or("remote_port is required")?,
+            payload_bytes: self.payload.len(),
+            payload_hex: hex::encode(&self.payload),
+            payload_preview,
+            duration_ms: self.duration_ms.unwrap_or(0),
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
@@ -0,0 +1,5 @@
+pub mod models;
+pub mod tracker;
+
+pub use models::{Incident, IncidentStatus};
+pub use tracker::{IncidentConfig, IncidentTracker};
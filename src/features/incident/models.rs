@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use crate::shared::traits::{Event, Severity, Identifiable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IncidentStatus {
+    // Still taking in new alerts
+    Open,
+    // A responder has the activity under control; new alerts open a new incident
+    Contained,
+    Resolved,
+}
+
+impl fmt::Display for IncidentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IncidentStatus::Open => "open",
+            IncidentStatus::Contained => "contained",
+            IncidentStatus::Resolved => "resolved",
+        })
+    }
+}
+
+impl FromStr for IncidentStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "open" => Ok(IncidentStatus::Open),
+            "contained" => Ok(IncidentStatus::Contained),
+            "resolved" => Ok(IncidentStatus::Resolved),
+            _ => Err(format!("Unknown incident status '{}', expected open, contained or resolved", status)),
+        }
+    }
+}
+
+// Alerts raised on this host close together in time, with the events behind them. Every
+// change is stored as a new revision of the incident in the incidents index; the highest
+// revision of an id is its current state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Incident {
+    pub id: String,
+    // When this revision was written
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub revision: u64,
+    pub status: IncidentStatus,
+    // Highest severity of its alerts
+    pub severity: Severity,
+    pub title: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub alert_ids: Vec<String>,
+    // Rules behind the alerts, in the order they first fired
+    pub rule_ids: Vec<String>,
+    // The events that raised the alerts, from their related_ids
+    pub related_ids: Vec<String>,
+    // Left by whoever last changed the status
+    pub note: Option<String>,
+}
+
+impl Event for Incident {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        "incident"
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl Identifiable for Incident {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
@@ -0,0 +1,225 @@
+use crate::features::detection::Alert;
+use crate::features::incident::models::{Incident, IncidentStatus};
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::traits::Severity;
+use crate::utils::load_config_section;
+use chrono::{Duration, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IncidentConfig {
+    pub enabled: bool,
+    // An alert joins the open incident when it comes within this many seconds of the
+    // incident's last alert, and opens a new one otherwise
+    pub window_secs: u64,
+    // Alerts below this severity are not grouped into incidents
+    pub min_severity: Severity,
+    // Supporting events kept in one incident's related_ids
+    pub max_related_ids: usize,
+    // Incidents kept in memory for the control channel; resolved and contained ones are
+    // forgotten first
+    pub max_incidents: usize,
+}
+
+impl Default for IncidentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: 1800,
+            min_severity: Severity::Medium,
+            max_related_ids: 500,
+            max_incidents: 200,
+        }
+    }
+}
+
+impl IncidentConfig {
+    pub fn load() -> Self {
+        load_config_section("incidents")
+    }
+}
+
+struct State {
+    config: IncidentConfig,
+    // Oldest first
+    incidents: VecDeque<Incident>,
+    // Incidents changed since the last take_pending
+    pending: Vec<String>,
+}
+
+fn push_new(ids: &mut Vec<String>, id: &str, max: usize) {
+    if ids.len() < max && !ids.iter().any(|known| known == id) {
+        ids.push(id.to_string());
+    }
+}
+
+// Groups the alerts raised on this host into incidents and keeps the recent ones, for the
+// control channel to list and update. Changed incidents wait in a queue the agent drains into
+// the incidents index.
+pub struct IncidentTracker {
+    state: Mutex<State>,
+    changed: Notify,
+}
+
+impl IncidentTracker {
+    pub fn new(config: IncidentConfig) -> Self {
+        Self {
+            state: Mutex::new(State {
+                config,
+                incidents: VecDeque::new(),
+                pending: Vec::new(),
+            }),
+            changed: Notify::new(),
+        }
+    }
+
+    pub fn global() -> Arc<IncidentTracker> {
+        static GLOBAL: OnceLock<Arc<IncidentTracker>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(IncidentTracker::new(IncidentConfig::load()))).clone()
+    }
+
+    // Applies a reloaded configuration; incidents already assembled are kept
+    pub fn configure(&self, config: IncidentConfig) {
+        if let Ok(mut state) = self.state.lock() {
+            state.config = config;
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.state.lock().is_ok_and(|state| state.config.enabled)
+    }
+
+    // Adds raised alerts to the open incident, or opens a new one
+    pub fn record(&self, alerts: &[Alert]) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let state = &mut *state;
+        let config = &state.config;
+        if !config.enabled {
+            return;
+        }
+        let window = Duration::seconds(config.window_secs as i64);
+        let mut changed = false;
+        for alert in alerts.iter().filter(|alert| alert.severity >= config.min_severity) {
+            let joins = state.incidents.back().is_some_and(|incident| {
+                incident.status == IncidentStatus::Open && alert.timestamp - incident.last_seen <= window
+            });
+            if !joins {
+                state.incidents.push_back(Incident {
+                    id: Uuid::new_v4().to_string(),
+                    timestamp: Utc::now(),
+                    source: HostIdentityService::global().name(),
+                    category: String::from("incident"),
+                    revision: 0,
+                    status: IncidentStatus::Open,
+                    severity: alert.severity,
+                    title: alert.rule_name.clone(),
+                    first_seen: alert.timestamp,
+                    last_seen: alert.timestamp,
+                    alert_ids: Vec::new(),
+                    rule_ids: Vec::new(),
+                    related_ids: Vec::new(),
+                    note: None,
+                });
+                info!("Opened an incident for alert {} ({})", alert.id, alert.rule_name);
+            }
+            let Some(incident) = state.incidents.back_mut() else {
+                continue;
+            };
+            // The most severe alert names the incident
+            if alert.severity > incident.severity {
+                incident.severity = alert.severity;
+                incident.title = alert.rule_name.clone();
+            }
+            incident.first_seen = incident.first_seen.min(alert.timestamp);
+            incident.last_seen = incident.last_seen.max(alert.timestamp);
+            incident.alert_ids.push(alert.id.clone());
+            push_new(&mut incident.rule_ids, &alert.rule_id, usize::MAX);
+            for id in &alert.related_ids {
+                push_new(&mut incident.related_ids, id, config.max_related_ids);
+            }
+            incident.revision += 1;
+            incident.timestamp = Utc::now();
+            let id = incident.id.clone();
+            if !state.pending.contains(&id) {
+                state.pending.push(id);
+            }
+            changed = true;
+        }
+
+        while state.incidents.len() > config.max_incidents {
+            let closed = state.incidents.iter().position(|incident| incident.status != IncidentStatus::Open);
+            state.incidents.remove(closed.unwrap_or(0));
+        }
+        if changed {
+            self.changed.notify_one();
+        }
+    }
+
+    // Sets an incident's status on behalf of a responder and returns the new revision
+    pub fn update(&self, id: &str, status: IncidentStatus, note: Option<String>) -> Result<Incident, String> {
+        let mut state = self.state.lock().map_err(|_| String::from("Incident state is unavailable"))?;
+        if !state.config.enabled {
+            return Err(String::from("Incidents are not enabled"));
+        }
+        let incident = state
+            .incidents
+            .iter_mut()
+            .find(|incident| incident.id == id)
+            .ok_or_else(|| format!("No incident {} on this host", id))?;
+        info!("Incident {} changed from {} to {}", id, incident.status, status);
+        incident.status = status;
+        incident.note = note;
+        incident.revision += 1;
+        incident.timestamp = Utc::now();
+        let incident = incident.clone();
+        if !state.pending.contains(&incident.id) {
+            state.pending.push(incident.id.clone());
+        }
+        self.changed.notify_one();
+        Ok(incident)
+    }
+
+    // The newest `limit` incidents, optionally only those with `status`, newest first; a
+    // limit of 0 returns all of them
+    pub fn list(&self, status: Option<IncidentStatus>, limit: usize) -> Vec<Incident> {
+        let Ok(state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let limit = if limit == 0 { state.incidents.len() } else { limit };
+        state
+            .incidents
+            .iter()
+            .rev()
+            .filter(|incident| status.is_none_or(|status| incident.status == status))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    // Resolves once incidents have changed since the last take_pending
+    pub async fn changed(&self) {
+        self.changed.notified().await
+    }
+
+    // The current revision of every incident changed since the last call
+    pub fn take_pending(&self) -> Vec<Incident> {
+        let Ok(mut state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let pending = std::mem::take(&mut state.pending);
+        state
+            .incidents
+            .iter()
+            .filter(|incident| pending.contains(&incident.id))
+            .cloned()
+            .collect()
+    }
+}
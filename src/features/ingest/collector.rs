@@ -0,0 +1,421 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector};
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::features::ingest::models::{IngestSubmission, IngestedEvent, IngestedEventBuilder};
+use crate::utils::load_config_section;
+use chrono::Utc;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{channel, error::TrySendError, Receiver, Sender};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IngestConfig {
+    pub enabled: bool,
+    // Unix domain socket used on Linux / macOS
+    pub socket_path: String,
+    // Octal permission bits applied to the socket file
+    pub socket_mode: u32,
+    // Named pipe used on Windows
+    pub pipe_name: String,
+    // Only accept submissions from these uids (Unix), empty allows any peer that can open the socket
+    pub allowed_uids: Vec<u32>,
+    // Categories tools may submit, empty allows any
+    pub allowed_categories: Vec<String>,
+    pub max_line_bytes: usize,
+    pub channel_capacity: usize,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            socket_path: String::from("/run/lsedr/ingest.sock"),
+            socket_mode: 0o660,
+            pipe_name: String::from(r"\\.\pipe\lsedr-ingest"),
+            allowed_uids: Vec::new(),
+            allowed_categories: Vec::new(),
+            max_line_bytes: 64 * 1024,
+            channel_capacity: 1024,
+        }
+    }
+}
+
+impl IngestConfig {
+    pub fn load() -> Self {
+        load_config_section("ingest")
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PeerInfo {
+    uid: Option<u32>,
+    pid: Option<u32>,
+}
+
+// State shared by every connection handler
+struct IngestContext {
+    config: IngestConfig,
+    host: Arc<HostIdentityService>,
+    sender: Sender<IngestedEvent>,
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl IngestContext {
+    fn accept_line(&self, line: &[u8], peer: PeerInfo) -> Result<String, String> {
+        if let Some(uid) = peer.uid {
+            if !self.config.allowed_uids.is_empty() && !self.config.allowed_uids.contains(&uid) {
+                return Err(format!("uid {} is not allowed to submit events", uid));
+            }
+        }
+
+        let submission: IngestSubmission = serde_json::from_slice(line)
+            .map_err(|e| format!("Invalid submission: {}", e))?;
+
+        if !self.config.allowed_categories.is_empty()
+            && !self.config.allowed_categories.contains(&submission.category)
+        {
+            return Err(format!("Category '{}' is not accepted", submission.category));
+        }
+
+        let mut builder = IngestedEventBuilder::from_submission(submission)
+            .source(self.host.name())
+            .received_at(Utc::now());
+        if let Some(uid) = peer.uid {
+            builder = builder.peer_uid(uid);
+        }
+        if let Some(pid) = peer.pid {
+            builder = builder.peer_pid(pid);
+        }
+        let event = builder.build()?;
+        let id = event.id.clone();
+
+        match self.sender.try_send(event) {
+            Ok(()) => Ok(id),
+            Err(TrySendError::Full(_)) => Err("Ingestion queue is full, retry later".to_string()),
+            Err(TrySendError::Closed(_)) => Err("Agent is shutting down".to_string()),
+        }
+    }
+
+    // Serves one client: newline-delimited JSON in, one JSON status line out per submission
+    async fn serve<S>(self: Arc<Self>, stream: S, peer: PeerInfo)
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+
+        loop {
+            line.clear();
+            let too_long = match Self::read_line(&mut reader, &mut line, self.config.max_line_bytes).await {
+                Ok(Some(too_long)) => too_long,
+                Ok(None) => return,
+                Err(e) => {
+                    debug!("Ingestion client disconnected: {}", e);
+                    return;
+                }
+            };
+            if line.iter().all(u8::is_ascii_whitespace) && !too_long {
+                continue;
+            }
+
+            let result = if too_long {
+                Err(format!("Submission exceeds {} bytes", self.config.max_line_bytes))
+            } else {
+                self.accept_line(&line, peer)
+            };
+
+            let reply = match result {
+                Ok(id) => {
+                    self.accepted.fetch_add(1, Ordering::Relaxed);
+                    json!({ "status": "accepted", "id": id })
+                }
+                Err(error) => {
+                    self.rejected.fetch_add(1, Ordering::Relaxed);
+                    debug!("Rejected ingestion submission: {}", error);
+                    json!({ "status": "rejected", "error": error })
+                }
+            };
+
+            let mut reply = reply.to_string();
+            reply.push('\n');
+            if writer.write_all(reply.as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    // Reads one line into `line`; returns Some(true) when it was longer than `max`
+    // (the excess is discarded) and None at end of stream.
+    async fn read_line<R>(reader: &mut BufReader<R>, line: &mut Vec<u8>, max: usize) -> std::io::Result<Option<bool>>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut too_long = false;
+        loop {
+            let available = reader.fill_buf().await?;
+            if available.is_empty() {
+                return Ok(if line.is_empty() && !too_long { None } else { Some(too_long) });
+            }
+
+            let (chunk, done) = match available.iter().position(|byte| *byte == b'\n') {
+                Some(newline) => (&available[..newline], Some(newline + 1)),
+                None => (available, None),
+            };
+            if !too_long {
+                if line.len() + chunk.len() > max {
+                    too_long = true;
+                    line.clear();
+                } else {
+                    line.extend_from_slice(chunk);
+                }
+            }
+
+            let consumed = done.unwrap_or(available.len());
+            reader.consume(consumed);
+            if done.is_some() {
+                return Ok(Some(too_long));
+            }
+        }
+    }
+}
+
+// Device and inode of a socket file
+type SocketIdentity = (u64, u64);
+
+// Local endpoint (Unix socket or Windows named pipe) where other on-host tools submit
+// events in the agent's schema. Accepted events are queued and handed to the regular
+// storage path on the next `collect`.
+pub struct IngestCollector {
+    context: Arc<IngestContext>,
+    event_receiver: Receiver<IngestedEvent>,
+    listener: JoinHandle<()>,
+    // The socket this instance bound, the only file Drop removes
+    socket: Option<SocketIdentity>,
+}
+
+impl IngestCollector {
+    pub fn new() -> Result<Self, CollectionError> {
+        Self::with_config(IngestConfig::load())
+    }
+
+    // Must be called from within a tokio runtime
+    pub fn with_config(config: IngestConfig) -> Result<Self, CollectionError> {
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|e| CollectionError::SystemApi(format!("Ingestion endpoint needs a tokio runtime: {}", e)))?;
+
+        let (tx, rx) = channel(config.channel_capacity.max(1));
+        let context = Arc::new(IngestContext {
+            config,
+            host: HostIdentityService::global(),
+            sender: tx,
+            accepted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        });
+
+        let (listener, socket) = Self::start_listener(&runtime, context.clone())?;
+
+        Ok(Self {
+            context,
+            event_receiver: rx,
+            listener,
+            socket,
+        })
+    }
+
+    #[cfg(unix)]
+    fn start_listener(
+        runtime: &tokio::runtime::Handle,
+        context: Arc<IngestContext>,
+    ) -> Result<(JoinHandle<()>, Option<SocketIdentity>), CollectionError> {
+        use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+        use std::os::unix::net::UnixStream;
+        use std::path::Path;
+        use tokio::net::UnixListener;
+
+        let path = Path::new(&context.config.socket_path);
+        let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        std::fs::create_dir_all(parent)?;
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if !metadata.file_type().is_socket() => {
+                return Err(CollectionError::SystemApi(format!(
+                    "Ingestion socket path {} exists and is not a socket",
+                    path.display()
+                )));
+            }
+            Ok(_) => match UnixStream::connect(path) {
+                // A running agent still accepts connections there
+                Ok(_) => {
+                    return Err(CollectionError::SystemApi(format!(
+                        "Ingestion socket {} is already in use",
+                        path.display()
+                    )));
+                }
+                // A stale socket left behind by a previous run would make bind fail
+                Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => std::fs::remove_file(path)?,
+                Err(e) => return Err(e.into()),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        // Bind in a directory only the agent can enter and move the socket into place once
+        // its mode is set, so it is never reachable with the umask's permissions
+        let staging = parent.join(format!(".lsedr-ingest.{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&staging);
+        std::fs::DirBuilder::new().mode(0o700).create(&staging)?;
+        let staged = staging.join("ingest.sock");
+        let bound = (|| {
+            let listener = {
+                let _guard = runtime.enter();
+                UnixListener::bind(&staged)?
+            };
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(context.config.socket_mode))?;
+            std::fs::rename(&staged, path)?;
+            Ok::<_, std::io::Error>(listener)
+        })();
+        let _ = std::fs::remove_dir_all(&staging);
+        let listener = bound?;
+        let socket = socket_identity(path);
+        info!("Accepting third-party events on {}", path.display());
+
+        let listener = runtime.spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let peer = stream
+                            .peer_cred()
+                            .map(|credentials| PeerInfo {
+                                uid: Some(credentials.uid()),
+                                pid: credentials.pid().map(|pid| pid as u32),
+                            })
+                            .unwrap_or_default();
+                        tokio::spawn(context.clone().serve(stream, peer));
+                    }
+                    Err(e) => {
+                        warn!("Failed to accept ingestion connection: {}", e);
+                        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    }
+                }
+            }
+        });
+        Ok((listener, socket))
+    }
+
+    #[cfg(windows)]
+    fn start_listener(
+        runtime: &tokio::runtime::Handle,
+        context: Arc<IngestContext>,
+    ) -> Result<(JoinHandle<()>, Option<SocketIdentity>), CollectionError> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        let pipe_name = context.config.pipe_name.clone();
+        let mut server = {
+            let _guard = runtime.enter();
+            ServerOptions::new().first_pipe_instance(true).create(&pipe_name)?
+        };
+        info!("Accepting third-party events on {}", pipe_name);
+
+        let listener = runtime.spawn(async move {
+            loop {
+                if let Err(e) = server.connect().await {
+                    warn!("Failed to accept ingestion connection: {}", e);
+                    continue;
+                }
+                // Open the next instance before serving so clients never see the pipe missing
+                let next = match ServerOptions::new().create(&pipe_name) {
+                    Ok(next) => next,
+                    Err(e) => {
+                        warn!("Failed to create ingestion pipe instance, stopping: {}", e);
+                        return;
+                    }
+                };
+                let connected = std::mem::replace(&mut server, next);
+                tokio::spawn(context.clone().serve(connected, PeerInfo::default()));
+            }
+        });
+        // The pipe goes away with its last handle
+        Ok((listener, None))
+    }
+
+    fn drain(&mut self) -> Vec<IngestedEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.event_receiver.try_recv() {
+            events.push(event);
+        }
+
+        let rejected = self.context.rejected.swap(0, Ordering::Relaxed);
+        let accepted = self.context.accepted.swap(0, Ordering::Relaxed);
+        if rejected > 0 {
+            warn!("Rejected {} ingestion submissions since last collection", rejected);
+        }
+        info!("Collected {} ingested events ({} accepted)", events.len(), accepted);
+        events
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if self.listener.is_finished() {
+            return Err(CollectionError::SystemApi(
+                "Ingestion listener is not running".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for IngestCollector {
+    fn drop(&mut self) {
+        self.listener.abort();
+        // Another agent may have replaced the socket since; only remove the one bound here
+        if self.socket.is_some() && socket_identity(std::path::Path::new(&self.context.config.socket_path)) == self.socket {
+            let _ = std::fs::remove_file(&self.context.config.socket_path);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn socket_identity(path: &std::path::Path) -> Option<SocketIdentity> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::symlink_metadata(path).ok().map(|metadata| (metadata.dev(), metadata.ino()))
+}
+
+#[cfg(windows)]
+fn socket_identity(_path: &std::path::Path) -> Option<SocketIdentity> {
+    None
+}
+
+impl DataCollector<Vec<IngestedEvent>> for IngestCollector {
+    fn collect(&mut self) -> Result<Vec<IngestedEvent>, CollectionError> {
+        Ok(self.drain())
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<IngestedEvent>> for IngestCollector {
+    async fn collect(&mut self) -> Result<Vec<IngestedEvent>, CollectionError> {
+        Ok(self.drain())
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
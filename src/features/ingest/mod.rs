@@ -0,0 +1,5 @@
+mod collector;
+mod models;
+
+pub use collector::{IngestCollector, IngestConfig};
+pub use models::{IngestSubmission, IngestedEvent, IngestedEventBuilder};
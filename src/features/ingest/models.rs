@@ -0,0 +1,209 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+// What a local tool writes to the ingestion socket, one JSON object per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IngestSubmission {
+    pub producer: String,
+    pub category: String,
+    pub event_type: String,
+    pub severity: Severity,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub message: Option<String>,
+    #[serde(default)]
+    pub data: Value,
+}
+
+// A submission after validation, stamped with this host and the submitting peer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestedEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub event_type: String,
+    pub severity: Severity,
+    pub producer: String,
+    pub message: Option<String>,
+    pub received_at: DateTime<Utc>,
+    pub peer_uid: Option<u32>,
+    pub peer_pid: Option<u32>,
+    pub data: Value,
+}
+
+impl Event for IngestedEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        &self.event_type
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl Identifiable for IngestedEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for IngestedEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.producer.trim().is_empty() {
+            return Err("Producer cannot be empty".to_string());
+        }
+
+        let valid_name = |value: &str| {
+            !value.is_empty()
+                && value.len() <= 64
+                && value.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        };
+        if !valid_name(&self.category) {
+            return Err("Category must be 1-64 characters of [a-z0-9_]".to_string());
+        }
+        if !valid_name(&self.event_type) {
+            return Err("Event type must be 1-64 characters of [a-z0-9_]".to_string());
+        }
+
+        if !(self.data.is_object() || self.data.is_null()) {
+            return Err("Data must be a JSON object when provided".to_string());
+        }
+
+        if self.timestamp > self.received_at + chrono::Duration::minutes(5) {
+            return Err("Timestamp cannot be in the future".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct IngestedEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    event_type: Option<String>,
+    severity: Option<Severity>,
+    producer: Option<String>,
+    message: Option<String>,
+    received_at: Option<DateTime<Utc>>,
+    peer_uid: Option<u32>,
+    peer_pid: Option<u32>,
+    data: Option<Value>,
+}
+
+impl IngestedEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_submission(submission: IngestSubmission) -> Self {
+        let mut builder = Self::new()
+            .category(submission.category)
+            .event_type(submission.event_type)
+            .severity(submission.severity)
+            .producer(submission.producer)
+            .data(submission.data);
+        builder.timestamp = submission.timestamp;
+        builder.message = submission.message;
+        builder
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn event_type(mut self, event_type: String) -> Self {
+        self.event_type = Some(event_type);
+        self
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    pub fn producer(mut self, producer: String) -> Self {
+        self.producer = Some(producer);
+        self
+    }
+
+    pub fn message(mut self, message: String) -> Self {
+        self.message = Some(message);
+        self
+    }
+
+    pub fn received_at(mut self, received_at: DateTime<Utc>) -> Self {
+        self.received_at = Some(received_at);
+        self
+    }
+
+    pub fn peer_uid(mut self, peer_uid: u32) -> Self {
+        self.peer_uid = Some(peer_uid);
+        self
+    }
+
+    pub fn peer_pid(mut self, peer_pid: u32) -> Self {
+        self.peer_pid = Some(peer_pid);
+        self
+    }
+
+    pub fn data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    pub fn build(self) -> Result<IngestedEvent, String> {
+        let received_at = self.received_at.unwrap_or_else(Utc::now);
+        let event = IngestedEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.unwrap_or(received_at),
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            event_type: self.event_type.ok_or("event_type is required")?,
+            severity: self.severity.ok_or("severity is required")?,
+            producer: self.producer.ok_or("producer is required")?,
+            message: self.message,
+            received_at,
+            peer_uid: self.peer_uid,
+            peer_pid: self.peer_pid,
+            data: self.data.unwrap_or(Value::Null),
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
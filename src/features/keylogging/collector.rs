@@ -0,0 +1,318 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector};
+use crate::shared::error::CollectionError;
+use crate::shared::etw::{EtwEvent, EtwProvider, EtwSession};
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::process_resolver::ProcessResolver;
+use crate::features::keylogging::models::{KeyloggingEvent, KeyloggingEventBuilder, KeyloggingIndicator};
+use crate::utils::load_config_section;
+use chrono::Utc;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+// Microsoft-Windows-Win32k
+const WIN32K_PROVIDER: &str = "8c416c79-d49b-4f01-a467-e56d3aa8234c";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyloggingConfig {
+    pub enabled: bool,
+    // Windows: ETW provider and keywords reporting hook installation (Win32k AuditApiCalls)
+    pub etw_provider: String,
+    pub etw_keywords: u64,
+    // Event / task names from the provider that count as hook installation
+    pub hook_apis: Vec<String>,
+    // Event properties that may carry the hook type, the first one present is used
+    pub hook_type_properties: Vec<String>,
+    // WH_KEYBOARD (2) and WH_KEYBOARD_LL (13)
+    pub hook_types: Vec<u32>,
+    // Linux: report processes holding keyboard /dev/input devices open
+    pub scan_input_devices: bool,
+    // Process names (case-insensitive) that legitimately read keyboards or install hooks
+    pub allowed_processes: Vec<String>,
+    pub max_pending_events: usize,
+}
+
+impl Default for KeyloggingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            etw_provider: WIN32K_PROVIDER.to_string(),
+            etw_keywords: 0x400,
+            hook_apis: vec![String::from("SetWindowsHookEx")],
+            hook_type_properties: vec![String::from("FilterType"), String::from("HookType"), String::from("idHook")],
+            hook_types: vec![2, 13],
+            scan_input_devices: true,
+            allowed_processes: [
+                "Xorg", "Xwayland", "gnome-shell", "mutter", "kwin_wayland", "kwin_x11", "sway", "weston",
+                "Hyprland", "systemd-logind", "acpid", "brltty", "ibus-daemon", "fcitx5", "keyd", "input-remapper-service",
+                "ctfmon.exe", "TextInputHost.exe", "osk.exe", "Narrator.exe", "Magnify.exe",
+            ]
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+            max_pending_events: 10000,
+        }
+    }
+}
+
+impl KeyloggingConfig {
+    pub fn load() -> Self {
+        load_config_section("keylogging")
+    }
+}
+
+#[cfg(target_os = "linux")]
+use crate::features::keylogging::linux::{device_holders, keyboard_devices};
+
+#[cfg(not(target_os = "linux"))]
+fn keyboard_devices() -> std::collections::HashMap<String, String> {
+    std::collections::HashMap::new()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn device_holders(_devices: &std::collections::HashMap<String, String>) -> Vec<(u32, String)> {
+    Vec::new()
+}
+
+// Keylogging indicators: keyboard hooks installed through SetWindowsHookEx on Windows (from
+// the Win32k ETW provider, which needs administrator rights) and processes reading keyboard
+// event devices directly on Linux. Each process is reported once per hook API / device.
+pub struct KeyloggingCollector {
+    config: KeyloggingConfig,
+    // Kept alive for as long as the collector, dropping it stops the trace
+    _session: Option<EtwSession>,
+    hook_events: Option<Mutex<Receiver<EtwEvent>>>,
+    reported_hooks: HashSet<(u32, String)>,
+    device_holders: HashSet<(u32, String)>,
+    resolver: Arc<ProcessResolver>,
+    host: Arc<HostIdentityService>,
+}
+
+impl KeyloggingCollector {
+    pub fn new() -> Self {
+        Self::with_config(KeyloggingConfig::load())
+    }
+
+    pub fn with_config(config: KeyloggingConfig) -> Self {
+        let (session, hook_events) = if cfg!(target_os = "windows") {
+            match Self::start_session(&config) {
+                Ok((session, receiver)) => (Some(session), Some(Mutex::new(receiver))),
+                Err(e) => {
+                    warn!("Keyboard hook tracing unavailable: {}", e);
+                    (None, None)
+                }
+            }
+        } else {
+            (None, None)
+        };
+
+        Self {
+            config,
+            _session: session,
+            hook_events,
+            reported_hooks: HashSet::new(),
+            device_holders: HashSet::new(),
+            resolver: ProcessResolver::global(),
+            host: HostIdentityService::global(),
+        }
+    }
+
+    fn start_session(config: &KeyloggingConfig) -> Result<(EtwSession, Receiver<EtwEvent>), CollectionError> {
+        let guid = EtwProvider::parse_guid(&config.etw_provider).ok_or_else(|| {
+            CollectionError::Parse(format!("Invalid ETW provider GUID: {}", config.etw_provider))
+        })?;
+        let provider = EtwProvider {
+            guid,
+            level: 5,
+            keywords: config.etw_keywords,
+        };
+        let (tx, rx) = sync_channel(config.max_pending_events);
+        let session = EtwSession::start("lsedr-keylogging", &[provider], tx)
+            .map_err(|e| CollectionError::SystemApi(e.to_string()))?;
+        info!("Tracing keyboard hook installation through ETW");
+        Ok((session, rx))
+    }
+
+    fn is_allowed(&self, name: &str) -> bool {
+        self.config
+            .allowed_processes
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(name))
+    }
+
+    fn event(&self, indicator: KeyloggingIndicator, pid: u32) -> Option<KeyloggingEventBuilder> {
+        let details = self.resolver.resolve(pid);
+        let name = details
+            .as_ref()
+            .map(|details| details.name.clone())
+            .unwrap_or_else(|| String::from("unknown"));
+        if self.is_allowed(&name) {
+            debug!("Ignoring keylogging indicator from allowed process {} ({})", name, pid);
+            return None;
+        }
+
+        let mut builder = KeyloggingEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("keylogging"))
+            .indicator(indicator)
+            .process_id(pid)
+            .process_name(name);
+        if let Some(details) = details {
+            builder = builder
+                .process_path(details.path)
+                .process_sha256(details.sha256)
+                .parent_pid(details.parent_pid)
+                .user(details.user);
+        }
+        Some(builder)
+    }
+
+    fn hook_type(&self, event: &EtwEvent) -> Option<u32> {
+        self.config
+            .hook_type_properties
+            .iter()
+            .find_map(|property| event.properties.get(property))
+            .and_then(|value| match value.strip_prefix("0x") {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => value.parse().ok(),
+            })
+    }
+
+    fn hook_events(&mut self) -> Result<Vec<KeyloggingEvent>, CollectionError> {
+        let Some(receiver) = self.hook_events.as_ref() else {
+            return Ok(Vec::new());
+        };
+        let received: Vec<EtwEvent> = match receiver.lock() {
+            Ok(receiver) => receiver.try_iter().collect(),
+            Err(_) => return Err(CollectionError::SystemApi("ETW receiver lock poisoned".to_string())),
+        };
+
+        let own_pid = std::process::id();
+        let mut events = Vec::new();
+        for etw_event in received {
+            let api = [etw_event.event_name.as_deref(), etw_event.task_name.as_deref()]
+                .into_iter()
+                .flatten()
+                .find_map(|name| self.config.hook_apis.iter().find(|api| name.contains(api.as_str())))
+                .cloned();
+            let Some(api) = api else {
+                continue;
+            };
+            // Events without a hook type property are kept, the provider schema varies by build
+            let hook_type = self.hook_type(&etw_event);
+            if hook_type.is_some_and(|hook_type| !self.config.hook_types.contains(&hook_type)) {
+                continue;
+            }
+            let pid = etw_event.process_id;
+            if pid == own_pid || !self.reported_hooks.insert((pid, api.clone())) {
+                continue;
+            }
+
+            let Some(mut builder) = self.event(KeyloggingIndicator::KeyboardHook, pid) else {
+                continue;
+            };
+            warn!("Process {} installed a keyboard hook through {}", pid, api);
+            builder = builder.timestamp(etw_event.timestamp).api(api);
+            if let Some(hook_type) = hook_type {
+                builder = builder.hook_type(hook_type);
+            }
+            events.push(builder.build().map_err(CollectionError::Parse)?);
+        }
+
+        // Forget processes that have exited so a reused PID is reported again
+        let resolver = self.resolver.clone();
+        self.reported_hooks.retain(|(pid, _)| resolver.resolve(*pid).is_some());
+        Ok(events)
+    }
+
+    fn device_events(&mut self) -> Result<Vec<KeyloggingEvent>, CollectionError> {
+        let devices = keyboard_devices();
+        let holders: HashSet<(u32, String)> = device_holders(&devices).into_iter().collect();
+        let own_pid = std::process::id();
+
+        let mut events = Vec::new();
+        for (pid, device) in &holders {
+            if *pid == own_pid || self.device_holders.contains(&(*pid, device.clone())) {
+                continue;
+            }
+            let Some(builder) = self.event(KeyloggingIndicator::InputDeviceAccess, *pid) else {
+                continue;
+            };
+            warn!("Process {} is reading keyboard device {}", pid, device);
+            events.push(
+                builder
+                    .device(device.clone())
+                    .device_name(devices.get(device).cloned())
+                    .build()
+                    .map_err(CollectionError::Parse)?,
+            );
+        }
+        self.device_holders = holders;
+        Ok(events)
+    }
+
+    fn collect_events(&mut self) -> Result<Vec<KeyloggingEvent>, CollectionError> {
+        let mut events = self.hook_events()?;
+        if self.config.scan_input_devices && Path::new("/proc/bus/input/devices").exists() {
+            events.extend(self.device_events()?);
+        }
+        info!("Collected {} keylogging indicators", events.len());
+        Ok(events)
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if cfg!(target_os = "windows") && self.hook_events.is_none() {
+            return Err(CollectionError::SystemApi(
+                "Keyboard hook tracing requires an ETW session (administrator rights)".to_string()
+            ));
+        }
+        if !cfg!(any(target_os = "windows", target_os = "linux")) {
+            return Err(CollectionError::SystemApi(
+                "Keylogging detection is not supported on this platform".to_string()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DataCollector<Vec<KeyloggingEvent>> for KeyloggingCollector {
+    fn collect(&mut self) -> Result<Vec<KeyloggingEvent>, CollectionError> {
+        self.collect_events()
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<KeyloggingEvent>> for KeyloggingCollector {
+    async fn collect(&mut self) -> Result<Vec<KeyloggingEvent>, CollectionError> {
+        DataCollector::collect(self)
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+impl Default for KeyloggingCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
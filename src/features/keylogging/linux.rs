@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::fs;
+
+// Keyboard event devices (/dev/input/eventN -> device name) from /proc/bus/input/devices,
+// where every device handled by the kbd handler is listed with "kbd" among its handlers
+pub fn keyboard_devices() -> HashMap<String, String> {
+    let Ok(devices) = fs::read_to_string("/proc/bus/input/devices") else {
+        return HashMap::new();
+    };
+
+    let mut keyboards = HashMap::new();
+    for block in devices.split("\n\n") {
+        let mut name = String::new();
+        let mut handlers = Vec::new();
+        for line in block.lines() {
+            if let Some(value) = line.strip_prefix("N: Name=") {
+                name = value.trim_matches('"').to_string();
+            } else if let Some(value) = line.strip_prefix("H: Handlers=") {
+                handlers = value.split_whitespace().map(str::to_string).collect();
+            }
+        }
+        if !handlers.iter().any(|handler| handler == "kbd") {
+            continue;
+        }
+        for handler in handlers.iter().filter(|handler| handler.starts_with("event")) {
+            keyboards.insert(format!("/dev/input/{}", handler), name.clone());
+        }
+    }
+    keyboards
+}
+
+// (pid, device) for every open file descriptor on one of the given devices
+pub fn device_holders(devices: &HashMap<String, String>) -> Vec<(u32, String)> {
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    let mut holders = Vec::new();
+    for process in processes.flatten() {
+        let Some(pid) = process.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        let Ok(descriptors) = fs::read_dir(process.path().join("fd")) else {
+            continue;
+        };
+        for descriptor in descriptors.flatten() {
+            let Ok(target) = fs::read_link(descriptor.path()) else {
+                continue;
+            };
+            let target = target.to_string_lossy();
+            if devices.contains_key(target.as_ref()) && !holders.iter().any(|(p, d)| *p == pid && d == target.as_ref()) {
+                holders.push((pid, target.into_owned()));
+            }
+        }
+    }
+    holders
+}
@@ -0,0 +1,7 @@
+mod collector;
+mod models;
+#[cfg(target_os = "linux")]
+mod linux;
+
+pub use collector::{KeyloggingCollector, KeyloggingConfig};
+pub use models::{KeyloggingEvent, KeyloggingEventBuilder, KeyloggingIndicator};
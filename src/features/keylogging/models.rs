@@ -0,0 +1,199 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyloggingIndicator {
+    // Windows: SetWindowsHookEx with a keyboard hook type, seen through ETW
+    KeyboardHook,
+    // Linux: a process holding a keyboard /dev/input event device open
+    InputDeviceAccess,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyloggingEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub indicator: KeyloggingIndicator,
+    pub process_id: u32,
+    pub process_name: String,
+    pub process_path: Option<String>,
+    pub process_sha256: Option<String>,
+    pub parent_pid: Option<u32>,
+    pub user: Option<String>,
+    // API reported by the ETW provider, e.g. "SetWindowsHookEx"
+    pub api: Option<String>,
+    pub hook_type: Option<u32>,
+    pub device: Option<String>,
+    pub device_name: Option<String>,
+}
+
+impl Event for KeyloggingEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.indicator {
+            KeyloggingIndicator::KeyboardHook => "keyboard_hook",
+            KeyloggingIndicator::InputDeviceAccess => "input_device_access",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::High
+    }
+}
+
+impl Identifiable for KeyloggingEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for KeyloggingEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.process_name.is_empty() {
+            return Err("Process name cannot be empty".to_string());
+        }
+        if self.indicator == KeyloggingIndicator::InputDeviceAccess && self.device.is_none() {
+            return Err("Input device access events require a device".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct KeyloggingEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    indicator: Option<KeyloggingIndicator>,
+    process_id: Option<u32>,
+    process_name: Option<String>,
+    process_path: Option<String>,
+    process_sha256: Option<String>,
+    parent_pid: Option<u32>,
+    user: Option<String>,
+    api: Option<String>,
+    hook_type: Option<u32>,
+    device: Option<String>,
+    device_name: Option<String>,
+}
+
+impl KeyloggingEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn indicator(mut self, indicator: KeyloggingIndicator) -> Self {
+        self.indicator = Some(indicator);
+        self
+    }
+
+    pub fn process_id(mut self, process_id: u32) -> Self {
+        self.process_id = Some(process_id);
+        self
+    }
+
+    pub fn process_name(mut self, process_name: String) -> Self {
+        self.process_name = Some(process_name);
+        self
+    }
+
+    pub fn process_path(mut self, process_path: Option<String>) -> Self {
+        self.process_path = process_path;
+        self
+    }
+
+    pub fn process_sha256(mut self, process_sha256: Option<String>) -> Self {
+        self.process_sha256 = process_sha256;
+        self
+    }
+
+    pub fn parent_pid(mut self, parent_pid: Option<u32>) -> Self {
+        self.parent_pid = parent_pid;
+        self
+    }
+
+    pub fn user(mut self, user: Option<String>) -> Self {
+        self.user = user;
+        self
+    }
+
+    pub fn api(mut self, api: String) -> Self {
+        self.api = Some(api);
+        self
+    }
+
+    pub fn hook_type(mut self, hook_type: u32) -> Self {
+        self.hook_type = Some(hook_type);
+        self
+    }
+
+    pub fn device(mut self, device: String) -> Self {
+        self.device = Some(device);
+        self
+    }
+
+    pub fn device_name(mut self, device_name: Option<String>) -> Self {
+        self.device_name = device_name;
+        self
+    }
+
+    pub fn build(self) -> Result<KeyloggingEvent, String> {
+        let event = KeyloggingEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            indicator: self.indicator.ok_or("indicator is required")?,
+            process_id: self.process_id.ok_or("process_id is required")?,
+            process_name: self.process_name.ok_or("process_name is required")?,
+            process_path: self.process_path,
+            process_sha256: self.process_sha256,
+            parent_pid: self.parent_pid,
+            user: self.user,
+            api: self.api,
+            hook_type: self.hook_type,
+            device: self.device,
+            device_name: self.device_name,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
@@ -0,0 +1,511 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector, Severity};
+use crate::shared::baseline::Baseline;
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::paths::expand_path;
+use crate::features::log_tail::models::{LogEvent, LogEventBuilder};
+use crate::features::log_tail::parsers::{Extractor, FieldNames, LogFormat};
+use crate::features::log_tail::tail::{file_identity, read_head, read_lines, Line};
+use crate::utils::load_config_section;
+use chrono::Utc;
+use log::{debug, info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartAt {
+    Beginning,
+    // Only what is written after the agent starts tailing
+    #[default]
+    End,
+}
+
+// Lines that continue a record, such as the frames of a stack trace, merged into it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MultilineConfig {
+    // Regex a line matches when it starts a new record; any other line continues the previous
+    pub start_pattern: String,
+    pub max_lines: usize,
+    // A record nothing was appended to for this long is complete
+    pub flush_secs: u64,
+}
+
+impl Default for MultilineConfig {
+    fn default() -> Self {
+        Self {
+            start_pattern: String::from(r"^\S"),
+            max_lines: 500,
+            flush_secs: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSourceConfig {
+    // Names the source in every record, as log_source
+    pub name: String,
+    // Path templates: ${USERPROFILE} / ${HOME}, other variables and * / ? wildcards, so rotated
+    // files (app.log.1) can be matched along with the live one. Compressed files are skipped.
+    pub paths: Vec<String>,
+    pub format: LogFormat,
+    pub fields: FieldNames,
+    pub multiline: Option<MultilineConfig>,
+    // Severity of the source's records; error, fatal and critical levels raise it
+    pub severity: Severity,
+    // Where to begin in files already present the first time the agent tails them; files
+    // appearing later are always read from the beginning
+    pub start_at: StartAt,
+}
+
+impl Default for LogSourceConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            paths: Vec::new(),
+            format: LogFormat::Plain,
+            fields: FieldNames::default(),
+            multiline: None,
+            severity: Severity::Low,
+            start_at: StartAt::End,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogTailConfig {
+    pub enabled: bool,
+    pub sources: Vec<LogSourceConfig>,
+    // Read from one file per collection at most, so a burst is shipped over several
+    pub max_bytes_per_file: u64,
+    // Longer lines are cut
+    pub max_line_bytes: usize,
+}
+
+impl Default for LogTailConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sources: Vec::new(),
+            max_bytes_per_file: 4 * 1024 * 1024,
+            max_line_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl LogTailConfig {
+    pub fn load() -> Self {
+        load_config_section("log_tail")
+    }
+
+    // What keeps a source from being tailed, for the configuration check
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.enabled {
+            return problems;
+        }
+        if self.max_bytes_per_file == 0 {
+            problems.push(String::from("log_tail.max_bytes_per_file must not be 0"));
+        }
+        if self.max_line_bytes == 0 {
+            problems.push(String::from("log_tail.max_line_bytes must not be 0"));
+        }
+        let mut names = HashSet::new();
+        for (position, source) in self.sources.iter().enumerate() {
+            let prefix = format!("log_tail.sources[{}]", position);
+            if source.name.trim().is_empty() {
+                problems.push(format!("{}.name is empty", prefix));
+            } else if !names.insert(source.name.as_str()) {
+                problems.push(format!("{}.name '{}' is used by another source", prefix, source.name));
+            }
+            if source.paths.is_empty() {
+                problems.push(format!("{}.paths is empty", prefix));
+            }
+            if let Err(e) = Extractor::new(&source.format) {
+                problems.push(format!("{}.format.pattern is not a valid regex: {}", prefix, e));
+            }
+            if let Some(multiline) = &source.multiline {
+                if let Err(e) = Regex::new(&multiline.start_pattern) {
+                    problems.push(format!("{}.multiline.start_pattern is not a valid regex: {}", prefix, e));
+                }
+                if multiline.max_lines == 0 {
+                    problems.push(format!("{}.multiline.max_lines must not be 0", prefix));
+                }
+            }
+        }
+        problems
+    }
+}
+
+// Bytes at the start of a file compared between reads to tell it was rewritten
+const HEAD_BYTES: u64 = 64;
+
+// Rotations compressed by logrotate and the like; their contents were read before
+const COMPRESSED_EXTENSIONS: [&str; 5] = ["gz", "bz2", "xz", "zst", "zip"];
+
+// A source with its format and multiline pattern compiled
+struct Source {
+    config: LogSourceConfig,
+    extractor: Extractor,
+    multiline: Option<(Regex, MultilineConfig)>,
+}
+
+// How far a file has been shipped, kept across restarts by the file's identity
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Checkpoint {
+    path: String,
+    offset: u64,
+}
+
+// A record still open to continuation lines
+struct Pending {
+    offset: u64,
+    text: String,
+    lines: usize,
+    since: Instant,
+}
+
+struct TailedFile {
+    source: usize,
+    path: String,
+    // Where the next read starts
+    offset: u64,
+    pending: Option<Pending>,
+    // Field names from the latest W3C #Fields directive
+    w3c_fields: Option<Vec<String>>,
+    // The start of the file as of the previous read
+    head: Vec<u8>,
+}
+
+impl TailedFile {
+    // Everything before this was shipped; a pending record is read again after a restart
+    fn committed(&self) -> u64 {
+        self.pending.as_ref().map_or(self.offset, |pending| pending.offset)
+    }
+}
+
+// Ships application logs (IIS, Apache, in-house services) through the event pipeline. Each
+// collection reads what was appended to every file the sources' paths match, merges
+// continuation lines into their record and extracts fields with the source's format.
+// Files are followed by identity rather than by name: a rotated file is read to its end
+// under its new name if the paths still match it, and a truncated one from its start.
+// Offsets are checkpointed in the baseline store so a restart resumes where it left off.
+pub struct LogTailCollector {
+    config: LogTailConfig,
+    sources: Vec<Source>,
+    files: HashMap<String, TailedFile>,
+    checkpoints: HashMap<String, Checkpoint>,
+    // Whether checkpoints were kept from before, or a collection already ran
+    resumed: bool,
+    host: Arc<HostIdentityService>,
+}
+
+impl LogTailCollector {
+    const CHECKPOINTS: &'static str = "log_tail";
+
+    pub fn new() -> Self {
+        Self::with_config(LogTailConfig::load())
+    }
+
+    pub fn with_config(config: LogTailConfig) -> Self {
+        let sources = config
+            .sources
+            .iter()
+            .filter_map(|source| {
+                let extractor = Extractor::new(&source.format)
+                    .map_err(|e| warn!("Skipping log source {}: {}", source.name, e))
+                    .ok()?;
+                let multiline = match &source.multiline {
+                    Some(multiline) => Some((
+                        Regex::new(&multiline.start_pattern)
+                            .map_err(|e| warn!("Skipping log source {}: {}", source.name, e))
+                            .ok()?,
+                        multiline.clone(),
+                    )),
+                    None => None,
+                };
+                Some(Source {
+                    config: source.clone(),
+                    extractor,
+                    multiline,
+                })
+            })
+            .collect();
+        let checkpoints: Option<HashMap<String, Checkpoint>> = Baseline::global().load(Self::CHECKPOINTS);
+
+        Self {
+            config,
+            sources,
+            files: HashMap::new(),
+            resumed: checkpoints.is_some(),
+            checkpoints: checkpoints.unwrap_or_default(),
+            host: HostIdentityService::global(),
+        }
+    }
+
+    fn event(&self, source: &Source, path: &str, offset: u64, text: String, lines: usize, w3c_fields: &mut Option<Vec<String>>) -> Option<LogEvent> {
+        let extracted = source.extractor.extract(&text, &source.config.fields, w3c_fields)?;
+        let level = extracted.level.clone();
+        let severity = level
+            .as_deref()
+            .map_or(source.config.severity, |level| level_severity(level).max(source.config.severity));
+        let event = LogEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(extracted.timestamp)
+            .source(self.host.name())
+            .category(String::from("log"))
+            .log_source(source.config.name.clone())
+            .path(path.to_string())
+            .offset(offset)
+            .lines(lines)
+            .message(extracted.message.unwrap_or(text))
+            .level(level)
+            .severity(severity)
+            .read_at(Utc::now())
+            .fields(extracted.fields)
+            .build();
+        match event {
+            Ok(event) => Some(event),
+            Err(e) => {
+                debug!("Dropping a record of {} at {}: {}", path, offset, e);
+                None
+            }
+        }
+    }
+
+    // Reads what was appended to one file and turns it into records
+    fn tail(&mut self, identity: &str, file: &mut File, events: &mut Vec<LogEvent>) {
+        let Some(mut tailed) = self.files.remove(identity) else {
+            return;
+        };
+        let source = &self.sources[tailed.source];
+        let length = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        let head = read_head(file, HEAD_BYTES.min(tailed.offset)).unwrap_or_default();
+        if length < tailed.offset || !head.starts_with(&tailed.head) {
+            info!("{} was truncated, reading it from the start", tailed.path);
+            tailed.offset = 0;
+            tailed.pending = None;
+            tailed.w3c_fields = None;
+            tailed.head = Vec::new();
+        }
+
+        let lines = match read_lines(file, tailed.offset, self.config.max_bytes_per_file, self.config.max_line_bytes) {
+            Ok((lines, offset)) => {
+                tailed.offset = offset;
+                lines
+            }
+            Err(e) => {
+                debug!("Failed to read {}: {}", tailed.path, e);
+                Vec::new()
+            }
+        };
+
+        let mut records = Vec::new();
+        match &source.multiline {
+            None => records.extend(lines.into_iter().map(|line| (line.offset, line.text, 1))),
+            Some((start, multiline)) => {
+                for Line { offset, text } in lines {
+                    match tailed.pending.as_mut() {
+                        Some(pending) if !start.is_match(&text) && pending.lines < multiline.max_lines => {
+                            pending.text.push('\n');
+                            pending.text.push_str(&text);
+                            pending.lines += 1;
+                            pending.since = Instant::now();
+                        }
+                        _ => {
+                            if let Some(pending) = tailed.pending.take() {
+                                records.push((pending.offset, pending.text, pending.lines));
+                            }
+                            tailed.pending = Some(Pending {
+                                offset,
+                                text,
+                                lines: 1,
+                                since: Instant::now(),
+                            });
+                        }
+                    }
+                }
+                let flush = Duration::from_secs(multiline.flush_secs);
+                if tailed.pending.as_ref().is_some_and(|pending| pending.since.elapsed() >= flush) {
+                    let pending = tailed.pending.take().expect("pending record");
+                    records.push((pending.offset, pending.text, pending.lines));
+                }
+            }
+        }
+
+        for (offset, text, lines) in records {
+            events.extend(self.event(source, &tailed.path, offset, text, lines, &mut tailed.w3c_fields));
+        }
+        if (tailed.head.len() as u64) < HEAD_BYTES.min(tailed.offset) {
+            tailed.head = read_head(file, HEAD_BYTES.min(tailed.offset)).unwrap_or_default();
+        }
+        self.files.insert(identity.to_string(), tailed);
+    }
+
+    // The last #Fields directive before `offset`, for a W3C file resumed in the middle
+    fn w3c_fields_before(file: &mut File, offset: u64, max_line_bytes: usize) -> Option<Vec<String>> {
+        let (lines, _) = read_lines(file, 0, offset, max_line_bytes).ok()?;
+        lines
+            .iter()
+            .rev()
+            .find_map(|line| line.text.strip_prefix("#Fields:"))
+            .map(|names| names.split_whitespace().map(str::to_string).collect())
+    }
+
+    fn collect_records(&mut self) -> Result<Vec<LogEvent>, CollectionError> {
+        let mut events = Vec::new();
+        let mut seen = HashSet::new();
+        for index in 0..self.sources.len() {
+            let paths: Vec<String> = self.sources[index]
+                .config
+                .paths
+                .iter()
+                .flat_map(|template| expand_path(template))
+                .collect();
+            for path in paths {
+                let compressed = Path::new(&path)
+                    .extension()
+                    .is_some_and(|extension| COMPRESSED_EXTENSIONS.iter().any(|compressed| extension.eq_ignore_ascii_case(compressed)));
+                if compressed || !Path::new(&path).is_file() {
+                    continue;
+                }
+                let Ok(mut file) = File::open(&path) else {
+                    debug!("Cannot open {}", path);
+                    continue;
+                };
+                let Ok(identity) = file_identity(&file) else {
+                    continue;
+                };
+                // A file matched by several paths or sources is read once, by the first
+                if !seen.insert(identity.clone()) {
+                    continue;
+                }
+
+                if let Some(tailed) = self.files.get_mut(&identity) {
+                    if tailed.path != path {
+                        debug!("{} was renamed to {}", tailed.path, path);
+                        tailed.path = path.clone();
+                    }
+                } else {
+                    let source = &self.sources[index];
+                    let offset = match self.checkpoints.get(&identity) {
+                        Some(checkpoint) => checkpoint.offset,
+                        None if !self.resumed && source.config.start_at == StartAt::End => {
+                            file.metadata().map(|metadata| metadata.len()).unwrap_or(0)
+                        }
+                        None => 0,
+                    };
+                    let w3c_fields = if source.extractor.w3c() && offset > 0 {
+                        Self::w3c_fields_before(&mut file, offset, self.config.max_line_bytes)
+                    } else {
+                        None
+                    };
+                    self.files.insert(
+                        identity.clone(),
+                        TailedFile {
+                            source: index,
+                            path: path.clone(),
+                            offset,
+                            pending: None,
+                            w3c_fields,
+                            head: Vec::new(),
+                        },
+                    );
+                }
+                self.tail(&identity, &mut file, &mut events);
+            }
+        }
+
+        // Files deleted or no longer matched are forgotten
+        self.files.retain(|identity, _| seen.contains(identity));
+        let checkpoints: HashMap<String, Checkpoint> = self
+            .files
+            .iter()
+            .map(|(identity, tailed)| {
+                (
+                    identity.clone(),
+                    Checkpoint {
+                        path: tailed.path.clone(),
+                        offset: tailed.committed(),
+                    },
+                )
+            })
+            .collect();
+        if !self.resumed || checkpoints != self.checkpoints {
+            Baseline::global().save(Self::CHECKPOINTS, &checkpoints);
+            self.checkpoints = checkpoints;
+        }
+        self.resumed = true;
+
+        if !events.is_empty() {
+            info!("Read {} log records from {} files", events.len(), self.files.len());
+        }
+        Ok(events)
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if self.sources.len() < self.config.sources.len() {
+            return Err(CollectionError::Parse(format!(
+                "{} log sources have an invalid format or multiline pattern",
+                self.config.sources.len() - self.sources.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+// Severity conventionally meant by a log level name
+fn level_severity(level: &str) -> Severity {
+    let level = level.to_ascii_lowercase();
+    if ["fatal", "crit", "emerg", "alert", "panic"].iter().any(|prefix| level.starts_with(prefix)) {
+        Severity::High
+    } else if level.starts_with("err") || level == "severe" {
+        Severity::Medium
+    } else {
+        Severity::Low
+    }
+}
+
+impl DataCollector<Vec<LogEvent>> for LogTailCollector {
+    fn collect(&mut self) -> Result<Vec<LogEvent>, CollectionError> {
+        self.collect_records()
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<LogEvent>> for LogTailCollector {
+    async fn collect(&mut self) -> Result<Vec<LogEvent>, CollectionError> {
+        DataCollector::collect(self)
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+impl Default for LogTailCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
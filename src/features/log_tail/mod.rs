@@ -0,0 +1,8 @@
+mod collector;
+mod models;
+mod parsers;
+mod tail;
+
+pub use collector::{LogSourceConfig, LogTailCollector, LogTailConfig, MultilineConfig, StartAt};
+pub use models::{LogEvent, LogEventBuilder};
+pub use parsers::{parse_timestamp, Extracted, Extractor, FieldNames, LogFormat};
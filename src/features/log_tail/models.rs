@@ -0,0 +1,183 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+// One record read from a tailed log file: a line, or several merged by the source's
+// multiline pattern, with what its extractor found in it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    // Configured name of the log source, such as iis or apache_access
+    pub log_source: String,
+    pub path: String,
+    // Byte offset of the record in the file
+    pub offset: u64,
+    pub lines: usize,
+    pub message: String,
+    pub level: Option<String>,
+    pub severity: Severity,
+    pub read_at: DateTime<Utc>,
+    pub fields: Map<String, Value>,
+}
+
+impl Event for LogEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        "log_record"
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl Identifiable for LogEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for LogEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.log_source.is_empty() {
+            return Err("Log source cannot be empty".to_string());
+        }
+
+        if self.path.is_empty() {
+            return Err("Log file path cannot be empty".to_string());
+        }
+
+        if self.lines == 0 {
+            return Err("A log record has at least one line".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct LogEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    log_source: Option<String>,
+    path: Option<String>,
+    offset: u64,
+    lines: usize,
+    message: Option<String>,
+    level: Option<String>,
+    severity: Option<Severity>,
+    read_at: Option<DateTime<Utc>>,
+    fields: Map<String, Value>,
+}
+
+impl LogEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: Option<DateTime<Utc>>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn log_source(mut self, log_source: String) -> Self {
+        self.log_source = Some(log_source);
+        self
+    }
+
+    pub fn path(mut self, path: String) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn lines(mut self, lines: usize) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    pub fn message(mut self, message: String) -> Self {
+        self.message = Some(message);
+        self
+    }
+
+    pub fn level(mut self, level: Option<String>) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    pub fn read_at(mut self, read_at: DateTime<Utc>) -> Self {
+        self.read_at = Some(read_at);
+        self
+    }
+
+    pub fn fields(mut self, fields: Map<String, Value>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    // Without a timestamp of its own the record is dated when it was read
+    pub fn build(self) -> Result<LogEvent, String> {
+        let read_at = self.read_at.unwrap_or_else(Utc::now);
+        let event = LogEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.unwrap_or(read_at),
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            log_source: self.log_source.ok_or("log_source is required")?,
+            path: self.path.ok_or("path is required")?,
+            offset: self.offset,
+            lines: self.lines,
+            message: self.message.ok_or("message is required")?,
+            level: self.level,
+            severity: self.severity.unwrap_or(Severity::Low),
+            read_at,
+            fields: self.fields,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
@@ -0,0 +1,191 @@
+use crate::features::network::{parse_dns_debug_line, parse_firewall_log_line};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+// How the records of a source are turned into fields
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LogFormat {
+    // The record is only the message
+    #[default]
+    Plain,
+    // Named capture groups become fields; records that do not match keep only the message
+    Regex { pattern: String },
+    // One JSON object per record, its members becoming fields
+    Json,
+    // W3C extended log format (IIS and others): space-separated values named by the latest
+    // #Fields directive of the file
+    W3c,
+    // Windows Firewall log (pfirewall.log), fields named like network connections
+    WindowsFirewall,
+    // Packets of the Windows DNS server debug log (dns.log); other lines are skipped
+    DnsDebug,
+}
+
+// Fields taken from one record
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Extracted {
+    pub fields: Map<String, Value>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub level: Option<String>,
+    pub message: Option<String>,
+}
+
+// Where the time, level and message of a record are found among its fields
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FieldNames {
+    pub timestamp: String,
+    // chrono format of the timestamp field, for values that are not RFC 3339; times without
+    // an offset are taken as the host's local time
+    pub timestamp_format: Option<String>,
+    pub level: String,
+    pub message: String,
+}
+
+impl Default for FieldNames {
+    fn default() -> Self {
+        Self {
+            timestamp: String::from("timestamp"),
+            timestamp_format: None,
+            level: String::from("level"),
+            message: String::from("message"),
+        }
+    }
+}
+
+// A source's format, compiled
+pub enum Extractor {
+    Plain,
+    Regex(Regex),
+    Json,
+    W3c,
+    WindowsFirewall,
+    DnsDebug,
+}
+
+impl Extractor {
+    pub fn new(format: &LogFormat) -> Result<Self, String> {
+        Ok(match format {
+            LogFormat::Plain => Extractor::Plain,
+            LogFormat::Regex { pattern } => Extractor::Regex(Regex::new(pattern).map_err(|e| e.to_string())?),
+            LogFormat::Json => Extractor::Json,
+            LogFormat::W3c => Extractor::W3c,
+            LogFormat::WindowsFirewall => Extractor::WindowsFirewall,
+            LogFormat::DnsDebug => Extractor::DnsDebug,
+        })
+    }
+
+    // Whether records are named by #Fields directives
+    pub fn w3c(&self) -> bool {
+        matches!(self, Extractor::W3c | Extractor::WindowsFirewall)
+    }
+
+    // What the record holds; None for records that carry no event, such as W3C directives,
+    // which update `w3c_fields` instead
+    pub fn extract(&self, record: &str, names: &FieldNames, w3c_fields: &mut Option<Vec<String>>) -> Option<Extracted> {
+        if self.w3c() {
+            if let Some(directive) = record.strip_prefix('#') {
+                if let Some(names) = directive.strip_prefix("Fields:") {
+                    *w3c_fields = Some(names.split_whitespace().map(str::to_string).collect());
+                }
+                return None;
+            }
+        }
+        let fields = match self {
+            Extractor::Plain => Map::new(),
+            Extractor::Regex(pattern) => pattern
+                .captures(record)
+                .map(|captures| {
+                    pattern
+                        .capture_names()
+                        .flatten()
+                        .filter_map(|name| Some((name.to_string(), Value::String(captures.name(name)?.as_str().to_string()))))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Extractor::Json => match serde_json::from_str::<Value>(record.trim()) {
+                Ok(Value::Object(fields)) => fields,
+                _ => Map::new(),
+            },
+            Extractor::W3c => w3c_record(record, w3c_fields.as_deref()?),
+            // Network logs become the normalized records, dated by their own timestamp
+            Extractor::WindowsFirewall => {
+                let record = parse_firewall_log_line(record, w3c_fields.as_deref()?)?;
+                return normalized(&record, record.timestamp);
+            }
+            Extractor::DnsDebug => {
+                let record = parse_dns_debug_line(record, names.timestamp_format.as_deref())?;
+                return normalized(&record, record.timestamp);
+            }
+        };
+        Some(Extracted {
+            timestamp: fields
+                .get(&names.timestamp)
+                .and_then(Value::as_str)
+                .and_then(|value| parse_timestamp(value, names.timestamp_format.as_deref()))
+                .or_else(|| fields.get(&names.timestamp).and_then(Value::as_i64).and_then(epoch_timestamp))
+                .or_else(|| matches!(self, Extractor::W3c).then(|| w3c_timestamp(&fields)).flatten()),
+            level: fields.get(&names.level).and_then(Value::as_str).map(str::to_string),
+            message: fields.get(&names.message).and_then(Value::as_str).map(str::to_string),
+            fields,
+        })
+    }
+}
+
+fn normalized<T: Serialize>(record: &T, timestamp: DateTime<Utc>) -> Option<Extracted> {
+    let Ok(Value::Object(fields)) = serde_json::to_value(record) else {
+        return None;
+    };
+    Some(Extracted {
+        fields,
+        timestamp: Some(timestamp),
+        level: None,
+        message: None,
+    })
+}
+
+// W3C values are separated by single spaces, "-" standing for no value
+fn w3c_record(record: &str, names: &[String]) -> Map<String, Value> {
+    names
+        .iter()
+        .zip(record.split(' '))
+        .filter(|(_, value)| *value != "-")
+        .map(|(name, value)| (name.clone(), Value::String(value.to_string())))
+        .collect()
+}
+
+// W3C logs date records with separate date and time fields, always in UTC
+fn w3c_timestamp(fields: &Map<String, Value>) -> Option<DateTime<Utc>> {
+    let date = fields.get("date")?.as_str()?;
+    let time = fields.get("time")?.as_str()?;
+    let naive = NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S").ok()?;
+    Some(Utc.from_utc_datetime(&naive))
+}
+
+// Seconds or, for values too large to be seconds, milliseconds since the epoch
+fn epoch_timestamp(value: i64) -> Option<DateTime<Utc>> {
+    if value > 100_000_000_000 {
+        DateTime::from_timestamp_millis(value)
+    } else {
+        DateTime::from_timestamp(value, 0)
+    }
+}
+
+pub fn parse_timestamp(value: &str, format: Option<&str>) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+    let format = format?;
+    if let Ok(timestamp) = DateTime::parse_from_str(value, format) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, format).ok()?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+
+// What identifies a file across renames: the device and inode on Unix, the volume serial
+// number and file index on Windows. A rotated file keeps its identity under its new name,
+// the file created in its place gets a new one.
+#[cfg(unix)]
+pub fn file_identity(file: &File) -> io::Result<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = file.metadata()?;
+    Ok(format!("{}:{}", metadata.dev(), metadata.ino()))
+}
+
+#[cfg(windows)]
+pub fn file_identity(file: &File) -> io::Result<String> {
+    use std::os::windows::io::AsRawHandle;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::Storage::FileSystem::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION};
+
+    let mut information = BY_HANDLE_FILE_INFORMATION::default();
+    unsafe {
+        if !GetFileInformationByHandle(HANDLE(file.as_raw_handle() as isize), &mut information).as_bool() {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(format!(
+        "{:08x}:{:08x}{:08x}",
+        information.dwVolumeSerialNumber, information.nFileIndexHigh, information.nFileIndexLow
+    ))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn file_identity(file: &File) -> io::Result<String> {
+    let created = file.metadata()?.created()?;
+    Ok(format!("{:?}", created))
+}
+
+// The first `length` bytes of a file, fewer if it is shorter. A file rewritten from the start
+// between two reads, without ever being shorter than what was read, shows as a different head.
+pub fn read_head(file: &mut File, length: u64) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut head = Vec::new();
+    file.by_ref().take(length).read_to_end(&mut head)?;
+    Ok(head)
+}
+
+// One complete line and the offset it starts at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub offset: u64,
+    pub text: String,
+}
+
+// The lines completed since `offset`, reading at most `max_bytes`, and the offset after the
+// last of them. A line still being written is left for the next read, unless it alone fills
+// `max_bytes`; lines longer than `max_line_bytes` are cut there.
+pub fn read_lines(file: &mut File, offset: u64, max_bytes: u64, max_line_bytes: usize) -> io::Result<(Vec<Line>, u64)> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buffer = Vec::new();
+    file.by_ref().take(max_bytes).read_to_end(&mut buffer)?;
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while let Some(end) = buffer[start..].iter().position(|byte| *byte == b'\n') {
+        let mut line = &buffer[start..start + end];
+        if offset + start as u64 == 0 {
+            line = line.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(line);
+        }
+        lines.push(Line {
+            offset: offset + start as u64,
+            text: decode_line(line.strip_suffix(b"\r").unwrap_or(line), max_line_bytes),
+        });
+        start += end + 1;
+    }
+    if start == 0 && !buffer.is_empty() && buffer.len() as u64 == max_bytes {
+        lines.push(Line {
+            offset,
+            text: decode_line(&buffer, max_line_bytes),
+        });
+        start = buffer.len();
+    }
+    Ok((lines, offset + start as u64))
+}
+
+fn decode_line(bytes: &[u8], max_line_bytes: usize) -> String {
+    let mut text = String::from_utf8_lossy(&bytes[..bytes.len().min(max_line_bytes)]).into_owned();
+    // A cut may split a character, which decodes to a replacement at the end
+    if bytes.len() > max_line_bytes && text.ends_with('\u{fffd}') {
+        text.pop();
+    }
+    text
+}
@@ -4,3 +4,23 @@ pub mod service;
 pub mod system_metrics;
 pub mod filesystem;
 pub mod registry;
+pub mod response;
+pub mod exec_trace;
+pub mod ingest;
+pub mod scheduled_tasks;
+pub mod osquery;
+pub mod honeypot;
+pub mod usb;
+pub mod deception;
+pub mod capture;
+pub mod keylogging;
+pub mod print_spooler;
+pub mod boot;
+pub mod persistence;
+pub mod log_tail;
+pub mod snmp_trap;
+pub mod directory;
+pub mod tls_probe;
+pub mod detection;
+pub mod incident;
+pub mod control;
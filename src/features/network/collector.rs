@@ -1,131 +1,510 @@
 use crate::shared::traits::{AsyncDataCollector, DataCollector};
 use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::baseline::Baseline;
+use crate::shared::process_resolver::{ProcessDetails, ProcessResolver};
+use crate::shared::traits::Severity;
 use crate::features::network::models::{
+    ListeningPort, ListeningPortChange, ListeningPortEvent, ListeningPortEventBuilder,
     NetworkInformation, NetworkConnectionInformation, 
-    NetworkMetrics, NetworkMetricsBuilder
+    NetworkMetrics, NetworkMetricsBuilder, ProcessNetworkUsage, ProcessNetworkUsageBuilder
 };
-use log::info;
-use sysinfo::{System, Networks};
-use chrono::Utc;
+use crate::utils::load_config_section;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sysinfo::Networks;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
 use uuid::Uuid;
 
+#[cfg(target_os = "linux")]
+fn connection_table() -> Result<Vec<NetworkConnectionInformation>, CollectionError> {
+    Ok(crate::features::network::linux::connections())
+}
+
+#[cfg(windows)]
+use crate::features::network::win32::connections as connection_table;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn connection_table() -> Result<Vec<NetworkConnectionInformation>, CollectionError> {
+    Ok(Vec::new())
+}
+
+const CONNECTIONS_BASELINE: &str = "network_connections";
+const LISTENING_PORTS_BASELINE: &str = "network_listening_ports";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub listening_ports: ListeningPortConfig,
+    pub process_usage: ProcessUsageConfig,
+}
+
+impl NetworkConfig {
+    pub fn load() -> Self {
+        load_config_section("network")
+    }
+}
+
+// Baseline of the ports this host listens on, with an event whenever one opens or closes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListeningPortConfig {
+    pub enabled: bool,
+    // Unconnected UDP sockets count as listening too; clients bind them as well, so they are
+    // noisier than TCP listeners
+    pub include_udp: bool,
+    // Collections in a row a port must be missing from before it counts as closed, so a
+    // service restarting between two collections is not reported
+    pub missing_collections: u32,
+    pub ignore_ports: Vec<u16>,
+    // Process names, compared case-insensitively
+    pub ignore_processes: Vec<String>,
+    // Ports that should always be listening, such as the management agent's; one of them
+    // closing is high severity
+    pub required_ports: Vec<u16>,
+}
+
+impl Default for ListeningPortConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            include_udp: false,
+            missing_collections: 2,
+            ignore_ports: Vec::new(),
+            ignore_processes: Vec::new(),
+            required_ports: Vec::new(),
+        }
+    }
+}
+
+impl ListeningPortConfig {
+    // What keeps the baseline from working, for the configuration check
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.enabled && self.missing_collections == 0 {
+            problems.push(String::from("network.listening_ports.missing_collections must be at least 1"));
+        }
+        problems
+    }
+
+    fn ignores(&self, port: &ListeningPort) -> bool {
+        self.ignore_ports.contains(&port.port)
+            || port.process_name.as_deref().is_some_and(|name| {
+                self.ignore_processes.iter().any(|ignored| ignored.eq_ignore_ascii_case(name))
+            })
+    }
+}
+
+// Bytes each process sends and receives, summed from the byte counters of its TCP sockets
+// (tcp_info on Linux, extended TCP statistics on Windows)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessUsageConfig {
+    pub enabled: bool,
+    // Processes that moved less than this since the previous collection are left out
+    pub min_bytes: u64,
+    // What a process sends is summed over windows this long
+    pub window_secs: u64,
+    // Sending this much within the window makes the process's usage high severity, once
+    // until its volume falls back under it
+    pub upload_alert_bytes: u64,
+}
+
+impl Default for ProcessUsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_bytes: 1024 * 1024,
+            window_secs: 3600,
+            upload_alert_bytes: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+impl ProcessUsageConfig {
+    // What keeps usage from being measured or alerted on, for the configuration check
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.enabled {
+            return problems;
+        }
+        if self.window_secs == 0 {
+            problems.push(String::from("network.process_usage.window_secs must be greater than 0"));
+        }
+        if self.upload_alert_bytes == 0 {
+            problems.push(String::from("network.process_usage.upload_alert_bytes must be greater than 0"));
+        }
+        problems
+    }
+}
+
+// Remote addresses listed in a process's usage
+const USAGE_REMOTE_ADDRESSES: usize = 10;
+
+// Last counters of a socket, and the connection its owner was taken from
+struct SocketUsage {
+    sent: u64,
+    received: u64,
+    owner: NetworkConnectionInformation,
+}
+
+// One process's traffic within a collection
+struct ProcessTraffic<'a> {
+    // The connection the owner is taken from
+    connection: &'a NetworkConnectionInformation,
+    sent: u64,
+    received: u64,
+    connections: usize,
+    remote_addresses: HashMap<&'a str, u64>,
+}
+
 pub struct NetworkCollector {
-    sys: System,
-    hostname: String,
+    config: NetworkConfig,
+    host: Arc<HostIdentityService>,
+    resolver: Arc<ProcessResolver>,
+    // First sighting of every open connection, saved to the baseline store so connections
+    // that outlive an agent restart keep their original first_seen
+    first_seen: HashMap<String, DateTime<Utc>>,
+    // Listening ports by key, None until the first collection makes the baseline
+    listening: Option<HashMap<String, ListeningPort>>,
+    port_changes: Vec<ListeningPortEvent>,
+    // Sent and received counters of every socket at the previous collection, None before the
+    // first one: the sockets open at startup carried traffic at unknown times
+    socket_traffic: Option<HashMap<String, SocketUsage>>,
+    // Bytes each process sent per collection within the window, by process key
+    uploads: HashMap<String, VecDeque<(DateTime<Utc>, u64)>>,
+    last_usage: Option<DateTime<Utc>>,
+    process_usage: Vec<ProcessNetworkUsage>,
 }
 
 impl NetworkCollector {
     pub fn new() -> Self {
-        let sys = System::new();
-        let hostname = whoami::hostname();
-        Self { sys, hostname }
+        Self::with_config(NetworkConfig::load())
     }
 
-    pub fn collect_interface_info(&self) -> Result<Vec<NetworkInformation>, CollectionError> {
-        let mut networks = Networks::new();
-        networks.refresh(true);
-        let mut interfaces = Vec::new();
-        
-        // Get network interfaces using sysinfo
-        interfaces = networks
+    pub fn with_config(config: NetworkConfig) -> Self {
+        let listening = if config.listening_ports.enabled {
+            Baseline::global().load(LISTENING_PORTS_BASELINE)
+        } else {
+            None
+        };
+        Self {
+            config,
+            host: HostIdentityService::global(),
+            resolver: ProcessResolver::global(),
+            first_seen: Baseline::global().load(CONNECTIONS_BASELINE).unwrap_or_default(),
+            listening,
+            port_changes: Vec::new(),
+            socket_traffic: None,
+            uploads: HashMap::new(),
+            last_usage: None,
+            process_usage: Vec::new(),
+        }
+    }
+
+    // Opened / closed listening port events found by the last collections, cleared on read
+    pub fn take_port_changes(&mut self) -> Vec<ListeningPortEvent> {
+        std::mem::take(&mut self.port_changes)
+    }
+
+    // Per-process traffic of the last collections, cleared on read
+    pub fn take_process_usage(&mut self) -> Vec<ProcessNetworkUsage> {
+        std::mem::take(&mut self.process_usage)
+    }
+
+    fn connection_key(connection: &NetworkConnectionInformation) -> String {
+        format!(
+            "{}|{}|{}|{}|{}|{}",
+            connection.protocol,
+            connection.local_address,
+            connection.local_port,
+            connection.remote_address,
+            connection.remote_port,
+            connection.process_name.as_deref().unwrap_or_default(),
+        )
+    }
+
+    // Stamps first_seen and forgets connections that have closed
+    fn track_connections(&mut self, connections: &mut [NetworkConnectionInformation]) {
+        let now = Utc::now();
+        let mut current = HashMap::with_capacity(connections.len());
+        for connection in connections.iter_mut() {
+            let key = Self::connection_key(connection);
+            let first_seen = self.first_seen.get(&key).copied().unwrap_or(now);
+            connection.first_seen = Some(first_seen);
+            current.insert(key, first_seen);
+        }
+
+        let changed = current.len() != self.first_seen.len() || current.keys().any(|key| !self.first_seen.contains_key(key));
+        self.first_seen = current;
+        if changed {
+            Baseline::global().save(CONNECTIONS_BASELINE, &self.first_seen);
+        }
+    }
+
+    fn listening_ports(&self, connections: &[NetworkConnectionInformation]) -> HashMap<String, ListeningPort> {
+        let settings = &self.config.listening_ports;
+        connections
             .iter()
-            .map(|(interface_name, data)| NetworkInformation {
-                interface_name: interface_name.to_string(),
-                mac_address: data.mac_address().to_string(),
-                ipv4_addresses: Vec::new(), // Will be populated below
-                ipv6_addresses: Vec::new(), // Will be populated below
-                received_bytes: data.received(),
-                transmitted_bytes: data.transmitted(),
-                received_packets: data.packets_received(),
-                transmitted_packets: data.packets_transmitted(),
-                errors: data.errors_on_received() + data.errors_on_transmitted(),
+            .filter(|connection| match connection.protocol.as_str() {
+                "TCP" => connection.state == "LISTENING",
+                "UDP" => settings.include_udp && connection.remote_port == 0,
+                _ => false,
             })
-            .collect();
+            .map(ListeningPort::from)
+            .filter(|port| !settings.ignores(port))
+            .map(|port| (port.key(), port))
+            .collect()
+    }
 
-        // Get IP addresses using ipconfig on Windows
-        if cfg!(target_os = "windows") {
-            let output = std::process::Command::new("ipconfig")
-                .output()
-                .map_err(|e| CollectionError::SystemApi(format!("Failed to execute ipconfig: {}", e)))?;
-            
-            let output_str = String::from_utf8_lossy(&output.stdout);
-            let mut current_interface = None;
-            let mut current_ipv4 = Vec::new();
-            let mut current_ipv6 = Vec::new();
-            
-            for line in output_str.lines() {
-                let line = line.trim();
-                if line.ends_with(':') {
-                    // New interface section
-                    if let Some(interface_name) = current_interface.take() {
-                        if let Some(interface) = interfaces.iter_mut()
-                            .find(|i| i.interface_name.contains(&interface_name)) {
-                            interface.ipv4_addresses = current_ipv4.clone();
-                            interface.ipv6_addresses = current_ipv6.clone();
-                        }
-                        current_ipv4.clear();
-                        current_ipv6.clear();
-                    }
-                    current_interface = Some(line[..line.len()-1].to_string());
-                } else if let Some(_) = &current_interface {
-                    if line.contains("IPv4") && line.contains(':') {
-                        if let Some(ip) = line.split(':').nth(1) {
-                            current_ipv4.push(ip.trim().to_string());
-                        }
-                    } else if line.contains("IPv6") && line.contains(':') {
-                        if let Some(ip) = line.split(':').nth(1) {
-                            current_ipv6.push(ip.trim().to_string());
-                        }
-                    }
-                }
+    fn port_change(&self, change: ListeningPortChange, port: &ListeningPort) -> Result<ListeningPortEvent, CollectionError> {
+        // Anything reachable from other hosts that starts listening may be a backdoor; a
+        // service that stops listening may have been killed
+        let severity = match change {
+            ListeningPortChange::Opened if port.exposed() => Severity::High,
+            ListeningPortChange::Closed if self.config.listening_ports.required_ports.contains(&port.port) => Severity::High,
+            _ => Severity::Medium,
+        };
+        ListeningPortEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("network"))
+            .change(change)
+            .port(port.clone())
+            .severity(severity)
+            .build()
+            .map_err(CollectionError::Parse)
+    }
+
+    // Compares the listening sockets with the baseline; the first collection on a host is the
+    // baseline itself, and a port counts as closed only after missing_collections misses
+    fn track_listening_ports(&mut self, connections: &[NetworkConnectionInformation]) -> Result<(), CollectionError> {
+        let current = self.listening_ports(connections);
+        let Some(known) = self.listening.take() else {
+            info!("Listening port baseline holds {} ports", current.len());
+            Baseline::global().save(LISTENING_PORTS_BASELINE, &current);
+            self.listening = Some(current);
+            return Ok(());
+        };
+
+        let mut changes = Vec::new();
+        let mut listening = HashMap::with_capacity(current.len());
+        for (key, mut port) in current {
+            match known.get(&key) {
+                Some(previous) => port.first_seen = previous.first_seen,
+                None => changes.push(self.port_change(ListeningPortChange::Opened, &port)?),
             }
-            
-            // Handle the last interface
-            if let Some(interface_name) = current_interface {
-                if let Some(interface) = interfaces.iter_mut()
-                    .find(|i| i.interface_name.contains(&interface_name)) {
-                    interface.ipv4_addresses = current_ipv4;
-                    interface.ipv6_addresses = current_ipv6;
-                }
+            listening.insert(key, port);
+        }
+        let missing_collections = self.config.listening_ports.missing_collections.max(1);
+        for (key, port) in &known {
+            if listening.contains_key(key) {
+                continue;
             }
+            let mut port = port.clone();
+            port.missed += 1;
+            if port.missed >= missing_collections {
+                changes.push(self.port_change(ListeningPortChange::Closed, &port)?);
+            } else {
+                listening.insert(key.clone(), port);
+            }
+        }
+        if !changes.is_empty() {
+            info!("{} listening ports were opened or closed", changes.len());
         }
 
-        Ok(interfaces)
+        if listening != known {
+            Baseline::global().save(LISTENING_PORTS_BASELINE, &listening);
+        }
+        self.listening = Some(listening);
+        self.port_changes.extend(changes);
+        Ok(())
     }
 
-    pub fn collect_connections(&self) -> Result<Vec<NetworkConnectionInformation>, CollectionError> {
-        let mut connections = Vec::new();
-        
-        if cfg!(target_os = "windows") {
-            let output = std::process::Command::new("netstat")
-                .args(["-n", "-o"])
-                .output()
-                .map_err(|e| CollectionError::SystemApi(format!("Failed to execute netstat: {}", e)))?;
-            
-            let output_str = String::from_utf8_lossy(&output.stdout);
-            
-            for line in output_str.lines().skip(4) { // Skip header lines
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 5 {
-                    let protocol = parts[0].to_string();
-                    if let Some((local_addr, local_p)) = parts[1].rsplit_once(':') {
-                        if let Some((remote_addr, remote_p)) = parts[2].rsplit_once(':') {
-                            if let (Ok(local_port), Ok(remote_port)) = (local_p.parse::<u16>(), remote_p.parse::<u16>()) {
-                                let mut conn = NetworkConnectionInformation {
-                                    protocol,
-                                    local_address: local_addr.to_string(),
-                                    local_port,
-                                    remote_address: remote_addr.to_string(),
-                                    remote_port,
-                                    state: parts[3].to_string(),
-                                    process_id: parts.get(4).and_then(|pid| pid.parse().ok()),
-                                };
-                                connections.push(conn);
-                            }
-                        }
-                    }
-                }
+    // Turns the per-socket counters into what each process sent and received since the
+    // previous collection. A socket first seen now counts in full, and one whose counters went
+    // down was replaced by a new one on the same ports. A socket its process closed counts
+    // toward the owner it had, as long as the kernel still lists it with its final counters
+    // (Linux, until the shutdown completes). Not counted: sockets opened and closed between two
+    // collections, the last bytes of a socket no longer listed when collected, and on Windows
+    // whatever a socket moved before the collection that first saw it, since its statistics
+    // are only switched on then.
+    fn track_process_usage(&mut self, connections: &[NetworkConnectionInformation]) -> Result<(), CollectionError> {
+        let settings = &self.config.process_usage;
+        let now = Utc::now();
+        let interval_secs = self.last_usage.map_or(0, |last| (now - last).num_seconds().max(0) as u64);
+        self.last_usage = Some(now);
+
+        let previous = self.socket_traffic.take();
+        let mut sockets = HashMap::new();
+        let mut processes: HashMap<String, ProcessTraffic> = HashMap::new();
+        for connection in connections {
+            let (Some(sent), Some(received)) = (connection.bytes_sent, connection.bytes_received) else {
+                continue;
+            };
+            // The addresses alone, a closed socket has lost its process
+            let socket = format!(
+                "{}|{}:{}|{}:{}",
+                connection.protocol,
+                connection.local_address,
+                connection.local_port,
+                connection.remote_address,
+                connection.remote_port
+            );
+            let last = previous.as_ref().and_then(|previous| previous.get(&socket));
+            let owner = match last {
+                Some(last) if connection.process_id.is_none() => &last.owner,
+                _ => connection,
+            };
+            let (sent_now, received_now) = match (previous.is_some(), last) {
+                (true, Some(last)) if sent >= last.sent && received >= last.received => (sent - last.sent, received - last.received),
+                (true, _) => (sent, received),
+                (false, _) => (0, 0),
+            };
+            sockets.insert(
+                socket,
+                SocketUsage {
+                    sent,
+                    received,
+                    owner: owner.clone(),
+                },
+            );
+            if sent_now == 0 && received_now == 0 {
+                continue;
+            }
+
+            let process = format!(
+                "{}|{}",
+                owner.process_id.unwrap_or_default(),
+                owner.process_path.as_deref().or(owner.process_name.as_deref()).unwrap_or_default()
+            );
+            let traffic = processes.entry(process).or_insert_with(|| ProcessTraffic {
+                connection: owner,
+                sent: 0,
+                received: 0,
+                connections: 0,
+                remote_addresses: HashMap::new(),
+            });
+            traffic.sent += sent_now;
+            traffic.received += received_now;
+            traffic.connections += 1;
+            *traffic.remote_addresses.entry(connection.remote_address.as_str()).or_default() += sent_now;
+        }
+        self.socket_traffic = Some(sockets);
+
+        let window_start = now - Duration::seconds(settings.window_secs as i64);
+        for uploads in self.uploads.values_mut() {
+            while uploads.front().is_some_and(|(time, _)| *time <= window_start) {
+                uploads.pop_front();
+            }
+        }
+
+        let mut usage = Vec::new();
+        for (process, traffic) in processes {
+            let uploads = self.uploads.entry(process).or_default();
+            let before: u64 = uploads.iter().map(|(_, sent)| sent).sum();
+            if traffic.sent > 0 {
+                uploads.push_back((now, traffic.sent));
+            }
+            let window_bytes_sent = before + traffic.sent;
+            let crossed = before < settings.upload_alert_bytes && window_bytes_sent >= settings.upload_alert_bytes;
+            if !crossed && traffic.sent + traffic.received < settings.min_bytes {
+                continue;
+            }
+            if crossed {
+                warn!(
+                    "{} sent {} bytes within {} seconds",
+                    traffic.connection.process_path.as_deref().or(traffic.connection.process_name.as_deref()).unwrap_or("unknown"),
+                    window_bytes_sent,
+                    settings.window_secs
+                );
             }
+
+            let mut remote_addresses: Vec<(&str, u64)> = traffic.remote_addresses.into_iter().collect();
+            remote_addresses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            usage.push(
+                ProcessNetworkUsageBuilder::new()
+                    .id(Uuid::new_v4().to_string())
+                    .timestamp(now)
+                    .source(self.host.name())
+                    .category(String::from("network"))
+                    .process(traffic.connection)
+                    .interval_secs(interval_secs)
+                    .traffic(traffic.sent, traffic.received)
+                    .window_bytes_sent(window_bytes_sent)
+                    .connections(traffic.connections)
+                    .remote_addresses(
+                        remote_addresses
+                            .into_iter()
+                            .take(USAGE_REMOTE_ADDRESSES)
+                            .map(|(address, _)| address.to_string())
+                            .collect(),
+                    )
+                    .severity(if crossed { Severity::High } else { Severity::Low })
+                    .build()
+                    .map_err(CollectionError::Parse)?,
+            );
+        }
+        self.uploads.retain(|_, uploads| !uploads.is_empty());
+
+        self.process_usage.extend(usage);
+        Ok(())
+    }
+
+    pub fn collect_interface_info(&self) -> Result<Vec<NetworkInformation>, CollectionError> {
+        let mut networks = Networks::new();
+        networks.refresh(true);
+        // Addresses come from the OS interface table rather than ipconfig, whose labels are localized
+        let interfaces = networks
+            .iter()
+            .map(|(interface_name, data)| {
+                let (ipv4, ipv6): (Vec<_>, Vec<_>) =
+                    data.ip_networks().iter().map(|network| network.addr).partition(|addr| addr.is_ipv4());
+                NetworkInformation {
+                    interface_name: interface_name.to_string(),
+                    mac_address: data.mac_address().to_string(),
+                    ipv4_addresses: ipv4.iter().map(|addr| addr.to_string()).collect(),
+                    ipv6_addresses: ipv6.iter().map(|addr| addr.to_string()).collect(),
+                    received_bytes: data.received(),
+                    transmitted_bytes: data.transmitted(),
+                    received_packets: data.packets_received(),
+                    transmitted_packets: data.packets_transmitted(),
+                    errors: data.errors_on_received() + data.errors_on_transmitted(),
+                }
+            })
+            .collect();
+
+        Ok(interfaces)
+    }
+
+    // Connection tables straight from the OS (GetExtendedTcpTable / GetExtendedUdpTable on
+    // Windows, /proc/net on Linux), each joined with its owning process right away so the
+    // document still names the right program after the PID is reused
+    pub fn collect_connections(&mut self) -> Result<Vec<NetworkConnectionInformation>, CollectionError> {
+        let mut connections = connection_table()?;
+        // Many sockets share an owner; resolve each PID once per collection
+        let mut owners: HashMap<u32, Option<ProcessDetails>> = HashMap::new();
+        for connection in &mut connections {
+            let Some(pid) = connection.process_id else {
+                continue;
+            };
+            let Some(owner) = owners.entry(pid).or_insert_with(|| self.resolver.resolve(pid)).clone() else {
+                continue;
+            };
+            connection.process_name = Some(owner.name);
+            connection.process_path = owner.path;
+            connection.process_sha256 = owner.sha256;
+            connection.user = owner.user;
+        }
+        self.track_connections(&mut connections);
+        if self.config.listening_ports.enabled {
+            self.track_listening_ports(&connections)?;
+        }
+        if self.config.process_usage.enabled {
+            self.track_process_usage(&connections)?;
         }
 
         info!("Found {} network connections", connections.len());
@@ -141,17 +520,15 @@ impl NetworkCollector {
 
 impl DataCollector<NetworkMetrics> for NetworkCollector {
     fn collect(&mut self) -> Result<NetworkMetrics, CollectionError> {
-        self.sys.refresh_all();
-
         let metrics = NetworkMetricsBuilder::new()
             .id(Uuid::new_v4().to_string())
             .timestamp(Utc::now())
-            .source(self.hostname.clone())
+            .source(self.host.name())
             .category(String::from("network"))
             .interfaces(self.collect_interface_info()?)
             .connections(self.collect_connections()?)
             .build()
-            .map_err(|e| CollectionError::Parse(e))?;
+            .map_err(CollectionError::Parse)?;
 
         info!("Collected network metrics");
         Ok(metrics)
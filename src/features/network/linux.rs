@@ -0,0 +1,136 @@
+use crate::features::network::models::NetworkConnectionInformation;
+use crate::features::network::parsers::{parse_proc_net, parse_ss_tcp_info, SocketEntry};
+use crate::shared::command;
+use log::debug;
+use std::collections::HashMap;
+use std::fs;
+
+// socket inode -> owning pid, from the socket:[inode] links under /proc/*/fd
+fn socket_owners() -> HashMap<u64, u32> {
+    let mut owners = HashMap::new();
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return owners;
+    };
+    for process in processes.flatten() {
+        let Some(pid) = process.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        let Ok(descriptors) = fs::read_dir(process.path().join("fd")) else {
+            continue;
+        };
+        for descriptor in descriptors.flatten() {
+            let Ok(target) = fs::read_link(descriptor.path()) else {
+                continue;
+            };
+            let inode = target
+                .to_str()
+                .and_then(|target| target.strip_prefix("socket:["))
+                .and_then(|target| target.strip_suffix(']'))
+                .and_then(|inode| inode.parse::<u64>().ok());
+            if let Some(inode) = inode {
+                owners.entry(inode).or_insert(pid);
+            }
+        }
+    }
+    owners
+}
+
+type Endpoints = ((String, u16), (String, u16));
+
+// Bytes sent and received per socket, from the kernel's tcp_info through ss: by inode, and by
+// local and remote address for sockets their process already closed. Empty where iproute2 is
+// not installed.
+#[derive(Default)]
+struct SocketCounters {
+    open: HashMap<u64, (u64, u64)>,
+    closed: HashMap<Endpoints, (u64, u64)>,
+}
+
+impl SocketCounters {
+    fn get(&self, socket: &SocketEntry) -> Option<(u64, u64)> {
+        if socket.inode != 0 {
+            return self.open.get(&socket.inode).copied();
+        }
+        let endpoints = (
+            (socket.local_address.clone(), socket.local_port),
+            (socket.remote_address.clone(), socket.remote_port),
+        );
+        self.closed.get(&endpoints).copied()
+    }
+}
+
+fn socket_traffic() -> SocketCounters {
+    let mut counters = SocketCounters::default();
+    match command::run("ss", ["-H", "-t", "-i", "-e", "-n"]) {
+        Ok(output) if output.success => {
+            let parsed = parse_ss_tcp_info(&output.stdout);
+            parsed.log_rejected("ss");
+            for socket in parsed.records {
+                let traffic = (socket.bytes_sent, socket.bytes_received);
+                match (socket.inode, socket.local, socket.remote) {
+                    (0, Some(local), Some(remote)) => {
+                        counters.closed.insert((local, remote), traffic);
+                    }
+                    (0, _, _) => {}
+                    (inode, _, _) => {
+                        counters.open.insert(inode, traffic);
+                    }
+                }
+            }
+        }
+        Ok(output) => debug!("ss failed, connections carry no byte counts: {}", output.stderr.trim()),
+        Err(e) => debug!("Failed to run ss, connections carry no byte counts: {}", e),
+    }
+    counters
+}
+
+fn parse_table(
+    content: &str,
+    path: &str,
+    protocol: &str,
+    owners: &HashMap<u64, u32>,
+    traffic: &SocketCounters,
+) -> Vec<NetworkConnectionInformation> {
+    let parsed = parse_proc_net(content, protocol == "TCP");
+    parsed.log_rejected(path);
+    parsed
+        .records
+        .into_iter()
+        .map(|socket| {
+            let traffic = (protocol == "TCP").then(|| traffic.get(&socket)).flatten();
+            NetworkConnectionInformation {
+                local_address: socket.local_address,
+                local_port: socket.local_port,
+                remote_address: socket.remote_address,
+                remote_port: socket.remote_port,
+                protocol: protocol.to_string(),
+                state: socket.state,
+                process_id: owners.get(&socket.inode).copied(),
+                process_name: None,
+                process_path: None,
+                process_sha256: None,
+                user: None,
+                first_seen: None,
+                bytes_sent: traffic.map(|(sent, _)| sent),
+                bytes_received: traffic.map(|(_, received)| received),
+            }
+        })
+        .collect()
+}
+
+pub fn connections() -> Vec<NetworkConnectionInformation> {
+    let owners = socket_owners();
+    let traffic = socket_traffic();
+    let mut connections = Vec::new();
+    for (path, protocol) in [
+        ("/proc/net/tcp", "TCP"),
+        ("/proc/net/tcp6", "TCP"),
+        ("/proc/net/udp", "UDP"),
+        ("/proc/net/udp6", "UDP"),
+    ] {
+        if let Ok(content) = fs::read_to_string(path) {
+            connections.extend(parse_table(&content, path, protocol, &owners, &traffic));
+        }
+    }
+    connections
+}
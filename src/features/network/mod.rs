@@ -1,5 +1,16 @@
 mod collector;
 mod models;
+pub mod parsers;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(windows)]
+mod win32;
 
-pub use collector::NetworkCollector;
-pub use models::{NetworkInformation, NetworkConnectionInformation, NetworkMetrics};
+pub use collector::{ListeningPortConfig, NetworkCollector, NetworkConfig, ProcessUsageConfig};
+pub use models::{
+    DnsLogRecord, FirewallLogRecord, ListeningPort, ListeningPortChange, ListeningPortEvent, NetworkInformation,
+    NetworkConnectionInformation, NetworkMetrics, ProcessNetworkUsage, ProcessNetworkUsageBuilder,
+};
+pub use parsers::{
+    parse_dns_debug_line, parse_firewall_log_line, parse_proc_net, parse_ss_tcp_info, SocketEntry, SocketTraffic,
+};
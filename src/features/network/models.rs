@@ -24,6 +24,24 @@ pub struct NetworkConnectionInformation {
     pub protocol: String,
     pub state: String,
     pub process_id: Option<u32>,
+    pub process_name: Option<String>,
+    // The owning process as it was when the connection was collected, since the PID alone
+    // may belong to another process by the time anyone looks
+    #[serde(default)]
+    pub process_path: Option<String>,
+    #[serde(default)]
+    pub process_sha256: Option<String>,
+    #[serde(default)]
+    pub user: Option<String>,
+    // When the agent first saw this connection, kept across restarts
+    #[serde(default)]
+    pub first_seen: Option<DateTime<Utc>>,
+    // Payload bytes over the socket's lifetime on Linux, since the agent first saw it on
+    // Windows; TCP only
+    #[serde(default)]
+    pub bytes_sent: Option<u64>,
+    #[serde(default)]
+    pub bytes_received: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -168,3 +186,412 @@ impl NetworkMetricsBuilder {
         Ok(metrics)
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListeningPortChange {
+    // A port not in the baseline started listening
+    Opened,
+    // A port of the baseline stopped listening
+    Closed,
+}
+
+// A listening socket as remembered between collections, and across restarts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListeningPort {
+    pub protocol: String,
+    pub local_address: String,
+    pub port: u16,
+    pub process_name: Option<String>,
+    pub process_path: Option<String>,
+    pub user: Option<String>,
+    pub first_seen: DateTime<Utc>,
+    // Collections in a row the port was not listening in
+    #[serde(default)]
+    pub missed: u32,
+}
+
+impl ListeningPort {
+    pub fn key(&self) -> String {
+        format!("{}|{}|{}", self.protocol, self.local_address, self.port)
+    }
+
+    // Reachable from other hosts, unlike a socket bound to a loopback address
+    pub fn exposed(&self) -> bool {
+        self.local_address
+            .parse::<std::net::IpAddr>()
+            .map(|address| !address.is_loopback())
+            .unwrap_or(true)
+    }
+}
+
+impl From<&NetworkConnectionInformation> for ListeningPort {
+    fn from(connection: &NetworkConnectionInformation) -> Self {
+        Self {
+            protocol: connection.protocol.clone(),
+            local_address: connection.local_address.clone(),
+            port: connection.local_port,
+            process_name: connection.process_name.clone(),
+            process_path: connection.process_path.clone(),
+            user: connection.user.clone(),
+            first_seen: connection.first_seen.unwrap_or_else(Utc::now),
+            missed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListeningPortEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub change: ListeningPortChange,
+    pub protocol: String,
+    pub local_address: String,
+    pub port: u16,
+    pub exposed: bool,
+    // For a closed port, the process that last held it
+    pub process_name: Option<String>,
+    pub process_path: Option<String>,
+    pub user: Option<String>,
+    pub first_seen: DateTime<Utc>,
+    pub severity: Severity,
+}
+
+impl Event for ListeningPortEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.change {
+            ListeningPortChange::Opened => "listening_port_opened",
+            ListeningPortChange::Closed => "listening_port_closed",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl Identifiable for ListeningPortEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for ListeningPortEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.port == 0 {
+            return Err("Listening port cannot be 0".to_string());
+        }
+        if self.protocol.is_empty() {
+            return Err("Protocol cannot be empty".to_string());
+        }
+        if self.local_address.is_empty() {
+            return Err("Local address cannot be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct ListeningPortEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    change: Option<ListeningPortChange>,
+    port: Option<ListeningPort>,
+    severity: Option<Severity>,
+}
+
+impl ListeningPortEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn change(mut self, change: ListeningPortChange) -> Self {
+        self.change = Some(change);
+        self
+    }
+
+    pub fn port(mut self, port: ListeningPort) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    pub fn build(self) -> Result<ListeningPortEvent, String> {
+        let port = self.port.ok_or("port is required")?;
+        let event = ListeningPortEvent {
+            id: self.id.ok_or("id is required")?,
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            change: self.change.ok_or("change is required")?,
+            exposed: port.exposed(),
+            protocol: port.protocol,
+            local_address: port.local_address,
+            port: port.port,
+            process_name: port.process_name,
+            process_path: port.process_path,
+            user: port.user,
+            first_seen: port.first_seen,
+            severity: self.severity.ok_or("severity is required")?,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
+
+// What one process sent and received over TCP since the previous collection, summed from the
+// byte counters of its sockets. A process that uploads an unusual amount within the window is
+// raised as high severity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessNetworkUsage {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub process_id: Option<u32>,
+    pub process_name: Option<String>,
+    pub process_path: Option<String>,
+    pub process_sha256: Option<String>,
+    pub user: Option<String>,
+    // Seconds since the previous collection
+    pub interval_secs: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    // Sent over the configured window, this collection included
+    pub window_bytes_sent: u64,
+    // Sockets that carried data
+    pub connections: usize,
+    // Remote addresses with the most data sent to them first
+    pub remote_addresses: Vec<String>,
+    pub severity: Severity,
+}
+
+impl Event for ProcessNetworkUsage {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        "process_network_usage"
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl Identifiable for ProcessNetworkUsage {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for ProcessNetworkUsage {
+    fn validate(&self) -> Result<(), String> {
+        if self.process_id.is_none() && self.process_name.is_none() {
+            return Err("Process id or name is required".to_string());
+        }
+        if self.bytes_sent == 0 && self.bytes_received == 0 {
+            return Err("Usage without any traffic".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct ProcessNetworkUsageBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    process: Option<NetworkConnectionInformation>,
+    interval_secs: u64,
+    bytes_sent: u64,
+    bytes_received: u64,
+    window_bytes_sent: u64,
+    connections: usize,
+    remote_addresses: Vec<String>,
+    severity: Option<Severity>,
+}
+
+impl ProcessNetworkUsageBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    // The process is taken from one of its connections
+    pub fn process(mut self, connection: &NetworkConnectionInformation) -> Self {
+        self.process = Some(connection.clone());
+        self
+    }
+
+    pub fn interval_secs(mut self, interval_secs: u64) -> Self {
+        self.interval_secs = interval_secs;
+        self
+    }
+
+    pub fn traffic(mut self, bytes_sent: u64, bytes_received: u64) -> Self {
+        self.bytes_sent = bytes_sent;
+        self.bytes_received = bytes_received;
+        self
+    }
+
+    pub fn window_bytes_sent(mut self, window_bytes_sent: u64) -> Self {
+        self.window_bytes_sent = window_bytes_sent;
+        self
+    }
+
+    pub fn connections(mut self, connections: usize) -> Self {
+        self.connections = connections;
+        self
+    }
+
+    pub fn remote_addresses(mut self, remote_addresses: Vec<String>) -> Self {
+        self.remote_addresses = remote_addresses;
+        self
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    pub fn build(self) -> Result<ProcessNetworkUsage, String> {
+        let process = self.process.ok_or("process is required")?;
+        let usage = ProcessNetworkUsage {
+            id: self.id.ok_or("id is required")?,
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            process_id: process.process_id,
+            process_name: process.process_name,
+            process_path: process.process_path,
+            process_sha256: process.process_sha256,
+            user: process.user,
+            interval_secs: self.interval_secs,
+            bytes_sent: self.bytes_sent,
+            bytes_received: self.bytes_received,
+            window_bytes_sent: self.window_bytes_sent,
+            connections: self.connections,
+            remote_addresses: self.remote_addresses,
+            severity: self.severity.unwrap_or(Severity::Low),
+        };
+
+        usage.validate()?;
+        Ok(usage)
+    }
+}
+
+// One connection attempt from the Windows Firewall log (pfirewall.log), named like the
+// connection table so the same rules and indicators apply to both
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirewallLogRecord {
+    pub timestamp: DateTime<Utc>,
+    // ALLOW, DROP, INFO-EVENTS-LOST, ...
+    pub action: String,
+    pub protocol: String,
+    // inbound / outbound / forward, from the log's path field
+    pub direction: Option<String>,
+    // The sending side for forwarded packets
+    pub local_address: Option<String>,
+    pub local_port: Option<u16>,
+    pub remote_address: Option<String>,
+    pub remote_port: Option<u16>,
+    pub size: Option<u64>,
+    pub tcp_flags: Option<String>,
+    pub icmp_type: Option<u8>,
+    pub icmp_code: Option<u8>,
+    pub info: Option<String>,
+    // Logged by Windows 10 1809 and later
+    pub process_id: Option<u32>,
+}
+
+// One packet from the Windows DNS server debug log: a client's query and the server's
+// answer, or the server's own recursive query to a forwarder or root server and its reply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DnsLogRecord {
+    pub timestamp: DateTime<Utc>,
+    pub protocol: String,
+    // sent / received, by the DNS server
+    pub direction: String,
+    pub remote_address: String,
+    pub transaction_id: u16,
+    pub response: bool,
+    // Q (query), N (notify), U (update), ? (unknown)
+    pub opcode: String,
+    // Authoritative (A), truncated (T), recursion desired (D), recursion available (R)
+    pub flags: String,
+    pub response_code: String,
+    pub query_type: String,
+    // Dotted, without the trailing dot
+    pub query_name: String,
+}
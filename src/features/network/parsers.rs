@@ -0,0 +1,306 @@
+use crate::features::network::models::{DnsLogRecord, FirewallLogRecord};
+use crate::shared::command::ParsedOutput;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::OnceLock;
+
+// One socket of /proc/net/{tcp,tcp6,udp,udp6}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocketEntry {
+    pub local_address: String,
+    pub local_port: u16,
+    pub remote_address: String,
+    pub remote_port: u16,
+    // Named like the Windows connection table states; NONE for UDP
+    pub state: String,
+    // Matched against the socket:[inode] links under /proc/*/fd for the owning process
+    pub inode: u64,
+}
+
+// st column of /proc/net/tcp*, named like the Windows connection table states
+fn tcp_state(code: u8) -> &'static str {
+    match code {
+        0x01 => "ESTABLISHED",
+        0x02 => "SYN_SENT",
+        0x03 => "SYN_RECEIVED",
+        0x04 => "FIN_WAIT_1",
+        0x05 => "FIN_WAIT_2",
+        0x06 => "TIME_WAIT",
+        0x07 => "CLOSED",
+        0x08 => "CLOSE_WAIT",
+        0x09 => "LAST_ACK",
+        0x0A => "LISTENING",
+        0x0B => "CLOSING",
+        _ => "UNKNOWN",
+    }
+}
+
+// The kernel prints each 32-bit word of the address in host byte order, as 8 hex digits for
+// IPv4 and 32 for IPv6; anything else is not an address
+fn parse_address(hex: &str) -> Option<String> {
+    if !matches!(hex.len(), 8 | 32) || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let words: Vec<u32> = (0..hex.len() / 8)
+        .map(|index| u32::from_str_radix(&hex[index * 8..index * 8 + 8], 16))
+        .collect::<Result<_, _>>()
+        .ok()?;
+    match words.as_slice() {
+        [word] => Some(Ipv4Addr::from(word.to_ne_bytes()).to_string()),
+        [a, b, c, d] => {
+            let mut bytes = [0u8; 16];
+            for (chunk, word) in bytes.chunks_exact_mut(4).zip([a, b, c, d]) {
+                chunk.copy_from_slice(&word.to_ne_bytes());
+            }
+            let address = Ipv6Addr::from(bytes);
+            // Dual-stack sockets show IPv4 peers as ::ffff:a.b.c.d
+            Some(match address.to_ipv4_mapped() {
+                Some(mapped) => mapped.to_string(),
+                None => address.to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn parse_endpoint(value: &str) -> Option<(String, u16)> {
+    let (address, port) = value.split_once(':')?;
+    if port.is_empty() || !port.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((parse_address(address)?, u16::from_str_radix(port, 16).ok()?))
+}
+
+// One /proc/net socket table; `tcp` names the st column, UDP sockets have no state
+pub fn parse_proc_net(content: &str, tcp: bool) -> ParsedOutput<SocketEntry> {
+    let mut parsed = ParsedOutput::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let number = index + 1;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // The header starts with "sl"
+        if fields.is_empty() || fields[0] == "sl" {
+            continue;
+        }
+        if fields.len() < 10 {
+            parsed.reject(number, "fewer than ten columns");
+            continue;
+        }
+        let Some((local_address, local_port)) = parse_endpoint(fields[1]) else {
+            parsed.reject(number, "malformed local address");
+            continue;
+        };
+        let Some((remote_address, remote_port)) = parse_endpoint(fields[2]) else {
+            parsed.reject(number, "malformed remote address");
+            continue;
+        };
+        let Ok(state) = u8::from_str_radix(fields[3], 16) else {
+            parsed.reject(number, "malformed socket state");
+            continue;
+        };
+        let Ok(inode) = fields[9].parse::<u64>() else {
+            parsed.reject(number, "malformed inode");
+            continue;
+        };
+        parsed.records.push(SocketEntry {
+            local_address,
+            local_port,
+            remote_address,
+            remote_port,
+            state: if tcp { tcp_state(state).to_string() } else { String::from("NONE") },
+            inode,
+        });
+    }
+    parsed
+}
+
+// Byte counters of one TCP socket over its lifetime. A socket its process already closed,
+// while the kernel still sends what was left, has inode 0 and is known by its addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocketTraffic {
+    pub inode: u64,
+    pub local: Option<(String, u16)>,
+    pub remote: Option<(String, u16)>,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+// An ss -n address: 10.0.0.1:22, [2001:db8::1]:443 or [fe80::1%eth0]:22
+fn ss_endpoint(value: &str) -> Option<(String, u16)> {
+    let (address, port) = value.rsplit_once(':')?;
+    let address = address.trim_start_matches('[').trim_end_matches(']');
+    let address = address.split_once('%').map_or(address, |(address, _)| address);
+    Some((address.to_string(), port.parse().ok()?))
+}
+
+fn counter(fields: &[&str], name: &str) -> Option<u64> {
+    fields
+        .iter()
+        .find_map(|field| field.strip_prefix(name)?.strip_prefix(':'))
+        .and_then(|value| value.parse().ok())
+}
+
+// `ss -H -t -i -e -n`: a line per socket (state, queues, local and peer address) carrying
+// ino:<inode>, followed by an indented line of tcp_info counters. Closed sockets still shutting
+// down have ino:0 and keep their counters; TIME_WAIT sockets have neither and are skipped.
+// Counters ss leaves out are 0. Kernels before 4.19 have no bytes_sent, where bytes_acked,
+// what the peer confirmed, is the closest.
+pub fn parse_ss_tcp_info(content: &str) -> ParsedOutput<SocketTraffic> {
+    let mut parsed = ParsedOutput::new();
+    // The socket of the last header line, waiting for its counters
+    let mut socket: Option<SocketTraffic> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let number = index + 1;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            socket = match fields.iter().find_map(|field| field.strip_prefix("ino:")) {
+                Some(inode) => match inode.parse::<u64>() {
+                    Ok(inode) => {
+                        let endpoint = |column: usize| fields.get(column).and_then(|value| ss_endpoint(value));
+                        Some(SocketTraffic {
+                            inode,
+                            local: endpoint(3),
+                            remote: endpoint(4),
+                            bytes_sent: 0,
+                            bytes_received: 0,
+                        })
+                    }
+                    Err(_) => {
+                        parsed.reject(number, "malformed inode");
+                        None
+                    }
+                },
+                None => None,
+            };
+            continue;
+        }
+
+        let Some(socket) = socket.take() else {
+            continue;
+        };
+        if socket.inode == 0 && (socket.local.is_none() || socket.remote.is_none()) {
+            parsed.reject(number, "closed socket without addresses");
+            continue;
+        }
+        parsed.records.push(SocketTraffic {
+            bytes_sent: counter(&fields, "bytes_sent")
+                .or_else(|| counter(&fields, "bytes_acked"))
+                .unwrap_or(0),
+            bytes_received: counter(&fields, "bytes_received").unwrap_or(0),
+            ..socket
+        });
+    }
+    parsed
+}
+
+// Both logs write the host's local time
+fn local_timestamp(value: &str, format: &str) -> Option<DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(value, format).ok()?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
+// One record of pfirewall.log, whose values are named by the latest #Fields directive
+// (date time action protocol src-ip dst-ip src-port dst-port size tcpflags tcpsyn tcpack
+// tcpwin icmptype icmpcode info path, and pid on recent Windows); "-" stands for no value.
+// Which address is local follows from the path: SEND is outbound, RECEIVE inbound.
+pub fn parse_firewall_log_line(line: &str, names: &[String]) -> Option<FirewallLogRecord> {
+    let values: HashMap<&str, &str> = names
+        .iter()
+        .map(String::as_str)
+        .zip(line.split_whitespace())
+        .filter(|(_, value)| *value != "-")
+        .collect();
+    let timestamp = local_timestamp(&format!("{} {}", values.get("date")?, values.get("time")?), "%Y-%m-%d %H:%M:%S")?;
+    let source = (values.get("src-ip").copied(), values.get("src-port").and_then(|port| port.parse().ok()));
+    let destination = (values.get("dst-ip").copied(), values.get("dst-port").and_then(|port| port.parse().ok()));
+    let (direction, (local_address, local_port), (remote_address, remote_port)) = match values.get("path").copied() {
+        Some("SEND") => (Some("outbound"), source, destination),
+        Some("RECEIVE") => (Some("inbound"), destination, source),
+        Some("FORWARD") => (Some("forward"), source, destination),
+        _ => (None, source, destination),
+    };
+
+    Some(FirewallLogRecord {
+        timestamp,
+        action: values.get("action")?.to_string(),
+        protocol: values.get("protocol").map(|protocol| protocol.to_string()).unwrap_or_default(),
+        direction: direction.map(str::to_string),
+        local_address: local_address.map(str::to_string),
+        local_port,
+        remote_address: remote_address.map(str::to_string),
+        remote_port,
+        size: values.get("size").and_then(|size| size.parse().ok()),
+        tcp_flags: values.get("tcpflags").map(|flags| flags.to_string()),
+        icmp_type: values.get("icmptype").and_then(|value| value.parse().ok()),
+        icmp_code: values.get("icmpcode").and_then(|value| value.parse().ok()),
+        info: values.get("info").map(|info| info.to_string()),
+        process_id: values.get("pid").and_then(|pid| pid.parse().ok()).filter(|pid| *pid != 0),
+    })
+}
+
+fn dns_packet() -> &'static Regex {
+    static PACKET: OnceLock<Regex> = OnceLock::new();
+    PACKET.get_or_init(|| {
+        Regex::new(concat!(
+            r"^(?P<when>.+?) [0-9A-Fa-f]+ PACKET\s+[0-9A-Fa-f]+\s+(?P<protocol>UDP|TCP)\s+(?P<direction>Snd|Rcv)\s+",
+            r"(?P<remote>\S+)\s+(?P<xid>[0-9A-Fa-f]{1,4})\s(?P<response>[ R])\s(?P<opcode>[QNU?])\s",
+            r"\[[0-9A-Fa-f]{4}\s+(?:(?P<flags>[ATDR]+)\s+)?(?P<rcode>\w+)\]\s+(?P<qtype>\S+)\s+(?P<qname>\S+)",
+        ))
+        .expect("valid DNS debug log regex")
+    })
+}
+
+// Date formats of the DNS debug log, which follows the server's locale; month-first is tried
+// before day-first, so servers with day-first dates need their format configured
+const DNS_TIMESTAMP_FORMATS: [&str; 5] = [
+    "%m/%d/%Y %I:%M:%S %p",
+    "%m/%d/%Y %H:%M:%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%d.%m.%Y %H:%M:%S",
+    "%d/%m/%Y %H:%M:%S",
+];
+
+// (7)example(3)com(0) -> example.com
+fn dns_name(encoded: &str) -> String {
+    encoded
+        .split('(')
+        .filter_map(|part| part.split_once(')').map(|(_, label)| label))
+        .filter(|label| !label.is_empty())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+// One PACKET line of the DNS server debug log (dns.log), None for headers, blank lines and
+// other contexts. `format` is a chrono format for the date and time, tried before the
+// common ones.
+pub fn parse_dns_debug_line(line: &str, format: Option<&str>) -> Option<DnsLogRecord> {
+    let captures = dns_packet().captures(line)?;
+    let when = captures["when"].trim();
+    let timestamp = format
+        .into_iter()
+        .chain(DNS_TIMESTAMP_FORMATS)
+        .find_map(|format| local_timestamp(when, format))?;
+
+    Some(DnsLogRecord {
+        timestamp,
+        protocol: captures["protocol"].to_string(),
+        direction: String::from(if &captures["direction"] == "Snd" { "sent" } else { "received" }),
+        remote_address: captures["remote"].to_string(),
+        transaction_id: u16::from_str_radix(&captures["xid"], 16).ok()?,
+        response: &captures["response"] == "R",
+        opcode: captures["opcode"].to_string(),
+        flags: captures.name("flags").map(|flags| flags.as_str().to_string()).unwrap_or_default(),
+        response_code: captures["rcode"].to_string(),
+        query_type: captures["qtype"].to_string(),
+        query_name: dns_name(&captures["qname"]),
+    })
+}
@@ -0,0 +1,220 @@
+use crate::features::network::models::NetworkConnectionInformation;
+use crate::shared::error::CollectionError;
+use std::ffi::c_void;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use windows::Win32::Foundation::{BOOL, BOOLEAN, ERROR_INSUFFICIENT_BUFFER, NO_ERROR};
+use windows::Win32::NetworkManagement::IpHelper::{
+    GetExtendedTcpTable, GetExtendedUdpTable, GetPerTcp6ConnectionEStats, GetPerTcpConnectionEStats,
+    SetPerTcp6ConnectionEStats, SetPerTcpConnectionEStats, TcpConnectionEstatsData, MIB_TCP6ROW,
+    MIB_TCP6ROW_OWNER_PID, MIB_TCPROW_LH, MIB_TCPROW_LH_0, MIB_TCPROW_OWNER_PID, MIB_TCP_STATE,
+    MIB_UDP6ROW_OWNER_PID, MIB_UDPROW_OWNER_PID, TCP_ESTATS_DATA_ROD_v0, TCP_ESTATS_DATA_RW_v0,
+    TCP_TABLE_OWNER_PID_ALL, UDP_TABLE_OWNER_PID,
+};
+use windows::Win32::Networking::WinSock::{AF_INET, AF_INET6, IN6_ADDR, IN6_ADDR_0};
+
+// MIB_TCP_STATE values
+fn tcp_state(state: u32) -> &'static str {
+    match state {
+        1 => "CLOSED",
+        2 => "LISTENING",
+        3 => "SYN_SENT",
+        4 => "SYN_RECEIVED",
+        5 => "ESTABLISHED",
+        6 => "FIN_WAIT_1",
+        7 => "FIN_WAIT_2",
+        8 => "CLOSE_WAIT",
+        9 => "CLOSING",
+        10 => "LAST_ACK",
+        11 => "TIME_WAIT",
+        12 => "DELETE_TCB",
+        _ => "UNKNOWN",
+    }
+}
+
+// Ports are stored in network byte order in the low 16 bits
+fn port(value: u32) -> u16 {
+    u16::from_be(value as u16)
+}
+
+fn ipv4(value: u32) -> String {
+    Ipv4Addr::from(value.to_ne_bytes()).to_string()
+}
+
+fn ipv6(value: [u8; 16]) -> String {
+    Ipv6Addr::from(value).to_string()
+}
+
+// Calls one of the GetExtended*Table functions until the buffer is large enough and returns
+// its rows, which follow the dwNumEntries count
+fn table<T: Copy>(query: impl Fn(Option<*mut c_void>, &mut u32) -> u32) -> Result<Vec<T>, CollectionError> {
+    let mut size = 0u32;
+    let mut buffer: Vec<u8> = Vec::new();
+    for _ in 0..4 {
+        let pointer = if buffer.is_empty() { None } else { Some(buffer.as_mut_ptr() as *mut c_void) };
+        match query(pointer, &mut size) {
+            code if code == NO_ERROR.0 && !buffer.is_empty() => {
+                let count = u32::from_ne_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+                let rows = buffer[std::mem::size_of::<u32>()..].as_ptr() as *const T;
+                let available = (buffer.len() - std::mem::size_of::<u32>()) / std::mem::size_of::<T>();
+                return Ok((0..count.min(available))
+                    .map(|index| unsafe { std::ptr::read_unaligned(rows.add(index)) })
+                    .collect());
+            }
+            code if code == ERROR_INSUFFICIENT_BUFFER.0 || (code == NO_ERROR.0 && buffer.is_empty()) => {
+                // Connections come and go between the two calls
+                buffer = vec![0u8; size as usize + 16 * std::mem::size_of::<T>()];
+                size = buffer.len() as u32;
+            }
+            code => {
+                return Err(CollectionError::SystemApi(format!(
+                    "Connection table query failed: {}",
+                    std::io::Error::from_raw_os_error(code as i32)
+                )));
+            }
+        }
+    }
+    Err(CollectionError::SystemApi("Connection table kept growing".to_string()))
+}
+
+// Connected states, in which a socket can still carry data
+fn connected(state: u32) -> bool {
+    (5..=10).contains(&state)
+}
+
+fn bytes<T>(value: &mut T) -> &mut [u8] {
+    unsafe { std::slice::from_raw_parts_mut(value as *mut T as *mut u8, std::mem::size_of::<T>()) }
+}
+
+// Data bytes sent and received over a TCP connection, from its extended statistics. Windows
+// only counts once collection is enabled on the connection, which needs an elevated token,
+// so a connection seen for the first time starts from zero.
+fn traffic(get: impl Fn(&mut [u8], &mut [u8]) -> u32, set: impl Fn(&mut [u8]) -> u32) -> Option<(u64, u64)> {
+    let mut rw = TCP_ESTATS_DATA_RW_v0::default();
+    let mut rod = TCP_ESTATS_DATA_ROD_v0::default();
+    if get(bytes(&mut rw), bytes(&mut rod)) != NO_ERROR.0 {
+        return None;
+    }
+    if rw.EnableCollection.0 != 0 {
+        return Some((rod.DataBytesOut, rod.DataBytesIn));
+    }
+    let mut enable = TCP_ESTATS_DATA_RW_v0 { EnableCollection: BOOLEAN(1) };
+    (set(bytes(&mut enable)) == NO_ERROR.0).then_some((0, 0))
+}
+
+fn tcp4_traffic(row: &MIB_TCPROW_OWNER_PID) -> Option<(u64, u64)> {
+    let row = MIB_TCPROW_LH {
+        Anonymous: MIB_TCPROW_LH_0 { dwState: row.dwState },
+        dwLocalAddr: row.dwLocalAddr,
+        dwLocalPort: row.dwLocalPort,
+        dwRemoteAddr: row.dwRemoteAddr,
+        dwRemotePort: row.dwRemotePort,
+    };
+    traffic(
+        |rw, rod| unsafe { GetPerTcpConnectionEStats(&row, TcpConnectionEstatsData, Some(rw), 0, None, 0, Some(rod), 0) },
+        |rw| unsafe { SetPerTcpConnectionEStats(&row, TcpConnectionEstatsData, rw, 0, 0) },
+    )
+}
+
+fn tcp6_traffic(row: &MIB_TCP6ROW_OWNER_PID) -> Option<(u64, u64)> {
+    let row = MIB_TCP6ROW {
+        State: MIB_TCP_STATE(row.dwState as i32),
+        LocalAddr: IN6_ADDR { u: IN6_ADDR_0 { Byte: row.ucLocalAddr } },
+        dwLocalScopeId: row.dwLocalScopeId,
+        dwLocalPort: row.dwLocalPort,
+        RemoteAddr: IN6_ADDR { u: IN6_ADDR_0 { Byte: row.ucRemoteAddr } },
+        dwRemoteScopeId: row.dwRemoteScopeId,
+        dwRemotePort: row.dwRemotePort,
+    };
+    traffic(
+        |rw, rod| unsafe { GetPerTcp6ConnectionEStats(&row, TcpConnectionEstatsData, Some(rw), 0, None, 0, Some(rod), 0) },
+        |rw| unsafe { SetPerTcp6ConnectionEStats(&row, TcpConnectionEstatsData, rw, 0, 0) },
+    )
+}
+
+fn connection(
+    protocol: &str,
+    (local_address, local_port): (String, u16),
+    (remote_address, remote_port): (String, u16),
+    state: &str,
+    pid: u32,
+    traffic: Option<(u64, u64)>,
+) -> NetworkConnectionInformation {
+    NetworkConnectionInformation {
+        local_address,
+        local_port,
+        remote_address,
+        remote_port,
+        protocol: protocol.to_string(),
+        state: state.to_string(),
+        process_id: Some(pid),
+        process_name: None,
+        process_path: None,
+        process_sha256: None,
+        user: None,
+        first_seen: None,
+        bytes_sent: traffic.map(|(sent, _)| sent),
+        bytes_received: traffic.map(|(_, received)| received),
+    }
+}
+
+pub fn connections() -> Result<Vec<NetworkConnectionInformation>, CollectionError> {
+    let mut connections = Vec::new();
+
+    let tcp4: Vec<MIB_TCPROW_OWNER_PID> = table(|buffer, size| unsafe {
+        GetExtendedTcpTable(buffer, size, BOOL(0), AF_INET.0 as u32, TCP_TABLE_OWNER_PID_ALL, 0)
+    })?;
+    connections.extend(tcp4.into_iter().map(|row| {
+        connection(
+            "TCP",
+            (ipv4(row.dwLocalAddr), port(row.dwLocalPort)),
+            (ipv4(row.dwRemoteAddr), port(row.dwRemotePort)),
+            tcp_state(row.dwState),
+            row.dwOwningPid,
+            connected(row.dwState).then(|| tcp4_traffic(&row)).flatten(),
+        )
+    }));
+
+    let tcp6: Vec<MIB_TCP6ROW_OWNER_PID> = table(|buffer, size| unsafe {
+        GetExtendedTcpTable(buffer, size, BOOL(0), AF_INET6.0 as u32, TCP_TABLE_OWNER_PID_ALL, 0)
+    })?;
+    connections.extend(tcp6.into_iter().map(|row| {
+        connection(
+            "TCP",
+            (ipv6(row.ucLocalAddr), port(row.dwLocalPort)),
+            (ipv6(row.ucRemoteAddr), port(row.dwRemotePort)),
+            tcp_state(row.dwState),
+            row.dwOwningPid,
+            connected(row.dwState).then(|| tcp6_traffic(&row)).flatten(),
+        )
+    }));
+
+    let udp4: Vec<MIB_UDPROW_OWNER_PID> = table(|buffer, size| unsafe {
+        GetExtendedUdpTable(buffer, size, BOOL(0), AF_INET.0 as u32, UDP_TABLE_OWNER_PID, 0)
+    })?;
+    connections.extend(udp4.into_iter().map(|row| {
+        connection(
+            "UDP",
+            (ipv4(row.dwLocalAddr), port(row.dwLocalPort)),
+            (String::from("0.0.0.0"), 0),
+            "NONE",
+            row.dwOwningPid,
+            None,
+        )
+    }));
+
+    let udp6: Vec<MIB_UDP6ROW_OWNER_PID> = table(|buffer, size| unsafe {
+        GetExtendedUdpTable(buffer, size, BOOL(0), AF_INET6.0 as u32, UDP_TABLE_OWNER_PID, 0)
+    })?;
+    connections.extend(udp6.into_iter().map(|row| {
+        connection(
+            "UDP",
+            (ipv6(row.ucLocalAddr), port(row.dwLocalPort)),
+            (String::from("::"), 0),
+            "NONE",
+            row.dwOwningPid,
+            None,
+        )
+    }));
+
+    Ok(connections)
+}
@@ -0,0 +1,328 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector, Severity};
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::command;
+use crate::features::osquery::models::{OsqueryAction, OsqueryResultEvent, OsqueryResultEventBuilder};
+use crate::features::osquery::pack::{parse_pack, platform_matches, ScheduledQuery};
+use crate::features::osquery::thrift::{ExtensionClient, Row};
+use crate::utils::load_config_section;
+use chrono::Utc;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use which::which;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OsqueryMode {
+    // Use the extension socket of a running osqueryd, fall back to osqueryi
+    Auto,
+    Osqueryi,
+    ExtensionSocket,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OsqueryQueryConfig {
+    pub name: String,
+    pub query: String,
+    pub interval_secs: u64,
+    pub snapshot: bool,
+    pub removed: bool,
+    pub severity: Severity,
+    pub platform: Option<String>,
+}
+
+impl Default for OsqueryQueryConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            query: String::new(),
+            interval_secs: 3600,
+            snapshot: false,
+            removed: true,
+            severity: Severity::Low,
+            platform: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OsqueryConfig {
+    pub enabled: bool,
+    pub mode: OsqueryMode,
+    pub osqueryi_path: String,
+    pub extension_socket: String,
+    pub socket_timeout_secs: u64,
+    // osqueryi is killed when a query runs longer
+    pub osqueryi_timeout_secs: u64,
+    // Rows beyond this are dropped so one broad query cannot flood storage
+    pub max_rows_per_query: usize,
+    // osquery pack files; the pack name is the file stem
+    pub packs: Vec<String>,
+    pub pack_severity: Severity,
+    pub queries: Vec<OsqueryQueryConfig>,
+}
+
+impl Default for OsqueryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: OsqueryMode::Auto,
+            osqueryi_path: String::from("osqueryi"),
+            extension_socket: if cfg!(target_os = "windows") {
+                String::from(r"\\.\pipe\osquery.em")
+            } else {
+                String::from("/var/osquery/osquery.em")
+            },
+            socket_timeout_secs: 30,
+            osqueryi_timeout_secs: 60,
+            max_rows_per_query: 10000,
+            packs: Vec::new(),
+            pack_severity: Severity::Low,
+            queries: Vec::new(),
+        }
+    }
+}
+
+impl OsqueryConfig {
+    pub fn load() -> Self {
+        load_config_section("osquery")
+    }
+}
+
+// Runs osquery scheduled queries and packs on their own intervals and turns result rows
+// into events. Differential queries report added / removed rows the way osqueryd logs them,
+// snapshot queries report every row on each run.
+pub struct OsqueryCollector {
+    config: OsqueryConfig,
+    queries: Vec<ScheduledQuery>,
+    next_run: HashMap<String, Instant>,
+    previous: HashMap<String, HashSet<Row>>,
+    host: Arc<HostIdentityService>,
+}
+
+impl OsqueryCollector {
+    pub fn new() -> Self {
+        Self::with_config(OsqueryConfig::load())
+    }
+
+    pub fn with_config(config: OsqueryConfig) -> Self {
+        let mut queries: Vec<ScheduledQuery> = config
+            .queries
+            .iter()
+            .filter(|query| !query.name.is_empty() && !query.query.trim().is_empty())
+            .filter(|query| platform_matches(query.platform.as_deref()))
+            .map(|query| ScheduledQuery {
+                name: query.name.clone(),
+                pack: None,
+                sql: query.query.clone(),
+                interval: Duration::from_secs(query.interval_secs.max(1)),
+                snapshot: query.snapshot,
+                removed: query.removed,
+                severity: query.severity,
+            })
+            .collect();
+
+        for pack_path in &config.packs {
+            let path = Path::new(pack_path);
+            let pack_name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| pack_path.clone());
+            match fs::read_to_string(path) {
+                Ok(content) => match parse_pack(&pack_name, &content, config.pack_severity) {
+                    Ok(pack_queries) => {
+                        info!("Loaded {} queries from osquery pack {}", pack_queries.len(), pack_name);
+                        queries.extend(pack_queries);
+                    }
+                    Err(e) => error!("{}", e),
+                },
+                Err(e) => error!("Failed to read osquery pack {}: {}", pack_path, e),
+            }
+        }
+
+        Self {
+            config,
+            queries,
+            next_run: HashMap::new(),
+            previous: HashMap::new(),
+            host: HostIdentityService::global(),
+        }
+    }
+
+    fn run_osqueryi(&self, sql: &str) -> Result<Vec<Row>, String> {
+        let timeout = Duration::from_secs(self.config.osqueryi_timeout_secs.max(1));
+        let output = command::run_with_timeout(&self.config.osqueryi_path, ["--json", sql], timeout)
+            .map_err(|e| format!("Failed to execute osqueryi: {}", e))?;
+        if !output.success {
+            return Err(format!("osqueryi failed: {}", output.stderr.trim()));
+        }
+
+        // Newer osqueryi versions emit numbers as JSON numbers, older ones only strings
+        let rows: Vec<BTreeMap<String, Value>> = serde_json::from_str(&output.stdout)
+            .map_err(|e| format!("Invalid osqueryi output: {}", e))?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(column, value)| match value {
+                        Value::String(text) => (column, text),
+                        Value::Null => (column, String::new()),
+                        other => (column, other.to_string()),
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    fn run_extension(&self, sql: &str) -> Result<Vec<Row>, String> {
+        let timeout = Duration::from_secs(self.config.socket_timeout_secs.max(1));
+        let mut client = ExtensionClient::connect(&self.config.extension_socket, timeout)
+            .map_err(|e| format!("Failed to connect to {}: {}", self.config.extension_socket, e))?;
+        client.query(sql).map_err(|e| format!("Extension query failed: {}", e))
+    }
+
+    fn run(&self, sql: &str) -> Result<Vec<Row>, String> {
+        match self.config.mode {
+            OsqueryMode::Osqueryi => self.run_osqueryi(sql),
+            OsqueryMode::ExtensionSocket => self.run_extension(sql),
+            OsqueryMode::Auto => self.run_extension(sql).or_else(|e| {
+                debug!("{}, falling back to osqueryi", e);
+                self.run_osqueryi(sql)
+            }),
+        }
+    }
+
+    fn event(&self, query: &ScheduledQuery, action: OsqueryAction, columns: Row) -> Result<OsqueryResultEvent, CollectionError> {
+        let mut builder = OsqueryResultEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("osquery"))
+            .query_name(query.name.clone())
+            .action(action)
+            .severity(query.severity)
+            .columns(columns);
+        if let Some(pack) = &query.pack {
+            builder = builder.pack(pack.clone());
+        }
+        builder.build().map_err(CollectionError::Parse)
+    }
+
+    fn collect_results(&mut self) -> Result<Vec<OsqueryResultEvent>, CollectionError> {
+        let now = Instant::now();
+        let due: Vec<ScheduledQuery> = self
+            .queries
+            .iter()
+            .filter(|query| self.next_run.get(&query.name).map_or(true, |next| *next <= now))
+            .cloned()
+            .collect();
+
+        let mut events = Vec::new();
+        for query in &due {
+            self.next_run.insert(query.name.clone(), now + query.interval);
+
+            let mut rows = match self.run(&query.sql) {
+                Ok(rows) => rows,
+                Err(e) => {
+                    error!("osquery query {} failed: {}", query.name, e);
+                    continue;
+                }
+            };
+            rows.retain(|row| !row.is_empty());
+            if rows.len() > self.config.max_rows_per_query {
+                warn!(
+                    "osquery query {} returned {} rows, keeping the first {}",
+                    query.name,
+                    rows.len(),
+                    self.config.max_rows_per_query
+                );
+                rows.truncate(self.config.max_rows_per_query);
+            }
+
+            if query.snapshot {
+                for row in rows {
+                    events.push(self.event(query, OsqueryAction::Snapshot, row)?);
+                }
+                continue;
+            }
+
+            // Like osqueryd, the first run of a differential query reports every row as added
+            let current: HashSet<Row> = rows.into_iter().collect();
+            let previous = self.previous.remove(&query.name).unwrap_or_default();
+            for row in current.difference(&previous) {
+                events.push(self.event(query, OsqueryAction::Added, row.clone())?);
+            }
+            if query.removed {
+                for row in previous.difference(&current) {
+                    events.push(self.event(query, OsqueryAction::Removed, row.clone())?);
+                }
+            }
+            self.previous.insert(query.name.clone(), current);
+        }
+
+        if !due.is_empty() {
+            info!("Ran {} osquery queries, {} result events", due.len(), events.len());
+        }
+        Ok(events)
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        let osqueryi = which(&self.config.osqueryi_path).is_ok();
+        let socket = Path::new(&self.config.extension_socket).exists();
+        let available = match self.config.mode {
+            OsqueryMode::Osqueryi => osqueryi,
+            OsqueryMode::ExtensionSocket => socket,
+            OsqueryMode::Auto => osqueryi || socket,
+        };
+        if !available {
+            return Err(CollectionError::SystemApi(
+                "Neither osqueryi nor the osquery extension socket is available".to_string()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DataCollector<Vec<OsqueryResultEvent>> for OsqueryCollector {
+    fn collect(&mut self) -> Result<Vec<OsqueryResultEvent>, CollectionError> {
+        self.collect_results()
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<OsqueryResultEvent>> for OsqueryCollector {
+    async fn collect(&mut self) -> Result<Vec<OsqueryResultEvent>, CollectionError> {
+        DataCollector::collect(self)
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+impl Default for OsqueryCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,7 @@
+mod collector;
+mod models;
+mod pack;
+mod thrift;
+
+pub use collector::{OsqueryCollector, OsqueryConfig, OsqueryMode, OsqueryQueryConfig};
+pub use models::{OsqueryAction, OsqueryResultEvent, OsqueryResultEventBuilder};
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+// Same meaning as the `action` field of osqueryd's result log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OsqueryAction {
+    Snapshot,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsqueryResultEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    // Scheduled query name, `pack_<pack>_<query>` for queries loaded from a pack
+    pub query_name: String,
+    pub pack: Option<String>,
+    pub action: OsqueryAction,
+    pub severity: Severity,
+    pub columns: BTreeMap<String, String>,
+}
+
+impl Event for OsqueryResultEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.action {
+            OsqueryAction::Snapshot => "osquery_snapshot",
+            OsqueryAction::Added => "osquery_added",
+            OsqueryAction::Removed => "osquery_removed",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl Identifiable for OsqueryResultEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for OsqueryResultEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.query_name.is_empty() {
+            return Err("Query name cannot be empty".to_string());
+        }
+
+        if self.columns.is_empty() {
+            return Err("Result row cannot be empty".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct OsqueryResultEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    query_name: Option<String>,
+    pack: Option<String>,
+    action: Option<OsqueryAction>,
+    severity: Option<Severity>,
+    columns: Option<BTreeMap<String, String>>,
+}
+
+impl OsqueryResultEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn query_name(mut self, query_name: String) -> Self {
+        self.query_name = Some(query_name);
+        self
+    }
+
+    pub fn pack(mut self, pack: String) -> Self {
+        self.pack = Some(pack);
+        self
+    }
+
+    pub fn action(mut self, action: OsqueryAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    pub fn columns(mut self, columns: BTreeMap<String, String>) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    pub fn build(self) -> Result<OsqueryResultEvent, String> {
+        let event = OsqueryResultEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            query_name: self.query_name.ok_or("query_name is required")?,
+            pack: self.pack,
+            action: self.action.ok_or("action is required")?,
+            severity: self.severity.unwrap_or(Severity::Low),
+            columns: self.columns.ok_or("columns is required")?,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::shared::traits::Severity;
+use serde_json::Value;
+use std::time::Duration;
+
+// A query ready to be scheduled, either from the agent config or from an osquery pack
+#[derive(Debug, Clone)]
+pub struct ScheduledQuery {
+    pub name: String,
+    pub pack: Option<String>,
+    pub sql: String,
+    pub interval: Duration,
+    pub snapshot: bool,
+    // Differential queries: also report rows that disappeared
+    pub removed: bool,
+    pub severity: Severity,
+}
+
+// Pack and query `platform` values use osquery's vocabulary
+pub fn platform_matches(platform: Option<&str>) -> bool {
+    let Some(platform) = platform.map(str::trim).filter(|platform| !platform.is_empty()) else {
+        return true;
+    };
+    platform.split(',').map(str::trim).any(|platform| match platform {
+        "all" | "any" => true,
+        "windows" => cfg!(target_os = "windows"),
+        "linux" => cfg!(target_os = "linux"),
+        "darwin" => cfg!(target_os = "macos"),
+        "freebsd" => cfg!(target_os = "freebsd"),
+        "posix" => cfg!(unix),
+        _ => false,
+    })
+}
+
+// Pack files are "JSON" with `//` comment lines and backslash line continuations
+fn normalize(content: &str) -> String {
+    content
+        .replace("\\\r\n", "")
+        .replace("\\\n", "")
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Numbers in packs are often quoted ("interval": "3600")
+fn number(value: Option<&Value>) -> Option<u64> {
+    match value? {
+        Value::Number(number) => number.as_u64(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn flag(value: Option<&Value>) -> Option<bool> {
+    match value? {
+        Value::Bool(flag) => Some(*flag),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+pub fn parse_pack(pack_name: &str, content: &str, severity: Severity) -> Result<Vec<ScheduledQuery>, String> {
+    let pack: Value = serde_json::from_str(&normalize(content))
+        .map_err(|e| format!("Invalid pack {}: {}", pack_name, e))?;
+    if !platform_matches(pack.get("platform").and_then(Value::as_str)) {
+        return Ok(Vec::new());
+    }
+    let queries = pack
+        .get("queries")
+        .and_then(Value::as_object)
+        .ok_or_else(|| format!("Pack {} has no queries", pack_name))?;
+
+    let mut scheduled = Vec::new();
+    for (name, query) in queries {
+        let Some(sql) = query.get("query").and_then(Value::as_str) else {
+            continue;
+        };
+        if !platform_matches(query.get("platform").and_then(Value::as_str)) {
+            continue;
+        }
+        scheduled.push(ScheduledQuery {
+            name: format!("pack_{}_{}", pack_name, name),
+            pack: Some(pack_name.to_string()),
+            sql: sql.to_string(),
+            interval: Duration::from_secs(number(query.get("interval")).unwrap_or(3600).max(1)),
+            snapshot: flag(query.get("snapshot")).unwrap_or(false),
+            removed: flag(query.get("removed")).unwrap_or(true),
+            severity,
+        });
+    }
+
+    Ok(scheduled)
+}
@@ -0,0 +1,273 @@
+// Just enough of the Thrift binary protocol to call `ExtensionManager.query` on a
+// running osqueryd through its extension socket (Unix socket, named pipe on Windows).
+use std::collections::BTreeMap;
+use std::io::{self, BufReader, Read, Write};
+use std::time::Duration;
+
+const VERSION_1: u32 = 0x8001_0000;
+const VERSION_MASK: u32 = 0xffff_0000;
+const MESSAGE_CALL: u32 = 1;
+const MESSAGE_REPLY: u32 = 2;
+const MESSAGE_EXCEPTION: u32 = 3;
+
+const T_STOP: u8 = 0;
+const T_BOOL: u8 = 2;
+const T_BYTE: u8 = 3;
+const T_DOUBLE: u8 = 4;
+const T_I16: u8 = 6;
+const T_I32: u8 = 8;
+const T_I64: u8 = 10;
+const T_STRING: u8 = 11;
+const T_STRUCT: u8 = 12;
+const T_MAP: u8 = 13;
+const T_SET: u8 = 14;
+const T_LIST: u8 = 15;
+
+const MAX_STRING_BYTES: usize = 16 * 1024 * 1024;
+const MAX_NESTING: usize = 32;
+
+pub type Row = BTreeMap<String, String>;
+
+trait Stream: Read + Write {}
+impl<T: Read + Write> Stream for T {}
+
+pub struct ExtensionClient {
+    stream: Box<dyn Stream + Send>,
+    sequence: i32,
+}
+
+impl ExtensionClient {
+    #[cfg(unix)]
+    pub fn connect(path: &str, timeout: Duration) -> io::Result<Self> {
+        let stream = std::os::unix::net::UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        Ok(Self { stream: Box::new(stream), sequence: 0 })
+    }
+
+    // Named pipes open like files; they have no per-call timeout
+    #[cfg(not(unix))]
+    pub fn connect(path: &str, _timeout: Duration) -> io::Result<Self> {
+        let stream = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self { stream: Box::new(stream), sequence: 0 })
+    }
+
+    pub fn query(&mut self, sql: &str) -> io::Result<Vec<Row>> {
+        self.sequence = self.sequence.wrapping_add(1);
+
+        let mut request = Vec::with_capacity(sql.len() + 32);
+        request.extend_from_slice(&(VERSION_1 | MESSAGE_CALL).to_be_bytes());
+        write_string(&mut request, "query");
+        request.extend_from_slice(&self.sequence.to_be_bytes());
+        // query_args { 1: string sql }
+        request.push(T_STRING);
+        request.extend_from_slice(&1i16.to_be_bytes());
+        write_string(&mut request, sql);
+        request.push(T_STOP);
+
+        self.stream.write_all(&request)?;
+        self.stream.flush()?;
+
+        let mut decoder = Decoder { reader: BufReader::new(&mut self.stream) };
+        decoder.query_reply()
+    }
+}
+
+fn write_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(&(value.len() as i32).to_be_bytes());
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+struct Decoder<R> {
+    reader: R,
+}
+
+impl<R: Read> Decoder<R> {
+    fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut buffer = [0u8; N];
+        self.reader.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    fn i16(&mut self) -> io::Result<i16> {
+        Ok(i16::from_be_bytes(self.bytes()?))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_be_bytes(self.bytes()?))
+    }
+
+    fn size(&mut self) -> io::Result<usize> {
+        let size = self.i32()?;
+        usize::try_from(size).map_err(|_| invalid(format!("Negative size {}", size)))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let length = self.size()?;
+        if length > MAX_STRING_BYTES {
+            return Err(invalid(format!("String of {} bytes exceeds limit", length)));
+        }
+        let mut buffer = vec![0u8; length];
+        self.reader.read_exact(&mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+
+    // Next field header of a struct, None at the end of the struct
+    fn field(&mut self) -> io::Result<Option<(u8, i16)>> {
+        match self.byte()? {
+            T_STOP => Ok(None),
+            field_type => Ok(Some((field_type, self.i16()?))),
+        }
+    }
+
+    fn skip(&mut self, field_type: u8, depth: usize) -> io::Result<()> {
+        if depth > MAX_NESTING {
+            return Err(invalid("Reply is nested too deeply"));
+        }
+        match field_type {
+            T_BOOL | T_BYTE => {
+                self.bytes::<1>()?;
+            }
+            T_I16 => {
+                self.bytes::<2>()?;
+            }
+            T_I32 => {
+                self.bytes::<4>()?;
+            }
+            T_DOUBLE | T_I64 => {
+                self.bytes::<8>()?;
+            }
+            T_STRING => {
+                self.string()?;
+            }
+            T_STRUCT => {
+                while let Some((inner, _)) = self.field()? {
+                    self.skip(inner, depth + 1)?;
+                }
+            }
+            T_MAP => {
+                let key_type = self.byte()?;
+                let value_type = self.byte()?;
+                for _ in 0..self.size()? {
+                    self.skip(key_type, depth + 1)?;
+                    self.skip(value_type, depth + 1)?;
+                }
+            }
+            T_SET | T_LIST => {
+                let element_type = self.byte()?;
+                for _ in 0..self.size()? {
+                    self.skip(element_type, depth + 1)?;
+                }
+            }
+            other => return Err(invalid(format!("Unknown field type {}", other))),
+        }
+        Ok(())
+    }
+
+    fn query_reply(&mut self) -> io::Result<Vec<Row>> {
+        let header = self.i32()? as u32;
+        if header & VERSION_MASK != VERSION_1 {
+            return Err(invalid(format!("Unsupported protocol header {:#x}", header)));
+        }
+        self.string()?;
+        self.i32()?;
+
+        match header & 0xff {
+            MESSAGE_REPLY => {}
+            MESSAGE_EXCEPTION => return Err(invalid(self.application_exception()?)),
+            other => return Err(invalid(format!("Unexpected message type {}", other))),
+        }
+
+        // query_result { 0: ExtensionResponse success }
+        let mut rows = None;
+        while let Some((field_type, id)) = self.field()? {
+            match (id, field_type) {
+                (0, T_STRUCT) => rows = Some(self.extension_response()?),
+                _ => self.skip(field_type, 0)?,
+            }
+        }
+        rows.ok_or_else(|| invalid("Reply carried no result"))?
+    }
+
+    fn application_exception(&mut self) -> io::Result<String> {
+        let mut message = String::from("osquery returned an exception");
+        while let Some((field_type, id)) = self.field()? {
+            match (id, field_type) {
+                (1, T_STRING) => message = self.string()?,
+                _ => self.skip(field_type, 0)?,
+            }
+        }
+        Ok(message)
+    }
+
+    // ExtensionResponse { 1: ExtensionStatus status, 2: list<map<string, string>> response }
+    fn extension_response(&mut self) -> io::Result<io::Result<Vec<Row>>> {
+        let mut status = (0, String::new());
+        let mut rows = Vec::new();
+
+        while let Some((field_type, id)) = self.field()? {
+            match (id, field_type) {
+                (1, T_STRUCT) => status = self.extension_status()?,
+                (2, T_LIST) => rows = self.rows()?,
+                _ => self.skip(field_type, 0)?,
+            }
+        }
+
+        Ok(match status {
+            (0, _) => Ok(rows),
+            (code, message) => Err(invalid(format!("Query failed ({}): {}", code, message))),
+        })
+    }
+
+    // ExtensionStatus { 1: i32 code, 2: string message, 3: i64 uuid }
+    fn extension_status(&mut self) -> io::Result<(i32, String)> {
+        let mut code = 0;
+        let mut message = String::new();
+        while let Some((field_type, id)) = self.field()? {
+            match (id, field_type) {
+                (1, T_I32) => code = self.i32()?,
+                (2, T_STRING) => message = self.string()?,
+                _ => self.skip(field_type, 0)?,
+            }
+        }
+        Ok((code, message))
+    }
+
+    fn rows(&mut self) -> io::Result<Vec<Row>> {
+        let element_type = self.byte()?;
+        let count = self.size()?;
+        if element_type != T_MAP {
+            for _ in 0..count {
+                self.skip(element_type, 1)?;
+            }
+            return Ok(Vec::new());
+        }
+
+        let mut rows = Vec::with_capacity(count.min(4096));
+        for _ in 0..count {
+            let key_type = self.byte()?;
+            let value_type = self.byte()?;
+            let columns = self.size()?;
+            let mut row = Row::new();
+            for _ in 0..columns {
+                if key_type == T_STRING && value_type == T_STRING {
+                    let key = self.string()?;
+                    row.insert(key, self.string()?);
+                } else {
+                    self.skip(key_type, 2)?;
+                    self.skip(value_type, 2)?;
+                }
+            }
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
@@ -0,0 +1,541 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector};
+use crate::shared::baseline::Baseline;
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::paths::expand_path;
+use crate::shared::patterns::{PatternSet, DEFAULT_PATTERNS};
+use crate::shared::user_profiles::{user_profiles, UserProfile};
+use crate::shared::user_resolver::UserResolver;
+use crate::features::persistence::models::{
+    PersistenceChange, PersistenceEntry, PersistenceEvent, PersistenceEventBuilder, PersistenceKind,
+};
+use crate::features::persistence::parsers::{
+    chromium_disabled_extensions, localized_message, parse_chromium_manifest, parse_desktop_entry,
+    parse_firefox_extensions, parse_shell_link,
+};
+use crate::utils::load_config_section;
+use chrono::Utc;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersistenceConfig {
+    pub enabled: bool,
+    // Emit a full snapshot every N collections; diffs are emitted in between
+    pub snapshot_every: u32,
+    // Folders whose contents start at logon, as path templates (${USERPROFILE}, ${PROGRAMDATA},
+    // wildcards); XDG autostart directories outside Windows
+    pub startup_folders: Vec<String>,
+    // Browser profile directories, as path templates; Chrome, Edge and Chromium profiles are
+    // told from Firefox ones by what they contain
+    pub browser_profiles: Vec<String>,
+    // Permanent WMI event consumers and their filters, Windows only
+    pub wmi_subscriptions: bool,
+    // COM servers registered in the loaded user hives, Windows only
+    pub com_hijacks: bool,
+    // Extension permissions that give an extension reach over every site or the host
+    pub risky_permissions: Vec<String>,
+    // Regexes (case-insensitive) that mark an entry's command as suspicious; also matched
+    // against base64 and hex found in the command
+    pub suspicious_patterns: Vec<String>,
+}
+
+impl Default for PersistenceConfig {
+    fn default() -> Self {
+        let (startup_folders, browser_profiles): (&[&str], &[&str]) = if cfg!(windows) {
+            (
+                &[
+                    "${USERPROFILE}\\AppData\\Roaming\\Microsoft\\Windows\\Start Menu\\Programs\\Startup",
+                    "${PROGRAMDATA}\\Microsoft\\Windows\\Start Menu\\Programs\\StartUp",
+                ],
+                &[
+                    "${USERPROFILE}\\AppData\\Local\\Google\\Chrome\\User Data\\*",
+                    "${USERPROFILE}\\AppData\\Local\\Microsoft\\Edge\\User Data\\*",
+                    "${USERPROFILE}\\AppData\\Local\\Chromium\\User Data\\*",
+                    "${USERPROFILE}\\AppData\\Roaming\\Mozilla\\Firefox\\Profiles\\*",
+                ],
+            )
+        } else if cfg!(target_os = "macos") {
+            // Login items live in a per-user database, and launchd agents are covered by the
+            // registry collector's autorun checks
+            (
+                &[],
+                &[
+                    "${HOME}/Library/Application Support/Google/Chrome/*",
+                    "${HOME}/Library/Application Support/Microsoft Edge/*",
+                    "${HOME}/Library/Application Support/Chromium/*",
+                    "${HOME}/Library/Application Support/Firefox/Profiles/*",
+                ],
+            )
+        } else {
+            (
+                &["/etc/xdg/autostart", "${HOME}/.config/autostart"],
+                &[
+                    "${HOME}/.config/google-chrome/*",
+                    "${HOME}/.config/microsoft-edge/*",
+                    "${HOME}/.config/chromium/*",
+                    "${HOME}/.mozilla/firefox/*",
+                ],
+            )
+        };
+        Self {
+            enabled: true,
+            snapshot_every: 60,
+            startup_folders: startup_folders.iter().map(|folder| folder.to_string()).collect(),
+            browser_profiles: browser_profiles.iter().map(|profile| profile.to_string()).collect(),
+            wmi_subscriptions: true,
+            com_hijacks: true,
+            risky_permissions: [
+                "<all_urls>",
+                "*://*/*",
+                "http://*/*",
+                "https://*/*",
+                "nativeMessaging",
+                "debugger",
+                "proxy",
+                "webRequestBlocking",
+                "management",
+                "desktopCapture",
+                "clipboardRead",
+            ]
+            .iter()
+            .map(|permission| permission.to_string())
+            .collect(),
+            suspicious_patterns: [
+                r"powershell(\.exe)?.*\s-(e|en|enc|encodedcommand)\s",
+                r"powershell(\.exe)?.*-w(indowstyle)?\s+hidden",
+                r"(iex|invoke-expression|downloadstring|downloadfile)",
+                r"\\appdata\\(local\\temp|roaming)\\",
+                r"\\(users\\public|programdata)\\[^\\]+\.(exe|dll)",
+                r"\b(mshta|regsvr32|rundll32|certutil|bitsadmin|wscript|cscript)(\.exe)?\b",
+                r"(curl|wget)\s[^|]*\|\s*(ba|z|da)?sh",
+                r"(^|[\s/])(tmp|dev/shm|var/tmp)/",
+            ]
+            .iter()
+            .chain(DEFAULT_PATTERNS)
+            .map(|pattern| pattern.to_string())
+            .collect(),
+        }
+    }
+}
+
+impl PersistenceConfig {
+    pub fn load() -> Self {
+        load_config_section("persistence")
+    }
+}
+
+#[cfg(windows)]
+use crate::features::persistence::win32::{com_servers, machine_classes, user_hives, wmi_consumers};
+
+#[cfg(not(windows))]
+use crate::features::persistence::parsers::{ComServer, WmiConsumer};
+
+#[cfg(not(windows))]
+fn wmi_consumers() -> Vec<WmiConsumer> {
+    Vec::new()
+}
+
+#[cfg(not(windows))]
+fn user_hives() -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(not(windows))]
+fn com_servers(_root: &str) -> Vec<ComServer> {
+    Vec::new()
+}
+
+#[cfg(not(windows))]
+fn machine_classes() -> HashSet<String> {
+    HashSet::new()
+}
+
+// Consumer classes that run something; the others log or send mail
+const EXECUTING_CONSUMERS: [&str; 2] = ["CommandLineEventConsumer", "ActiveScriptEventConsumer"];
+
+// Startup scripts are small; anything larger is reported without a hash
+const MAX_HASH_BYTES: u64 = 16 * 1024 * 1024;
+
+// Persistence outside the autorun registry keys and services: what the Startup folders (XDG
+// autostart elsewhere) start at logon, browser extensions, permanent WMI event subscriptions
+// and per-user COM server registrations that take precedence over the machine's. Reported
+// like scheduled tasks, a full snapshot every `snapshot_every` collections and what was
+// created, modified or deleted in between; entries seen before a restart are not new again.
+pub struct PersistenceCollector {
+    config: PersistenceConfig,
+    patterns: PatternSet,
+    known: HashMap<(PersistenceKind, String, String), PersistenceEntry>,
+    // Whether `known` holds a previous inventory to diff against
+    baselined: bool,
+    // CLSIDs registered machine-wide, from the latest collection
+    machine_classes: HashSet<String>,
+    collections: u32,
+    host: Arc<HostIdentityService>,
+}
+
+impl PersistenceCollector {
+    const BASELINE: &'static str = "persistence";
+
+    pub fn new() -> Self {
+        Self::with_config(PersistenceConfig::load())
+    }
+
+    pub fn with_config(config: PersistenceConfig) -> Self {
+        let patterns = PatternSet::compile_regexes(&config.suspicious_patterns);
+        let known: Option<Vec<PersistenceEntry>> = Baseline::global().load(Self::BASELINE);
+
+        Self {
+            config,
+            patterns,
+            baselined: known.is_some(),
+            known: known
+                .unwrap_or_default()
+                .into_iter()
+                .map(|entry| (entry.key(), entry))
+                .collect(),
+            machine_classes: HashSet::new(),
+            collections: 0,
+            host: HostIdentityService::global(),
+        }
+    }
+
+    // The user whose profile holds `path`, if any
+    fn owner(path: &Path, profiles: &[UserProfile]) -> Option<String> {
+        profiles
+            .iter()
+            .find(|profile| path.starts_with(&profile.path))
+            .map(|profile| profile.user.clone())
+    }
+
+    fn hash_file(path: &Path) -> Option<String> {
+        if fs::metadata(path).ok()?.len() > MAX_HASH_BYTES {
+            return None;
+        }
+        let mut file = fs::File::open(path).ok()?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher).ok()?;
+        Some(format!("sha256:{:x}", hasher.finalize()))
+    }
+
+    fn startup_entries(&self, profiles: &[UserProfile]) -> Vec<PersistenceEntry> {
+        let mut entries = Vec::new();
+        for folder in self.config.startup_folders.iter().flat_map(|template| expand_path(template)) {
+            let Ok(files) = fs::read_dir(&folder) else {
+                continue;
+            };
+            for path in files.flatten().map(|file| file.path()).filter(|path| path.is_file()) {
+                let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                if name.eq_ignore_ascii_case("desktop.ini") {
+                    continue;
+                }
+                let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+                let mut entry = PersistenceEntry {
+                    kind: PersistenceKind::StartupFolder,
+                    location: folder.clone(),
+                    name,
+                    command: path.to_string_lossy().into_owned(),
+                    detail: None,
+                    permissions: Vec::new(),
+                    user: Self::owner(&path, profiles),
+                    enabled: true,
+                };
+                match extension.as_deref() {
+                    Some("lnk") => {
+                        if let Some(command) = fs::read(&path).ok().and_then(|data| parse_shell_link(&data)?.command_line()) {
+                            entry.command = command;
+                        }
+                    }
+                    Some("desktop") => {
+                        let Some(desktop) = fs::read_to_string(&path).ok().and_then(|content| parse_desktop_entry(&content)) else {
+                            continue;
+                        };
+                        entry.command = desktop.exec;
+                        entry.detail = desktop.name;
+                        entry.enabled = desktop.enabled;
+                    }
+                    // Scripts and executables dropped in the folder run as they are
+                    _ => entry.detail = Self::hash_file(&path),
+                }
+                entries.push(entry);
+            }
+        }
+        entries
+    }
+
+    // The newest installed version directory of each extension in a Chromium profile
+    fn chromium_extensions(profile: &Path, user: Option<String>) -> Vec<PersistenceEntry> {
+        let Ok(installed) = fs::read_dir(profile.join("Extensions")) else {
+            return Vec::new();
+        };
+        let disabled: HashSet<String> = ["Preferences", "Secure Preferences"]
+            .iter()
+            .filter_map(|file| fs::read_to_string(profile.join(file)).ok())
+            .flat_map(|preferences| chromium_disabled_extensions(&preferences))
+            .collect();
+
+        let mut entries = Vec::new();
+        for extension in installed.flatten().filter(|entry| entry.path().is_dir()) {
+            let id = extension.file_name().to_string_lossy().into_owned();
+            if id == "Temp" {
+                continue;
+            }
+            let mut versions: Vec<PathBuf> = fs::read_dir(extension.path())
+                .map(|versions| versions.flatten().map(|version| version.path()).filter(|path| path.is_dir()).collect())
+                .unwrap_or_default();
+            versions.sort();
+            let Some(directory) = versions.pop() else {
+                continue;
+            };
+            let Some(manifest) = fs::read_to_string(directory.join("manifest.json"))
+                .ok()
+                .and_then(|content| parse_chromium_manifest(&content))
+            else {
+                debug!("Skipping extension {} without a readable manifest", directory.display());
+                continue;
+            };
+            let name = manifest
+                .default_locale
+                .as_ref()
+                .and_then(|locale| fs::read_to_string(directory.join("_locales").join(locale).join("messages.json")).ok())
+                .and_then(|messages| localized_message(&messages, &manifest.name))
+                .unwrap_or(manifest.name);
+            entries.push(PersistenceEntry {
+                kind: PersistenceKind::BrowserExtension,
+                location: profile.to_string_lossy().into_owned(),
+                enabled: !disabled.contains(&id),
+                name: id,
+                command: directory.to_string_lossy().into_owned(),
+                detail: Some(format!("{} {}", name, manifest.version)),
+                permissions: manifest.permissions,
+                user: user.clone(),
+            });
+        }
+        entries
+    }
+
+    fn firefox_extensions(profile: &Path, user: Option<String>) -> Vec<PersistenceEntry> {
+        let Ok(content) = fs::read_to_string(profile.join("extensions.json")) else {
+            return Vec::new();
+        };
+        parse_firefox_extensions(&content)
+            .into_iter()
+            .map(|addon| PersistenceEntry {
+                kind: PersistenceKind::BrowserExtension,
+                location: profile.to_string_lossy().into_owned(),
+                command: addon
+                    .path
+                    .unwrap_or_else(|| profile.join("extensions").join(format!("{}.xpi", addon.id)).to_string_lossy().into_owned()),
+                detail: Some(format!("{} {}", addon.name.as_deref().unwrap_or(&addon.id), addon.version)),
+                name: addon.id,
+                permissions: addon.permissions,
+                user: user.clone(),
+                enabled: addon.active,
+            })
+            .collect()
+    }
+
+    fn extension_entries(&self, profiles: &[UserProfile]) -> Vec<PersistenceEntry> {
+        let mut entries = Vec::new();
+        for profile in self.config.browser_profiles.iter().flat_map(|template| expand_path(template)) {
+            let profile = PathBuf::from(profile);
+            let user = Self::owner(&profile, profiles);
+            if profile.join("Extensions").is_dir() {
+                entries.extend(Self::chromium_extensions(&profile, user));
+            } else if profile.join("extensions.json").is_file() {
+                entries.extend(Self::firefox_extensions(&profile, user));
+            }
+        }
+        entries
+    }
+
+    fn wmi_entries(&self) -> Vec<PersistenceEntry> {
+        wmi_consumers()
+            .into_iter()
+            .map(|consumer| PersistenceEntry {
+                kind: PersistenceKind::WmiSubscription,
+                location: format!("root/subscription:{}", consumer.class),
+                // A consumer without a binding never fires
+                enabled: !consumer.filters.is_empty(),
+                detail: (!consumer.filters.is_empty()).then(|| consumer.filters.join("; ")),
+                name: consumer.name,
+                command: consumer.command,
+                permissions: Vec::new(),
+                user: None,
+            })
+            .collect()
+    }
+
+    // Per-user registrations are looked up before the machine's, so a class registered in a
+    // user's hive is what that user's processes load
+    fn com_entries(&mut self) -> Vec<PersistenceEntry> {
+        let hives = user_hives();
+        if hives.is_empty() {
+            return Vec::new();
+        }
+        self.machine_classes = machine_classes();
+        let resolver = UserResolver::global();
+        let mut entries = Vec::new();
+        for sid in hives {
+            let root = format!(r"HKEY_USERS\{}_Classes\CLSID", sid);
+            let user = resolver.display_id(&sid);
+            entries.extend(com_servers(&root).into_iter().map(|server| PersistenceEntry {
+                kind: PersistenceKind::ComHijack,
+                location: root.clone(),
+                name: format!("{}\\{}", server.clsid, server.server),
+                command: server.path,
+                detail: None,
+                permissions: Vec::new(),
+                user: Some(user.clone()),
+                enabled: true,
+            }));
+        }
+        entries
+    }
+
+    fn enumerate(&mut self) -> Vec<PersistenceEntry> {
+        let profiles = user_profiles();
+        let mut entries = self.startup_entries(&profiles);
+        entries.extend(self.extension_entries(&profiles));
+        if cfg!(windows) && self.config.wmi_subscriptions {
+            entries.extend(self.wmi_entries());
+        }
+        if cfg!(windows) && self.config.com_hijacks {
+            entries.extend(self.com_entries());
+        }
+        entries
+    }
+
+    fn suspicious_reasons(&self, entry: &PersistenceEntry) -> Vec<String> {
+        let mut reasons: Vec<String> = self
+            .patterns
+            .matches(&entry.command)
+            .iter()
+            .map(|matched| format!("command matches {}", matched.describe()))
+            .collect();
+        reasons.extend(
+            entry
+                .permissions
+                .iter()
+                .filter(|permission| self.config.risky_permissions.contains(permission))
+                .map(|permission| format!("requests the {} permission", permission)),
+        );
+        match entry.kind {
+            PersistenceKind::WmiSubscription if entry.enabled && EXECUTING_CONSUMERS.iter().any(|class| entry.location.ends_with(class)) => {
+                reasons.push(String::from("bound consumer runs a command or script"));
+            }
+            PersistenceKind::ComHijack => {
+                let clsid = entry.name.split('\\').next().unwrap_or_default();
+                if self.machine_classes.contains(clsid) {
+                    reasons.push(format!("overrides the machine-wide registration of {}", clsid));
+                }
+            }
+            _ => {}
+        }
+        reasons
+    }
+
+    fn event(&self, change: PersistenceChange, entry: PersistenceEntry, previous: Option<&PersistenceEntry>) -> Result<PersistenceEvent, CollectionError> {
+        let reasons = self.suspicious_reasons(&entry);
+        let mut builder = PersistenceEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("persistence"))
+            .change(change)
+            .entry(entry)
+            .suspicious_reasons(reasons);
+        if let Some(previous) = previous {
+            builder = builder.previous_command(previous.command.clone());
+        }
+        builder.build().map_err(CollectionError::Parse)
+    }
+
+    fn collect_entries(&mut self) -> Result<Vec<PersistenceEvent>, CollectionError> {
+        let current: HashMap<_, _> = self
+            .enumerate()
+            .into_iter()
+            .map(|entry| (entry.key(), entry))
+            .collect();
+
+        let snapshot = self.collections % self.config.snapshot_every.max(1) == 0;
+        self.collections = self.collections.wrapping_add(1);
+
+        let mut events = Vec::new();
+        for (key, entry) in &current {
+            match self.known.get(key) {
+                // The first inventory is the baseline, nothing in it counts as new
+                None if self.baselined => {
+                    events.push(self.event(PersistenceChange::Created, entry.clone(), None)?);
+                }
+                Some(previous) if previous != entry => {
+                    events.push(self.event(PersistenceChange::Modified, entry.clone(), Some(previous))?);
+                }
+                _ if snapshot => {
+                    events.push(self.event(PersistenceChange::Snapshot, entry.clone(), None)?);
+                }
+                _ => {}
+            }
+        }
+        for (key, entry) in &self.known {
+            if !current.contains_key(key) {
+                events.push(self.event(PersistenceChange::Deleted, entry.clone(), None)?);
+            }
+        }
+
+        let suspicious = events.iter().filter(|event| event.suspicious).count();
+        if suspicious > 0 {
+            warn!("{} persistence entries look suspicious", suspicious);
+        }
+        info!("Found {} persistence entries, {} events", current.len(), events.len());
+
+        if !self.baselined || current != self.known {
+            Baseline::global().save(Self::BASELINE, &current.values().collect::<Vec<_>>());
+        }
+        self.known = current;
+        self.baselined = true;
+        Ok(events)
+    }
+}
+
+impl DataCollector<Vec<PersistenceEvent>> for PersistenceCollector {
+    fn collect(&mut self) -> Result<Vec<PersistenceEvent>, CollectionError> {
+        self.collect_entries()
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        Ok(())
+    }
+
+    fn health_check(&self) -> bool {
+        true
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<PersistenceEvent>> for PersistenceCollector {
+    async fn collect(&mut self) -> Result<Vec<PersistenceEvent>, CollectionError> {
+        DataCollector::collect(self)
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        Ok(())
+    }
+
+    async fn health_check(&self) -> bool {
+        true
+    }
+}
+
+impl Default for PersistenceCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,14 @@
+mod collector;
+mod models;
+mod parsers;
+#[cfg(windows)]
+mod win32;
+
+pub use collector::{PersistenceCollector, PersistenceConfig};
+pub use models::{
+    PersistenceChange, PersistenceEntry, PersistenceEvent, PersistenceEventBuilder, PersistenceKind,
+};
+pub use parsers::{
+    parse_com_servers, parse_desktop_entry, parse_shell_link, parse_subkeys, parse_wmi_subscriptions, ComServer,
+    DesktopEntry, ShellLink, WmiConsumer,
+};
@@ -0,0 +1,208 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PersistenceKind {
+    StartupFolder,
+    BrowserExtension,
+    WmiSubscription,
+    ComHijack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PersistenceChange {
+    Snapshot,
+    Created,
+    Modified,
+    Deleted,
+}
+
+// One persistence entry as found on the host, before it becomes an event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistenceEntry {
+    pub kind: PersistenceKind,
+    // Folder, browser profile, WMI namespace or registry key the entry was found in
+    pub location: String,
+    pub name: String,
+    // What runs: a shortcut's target and arguments, an extension's directory, a consumer's
+    // command line or script, a COM server's DLL or executable
+    pub command: String,
+    // Kind-specific: a script's hash, an extension's display name and version, the event
+    // filter a consumer is bound to
+    pub detail: Option<String>,
+    // Permissions a browser extension requests
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    pub user: Option<String>,
+    pub enabled: bool,
+}
+
+impl PersistenceEntry {
+    pub fn key(&self) -> (PersistenceKind, String, String) {
+        (self.kind, self.location.clone(), self.name.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistenceEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub change: PersistenceChange,
+    pub kind: PersistenceKind,
+    pub location: String,
+    pub name: String,
+    pub command: String,
+    pub detail: Option<String>,
+    pub permissions: Vec<String>,
+    pub user: Option<String>,
+    pub enabled: bool,
+    pub previous_command: Option<String>,
+    pub suspicious: bool,
+    pub suspicious_reasons: Vec<String>,
+}
+
+impl Event for PersistenceEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.change {
+            PersistenceChange::Snapshot => "persistence_snapshot",
+            PersistenceChange::Created => "persistence_created",
+            PersistenceChange::Modified => "persistence_modified",
+            PersistenceChange::Deleted => "persistence_deleted",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        match self.change {
+            PersistenceChange::Created | PersistenceChange::Modified if self.suspicious => Severity::Critical,
+            PersistenceChange::Created | PersistenceChange::Modified => Severity::High,
+            PersistenceChange::Snapshot if self.suspicious => Severity::Medium,
+            PersistenceChange::Deleted => Severity::Medium,
+            PersistenceChange::Snapshot => Severity::Low,
+        }
+    }
+}
+
+impl Identifiable for PersistenceEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for PersistenceEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("Persistence entry name cannot be empty".to_string());
+        }
+
+        if self.location.is_empty() {
+            return Err("Persistence entry location cannot be empty".to_string());
+        }
+
+        if self.suspicious && self.suspicious_reasons.is_empty() {
+            return Err("Suspicious entries must carry at least one reason".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct PersistenceEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    change: Option<PersistenceChange>,
+    entry: Option<PersistenceEntry>,
+    previous_command: Option<String>,
+    suspicious_reasons: Vec<String>,
+}
+
+impl PersistenceEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn change(mut self, change: PersistenceChange) -> Self {
+        self.change = Some(change);
+        self
+    }
+
+    pub fn entry(mut self, entry: PersistenceEntry) -> Self {
+        self.entry = Some(entry);
+        self
+    }
+
+    pub fn previous_command(mut self, previous_command: String) -> Self {
+        self.previous_command = Some(previous_command);
+        self
+    }
+
+    pub fn suspicious_reasons(mut self, suspicious_reasons: Vec<String>) -> Self {
+        self.suspicious_reasons = suspicious_reasons;
+        self
+    }
+
+    pub fn build(self) -> Result<PersistenceEvent, String> {
+        let entry = self.entry.ok_or("entry is required")?;
+        let event = PersistenceEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            change: self.change.ok_or("change is required")?,
+            kind: entry.kind,
+            location: entry.location,
+            name: entry.name,
+            command: entry.command,
+            detail: entry.detail,
+            permissions: entry.permissions,
+            user: entry.user,
+            enabled: entry.enabled,
+            previous_command: self.previous_command,
+            suspicious: !self.suspicious_reasons.is_empty(),
+            suspicious_reasons: self.suspicious_reasons,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
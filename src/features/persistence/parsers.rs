@@ -0,0 +1,450 @@
+use serde_json::Value;
+use std::collections::HashSet;
+
+// What a Windows shortcut (.lnk, MS-SHLLINK) starts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShellLink {
+    pub target: Option<String>,
+    pub arguments: Option<String>,
+    pub working_directory: Option<String>,
+}
+
+impl ShellLink {
+    pub fn command_line(&self) -> Option<String> {
+        let target = self.target.as_deref().filter(|target| !target.is_empty())?;
+        Some(match self.arguments.as_deref().map(str::trim).filter(|arguments| !arguments.is_empty()) {
+            Some(arguments) => format!("{} {}", target, arguments),
+            None => target.to_string(),
+        })
+    }
+}
+
+const SHELL_LINK_HEADER_SIZE: usize = 0x4C;
+const SHELL_LINK_CLSID: [u8; 16] = [
+    0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+];
+
+// LinkFlags
+const HAS_LINK_TARGET_ID_LIST: u32 = 0x01;
+const HAS_LINK_INFO: u32 = 0x02;
+const HAS_NAME: u32 = 0x04;
+const HAS_RELATIVE_PATH: u32 = 0x08;
+const HAS_WORKING_DIR: u32 = 0x10;
+const HAS_ARGUMENTS: u32 = 0x20;
+const HAS_ICON_LOCATION: u32 = 0x40;
+const IS_UNICODE: u32 = 0x80;
+
+// LinkInfoFlags
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x01;
+const COMMON_NETWORK_RELATIVE_LINK: u32 = 0x02;
+
+// Extra data block holding the target with environment variables unexpanded, for links to
+// %windir% and the like that carry no LinkInfo
+const ENVIRONMENT_VARIABLE_BLOCK: u32 = 0xA000_0001;
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// NUL-terminated code page string, read as UTF-8 with anything else replaced
+fn c_string(data: &[u8], offset: usize) -> Option<String> {
+    let bytes = data.get(offset..)?;
+    let end = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+    Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+// NUL-terminated UTF-16LE string
+fn c_wstring(data: &[u8], offset: usize) -> Option<String> {
+    let units: Vec<u16> = data
+        .get(offset..)?
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|unit| *unit != 0)
+        .collect();
+    Some(String::from_utf16_lossy(&units))
+}
+
+// The local or network path in a LinkInfo structure
+fn link_info_target(info: &[u8]) -> Option<String> {
+    let header_size = u32_at(info, 4)? as usize;
+    let flags = u32_at(info, 8)?;
+    let suffix = if header_size >= 0x24 {
+        c_wstring(info, u32_at(info, 32)? as usize)
+    } else {
+        c_string(info, u32_at(info, 24)? as usize)
+    }
+    .unwrap_or_default();
+
+    if flags & VOLUME_ID_AND_LOCAL_BASE_PATH != 0 {
+        let base = if header_size >= 0x24 {
+            c_wstring(info, u32_at(info, 28)? as usize)
+        } else {
+            c_string(info, u32_at(info, 16)? as usize)
+        }?;
+        return Some(base + &suffix);
+    }
+    if flags & COMMON_NETWORK_RELATIVE_LINK != 0 {
+        let link = u32_at(info, 20)? as usize;
+        let share = c_string(info, link.checked_add(u32_at(info, link.checked_add(8)?)? as usize)?)?;
+        return Some(if suffix.is_empty() { share } else { format!("{}\\{}", share, suffix) });
+    }
+    None
+}
+
+// Target, arguments and working directory of a shortcut; None when the data is not a
+// shell link. Truncated links give what could be read before the end.
+pub fn parse_shell_link(data: &[u8]) -> Option<ShellLink> {
+    if u32_at(data, 0)? as usize != SHELL_LINK_HEADER_SIZE || data.get(4..20)? != SHELL_LINK_CLSID {
+        return None;
+    }
+    let flags = u32_at(data, 20)?;
+    let mut link = ShellLink::default();
+    let mut offset = SHELL_LINK_HEADER_SIZE;
+
+    if flags & HAS_LINK_TARGET_ID_LIST != 0 {
+        offset += 2 + u16_at(data, offset)? as usize;
+    }
+    if flags & HAS_LINK_INFO != 0 {
+        let size = u32_at(data, offset)? as usize;
+        link.target = data
+            .get(offset..offset.checked_add(size)?)
+            .and_then(link_info_target)
+            .filter(|target| !target.is_empty());
+        offset = offset.checked_add(size)?;
+    }
+
+    // StringData, each a character count and the characters, in this order
+    let mut relative_path = None;
+    for flag in [HAS_NAME, HAS_RELATIVE_PATH, HAS_WORKING_DIR, HAS_ARGUMENTS, HAS_ICON_LOCATION] {
+        if flags & flag == 0 {
+            continue;
+        }
+        let Some(count) = u16_at(data, offset).map(usize::from) else {
+            return Some(link);
+        };
+        offset += 2;
+        let length = if flags & IS_UNICODE != 0 { count * 2 } else { count };
+        let Some(bytes) = data.get(offset..offset + length) else {
+            return Some(link);
+        };
+        offset += length;
+        let value = if flags & IS_UNICODE != 0 {
+            let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+            String::from_utf16_lossy(&units)
+        } else {
+            String::from_utf8_lossy(bytes).into_owned()
+        };
+        match flag {
+            HAS_RELATIVE_PATH => relative_path = Some(value),
+            HAS_WORKING_DIR => link.working_directory = Some(value),
+            HAS_ARGUMENTS => link.arguments = Some(value),
+            _ => {}
+        }
+    }
+
+    // ExtraData blocks, until the terminal block smaller than a block header
+    while link.target.is_none() {
+        let Some(size) = u32_at(data, offset).map(|size| size as usize).filter(|size| *size >= 8) else {
+            break;
+        };
+        if u32_at(data, offset + 4) == Some(ENVIRONMENT_VARIABLE_BLOCK) {
+            let unicode = data.get(offset + 268..offset + 788).and_then(|block| c_wstring(block, 0));
+            let ansi = data.get(offset + 8..offset + 268).and_then(|block| c_string(block, 0));
+            link.target = unicode.filter(|target| !target.is_empty()).or(ansi).filter(|target| !target.is_empty());
+        }
+        offset = offset.saturating_add(size);
+    }
+    if link.target.is_none() {
+        link.target = relative_path;
+    }
+    Some(link)
+}
+
+// An XDG autostart entry (.desktop)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopEntry {
+    pub name: Option<String>,
+    pub exec: String,
+    pub enabled: bool,
+}
+
+// The [Desktop Entry] group of an autostart file; None without an Exec line
+pub fn parse_desktop_entry(content: &str) -> Option<DesktopEntry> {
+    let mut in_group = false;
+    let (mut name, mut exec) = (None, None);
+    let mut enabled = true;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_group = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_group || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "Name" => name = Some(value.to_string()),
+            "Exec" => exec = Some(value.to_string()),
+            "Hidden" if value.eq_ignore_ascii_case("true") => enabled = false,
+            "X-GNOME-Autostart-enabled" if value.eq_ignore_ascii_case("false") => enabled = false,
+            _ => {}
+        }
+    }
+    Some(DesktopEntry {
+        name,
+        exec: exec.filter(|exec| !exec.is_empty())?,
+        enabled,
+    })
+}
+
+// The parts of a Chrome / Edge / Chromium extension manifest.json the collector reports
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtensionManifest {
+    // May be a __MSG_name__ placeholder, see localized_message
+    pub name: String,
+    pub version: String,
+    pub default_locale: Option<String>,
+    // API permissions and host permissions, as requested
+    pub permissions: Vec<String>,
+}
+
+fn strings(value: Option<&Value>) -> impl Iterator<Item = String> + '_ {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+}
+
+pub fn parse_chromium_manifest(content: &str) -> Option<ExtensionManifest> {
+    let manifest: Value = serde_json::from_str(content.trim_start_matches('\u{feff}')).ok()?;
+    let field = |key: &str| manifest.get(key).and_then(Value::as_str).map(str::to_string);
+    let mut permissions: Vec<String> = strings(manifest.get("permissions"))
+        .chain(strings(manifest.get("host_permissions")))
+        .chain(strings(manifest.get("optional_permissions")))
+        .collect();
+    let mut seen = HashSet::new();
+    permissions.retain(|permission| seen.insert(permission.clone()));
+    Some(ExtensionManifest {
+        name: field("name")?,
+        version: field("version").unwrap_or_default(),
+        default_locale: field("default_locale"),
+        permissions,
+    })
+}
+
+// Resolves a __MSG_key__ placeholder from a _locales/<locale>/messages.json; keys are
+// case-insensitive
+pub fn localized_message(messages: &str, placeholder: &str) -> Option<String> {
+    let key = placeholder.strip_prefix("__MSG_")?.strip_suffix("__")?;
+    let messages: Value = serde_json::from_str(messages.trim_start_matches('\u{feff}')).ok()?;
+    messages
+        .as_object()?
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))?
+        .1
+        .get("message")?
+        .as_str()
+        .map(str::to_string)
+}
+
+// Ids of the extensions a Chromium profile's Preferences or Secure Preferences mark as
+// disabled: state 0 in older versions, any disable reason in newer ones
+pub fn chromium_disabled_extensions(preferences: &str) -> HashSet<String> {
+    let Ok(preferences) = serde_json::from_str::<Value>(preferences) else {
+        return HashSet::new();
+    };
+    let Some(settings) = preferences.pointer("/extensions/settings").and_then(Value::as_object) else {
+        return HashSet::new();
+    };
+    settings
+        .iter()
+        .filter(|(_, setting)| {
+            let reasons = setting.get("disable_reasons");
+            setting.get("state").and_then(Value::as_u64) == Some(0)
+                || reasons.and_then(Value::as_u64).is_some_and(|reasons| reasons != 0)
+                || reasons.and_then(Value::as_array).is_some_and(|reasons| !reasons.is_empty())
+        })
+        .map(|(id, _)| id.clone())
+        .collect()
+}
+
+// One add-on from a Firefox profile's extensions.json
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FirefoxAddon {
+    pub id: String,
+    pub name: Option<String>,
+    pub version: String,
+    pub active: bool,
+    pub path: Option<String>,
+    pub permissions: Vec<String>,
+}
+
+// Extensions the user or a program installed; built-in and system add-ons that ship with
+// Firefox are left out
+pub fn parse_firefox_extensions(content: &str) -> Vec<FirefoxAddon> {
+    let Ok(document) = serde_json::from_str::<Value>(content) else {
+        return Vec::new();
+    };
+    let Some(addons) = document.get("addons").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    addons
+        .iter()
+        .filter(|addon| addon.get("type").and_then(Value::as_str) == Some("extension"))
+        .filter(|addon| {
+            addon
+                .get("location")
+                .and_then(Value::as_str)
+                .is_none_or(|location| !location.starts_with("app-builtin") && !location.starts_with("app-system"))
+        })
+        .filter_map(|addon| {
+            let field = |key: &str| addon.get(key).and_then(Value::as_str).map(str::to_string);
+            let permissions = addon.get("userPermissions");
+            Some(FirefoxAddon {
+                id: field("id")?,
+                name: addon.pointer("/defaultLocale/name").and_then(Value::as_str).map(str::to_string),
+                version: field("version").unwrap_or_default(),
+                active: addon.get("active").and_then(Value::as_bool).unwrap_or(false),
+                path: field("path"),
+                permissions: strings(permissions.and_then(|permissions| permissions.get("permissions")))
+                    .chain(strings(permissions.and_then(|permissions| permissions.get("origins"))))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+// A permanent WMI event consumer, with the filters that trigger it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WmiConsumer {
+    pub name: String,
+    // CommandLineEventConsumer, ActiveScriptEventConsumer, ...
+    pub class: String,
+    pub command: String,
+    // "<filter name>: <query>" of every bound filter
+    pub filters: Vec<String>,
+}
+
+// ConvertTo-Json writes a single object where a one-element list was meant
+fn items(value: Option<&Value>) -> Vec<&Value> {
+    match value {
+        Some(Value::Array(items)) => items.iter().collect(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(item) => vec![item],
+    }
+}
+
+// The {"filters", "consumers", "bindings"} document written by the subscription query
+pub fn parse_wmi_subscriptions(content: &str) -> Vec<WmiConsumer> {
+    let Ok(document) = serde_json::from_str::<Value>(content.trim_start_matches('\u{feff}')) else {
+        return Vec::new();
+    };
+    let text = |item: &Value, key: &str| {
+        item.get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    let filters: Vec<(String, String)> = items(document.get("filters"))
+        .into_iter()
+        .filter_map(|filter| Some((text(filter, "Name")?, text(filter, "Query").unwrap_or_default())))
+        .collect();
+    let bindings: Vec<(String, String)> = items(document.get("bindings"))
+        .into_iter()
+        .filter_map(|binding| Some((text(binding, "Consumer")?, text(binding, "Filter")?)))
+        .collect();
+
+    items(document.get("consumers"))
+        .into_iter()
+        .filter_map(|consumer| {
+            let name = text(consumer, "Name")?;
+            // What each consumer class runs
+            let command = ["CommandLineTemplate", "ExecutablePath", "ScriptFileName", "ScriptText"]
+                .iter()
+                .find_map(|key| text(consumer, key))
+                .unwrap_or_default();
+            let filters = bindings
+                .iter()
+                .filter(|(bound, _)| *bound == name)
+                .map(|(_, filter)| match filters.iter().find(|(known, _)| known == filter) {
+                    Some((_, query)) => format!("{}: {}", filter, query),
+                    None => filter.clone(),
+                })
+                .collect();
+            Some(WmiConsumer {
+                class: text(consumer, "Class").unwrap_or_default(),
+                name,
+                command,
+                filters,
+            })
+        })
+        .collect()
+}
+
+// A COM server registration: InprocServer32 or LocalServer32 below CLSID\{guid}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComServer {
+    pub clsid: String,
+    pub server: String,
+    pub path: String,
+}
+
+const COM_SERVER_KEYS: [&str; 2] = ["InprocServer32", "LocalServer32"];
+
+// The server registrations in `reg query <...>\CLSID /s` output. The default value's name
+// is localized, "(Default)" in English, but always in parentheses.
+pub fn parse_com_servers(output: &str) -> Vec<ComServer> {
+    let mut servers = Vec::new();
+    let mut current: Option<(String, String)> = None;
+    for line in output.lines() {
+        if line.starts_with("HKEY_") {
+            let mut components = line.trim().rsplit('\\');
+            let server = components.next().unwrap_or_default();
+            let clsid = components.next().unwrap_or_default();
+            current = COM_SERVER_KEYS
+                .iter()
+                .find(|key| key.eq_ignore_ascii_case(server))
+                .filter(|_| clsid.starts_with('{') && clsid.ends_with('}'))
+                .map(|key| (clsid.to_uppercase(), key.to_string()));
+            continue;
+        }
+        let Some((clsid, server)) = current.as_ref() else {
+            continue;
+        };
+        let line = line.trim();
+        let Some((name, data)) = ["    REG_SZ", "    REG_EXPAND_SZ"].iter().find_map(|kind| line.split_once(kind)) else {
+            continue;
+        };
+        let (name, data) = (name.trim(), data.trim());
+        if name.starts_with('(') && name.ends_with(')') && !data.is_empty() {
+            servers.push(ComServer {
+                clsid: clsid.clone(),
+                server: server.clone(),
+                path: data.to_string(),
+            });
+        }
+    }
+    servers
+}
+
+// Subkey names of a `reg query <key>` listing (without /s), upper-cased
+pub fn parse_subkeys(output: &str) -> HashSet<String> {
+    output
+        .lines()
+        .filter(|line| line.starts_with("HKEY_"))
+        .filter_map(|line| line.trim().rsplit('\\').next())
+        .map(str::to_uppercase)
+        .collect()
+}
@@ -0,0 +1,67 @@
+use crate::features::persistence::parsers::{parse_com_servers, parse_subkeys, parse_wmi_subscriptions, ComServer, WmiConsumer};
+use crate::shared::command;
+use log::{debug, error};
+use std::collections::HashSet;
+
+// Every permanent subscription in root/subscription as one JSON document. Bindings refer to
+// their filter and consumer by reference, which is flattened to the name here.
+const WMI_SUBSCRIPTIONS: &str = "\
+$filters = @(Get-CimInstance -Namespace root/subscription -ClassName __EventFilter | Select-Object Name, Query); \
+$consumers = @(Get-CimInstance -Namespace root/subscription -ClassName __EventConsumer | ForEach-Object { \
+[pscustomobject]@{ Name = $_.Name; Class = $_.CimClass.CimClassName; CommandLineTemplate = $_.CommandLineTemplate; \
+ExecutablePath = $_.ExecutablePath; ScriptFileName = $_.ScriptFileName; ScriptText = $_.ScriptText } }); \
+$bindings = @(Get-CimInstance -Namespace root/subscription -ClassName __FilterToConsumerBinding | ForEach-Object { \
+[pscustomobject]@{ Filter = [string]$_.Filter.Name; Consumer = [string]$_.Consumer.Name } }); \
+@{ filters = $filters; consumers = $consumers; bindings = $bindings } | ConvertTo-Json -Compress -Depth 3";
+
+pub fn wmi_consumers() -> Vec<WmiConsumer> {
+    match command::run("powershell", ["-NoProfile", "-NonInteractive", "-Command", WMI_SUBSCRIPTIONS]) {
+        Ok(output) if output.success => parse_wmi_subscriptions(&output.stdout),
+        Ok(output) => {
+            error!("Failed to query WMI event subscriptions: {}", output.stderr.trim());
+            Vec::new()
+        }
+        Err(e) => {
+            error!("Failed to execute powershell command: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+// SIDs of the user hives loaded now, those of users logged on
+pub fn user_hives() -> Vec<String> {
+    let Ok(output) = command::run("reg", ["query", "HKU"]) else {
+        return Vec::new();
+    };
+    output
+        .stdout
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix(r"HKEY_USERS\"))
+        .filter(|sid| sid.starts_with("S-1-5-21-") && !sid.ends_with("_Classes"))
+        .map(str::to_string)
+        .collect()
+}
+
+// COM servers registered below a key, with every subkey read
+pub fn com_servers(root: &str) -> Vec<ComServer> {
+    match command::run("reg", ["query", root, "/s"]) {
+        Ok(output) if output.success => parse_com_servers(&output.stdout),
+        Ok(_) => Vec::new(),
+        Err(e) => {
+            debug!("Failed to query {}: {}", root, e);
+            Vec::new()
+        }
+    }
+}
+
+// CLSIDs registered machine-wide, upper-cased
+pub fn machine_classes() -> HashSet<String> {
+    match command::run("reg", ["query", r"HKLM\SOFTWARE\Classes\CLSID"]) {
+        Ok(output) if output.success => parse_subkeys(&output.stdout),
+        Ok(_) => HashSet::new(),
+        Err(e) => {
+            debug!("Failed to query the machine-wide COM classes: {}", e);
+            HashSet::new()
+        }
+    }
+}
@@ -0,0 +1,391 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector};
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::matchers::ExtensionSet;
+use crate::shared::process_resolver::ProcessResolver;
+use crate::features::print_spooler::models::{PrintSpoolerEvent, PrintSpoolerEventBuilder, SpoolerAction};
+use crate::utils::load_config_section;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrintSpoolerConfig {
+    pub enabled: bool,
+    // Scanned recursively for new or changed driver files
+    pub driver_directories: Vec<String>,
+    pub driver_extensions: Vec<String>,
+    // Driver and port monitor registrations; a new "Driver" value below them is reported
+    pub driver_registry_key: String,
+    pub monitor_registry_key: String,
+    pub point_and_print_key: String,
+    pub spooler_process: String,
+    // Children of the spooler (case-insensitive executable names) that are part of normal printing
+    pub allowed_children: Vec<String>,
+    // A driver change and a spooler child process this close together are reported as correlated
+    pub correlation_window_secs: u64,
+}
+
+impl Default for PrintSpoolerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            driver_directories: vec![String::from(r"C:\Windows\System32\spool\drivers")],
+            driver_extensions: vec![String::from("dll")],
+            driver_registry_key: String::from(r"HKLM\SYSTEM\CurrentControlSet\Control\Print\Environments"),
+            monitor_registry_key: String::from(r"HKLM\SYSTEM\CurrentControlSet\Control\Print\Monitors"),
+            point_and_print_key: String::from(
+                r"HKLM\SOFTWARE\Policies\Microsoft\Windows NT\Printers\PointAndPrint",
+            ),
+            spooler_process: String::from("spoolsv.exe"),
+            allowed_children: ["splwow64.exe", "printisolationhost.exe", "conhost.exe", "werfault.exe"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            correlation_window_secs: 300,
+        }
+    }
+}
+
+impl PrintSpoolerConfig {
+    pub fn load() -> Self {
+        load_config_section("print_spooler")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RegistryEntry {
+    pub key: String,
+    pub name: String,
+    pub data: String,
+}
+
+#[cfg(windows)]
+use crate::features::print_spooler::win32::registry_entries;
+
+#[cfg(not(windows))]
+fn registry_entries(_root: &str) -> Vec<RegistryEntry> {
+    Vec::new()
+}
+
+// Point and Print policy values that let a standard user install a driver silently, with
+// the value each must have to be insecure
+const INSECURE_POINT_AND_PRINT: [(&str, &str); 3] = [
+    ("NoWarningNoElevationOnInstall", "1"),
+    ("UpdatePromptSettings", "2"),
+    ("RestrictDriverInstallationToAdministrators", "0"),
+];
+
+// Print subsystem abuse in the style of PrintNightmare: driver DLLs dropped into the spooler
+// driver store, new driver and port monitor registrations, Point and Print policy weakened,
+// and the spooler starting processes it normally never starts. The spooler loading an
+// attacker DLL and then spawning a child is the exploitation pattern, so both halves seen
+// within the correlation window are raised to critical.
+pub struct PrintSpoolerCollector {
+    config: PrintSpoolerConfig,
+    // config.driver_extensions, compiled once
+    driver_extensions: ExtensionSet,
+    sys: System,
+    driver_files: HashMap<PathBuf, SystemTime>,
+    registrations: HashSet<RegistryEntry>,
+    insecure_policy: Vec<String>,
+    // (pid, start time) of spooler children already reported
+    reported_children: HashSet<(u32, u64)>,
+    last_driver_change: Option<DateTime<Utc>>,
+    last_spooler_child: Option<DateTime<Utc>>,
+    resolver: Arc<ProcessResolver>,
+    host: Arc<HostIdentityService>,
+}
+
+impl PrintSpoolerCollector {
+    pub fn new() -> Self {
+        Self::with_config(PrintSpoolerConfig::load())
+    }
+
+    pub fn with_config(config: PrintSpoolerConfig) -> Self {
+        let mut collector = Self {
+            driver_extensions: ExtensionSet::new(&config.driver_extensions),
+            config,
+            sys: System::new(),
+            driver_files: HashMap::new(),
+            registrations: HashSet::new(),
+            insecure_policy: Vec::new(),
+            reported_children: HashSet::new(),
+            last_driver_change: None,
+            last_spooler_child: None,
+            resolver: ProcessResolver::global(),
+            host: HostIdentityService::global(),
+        };
+        // Drivers installed before the agent started are the baseline
+        collector.driver_files = collector.scan_driver_files();
+        collector.registrations = collector.scan_registrations();
+        collector
+    }
+
+    fn event(&self, action: SpoolerAction) -> PrintSpoolerEventBuilder {
+        PrintSpoolerEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("print_spooler"))
+            .action(action)
+    }
+
+    fn is_driver_file(&self, path: &Path) -> bool {
+        self.driver_extensions.contains(path)
+    }
+
+    fn scan_directory(&self, directory: &Path, files: &mut HashMap<PathBuf, SystemTime>) {
+        let Ok(entries) = fs::read_dir(directory) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let path = entry.path();
+            if metadata.is_dir() {
+                self.scan_directory(&path, files);
+            } else if self.is_driver_file(&path) {
+                files.insert(path, metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+            }
+        }
+    }
+
+    fn scan_driver_files(&self) -> HashMap<PathBuf, SystemTime> {
+        let mut files = HashMap::new();
+        for directory in &self.config.driver_directories {
+            self.scan_directory(Path::new(directory), &mut files);
+        }
+        files
+    }
+
+    fn scan_registrations(&self) -> HashSet<RegistryEntry> {
+        [&self.config.driver_registry_key, &self.config.monitor_registry_key]
+            .into_iter()
+            .flat_map(|root| registry_entries(root))
+            .filter(|entry| entry.name.eq_ignore_ascii_case("Driver"))
+            .collect()
+    }
+
+    fn driver_file_events(&mut self) -> Result<Vec<PrintSpoolerEvent>, CollectionError> {
+        let current = self.scan_driver_files();
+        let mut events = Vec::new();
+        for (path, modified) in &current {
+            let action = match self.driver_files.get(path) {
+                None => SpoolerAction::DriverFileAdded,
+                Some(previous) if previous != modified => SpoolerAction::DriverFileModified,
+                Some(_) => continue,
+            };
+            let signature = self.resolver.executable_signature(path);
+            warn!("Spooler driver file {:?}: {} ({:?})", action, path.display(), signature);
+            events.push(
+                self.event(action)
+                    .file_path(path.to_string_lossy().into_owned())
+                    .sha256(self.resolver.executable_sha256(path))
+                    .signature_status(signature)
+                    .build()
+                    .map_err(CollectionError::Parse)?,
+            );
+        }
+        self.driver_files = current;
+        Ok(events)
+    }
+
+    fn registration_events(&mut self) -> Result<Vec<PrintSpoolerEvent>, CollectionError> {
+        let current = self.scan_registrations();
+        let mut events = Vec::new();
+        for entry in current.difference(&self.registrations) {
+            let action = if entry.key.to_lowercase().contains(r"\print\monitors\") {
+                SpoolerAction::PortMonitorRegistered
+            } else {
+                SpoolerAction::DriverRegistered
+            };
+            warn!("{:?} at {}: {}", action, entry.key, entry.data);
+            events.push(
+                self.event(action)
+                    .registry_key(entry.key.clone())
+                    .registry_value(entry.name.clone())
+                    .registry_data(entry.data.clone())
+                    .build()
+                    .map_err(CollectionError::Parse)?,
+            );
+        }
+        self.registrations = current;
+        Ok(events)
+    }
+
+    // Reported whenever the set of insecure values changes to a non-empty one, so also once
+    // at startup when the host is already exposed
+    fn point_and_print_events(&mut self) -> Result<Vec<PrintSpoolerEvent>, CollectionError> {
+        let entries = registry_entries(&self.config.point_and_print_key);
+        let insecure: Vec<&RegistryEntry> = entries
+            .iter()
+            .filter(|entry| {
+                INSECURE_POINT_AND_PRINT
+                    .iter()
+                    .any(|(name, value)| entry.name.eq_ignore_ascii_case(name) && entry.data == *value)
+            })
+            .collect();
+        let mut names: Vec<String> = insecure.iter().map(|entry| entry.name.clone()).collect();
+        names.sort();
+        if names == self.insecure_policy {
+            return Ok(Vec::new());
+        }
+        self.insecure_policy = names;
+
+        let mut events = Vec::new();
+        for entry in insecure {
+            warn!("Insecure Point and Print policy: {} = {}", entry.name, entry.data);
+            events.push(
+                self.event(SpoolerAction::InsecurePointAndPrint)
+                    .registry_key(entry.key.clone())
+                    .registry_value(entry.name.clone())
+                    .registry_data(entry.data.clone())
+                    .build()
+                    .map_err(CollectionError::Parse)?,
+            );
+        }
+        Ok(events)
+    }
+
+    fn spooler_child_events(&mut self) -> Result<Vec<PrintSpoolerEvent>, CollectionError> {
+        self.sys.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing()
+                .with_exe(UpdateKind::OnlyIfNotSet)
+                .with_cmd(UpdateKind::OnlyIfNotSet),
+        );
+        let spooler = self.config.spooler_process.to_lowercase();
+        let spoolers: HashSet<Pid> = self
+            .sys
+            .processes()
+            .iter()
+            .filter(|(_, process)| process.name().to_string_lossy().to_lowercase() == spooler)
+            .map(|(pid, _)| *pid)
+            .collect();
+
+        let mut events = Vec::new();
+        let mut alive = HashSet::new();
+        for (pid, process) in self.sys.processes() {
+            if !process.parent().is_some_and(|parent| spoolers.contains(&parent)) {
+                continue;
+            }
+            let key = (pid.as_u32(), process.start_time());
+            alive.insert(key);
+            let name = process.name().to_string_lossy().into_owned();
+            if self.reported_children.contains(&key)
+                || self.config.allowed_children.iter().any(|allowed| allowed.eq_ignore_ascii_case(&name))
+            {
+                continue;
+            }
+
+            let command_line = process
+                .cmd()
+                .iter()
+                .map(|argument| argument.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" ");
+            warn!("Print spooler started {} (pid {}): {}", name, pid, command_line);
+            let mut builder = self
+                .event(SpoolerAction::SpoolerChildProcess)
+                .process_id(pid.as_u32())
+                .process_name(name)
+                .command_line(command_line);
+            if let Some(exe) = process.exe() {
+                builder = builder
+                    .file_path(exe.to_string_lossy().into_owned())
+                    .sha256(self.resolver.executable_sha256(exe))
+                    .signature_status(self.resolver.executable_signature(exe));
+            }
+            events.push(builder.build().map_err(CollectionError::Parse)?);
+        }
+        self.reported_children.retain(|key| alive.contains(key));
+        self.reported_children.extend(alive);
+        Ok(events)
+    }
+
+    fn collect_events(&mut self) -> Result<Vec<PrintSpoolerEvent>, CollectionError> {
+        let mut driver_events = self.driver_file_events()?;
+        driver_events.extend(self.registration_events()?);
+        let mut child_events = self.spooler_child_events()?;
+
+        let now = Utc::now();
+        if !driver_events.is_empty() {
+            self.last_driver_change = Some(now);
+        }
+        if !child_events.is_empty() {
+            self.last_spooler_child = Some(now);
+        }
+        let window = chrono::Duration::seconds(self.config.correlation_window_secs as i64);
+        let recent = |seen: Option<DateTime<Utc>>| seen.is_some_and(|seen| now - seen <= window);
+        if recent(self.last_driver_change) && recent(self.last_spooler_child) {
+            warn!(
+                "Spooler driver change and spooler child process within {}s, possible print spooler exploitation",
+                window.num_seconds()
+            );
+            for event in driver_events.iter_mut().chain(child_events.iter_mut()) {
+                event.correlated = true;
+            }
+        }
+
+        let mut events = driver_events;
+        events.extend(child_events);
+        events.extend(self.point_and_print_events()?);
+        info!("Collected {} print spooler events", events.len());
+        Ok(events)
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if !cfg!(target_os = "windows") {
+            return Err(CollectionError::SystemApi(
+                "Print spooler monitoring is only supported on Windows".to_string()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DataCollector<Vec<PrintSpoolerEvent>> for PrintSpoolerCollector {
+    fn collect(&mut self) -> Result<Vec<PrintSpoolerEvent>, CollectionError> {
+        self.collect_events()
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<PrintSpoolerEvent>> for PrintSpoolerCollector {
+    async fn collect(&mut self) -> Result<Vec<PrintSpoolerEvent>, CollectionError> {
+        DataCollector::collect(self)
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+impl Default for PrintSpoolerCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
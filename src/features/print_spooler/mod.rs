@@ -0,0 +1,7 @@
+mod collector;
+mod models;
+#[cfg(windows)]
+mod win32;
+
+pub use collector::{PrintSpoolerCollector, PrintSpoolerConfig};
+pub use models::{PrintSpoolerEvent, PrintSpoolerEventBuilder, SpoolerAction};
@@ -0,0 +1,230 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::signature::SignatureStatus;
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpoolerAction {
+    // A DLL appeared in or changed under the spooler driver directory
+    DriverFileAdded,
+    DriverFileModified,
+    // A printer driver or port monitor was registered
+    DriverRegistered,
+    PortMonitorRegistered,
+    // Point and Print policy that lets non-admins install drivers without a prompt
+    InsecurePointAndPrint,
+    // spoolsv.exe started a process outside the expected helpers
+    SpoolerChildProcess,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintSpoolerEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub action: SpoolerAction,
+    pub file_path: Option<String>,
+    pub sha256: Option<String>,
+    pub signature_status: Option<SignatureStatus>,
+    pub registry_key: Option<String>,
+    pub registry_value: Option<String>,
+    pub registry_data: Option<String>,
+    pub process_id: Option<u32>,
+    pub process_name: Option<String>,
+    pub command_line: Option<String>,
+    // A driver change and a spooler child process close together, the PrintNightmare pattern
+    pub correlated: bool,
+}
+
+impl Event for PrintSpoolerEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.action {
+            SpoolerAction::DriverFileAdded => "spooler_driver_file_added",
+            SpoolerAction::DriverFileModified => "spooler_driver_file_modified",
+            SpoolerAction::DriverRegistered => "spooler_driver_registered",
+            SpoolerAction::PortMonitorRegistered => "spooler_port_monitor_registered",
+            SpoolerAction::InsecurePointAndPrint => "spooler_insecure_point_and_print",
+            SpoolerAction::SpoolerChildProcess => "spooler_child_process",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        if self.correlated {
+            return Severity::Critical;
+        }
+        match self.action {
+            SpoolerAction::DriverFileAdded | SpoolerAction::DriverFileModified => {
+                if self.signature_status == Some(SignatureStatus::Signed) {
+                    Severity::Medium
+                } else {
+                    Severity::High
+                }
+            }
+            SpoolerAction::PortMonitorRegistered | SpoolerAction::SpoolerChildProcess => Severity::High,
+            SpoolerAction::DriverRegistered | SpoolerAction::InsecurePointAndPrint => Severity::Medium,
+        }
+    }
+}
+
+impl Identifiable for PrintSpoolerEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for PrintSpoolerEvent {
+    fn validate(&self) -> Result<(), String> {
+        match self.action {
+            SpoolerAction::DriverFileAdded | SpoolerAction::DriverFileModified if self.file_path.is_none() => {
+                Err("Driver file events require a file path".to_string())
+            }
+            SpoolerAction::SpoolerChildProcess if self.process_id.is_none() => {
+                Err("Spooler child process events require a process id".to_string())
+            }
+            SpoolerAction::DriverRegistered | SpoolerAction::PortMonitorRegistered | SpoolerAction::InsecurePointAndPrint
+                if self.registry_key.is_none() =>
+            {
+                Err("Registry events require a registry key".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PrintSpoolerEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    action: Option<SpoolerAction>,
+    file_path: Option<String>,
+    sha256: Option<String>,
+    signature_status: Option<SignatureStatus>,
+    registry_key: Option<String>,
+    registry_value: Option<String>,
+    registry_data: Option<String>,
+    process_id: Option<u32>,
+    process_name: Option<String>,
+    command_line: Option<String>,
+    correlated: bool,
+}
+
+impl PrintSpoolerEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn action(mut self, action: SpoolerAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn file_path(mut self, file_path: String) -> Self {
+        self.file_path = Some(file_path);
+        self
+    }
+
+    pub fn sha256(mut self, sha256: Option<String>) -> Self {
+        self.sha256 = sha256;
+        self
+    }
+
+    pub fn signature_status(mut self, signature_status: Option<SignatureStatus>) -> Self {
+        self.signature_status = signature_status;
+        self
+    }
+
+    pub fn registry_key(mut self, registry_key: String) -> Self {
+        self.registry_key = Some(registry_key);
+        self
+    }
+
+    pub fn registry_value(mut self, registry_value: String) -> Self {
+        self.registry_value = Some(registry_value);
+        self
+    }
+
+    pub fn registry_data(mut self, registry_data: String) -> Self {
+        self.registry_data = Some(registry_data);
+        self
+    }
+
+    pub fn process_id(mut self, process_id: u32) -> Self {
+        self.process_id = Some(process_id);
+        self
+    }
+
+    pub fn process_name(mut self, process_name: String) -> Self {
+        self.process_name = Some(process_name);
+        self
+    }
+
+    pub fn command_line(mut self, command_line: String) -> Self {
+        self.command_line = Some(command_line);
+        self
+    }
+
+    pub fn correlated(mut self, correlated: bool) -> Self {
+        self.correlated = correlated;
+        self
+    }
+
+    pub fn build(self) -> Result<PrintSpoolerEvent, String> {
+        let event = PrintSpoolerEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            action: self.action.ok_or("action is required")?,
+            file_path: self.file_path,
+            sha256: self.sha256,
+            signature_status: self.signature_status,
+            registry_key: self.registry_key,
+            registry_value: self.registry_value,
+            registry_data: self.registry_data,
+            process_id: self.process_id,
+            process_name: self.process_name,
+            command_line: self.command_line,
+            correlated: self.correlated,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
@@ -0,0 +1,50 @@
+use crate::features::print_spooler::collector::RegistryEntry;
+use crate::shared::command;
+use log::debug;
+
+const VALUE_TYPES: [&str; 7] = [
+    "REG_SZ", "REG_EXPAND_SZ", "REG_MULTI_SZ", "REG_DWORD", "REG_QWORD", "REG_BINARY", "REG_NONE",
+];
+
+// Every value below a key, recursively, as reported by reg.exe. An absent key is no entries.
+pub fn registry_entries(root: &str) -> Vec<RegistryEntry> {
+    let output = match command::run("reg", ["query", root, "/s"]) {
+        Ok(output) if output.success => output,
+        Ok(_) => return Vec::new(),
+        Err(e) => {
+            debug!("Failed to query {}: {}", root, e);
+            return Vec::new();
+        }
+    };
+
+    let mut entries = Vec::new();
+    let mut key = String::new();
+    for line in output.stdout.lines() {
+        if line.starts_with("HKEY_") {
+            key = line.trim().to_string();
+            continue;
+        }
+        // "    <name>    <type>    <data>", where the name may contain spaces
+        let line = line.trim();
+        let Some((name, kind, data)) = VALUE_TYPES.iter().find_map(|kind| {
+            let separator = format!("    {}", kind);
+            line.split_once(&separator).map(|(name, data)| (name, *kind, data))
+        }) else {
+            continue;
+        };
+        let data = data.trim();
+        let data = match kind {
+            // DWORDs come out as 0x-prefixed hex
+            "REG_DWORD" | "REG_QWORD" => u64::from_str_radix(data.trim_start_matches("0x"), 16)
+                .map(|value| value.to_string())
+                .unwrap_or_else(|_| data.to_string()),
+            _ => data.to_string(),
+        };
+        entries.push(RegistryEntry {
+            key: key.clone(),
+            name: name.trim().to_string(),
+            data,
+        });
+    }
+    entries
+}
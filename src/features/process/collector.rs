@@ -1,45 +1,235 @@
 use crate::shared::traits::{AsyncDataCollector, DataCollector};
 use crate::shared::error::CollectionError;
-use crate::features::process::models::{ProcessInformation, ProcessInformationBuilder};
-use log::info;
-use sysinfo::System;
-use chrono::Utc;
+use crate::shared::user_resolver::UserResolver;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::process_resolver::ProcessResolver;
+use crate::features::process::models::{
+    ProcessInformation, ProcessInformationBuilder, ProcessLifecycleAction, ProcessLifecycleEvent,
+    ProcessLifecycleEventBuilder,
+};
+use crate::utils::load_config_section;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use sysinfo::{Process, System};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
 use uuid::Uuid;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessReportMode {
+    // Full process table every collection
+    Inventory,
+    // Only processes started or stopped since the previous collection
+    Delta,
+    Both,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessConfig {
+    pub mode: ProcessReportMode,
+    // In delta mode, still send the full table every N collections (0 = never)
+    pub inventory_every: u32,
+    // SHA-256 of each executable, cached by path and modification time
+    pub hash_executables: bool,
+    // Authenticode verification of each executable (Windows only), cached the same way
+    pub verify_signatures: bool,
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        Self {
+            mode: ProcessReportMode::Inventory,
+            inventory_every: 60,
+            hash_executables: false,
+            verify_signatures: false,
+        }
+    }
+}
+
+impl ProcessConfig {
+    pub fn load() -> Self {
+        load_config_section("process")
+    }
+}
+
+// What is remembered about a running process so its exit can still be described
+struct TrackedProcess {
+    parent_pid: Option<u32>,
+    name: String,
+    executable: Option<String>,
+    command_line: String,
+    user: String,
+}
+
+// PIDs are reused, so a process is identified by its PID together with its start time
+type ProcessKey = (u32, u64);
+
 pub struct ProcessCollector {
+    config: ProcessConfig,
     sys: System,
-    hostname: String,
+    users: Arc<UserResolver>,
+    resolver: Arc<ProcessResolver>,
+    host: Arc<HostIdentityService>,
+    table: HashMap<ProcessKey, TrackedProcess>,
+    lifecycle_events: Vec<ProcessLifecycleEvent>,
+    collections: u64,
 }
 
 impl ProcessCollector {
     pub fn new() -> Self {
+        Self::with_config(ProcessConfig::load())
+    }
+
+    pub fn with_config(config: ProcessConfig) -> Self {
         let mut sys = System::new();
         sys.refresh_all();
-        let hostname = whoami::hostname();
-        Self { sys, hostname }
+        let mut collector = Self {
+            config,
+            sys,
+            users: UserResolver::global(),
+            resolver: ProcessResolver::global(),
+            host: HostIdentityService::global(),
+            table: HashMap::new(),
+            lifecycle_events: Vec::new(),
+            collections: 0,
+        };
+        // Whatever is running at startup is the baseline, not a burst of starts
+        collector.table = collector.current_table();
+        collector
+    }
+
+    // Started / stopped events found by the last collections, cleared on read
+    pub fn take_lifecycle_events(&mut self) -> Vec<ProcessLifecycleEvent> {
+        std::mem::take(&mut self.lifecycle_events)
+    }
+
+    fn user_of(&self, process: &Process) -> String {
+        process
+            .user_id()
+            .map(|uid| self.users.display_id(&uid.to_string()))
+            .unwrap_or_else(|| String::from("unknown"))
+    }
+
+    fn current_table(&self) -> HashMap<ProcessKey, TrackedProcess> {
+        self.sys
+            .processes()
+            .iter()
+            .map(|(pid, process)| {
+                let command_line = process
+                    .cmd()
+                    .iter()
+                    .map(|argument| argument.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let tracked = TrackedProcess {
+                    parent_pid: process.parent().map(|parent| parent.as_u32()),
+                    name: process.name().to_string_lossy().into_owned(),
+                    executable: process.exe().map(|path| path.to_string_lossy().into_owned()),
+                    command_line,
+                    user: self.user_of(process),
+                };
+                ((pid.as_u32(), process.start_time()), tracked)
+            })
+            .collect()
+    }
+
+    fn lifecycle_event(
+        &self,
+        action: ProcessLifecycleAction,
+        (pid, start_time): ProcessKey,
+        process: &TrackedProcess,
+    ) -> Result<ProcessLifecycleEvent, CollectionError> {
+        let now = Utc::now();
+        let started = DateTime::from_timestamp(start_time as i64, 0).unwrap_or(now);
+        let mut builder = ProcessLifecycleEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(now)
+            .source(self.host.name())
+            .category(String::from("process"))
+            .action(action)
+            .pid(pid)
+            .parent_pid(process.parent_pid)
+            .name(process.name.clone())
+            .executable(process.executable.clone())
+            .command_line(process.command_line.clone())
+            .user(process.user.clone())
+            .start_time(started);
+        if action == ProcessLifecycleAction::Stopped {
+            builder = builder.run_time_secs((now - started).num_seconds().max(0) as u64);
+        }
+        builder.build().map_err(CollectionError::Parse)
+    }
+
+    // Diffs the freshly refreshed process table against the previous one. Processes that
+    // start and exit between two collections are never seen here, exec_trace covers those.
+    fn track_lifecycle(&mut self) -> Result<(), CollectionError> {
+        let current = self.current_table();
+        let mut events = Vec::new();
+        for (key, process) in &current {
+            if !self.table.contains_key(key) {
+                events.push(self.lifecycle_event(ProcessLifecycleAction::Started, *key, process)?);
+            }
+        }
+        for (key, process) in &self.table {
+            if !current.contains_key(key) {
+                events.push(self.lifecycle_event(ProcessLifecycleAction::Stopped, *key, process)?);
+            }
+        }
+        debug!("{} processes started or stopped since the last collection", events.len());
+
+        self.table = current;
+        self.lifecycle_events.extend(events);
+        Ok(())
+    }
+
+    fn inventory_due(&self) -> bool {
+        match self.config.mode {
+            ProcessReportMode::Inventory | ProcessReportMode::Both => true,
+            ProcessReportMode::Delta => {
+                self.config.inventory_every > 0 && self.collections % self.config.inventory_every as u64 == 0
+            }
+        }
     }
 
     fn collect_processes(&mut self) -> Result<Vec<ProcessInformation>, CollectionError> {
         self.sys.refresh_all();
-        
+        if self.config.mode != ProcessReportMode::Inventory {
+            self.track_lifecycle()?;
+        }
+        let inventory_due = self.inventory_due();
+        self.collections += 1;
+        if !inventory_due {
+            return Ok(Vec::new());
+        }
+
         let processes = self
             .sys
             .processes()
             .iter()
             .map(|(pid, process)| {
+                let sha256 = process
+                    .exe()
+                    .filter(|_| self.config.hash_executables)
+                    .and_then(|path| self.resolver.executable_sha256(path));
+                let signature_status = process
+                    .exe()
+                    .filter(|_| self.config.verify_signatures)
+                    .and_then(|path| self.resolver.executable_signature(path));
+
                 ProcessInformationBuilder::new()
                     .id(Uuid::new_v4().to_string())
                     .timestamp(Utc::now())
-                    .source(self.hostname.clone())
+                    .source(self.host.name())
                     .category(String::from("process"))
                     .pid(pid.as_u32())
                     .name(process.name().to_string_lossy().into_owned())
                     .cpu_usage(process.cpu_usage())
                     .memory_usage(process.memory())
                     .status(format!("{:?}", process.status()))
-                    .user(process.user_id()
-                        .map(|uid| uid.to_string())
-                        .unwrap_or_else(|| String::from("unknown")))
+                    .user(self.user_of(process))
                     .command(process.exe()
                         .map(|p| p.to_string_lossy().into_owned())
                         .unwrap_or_else(|| process.name().to_string_lossy().into_owned()))
@@ -50,6 +240,8 @@ impl ProcessCollector {
                         // platform-specific thread counting in the future
                         1
                     })
+                    .sha256(sha256)
+                    .signature_status(signature_status)
                     .build()
                     .map_err(|e| CollectionError::Parse(e))
             })
@@ -1,5 +1,8 @@
 pub mod models;
 pub mod collector;
 
-pub use models::{ProcessInformation, ProcessInformationBuilder};
-pub use collector::ProcessCollector;
+pub use models::{
+    ProcessInformation, ProcessInformationBuilder, ProcessLifecycleAction, ProcessLifecycleEvent,
+    ProcessLifecycleEventBuilder,
+};
+pub use collector::{ProcessCollector, ProcessConfig, ProcessReportMode};
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use crate::shared::signature::SignatureStatus;
 use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
 use uuid::Uuid;
 
@@ -17,6 +18,9 @@ pub struct ProcessInformation {
     pub user: String,
     pub command: String,
     pub threads: u32,
+    pub sha256: Option<String>,
+    // Authenticode status of the executable, Windows only
+    pub signature_status: Option<SignatureStatus>,
 }
 
 impl Event for ProcessInformation {
@@ -98,6 +102,8 @@ pub struct ProcessInformationBuilder {
     user: Option<String>,
     command: Option<String>,
     threads: Option<u32>,
+    sha256: Option<String>,
+    signature_status: Option<SignatureStatus>,
 }
 
 impl ProcessInformationBuilder {
@@ -165,6 +171,16 @@ impl ProcessInformationBuilder {
         self
     }
 
+    pub fn sha256(mut self, sha256: Option<String>) -> Self {
+        self.sha256 = sha256;
+        self
+    }
+
+    pub fn signature_status(mut self, signature_status: Option<SignatureStatus>) -> Self {
+        self.signature_status = signature_status;
+        self
+    }
+
     pub fn build(self) -> Result<ProcessInformation, String> {
         let process = ProcessInformation {
             id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
@@ -179,9 +195,189 @@ impl ProcessInformationBuilder {
             user: self.user.ok_or("user is required")?,
             command: self.command.ok_or("command is required")?,
             threads: self.threads.ok_or("threads is required")?,
+            sha256: self.sha256,
+            signature_status: self.signature_status,
         };
 
         process.validate()?;
         Ok(process)
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessLifecycleAction {
+    Started,
+    Stopped,
+}
+
+// One process appearing in or disappearing from the process table between two collections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessLifecycleEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub action: ProcessLifecycleAction,
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    pub name: String,
+    pub executable: Option<String>,
+    pub command_line: String,
+    pub user: String,
+    pub start_time: DateTime<Utc>,
+    // Only known for stopped processes
+    pub run_time_secs: Option<u64>,
+}
+
+impl Event for ProcessLifecycleEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.action {
+            ProcessLifecycleAction::Started => "process_started",
+            ProcessLifecycleAction::Stopped => "process_stopped",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Low
+    }
+}
+
+impl Identifiable for ProcessLifecycleEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for ProcessLifecycleEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("Process name cannot be empty".to_string());
+        }
+        if self.user.is_empty() {
+            return Err("User cannot be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct ProcessLifecycleEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    action: Option<ProcessLifecycleAction>,
+    pid: Option<u32>,
+    parent_pid: Option<u32>,
+    name: Option<String>,
+    executable: Option<String>,
+    command_line: Option<String>,
+    user: Option<String>,
+    start_time: Option<DateTime<Utc>>,
+    run_time_secs: Option<u64>,
+}
+
+impl ProcessLifecycleEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn action(mut self, action: ProcessLifecycleAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn pid(mut self, pid: u32) -> Self {
+        self.pid = Some(pid);
+        self
+    }
+
+    pub fn parent_pid(mut self, parent_pid: Option<u32>) -> Self {
+        self.parent_pid = parent_pid;
+        self
+    }
+
+    pub fn name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn executable(mut self, executable: Option<String>) -> Self {
+        self.executable = executable;
+        self
+    }
+
+    pub fn command_line(mut self, command_line: String) -> Self {
+        self.command_line = Some(command_line);
+        self
+    }
+
+    pub fn user(mut self, user: String) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    pub fn start_time(mut self, start_time: DateTime<Utc>) -> Self {
+        self.start_time = Some(start_time);
+        self
+    }
+
+    pub fn run_time_secs(mut self, run_time_secs: u64) -> Self {
+        self.run_time_secs = Some(run_time_secs);
+        self
+    }
+
+    pub fn build(self) -> Result<ProcessLifecycleEvent, String> {
+        let event = ProcessLifecycleEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            action: self.action.ok_or("action is required")?,
+            pid: self.pid.ok_or("pid is required")?,
+            parent_pid: self.parent_pid,
+            name: self.name.ok_or("name is required")?,
+            executable: self.executable,
+            command_line: self.command_line.unwrap_or_default(),
+            user: self.user.ok_or("user is required")?,
+            start_time: self.start_time.ok_or("start_time is required")?,
+            run_time_secs: self.run_time_secs,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
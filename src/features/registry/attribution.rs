@@ -0,0 +1,238 @@
+#[cfg(windows)]
+use crate::shared::etw::{EtwEvent, EtwProvider, EtwSession};
+#[cfg(windows)]
+use crate::shared::error::CollectionError;
+use crate::shared::process_resolver::ProcessResolver;
+use crate::features::registry::collector::RegistrySettings;
+use crate::features::registry::models::RegistryEvent;
+#[cfg(windows)]
+use log::{info, warn};
+use std::collections::VecDeque;
+#[cfg(windows)]
+use std::collections::HashMap;
+#[cfg(windows)]
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::Arc;
+#[cfg(windows)]
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Microsoft-Windows-Kernel-Registry
+pub const KERNEL_REGISTRY_PROVIDER: &str = "70eb4f03-c1de-4f73-a051-33d13d5413bd";
+
+#[cfg(windows)]
+const CREATE_KEY: u16 = 1;
+#[cfg(windows)]
+const OPEN_KEY: u16 = 2;
+#[cfg(windows)]
+const DELETE_KEY: u16 = 3;
+#[cfg(windows)]
+const SET_VALUE_KEY: u16 = 5;
+#[cfg(windows)]
+const DELETE_VALUE_KEY: u16 = 6;
+
+// How long a change is remembered for the registry event it explains
+const RETENTION_SECS: u64 = 30;
+#[cfg(windows)]
+const MAX_CHANGES: usize = 10_000;
+// Key objects remembered to name the changes made through them; forgotten all at once
+// when full
+#[cfg(windows)]
+const MAX_OPEN_KEYS: usize = 50_000;
+
+// One create, set or delete seen by the kernel, with the key in the collector's naming
+struct RegistryChange {
+    key: String,
+    value_name: Option<String>,
+    process_id: u32,
+    process_name: Option<String>,
+    user: Option<String>,
+    seen: Instant,
+}
+
+// Which process created, set or deleted a key or value, from the Kernel-Registry ETW
+// provider. RegNotifyChangeKeyValue and the autorun scan only say that something changed.
+// Needs administrator rights; without them events stay unattributed.
+pub struct RegistryAttribution {
+    #[cfg(windows)]
+    _session: Option<EtwSession>,
+    #[cfg(windows)]
+    events: Option<Mutex<Receiver<EtwEvent>>>,
+    #[cfg(windows)]
+    open_keys: HashMap<String, String>,
+    changes: VecDeque<RegistryChange>,
+    resolver: Arc<ProcessResolver>,
+}
+
+impl RegistryAttribution {
+    #[cfg(not(windows))]
+    pub fn new(_settings: &RegistrySettings) -> Self {
+        Self {
+            changes: VecDeque::new(),
+            resolver: ProcessResolver::global(),
+        }
+    }
+
+    #[cfg(windows)]
+    pub fn new(settings: &RegistrySettings) -> Self {
+        let (session, events) = if settings.process_attribution {
+            match Self::start_session(settings) {
+                Ok((session, events)) => (Some(session), Some(Mutex::new(events))),
+                Err(e) => {
+                    warn!("Registry process attribution unavailable: {}", e);
+                    (None, None)
+                }
+            }
+        } else {
+            (None, None)
+        };
+        Self {
+            _session: session,
+            events,
+            open_keys: HashMap::new(),
+            changes: VecDeque::new(),
+            resolver: ProcessResolver::global(),
+        }
+    }
+
+    #[cfg(windows)]
+    fn start_session(settings: &RegistrySettings) -> Result<(EtwSession, Receiver<EtwEvent>), CollectionError> {
+        let guid = EtwProvider::parse_guid(&settings.etw_provider).ok_or_else(|| {
+            CollectionError::Parse(format!("Invalid ETW provider GUID: {}", settings.etw_provider))
+        })?;
+        let provider = EtwProvider {
+            guid,
+            level: 4,
+            keywords: settings.etw_keywords,
+        };
+        let (tx, rx) = sync_channel(settings.max_pending_events);
+        let session = EtwSession::start("lsedr-registry", &[provider], tx)
+            .map_err(|e| CollectionError::SystemApi(e.to_string()))?;
+        info!("Attributing registry changes through ETW");
+        Ok((session, rx))
+    }
+
+    // \REGISTRY\MACHINE\... and \REGISTRY\USER\<sid>\... as the collector names keys. Every
+    // user's hive maps to HKEY_CURRENT_USER, the only user hive the collector watches.
+    fn normalize(key: &str) -> Option<String> {
+        let lower = key.to_ascii_lowercase();
+        if let Some(rest) = lower.strip_prefix(r"\registry\machine\") {
+            return Some(format!(r"hkey_local_machine\{}", rest));
+        }
+        if let Some(rest) = lower.strip_prefix(r"\registry\user\") {
+            let rest = rest.split_once('\\').map_or("", |(_, rest)| rest);
+            return Some(format!(r"hkey_current_user\{}", rest));
+        }
+        if lower.starts_with("hkey_") {
+            return Some(lower);
+        }
+        None
+    }
+
+    #[cfg(windows)]
+    fn property<'a>(event: &'a EtwEvent, name: &str) -> Option<&'a String> {
+        event.properties.get(name).filter(|value| !value.is_empty())
+    }
+
+    // Full name of the key an event touches, from the event itself or the open that named
+    // its key object
+    #[cfg(windows)]
+    fn key_name(&mut self, event: &EtwEvent) -> Option<String> {
+        match event.event_id {
+            CREATE_KEY | OPEN_KEY => {
+                let relative = Self::property(event, "RelativeName")?;
+                let name = match Self::property(event, "BaseName") {
+                    Some(base) if !relative.starts_with('\\') => format!(r"{}\{}", base, relative),
+                    _ => relative.clone(),
+                };
+                if let Some(object) = Self::property(event, "KeyObject") {
+                    if self.open_keys.len() >= MAX_OPEN_KEYS {
+                        self.open_keys.clear();
+                    }
+                    self.open_keys.insert(object.clone(), name.clone());
+                }
+                Some(name)
+            }
+            _ => Self::property(event, "KeyName")
+                .cloned()
+                .or_else(|| Self::property(event, "KeyObject").and_then(|object| self.open_keys.get(object)).cloned()),
+        }
+    }
+
+    #[cfg(windows)]
+    fn refresh(&mut self) {
+        let received: Vec<EtwEvent> = match self.events.as_ref().map(|events| events.lock()) {
+            Some(Ok(events)) => events.try_iter().collect(),
+            _ => return,
+        };
+
+        let own_pid = std::process::id();
+        for event in received {
+            let key = self.key_name(&event);
+            // Opens only name key objects; failed operations changed nothing
+            if !matches!(event.event_id, CREATE_KEY | DELETE_KEY | SET_VALUE_KEY | DELETE_VALUE_KEY)
+                || Self::property(&event, "Status").is_some_and(|status| !matches!(status.as_str(), "0" | "0x0"))
+                || matches!(event.process_id, 0 | 4)
+                || event.process_id == own_pid
+            {
+                continue;
+            }
+            let Some(key) = key.as_deref().and_then(Self::normalize) else {
+                continue;
+            };
+            // Named while the process is still around to be resolved
+            let details = self.resolver.resolve(event.process_id);
+            if self.changes.len() >= MAX_CHANGES {
+                self.changes.pop_front();
+            }
+            self.changes.push_back(RegistryChange {
+                key,
+                value_name: Self::property(&event, "ValueName").cloned(),
+                process_id: event.process_id,
+                process_name: details.as_ref().map(|details| details.name.clone()),
+                user: details.and_then(|details| details.user),
+                seen: Instant::now(),
+            });
+        }
+    }
+
+    #[cfg(not(windows))]
+    fn refresh(&mut self) {}
+
+    // Fills in the process of an event from the latest matching change: the same value, or
+    // for a change notification on a watched key, anything at or below it
+    pub fn attribute(&mut self, event: &mut RegistryEvent) {
+        if event.process_id.is_some() {
+            return;
+        }
+        self.refresh();
+        let retention = Duration::from_secs(RETENTION_SECS);
+        while self.changes.front().is_some_and(|change| change.seen.elapsed() >= retention) {
+            self.changes.pop_front();
+        }
+
+        let Some(key) = Self::normalize(&event.key_path) else {
+            return;
+        };
+        let value_name = event.value_name.as_deref().map(str::to_ascii_lowercase);
+        let change = self.changes.iter().rev().find(|change| match &value_name {
+            Some(value_name) => {
+                change.key == key
+                    && change.value_name.as_deref().is_some_and(|name| name.eq_ignore_ascii_case(value_name))
+            }
+            None => change.key == key || change.key.starts_with(&format!(r"{}\", key)),
+        });
+        let Some(change) = change else {
+            return;
+        };
+
+        event.process_id = Some(change.process_id);
+        event.process_name = change
+            .process_name
+            .clone()
+            .or_else(|| self.resolver.resolve(change.process_id).map(|details| details.name));
+        if event.user.is_none() {
+            event.user = change.user.clone();
+        }
+    }
+}
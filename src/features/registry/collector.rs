@@ -1,145 +1,338 @@
 use crate::shared::traits::{AsyncDataCollector, DataCollector};
 use crate::shared::error::CollectionError;
+use crate::shared::process_resolver::ProcessResolver;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::baseline::Baseline;
+use crate::shared::matchers::SubstringSet;
+use crate::shared::patterns::{PatternSet, DEFAULT_PATTERNS};
+use crate::utils::{config_generation, load_config_section};
 use crate::features::registry::models::{
-    RegistryEvent, RegistryEventType, SuspiciousRegistryOperation,
-    RegistryEventBuilder, SuspiciousRegistryOperationBuilder
+    PersistenceAudit, RegistryEvent, RegistryEventBuilder, RegistryEventType, SuspiciousRegistryOperation,
+    SuspiciousRegistryOperationBuilder
 };
-use log::{info, warn, error};
+use crate::features::registry::attribution::{RegistryAttribution, KERNEL_REGISTRY_PROVIDER};
+use log::{info, warn};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use windows::Win32::System::Registry::*;
-use windows::Win32::Foundation::*;
-use windows::Win32::System::Threading::*;
-use windows::Win32::Security::*;
-use windows::core::{PCSTR, PSTR};
 use std::collections::HashMap;
+#[cfg(any(windows, target_os = "macos"))]
+use std::collections::HashSet;
 use tokio::sync::mpsc::{channel, Sender, Receiver};
 use std::thread;
-use sysinfo::{System, Pid, ProcessRefreshKind, ProcessesToUpdate};
-use std::ffi::CString;
-use std::time::Duration;
+use std::sync::Arc;
 use uuid::Uuid;
+#[cfg(windows)]
+use {
+    log::error,
+    std::ffi::CString,
+    std::time::Duration,
+    windows::core::{PCSTR, PSTR},
+    windows::Win32::Foundation::*,
+    windows::Win32::System::Registry::*,
+    windows::Win32::System::Threading::*,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct RegistryConfig {
+    pub autorun_paths: Vec<String>,
+    // Case-insensitive substrings, or regexes when prefixed with `re:`; also matched against
+    // base64 and hex found in the data
+    pub suspicious_patterns: Vec<String>,
+    pub settings: RegistrySettings,
+}
+
+// Standard autorun locations and the command lines and keys attackers favour
+impl Default for RegistryConfig {
+    fn default() -> Self {
+        Self {
+            autorun_paths: [
+                r"SOFTWARE\Microsoft\Windows\CurrentVersion\Run",
+                r"SOFTWARE\Microsoft\Windows\CurrentVersion\RunOnce",
+                r"SOFTWARE\Microsoft\Windows\CurrentVersion\RunServices",
+                r"SOFTWARE\Microsoft\Windows\CurrentVersion\RunServicesOnce",
+                r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Winlogon\Userinit",
+                r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Winlogon\Shell",
+                r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Windows\AppInit_DLLs",
+                r"SOFTWARE\Microsoft\Windows\CurrentVersion\Explorer\Shell Folders",
+                r"SOFTWARE\Microsoft\Windows\CurrentVersion\Explorer\User Shell Folders",
+                r"SOFTWARE\Microsoft\Windows\CurrentVersion\Policies\Explorer\Run",
+                r"SOFTWARE\Microsoft\Active Setup\Installed Components",
+                // The 32-bit mirrors that 32-bit programs on 64-bit Windows write to
+                r"SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\Run",
+                r"SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\RunOnce",
+                r"SOFTWARE\WOW6432Node\Microsoft\Windows NT\CurrentVersion\Windows\AppInit_DLLs",
+                r"SOFTWARE\WOW6432Node\Microsoft\Active Setup\Installed Components",
+                // File association and COM hijacks
+                r"\shell\open\command",
+                r"\Classes\CLSID",
+                r"\Classes\WOW6432Node\CLSID",
+                // launchd jobs, the autoruns of macOS
+                "/Library/LaunchAgents",
+                "/Library/LaunchDaemons",
+            ]
+            .iter()
+            .map(|path| path.to_string())
+            .collect(),
+            suspicious_patterns: [
+                "powershell.exe -enc",
+                "cmd.exe /c",
+                "regsvr32.exe /s /u /i:",
+                "rundll32.exe javascript:",
+                "mshta.exe",
+                "wscript.exe",
+                "cscript.exe",
+                "certutil.exe -urlcache",
+                "bitsadmin /transfer",
+                r"\AppData\Roaming\Microsoft\Windows\Start Menu\Programs\Startup",
+                r"\CurrentControlSet\Services",
+                "javascript:",
+                "vbscript:",
+                r"\Control\SecurityProviders\WDigest",
+                r"\Microsoft\Windows NT\CurrentVersion\Image File Execution Options",
+                r"\Microsoft\Windows NT\CurrentVersion\SilentProcessExit",
+                "osascript -e",
+            ]
+            .iter()
+            .chain(DEFAULT_PATTERNS)
+            .map(|pattern| pattern.to_string())
+            .collect(),
+            settings: RegistrySettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct RegistrySettings {
+    pub check_interval_ms: u64,
+    pub max_events_per_collection: usize,
+    // Windows: name the process behind each change from the Kernel-Registry ETW provider,
+    // which needs administrator rights
+    pub process_attribution: bool,
+    pub etw_provider: String,
+    pub etw_keywords: u64,
+    pub max_pending_events: usize,
+}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct MonitorConfig {
-    paths: Vec<String>,
-    settings: FileSystemSettings,
-    registry: RegistryConfig,
+impl Default for RegistrySettings {
+    fn default() -> Self {
+        Self {
+            check_interval_ms: 1000,
+            max_events_per_collection: 100,
+            process_attribution: true,
+            etw_provider: KERNEL_REGISTRY_PROVIDER.to_string(),
+            etw_keywords: 0xffff,
+            max_pending_events: 10000,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct FileSystemSettings {
-    recursive: bool,
-    extensions: Vec<String>,
+impl RegistryConfig {
+    pub fn load() -> Self {
+        load_config_section("registry")
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct RegistryConfig {
-    autorun_paths: Vec<String>,
-    suspicious_patterns: Vec<String>,
-    settings: RegistrySettings,
+// suspicious_patterns and autorun_paths compiled for matching, once per configuration
+struct RegistryMatchers {
+    patterns: PatternSet,
+    autorun_paths: SubstringSet,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct RegistrySettings {
-    check_interval_ms: u64,
-    max_events_per_collection: usize,
+impl RegistryMatchers {
+    fn new(config: &RegistryConfig) -> Self {
+        Self {
+            patterns: PatternSet::compile(&config.suspicious_patterns),
+            autorun_paths: SubstringSet::new(&config.autorun_paths),
+        }
+    }
 }
 
 pub struct RegistryCollector {
     config: RegistryConfig,
-    sys: System,
+    // Installed configuration the settings were last read from, see follow_config
+    config_generation: u64,
+    resolver: Arc<ProcessResolver>,
+    #[cfg_attr(not(any(windows, target_os = "macos")), allow(dead_code))]
     autorun_cache: HashMap<String, String>,
     last_check: chrono::DateTime<Utc>,
     event_receiver: Option<Receiver<RegistryEvent>>,
     _monitor_thread: Option<thread::JoinHandle<()>>,
-    hostname: String,
+    host: Arc<HostIdentityService>,
+    attribution: RegistryAttribution,
+    matchers: RegistryMatchers,
+    // Suspicious operations found in collected events, picked up with
+    // take_suspicious_operations
+    suspicious_operations: Vec<SuspiciousRegistryOperation>,
 }
 
+// Key path, value name, data and the user of its hive
+type AutorunValue = (String, String, String, Option<String>);
+
+// Index the persistence audit report is stored in
+pub const PERSISTENCE_AUDIT_INDEX: &str = "persistence_audit";
+
 impl RegistryCollector {
-    const AUTORUN_LOCATIONS: &'static [(&'static str, &'static str)] = &[
-        (r"SOFTWARE\Microsoft\Windows\CurrentVersion\Run", "HKEY_LOCAL_MACHINE"),
-        (r"SOFTWARE\Microsoft\Windows\CurrentVersion\RunOnce", "HKEY_LOCAL_MACHINE"),
-        (r"Software\Microsoft\Windows\CurrentVersion\Run", "HKEY_CURRENT_USER"),
-        (r"Software\Microsoft\Windows\CurrentVersion\RunOnce", "HKEY_CURRENT_USER"),
-        (r"SOFTWARE\Microsoft\Windows\CurrentVersion\RunServices", "HKEY_LOCAL_MACHINE"),
-        (r"SOFTWARE\Microsoft\Windows\CurrentVersion\RunServicesOnce", "HKEY_LOCAL_MACHINE"),
-        (r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Winlogon\Userinit", "HKEY_LOCAL_MACHINE"),
-        (r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Winlogon\Shell", "HKEY_LOCAL_MACHINE"),
-        (r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Windows\AppInit_DLLs", "HKEY_LOCAL_MACHINE"),
+    const AUTORUN_BASELINE: &'static str = "registry_autoruns";
+
+    // Key, hive, and whether 64-bit Windows keeps a separate 32-bit copy of it (the
+    // WOW6432Node mirror) that is checked as well
+    #[cfg(windows)]
+    const AUTORUN_LOCATIONS: &'static [(&'static str, &'static str, bool)] = &[
+        (r"SOFTWARE\Microsoft\Windows\CurrentVersion\Run", "HKEY_LOCAL_MACHINE", true),
+        (r"SOFTWARE\Microsoft\Windows\CurrentVersion\RunOnce", "HKEY_LOCAL_MACHINE", true),
+        (r"Software\Microsoft\Windows\CurrentVersion\Run", "HKEY_CURRENT_USER", false),
+        (r"Software\Microsoft\Windows\CurrentVersion\RunOnce", "HKEY_CURRENT_USER", false),
+        (r"SOFTWARE\Microsoft\Windows\CurrentVersion\RunServices", "HKEY_LOCAL_MACHINE", true),
+        (r"SOFTWARE\Microsoft\Windows\CurrentVersion\RunServicesOnce", "HKEY_LOCAL_MACHINE", true),
+        (r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Winlogon\Userinit", "HKEY_LOCAL_MACHINE", true),
+        (r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Winlogon\Shell", "HKEY_LOCAL_MACHINE", true),
+        (r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Windows\AppInit_DLLs", "HKEY_LOCAL_MACHINE", true),
+        // Commands run for an extension or protocol; the user's copy wins over the machine's,
+        // which is how the ms-settings and Folder UAC bypasses work
+        (r"SOFTWARE\Classes\exefile\shell\open\command", "HKEY_LOCAL_MACHINE", false),
+        (r"SOFTWARE\Classes\batfile\shell\open\command", "HKEY_LOCAL_MACHINE", false),
+        (r"SOFTWARE\Classes\cmdfile\shell\open\command", "HKEY_LOCAL_MACHINE", false),
+        (r"SOFTWARE\Classes\htafile\shell\open\command", "HKEY_LOCAL_MACHINE", false),
+        (r"Software\Classes\exefile\shell\open\command", "HKEY_CURRENT_USER", false),
+        (r"Software\Classes\ms-settings\shell\open\command", "HKEY_CURRENT_USER", false),
+        (r"Software\Classes\Folder\shell\open\command", "HKEY_CURRENT_USER", false),
     ];
 
-    const SENSITIVE_KEYS: &'static [(&'static str, &'static str)] = &[
-        (r"SOFTWARE\Microsoft\Windows\CurrentVersion\Policies", "HKEY_LOCAL_MACHINE"),
-        (r"SOFTWARE\Policies\Microsoft\Windows\System", "HKEY_LOCAL_MACHINE"),
-        (r"SYSTEM\CurrentControlSet\Services", "HKEY_LOCAL_MACHINE"),
-        (r"SOFTWARE\Microsoft\Windows\CurrentVersion\Explorer\ShellExecuteHooks", "HKEY_LOCAL_MACHINE"),
-        (r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Image File Execution Options", "HKEY_LOCAL_MACHINE"),
-        (r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\SilentProcessExit", "HKEY_LOCAL_MACHINE"),
+    #[cfg(windows)]
+    const SENSITIVE_KEYS: &'static [(&'static str, &'static str, bool)] = &[
+        (r"SOFTWARE\Microsoft\Windows\CurrentVersion\Policies", "HKEY_LOCAL_MACHINE", false),
+        (r"SOFTWARE\Policies\Microsoft\Windows\System", "HKEY_LOCAL_MACHINE", false),
+        (r"SYSTEM\CurrentControlSet\Services", "HKEY_LOCAL_MACHINE", false),
+        (r"SOFTWARE\Microsoft\Windows\CurrentVersion\Explorer\ShellExecuteHooks", "HKEY_LOCAL_MACHINE", true),
+        (r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\Image File Execution Options", "HKEY_LOCAL_MACHINE", true),
+        (r"SOFTWARE\Microsoft\Windows NT\CurrentVersion\SilentProcessExit", "HKEY_LOCAL_MACHINE", true),
+        // COM hijacks: an InprocServer32 or LocalServer32 registered under the user's CLSID
+        // is loaded instead of the machine's
+        (r"SOFTWARE\Classes\CLSID", "HKEY_LOCAL_MACHINE", true),
+        (r"Software\Classes\CLSID", "HKEY_CURRENT_USER", true),
+        (r"SOFTWARE\Classes\*\shellex\ContextMenuHandlers", "HKEY_LOCAL_MACHINE", false),
+        (r"SOFTWARE\Classes\Directory\shellex\ContextMenuHandlers", "HKEY_LOCAL_MACHINE", false),
+        (r"SOFTWARE\Classes\Protocols\Handler", "HKEY_LOCAL_MACHINE", false),
+        (r"SOFTWARE\Classes\Protocols\Filter", "HKEY_LOCAL_MACHINE", false),
     ];
 
     pub fn new() -> Result<Self, CollectionError> {
-        let config_path = "config/monitor.yaml";
-        info!("Reading config from: {}", config_path);
-        let config_content = std::fs::read_to_string(config_path)
-            .map_err(|e| CollectionError::Parse(format!("Failed to read config: {}", e)))?;
-        
-        let config: MonitorConfig = serde_yaml::from_str(&config_content)
-            .map_err(|e| CollectionError::Parse(format!("Failed to parse config: {}", e)))?;
-        
-        info!("Loaded registry monitor config: {:?}", config.registry);
+        Self::with_config(RegistryConfig::load())
+    }
+
+    pub fn with_config(config: RegistryConfig) -> Result<Self, CollectionError> {
+        info!("Loaded registry monitor config: {:?}", config);
 
         let (tx, rx) = channel(100);
-        let registry_config = config.registry.clone();
-        let hostname = whoami::hostname();
-        let hostname_clone = hostname.clone();
+        let registry_config = config.clone();
+        let host = HostIdentityService::global();
+        let host_clone = host.clone();
         
         // Start registry monitoring thread
         let monitor_thread = thread::spawn(move || {
-            Self::monitor_registry_changes(tx, &registry_config, &hostname_clone);
+            Self::monitor_registry_changes(tx, &registry_config, &host_clone);
         });
 
+        let attribution = RegistryAttribution::new(&config.settings);
+        let matchers = RegistryMatchers::new(&config);
         Ok(Self {
-            config: config.registry,
-            sys: System::new(),
-            autorun_cache: HashMap::new(),
+            config,
+            config_generation: config_generation(),
+            resolver: ProcessResolver::global(),
+            // Autoruns seen before a restart are not new again
+            autorun_cache: Baseline::global().load(Self::AUTORUN_BASELINE).unwrap_or_default(),
             last_check: Utc::now(),
             event_receiver: Some(rx),
             _monitor_thread: Some(monitor_thread),
-            hostname,
+            host,
+            attribution,
+            matchers,
+            suspicious_operations: Vec::new(),
         })
     }
 
-    fn monitor_registry_changes(tx: Sender<RegistryEvent>, config: &RegistryConfig, hostname: &str) {
+    // Whether Windows itself is 64-bit, including when this is a 32-bit build running under
+    // WOW64; only then does a key have a separate 32-bit view
+    #[cfg(windows)]
+    fn has_wow64_views() -> bool {
+        cfg!(target_pointer_width = "64") || std::env::var_os("PROCESSOR_ARCHITEW6432").is_some()
+    }
+
+    // Where the 32-bit view of a key lives: SOFTWARE\WOW6432Node for most keys, and
+    // Classes\WOW6432Node for the few redirected class keys such as CLSID
+    #[cfg(windows)]
+    fn wow64_path(subkey: &str) -> String {
+        let lower = subkey.to_ascii_lowercase();
+        let split = if lower.starts_with(r"software\classes\") {
+            r"software\classes\".len()
+        } else if lower.starts_with(r"software\") {
+            r"software\".len()
+        } else {
+            return subkey.to_string();
+        };
+        format!(r"{}WOW6432Node\{}", &subkey[..split], &subkey[split..])
+    }
+
+    // The views a key is opened in, each with the access flag that selects it and the path
+    // it is reported under; explicit flags keep a 32-bit build from being redirected too
+    #[cfg(windows)]
+    fn views(subkey: &str, hive: &str, mirrored: bool) -> Vec<(REG_SAM_FLAGS, String)> {
+        if mirrored && Self::has_wow64_views() {
+            vec![
+                (KEY_WOW64_64KEY, format!("{}\\{}", hive, subkey)),
+                (KEY_WOW64_32KEY, format!("{}\\{}", hive, Self::wow64_path(subkey))),
+            ]
+        } else if Self::has_wow64_views() {
+            vec![(KEY_WOW64_64KEY, format!("{}\\{}", hive, subkey))]
+        } else {
+            vec![(REG_SAM_FLAGS(0), format!("{}\\{}", hive, subkey))]
+        }
+    }
+
+    // The registry only exists on Windows; elsewhere the collector only checks autoruns
+    #[cfg(not(windows))]
+    fn monitor_registry_changes(_tx: Sender<RegistryEvent>, _config: &RegistryConfig, _host: &HostIdentityService) {}
+
+    #[cfg(not(any(windows, target_os = "macos")))]
+    fn check_autorun_entries(&mut self) -> Vec<RegistryEvent> {
+        Vec::new()
+    }
+
+    #[cfg(windows)]
+    fn monitor_registry_changes(tx: Sender<RegistryEvent>, config: &RegistryConfig, host: &HostIdentityService) {
         let mut change_handles = Vec::new();
 
         // Monitor autorun and sensitive keys
-        for (subkey, hive) in Self::AUTORUN_LOCATIONS.iter().chain(Self::SENSITIVE_KEYS.iter()) {
+        for (subkey, hive, mirrored) in Self::AUTORUN_LOCATIONS.iter().chain(Self::SENSITIVE_KEYS.iter()) {
             let hkey = match *hive {
                 "HKEY_LOCAL_MACHINE" => HKEY_LOCAL_MACHINE,
                 "HKEY_CURRENT_USER" => HKEY_CURRENT_USER,
                 _ => continue,
             };
 
-            unsafe {
-                let mut key = HKEY::default();
-                let subkey_cstr = CString::new(*subkey).unwrap();
-                if RegOpenKeyExA(
-                    hkey,
-                    PCSTR(subkey_cstr.as_ptr() as *const u8),
-                    0,
-                    KEY_NOTIFY | KEY_READ,
-                    &mut key,
-                ).is_ok() {
-                    if let Ok(event) = CreateEventA(None, true, false, None) {
-                        if RegNotifyChangeKeyValue(
-                            key,
-                            true,
-                            REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET,
-                            event,
-                            true,
-                        ).is_ok() {
-                            change_handles.push((key, event, format!("{}\\{}", hive, subkey)));
+            for (view, path) in Self::views(subkey, hive, *mirrored) {
+                unsafe {
+                    let mut key = HKEY::default();
+                    let subkey_cstr = CString::new(*subkey).unwrap();
+                    if RegOpenKeyExA(
+                        hkey,
+                        PCSTR(subkey_cstr.as_ptr() as *const u8),
+                        0,
+                        KEY_NOTIFY | KEY_READ | view,
+                        &mut key,
+                    ).is_ok() {
+                        if let Ok(event) = CreateEventA(None, true, false, None) {
+                            if RegNotifyChangeKeyValue(
+                                key,
+                                true,
+                                REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET,
+                                event,
+                                true,
+                            ).is_ok() {
+                                change_handles.push((key, event, path));
+                            }
                         }
                     }
                 }
@@ -150,10 +343,12 @@ impl RegistryCollector {
             for (key, event, path) in &change_handles {
                 unsafe {
                     if WaitForSingleObject(*event, 0) == WAIT_OBJECT_0 {
+                        // The user is that of the changing process, filled in by the ETW
+                        // attribution; the agent's own HKCU says nothing about who wrote it
                         let registry_event = RegistryEventBuilder::new()
                             .id(Uuid::new_v4().to_string())
                             .timestamp(Utc::now())
-                            .source(hostname.to_string())
+                            .source(host.name())
                             .category(String::from("registry"))
                             .event_type(RegistryEventType::Modified)
                             .key_path(path.clone())
@@ -183,171 +378,415 @@ impl RegistryCollector {
         }
     }
 
-    fn get_process_info(&mut self, pid: u32) -> (String, u32) {
-        let pid = Pid::from_u32(pid);
-        self.sys.refresh_processes_specifics(
-            ProcessesToUpdate::All,
-            true,
-            ProcessRefreshKind::everything(),
-        );
-        
-        if let Some(process) = self.sys.process(pid) {
-            (process.name().to_string_lossy().into_owned(), pid.as_u32())
-        } else {
-            ("unknown".to_string(), 0)
+    fn get_process_info(&self, pid: u32) -> (String, u32) {
+        match self.resolver.resolve(pid) {
+            Some(process) => (process.name, process.pid),
+            None => ("unknown".to_string(), 0),
         }
     }
 
-    fn check_autorun_entries(&mut self) -> Vec<RegistryEvent> {
-        let mut events = Vec::new();
-        
-        for (subkey, hive) in Self::AUTORUN_LOCATIONS {
+    // Values of a key as (name, data), and whether the key was read to the end or found
+    // missing; only then does a value left out of the list no longer exist
+    #[cfg(windows)]
+    fn read_values(hkey: HKEY, subkey: &str, view: REG_SAM_FLAGS) -> (Vec<(String, String)>, bool) {
+        let mut values = Vec::new();
+        let mut complete = false;
+        unsafe {
+            let mut key = HKEY::default();
+            let subkey_cstr = CString::new(subkey).unwrap();
+            let opened = RegOpenKeyExA(
+                hkey,
+                PCSTR(subkey_cstr.as_ptr() as *const u8),
+                0,
+                KEY_READ | view,
+                &mut key,
+            );
+            // A deleted key took all of its values with it
+            if opened == ERROR_FILE_NOT_FOUND {
+                complete = true;
+            }
+            if opened.is_ok() {
+                let mut index = 0u32;
+                let mut name_buf = vec![0u8; 256];
+                let mut data_buf = vec![0u8; 1024];
+                
+                loop {
+                    let mut name_size = name_buf.len() as u32;
+                    let mut data_size = data_buf.len() as u32;
+                    let mut value_type = 0u32;
+                    
+                    let status = RegEnumValueA(
+                        key,
+                        index,
+                        PSTR(name_buf.as_mut_ptr()),
+                        &mut name_size,
+                        None,
+                        Some(&mut value_type),
+                        Some(data_buf.as_mut_ptr()),
+                        Some(&mut data_size),
+                    );
+
+                    if status == ERROR_NO_MORE_ITEMS {
+                        complete = true;
+                        break;
+                    }
+
+                    if status.is_ok() {
+                        if let (Ok(name), Ok(data)) = (
+                            String::from_utf8(name_buf[..name_size as usize].to_vec()),
+                            String::from_utf8(data_buf[..data_size as usize].to_vec())
+                        ) {
+                            values.push((name, data));
+                        }
+                        index += 1;
+                    } else {
+                        break;
+                    }
+                }
+                RegCloseKey(key);
+            }
+        }
+        (values, complete)
+    }
+
+    // Every autorun value in every view, and the keys read to the end (or found missing); only
+    // those keys can tell that a value is gone. The hives carry no user: HKCU is the agent's own.
+    #[cfg(windows)]
+    fn autorun_entries() -> (Vec<AutorunValue>, Vec<(String, Option<String>)>) {
+        let mut inventory = Vec::new();
+        let mut enumerated = Vec::new();
+        for (subkey, hive, mirrored) in Self::AUTORUN_LOCATIONS {
             let hkey = match *hive {
                 "HKEY_LOCAL_MACHINE" => HKEY_LOCAL_MACHINE,
                 "HKEY_CURRENT_USER" => HKEY_CURRENT_USER,
                 _ => continue,
             };
+            for (view, key_path) in Self::views(subkey, hive, *mirrored) {
+                let (values, complete) = Self::read_values(hkey, subkey, view);
+                if complete {
+                    enumerated.push((key_path.clone(), None));
+                }
+                for (name, data) in values {
+                    inventory.push((key_path.clone(), name, data, None));
+                }
+            }
+        }
+        (inventory, enumerated)
+    }
 
-            unsafe {
-                let mut key = HKEY::default();
-                let subkey_cstr = CString::new(*subkey).unwrap();
-                if RegOpenKeyExA(
-                    hkey,
-                    PCSTR(subkey_cstr.as_ptr() as *const u8),
-                    0,
-                    KEY_READ,
-                    &mut key,
-                ).is_ok() {
-                    let mut index = 0u32;
-                    let mut name_buf = vec![0u8; 256];
-                    let mut data_buf = vec![0u8; 1024];
-                    
-                    loop {
-                        let mut name_size = name_buf.len() as u32;
-                        let mut data_size = data_buf.len() as u32;
-                        let mut value_type = 0u32;
-                        
-                        let status = RegEnumValueA(
-                            key,
-                            index,
-                            PSTR(name_buf.as_mut_ptr()),
-                            &mut name_size,
-                            None,
-                            Some(&mut value_type),
-                            Some(data_buf.as_mut_ptr()),
-                            Some(&mut data_size),
-                        );
-
-                        if status == ERROR_NO_MORE_ITEMS {
-                            break;
-                        }
+    // launchd jobs stand in for the Run keys on macOS: the directory is the key, the property
+    // list the value and the command it starts the data. A directory that cannot be read
+    // says nothing about the jobs it had. Apple's own jobs below /System are on the sealed
+    // system volume, out of reach of an installer.
+    #[cfg(target_os = "macos")]
+    fn autorun_entries() -> (Vec<AutorunValue>, Vec<(String, Option<String>)>) {
+        use crate::features::service::launchd;
+
+        let mut inventory = Vec::new();
+        let mut enumerated = Vec::new();
+        for (directory, user) in launchd::job_directories() {
+            if directory.starts_with("/System") {
+                continue;
+            }
+            let Some(jobs) = launchd::jobs_in(&directory, user.as_deref()) else {
+                continue;
+            };
+            let key_path = directory.to_string_lossy().into_owned();
+            for job in jobs {
+                let Some(name) = job.path.file_name().map(|name| name.to_string_lossy().into_owned()) else {
+                    continue;
+                };
+                let data = job.command_line.clone().unwrap_or_else(|| job.label.clone());
+                inventory.push((key_path.clone(), name, data, user.clone()));
+            }
+            enumerated.push((key_path, user));
+        }
+        (inventory, enumerated)
+    }
 
-                        if status.is_ok() {
-                            if let (Ok(name), Ok(data)) = (
-                                String::from_utf8(name_buf[..name_size as usize].to_vec()),
-                                String::from_utf8(data_buf[..data_size as usize].to_vec())
-                            ) {
-                                let key_path = format!("{}\\{}", hive, subkey);
-                                let cache_key = format!("{}\\{}", key_path, name);
-                                
-                                let event = if let Some(old_data) = self.autorun_cache.get(&cache_key) {
-                                    if old_data != &data {
-                                        Some(RegistryEventBuilder::new()
-                                            .id(Uuid::new_v4().to_string())
-                                            .timestamp(Utc::now())
-                                            .source(self.hostname.clone())
-                                            .category(String::from("registry"))
-                                            .event_type(RegistryEventType::Modified)
-                                            .key_path(key_path)
-                                            .value_name(name)
-                                            .old_data(old_data.clone())
-                                            .new_data(data.clone())
-                                            .build()
-                                            .ok())
-                                    } else {
-                                        None
-                                    }
-                                } else {
-                                    Some(RegistryEventBuilder::new()
-                                        .id(Uuid::new_v4().to_string())
-                                        .timestamp(Utc::now())
-                                        .source(self.hostname.clone())
-                                        .category(String::from("registry"))
-                                        .event_type(RegistryEventType::Created)
-                                        .key_path(key_path)
-                                        .value_name(name)
-                                        .new_data(data.clone())
-                                        .build()
-                                        .ok())
-                                };
-                                
-                                if let Some(event) = event.flatten() {
-                                    events.push(event);
-                                }
-                                
-                                self.autorun_cache.insert(cache_key, data);
-                            }
-                            index += 1;
-                        } else {
-                            break;
-                        }
-                    }
-                    RegCloseKey(key);
+    // Autoruns only exist as registry values and launchd jobs
+    #[cfg(not(any(windows, target_os = "macos")))]
+    fn autorun_entries() -> (Vec<AutorunValue>, Vec<(String, Option<String>)>) {
+        (Vec::new(), Vec::new())
+    }
+
+    fn autorun_inventory() -> Vec<AutorunValue> {
+        Self::autorun_entries().0
+    }
+
+    #[cfg(any(windows, target_os = "macos"))]
+    fn check_autorun_entries(&mut self) -> Vec<RegistryEvent> {
+        let mut events = Vec::new();
+        // Values seen this pass
+        let mut present = HashSet::new();
+        let (inventory, enumerated) = Self::autorun_entries();
+
+        for (key_path, name, data, user) in inventory {
+            let cache_key = format!("{}\\{}", key_path, name);
+            present.insert(cache_key.clone());
+
+            let builder = match self.autorun_cache.get(&cache_key) {
+                Some(old_data) if old_data == &data => None,
+                Some(old_data) => Some(
+                    RegistryEventBuilder::new()
+                        .event_type(RegistryEventType::Modified)
+                        .old_data(old_data.clone()),
+                ),
+                None => Some(RegistryEventBuilder::new().event_type(RegistryEventType::Created)),
+            };
+            if let Some(builder) = builder {
+                let event = builder
+                    .id(Uuid::new_v4().to_string())
+                    .timestamp(Utc::now())
+                    .source(self.host.name())
+                    .category(String::from("registry"))
+                    .key_path(key_path)
+                    .value_name(name)
+                    .new_data(data.clone())
+                    .build();
+                if let Ok(mut event) = event {
+                    event.user = user;
+                    events.push(event);
                 }
             }
+
+            self.autorun_cache.insert(cache_key, data);
+        }
+
+        events.extend(self.deleted_autorun_entries(&present, &enumerated));
+
+        if !events.is_empty() {
+            Baseline::global().save(Self::AUTORUN_BASELINE, &self.autorun_cache);
         }
-        
         events
     }
 
-    fn check_suspicious_operations(&self, event: &RegistryEvent) -> Option<SuspiciousRegistryOperation> {
-        // Check suspicious registry operation patterns
-        if let Some(data) = event.new_data.as_ref() {
-            for pattern in &self.config.suspicious_patterns {
-                if data.to_lowercase().contains(&pattern.to_lowercase()) {
-                    return SuspiciousRegistryOperationBuilder::new()
-                        .id(Uuid::new_v4().to_string())
-                        .timestamp(event.timestamp)
-                        .source(self.hostname.clone())
-                        .category(String::from("registry_suspicious"))
-                        .operation(format!("{:?}", event.event_type))
-                        .key_path(event.key_path.clone())
-                        .value_name(event.value_name.clone().unwrap_or_default())
-                        .data(data.clone())
-                        .process_name(event.process_name.clone().unwrap_or_default())
-                        .process_id(event.process_id.unwrap_or_default())
-                        .severity_level(crate::shared::traits::Severity::High)
-                        .reason(format!("Suspicious command pattern detected: {}", pattern))
-                        .build()
-                        .ok();
-                }
+    // Cached values of fully read keys that were not seen this pass, dropped from the cache
+    #[cfg(any(windows, target_os = "macos"))]
+    fn deleted_autorun_entries(
+        &mut self,
+        present: &HashSet<String>,
+        enumerated: &[(String, Option<String>)],
+    ) -> Vec<RegistryEvent> {
+        let deleted: Vec<(String, String, Option<String>)> = self
+            .autorun_cache
+            .keys()
+            .filter(|cache_key| !present.contains(*cache_key))
+            .filter_map(|cache_key| {
+                enumerated.iter().find_map(|(key_path, user)| {
+                    let name = cache_key.strip_prefix(key_path.as_str())?.strip_prefix('\\')?;
+                    Some((cache_key.clone(), name.to_string(), user.clone()))
+                })
+            })
+            .collect();
+
+        let mut events = Vec::new();
+        for (cache_key, name, user) in deleted {
+            let Some(old_data) = self.autorun_cache.remove(&cache_key) else {
+                continue;
+            };
+            let key_path = cache_key[..cache_key.len() - name.len() - 1].to_string();
+            let mut builder = RegistryEventBuilder::new()
+                .id(Uuid::new_v4().to_string())
+                .timestamp(Utc::now())
+                .source(self.host.name())
+                .category(String::from("registry"))
+                .event_type(RegistryEventType::Deleted)
+                .key_path(key_path)
+                .value_name(name)
+                .old_data(old_data);
+            if let Some(user) = user {
+                builder = builder.user(user);
+            }
+            match builder.build() {
+                Ok(event) => events.push(event),
+                Err(e) => warn!("Failed to build registry deletion event: {}", e),
             }
         }
+        events
+    }
 
-        // Check sensitive registry paths
-        for path in &self.config.autorun_paths {
-            if event.key_path.contains(path) {
+    pub fn take_suspicious_operations(&mut self) -> Vec<SuspiciousRegistryOperation> {
+        std::mem::take(&mut self.suspicious_operations)
+    }
+
+    fn check_suspicious_operations(&self, event: &RegistryEvent) -> Option<SuspiciousRegistryOperation> {
+        Self::suspicious_operation(&self.config, &self.matchers, self.host.name(), event)
+    }
+
+    fn suspicious_operation(
+        config: &RegistryConfig,
+        matchers: &RegistryMatchers,
+        source: String,
+        event: &RegistryEvent,
+    ) -> Option<SuspiciousRegistryOperation> {
+        // A deleted value is judged by the data it had
+        let data = event.new_data.as_ref().or(event.old_data.as_ref());
+
+        // Check suspicious registry operation patterns
+        if let Some(data) = data {
+            if let Some(matched) = matchers.patterns.first_match(data) {
                 return SuspiciousRegistryOperationBuilder::new()
                     .id(Uuid::new_v4().to_string())
                     .timestamp(event.timestamp)
-                    .source(self.hostname.clone())
+                    .source(source)
                     .category(String::from("registry_suspicious"))
                     .operation(format!("{:?}", event.event_type))
                     .key_path(event.key_path.clone())
                     .value_name(event.value_name.clone().unwrap_or_default())
-                    .data(event.new_data.clone().unwrap_or_default())
+                    .data(data.clone())
                     .process_name(event.process_name.clone().unwrap_or_default())
                     .process_id(event.process_id.unwrap_or_default())
-                    .severity_level(crate::shared::traits::Severity::Medium)
-                    .reason(format!("Modification to sensitive registry path: {}", path))
+                    .severity_level(crate::shared::traits::Severity::High)
+                    .reason(format!("Suspicious command pattern detected: {}", matched.describe()))
                     .build()
                     .ok();
             }
         }
 
+        // Check sensitive registry paths
+        if let Some(path) = matchers.autorun_paths.first_in(&event.key_path).map(|index| &config.autorun_paths[index]) {
+            return SuspiciousRegistryOperationBuilder::new()
+                .id(Uuid::new_v4().to_string())
+                .timestamp(event.timestamp)
+                .source(source)
+                .category(String::from("registry_suspicious"))
+                .operation(format!("{:?}", event.event_type))
+                .key_path(event.key_path.clone())
+                .value_name(event.value_name.clone().unwrap_or_default())
+                .data(data.cloned().unwrap_or_default())
+                .process_name(event.process_name.clone().unwrap_or_default())
+                .process_id(event.process_id.unwrap_or_default())
+                .severity_level(crate::shared::traits::Severity::Medium)
+                .reason(format!("Modification to sensitive registry path: {}", path))
+                .build()
+                .ok();
+        }
+
         None
     }
 
+    // One-shot sweep of the autorun baseline and the autoruns present now through the same
+    // checks as collected events, without touching the baseline. Baseline values that are
+    // gone are judged by the data they last had.
+    pub fn persistence_audit(config: &RegistryConfig) -> PersistenceAudit {
+        let host = HostIdentityService::global();
+        let matchers = RegistryMatchers::new(config);
+        let mut baseline: HashMap<String, String> = Baseline::global().load(Self::AUTORUN_BASELINE).unwrap_or_default();
+        let baseline_entries = baseline.len();
+        let now = Utc::now();
+
+        let mut audited = Vec::new();
+        let mut changed_entries = 0;
+        let inventory = Self::autorun_inventory();
+        let present_entries = inventory.len();
+        // Each finding's operation says how its value compares with the baseline: New,
+        // Present, Changed or Removed
+        for (key_path, name, data, user) in inventory {
+            let (status, old_data) = match baseline.remove(&format!("{}\\{}", key_path, name)) {
+                Some(old_data) if old_data != data => {
+                    changed_entries += 1;
+                    ("Changed", Some(old_data))
+                }
+                Some(_) => ("Present", None),
+                None => ("New", None),
+            };
+            let mut builder = RegistryEventBuilder::new()
+                .id(Uuid::new_v4().to_string())
+                .timestamp(now)
+                .source(host.name())
+                .category(String::from("registry"))
+                .event_type(if old_data.is_some() { RegistryEventType::Modified } else { RegistryEventType::Created })
+                .key_path(key_path)
+                .value_name(name)
+                .new_data(data);
+            if let Some(old_data) = old_data {
+                builder = builder.old_data(old_data);
+            }
+            if let Some(user) = user {
+                builder = builder.user(user);
+            }
+            audited.push((status, builder.build()));
+        }
+        let removed_entries = baseline.len();
+        for (cache_key, old_data) in baseline {
+            let Some((key_path, name)) = cache_key.rsplit_once('\\') else {
+                continue;
+            };
+            let event = RegistryEventBuilder::new()
+                .id(Uuid::new_v4().to_string())
+                .timestamp(now)
+                .source(host.name())
+                .category(String::from("registry"))
+                .event_type(RegistryEventType::Deleted)
+                .key_path(key_path.to_string())
+                .value_name(name.to_string())
+                .old_data(old_data)
+                .build();
+            audited.push(("Removed", event));
+        }
+
+        let mut findings = Vec::new();
+        for (status, event) in audited {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Skipping autorun entry in persistence audit: {}", e);
+                    continue;
+                }
+            };
+            if let Some(mut finding) = Self::suspicious_operation(config, &matchers, host.name(), &event) {
+                finding.operation = status.to_string();
+                findings.push(finding);
+            }
+        }
+        // Worst first, so a reader of the report sees what matters at the top
+        findings.sort_by(|a, b| b.severity_level.cmp(&a.severity_level).then_with(|| a.key_path.cmp(&b.key_path)));
+
+        PersistenceAudit {
+            id: Uuid::new_v4().to_string(),
+            timestamp: now,
+            source: host.name(),
+            category: String::from("persistence_audit"),
+            baseline_entries,
+            present_entries,
+            changed_entries,
+            removed_entries,
+            highest_severity: findings.first().map(|finding| finding.severity_level),
+            findings,
+        }
+    }
+
+    // Switches to new autorun paths, suspicious patterns and event cap. The watched keys are
+    // built in, and the monitor thread and attribution keep their settings until a restart.
+    pub fn apply_config(&mut self, config: RegistryConfig) {
+        let settings = &self.config.settings;
+        if config.settings.check_interval_ms != settings.check_interval_ms
+            || config.settings.process_attribution != settings.process_attribution
+            || config.settings.etw_provider != settings.etw_provider
+            || config.settings.etw_keywords != settings.etw_keywords
+            || config.settings.max_pending_events != settings.max_pending_events
+        {
+            info!("Registry monitoring and attribution settings take effect after a restart");
+        }
+        self.matchers = RegistryMatchers::new(&config);
+        self.config = config;
+    }
+
+    // Applies the registry section of a configuration installed since the last run
+    fn follow_config(&mut self) {
+        let generation = config_generation();
+        if generation == self.config_generation {
+            return;
+        }
+        self.config_generation = generation;
+        let config = RegistryConfig::load();
+        if config != self.config {
+            info!("Applying the reloaded registry configuration");
+            self.apply_config(config);
+        }
+    }
+
     fn internal_validate(&self) -> Result<(), CollectionError> {
         if self.event_receiver.is_none() {
             return Err(CollectionError::SystemApi(
@@ -360,6 +799,7 @@ impl RegistryCollector {
 
 impl DataCollector<Vec<RegistryEvent>> for RegistryCollector {
     fn collect(&mut self) -> Result<Vec<RegistryEvent>, CollectionError> {
+        self.follow_config();
         let mut events = Vec::new();
         
         // Collect real-time registry change events
@@ -376,12 +816,17 @@ impl DataCollector<Vec<RegistryEvent>> for RegistryCollector {
         let autorun_events = self.check_autorun_entries();
         events.extend(autorun_events);
 
+        for event in &mut events {
+            self.attribution.attribute(event);
+        }
+
         info!("Collected {} registry events", events.len());
         
         // Check suspicious operations
         for event in &events {
             if let Some(suspicious_op) = self.check_suspicious_operations(event) {
                 warn!("Detected suspicious registry operation: {:?}", suspicious_op);
+                self.suspicious_operations.push(suspicious_op);
             }
         }
 
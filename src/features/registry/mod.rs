@@ -1,5 +1,6 @@
+mod attribution;
 mod collector;
 mod models;
 
-pub use collector::RegistryCollector;
-pub use models::{RegistryEvent, RegistryEventType, AutoRunEntry, SuspiciousRegistryOperation};
+pub use collector::{RegistryCollector, RegistryConfig, RegistrySettings, PERSISTENCE_AUDIT_INDEX};
+pub use models::{RegistryEvent, RegistryEventBuilder, RegistryEventType, AutoRunEntry, PersistenceAudit, SuspiciousRegistryOperation};
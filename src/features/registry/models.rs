@@ -23,6 +23,7 @@ pub struct RegistryEvent {
     pub new_data: Option<String>,
     pub process_name: Option<String>,
     pub process_id: Option<u32>,
+    pub user: Option<String>,
 }
 
 impl Event for RegistryEvent {
@@ -188,6 +189,51 @@ impl Validatable for SuspiciousRegistryOperation {
     }
 }
 
+// Outcome of one sweep of a host's autorun baseline and current autoruns through the
+// suspicious operation checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistenceAudit {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub baseline_entries: usize,
+    pub present_entries: usize,
+    pub changed_entries: usize,
+    // In the baseline but no longer in the registry
+    pub removed_entries: usize,
+    pub highest_severity: Option<Severity>,
+    pub findings: Vec<SuspiciousRegistryOperation>,
+}
+
+impl Event for PersistenceAudit {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        "persistence_audit"
+    }
+
+    fn severity(&self) -> Severity {
+        self.highest_severity.unwrap_or(Severity::Low)
+    }
+}
+
+impl Identifiable for PersistenceAudit {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
 #[derive(Default)]
 pub struct RegistryEventBuilder {
     id: Option<String>,
@@ -201,6 +247,7 @@ pub struct RegistryEventBuilder {
     new_data: Option<String>,
     process_name: Option<String>,
     process_id: Option<u32>,
+    user: Option<String>,
 }
 
 impl RegistryEventBuilder {
@@ -263,6 +310,11 @@ impl RegistryEventBuilder {
         self
     }
 
+    pub fn user(mut self, user: String) -> Self {
+        self.user = Some(user);
+        self
+    }
+
     pub fn build(self) -> Result<RegistryEvent, String> {
         let event = RegistryEvent {
             id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
@@ -276,6 +328,7 @@ impl RegistryEventBuilder {
             new_data: self.new_data,
             process_name: self.process_name,
             process_id: self.process_id,
+            user: self.user,
         };
 
         event.validate()?;
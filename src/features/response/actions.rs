@@ -0,0 +1,330 @@
+use crate::shared::error::ActionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::pseudonymizer::{PseudonymEntry, Pseudonymizer};
+use crate::features::response::models::{
+    ActionAuditEvent, FileRetrievalRequest, ResponseActionType, RetrievedFile,
+    RetrievedFileBuilder, SignedActionRequest,
+};
+use crate::features::response::authorization::{ActionAuthorizer, AuthorizationPolicy};
+use crate::utils::load_config_section;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResponseConfig {
+    pub fetch_file: FileRetrievalPolicy,
+    pub authorization: AuthorizationPolicy,
+}
+
+impl ResponseConfig {
+    pub fn load() -> Self {
+        load_config_section("response")
+    }
+
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.fetch_file.max_file_size == 0 {
+            problems.push(String::from("response.fetch_file.max_file_size must not be 0"));
+        }
+        let authorization = &self.authorization;
+        if authorization.signing_key_env.trim().is_empty() {
+            problems.push(String::from("response.authorization.signing_key_env is empty"));
+        }
+        if authorization.max_validity_secs <= 0 {
+            problems.push(String::from("response.authorization.max_validity_secs must be above 0"));
+        }
+        if authorization.clock_skew_secs < 0 {
+            problems.push(String::from("response.authorization.clock_skew_secs must not be negative"));
+        }
+        problems
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileRetrievalPolicy {
+    pub max_file_size: u64,
+    pub allowed_extensions: Vec<String>,
+    pub denied_extensions: Vec<String>,
+}
+
+impl Default for FileRetrievalPolicy {
+    fn default() -> Self {
+        Self {
+            max_file_size: 32 * 1024 * 1024,
+            allowed_extensions: Vec::new(),
+            denied_extensions: Vec::new(),
+        }
+    }
+}
+
+// Reads the file of a fetch_file action. Requests reach it only through
+// ResponseActionExecutor, from the control channel's ExecuteAction, so they are signed,
+// authorized and audited first.
+pub struct FileRetriever {
+    policy: FileRetrievalPolicy,
+    host: Arc<HostIdentityService>,
+}
+
+impl FileRetriever {
+    pub fn with_policy(policy: FileRetrievalPolicy) -> Self {
+        Self {
+            policy,
+            host: HostIdentityService::global(),
+        }
+    }
+
+    fn check_extension(&self, path: &Path) -> Result<(), ActionError> {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| format!(".{}", ext.to_lowercase()))
+            .unwrap_or_default();
+
+        let matches = |list: &[String]| list.iter().any(|e| e.to_lowercase() == ext);
+
+        if matches(&self.policy.denied_extensions) {
+            return Err(ActionError::PolicyViolation(format!(
+                "Extension '{}' is denied for retrieval", ext
+            )));
+        }
+        if !self.policy.allowed_extensions.is_empty() && !matches(&self.policy.allowed_extensions) {
+            return Err(ActionError::PolicyViolation(format!(
+                "Extension '{}' is not in the retrieval allow-list", ext
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn retrieve(&self, request: &FileRetrievalRequest) -> Result<RetrievedFile, ActionError> {
+        if request.request_id.is_empty() || request.path.is_empty() {
+            return Err(ActionError::InvalidRequest(
+                "request_id and path are required".to_string()
+            ));
+        }
+
+        let path = Path::new(&request.path);
+        info!("Fetch file requested by {}: {}", request.requested_by, path.display());
+
+        let metadata = fs::symlink_metadata(path)?;
+        if !metadata.is_file() {
+            return Err(ActionError::PolicyViolation(format!(
+                "Not a regular file: {}", path.display()
+            )));
+        }
+        self.check_extension(path)?;
+
+        if metadata.len() > self.policy.max_file_size {
+            return Err(ActionError::PolicyViolation(format!(
+                "File size {} exceeds retrieval limit of {} bytes",
+                metadata.len(),
+                self.policy.max_file_size
+            )));
+        }
+
+        // Read at most one byte past the limit so a file growing underneath us is still caught
+        let mut content = Vec::with_capacity(metadata.len() as usize);
+        fs::File::open(path)?
+            .take(self.policy.max_file_size + 1)
+            .read_to_end(&mut content)?;
+        if content.len() as u64 > self.policy.max_file_size {
+            return Err(ActionError::PolicyViolation(format!(
+                "File grew beyond retrieval limit of {} bytes while reading",
+                self.policy.max_file_size
+            )));
+        }
+
+        let sha256 = format!("{:x}", Sha256::digest(&content));
+        if let Some(expected) = &request.expected_sha256 {
+            if !expected.eq_ignore_ascii_case(&sha256) {
+                warn!("Hash mismatch for {}: expected {}, got {}", path.display(), expected, sha256);
+                return Err(ActionError::Integrity(format!(
+                    "SHA-256 mismatch: expected {}, got {}", expected, sha256
+                )));
+            }
+        }
+
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let retrieved = RetrievedFileBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("response"))
+            .related_ids(request.related_ids.clone())
+            .request_id(request.request_id.clone())
+            .requested_by(request.requested_by.clone())
+            .path(request.path.clone())
+            .file_name(file_name)
+            .file_size(content.len() as u64)
+            .sha256(sha256)
+            .content_base64(STANDARD.encode(&content))
+            .build()
+            .map_err(ActionError::InvalidRequest)?;
+
+        info!("Retrieved {} ({} bytes, sha256 {})", retrieved.path, retrieved.file_size, retrieved.sha256);
+        Ok(retrieved)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FetchFileParameters {
+    path: String,
+    expected_sha256: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResolvePseudonymParameters {
+    pseudonyms: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ActionOutput {
+    FileRetrieved(Box<RetrievedFile>),
+    // Entries for the pseudonyms this agent has issued; unknown ones are left out
+    PseudonymsResolved(Vec<PseudonymEntry>),
+}
+
+impl ActionOutput {
+    pub fn action(&self) -> ResponseActionType {
+        match self {
+            ActionOutput::FileRetrieved(_) => ResponseActionType::FetchFile,
+            ActionOutput::PseudonymsResolved(_) => ResponseActionType::ResolvePseudonym,
+        }
+    }
+}
+
+// Every response action goes through here so it is authorized and audited. The control
+// server runs its ExecuteAction calls here; the audit records and retrieved files wait in a
+// queue the agent drains into the action_audit and retrieved_files indices.
+pub struct ResponseActionExecutor {
+    authorizer: Mutex<ActionAuthorizer>,
+    retriever: FileRetriever,
+    retrieved: Mutex<Vec<RetrievedFile>>,
+    changed: Notify,
+}
+
+impl ResponseActionExecutor {
+    pub fn new() -> Self {
+        Self::with_config(ResponseConfig::load())
+    }
+
+    pub fn with_config(config: ResponseConfig) -> Self {
+        Self {
+            authorizer: Mutex::new(ActionAuthorizer::new(config.authorization)),
+            retriever: FileRetriever::with_policy(config.fetch_file),
+            retrieved: Mutex::new(Vec::new()),
+            changed: Notify::new(),
+        }
+    }
+
+    pub fn global() -> Arc<ResponseActionExecutor> {
+        static GLOBAL: OnceLock<Arc<ResponseActionExecutor>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(ResponseActionExecutor::new())).clone()
+    }
+
+    fn with_authorizer<T>(&self, f: impl FnOnce(&mut ActionAuthorizer) -> T) -> Result<T, ActionError> {
+        let mut authorizer = self
+            .authorizer
+            .lock()
+            .map_err(|_| ActionError::PolicyViolation("Action authorizer is unavailable".to_string()))?;
+        Ok(f(&mut authorizer))
+    }
+
+    pub fn execute(&self, request: &SignedActionRequest) -> Result<ActionOutput, ActionError> {
+        if let Err(e) = self.with_authorizer(|authorizer| authorizer.authorize(request))? {
+            self.changed.notify_one();
+            return Err(e);
+        }
+
+        let result = self.dispatch(request);
+        self.with_authorizer(|authorizer| authorizer.record_result(request, result.as_ref().map(|_| ())))?;
+        if let (Ok(ActionOutput::FileRetrieved(file)), Ok(mut retrieved)) = (&result, self.retrieved.lock()) {
+            retrieved.push(file.as_ref().clone());
+        }
+        self.changed.notify_one();
+        result
+    }
+
+    // A request as the control channel carries it, in JSON. One that does not decode is
+    // denied and audited with whatever of it could be read.
+    pub fn execute_json(&self, request: &str) -> Result<ActionOutput, ActionError> {
+        match serde_json::from_str::<SignedActionRequest>(request) {
+            Ok(request) => self.execute(&request),
+            Err(e) => {
+                let error = ActionError::InvalidRequest(format!("Undecodable action request: {}", e));
+                let raw = serde_json::from_str(request).unwrap_or(serde_json::Value::Null);
+                self.with_authorizer(|authorizer| authorizer.record_undecodable(&raw, &error))?;
+                self.changed.notify_one();
+                Err(error)
+            }
+        }
+    }
+
+    fn dispatch(&self, request: &SignedActionRequest) -> Result<ActionOutput, ActionError> {
+        match request.action {
+            ResponseActionType::FetchFile => {
+                let params: FetchFileParameters = serde_json::from_value(request.parameters.clone())
+                    .map_err(|e| ActionError::InvalidRequest(format!("Invalid fetch_file parameters: {}", e)))?;
+                let retrieval = FileRetrievalRequest {
+                    request_id: request.request_id.clone(),
+                    related_ids: request.related_ids(),
+                    path: params.path,
+                    expected_sha256: params.expected_sha256,
+                    requested_by: request.requested_by.clone(),
+                };
+                self.retriever.retrieve(&retrieval).map(|file| ActionOutput::FileRetrieved(Box::new(file)))
+            }
+            ResponseActionType::ResolvePseudonym => {
+                let params: ResolvePseudonymParameters = serde_json::from_value(request.parameters.clone())
+                    .map_err(|e| ActionError::InvalidRequest(format!("Invalid resolve_pseudonym parameters: {}", e)))?;
+                if params.pseudonyms.is_empty() {
+                    return Err(ActionError::InvalidRequest("pseudonyms cannot be empty".to_string()));
+                }
+                let pseudonymizer = Pseudonymizer::global();
+                let entries: Vec<PseudonymEntry> = params
+                    .pseudonyms
+                    .iter()
+                    .filter_map(|pseudonym| pseudonymizer.resolve(pseudonym))
+                    .collect();
+                info!(
+                    "Resolved {} of {} pseudonyms for {}",
+                    entries.len(), params.pseudonyms.len(), request.requested_by
+                );
+                Ok(ActionOutput::PseudonymsResolved(entries))
+            }
+        }
+    }
+
+    // Resolves once an action has left audit records or files to store
+    pub async fn changed(&self) {
+        self.changed.notified().await
+    }
+
+    pub fn drain_audit_events(&self) -> Vec<ActionAuditEvent> {
+        self.with_authorizer(|authorizer| authorizer.drain_audit_events()).unwrap_or_default()
+    }
+
+    pub fn drain_retrieved_files(&self) -> Vec<RetrievedFile> {
+        self.retrieved.lock().map(|mut retrieved| std::mem::take(&mut *retrieved)).unwrap_or_default()
+    }
+}
+
+impl Default for ResponseActionExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
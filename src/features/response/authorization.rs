@@ -0,0 +1,210 @@
+use crate::shared::error::ActionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::features::response::models::{
+    ActionAuditEvent, ActionAuditEventBuilder, AuditOutcome, ResponseActionType, SignedActionRequest,
+};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthorizationPolicy {
+    pub agent_id: Option<String>,
+    pub signing_key_env: String,
+    pub max_validity_secs: i64,
+    pub clock_skew_secs: i64,
+    pub roles: HashMap<String, Vec<ResponseActionType>>,
+}
+
+impl Default for AuthorizationPolicy {
+    fn default() -> Self {
+        Self {
+            agent_id: None,
+            signing_key_env: String::from("LSEDR_ACTION_KEY"),
+            max_validity_secs: 900,
+            clock_skew_secs: 60,
+            roles: HashMap::new(),
+        }
+    }
+}
+
+pub struct ActionAuthorizer {
+    policy: AuthorizationPolicy,
+    signing_key: Option<Vec<u8>>,
+    agent_id: String,
+    host: Arc<HostIdentityService>,
+    seen_nonces: HashMap<String, DateTime<Utc>>,
+    audit_events: Vec<ActionAuditEvent>,
+}
+
+impl ActionAuthorizer {
+    pub fn new(policy: AuthorizationPolicy) -> Self {
+        let host = HostIdentityService::global();
+        let agent_id = policy.agent_id.clone().unwrap_or_else(|| host.name());
+        let signing_key = std::env::var(&policy.signing_key_env)
+            .ok()
+            .filter(|key| !key.is_empty())
+            .map(String::into_bytes);
+
+        if signing_key.is_none() {
+            warn!(
+                "No action signing key found in ${}, all response actions will be denied",
+                policy.signing_key_env
+            );
+        }
+
+        Self {
+            policy,
+            signing_key,
+            agent_id,
+            host,
+            seen_nonces: HashMap::new(),
+            audit_events: Vec::new(),
+        }
+    }
+
+    pub fn agent_id(&self) -> &str {
+        &self.agent_id
+    }
+
+    fn verify_signature(&self, request: &SignedActionRequest) -> Result<(), ActionError> {
+        let key = self.signing_key.as_ref().ok_or_else(|| {
+            ActionError::PolicyViolation("Agent has no action signing key configured".to_string())
+        })?;
+
+        let signature = hex::decode(&request.signature)
+            .map_err(|_| ActionError::InvalidRequest("Signature is not valid hex".to_string()))?;
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(key)
+            .map_err(|e| ActionError::InvalidRequest(e.to_string()))?;
+        mac.update(request.signing_payload().as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| ActionError::PolicyViolation("Invalid request signature".to_string()))
+    }
+
+    fn check(&mut self, request: &SignedActionRequest) -> Result<(), ActionError> {
+        self.verify_signature(request)?;
+
+        if request.agent_id != self.agent_id {
+            return Err(ActionError::PolicyViolation(format!(
+                "Request is bound to agent '{}', not '{}'", request.agent_id, self.agent_id
+            )));
+        }
+
+        let now = Utc::now();
+        let skew = Duration::seconds(self.policy.clock_skew_secs);
+        if request.expires_at < now - skew {
+            return Err(ActionError::PolicyViolation("Request has expired".to_string()));
+        }
+        if request.issued_at > now + skew {
+            return Err(ActionError::PolicyViolation("Request is issued in the future".to_string()));
+        }
+        if request.expires_at - request.issued_at > Duration::seconds(self.policy.max_validity_secs) {
+            return Err(ActionError::PolicyViolation(format!(
+                "Request validity exceeds {} seconds", self.policy.max_validity_secs
+            )));
+        }
+
+        let permitted = self
+            .policy
+            .roles
+            .get(&request.role)
+            .map(|actions| actions.contains(&request.action))
+            .unwrap_or(false);
+        if !permitted {
+            return Err(ActionError::PolicyViolation(format!(
+                "Role '{}' is not permitted to run '{}'", request.role, request.action.as_str()
+            )));
+        }
+
+        // Reject replays of a nonce until the original request would have expired anyway
+        self.seen_nonces.retain(|_, expires_at| *expires_at + skew >= now);
+        if self.seen_nonces.contains_key(&request.nonce) {
+            return Err(ActionError::PolicyViolation("Request nonce has already been used".to_string()));
+        }
+        self.seen_nonces.insert(request.nonce.clone(), request.expires_at);
+
+        Ok(())
+    }
+
+    pub fn authorize(&mut self, request: &SignedActionRequest) -> Result<(), ActionError> {
+        let result = self.check(request);
+        match &result {
+            Ok(()) => info!(
+                "Authorized '{}' request {} from {} ({})",
+                request.action.as_str(), request.request_id, request.requested_by, request.role
+            ),
+            Err(e) => {
+                warn!("Denied '{}' request {}: {}", request.action.as_str(), request.request_id, e);
+                self.record(request, AuditOutcome::Denied, Some(e.to_string()));
+            }
+        }
+        result
+    }
+
+    pub fn record_result(&mut self, request: &SignedActionRequest, result: Result<(), &ActionError>) {
+        match result {
+            Ok(()) => self.record(request, AuditOutcome::Succeeded, None),
+            Err(e) => self.record(request, AuditOutcome::Failed, Some(e.to_string())),
+        }
+    }
+
+    fn record(&mut self, request: &SignedActionRequest, outcome: AuditOutcome, reason: Option<String>) {
+        let mut builder = self
+            .audit_builder()
+            .related_ids(request.related_ids())
+            .request_id(request.request_id.clone())
+            .action(request.action)
+            .requested_by(request.requested_by.clone())
+            .role(request.role.clone())
+            .outcome(outcome);
+        if let Some(reason) = reason {
+            builder = builder.reason(reason);
+        }
+        self.push(builder);
+    }
+
+    // A request that never reached authorize because it did not decode; the fields that
+    // can be read from it are kept so the attempt can still be traced
+    pub fn record_undecodable(&mut self, request: &serde_json::Value, error: &ActionError) {
+        warn!("Denied undecodable action request: {}", error);
+        let field = |name: &str| request.get(name).and_then(|value| value.as_str()).unwrap_or_default().to_string();
+        let mut builder = self
+            .audit_builder()
+            .request_id(field("request_id"))
+            .requested_by(field("requested_by"))
+            .role(field("role"))
+            .outcome(AuditOutcome::Denied)
+            .reason(error.to_string());
+        if let Some(action) = request.get("action").and_then(|action| serde_json::from_value(action.clone()).ok()) {
+            builder = builder.action(action);
+        }
+        self.push(builder);
+    }
+
+    fn audit_builder(&self) -> ActionAuditEventBuilder {
+        ActionAuditEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("audit"))
+            .agent_id(self.agent_id.clone())
+    }
+
+    fn push(&mut self, builder: ActionAuditEventBuilder) {
+        match builder.build() {
+            Ok(event) => self.audit_events.push(event),
+            Err(e) => error!("Failed to build action audit event: {}", e),
+        }
+    }
+
+    pub fn drain_audit_events(&mut self) -> Vec<ActionAuditEvent> {
+        std::mem::take(&mut self.audit_events)
+    }
+}
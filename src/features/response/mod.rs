@@ -0,0 +1,12 @@
+pub mod models;
+pub mod actions;
+pub mod authorization;
+
+pub use models::{
+    ResponseActionType, FileRetrievalRequest, RetrievedFile, RetrievedFileBuilder,
+    SignedActionRequest, ActionAuditEvent, AuditOutcome,
+};
+pub use actions::{
+    FileRetriever, FileRetrievalPolicy, ResponseConfig, ResponseActionExecutor, ActionOutput,
+};
+pub use authorization::{ActionAuthorizer, AuthorizationPolicy};
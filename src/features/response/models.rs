@@ -0,0 +1,414 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseActionType {
+    FetchFile,
+    ResolvePseudonym,
+}
+
+impl ResponseActionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResponseActionType::FetchFile => "fetch_file",
+            ResponseActionType::ResolvePseudonym => "resolve_pseudonym",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRetrievalRequest {
+    pub request_id: String,
+    // Alerts the retrieval follows up on
+    #[serde(default)]
+    pub related_ids: Vec<String>,
+    pub path: String,
+    pub expected_sha256: Option<String>,
+    pub requested_by: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrievedFile {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    // Alerts named in the request the file was retrieved for
+    #[serde(default)]
+    pub related_ids: Vec<String>,
+    pub request_id: String,
+    pub requested_by: String,
+    pub path: String,
+    pub file_name: String,
+    pub file_size: u64,
+    pub sha256: String,
+    pub content_base64: String,
+}
+
+impl Event for RetrievedFile {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        "file_retrieved"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Medium
+    }
+}
+
+impl Identifiable for RetrievedFile {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for RetrievedFile {
+    fn validate(&self) -> Result<(), String> {
+        if self.request_id.is_empty() {
+            return Err("Request ID cannot be empty".to_string());
+        }
+        if self.path.is_empty() {
+            return Err("File path cannot be empty".to_string());
+        }
+        if self.sha256.len() != 64 {
+            return Err("SHA-256 digest must be 64 hex characters".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct RetrievedFileBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    related_ids: Vec<String>,
+    request_id: Option<String>,
+    requested_by: Option<String>,
+    path: Option<String>,
+    file_name: Option<String>,
+    file_size: Option<u64>,
+    sha256: Option<String>,
+    content_base64: Option<String>,
+}
+
+impl RetrievedFileBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn related_ids(mut self, related_ids: Vec<String>) -> Self {
+        self.related_ids = related_ids;
+        self
+    }
+
+    pub fn request_id(mut self, request_id: String) -> Self {
+        self.request_id = Some(request_id);
+        self
+    }
+
+    pub fn requested_by(mut self, requested_by: String) -> Self {
+        self.requested_by = Some(requested_by);
+        self
+    }
+
+    pub fn path(mut self, path: String) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn file_name(mut self, file_name: String) -> Self {
+        self.file_name = Some(file_name);
+        self
+    }
+
+    pub fn file_size(mut self, file_size: u64) -> Self {
+        self.file_size = Some(file_size);
+        self
+    }
+
+    pub fn sha256(mut self, sha256: String) -> Self {
+        self.sha256 = Some(sha256);
+        self
+    }
+
+    pub fn content_base64(mut self, content_base64: String) -> Self {
+        self.content_base64 = Some(content_base64);
+        self
+    }
+
+    pub fn build(self) -> Result<RetrievedFile, String> {
+        let file = RetrievedFile {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            related_ids: self.related_ids,
+            request_id: self.request_id.ok_or("request_id is required")?,
+            requested_by: self.requested_by.ok_or("requested_by is required")?,
+            path: self.path.ok_or("path is required")?,
+            file_name: self.file_name.ok_or("file_name is required")?,
+            file_size: self.file_size.ok_or("file_size is required")?,
+            sha256: self.sha256.ok_or("sha256 is required")?,
+            content_base64: self.content_base64.ok_or("content_base64 is required")?,
+        };
+
+        file.validate()?;
+        Ok(file)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedActionRequest {
+    pub request_id: String,
+    pub action: ResponseActionType,
+    pub agent_id: String,
+    pub requested_by: String,
+    pub role: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub nonce: String,
+    pub parameters: serde_json::Value,
+    pub signature: String,
+}
+
+impl SignedActionRequest {
+    // Canonical byte string covered by the HMAC signature: the fields as a JSON array in this
+    // order, so no field's content can pass for a neighbour's
+    pub fn signing_payload(&self) -> String {
+        serde_json::json!([
+            self.request_id,
+            self.action.as_str(),
+            self.agent_id,
+            self.requested_by,
+            self.role,
+            self.issued_at.timestamp(),
+            self.expires_at.timestamp(),
+            self.nonce,
+            self.parameters,
+        ])
+        .to_string()
+    }
+
+    // Alerts the request names in parameters.related_ids, linked from what the action
+    // produces; covered by the signature like every other parameter
+    pub fn related_ids(&self) -> Vec<String> {
+        self.parameters
+            .get("related_ids")
+            .and_then(|ids| ids.as_array())
+            .map(|ids| ids.iter().filter_map(|id| id.as_str()).map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Denied,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionAuditEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    // Alerts named in the request, e.g. the detection a remediation responds to
+    #[serde(default)]
+    pub related_ids: Vec<String>,
+    pub agent_id: String,
+    pub request_id: String,
+    // None for a request that did not decode far enough to name its action
+    pub action: Option<ResponseActionType>,
+    pub requested_by: String,
+    pub role: String,
+    pub outcome: AuditOutcome,
+    pub reason: Option<String>,
+}
+
+impl Event for ActionAuditEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.outcome {
+            AuditOutcome::Denied => "action_denied",
+            AuditOutcome::Succeeded => "action_succeeded",
+            AuditOutcome::Failed => "action_failed",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        match self.outcome {
+            AuditOutcome::Denied => Severity::High,
+            AuditOutcome::Failed => Severity::Medium,
+            AuditOutcome::Succeeded => Severity::Low,
+        }
+    }
+}
+
+impl Identifiable for ActionAuditEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for ActionAuditEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.agent_id.is_empty() {
+            return Err("Agent ID cannot be empty".to_string());
+        }
+        if self.outcome != AuditOutcome::Succeeded && self.reason.is_none() {
+            return Err("Reason is required for denied or failed actions".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct ActionAuditEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    related_ids: Vec<String>,
+    agent_id: Option<String>,
+    request_id: Option<String>,
+    action: Option<ResponseActionType>,
+    requested_by: Option<String>,
+    role: Option<String>,
+    outcome: Option<AuditOutcome>,
+    reason: Option<String>,
+}
+
+impl ActionAuditEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn agent_id(mut self, agent_id: String) -> Self {
+        self.agent_id = Some(agent_id);
+        self
+    }
+
+    pub fn related_ids(mut self, related_ids: Vec<String>) -> Self {
+        self.related_ids = related_ids;
+        self
+    }
+
+    pub fn request_id(mut self, request_id: String) -> Self {
+        self.request_id = Some(request_id);
+        self
+    }
+
+    pub fn action(mut self, action: ResponseActionType) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn requested_by(mut self, requested_by: String) -> Self {
+        self.requested_by = Some(requested_by);
+        self
+    }
+
+    pub fn role(mut self, role: String) -> Self {
+        self.role = Some(role);
+        self
+    }
+
+    pub fn outcome(mut self, outcome: AuditOutcome) -> Self {
+        self.outcome = Some(outcome);
+        self
+    }
+
+    pub fn reason(mut self, reason: String) -> Self {
+        self.reason = Some(reason);
+        self
+    }
+
+    pub fn build(self) -> Result<ActionAuditEvent, String> {
+        let event = ActionAuditEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            related_ids: self.related_ids,
+            agent_id: self.agent_id.ok_or("agent_id is required")?,
+            request_id: self.request_id.ok_or("request_id is required")?,
+            action: self.action,
+            requested_by: self.requested_by.ok_or("requested_by is required")?,
+            role: self.role.ok_or("role is required")?,
+            outcome: self.outcome.ok_or("outcome is required")?,
+            reason: self.reason,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
@@ -0,0 +1,376 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector};
+use crate::shared::command;
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::patterns::{PatternSet, DEFAULT_PATTERNS};
+use crate::features::scheduled_tasks::models::{
+    ScheduledTask, ScheduledTaskChange, ScheduledTaskEvent, ScheduledTaskEventBuilder, ScheduledTaskKind,
+};
+use crate::features::scheduled_tasks::parsers::{
+    exec_start_command, parse_crontab, parse_schtasks_csv, parse_systemctl_show, property,
+};
+use crate::utils::load_config_section;
+use chrono::Utc;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+use which::which;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduledTaskConfig {
+    pub enabled: bool,
+    // Emit a full snapshot every N collections; diffs are emitted in between
+    pub snapshot_every: u32,
+    pub cron_files: Vec<String>,
+    pub cron_directories: Vec<String>,
+    // Per-user crontabs, named after their owner
+    pub user_cron_directories: Vec<String>,
+    pub include_systemd_timers: bool,
+    // Regexes (case-insensitive) that mark a task command as suspicious; also matched
+    // against base64 and hex found in the command
+    pub suspicious_patterns: Vec<String>,
+}
+
+impl Default for ScheduledTaskConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            snapshot_every: 60,
+            cron_files: vec![String::from("/etc/crontab")],
+            cron_directories: vec![String::from("/etc/cron.d")],
+            user_cron_directories: vec![
+                String::from("/var/spool/cron/crontabs"),
+                String::from("/var/spool/cron"),
+            ],
+            include_systemd_timers: true,
+            suspicious_patterns: [
+                r"(curl|wget)\s[^|]*\|\s*(ba|z|da)?sh",
+                r"base64\s+(-d|--decode)",
+                r"/dev/tcp/",
+                r"\bnc(at)?\b.*\s-e\s",
+                r"(^|[\s/])(tmp|dev/shm|var/tmp)/",
+                r"powershell(\.exe)?.*\s-(e|en|enc|encodedcommand)\s",
+                r"powershell(\.exe)?.*-w(indowstyle)?\s+hidden",
+                r"(iex|invoke-expression|downloadstring|downloadfile)",
+                r"\\appdata\\(local\\temp|roaming)\\",
+                r"\b(mshta|regsvr32|rundll32|certutil|bitsadmin)(\.exe)?\b",
+            ]
+            .iter()
+            .chain(DEFAULT_PATTERNS)
+            .map(|pattern| pattern.to_string())
+            .collect(),
+        }
+    }
+}
+
+impl ScheduledTaskConfig {
+    pub fn load() -> Self {
+        load_config_section("scheduled_tasks")
+    }
+}
+
+// Enumerates Windows Task Scheduler entries and Linux cron / systemd timers. The
+// first collection (and every `snapshot_every`-th after it) reports every task,
+// other collections report only what was created, modified or deleted.
+pub struct ScheduledTaskCollector {
+    config: ScheduledTaskConfig,
+    patterns: PatternSet,
+    known: HashMap<(ScheduledTaskKind, String, String), ScheduledTask>,
+    collections: u32,
+    host: Arc<HostIdentityService>,
+}
+
+impl ScheduledTaskCollector {
+    pub fn new() -> Self {
+        Self::with_config(ScheduledTaskConfig::load())
+    }
+
+    pub fn with_config(config: ScheduledTaskConfig) -> Self {
+        let patterns = PatternSet::compile_regexes(&config.suspicious_patterns);
+
+        Self {
+            config,
+            patterns,
+            known: HashMap::new(),
+            collections: 0,
+            host: HostIdentityService::global(),
+        }
+    }
+
+    fn enumerate(&self) -> Vec<ScheduledTask> {
+        if cfg!(target_os = "windows") {
+            self.windows_tasks()
+        } else if cfg!(target_os = "linux") {
+            let mut tasks = self.cron_tasks();
+            if self.config.include_systemd_timers {
+                tasks.extend(self.systemd_timers());
+            }
+            tasks
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn windows_tasks(&self) -> Vec<ScheduledTask> {
+        match command::run("schtasks", ["/query", "/fo", "csv", "/v", "/nh"]) {
+            Ok(output) => {
+                let mut tasks = parse_schtasks_csv(&output.stdout);
+                for task in &mut tasks {
+                    if let Some(enabled) = Self::task_definition_enabled(task) {
+                        task.enabled = Some(enabled);
+                    }
+                }
+                tasks
+            }
+            Err(e) => {
+                error!("Failed to execute schtasks command: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    // The schtasks status column is translated, so the enabled flag is read from the task's
+    // XML definition (UTF-16) under System32\Tasks instead; None if it cannot be read
+    fn task_definition_enabled(task: &ScheduledTask) -> Option<bool> {
+        let system_root = std::env::var("SystemRoot").unwrap_or_else(|_| String::from(r"C:\Windows"));
+        let mut path = Path::new(&system_root).join("System32").join("Tasks");
+        for component in task.origin.split('\\').filter(|component| !component.is_empty()) {
+            path.push(component);
+        }
+        path.push(&task.name);
+
+        let definition = command::decode(&fs::read(path).ok()?);
+        let settings = definition.split_once("<Settings>")?.1.split_once("</Settings>")?.0;
+        // Enabled defaults to true when the element is absent
+        let enabled = settings
+            .split_once("<Enabled>")
+            .and_then(|(_, rest)| rest.split_once("</Enabled>"))
+            .is_none_or(|(value, _)| value.trim() != "false");
+        Some(enabled)
+    }
+
+    fn cron_tasks(&self) -> Vec<ScheduledTask> {
+        let mut tasks = Vec::new();
+        let mut read = |path: &Path, owner: Option<&str>| {
+            if let Ok(content) = fs::read_to_string(path) {
+                tasks.extend(parse_crontab(&content, &path.to_string_lossy(), owner));
+            }
+        };
+
+        for file in &self.config.cron_files {
+            read(Path::new(file), None);
+        }
+        for directory in &self.config.cron_directories {
+            for path in Self::files_in(directory) {
+                read(&path, None);
+            }
+        }
+        for directory in &self.config.user_cron_directories {
+            for path in Self::files_in(directory) {
+                let owner = path.file_name().map(|name| name.to_string_lossy().into_owned());
+                read(&path, owner.as_deref());
+            }
+        }
+
+        tasks
+    }
+
+    fn files_in(directory: &str) -> Vec<std::path::PathBuf> {
+        match fs::read_dir(directory) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn systemctl(args: &[&str]) -> Option<String> {
+        let systemctl = which("systemctl").ok()?;
+        match command::run(systemctl, args) {
+            Ok(output) => Some(output.stdout),
+            Err(e) => {
+                error!("Failed to execute systemctl command: {}", e);
+                None
+            }
+        }
+    }
+
+    fn systemd_timers(&self) -> Vec<ScheduledTask> {
+        let Some(listing) = Self::systemctl(&["list-units", "--type=timer", "--all", "--no-legend", "--plain", "--no-pager"]) else {
+            return Vec::new();
+        };
+        let timers: Vec<&str> = listing
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .filter(|unit| unit.ends_with(".timer"))
+            .collect();
+        if timers.is_empty() {
+            return Vec::new();
+        }
+
+        let mut args = vec!["show", "-p", "Id", "-p", "Unit", "-p", "TimersCalendar", "-p", "TimersMonotonic", "-p", "UnitFileState"];
+        args.extend(timers.iter().copied());
+        let timer_properties = parse_systemctl_show(&Self::systemctl(&args).unwrap_or_default());
+
+        let services: Vec<String> = timer_properties
+            .iter()
+            .filter_map(|properties| property(properties, "Unit").map(str::to_string))
+            .collect();
+        let mut args = vec!["show", "-p", "Id", "-p", "ExecStart", "-p", "User"];
+        args.extend(services.iter().map(String::as_str));
+        let service_properties: HashMap<String, Vec<(String, String)>> =
+            parse_systemctl_show(&Self::systemctl(&args).unwrap_or_default())
+                .into_iter()
+                .filter_map(|properties| Some((property(&properties, "Id")?.to_string(), properties)))
+                .collect();
+
+        timer_properties
+            .iter()
+            .filter_map(|properties| {
+                let name = property(properties, "Id")?.to_string();
+                let unit = property(properties, "Unit").unwrap_or_default();
+                let service = service_properties.get(unit);
+                let schedule = [property(properties, "TimersCalendar"), property(properties, "TimersMonotonic")]
+                    .into_iter()
+                    .flatten()
+                    .filter(|value| !value.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                Some(ScheduledTask {
+                    kind: ScheduledTaskKind::SystemdTimer,
+                    name,
+                    command: service
+                        .and_then(|service| property(service, "ExecStart"))
+                        .and_then(exec_start_command)
+                        .unwrap_or_else(|| unit.to_string()),
+                    schedule,
+                    user: service
+                        .and_then(|service| property(service, "User"))
+                        .filter(|user| !user.is_empty())
+                        .map(str::to_string)
+                        .or_else(|| Some(String::from("root"))),
+                    enabled: property(properties, "UnitFileState").map(|state| state == "enabled"),
+                    origin: unit.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn suspicious_reasons(&self, task: &ScheduledTask) -> Vec<String> {
+        self.patterns
+            .matches(&task.command)
+            .iter()
+            .map(|matched| format!("command matches {}", matched.describe()))
+            .collect()
+    }
+
+    fn event(&self, change: ScheduledTaskChange, task: ScheduledTask, previous: Option<&ScheduledTask>) -> Result<ScheduledTaskEvent, CollectionError> {
+        let reasons = self.suspicious_reasons(&task);
+        let mut builder = ScheduledTaskEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("scheduled_tasks"))
+            .change(change)
+            .task(task)
+            .suspicious_reasons(reasons);
+        if let Some(previous) = previous {
+            builder = builder.previous_command(previous.command.clone());
+        }
+        builder.build().map_err(CollectionError::Parse)
+    }
+
+    fn collect_tasks(&mut self) -> Result<Vec<ScheduledTaskEvent>, CollectionError> {
+        let current: HashMap<_, _> = self
+            .enumerate()
+            .into_iter()
+            .map(|task| (task.key(), task))
+            .collect();
+
+        let snapshot = self.collections % self.config.snapshot_every.max(1) == 0;
+        self.collections = self.collections.wrapping_add(1);
+
+        let mut events = Vec::new();
+        for (key, task) in &current {
+            match self.known.get(key) {
+                // The first collection is the baseline, nothing in it counts as new
+                None if self.collections > 1 => {
+                    events.push(self.event(ScheduledTaskChange::Created, task.clone(), None)?);
+                }
+                Some(previous) if previous != task => {
+                    events.push(self.event(ScheduledTaskChange::Modified, task.clone(), Some(previous))?);
+                }
+                _ if snapshot => {
+                    events.push(self.event(ScheduledTaskChange::Snapshot, task.clone(), None)?);
+                }
+                _ => {}
+            }
+        }
+        for (key, task) in &self.known {
+            if !current.contains_key(key) {
+                events.push(self.event(ScheduledTaskChange::Deleted, task.clone(), None)?);
+            }
+        }
+
+        let suspicious = events.iter().filter(|event| event.suspicious).count();
+        if suspicious > 0 {
+            warn!("{} scheduled tasks have suspicious commands", suspicious);
+        }
+        info!("Found {} scheduled tasks, {} events", current.len(), events.len());
+
+        self.known = current;
+        Ok(events)
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if cfg!(target_os = "windows") && which("schtasks").is_err() {
+            return Err(CollectionError::SystemApi(
+                "schtasks command not found".to_string()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl DataCollector<Vec<ScheduledTaskEvent>> for ScheduledTaskCollector {
+    fn collect(&mut self) -> Result<Vec<ScheduledTaskEvent>, CollectionError> {
+        self.collect_tasks()
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<ScheduledTaskEvent>> for ScheduledTaskCollector {
+    async fn collect(&mut self) -> Result<Vec<ScheduledTaskEvent>, CollectionError> {
+        DataCollector::collect(self)
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+impl Default for ScheduledTaskCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
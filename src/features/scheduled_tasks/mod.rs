@@ -0,0 +1,8 @@
+mod collector;
+mod models;
+mod parsers;
+
+pub use collector::{ScheduledTaskCollector, ScheduledTaskConfig};
+pub use models::{
+    ScheduledTask, ScheduledTaskChange, ScheduledTaskEvent, ScheduledTaskEventBuilder, ScheduledTaskKind,
+};
@@ -0,0 +1,197 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduledTaskKind {
+    WindowsTask,
+    Cron,
+    SystemdTimer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduledTaskChange {
+    Snapshot,
+    Created,
+    Modified,
+    Deleted,
+}
+
+// One scheduled entry as enumerated from the OS, before it becomes an event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    pub kind: ScheduledTaskKind,
+    pub name: String,
+    pub command: String,
+    pub schedule: String,
+    pub user: Option<String>,
+    pub enabled: Option<bool>,
+    // File or Task Scheduler folder the entry was read from
+    pub origin: String,
+}
+
+impl ScheduledTask {
+    pub fn key(&self) -> (ScheduledTaskKind, String, String) {
+        (self.kind, self.origin.clone(), self.name.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledTaskEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub change: ScheduledTaskChange,
+    pub kind: ScheduledTaskKind,
+    pub name: String,
+    pub command: String,
+    pub schedule: String,
+    pub user: Option<String>,
+    pub enabled: Option<bool>,
+    pub origin: String,
+    pub previous_command: Option<String>,
+    pub suspicious: bool,
+    pub suspicious_reasons: Vec<String>,
+}
+
+impl Event for ScheduledTaskEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.change {
+            ScheduledTaskChange::Snapshot => "scheduled_task_snapshot",
+            ScheduledTaskChange::Created => "scheduled_task_created",
+            ScheduledTaskChange::Modified => "scheduled_task_modified",
+            ScheduledTaskChange::Deleted => "scheduled_task_deleted",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        match self.change {
+            ScheduledTaskChange::Created | ScheduledTaskChange::Modified if self.suspicious => Severity::Critical,
+            ScheduledTaskChange::Created | ScheduledTaskChange::Modified => Severity::Medium,
+            ScheduledTaskChange::Snapshot if self.suspicious => Severity::Medium,
+            ScheduledTaskChange::Deleted | ScheduledTaskChange::Snapshot => Severity::Low,
+        }
+    }
+}
+
+impl Identifiable for ScheduledTaskEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for ScheduledTaskEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("Task name cannot be empty".to_string());
+        }
+
+        if self.origin.is_empty() {
+            return Err("Task origin cannot be empty".to_string());
+        }
+
+        if self.suspicious && self.suspicious_reasons.is_empty() {
+            return Err("Suspicious tasks must carry at least one reason".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct ScheduledTaskEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    change: Option<ScheduledTaskChange>,
+    task: Option<ScheduledTask>,
+    previous_command: Option<String>,
+    suspicious_reasons: Vec<String>,
+}
+
+impl ScheduledTaskEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn change(mut self, change: ScheduledTaskChange) -> Self {
+        self.change = Some(change);
+        self
+    }
+
+    pub fn task(mut self, task: ScheduledTask) -> Self {
+        self.task = Some(task);
+        self
+    }
+
+    pub fn previous_command(mut self, previous_command: String) -> Self {
+        self.previous_command = Some(previous_command);
+        self
+    }
+
+    pub fn suspicious_reasons(mut self, suspicious_reasons: Vec<String>) -> Self {
+        self.suspicious_reasons = suspicious_reasons;
+        self
+    }
+
+    pub fn build(self) -> Result<ScheduledTaskEvent, String> {
+        let task = self.task.ok_or("task is required")?;
+        let event = ScheduledTaskEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            change: self.change.ok_or("change is required")?,
+            kind: task.kind,
+            name: task.name,
+            command: task.command,
+            schedule: task.schedule,
+            user: task.user,
+            enabled: task.enabled,
+            origin: task.origin,
+            previous_command: self.previous_command,
+            suspicious: !self.suspicious_reasons.is_empty(),
+            suspicious_reasons: self.suspicious_reasons,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
@@ -0,0 +1,164 @@
+use crate::features::scheduled_tasks::models::{ScheduledTask, ScheduledTaskKind};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+// Column positions in `schtasks /query /fo csv /v /nh`; headers are localized but the order is not
+const SCHTASKS_TASK_NAME: usize = 1;
+const SCHTASKS_TASK_TO_RUN: usize = 8;
+const SCHTASKS_STATE: usize = 11;
+const SCHTASKS_RUN_AS_USER: usize = 14;
+const SCHTASKS_SCHEDULE_TYPE: usize = 18;
+
+// Minimal RFC 4180 line splitter: quoted fields, doubled quotes as escapes
+pub fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+// One task can appear on several rows (one per trigger); the first row wins
+pub fn parse_schtasks_csv(output: &str) -> Vec<ScheduledTask> {
+    let mut tasks = Vec::new();
+    let mut seen = HashSet::new();
+
+    for line in output.lines().filter(|line| !line.trim().is_empty()) {
+        let fields = split_csv_line(line);
+        if fields.len() <= SCHTASKS_SCHEDULE_TYPE {
+            continue;
+        }
+        let full_name = fields[SCHTASKS_TASK_NAME].trim();
+        if !full_name.starts_with('\\') || !seen.insert(full_name.to_string()) {
+            continue;
+        }
+
+        let (folder, name) = match full_name.rfind('\\') {
+            Some(0) => ("\\", &full_name[1..]),
+            Some(split) => (&full_name[..split], &full_name[split + 1..]),
+            None => ("\\", full_name),
+        };
+        let state = fields[SCHTASKS_STATE].trim();
+        let user = fields[SCHTASKS_RUN_AS_USER].trim();
+
+        tasks.push(ScheduledTask {
+            kind: ScheduledTaskKind::WindowsTask,
+            name: name.to_string(),
+            command: fields[SCHTASKS_TASK_TO_RUN].trim().to_string(),
+            schedule: fields[SCHTASKS_SCHEDULE_TYPE].trim().to_string(),
+            user: if user.is_empty() { None } else { Some(user.to_string()) },
+            // Only the English "Disabled" is recognized here; the collector corrects this
+            // from the task definition on other display languages
+            enabled: Some(!state.eq_ignore_ascii_case("disabled")),
+            origin: folder.to_string(),
+        });
+    }
+
+    tasks
+}
+
+// Parses a crontab. System crontabs (/etc/crontab, /etc/cron.d) carry a user column,
+// per-user spool crontabs take the owner from the file name.
+pub fn parse_crontab(content: &str, origin: &str, owner: Option<&str>) -> Vec<ScheduledTask> {
+    let mut tasks = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Environment assignments such as PATH=/usr/bin or MAILTO=""
+        if let Some(equals) = line.find('=') {
+            let before = &line[..equals];
+            if !before.contains(char::is_whitespace) {
+                continue;
+            }
+        }
+
+        let schedule_fields = if line.starts_with('@') { 1 } else { 5 };
+        let user_fields = if owner.is_none() { 1 } else { 0 };
+        let Some((leading, command)) = split_leading(line, schedule_fields + user_fields) else {
+            continue;
+        };
+        let command = command.trim().to_string();
+        if command.is_empty() {
+            continue;
+        }
+        let user = match owner {
+            Some(owner) => Some(owner.to_string()),
+            None => leading.last().map(|user| user.to_string()),
+        };
+
+        // Cron lines have no name; identify them by content so reordering a file is not a change
+        let schedule = leading[..schedule_fields].join(" ");
+        let digest = Sha256::digest(format!("{}|{:?}|{}", schedule, user, command).as_bytes());
+
+        tasks.push(ScheduledTask {
+            kind: ScheduledTaskKind::Cron,
+            name: format!("cron-{}", &hex::encode(digest)[..12]),
+            command,
+            schedule,
+            user,
+            enabled: Some(true),
+            origin: origin.to_string(),
+        });
+    }
+
+    tasks
+}
+
+// First `count` whitespace separated tokens and the untouched remainder of the line
+fn split_leading(line: &str, count: usize) -> Option<(Vec<&str>, &str)> {
+    let mut tokens = Vec::with_capacity(count);
+    let mut rest = line;
+    for _ in 0..count {
+        rest = rest.trim_start();
+        let end = rest.find(char::is_whitespace)?;
+        tokens.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    Some((tokens, rest))
+}
+
+// Splits `systemctl show` output for several units into per-unit property maps
+pub fn parse_systemctl_show(output: &str) -> Vec<Vec<(String, String)>> {
+    output
+        .split("\n\n")
+        .map(|block| {
+            block
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|properties| !properties.is_empty())
+        .collect()
+}
+
+// `{ path=/usr/bin/foo ; argv[]=/usr/bin/foo --bar ; ignore_errors=no ; ... }` -> "/usr/bin/foo --bar"
+pub fn exec_start_command(exec_start: &str) -> Option<String> {
+    let start = exec_start.find("argv[]=")? + "argv[]=".len();
+    let rest = &exec_start[start..];
+    let end = rest.find(" ;").unwrap_or(rest.len());
+    Some(rest[..end].trim().to_string())
+}
+
+pub fn property<'a>(properties: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    properties
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.as_str())
+}
@@ -1,138 +1,250 @@
 use crate::shared::traits::{AsyncDataCollector, DataCollector};
+use crate::shared::baseline::Baseline;
+use crate::shared::command;
 use crate::shared::error::CollectionError;
-use crate::features::service::models::{ServiceInformation, ServiceInformationBuilder};
-use encoding_rs::GBK;
+use crate::shared::host_identity::HostIdentityService;
+use crate::features::service::models::{
+    InstalledService, ServiceChange, ServiceChangeEvent, ServiceChangeEventBuilder, ServiceInformation,
+    ServiceInformationBuilder,
+};
+use crate::features::service::parsers::{self, ServiceEntry, UnitDetails};
 use log::{error, info, warn};
-use regex::Regex;
-use std::process::Command;
 use which::which;
 use chrono::Utc;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
 use uuid::Uuid;
 
+// Windows services come from the service control manager rather than from `sc query`, whose
+// text is localized and leaves out the configuration
+#[cfg(windows)]
+use crate::features::service::win32::services as windows_services;
+
+#[cfg(not(windows))]
+fn windows_services() -> Result<Vec<ServiceEntry>, CollectionError> {
+    Ok(Vec::new())
+}
+
+// launchd jobs are read from their property lists, which `launchctl list` does not show
+#[cfg(target_os = "macos")]
+use crate::features::service::launchd::services as launchd_services;
+
+#[cfg(not(target_os = "macos"))]
+fn launchd_services() -> Result<Vec<ServiceEntry>, CollectionError> {
+    Ok(Vec::new())
+}
+
+// Units described by one `systemctl show`, keeping its command line well under ARG_MAX
+const SHOW_BATCH: usize = 100;
+
+// Startup type of a service whose configuration could not be read
+const UNKNOWN_STARTUP_TYPE: &str = "Unknown";
+
 pub struct ServiceCollector {
-    hostname: String,
+    host: Arc<HostIdentityService>,
+    // Services of the previous collection by name, kept across restarts so a service
+    // installed while the agent was down is still reported
+    known: HashMap<String, InstalledService>,
+    changes: Vec<ServiceChangeEvent>,
 }
 
 impl ServiceCollector {
+    const SERVICES_BASELINE: &'static str = "services";
+
     pub fn new() -> Self {
         Self {
-            hostname: whoami::hostname(),
+            host: HostIdentityService::global(),
+            known: Baseline::global().load(Self::SERVICES_BASELINE).unwrap_or_default(),
+            changes: Vec::new(),
         }
     }
 
-    fn collect_services(&self) -> Result<Vec<ServiceInformation>, CollectionError> {
-        let mut services = Vec::new();
-        
+    // Installed / removed / reconfigured events found by the last collections, cleared on read
+    pub fn take_changes(&mut self) -> Vec<ServiceChangeEvent> {
+        std::mem::take(&mut self.changes)
+    }
+
+    fn service(&self, entry: ServiceEntry) -> Result<ServiceInformation, CollectionError> {
+        let mut builder = ServiceInformationBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("service"))
+            .name(entry.name)
+            .display_name(entry.display_name)
+            .status(entry.state)
+            .startup_type(entry.startup_type.unwrap_or_else(|| String::from(UNKNOWN_STARTUP_TYPE)))
+            .binary_path(entry.binary_path)
+            .account(entry.account)
+            .unit_file(entry.unit_file)
+            .dependencies(entry.dependencies);
+        if let Some(pid) = entry.process_id {
+            builder = builder.process_id(pid);
+        }
+        builder.build().map_err(CollectionError::Parse)
+    }
+
+    // Services as the OS lists them, None when they could not be listed
+    fn entries(&self) -> Option<Vec<ServiceEntry>> {
         if cfg!(target_os = "windows") {
             info!("Collecting Windows services");
-            match Command::new("sc").args(["query"]).output() {
-                Ok(output) => {
-                    let (cow, _encoding_used, had_errors) = GBK.decode(&output.stdout);
-                    if had_errors {
-                        error!("Error decoding sc command output with GBK encoding");
-                    }
-
-                    let output_str = cow.into_owned();
-                    let service_name_re = Regex::new(r"SERVICE_NAME:\s*(.+)").unwrap();
-                    let display_name_re = Regex::new(r"DISPLAY_NAME:\s*(.+)").unwrap();
-                    let state_re = Regex::new(r"STATE\s*:\s*\d+\s*(.+)").unwrap();
-                    
-                    let mut current_service = None;
-                    let mut current_name = String::new();
-                    
-                    for line in output_str.lines() {
-                        if let Some(cap) = service_name_re.captures(line) {
-                            if let Some(service) = current_service.take() {
-                                services.push(service);
-                            }
-                            current_name = cap[1].trim().to_string();
-                        } else if let Some(cap) = display_name_re.captures(line) {
-                            let display_name = cap[1].trim().to_string();
-                            current_service = Some(ServiceInformationBuilder::new()
-                                .id(Uuid::new_v4().to_string())
-                                .timestamp(Utc::now())
-                                .source(self.hostname.clone())
-                                .category(String::from("service"))
-                                .name(current_name.clone())
-                                .display_name(display_name)
-                                .status(String::from("Unknown"))
-                                .startup_type(String::from("Unknown"))
-                                .build()
-                                .map_err(|e| CollectionError::Parse(e))?);
-                        } else if let Some(cap) = state_re.captures(line) {
-                            if let Some(service) = &mut current_service {
-                                service.status = cap[1].trim().to_string();
-                            }
-                        }
-                    }
-                    
-                    if let Some(service) = current_service {
-                        services.push(service);
-                    }
-                    info!("Found {} Windows services", services.len());
-                }
+            match windows_services() {
+                Ok(entries) => Some(entries),
                 Err(e) => {
-                    error!("Failed to execute sc command: {}", e);
+                    error!("Failed to list services: {}", e);
+                    None
                 }
             }
         } else if cfg!(target_os = "linux") {
             info!("Collecting Linux services");
-            if let Ok(systemctl_path) = which("systemctl") {
-                match Command::new(systemctl_path)
-                    .args(["list-units", "--type=service", "--all", "--no-pager", "--plain"])
-                    .output() 
-                {
-                    Ok(output) => {
-                        if let Ok(output_str) = String::from_utf8(output.stdout) {
-                            for line in output_str.lines().skip(1) {
-                                let parts: Vec<&str> = line.split_whitespace().collect();
-                                if parts.len() >= 4 {
-                                    let name = parts[0].trim_end_matches(".service").to_string();
-                                    let service = ServiceInformationBuilder::new()
-                                        .id(Uuid::new_v4().to_string())
-                                        .timestamp(Utc::now())
-                                        .source(self.hostname.clone())
-                                        .category(String::from("service"))
-                                        .name(name.clone())
-                                        .display_name(name)
-                                        .status(parts[3].to_string())
-                                        .startup_type(String::from("Unknown"))
-                                        .build()
-                                        .map_err(|e| CollectionError::Parse(e))?;
-                                    services.push(service);
-                                }
-                            }
-                            info!("Found {} Linux services", services.len());
-                        } else {
-                            error!("Failed to parse systemctl output");
-                        }
-                    }
-                    Err(e) => {
-                        error!("Failed to execute systemctl command: {}", e);
-                    }
-                }
-            } else {
+            let Ok(systemctl_path) = which("systemctl") else {
                 warn!("systemctl command not found");
+                return None;
+            };
+            match command::run(&systemctl_path, ["list-units", "--type=service", "--all", "--no-pager", "--plain"]) {
+                Ok(output) => {
+                    let parsed = parsers::parse_systemctl_units(&output.stdout);
+                    parsed.log_rejected("systemctl list-units");
+                    let mut entries = parsed.records;
+                    Self::add_unit_details(&systemctl_path, &mut entries);
+                    Some(entries)
+                }
+                Err(e) => {
+                    error!("Failed to execute systemctl command: {}", e);
+                    None
+                }
+            }
+        } else if cfg!(target_os = "macos") {
+            info!("Collecting launchd services");
+            match launchd_services() {
+                Ok(entries) => Some(entries),
+                Err(e) => {
+                    error!("Failed to list launchd jobs: {}", e);
+                    None
+                }
             }
+        } else {
+            None
         }
-        
+    }
+
+    // Startup type, command line, unit file, main PID and account of each unit, asked for a
+    // batch of units at a time; units systemctl cannot describe keep what list-units gave
+    fn add_unit_details(systemctl_path: &Path, entries: &mut [ServiceEntry]) {
+        for batch in entries.chunks_mut(SHOW_BATCH) {
+            let mut args = vec![
+                String::from("show"),
+                String::from("--property=Id,UnitFileState,ExecStart,FragmentPath,MainPID,User"),
+            ];
+            args.extend(batch.iter().map(|entry| format!("{}.service", entry.name)));
+            let output = match command::run(systemctl_path, &args) {
+                Ok(output) => output,
+                Err(e) => {
+                    warn!("Failed to execute systemctl show: {}", e);
+                    return;
+                }
+            };
+            let parsed = parsers::parse_systemctl_show(&output.stdout);
+            parsed.log_rejected("systemctl show");
+            let mut details: HashMap<String, UnitDetails> =
+                parsed.records.into_iter().map(|details| (details.name.clone(), details)).collect();
+            for entry in batch {
+                if let Some(details) = details.remove(&entry.name) {
+                    details.apply(entry);
+                }
+            }
+        }
+    }
+
+    fn collect_services(&mut self) -> Result<Vec<ServiceInformation>, CollectionError> {
+        let Some(entries) = self.entries() else {
+            // Nothing to compare against; every known service would look removed
+            return Ok(Vec::new());
+        };
+        let services = entries
+            .into_iter()
+            .map(|entry| self.service(entry))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.track_changes(&services)?;
         Ok(services)
     }
 
-    fn internal_validate(&self) -> Result<(), CollectionError> {
-        if cfg!(target_os = "windows") {
-            if which("sc").is_err() {
-                return Err(CollectionError::SystemApi(
-                    "sc command not found".to_string()
-                ));
+    fn change(
+        &self,
+        change: ServiceChange,
+        service: InstalledService,
+        previous: Option<&InstalledService>,
+    ) -> Result<ServiceChangeEvent, CollectionError> {
+        let mut builder = ServiceChangeEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("service"))
+            .change(change)
+            .service(service);
+        if let Some(previous) = previous {
+            builder = builder.previous(previous.clone());
+        }
+        builder.build().map_err(CollectionError::Parse)
+    }
+
+    fn track_changes(&mut self, services: &[ServiceInformation]) -> Result<(), CollectionError> {
+        let current: HashMap<String, InstalledService> = services
+            .iter()
+            .map(|service| (service.name.clone(), InstalledService::from(service)))
+            .collect();
+        // The first collection on a host is the baseline, nothing in it counts as new
+        let baseline = self.known.is_empty();
+
+        let mut changes = Vec::new();
+        for (name, service) in &current {
+            match self.known.get(name) {
+                None if !baseline => changes.push(self.change(ServiceChange::Installed, service.clone(), None)?),
+                None => {}
+                // A configuration that could not be read this time is not a change
+                Some(previous) => {
+                    if previous.binary_path.is_some()
+                        && service.binary_path.is_some()
+                        && previous.binary_path != service.binary_path
+                    {
+                        changes.push(self.change(ServiceChange::BinaryPathChanged, service.clone(), Some(previous))?);
+                    }
+                    if previous.startup_type != UNKNOWN_STARTUP_TYPE
+                        && service.startup_type != UNKNOWN_STARTUP_TYPE
+                        && previous.startup_type != service.startup_type
+                    {
+                        changes.push(self.change(ServiceChange::StartupTypeChanged, service.clone(), Some(previous))?);
+                    }
+                }
             }
-        } else if cfg!(target_os = "linux") {
-            if which("systemctl").is_err() {
-                return Err(CollectionError::SystemApi(
-                    "systemctl command not found".to_string()
-                ));
+        }
+        for (name, service) in &self.known {
+            if !current.contains_key(name) {
+                changes.push(self.change(ServiceChange::Removed, service.clone(), None)?);
             }
         }
+        if !changes.is_empty() {
+            info!("{} services were installed, removed or reconfigured", changes.len());
+        }
+
+        if current != self.known {
+            Baseline::global().save(Self::SERVICES_BASELINE, &current);
+            self.known = current;
+        }
+        self.changes.extend(changes);
+        Ok(())
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if cfg!(target_os = "linux") && which("systemctl").is_err() {
+            return Err(CollectionError::SystemApi(
+                "systemctl command not found".to_string()
+            ));
+        }
+        if cfg!(target_os = "macos") && which("launchctl").is_err() {
+            return Err(CollectionError::SystemApi(String::from("launchctl command not found")));
+        }
         Ok(())
     }
 }
@@ -0,0 +1,147 @@
+use crate::features::service::parsers::{self, ServiceEntry};
+use crate::shared::command;
+use crate::shared::error::CollectionError;
+use crate::shared::user_profiles::user_profiles;
+use log::debug;
+use plist::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use which::which;
+
+// Where launchd reads job definitions from: daemons start at boot as root or their UserName,
+// agents in every user session. /System holds Apple's own, sealed on current macOS.
+const JOB_DIRECTORIES: &[&str] = &[
+    "/Library/LaunchDaemons",
+    "/Library/LaunchAgents",
+    "/System/Library/LaunchDaemons",
+    "/System/Library/LaunchAgents",
+];
+
+// Agents a user installs for their own sessions, below their home directory
+const USER_AGENTS: &str = "Library/LaunchAgents";
+
+// One job as its property list defines it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchdJob {
+    pub label: String,
+    pub path: PathBuf,
+    // ProgramArguments joined with spaces, or Program
+    pub command_line: Option<String>,
+    pub run_at_load: bool,
+    pub keep_alive: bool,
+    pub disabled: bool,
+    pub user_name: Option<String>,
+    // The user whose LaunchAgents directory holds the job
+    pub profile_user: Option<String>,
+}
+
+impl LaunchdJob {
+    // None when the file is not a property list holding a dictionary; XML and binary lists
+    // are both read
+    fn read(path: &Path, profile_user: Option<&str>) -> Option<Self> {
+        let value = match Value::from_file(path) {
+            Ok(value) => value,
+            Err(e) => {
+                debug!("Skipping launchd job {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        let job = value.as_dictionary()?;
+        let string = |key: &str| job.get(key).and_then(Value::as_string).map(str::to_string);
+        let flag = |key: &str| job.get(key).and_then(Value::as_boolean).unwrap_or(false);
+        let arguments: Vec<&str> = job
+            .get("ProgramArguments")
+            .and_then(Value::as_array)
+            .map(|arguments| arguments.iter().filter_map(Value::as_string).collect())
+            .unwrap_or_default();
+        Some(Self {
+            // launchd knows a job by its Label; the file name is only a convention
+            label: string("Label").or_else(|| Some(path.file_stem()?.to_string_lossy().into_owned()))?,
+            path: path.to_path_buf(),
+            command_line: if arguments.is_empty() { string("Program") } else { Some(arguments.join(" ")) },
+            run_at_load: flag("RunAtLoad"),
+            // A dictionary of conditions still has launchd start the job again
+            keep_alive: job.get("KeepAlive").is_some_and(|keep_alive| keep_alive.as_boolean() != Some(false)),
+            disabled: flag("Disabled"),
+            user_name: string("UserName"),
+            profile_user: profile_user.map(str::to_string),
+        })
+    }
+
+    // Automatic when launchd starts the job by itself, On Demand when it waits for a
+    // connection, a schedule or a watched path
+    pub fn startup_type(&self) -> &'static str {
+        if self.disabled {
+            "Disabled"
+        } else if self.run_at_load || self.keep_alive {
+            "Automatic"
+        } else {
+            "On Demand"
+        }
+    }
+
+    // Agents run as the user of the session they are loaded in
+    pub fn account(&self) -> String {
+        self.user_name
+            .clone()
+            .or_else(|| self.profile_user.clone())
+            .unwrap_or_else(|| String::from("root"))
+    }
+}
+
+// Every directory jobs are defined in, with the user a per-user directory belongs to
+pub fn job_directories() -> Vec<(PathBuf, Option<String>)> {
+    let mut directories: Vec<(PathBuf, Option<String>)> =
+        JOB_DIRECTORIES.iter().map(|directory| (PathBuf::from(directory), None)).collect();
+    directories.extend(
+        user_profiles()
+            .into_iter()
+            .map(|profile| (profile.path.join(USER_AGENTS), Some(profile.user))),
+    );
+    directories
+}
+
+// The jobs defined in one directory, None when it cannot be read
+pub fn jobs_in(directory: &Path, user: Option<&str>) -> Option<Vec<LaunchdJob>> {
+    let entries = fs::read_dir(directory).ok()?;
+    let mut jobs: Vec<LaunchdJob> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "plist"))
+        .filter_map(|path| LaunchdJob::read(&path, user))
+        .collect();
+    jobs.sort_by(|a, b| a.path.cmp(&b.path));
+    Some(jobs)
+}
+
+// Every job defined on the host, loaded or not, with what `launchctl list` says of those
+// loaded in the agent's domain. Loaded jobs without a file (submitted ones, XPC services)
+// are listed as they are.
+pub fn services() -> Result<Vec<ServiceEntry>, CollectionError> {
+    let launchctl = which("launchctl").map_err(|_| CollectionError::SystemApi(String::from("launchctl command not found")))?;
+    let output = command::run(&launchctl, ["list"])?;
+    let parsed = parsers::parse_launchctl_list(&output.stdout);
+    parsed.log_rejected("launchctl list");
+    let mut loaded: HashMap<String, ServiceEntry> =
+        parsed.records.into_iter().map(|entry| (entry.name.clone(), entry)).collect();
+
+    let mut entries = Vec::new();
+    for (directory, user) in job_directories() {
+        for job in jobs_in(&directory, user.as_deref()).unwrap_or_default() {
+            let mut entry = loaded.remove(&job.label).unwrap_or_else(|| ServiceEntry {
+                name: job.label.clone(),
+                display_name: job.label.clone(),
+                state: String::from("unloaded"),
+                ..ServiceEntry::default()
+            });
+            entry.startup_type = Some(job.startup_type().to_string());
+            entry.binary_path = job.command_line.clone();
+            entry.account = Some(job.account());
+            entry.unit_file = Some(job.path.to_string_lossy().into_owned());
+            entries.push(entry);
+        }
+    }
+    entries.extend(loaded.into_values());
+    Ok(entries)
+}
@@ -1,5 +1,16 @@
 pub mod models;
 pub mod collector;
+pub mod parsers;
+#[cfg(windows)]
+mod win32;
+#[cfg(target_os = "macos")]
+pub mod launchd;
 
-pub use models::{ServiceInformation, ServiceInformationBuilder};
+pub use models::{
+    InstalledService, ServiceChange, ServiceChangeEvent, ServiceChangeEventBuilder, ServiceInformation,
+    ServiceInformationBuilder,
+};
 pub use collector::ServiceCollector;
+pub use parsers::{
+    parse_launchctl_list, parse_sc_query, parse_systemctl_show, parse_systemctl_units, ServiceEntry, UnitDetails,
+};
@@ -13,6 +13,15 @@ pub struct ServiceInformation {
     pub display_name: String,
     pub status: String,
     pub startup_type: String,
+    // Command line the service control manager or systemd starts
+    #[serde(default)]
+    pub binary_path: Option<String>,
+    // Account the service runs as
+    #[serde(default)]
+    pub account: Option<String>,
+    // Unit file the service was loaded from, Linux only
+    #[serde(default)]
+    pub unit_file: Option<String>,
     pub process_id: Option<u32>,
     pub dependencies: Vec<String>,
 }
@@ -77,6 +86,9 @@ pub struct ServiceInformationBuilder {
     display_name: Option<String>,
     status: Option<String>,
     startup_type: Option<String>,
+    binary_path: Option<String>,
+    account: Option<String>,
+    unit_file: Option<String>,
     process_id: Option<u32>,
     dependencies: Option<Vec<String>>,
 }
@@ -126,6 +138,21 @@ impl ServiceInformationBuilder {
         self
     }
 
+    pub fn binary_path(mut self, binary_path: Option<String>) -> Self {
+        self.binary_path = binary_path;
+        self
+    }
+
+    pub fn account(mut self, account: Option<String>) -> Self {
+        self.account = account;
+        self
+    }
+
+    pub fn unit_file(mut self, unit_file: Option<String>) -> Self {
+        self.unit_file = unit_file;
+        self
+    }
+
     pub fn process_id(mut self, process_id: u32) -> Self {
         self.process_id = Some(process_id);
         self
@@ -146,6 +173,9 @@ impl ServiceInformationBuilder {
             display_name: self.display_name.ok_or("display_name is required")?,
             status: self.status.ok_or("status is required")?,
             startup_type: self.startup_type.ok_or("startup_type is required")?,
+            binary_path: self.binary_path,
+            account: self.account,
+            unit_file: self.unit_file,
             process_id: self.process_id,
             dependencies: self.dependencies.unwrap_or_default(),
         };
@@ -154,3 +184,191 @@ impl ServiceInformationBuilder {
         Ok(service)
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceChange {
+    Installed,
+    Removed,
+    BinaryPathChanged,
+    StartupTypeChanged,
+}
+
+// What is remembered about a service between collections, and across restarts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledService {
+    pub name: String,
+    pub display_name: String,
+    pub status: String,
+    pub startup_type: String,
+    pub binary_path: Option<String>,
+    pub account: Option<String>,
+    #[serde(default)]
+    pub unit_file: Option<String>,
+}
+
+impl From<&ServiceInformation> for InstalledService {
+    fn from(service: &ServiceInformation) -> Self {
+        Self {
+            name: service.name.clone(),
+            display_name: service.display_name.clone(),
+            status: service.status.clone(),
+            startup_type: service.startup_type.clone(),
+            binary_path: service.binary_path.clone(),
+            account: service.account.clone(),
+            unit_file: service.unit_file.clone(),
+        }
+    }
+}
+
+// A service installed, removed or reconfigured since the previous collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceChangeEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub change: ServiceChange,
+    pub name: String,
+    pub display_name: String,
+    // Last known status of a removed service
+    pub status: String,
+    pub startup_type: String,
+    pub binary_path: Option<String>,
+    pub account: Option<String>,
+    pub unit_file: Option<String>,
+    pub previous_startup_type: Option<String>,
+    pub previous_binary_path: Option<String>,
+}
+
+impl Event for ServiceChangeEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.change {
+            ServiceChange::Installed => "service_installed",
+            ServiceChange::Removed => "service_removed",
+            ServiceChange::BinaryPathChanged => "service_binary_path_changed",
+            ServiceChange::StartupTypeChanged => "service_startup_type_changed",
+        }
+    }
+
+    // A new service, or an existing one pointed at another binary, is how malware persists
+    // as SYSTEM
+    fn severity(&self) -> Severity {
+        match self.change {
+            ServiceChange::Installed | ServiceChange::BinaryPathChanged => Severity::High,
+            ServiceChange::StartupTypeChanged => Severity::Medium,
+            ServiceChange::Removed => Severity::Low,
+        }
+    }
+}
+
+impl Identifiable for ServiceChangeEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for ServiceChangeEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("Service name cannot be empty".to_string());
+        }
+        match self.change {
+            ServiceChange::BinaryPathChanged if self.binary_path == self.previous_binary_path => {
+                Err("A binary path change needs a different previous path".to_string())
+            }
+            ServiceChange::StartupTypeChanged if self.previous_startup_type.is_none() => {
+                Err("A startup type change needs the previous startup type".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ServiceChangeEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    change: Option<ServiceChange>,
+    service: Option<InstalledService>,
+    previous: Option<InstalledService>,
+}
+
+impl ServiceChangeEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn change(mut self, change: ServiceChange) -> Self {
+        self.change = Some(change);
+        self
+    }
+
+    pub fn service(mut self, service: InstalledService) -> Self {
+        self.service = Some(service);
+        self
+    }
+
+    // The service as the previous collection saw it, for changes
+    pub fn previous(mut self, previous: InstalledService) -> Self {
+        self.previous = Some(previous);
+        self
+    }
+
+    pub fn build(self) -> Result<ServiceChangeEvent, String> {
+        let service = self.service.ok_or("service is required")?;
+        let event = ServiceChangeEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            change: self.change.ok_or("change is required")?,
+            name: service.name,
+            display_name: service.display_name,
+            status: service.status,
+            startup_type: service.startup_type,
+            binary_path: service.binary_path,
+            account: service.account,
+            unit_file: service.unit_file,
+            previous_startup_type: self.previous.as_ref().map(|previous| previous.startup_type.clone()),
+            previous_binary_path: self.previous.and_then(|previous| previous.binary_path),
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
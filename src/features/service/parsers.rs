@@ -0,0 +1,261 @@
+use crate::shared::command::ParsedOutput;
+
+// One service as listed by the service control manager, sc, systemctl or launchctl
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServiceEntry {
+    pub name: String,
+    pub display_name: String,
+    // RUNNING, STOPPED, ... on Windows; the sub state (running, exited, dead, ...) from systemctl;
+    // running, stopped or unloaded for launchd jobs
+    pub state: String,
+    // The rest comes from the service control manager, or from `systemctl show`
+    pub startup_type: Option<String>,
+    pub binary_path: Option<String>,
+    pub account: Option<String>,
+    pub process_id: Option<u32>,
+    pub dependencies: Vec<String>,
+    // Unit file systemd loaded the service from, or the property list of a launchd job
+    pub unit_file: Option<String>,
+}
+
+// sc prints the state as a number followed by its name; the number is the same in every
+// language, and the one SERVICE_STATUS carries
+pub(crate) fn service_state(code: &str) -> Option<&'static str> {
+    match code {
+        "1" => Some("STOPPED"),
+        "2" => Some("START_PENDING"),
+        "3" => Some("STOP_PENDING"),
+        "4" => Some("RUNNING"),
+        "5" => Some("CONTINUE_PENDING"),
+        "6" => Some("PAUSE_PENDING"),
+        "7" => Some("PAUSED"),
+        _ => None,
+    }
+}
+
+// Names end up in documents and log lines; control characters there are never legitimate
+fn printable(value: &str) -> bool {
+    !value.is_empty() && !value.chars().any(char::is_control)
+}
+
+// `KEY: value` or `KEY   : value` at the start of a line, as sc prints its fields
+fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let rest = line.trim_start().strip_prefix(key)?;
+    let value = rest.trim_start().strip_prefix(':')?;
+    Some(value.trim())
+}
+
+// `sc query` output: one block per service starting with SERVICE_NAME. A block without a
+// DISPLAY_NAME keeps the service name as its display name rather than being dropped.
+pub fn parse_sc_query(output: &str) -> ParsedOutput<ServiceEntry> {
+    let mut parsed = ParsedOutput::new();
+    let mut current: Option<ServiceEntry> = None;
+
+    for (index, line) in output.lines().enumerate() {
+        let number = index + 1;
+        if let Some(name) = field(line, "SERVICE_NAME") {
+            parsed.records.extend(current.take());
+            if !printable(name) {
+                parsed.reject(number, "service name is empty or has control characters");
+                continue;
+            }
+            current = Some(ServiceEntry {
+                name: name.to_string(),
+                display_name: name.to_string(),
+                state: String::from("Unknown"),
+                ..ServiceEntry::default()
+            });
+        } else if let Some(display_name) = field(line, "DISPLAY_NAME") {
+            match current.as_mut() {
+                Some(service) if printable(display_name) => service.display_name = display_name.to_string(),
+                Some(_) => parsed.reject(number, "display name is empty or has control characters"),
+                None => parsed.reject(number, "DISPLAY_NAME outside a service block"),
+            }
+        } else if let Some(state) = field(line, "STATE") {
+            let Some(service) = current.as_mut() else {
+                parsed.reject(number, "STATE outside a service block");
+                continue;
+            };
+            let mut parts = state.split_whitespace();
+            match (parts.next().and_then(service_state), parts.next()) {
+                (Some(state), _) => service.state = state.to_string(),
+                (None, Some(name)) if printable(name) => service.state = name.to_string(),
+                _ => parsed.reject(number, "unrecognized service state"),
+            }
+        }
+    }
+    parsed.records.extend(current);
+    parsed
+}
+
+// `systemctl list-units --type=service --all --no-pager --plain`: UNIT LOAD ACTIVE SUB
+// DESCRIPTION, then a legend and a unit count that are not services
+pub fn parse_systemctl_units(output: &str) -> ParsedOutput<ServiceEntry> {
+    let mut parsed = ParsedOutput::new();
+
+    for (index, line) in output.lines().enumerate() {
+        let number = index + 1;
+        let mut fields: Vec<&str> = line.split_whitespace().collect();
+        // Failed and not-found units are marked with a bullet when the output is not plain
+        if matches!(fields.first(), Some(&"●") | Some(&"*")) {
+            fields.remove(0);
+        }
+        let Some(unit) = fields.first() else {
+            continue;
+        };
+        // Header, legend (LOAD   = ...) and the closing "N loaded units listed."
+        if *unit == "UNIT" || fields.get(1) == Some(&"=") || line.trim_end().ends_with("listed.") {
+            continue;
+        }
+        let Some(name) = unit.strip_suffix(".service") else {
+            parsed.reject(number, "not a service unit");
+            continue;
+        };
+        if fields.len() < 4 {
+            parsed.reject(number, "fewer than four columns");
+            continue;
+        }
+        if !printable(name) {
+            parsed.reject(number, "unit name has control characters");
+            continue;
+        }
+        let description = fields[4..].join(" ");
+        parsed.records.push(ServiceEntry {
+            name: name.to_string(),
+            display_name: if printable(&description) { description } else { name.to_string() },
+            state: fields[3].to_string(),
+            ..ServiceEntry::default()
+        });
+    }
+    parsed
+}
+
+// What `systemctl show` knows about one service unit beyond list-units
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnitDetails {
+    pub name: String,
+    // enabled, disabled, static, masked, ...
+    pub unit_file_state: Option<String>,
+    // Command lines of the ExecStart entries, in order
+    pub exec_start: Vec<String>,
+    pub fragment_path: Option<String>,
+    pub main_pid: Option<u32>,
+    // Empty when the service runs as root
+    pub user: Option<String>,
+}
+
+impl UnitDetails {
+    // Fills in what list-units leaves out
+    pub fn apply(self, entry: &mut ServiceEntry) {
+        entry.startup_type = self.unit_file_state;
+        if !self.exec_start.is_empty() {
+            entry.binary_path = Some(self.exec_start.join("; "));
+        }
+        entry.account = Some(self.user.unwrap_or_else(|| String::from("root")));
+        entry.process_id = self.main_pid;
+        entry.unit_file = self.fragment_path;
+    }
+}
+
+// `argv[]=` of every `{ path=... ; argv[]=... ; ... }` group systemctl prints for ExecStart
+fn exec_command_lines(value: &str) -> Vec<String> {
+    value
+        .split("argv[]=")
+        .skip(1)
+        .filter_map(|rest| {
+            let command = rest.split(" ;").next()?.trim();
+            (!command.is_empty()).then(|| command.to_string())
+        })
+        .collect()
+}
+
+// `systemctl show --property=Id,UnitFileState,ExecStart,FragmentPath,MainPID,User UNIT...`:
+// Key=Value lines, one block per unit separated by a blank line. A block without an Id cannot
+// be matched to its unit and is dropped.
+pub fn parse_systemctl_show(output: &str) -> ParsedOutput<UnitDetails> {
+    let mut parsed = ParsedOutput::new();
+    // The block being read and the line it started on
+    let mut current: Option<(usize, UnitDetails)> = None;
+    let optional = |value: &str| Some(value.to_string()).filter(|value| printable(value));
+    let finish = |parsed: &mut ParsedOutput<UnitDetails>, block: Option<(usize, UnitDetails)>| match block {
+        Some((_, details)) if !details.name.is_empty() => parsed.records.push(details),
+        Some((start, _)) => parsed.reject(start, "unit block without a service Id"),
+        None => {}
+    };
+
+    for (index, line) in output.lines().enumerate() {
+        let number = index + 1;
+        if line.trim().is_empty() {
+            finish(&mut parsed, current.take());
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            parsed.reject(number, "not a Key=Value line");
+            continue;
+        };
+        let (_, details) = current.get_or_insert_with(|| (number, UnitDetails::default()));
+        match key {
+            "Id" => {
+                if let Some(name) = value.strip_suffix(".service").filter(|name| printable(name)) {
+                    details.name = name.to_string();
+                }
+            }
+            "UnitFileState" => details.unit_file_state = optional(value),
+            "ExecStart" => details.exec_start = exec_command_lines(value),
+            "FragmentPath" => details.fragment_path = optional(value),
+            "MainPID" => details.main_pid = value.parse().ok().filter(|pid| *pid != 0),
+            "User" => details.user = optional(value),
+            _ => {}
+        }
+    }
+    finish(&mut parsed, current);
+    parsed
+}
+
+// `launchctl list`: PID, last exit status and label, tab separated, under a header. A job
+// that is loaded but not running has `-` for its PID.
+pub fn parse_launchctl_list(output: &str) -> ParsedOutput<ServiceEntry> {
+    let mut parsed = ParsedOutput::new();
+
+    for (index, line) in output.lines().enumerate() {
+        let number = index + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 3 {
+            parsed.reject(number, "not three tab-separated columns");
+            continue;
+        }
+        if fields[0] == "PID" && fields[2] == "Label" {
+            continue;
+        }
+        let process_id = match fields[0] {
+            "-" => None,
+            pid => match pid.parse::<u32>() {
+                Ok(pid) => Some(pid),
+                Err(_) => {
+                    parsed.reject(number, "PID is neither a number nor -");
+                    continue;
+                }
+            },
+        };
+        if fields[1].parse::<i64>().is_err() {
+            parsed.reject(number, "exit status is not a number");
+            continue;
+        }
+        let label = fields[2].trim();
+        if !printable(label) {
+            parsed.reject(number, "label is empty or has control characters");
+            continue;
+        }
+        parsed.records.push(ServiceEntry {
+            name: label.to_string(),
+            display_name: label.to_string(),
+            state: String::from(if process_id.is_some() { "running" } else { "stopped" }),
+            process_id,
+            ..ServiceEntry::default()
+        });
+    }
+    parsed
+}
@@ -0,0 +1,175 @@
+use crate::features::service::parsers::{service_state, ServiceEntry};
+use crate::shared::error::CollectionError;
+use log::debug;
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{GetLastError, ERROR_MORE_DATA};
+use windows::Win32::Security::SC_HANDLE;
+use windows::Win32::System::Services::{
+    CloseServiceHandle, EnumServicesStatusExW, OpenSCManagerW, OpenServiceW, QueryServiceConfig2W, QueryServiceConfigW,
+    ENUM_SERVICE_STATUS_PROCESSW, QUERY_SERVICE_CONFIGW, SC_ENUM_PROCESS_INFO, SC_MANAGER_CONNECT,
+    SC_MANAGER_ENUMERATE_SERVICE, SERVICE_AUTO_START, SERVICE_BOOT_START, SERVICE_CONFIG_DELAYED_AUTO_START_INFO,
+    SERVICE_DELAYED_AUTO_START_INFO, SERVICE_DEMAND_START, SERVICE_DISABLED, SERVICE_QUERY_CONFIG, SERVICE_STATE_ALL,
+    SERVICE_SYSTEM_START, SERVICE_WIN32,
+};
+
+// A service control manager or service handle, closed when dropped
+struct ServiceHandle(SC_HANDLE);
+
+impl Drop for ServiceHandle {
+    fn drop(&mut self) {
+        unsafe {
+            CloseServiceHandle(self.0);
+        }
+    }
+}
+
+fn text(value: PWSTR) -> Option<String> {
+    if value.is_null() {
+        return None;
+    }
+    let value = String::from_utf16_lossy(unsafe { value.as_wide() }).trim().to_string();
+    if value.is_empty() { None } else { Some(value) }
+}
+
+// lpDependencies is a list of NUL-terminated names ended by an empty one; load order groups
+// come prefixed with '+'
+fn names(value: PWSTR) -> Vec<String> {
+    let mut names = Vec::new();
+    if value.is_null() {
+        return names;
+    }
+    let mut cursor = value.as_ptr() as *const u16;
+    unsafe {
+        loop {
+            let length = (0..).take_while(|offset| *cursor.add(*offset) != 0).count();
+            if length == 0 {
+                break;
+            }
+            names.push(String::from_utf16_lossy(std::slice::from_raw_parts(cursor, length)));
+            cursor = cursor.add(length + 1);
+        }
+    }
+    names
+}
+
+// Buffers the SCM fills with a structure followed by its strings; u64 storage keeps the
+// structure aligned
+fn aligned_buffer(bytes: u32) -> Vec<u64> {
+    vec![0u64; (bytes as usize).div_ceil(8)]
+}
+
+fn as_bytes(buffer: &mut [u64]) -> &mut [u8] {
+    unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, buffer.len() * 8) }
+}
+
+// As services.msc names them
+fn startup_type(service: &ServiceHandle, config: &QUERY_SERVICE_CONFIGW) -> &'static str {
+    match config.dwStartType {
+        SERVICE_BOOT_START => "Boot",
+        SERVICE_SYSTEM_START => "System",
+        SERVICE_AUTO_START if delayed_start(service) => "Automatic (Delayed Start)",
+        SERVICE_AUTO_START => "Automatic",
+        SERVICE_DEMAND_START => "Manual",
+        SERVICE_DISABLED => "Disabled",
+        _ => "Unknown",
+    }
+}
+
+fn delayed_start(service: &ServiceHandle) -> bool {
+    let mut buffer = aligned_buffer(std::mem::size_of::<SERVICE_DELAYED_AUTO_START_INFO>() as u32);
+    let mut needed = 0u32;
+    let ok = unsafe {
+        QueryServiceConfig2W(service.0, SERVICE_CONFIG_DELAYED_AUTO_START_INFO, Some(as_bytes(&mut buffer)), &mut needed)
+    };
+    ok.as_bool() && unsafe { (*(buffer.as_ptr() as *const SERVICE_DELAYED_AUTO_START_INFO)).fDelayedAutostart.as_bool() }
+}
+
+// Startup type, binary path, account and dependencies; services this account may not query
+// keep what the enumeration gave
+fn configure(manager: &ServiceHandle, name: PWSTR, entry: &mut ServiceEntry) {
+    let service = match unsafe { OpenServiceW(manager.0, PCWSTR(name.as_ptr()), SERVICE_QUERY_CONFIG) } {
+        Ok(handle) => ServiceHandle(handle),
+        Err(e) => {
+            debug!("Cannot query the configuration of service {}: {}", entry.name, e);
+            return;
+        }
+    };
+    let mut needed = 0u32;
+    unsafe {
+        QueryServiceConfigW(service.0, None, 0, &mut needed);
+    }
+    if needed == 0 {
+        return;
+    }
+    let mut buffer = aligned_buffer(needed);
+    let config = buffer.as_mut_ptr() as *mut QUERY_SERVICE_CONFIGW;
+    let ok = unsafe { QueryServiceConfigW(service.0, Some(config), (buffer.len() * 8) as u32, &mut needed) };
+    if !ok.as_bool() {
+        debug!("Failed to query the configuration of service {}", entry.name);
+        return;
+    }
+    let config = unsafe { &*config };
+    entry.startup_type = Some(startup_type(&service, config).to_string());
+    entry.binary_path = text(config.lpBinaryPathName);
+    entry.account = text(config.lpServiceStartName);
+    entry.dependencies = names(config.lpDependencies);
+}
+
+// Every Win32 service from the service control manager, with its configuration
+pub fn services() -> Result<Vec<ServiceEntry>, CollectionError> {
+    let manager = unsafe { OpenSCManagerW(PCWSTR::null(), PCWSTR::null(), SC_MANAGER_CONNECT | SC_MANAGER_ENUMERATE_SERVICE) }
+        .map(ServiceHandle)
+        .map_err(|e| CollectionError::SystemApi(format!("Failed to open the service control manager: {}", e)))?;
+
+    let mut entries = Vec::new();
+    let mut resume = 0u32;
+    let mut buffer: Vec<u64> = Vec::new();
+    loop {
+        let mut needed = 0u32;
+        let mut returned = 0u32;
+        let bytes = if buffer.is_empty() { None } else { Some(as_bytes(&mut buffer)) };
+        let ok = unsafe {
+            EnumServicesStatusExW(
+                manager.0,
+                SC_ENUM_PROCESS_INFO,
+                SERVICE_WIN32,
+                SERVICE_STATE_ALL,
+                bytes,
+                &mut needed,
+                &mut returned,
+                Some(&mut resume as *mut u32),
+                PCWSTR::null(),
+            )
+        };
+        if !ok.as_bool() && unsafe { GetLastError() } != ERROR_MORE_DATA {
+            return Err(CollectionError::SystemApi(format!(
+                "Failed to enumerate services: {}",
+                std::io::Error::last_os_error()
+            )));
+        }
+
+        let records = buffer.as_ptr() as *const ENUM_SERVICE_STATUS_PROCESSW;
+        for index in 0..returned as usize {
+            let record = unsafe { &*records.add(index) };
+            let Some(name) = text(record.lpServiceName) else {
+                continue;
+            };
+            let status = &record.ServiceStatusProcess;
+            let mut entry = ServiceEntry {
+                display_name: text(record.lpDisplayName).unwrap_or_else(|| name.clone()),
+                name,
+                state: service_state(&status.dwCurrentState.0.to_string()).unwrap_or("Unknown").to_string(),
+                process_id: Some(status.dwProcessId).filter(|pid| *pid != 0),
+                ..ServiceEntry::default()
+            };
+            configure(&manager, record.lpServiceName, &mut entry);
+            entries.push(entry);
+        }
+
+        if ok.as_bool() {
+            return Ok(entries);
+        }
+        // The rest comes from the resume point; services may have been added since
+        buffer = aligned_buffer(needed + 4096);
+    }
+}
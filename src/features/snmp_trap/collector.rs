@@ -0,0 +1,341 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector, Severity};
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::features::detection::{contains, parse_network};
+use crate::features::snmp_trap::models::{SnmpTrapEvent, SnmpTrapEventBuilder};
+use crate::features::snmp_trap::parsers::parse_trap;
+use crate::utils::load_config_section;
+use chrono::Utc;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc::{channel, error::TrySendError, Receiver, Sender};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+// Names and severity for the traps under an OID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrapRule {
+    // Trap OID, or a prefix covering a device's whole trap tree
+    pub oid: String,
+    pub name: Option<String>,
+    pub severity: Severity,
+}
+
+impl Default for TrapRule {
+    fn default() -> Self {
+        Self {
+            oid: String::new(),
+            name: None,
+            severity: Severity::Medium,
+        }
+    }
+}
+
+impl TrapRule {
+    fn new(oid: &str, name: &str, severity: Severity) -> Self {
+        Self {
+            oid: oid.to_string(),
+            name: Some(name.to_string()),
+            severity,
+        }
+    }
+
+    fn covers(&self, trap_oid: &str) -> bool {
+        trap_oid
+            .strip_prefix(self.oid.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnmpTrapConfig {
+    pub enabled: bool,
+    pub bind_address: String,
+    pub port: u16,
+    // Community strings traps must carry, empty accepts any
+    pub communities: Vec<String>,
+    // Addresses or CIDR blocks traps are accepted from, empty accepts any
+    pub allowed_sources: Vec<String>,
+    // Severity of traps no rule covers
+    pub default_severity: Severity,
+    // The most specific rule covering a trap's OID names it and sets its severity
+    pub rules: Vec<TrapRule>,
+    pub channel_capacity: usize,
+}
+
+impl Default for SnmpTrapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: String::from("0.0.0.0"),
+            port: 162,
+            communities: Vec::new(),
+            allowed_sources: Vec::new(),
+            default_severity: Severity::Low,
+            rules: vec![
+                // Generic traps of SNMPv2-MIB and IF-MIB
+                TrapRule::new("1.3.6.1.6.3.1.1.5.1", "coldStart", Severity::Medium),
+                TrapRule::new("1.3.6.1.6.3.1.1.5.2", "warmStart", Severity::Low),
+                TrapRule::new("1.3.6.1.6.3.1.1.5.3", "linkDown", Severity::Medium),
+                TrapRule::new("1.3.6.1.6.3.1.1.5.4", "linkUp", Severity::Low),
+                // Someone is guessing community strings
+                TrapRule::new("1.3.6.1.6.3.1.1.5.5", "authenticationFailure", Severity::High),
+                // UPS-MIB
+                TrapRule::new("1.3.6.1.2.1.33.2.1", "upsTrapOnBattery", Severity::High),
+                TrapRule::new("1.3.6.1.2.1.33.2.2", "upsTrapTestCompleted", Severity::Low),
+                TrapRule::new("1.3.6.1.2.1.33.2.3", "upsTrapAlarmEntryAdded", Severity::High),
+                TrapRule::new("1.3.6.1.2.1.33.2.4", "upsTrapAlarmEntryRemoved", Severity::Low),
+            ],
+            channel_capacity: 1024,
+        }
+    }
+}
+
+impl SnmpTrapConfig {
+    pub fn load() -> Self {
+        load_config_section("snmp_trap")
+    }
+
+    // What keeps the listener from starting or a setting from applying, for the configuration check
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.enabled {
+            return problems;
+        }
+        if self.bind_address.parse::<IpAddr>().is_err() {
+            problems.push(format!("snmp_trap.bind_address '{}' is not an IP address", self.bind_address));
+        }
+        if self.port == 0 {
+            problems.push(String::from("snmp_trap.port must not be 0"));
+        }
+        for source in self.allowed_sources.iter().filter(|source| parse_network(source).is_none()) {
+            problems.push(format!("snmp_trap.allowed_sources entry '{}' is not an address or CIDR block", source));
+        }
+        for (position, rule) in self.rules.iter().enumerate() {
+            let valid = !rule.oid.is_empty() && rule.oid.split('.').all(|arc| arc.parse::<u64>().is_ok());
+            if !valid {
+                problems.push(format!("snmp_trap.rules[{}].oid '{}' is not a dotted OID", position, rule.oid));
+            }
+        }
+        problems
+    }
+}
+
+struct TrapContext {
+    config: SnmpTrapConfig,
+    allowed_sources: Vec<(IpAddr, u32)>,
+    host: Arc<HostIdentityService>,
+    sender: Sender<SnmpTrapEvent>,
+    rejected: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl TrapContext {
+    fn allowed(&self, address: IpAddr) -> bool {
+        self.allowed_sources.is_empty() || self.allowed_sources.iter().any(|network| contains(*network, address))
+    }
+
+    fn rule(&self, trap_oid: &str) -> Option<&TrapRule> {
+        self.config
+            .rules
+            .iter()
+            .filter(|rule| rule.covers(trap_oid))
+            .max_by_key(|rule| rule.oid.len())
+    }
+
+    // The event for one datagram and, for an inform, the acknowledgement to send back; None
+    // for anything that is not an accepted trap
+    fn receive(&self, datagram: &[u8], peer: SocketAddr) -> Option<(SnmpTrapEvent, Option<Vec<u8>>)> {
+        if !self.allowed(peer.ip()) {
+            debug!("Ignoring SNMP datagram from {}, not an allowed source", peer);
+            return None;
+        }
+        let trap = match parse_trap(datagram) {
+            Ok(trap) => trap,
+            Err(e) => {
+                debug!("Ignoring SNMP datagram from {}: {}", peer, e);
+                return None;
+            }
+        };
+        if !self.config.communities.is_empty() && !self.config.communities.contains(&trap.community) {
+            debug!("Ignoring SNMP trap from {} with an unknown community", peer);
+            return None;
+        }
+
+        let acknowledgement = trap.acknowledgement(datagram);
+        let rule = self.rule(&trap.trap_oid);
+        let event = SnmpTrapEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("snmp_trap"))
+            .sender(peer.ip().to_string(), peer.port())
+            .trap_name(rule.and_then(|rule| rule.name.clone()))
+            .severity(rule.map_or(self.config.default_severity, |rule| rule.severity))
+            .trap(trap)
+            .build();
+        match event {
+            Ok(event) => Some((event, acknowledgement)),
+            Err(e) => {
+                debug!("Ignoring SNMP trap from {}: {}", peer, e);
+                None
+            }
+        }
+    }
+
+    async fn serve(self: Arc<Self>, socket: UdpSocket) {
+        let mut buffer = vec![0u8; 65535];
+        loop {
+            let (length, peer) = match socket.recv_from(&mut buffer).await {
+                Ok(received) => received,
+                Err(e) => {
+                    warn!("Failed to receive an SNMP trap: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let Some((event, acknowledgement)) = self.receive(&buffer[..length], peer) else {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                continue;
+            };
+            if let Some(acknowledgement) = acknowledgement {
+                if let Err(e) = socket.send_to(&acknowledgement, peer).await {
+                    debug!("Failed to acknowledge an SNMP inform from {}: {}", peer, e);
+                }
+            }
+            if let Err(TrySendError::Full(_)) = self.sender.try_send(event) {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+// Receives SNMPv1 and SNMPv2c traps and informs from nearby network gear (switches, UPSes,
+// printers) so a small site can forward their alerts through the agent instead of running
+// a separate trap collector. Informs are acknowledged once accepted.
+pub struct SnmpTrapCollector {
+    context: Arc<TrapContext>,
+    event_receiver: Receiver<SnmpTrapEvent>,
+    listener: JoinHandle<()>,
+}
+
+impl SnmpTrapCollector {
+    pub fn new() -> Result<Self, CollectionError> {
+        Self::with_config(SnmpTrapConfig::load())
+    }
+
+    // Must be called from within a tokio runtime
+    pub fn with_config(config: SnmpTrapConfig) -> Result<Self, CollectionError> {
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|e| CollectionError::SystemApi(format!("The SNMP trap listener needs a tokio runtime: {}", e)))?;
+        let address: IpAddr = config
+            .bind_address
+            .parse()
+            .map_err(|e| CollectionError::Parse(format!("invalid bind address {}: {}", config.bind_address, e)))?;
+        let allowed_sources = config
+            .allowed_sources
+            .iter()
+            .filter_map(|source| match parse_network(source) {
+                Some(network) => Some(network),
+                None => {
+                    warn!("Ignoring invalid snmp_trap allowed_sources entry {}", source);
+                    None
+                }
+            })
+            .collect();
+
+        let std_socket = std::net::UdpSocket::bind(SocketAddr::new(address, config.port))?;
+        std_socket.set_nonblocking(true)?;
+        let socket = {
+            let _guard = runtime.enter();
+            UdpSocket::from_std(std_socket)?
+        };
+        info!("SNMP trap listener open on {}:{}", address, config.port);
+
+        let (tx, rx) = channel(config.channel_capacity.max(1));
+        let context = Arc::new(TrapContext {
+            config,
+            allowed_sources,
+            host: HostIdentityService::global(),
+            sender: tx,
+            rejected: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        });
+        let listener = runtime.spawn(context.clone().serve(socket));
+
+        Ok(Self {
+            context,
+            event_receiver: rx,
+            listener,
+        })
+    }
+
+    fn drain(&mut self) -> Vec<SnmpTrapEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.event_receiver.try_recv() {
+            events.push(event);
+        }
+
+        let rejected = self.context.rejected.swap(0, Ordering::Relaxed);
+        if rejected > 0 {
+            debug!("Ignored {} SNMP datagrams that were not accepted traps", rejected);
+        }
+        let dropped = self.context.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            warn!("Dropped {} SNMP traps, queue was full", dropped);
+        }
+        if !events.is_empty() {
+            info!("Received {} SNMP traps", events.len());
+        }
+        events
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if self.listener.is_finished() {
+            return Err(CollectionError::SystemApi(String::from("The SNMP trap listener is not running")));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SnmpTrapCollector {
+    fn drop(&mut self) {
+        self.listener.abort();
+    }
+}
+
+impl DataCollector<Vec<SnmpTrapEvent>> for SnmpTrapCollector {
+    fn collect(&mut self) -> Result<Vec<SnmpTrapEvent>, CollectionError> {
+        Ok(self.drain())
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<SnmpTrapEvent>> for SnmpTrapCollector {
+    async fn collect(&mut self) -> Result<Vec<SnmpTrapEvent>, CollectionError> {
+        Ok(self.drain())
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
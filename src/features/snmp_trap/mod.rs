@@ -0,0 +1,7 @@
+mod collector;
+mod models;
+mod parsers;
+
+pub use collector::{SnmpTrapCollector, SnmpTrapConfig, TrapRule};
+pub use models::{SnmpTrapEvent, SnmpTrapEventBuilder};
+pub use parsers::{parse_trap, SnmpTrap, SnmpVariable, SnmpVersion};
@@ -0,0 +1,163 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::features::snmp_trap::parsers::{SnmpTrap, SnmpVariable, SnmpVersion};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+// A trap or inform from a device on the local network (switch, UPS, printer), received by
+// the agent on its behalf. The community string is not kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnmpTrapEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub sender_address: String,
+    pub sender_port: u16,
+    // The device that raised the trap: the SNMPv1 agent address, otherwise the sender
+    pub device_address: String,
+    pub version: SnmpVersion,
+    pub inform: bool,
+    pub trap_oid: String,
+    // Name given to the trap OID by the configured rules
+    pub trap_name: Option<String>,
+    pub enterprise: Option<String>,
+    pub generic_trap: Option<i64>,
+    pub specific_trap: Option<i64>,
+    pub device_uptime: Option<u64>,
+    pub variables: Vec<SnmpVariable>,
+    pub severity: Severity,
+}
+
+impl Event for SnmpTrapEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        "snmp_trap"
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl Identifiable for SnmpTrapEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for SnmpTrapEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.sender_address.is_empty() {
+            return Err("Sender address cannot be empty".to_string());
+        }
+
+        if self.trap_oid.is_empty() {
+            return Err("Trap OID cannot be empty".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct SnmpTrapEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    sender_address: Option<String>,
+    sender_port: u16,
+    trap: Option<SnmpTrap>,
+    trap_name: Option<String>,
+    severity: Option<Severity>,
+}
+
+impl SnmpTrapEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn sender(mut self, address: String, port: u16) -> Self {
+        self.sender_address = Some(address);
+        self.sender_port = port;
+        self
+    }
+
+    pub fn trap(mut self, trap: SnmpTrap) -> Self {
+        self.trap = Some(trap);
+        self
+    }
+
+    pub fn trap_name(mut self, trap_name: Option<String>) -> Self {
+        self.trap_name = trap_name;
+        self
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    pub fn build(self) -> Result<SnmpTrapEvent, String> {
+        let sender_address = self.sender_address.ok_or("sender is required")?;
+        let trap = self.trap.ok_or("trap is required")?;
+        let event = SnmpTrapEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.unwrap_or_else(Utc::now),
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            // Agents that do not know their own address send 0.0.0.0
+            device_address: trap
+                .agent_address
+                .filter(|address| address != "0.0.0.0")
+                .unwrap_or_else(|| sender_address.clone()),
+            sender_address,
+            sender_port: self.sender_port,
+            version: trap.version,
+            inform: trap.inform,
+            trap_oid: trap.trap_oid,
+            trap_name: self.trap_name,
+            enterprise: trap.enterprise,
+            generic_trap: trap.generic_trap,
+            specific_trap: trap.specific_trap,
+            device_uptime: trap.uptime,
+            variables: trap.variables,
+            severity: self.severity.unwrap_or(Severity::Low),
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
@@ -0,0 +1,193 @@
+use crate::shared::ber::{integer, object_identifier, unsigned, Reader};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// sysUpTime.0, snmpTrapOID.0 and snmpTrapEnterprise.0, which SNMPv2 puts among the variables
+const SYS_UP_TIME: &str = "1.3.6.1.2.1.1.3.0";
+const SNMP_TRAP_OID: &str = "1.3.6.1.6.3.1.1.4.1.0";
+const SNMP_TRAP_ENTERPRISE: &str = "1.3.6.1.6.3.1.1.4.3.0";
+// snmpTraps, under which the generic SNMPv1 traps are numbered from coldStart
+const SNMP_TRAPS: &str = "1.3.6.1.6.3.1.1.5";
+
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_TRAP_V1: u8 = 0xA4;
+const TAG_INFORM: u8 = 0xA6;
+const TAG_TRAP_V2: u8 = 0xA7;
+const TAG_RESPONSE: u8 = 0xA2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnmpVersion {
+    V1,
+    V2c,
+}
+
+// One variable binding, its value as JSON: numbers for the integer types, dotted strings for
+// object identifiers and addresses, text or hex for octet strings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnmpVariable {
+    pub oid: String,
+    // integer, octet_string, object_identifier, ip_address, counter32, gauge32, timeticks, ...
+    pub kind: String,
+    pub value: Value,
+}
+
+// A trap or inform as received, with the SNMPv1 trap mapped to its SNMPv2 trap OID (RFC 3584)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnmpTrap {
+    pub version: SnmpVersion,
+    pub community: String,
+    // Informs wait for an acknowledgement
+    pub inform: bool,
+    pub trap_oid: String,
+    pub enterprise: Option<String>,
+    // Address of the device that raised an SNMPv1 trap, which may differ from the sender
+    pub agent_address: Option<String>,
+    pub generic_trap: Option<i64>,
+    pub specific_trap: Option<i64>,
+    // Hundredths of a second since the device's SNMP agent started
+    pub uptime: Option<u64>,
+    pub variables: Vec<SnmpVariable>,
+    // Where the PDU starts in the datagram
+    pdu_offset: usize,
+}
+
+impl SnmpTrap {
+    // The response acknowledging an inform: the same message with the PDU tagged as a
+    // response, which carries the inform's request id and variables with no error
+    pub fn acknowledgement(&self, datagram: &[u8]) -> Option<Vec<u8>> {
+        if !self.inform || datagram.get(self.pdu_offset) != Some(&TAG_INFORM) {
+            return None;
+        }
+        let mut response = datagram.to_vec();
+        response[self.pdu_offset] = TAG_RESPONSE;
+        Some(response)
+    }
+}
+
+fn ip_address(contents: &[u8]) -> Result<String, String> {
+    let octets: [u8; 4] = contents.try_into().map_err(|_| String::from("invalid IP address"))?;
+    Ok(std::net::Ipv4Addr::from(octets).to_string())
+}
+
+// Text when the octets are printable UTF-8, hex otherwise (MAC addresses, binary states)
+fn octet_string(contents: &[u8]) -> String {
+    match std::str::from_utf8(contents) {
+        Ok(text) if !text.chars().any(|c| c.is_control() && !matches!(c, '\t' | '\r' | '\n')) => text.to_string(),
+        _ => hex::encode(contents),
+    }
+}
+
+fn value(tag: u8, contents: &[u8]) -> Result<(&'static str, Value), String> {
+    Ok(match tag {
+        0x02 => ("integer", Value::from(integer(contents)?)),
+        0x04 => ("octet_string", Value::from(octet_string(contents))),
+        0x05 => ("null", Value::Null),
+        0x06 => ("object_identifier", Value::from(object_identifier(contents)?)),
+        0x40 => ("ip_address", Value::from(ip_address(contents)?)),
+        0x41 => ("counter32", Value::from(unsigned(contents)?)),
+        0x42 => ("gauge32", Value::from(unsigned(contents)?)),
+        0x43 => ("timeticks", Value::from(unsigned(contents)?)),
+        0x44 => ("opaque", Value::from(hex::encode(contents))),
+        0x46 => ("counter64", Value::from(unsigned(contents)?)),
+        0x80 => ("no_such_object", Value::Null),
+        0x81 => ("no_such_instance", Value::Null),
+        0x82 => ("end_of_mib_view", Value::Null),
+        _ => ("unknown", Value::from(hex::encode(contents))),
+    })
+}
+
+fn variables(contents: &[u8]) -> Result<Vec<SnmpVariable>, String> {
+    let mut reader = Reader::new(contents);
+    let mut variables = Vec::new();
+    while !reader.is_empty() {
+        let mut binding = Reader::new(reader.expect(TAG_SEQUENCE, "variable binding")?);
+        let oid = object_identifier(binding.expect(0x06, "variable name")?)?;
+        let (tag, contents) = binding.element()?;
+        let (kind, value) = value(tag, contents)?;
+        variables.push(SnmpVariable {
+            oid,
+            kind: kind.to_string(),
+            value,
+        });
+    }
+    Ok(variables)
+}
+
+// An SNMPv1 or SNMPv2c trap or inform. SNMPv3 messages, and any other PDU, are refused:
+// their authentication and privacy settings are not configured anywhere.
+pub fn parse_trap(datagram: &[u8]) -> Result<SnmpTrap, String> {
+    let mut outer = Reader::new(datagram);
+    let message = outer.expect(TAG_SEQUENCE, "message")?;
+    // Where the message's contents start, so offsets in `message` map to the datagram
+    let base = outer.position() - message.len();
+    let mut reader = Reader::new(message);
+    let version = match reader.integer("version")? {
+        0 => SnmpVersion::V1,
+        1 => SnmpVersion::V2c,
+        3 => return Err(String::from("SNMPv3 messages are not supported")),
+        other => return Err(format!("unknown SNMP version {}", other)),
+    };
+    let community = String::from_utf8_lossy(reader.expect(0x04, "community")?).into_owned();
+    let pdu_offset = base + reader.position();
+    let (tag, pdu) = reader.element()?;
+    let mut pdu = Reader::new(pdu);
+
+    match (version, tag) {
+        (SnmpVersion::V1, TAG_TRAP_V1) => {
+            let enterprise = object_identifier(pdu.expect(0x06, "enterprise")?)?;
+            let agent_address = ip_address(pdu.expect(0x40, "agent address")?)?;
+            let generic_trap = pdu.integer("generic trap")?;
+            let specific_trap = pdu.integer("specific trap")?;
+            let uptime = unsigned(pdu.expect(0x43, "time stamp")?)?;
+            let variables = variables(pdu.expect(TAG_SEQUENCE, "variable bindings")?)?;
+            // Generic traps 0-5 are coldStart..egpNeighborLoss, 6 is enterprise specific
+            let trap_oid = match generic_trap {
+                0..=5 => format!("{}.{}", SNMP_TRAPS, generic_trap + 1),
+                _ => format!("{}.0.{}", enterprise, specific_trap),
+            };
+            Ok(SnmpTrap {
+                version,
+                community,
+                inform: false,
+                trap_oid,
+                enterprise: Some(enterprise),
+                agent_address: Some(agent_address),
+                generic_trap: Some(generic_trap),
+                specific_trap: Some(specific_trap),
+                uptime: Some(uptime),
+                variables,
+                pdu_offset,
+            })
+        }
+        (SnmpVersion::V2c, TAG_TRAP_V2 | TAG_INFORM) => {
+            pdu.integer("request id")?;
+            pdu.integer("error status")?;
+            pdu.integer("error index")?;
+            let mut variables = variables(pdu.expect(TAG_SEQUENCE, "variable bindings")?)?;
+            let mut take = |oid: &str| {
+                let position = variables.iter().position(|variable| variable.oid == oid)?;
+                Some(variables.remove(position).value)
+            };
+            let uptime = take(SYS_UP_TIME).and_then(|value| value.as_u64());
+            let trap_oid = take(SNMP_TRAP_OID)
+                .and_then(|value| value.as_str().map(str::to_string))
+                .ok_or("trap without snmpTrapOID.0")?;
+            let enterprise = take(SNMP_TRAP_ENTERPRISE).and_then(|value| value.as_str().map(str::to_string));
+            Ok(SnmpTrap {
+                version,
+                community,
+                inform: tag == TAG_INFORM,
+                trap_oid,
+                enterprise,
+                agent_address: None,
+                generic_trap: None,
+                specific_trap: None,
+                uptime,
+                variables,
+                pdu_offset,
+            })
+        }
+        (_, tag) => Err(format!("PDU tag {:#04x} is not a trap", tag)),
+    }
+}
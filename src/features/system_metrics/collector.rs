@@ -1,5 +1,6 @@
 use crate::shared::traits::{AsyncDataCollector, DataCollector};
 use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
 use crate::features::system_metrics::models::{
     SystemMetrics, CpuInformation, MemoryInformation,
     DiskInformation, SystemLoadInformation, SystemMetricsBuilder
@@ -7,18 +8,18 @@ use crate::features::system_metrics::models::{
 use log::info;
 use sysinfo::{System, Disks};
 use chrono::Utc;
+use std::sync::Arc;
 use uuid::Uuid;
 
 pub struct SystemMetricsCollector {
     sys: System,
-    hostname: String,
+    host: Arc<HostIdentityService>,
 }
 
 impl SystemMetricsCollector {
     pub fn new() -> Self {
         let sys = System::new();
-        let hostname = whoami::hostname();
-        Self { sys, hostname }
+        Self { sys, host: HostIdentityService::global() }
     }
 
     pub fn collect_cpu_info(&self) -> Result<CpuInformation, CollectionError> {
@@ -88,7 +89,7 @@ impl DataCollector<SystemMetrics> for SystemMetricsCollector {
         let metrics = SystemMetricsBuilder::new()
             .id(Uuid::new_v4().to_string())
             .timestamp(Utc::now())
-            .source(self.hostname.clone())
+            .source(self.host.name())
             .category(String::from("system"))
             .cpu_info(self.collect_cpu_info()?)
             .memory_info(self.collect_memory_info()?)
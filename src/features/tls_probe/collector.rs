@@ -0,0 +1,395 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector, Severity};
+use crate::shared::baseline::Baseline;
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::features::tls_probe::models::{TlsFinding, TlsProbeEvent, TlsProbeEventBuilder};
+use crate::features::tls_probe::parsers::{parse_certificate, CertificateInfo};
+use crate::utils::load_config_section;
+use chrono::Utc;
+use log::{debug, info, warn};
+use native_tls::{Protocol, TlsConnector};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsEndpoint {
+    pub name: Option<String>,
+    // host:port, [v6 address]:port for IPv6
+    pub address: String,
+    // Name sent for SNI and checked against the certificate, the host of the address by default
+    pub server_name: Option<String>,
+    // Whether the chain and host name must validate; off for internal services with
+    // self-signed certificates
+    pub verify: Option<bool>,
+    // Part of the issuer name the certificate must carry, such as the internal CA's name
+    pub expected_issuer: Option<String>,
+}
+
+impl TlsEndpoint {
+    fn host_port(&self) -> Option<(&str, u16)> {
+        let (host, port) = self.address.rsplit_once(':')?;
+        let host = host.strip_prefix('[').and_then(|host| host.strip_suffix(']')).unwrap_or(host);
+        let port = port.parse().ok().filter(|port| *port != 0)?;
+        (!host.is_empty()).then_some((host, port))
+    }
+
+    fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.address.clone())
+    }
+
+    fn server_name(&self) -> String {
+        self.server_name
+            .clone()
+            .or_else(|| self.host_port().map(|(host, _)| host.to_string()))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsProbeConfig {
+    pub enabled: bool,
+    pub endpoints: Vec<TlsEndpoint>,
+    // Certificates rarely change; every probe makes several handshakes per endpoint
+    pub probe_interval_secs: u64,
+    pub timeout_secs: u64,
+    // A certificate expiring within this many days is a warning, within critical_days high severity
+    pub warning_days: i64,
+    pub critical_days: i64,
+    // Try TLS 1.0 and 1.1 handshakes, as far as the local TLS library still offers them
+    pub check_weak_protocols: bool,
+}
+
+impl Default for TlsProbeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoints: Vec::new(),
+            probe_interval_secs: 3600,
+            timeout_secs: 10,
+            warning_days: 30,
+            critical_days: 7,
+            check_weak_protocols: true,
+        }
+    }
+}
+
+impl TlsProbeConfig {
+    pub fn load() -> Self {
+        load_config_section("tls_probe")
+    }
+
+    // What keeps endpoints from being probed or findings from being raised, for the configuration check
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.enabled {
+            return problems;
+        }
+        if self.endpoints.is_empty() {
+            problems.push(String::from("tls_probe is enabled without any endpoints"));
+        }
+        for (position, endpoint) in self.endpoints.iter().enumerate() {
+            if endpoint.host_port().is_none() {
+                problems.push(format!(
+                    "tls_probe.endpoints[{}].address '{}' is not host:port",
+                    position, endpoint.address
+                ));
+            }
+        }
+        if self.timeout_secs == 0 {
+            problems.push(String::from("tls_probe.timeout_secs must be greater than 0"));
+        }
+        if self.critical_days > self.warning_days {
+            problems.push(format!(
+                "tls_probe.critical_days ({}) is longer than warning_days ({})",
+                self.critical_days, self.warning_days
+            ));
+        }
+        problems
+    }
+}
+
+// What one probe of an endpoint found
+struct Probe {
+    certificate: CertificateInfo,
+    // Why the certificate did not validate
+    untrusted: Option<String>,
+    weak_protocols: Vec<String>,
+}
+
+// What is remembered of an endpoint between probes, and across restarts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct EndpointState {
+    sha256: Option<String>,
+    issuer: Option<String>,
+    // Findings already reported for the current certificate
+    reported: BTreeSet<String>,
+}
+
+const ENDPOINTS_BASELINE: &str = "tls_probe_endpoints";
+
+// Old protocol versions tried against every endpoint
+const WEAK_PROTOCOLS: [(Protocol, &str); 2] = [(Protocol::Tlsv10, "TLSv1.0"), (Protocol::Tlsv11, "TLSv1.1")];
+
+// Connects to the TLS endpoints in the configuration (local services and remote ones this host
+// depends on) and checks the certificate each presents: expiry ahead of time so renewals are
+// not missed, old protocol versions still accepted, and a certificate replaced by one from
+// another issuer, which is how an intercepting proxy or a fraudulently issued certificate
+// shows. Each finding is reported once per certificate.
+pub struct TlsProbeCollector {
+    config: TlsProbeConfig,
+    host: Arc<HostIdentityService>,
+    endpoints: HashMap<String, EndpointState>,
+    last_probe: Option<Instant>,
+}
+
+impl TlsProbeCollector {
+    pub fn new() -> Self {
+        Self::with_config(TlsProbeConfig::load())
+    }
+
+    pub fn with_config(config: TlsProbeConfig) -> Self {
+        Self {
+            config,
+            host: HostIdentityService::global(),
+            endpoints: Baseline::global().load(ENDPOINTS_BASELINE).unwrap_or_default(),
+            last_probe: None,
+        }
+    }
+
+    fn connect(&self, endpoint: &TlsEndpoint) -> Result<TcpStream, String> {
+        let (host, port) = endpoint.host_port().ok_or_else(|| format!("invalid address {}", endpoint.address))?;
+        let timeout = Duration::from_secs(self.config.timeout_secs.max(1));
+        let address = (host, port)
+            .to_socket_addrs()
+            .map_err(|e| format!("cannot resolve {}: {}", host, e))?
+            .next()
+            .ok_or_else(|| format!("{} has no address", host))?;
+        let stream = TcpStream::connect_timeout(&address, timeout).map_err(|e| format!("cannot connect to {}: {}", address, e))?;
+        stream.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+        stream.set_write_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+        Ok(stream)
+    }
+
+    // A handshake with the given settings, and the certificate the server presented
+    fn handshake(&self, endpoint: &TlsEndpoint, verify: bool, protocol: Option<Protocol>) -> Result<Option<CertificateInfo>, String> {
+        let mut builder = TlsConnector::builder();
+        builder.danger_accept_invalid_certs(!verify).danger_accept_invalid_hostnames(!verify);
+        if protocol.is_some() {
+            builder.min_protocol_version(protocol).max_protocol_version(protocol);
+        }
+        let connector = builder.build().map_err(|e| e.to_string())?;
+        let stream = self.connect(endpoint)?;
+        let tls = connector.connect(&endpoint.server_name(), stream).map_err(|e| e.to_string())?;
+        let certificate = tls.peer_certificate().map_err(|e| e.to_string())?;
+        certificate
+            .map(|certificate| certificate.to_der().map_err(|e| e.to_string()).and_then(|der| parse_certificate(&der)))
+            .transpose()
+    }
+
+    fn probe(&self, endpoint: &TlsEndpoint) -> Result<Probe, String> {
+        // Connection errors are told apart from a certificate that does not validate
+        drop(self.connect(endpoint)?);
+        let verify = endpoint.verify.unwrap_or(true);
+        let (certificate, untrusted) = match self.handshake(endpoint, verify, None) {
+            Ok(certificate) => (certificate, None),
+            Err(e) if verify => (self.handshake(endpoint, false, None)?, Some(e)),
+            Err(e) => return Err(e),
+        };
+        let certificate = certificate.ok_or("the server presented no certificate")?;
+
+        let mut weak_protocols = Vec::new();
+        if self.config.check_weak_protocols {
+            for (protocol, name) in WEAK_PROTOCOLS {
+                match self.handshake(endpoint, false, Some(protocol)) {
+                    Ok(_) => weak_protocols.push(name.to_string()),
+                    Err(e) => debug!("{} refused {}: {}", endpoint.address, name, e),
+                }
+            }
+        }
+        Ok(Probe {
+            certificate,
+            untrusted,
+            weak_protocols,
+        })
+    }
+
+    fn event(&self, endpoint: &TlsEndpoint, finding: TlsFinding, severity: Severity) -> TlsProbeEventBuilder {
+        TlsProbeEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(Utc::now())
+            .source(self.host.name())
+            .category(String::from("tls_probe"))
+            .endpoint(endpoint.label(), endpoint.address.clone(), endpoint.server_name())
+            .finding(finding)
+            .severity(severity)
+    }
+
+    // Findings of one probe, with the key each is remembered under for the certificate
+    fn findings(&self, endpoint: &TlsEndpoint, probe: &Probe) -> Vec<(String, TlsFinding, Severity, Option<String>)> {
+        let certificate = &probe.certificate;
+        let mut findings = Vec::new();
+        let days_remaining = (certificate.not_after - Utc::now()).num_days();
+        if certificate.not_after <= Utc::now() {
+            findings.push((String::from("expired"), TlsFinding::CertificateExpired, Severity::High, None));
+        } else if days_remaining < self.config.critical_days {
+            findings.push((String::from("expiring_critical"), TlsFinding::CertificateExpiring, Severity::High, None));
+        } else if days_remaining < self.config.warning_days {
+            findings.push((String::from("expiring"), TlsFinding::CertificateExpiring, Severity::Medium, None));
+        }
+        if let Some(error) = &probe.untrusted {
+            findings.push((String::from("untrusted"), TlsFinding::CertificateUntrusted, Severity::Medium, Some(error.clone())));
+        }
+        if let Some(expected) = &endpoint.expected_issuer {
+            if !certificate.issuer.to_lowercase().contains(&expected.to_lowercase()) {
+                let detail = format!("expected an issuer containing '{}'", expected);
+                findings.push((String::from("unexpected_issuer"), TlsFinding::UnexpectedIssuer, Severity::High, Some(detail)));
+            }
+        }
+        for protocol in &probe.weak_protocols {
+            findings.push((format!("weak_protocol:{}", protocol), TlsFinding::WeakProtocol, Severity::Medium, None));
+        }
+        let weaknesses = certificate.weaknesses();
+        if !weaknesses.is_empty() {
+            findings.push((String::from("weak_certificate"), TlsFinding::WeakCertificate, Severity::Medium, Some(weaknesses.join(", "))));
+        }
+        findings
+    }
+
+    fn endpoint_events(&mut self, endpoint: &TlsEndpoint) -> Result<Vec<TlsProbeEvent>, CollectionError> {
+        let key = endpoint.address.clone();
+        let mut state = self.endpoints.get(&key).cloned().unwrap_or_default();
+        let mut events = Vec::new();
+
+        let probe = match self.probe(endpoint) {
+            Ok(probe) => probe,
+            Err(e) => {
+                debug!("TLS probe of {} failed: {}", endpoint.address, e);
+                if state.reported.insert(String::from("unreachable")) {
+                    events.push(
+                        self.event(endpoint, TlsFinding::EndpointUnreachable, Severity::Medium)
+                            .detail(Some(e))
+                            .build()
+                            .map_err(CollectionError::Parse)?,
+                    );
+                }
+                self.endpoints.insert(key, state);
+                return Ok(events);
+            }
+        };
+        let certificate = &probe.certificate;
+
+        if state.sha256.as_ref().is_some_and(|sha256| *sha256 != certificate.sha256) {
+            // A different issuer may be an intercepting proxy or a certificate obtained by
+            // someone else; a renewal from the same CA is only recorded
+            let (finding, severity) = if state.issuer.as_deref() == Some(certificate.issuer.as_str()) {
+                (TlsFinding::CertificateRenewed, Severity::Low)
+            } else {
+                (TlsFinding::IssuerChanged, Severity::High)
+            };
+            events.push(
+                self.event(endpoint, finding, severity)
+                    .certificate(Some(certificate.clone()))
+                    .previous_issuer(state.issuer.clone())
+                    .build()
+                    .map_err(CollectionError::Parse)?,
+            );
+            state.reported.clear();
+        }
+
+        let findings = self.findings(endpoint, &probe);
+        let mut reported = BTreeSet::new();
+        for (finding_key, finding, severity, detail) in findings {
+            if !state.reported.contains(&finding_key) {
+                let weak_protocols = match finding {
+                    TlsFinding::WeakProtocol => vec![finding_key.trim_start_matches("weak_protocol:").to_string()],
+                    _ => Vec::new(),
+                };
+                events.push(
+                    self.event(endpoint, finding, severity)
+                        .certificate(Some(certificate.clone()))
+                        .weak_protocols(weak_protocols)
+                        .detail(detail)
+                        .build()
+                        .map_err(CollectionError::Parse)?,
+                );
+            }
+            reported.insert(finding_key);
+        }
+        // Findings that no longer apply are reported again if they come back
+        state.reported = reported;
+        state.sha256 = Some(certificate.sha256.clone());
+        state.issuer = Some(certificate.issuer.clone());
+        self.endpoints.insert(key, state);
+        Ok(events)
+    }
+
+    fn collect_events(&mut self) -> Result<Vec<TlsProbeEvent>, CollectionError> {
+        let interval = Duration::from_secs(self.config.probe_interval_secs);
+        if self.last_probe.is_some_and(|last_probe| last_probe.elapsed() < interval) {
+            return Ok(Vec::new());
+        }
+        self.last_probe = Some(Instant::now());
+
+        let mut events = Vec::new();
+        for endpoint in self.config.endpoints.clone() {
+            match self.endpoint_events(&endpoint) {
+                Ok(found) => events.extend(found),
+                Err(e) => warn!("Failed to report the TLS probe of {}: {}", endpoint.address, e),
+            }
+        }
+        // Endpoints removed from the configuration are forgotten
+        let configured: Vec<&String> = self.config.endpoints.iter().map(|endpoint| &endpoint.address).collect();
+        self.endpoints.retain(|address, _| configured.contains(&address));
+        Baseline::global().save(ENDPOINTS_BASELINE, &self.endpoints);
+
+        info!("Probed {} TLS endpoints, {} findings", self.config.endpoints.len(), events.len());
+        Ok(events)
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        TlsConnector::new()
+            .map(|_| ())
+            .map_err(|e| CollectionError::SystemApi(format!("TLS is unavailable: {}", e)))
+    }
+}
+
+impl DataCollector<Vec<TlsProbeEvent>> for TlsProbeCollector {
+    fn collect(&mut self) -> Result<Vec<TlsProbeEvent>, CollectionError> {
+        self.collect_events()
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<TlsProbeEvent>> for TlsProbeCollector {
+    async fn collect(&mut self) -> Result<Vec<TlsProbeEvent>, CollectionError> {
+        DataCollector::collect(self)
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+impl Default for TlsProbeCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
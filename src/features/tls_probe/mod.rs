@@ -0,0 +1,7 @@
+pub mod models;
+pub mod collector;
+pub mod parsers;
+
+pub use models::{TlsFinding, TlsProbeEvent, TlsProbeEventBuilder};
+pub use collector::{TlsEndpoint, TlsProbeCollector, TlsProbeConfig};
+pub use parsers::{parse_certificate, CertificateInfo};
@@ -0,0 +1,220 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::features::tls_probe::parsers::CertificateInfo;
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsFinding {
+    // Expires within the warning or critical days
+    CertificateExpiring,
+    CertificateExpired,
+    // The endpoint still accepts TLS 1.0 or 1.1
+    WeakProtocol,
+    // MD5 / SHA-1 signature or a short RSA key
+    WeakCertificate,
+    // The chain or host name does not validate
+    CertificateUntrusted,
+    // The issuer is not the one the endpoint is configured to expect
+    UnexpectedIssuer,
+    // The certificate was replaced by one from another issuer
+    IssuerChanged,
+    // The certificate was replaced by one from the same issuer
+    CertificateRenewed,
+    EndpointUnreachable,
+}
+
+impl TlsFinding {
+    pub fn event_type(self) -> &'static str {
+        match self {
+            Self::CertificateExpiring => "certificate_expiring",
+            Self::CertificateExpired => "certificate_expired",
+            Self::WeakProtocol => "weak_protocol",
+            Self::WeakCertificate => "weak_certificate",
+            Self::CertificateUntrusted => "certificate_untrusted",
+            Self::UnexpectedIssuer => "unexpected_issuer",
+            Self::IssuerChanged => "issuer_changed",
+            Self::CertificateRenewed => "certificate_renewed",
+            Self::EndpointUnreachable => "endpoint_unreachable",
+        }
+    }
+}
+
+// Something the TLS probe found on a configured endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsProbeEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub finding: TlsFinding,
+    // The endpoint's configured name, or its address
+    pub endpoint: String,
+    pub address: String,
+    pub server_name: String,
+    pub subject: Option<String>,
+    pub issuer: Option<String>,
+    // Issuer of the certificate that was replaced
+    pub previous_issuer: Option<String>,
+    pub serial: Option<String>,
+    pub sha256: Option<String>,
+    pub not_after: Option<DateTime<Utc>>,
+    pub days_remaining: Option<i64>,
+    pub alt_names: Vec<String>,
+    pub weak_protocols: Vec<String>,
+    // Validation or connection error, or what makes the certificate weak
+    pub detail: Option<String>,
+    pub severity: Severity,
+}
+
+impl Event for TlsProbeEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        self.finding.event_type()
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl Identifiable for TlsProbeEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for TlsProbeEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.address.is_empty() {
+            return Err("Endpoint address cannot be empty".to_string());
+        }
+
+        if self.finding != TlsFinding::EndpointUnreachable && self.sha256.is_none() {
+            return Err("A certificate finding needs the certificate".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct TlsProbeEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    finding: Option<TlsFinding>,
+    endpoint: Option<String>,
+    address: Option<String>,
+    server_name: Option<String>,
+    certificate: Option<CertificateInfo>,
+    previous_issuer: Option<String>,
+    weak_protocols: Vec<String>,
+    detail: Option<String>,
+    severity: Option<Severity>,
+}
+
+impl TlsProbeEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn finding(mut self, finding: TlsFinding) -> Self {
+        self.finding = Some(finding);
+        self
+    }
+
+    pub fn endpoint(mut self, endpoint: String, address: String, server_name: String) -> Self {
+        self.endpoint = Some(endpoint);
+        self.address = Some(address);
+        self.server_name = Some(server_name);
+        self
+    }
+
+    pub fn certificate(mut self, certificate: Option<CertificateInfo>) -> Self {
+        self.certificate = certificate;
+        self
+    }
+
+    pub fn previous_issuer(mut self, previous_issuer: Option<String>) -> Self {
+        self.previous_issuer = previous_issuer;
+        self
+    }
+
+    pub fn weak_protocols(mut self, weak_protocols: Vec<String>) -> Self {
+        self.weak_protocols = weak_protocols;
+        self
+    }
+
+    pub fn detail(mut self, detail: Option<String>) -> Self {
+        self.detail = detail;
+        self
+    }
+
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    pub fn build(self) -> Result<TlsProbeEvent, String> {
+        let timestamp = self.timestamp.unwrap_or_else(Utc::now);
+        let certificate = self.certificate;
+        let event = TlsProbeEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            finding: self.finding.ok_or("finding is required")?,
+            endpoint: self.endpoint.ok_or("endpoint is required")?,
+            address: self.address.ok_or("address is required")?,
+            server_name: self.server_name.ok_or("server name is required")?,
+            subject: certificate.as_ref().map(|certificate| certificate.subject.clone()),
+            issuer: certificate.as_ref().map(|certificate| certificate.issuer.clone()),
+            previous_issuer: self.previous_issuer,
+            serial: certificate.as_ref().map(|certificate| certificate.serial.clone()),
+            sha256: certificate.as_ref().map(|certificate| certificate.sha256.clone()),
+            not_after: certificate.as_ref().map(|certificate| certificate.not_after),
+            days_remaining: certificate.as_ref().map(|certificate| (certificate.not_after - timestamp).num_days()),
+            alt_names: certificate.map(|certificate| certificate.alt_names).unwrap_or_default(),
+            weak_protocols: self.weak_protocols,
+            detail: self.detail,
+            severity: self.severity.unwrap_or(Severity::Medium),
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
@@ -0,0 +1,256 @@
+use crate::shared::ber::{object_identifier, Reader};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_VERSION: u8 = 0xA0;
+const TAG_EXTENSIONS: u8 = 0xA3;
+const SUBJECT_ALT_NAME: &str = "2.5.29.17";
+const RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.1";
+const EC_PUBLIC_KEY: &str = "1.2.840.10045.2.1";
+
+// What the probe looks at in a server certificate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    // Distinguished names as OpenSSL prints them: C=US, O=Let's Encrypt, CN=R3
+    pub subject: String,
+    pub issuer: String,
+    // Hex
+    pub serial: String,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    pub signature_algorithm: String,
+    pub key_algorithm: String,
+    // RSA modulus or elliptic curve size
+    pub key_bits: Option<u32>,
+    // DNS names and IP addresses of the subject alternative names
+    pub alt_names: Vec<String>,
+    // Of the whole DER encoding, the certificate's identity
+    pub sha256: String,
+}
+
+impl CertificateInfo {
+    pub fn self_signed(&self) -> bool {
+        self.subject == self.issuer
+    }
+
+    // What makes the certificate itself weak, whatever the protocol
+    pub fn weaknesses(&self) -> Vec<String> {
+        let mut weaknesses = Vec::new();
+        let signature = self.signature_algorithm.to_lowercase();
+        if signature.starts_with("md5") || signature.starts_with("sha1") || signature == "ecdsa-with-sha1" {
+            weaknesses.push(format!("{} signature", self.signature_algorithm));
+        }
+        if self.key_algorithm == "RSA" && self.key_bits.is_some_and(|bits| bits < 2048) {
+            weaknesses.push(format!("{}-bit RSA key", self.key_bits.unwrap_or_default()));
+        }
+        weaknesses
+    }
+}
+
+fn algorithm_name(oid: &str) -> String {
+    match oid {
+        "1.2.840.113549.1.1.4" => "md5WithRSAEncryption",
+        "1.2.840.113549.1.1.5" => "sha1WithRSAEncryption",
+        "1.2.840.113549.1.1.10" => "rsassaPss",
+        "1.2.840.113549.1.1.11" => "sha256WithRSAEncryption",
+        "1.2.840.113549.1.1.12" => "sha384WithRSAEncryption",
+        "1.2.840.113549.1.1.13" => "sha512WithRSAEncryption",
+        "1.2.840.10045.4.1" => "ecdsa-with-SHA1",
+        "1.2.840.10045.4.3.2" => "ecdsa-with-SHA256",
+        "1.2.840.10045.4.3.3" => "ecdsa-with-SHA384",
+        "1.2.840.10045.4.3.4" => "ecdsa-with-SHA512",
+        "1.3.101.112" => "Ed25519",
+        "1.3.101.113" => "Ed448",
+        oid => return oid.to_string(),
+    }
+    .to_string()
+}
+
+fn attribute_name(oid: &str) -> String {
+    match oid {
+        "2.5.4.3" => "CN",
+        "2.5.4.5" => "serialNumber",
+        "2.5.4.6" => "C",
+        "2.5.4.7" => "L",
+        "2.5.4.8" => "ST",
+        "2.5.4.10" => "O",
+        "2.5.4.11" => "OU",
+        "0.9.2342.19200300.100.1.25" => "DC",
+        "1.2.840.113549.1.9.1" => "emailAddress",
+        oid => return oid.to_string(),
+    }
+    .to_string()
+}
+
+// The string types names are written in; BMPString is UTF-16
+fn directory_string(tag: u8, contents: &[u8]) -> String {
+    match tag {
+        0x1E => {
+            let units: Vec<u16> = contents.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(contents).into_owned(),
+    }
+}
+
+fn name(contents: &[u8]) -> Result<String, String> {
+    let mut names = Vec::new();
+    let mut sets = Reader::new(contents);
+    while !sets.is_empty() {
+        let mut attributes = Reader::new(sets.expect(TAG_SET, "relative distinguished name")?);
+        while !attributes.is_empty() {
+            let mut attribute = Reader::new(attributes.expect(TAG_SEQUENCE, "name attribute")?);
+            let oid = object_identifier(attribute.expect(0x06, "attribute type")?)?;
+            let (tag, value) = attribute.element()?;
+            names.push(format!("{}={}", attribute_name(&oid), directory_string(tag, value)));
+        }
+    }
+    Ok(names.join(", "))
+}
+
+// UTCTime (two-digit years, 1950-2049) or GeneralizedTime, in UTC
+fn time(tag: u8, contents: &[u8]) -> Result<DateTime<Utc>, String> {
+    let text = std::str::from_utf8(contents).map_err(|_| String::from("invalid time"))?;
+    let digits = text.trim_end_matches('Z');
+    let full = match tag {
+        0x17 => {
+            let year: u32 = digits.get(..2).and_then(|year| year.parse().ok()).ok_or("invalid UTCTime")?;
+            format!("{}{}", if year < 50 { "20" } else { "19" }, digits)
+        }
+        0x18 => digits.to_string(),
+        tag => return Err(format!("unexpected time tag {:#04x}", tag)),
+    };
+    let full = full.get(..14).ok_or("truncated time")?;
+    NaiveDateTime::parse_from_str(full, "%Y%m%d%H%M%S")
+        .map(|naive| naive.and_utc())
+        .map_err(|e| format!("invalid time {}: {}", text, e))
+}
+
+fn algorithm(contents: &[u8]) -> Result<(String, Option<String>), String> {
+    let mut reader = Reader::new(contents);
+    let oid = object_identifier(reader.expect(0x06, "algorithm")?)?;
+    // Named curve of an EC key
+    let parameter = match reader.element() {
+        Ok((0x06, parameter)) => Some(object_identifier(parameter)?),
+        _ => None,
+    };
+    Ok((oid, parameter))
+}
+
+fn public_key(contents: &[u8]) -> Result<(String, Option<u32>), String> {
+    let mut reader = Reader::new(contents);
+    let (oid, parameter) = algorithm(reader.expect(TAG_SEQUENCE, "public key algorithm")?)?;
+    let key = reader.expect(0x03, "public key")?;
+    Ok(match oid.as_str() {
+        RSA_ENCRYPTION => {
+            // The bit string starts with its count of unused bits
+            let mut rsa = Reader::new(key.get(1..).unwrap_or_default());
+            let mut rsa = Reader::new(rsa.expect(TAG_SEQUENCE, "RSA public key")?);
+            let modulus = rsa.expect(0x02, "modulus")?;
+            let modulus = match modulus {
+                [0, rest @ ..] => rest,
+                modulus => modulus,
+            };
+            let bits = modulus
+                .first()
+                .map(|first| (modulus.len() as u32 * 8).saturating_sub(first.leading_zeros()));
+            (String::from("RSA"), bits)
+        }
+        EC_PUBLIC_KEY => {
+            let bits = match parameter.as_deref() {
+                Some("1.2.840.10045.3.1.7") => Some(256),
+                Some("1.3.132.0.34") => Some(384),
+                Some("1.3.132.0.35") => Some(521),
+                _ => None,
+            };
+            (String::from("EC"), bits)
+        }
+        "1.3.101.112" => (String::from("Ed25519"), Some(256)),
+        oid => (algorithm_name(oid), None),
+    })
+}
+
+fn alt_names(contents: &[u8]) -> Result<Vec<String>, String> {
+    let mut names = Vec::new();
+    let mut reader = Reader::new(contents);
+    let mut general_names = Reader::new(reader.expect(TAG_SEQUENCE, "subject alternative names")?);
+    while !general_names.is_empty() {
+        match general_names.element()? {
+            // dNSName
+            (0x82, name) => names.push(String::from_utf8_lossy(name).into_owned()),
+            // iPAddress
+            (0x87, address) => match address.len() {
+                4 => names.push(Ipv4Addr::from(<[u8; 4]>::try_from(address).unwrap_or_default()).to_string()),
+                16 => names.push(Ipv6Addr::from(<[u8; 16]>::try_from(address).unwrap_or_default()).to_string()),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    Ok(names)
+}
+
+fn extensions(contents: &[u8]) -> Result<Vec<String>, String> {
+    let mut reader = Reader::new(contents);
+    let mut extensions = Reader::new(reader.expect(TAG_SEQUENCE, "extensions")?);
+    while !extensions.is_empty() {
+        let mut extension = Reader::new(extensions.expect(TAG_SEQUENCE, "extension")?);
+        let oid = object_identifier(extension.expect(0x06, "extension id")?)?;
+        // The critical flag is optional
+        let value = match extension.element()? {
+            (0x01, _) => extension.expect(0x04, "extension value")?,
+            (0x04, value) => value,
+            (tag, _) => return Err(format!("unexpected extension tag {:#04x}", tag)),
+        };
+        if oid == SUBJECT_ALT_NAME {
+            return alt_names(value);
+        }
+    }
+    Ok(Vec::new())
+}
+
+// A DER-encoded X.509 certificate
+pub fn parse_certificate(der: &[u8]) -> Result<CertificateInfo, String> {
+    let mut outer = Reader::new(der);
+    let mut certificate = Reader::new(outer.expect(TAG_SEQUENCE, "certificate")?);
+    let mut tbs = Reader::new(certificate.expect(TAG_SEQUENCE, "certificate body")?);
+    let (signature_algorithm, _) = algorithm(certificate.expect(TAG_SEQUENCE, "signature algorithm")?)?;
+
+    if tbs.peek() == Some(TAG_VERSION) {
+        tbs.element()?;
+    }
+    let serial = hex::encode(tbs.expect(0x02, "serial number")?);
+    tbs.expect(TAG_SEQUENCE, "signature")?;
+    let issuer = name(tbs.expect(TAG_SEQUENCE, "issuer")?)?;
+    let mut validity = Reader::new(tbs.expect(TAG_SEQUENCE, "validity")?);
+    let (tag, contents) = validity.element()?;
+    let not_before = time(tag, contents)?;
+    let (tag, contents) = validity.element()?;
+    let not_after = time(tag, contents)?;
+    let subject = name(tbs.expect(TAG_SEQUENCE, "subject")?)?;
+    let (key_algorithm, key_bits) = public_key(tbs.expect(TAG_SEQUENCE, "subject public key")?)?;
+    let mut alt_names = Vec::new();
+    while !tbs.is_empty() {
+        // Issuer and subject unique ids come before the extensions
+        if let (TAG_EXTENSIONS, contents) = tbs.element()? {
+            alt_names = extensions(contents)?;
+        }
+    }
+
+    Ok(CertificateInfo {
+        subject,
+        issuer,
+        serial,
+        not_before,
+        not_after,
+        signature_algorithm: algorithm_name(&signature_algorithm),
+        key_algorithm,
+        key_bits,
+        alt_names,
+        sha256: format!("{:x}", Sha256::digest(der)),
+    })
+}
@@ -0,0 +1,378 @@
+use crate::shared::traits::{AsyncDataCollector, DataCollector};
+use crate::shared::error::CollectionError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::matchers::ExtensionSet;
+use crate::features::usb::models::{RemovableDrive, UsbAction, UsbEvent, UsbEventBuilder};
+use crate::utils::load_config_section;
+use chrono::{DateTime, Utc};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsbConfig {
+    pub enabled: bool,
+    // Wait after a device notification so the drive has time to be mounted
+    pub settle_secs: u64,
+    pub scan_executables: bool,
+    // Lowercase extensions treated as executable; extensionless files are checked for MZ / ELF headers
+    pub executable_extensions: Vec<String>,
+    pub max_scan_depth: usize,
+    pub max_scan_files: usize,
+    // Larger executables are reported without a hash
+    pub max_hash_bytes: u64,
+}
+
+impl Default for UsbConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            settle_secs: 2,
+            scan_executables: true,
+            executable_extensions: [
+                "exe", "dll", "sys", "scr", "com", "cpl", "msi", "bat", "cmd", "ps1", "vbs", "vbe",
+                "js", "jse", "wsf", "hta", "lnk", "jar", "sh", "elf", "appimage",
+            ]
+            .iter()
+            .map(|extension| extension.to_string())
+            .collect(),
+            max_scan_depth: 6,
+            max_scan_files: 20000,
+            max_hash_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl UsbConfig {
+    pub fn load() -> Self {
+        load_config_section("usb")
+    }
+}
+
+#[cfg(target_os = "linux")]
+use crate::features::usb::linux::{enumerate as enumerate_drives, watch as watch_devices};
+#[cfg(windows)]
+use crate::features::usb::win32::{enumerate as enumerate_drives, watch as watch_devices};
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn enumerate_drives() -> Vec<RemovableDrive> {
+    Vec::new()
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn watch_devices(_notify: std::sync::mpsc::SyncSender<()>) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "no device notifications on this platform"))
+}
+
+// Drives currently attached plus insert / remove transitions not yet collected
+#[derive(Default)]
+struct DriveState {
+    drives: HashMap<String, RemovableDrive>,
+    initialized: bool,
+    pending: Vec<(DateTime<Utc>, UsbAction, RemovableDrive)>,
+}
+
+impl DriveState {
+    fn refresh(&mut self) {
+        let current: HashMap<String, RemovableDrive> = enumerate_drives()
+            .into_iter()
+            .map(|drive| (drive.key(), drive))
+            .collect();
+
+        // Drives attached before the agent started are not insertions
+        if !self.initialized {
+            self.initialized = true;
+            if !current.is_empty() {
+                info!("{} removable drives already attached", current.len());
+            }
+            self.drives = current;
+            return;
+        }
+
+        let now = Utc::now();
+        for (key, drive) in &current {
+            if !self.drives.contains_key(key) {
+                info!("Removable drive {} inserted", drive.device);
+                self.pending.push((now, UsbAction::Inserted, drive.clone()));
+            }
+        }
+        for (key, drive) in &self.drives {
+            if !current.contains_key(key) {
+                info!("Removable drive {} removed", drive.device);
+                self.pending.push((now, UsbAction::Removed, drive.clone()));
+            }
+        }
+        self.drives = current;
+    }
+}
+
+// Executables seen on one drive: path -> (modified, size)
+type ExecutableIndex = HashMap<PathBuf, (SystemTime, u64)>;
+
+// Reports removable drive insertion and removal with vendor, serial, mount point and
+// capacity, and flags executables that appear on attached removable media. Device
+// notifications (udev uevents / WM_DEVICECHANGE) trigger an immediate refresh so short
+// insertions are not missed between collections.
+pub struct UsbCollector {
+    config: UsbConfig,
+    // config.executable_extensions, compiled once
+    executable_extensions: ExtensionSet,
+    state: Arc<Mutex<DriveState>>,
+    executables: HashMap<String, ExecutableIndex>,
+    watching: bool,
+    host: Arc<HostIdentityService>,
+}
+
+impl UsbCollector {
+    pub fn new() -> Self {
+        Self::with_config(UsbConfig::load())
+    }
+
+    pub fn with_config(config: UsbConfig) -> Self {
+        let state = Arc::new(Mutex::new(DriveState::default()));
+        if let Ok(mut state) = state.lock() {
+            state.refresh();
+        }
+
+        let (tx, rx) = sync_channel(1);
+        let watching = match watch_devices(tx) {
+            Ok(()) => {
+                Self::start_refresher(rx, Arc::downgrade(&state), Duration::from_secs(config.settle_secs));
+                true
+            }
+            Err(e) => {
+                warn!("Removable media notifications unavailable, polling on collection: {}", e);
+                false
+            }
+        };
+
+        Self {
+            executable_extensions: ExtensionSet::new(&config.executable_extensions),
+            config,
+            state,
+            executables: HashMap::new(),
+            watching,
+            host: HostIdentityService::global(),
+        }
+    }
+
+    fn start_refresher(notifications: Receiver<()>, state: Weak<Mutex<DriveState>>, settle: Duration) {
+        let spawned = thread::Builder::new()
+            .name(String::from("usb-refresh"))
+            .spawn(move || loop {
+                match notifications.recv_timeout(Duration::from_secs(1)) {
+                    Ok(()) => {}
+                    Err(RecvTimeoutError::Timeout) => {
+                        if state.strong_count() == 0 {
+                            return;
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+                thread::sleep(settle);
+                // Collapse the burst of notifications one insertion produces
+                while notifications.try_recv().is_ok() {}
+
+                let Some(shared) = state.upgrade() else {
+                    return;
+                };
+                if let Ok(mut drives) = shared.lock() {
+                    drives.refresh();
+                };
+            });
+        if let Err(e) = spawned {
+            error!("Failed to start removable media refresher: {}", e);
+        }
+    }
+
+    fn is_executable(&self, path: &Path) -> bool {
+        match path.extension() {
+            Some(_) => self.executable_extensions.contains(path),
+            None => {
+                let mut header = [0u8; 4];
+                match File::open(path).and_then(|mut file| file.read_exact(&mut header)) {
+                    Ok(()) => header.starts_with(b"MZ") || header == *b"\x7fELF",
+                    Err(_) => false,
+                }
+            }
+        }
+    }
+
+    fn scan(&self, mount_points: &[String]) -> ExecutableIndex {
+        let mut index = ExecutableIndex::new();
+        let mut visited = 0usize;
+        let mut pending: Vec<(PathBuf, usize)> = mount_points.iter().map(|mount| (PathBuf::from(mount), 0)).collect();
+
+        while let Some((directory, depth)) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&directory) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                visited += 1;
+                if visited > self.config.max_scan_files {
+                    debug!("Stopped removable media scan after {} entries", self.config.max_scan_files);
+                    return index;
+                }
+                // Symlinks are not followed off the drive
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                let path = entry.path();
+                if metadata.is_dir() {
+                    if depth < self.config.max_scan_depth {
+                        pending.push((path, depth + 1));
+                    }
+                } else if metadata.is_file() && self.is_executable(&path) {
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    index.insert(path, (modified, metadata.len()));
+                }
+            }
+        }
+
+        index
+    }
+
+    fn hash(&self, path: &Path, size: u64) -> Option<String> {
+        if size > self.config.max_hash_bytes {
+            return None;
+        }
+        let mut file = File::open(path).ok()?;
+        let mut hasher = Sha256::new();
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer).ok()?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Some(hex::encode(hasher.finalize()))
+    }
+
+    fn event(&self, timestamp: DateTime<Utc>, action: UsbAction, drive: RemovableDrive) -> UsbEventBuilder {
+        UsbEventBuilder::new()
+            .id(Uuid::new_v4().to_string())
+            .timestamp(timestamp)
+            .source(self.host.name())
+            .category(String::from("usb"))
+            .action(action)
+            .drive(drive)
+    }
+
+    // New or rewritten executables on each attached drive. The first scan of a drive is
+    // the baseline, what it already carried when it was plugged in is not a write.
+    fn executable_events(&mut self, drives: &HashMap<String, RemovableDrive>) -> Result<Vec<UsbEvent>, CollectionError> {
+        let mut events = Vec::new();
+        self.executables.retain(|key, _| drives.contains_key(key));
+
+        for (key, drive) in drives {
+            if drive.mount_points.is_empty() {
+                continue;
+            }
+            let current = self.scan(&drive.mount_points);
+            let Some(previous) = self.executables.get(key) else {
+                self.executables.insert(key.clone(), current);
+                continue;
+            };
+
+            for (path, (modified, size)) in &current {
+                if previous.get(path) == Some(&(*modified, *size)) {
+                    continue;
+                }
+                warn!("Executable written to removable drive {}: {}", drive.device, path.display());
+                let mut builder = self
+                    .event(Utc::now(), UsbAction::ExecutableWritten, drive.clone())
+                    .file_path(path.to_string_lossy().into_owned())
+                    .file_size(*size);
+                if let Some(sha256) = self.hash(path, *size) {
+                    builder = builder.file_sha256(sha256);
+                }
+                events.push(builder.build().map_err(CollectionError::Parse)?);
+            }
+            self.executables.insert(key.clone(), current);
+        }
+
+        Ok(events)
+    }
+
+    fn collect_events(&mut self) -> Result<Vec<UsbEvent>, CollectionError> {
+        let (pending, drives) = {
+            let mut state = self
+                .state
+                .lock()
+                .map_err(|_| CollectionError::SystemApi("USB state lock poisoned".to_string()))?;
+            // Also refreshes mount points that appeared after the insertion was seen
+            state.refresh();
+            (std::mem::take(&mut state.pending), state.drives.clone())
+        };
+
+        let mut events = Vec::new();
+        for (timestamp, action, drive) in pending {
+            events.push(self.event(timestamp, action, drive).build().map_err(CollectionError::Parse)?);
+        }
+        if self.config.scan_executables {
+            events.extend(self.executable_events(&drives)?);
+        }
+
+        info!("Tracking {} removable drives, {} USB events", drives.len(), events.len());
+        Ok(events)
+    }
+
+    fn internal_validate(&self) -> Result<(), CollectionError> {
+        if !cfg!(any(target_os = "windows", target_os = "linux")) {
+            return Err(CollectionError::SystemApi(
+                "Removable media monitoring is not supported on this platform".to_string()
+            ));
+        }
+        if !self.watching {
+            debug!("Removable media changes are only seen at collection time");
+        }
+        Ok(())
+    }
+}
+
+impl DataCollector<Vec<UsbEvent>> for UsbCollector {
+    fn collect(&mut self) -> Result<Vec<UsbEvent>, CollectionError> {
+        self.collect_events()
+    }
+
+    fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncDataCollector<Vec<UsbEvent>> for UsbCollector {
+    async fn collect(&mut self) -> Result<Vec<UsbEvent>, CollectionError> {
+        DataCollector::collect(self)
+    }
+
+    async fn validate(&self) -> Result<(), CollectionError> {
+        self.internal_validate()
+    }
+
+    async fn health_check(&self) -> bool {
+        self.internal_validate().is_ok()
+    }
+}
+
+impl Default for UsbCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,178 @@
+use crate::features::exec_trace::netlink::NetlinkSocket;
+use crate::features::usb::models::RemovableDrive;
+use log::warn;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+
+const NETLINK_KOBJECT_UEVENT: i32 = 15;
+const KERNEL_UEVENT_GROUP: u32 = 1;
+
+fn read_trimmed(path: impl AsRef<Path>) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+// /proc/mounts and /dev/disk/by-label escape spaces and friends as \040 / \x20
+fn unescape(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('\\') {
+        output.push_str(&rest[..start]);
+        let escape = &rest[start + 1..];
+        let decoded = if escape.starts_with('x') {
+            escape.get(1..3).and_then(|hex| u8::from_str_radix(hex, 16).ok()).map(|byte| (byte, 3))
+        } else {
+            escape.get(..3).and_then(|octal| u8::from_str_radix(octal, 8).ok()).map(|byte| (byte, 3))
+        };
+        match decoded {
+            Some((byte, length)) => {
+                output.push(byte as char);
+                rest = &escape[length..];
+            }
+            None => {
+                output.push('\\');
+                rest = escape;
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+// sdb1, mmcblk0p1 and nvme0n1p1 all belong to their disk
+fn is_partition_of(partition: &str, disk: &str) -> bool {
+    match partition.strip_prefix(disk) {
+        Some(suffix) => {
+            let suffix = suffix.strip_prefix('p').unwrap_or(suffix);
+            !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+// USB serial numbers live on the usb_device a few levels above the block device
+fn usb_serial(device_path: &Path) -> Option<String> {
+    device_path
+        .ancestors()
+        .take(8)
+        .find_map(|ancestor| read_trimmed(ancestor.join("serial")))
+}
+
+fn labels() -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir("/dev/disk/by-label") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let target = fs::canonicalize(entry.path()).ok()?;
+            let device = target.file_name()?.to_string_lossy().into_owned();
+            Some((device, unescape(&entry.file_name().to_string_lossy())))
+        })
+        .collect()
+}
+
+pub fn enumerate() -> Vec<RemovableDrive> {
+    let Ok(entries) = fs::read_dir("/sys/block") else {
+        return Vec::new();
+    };
+    let mounts = fs::read_to_string("/proc/mounts").unwrap_or_default();
+    let labels = labels();
+
+    let mut drives = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if ["loop", "ram", "zram", "dm-", "sr", "md"].iter().any(|prefix| name.starts_with(prefix)) {
+            continue;
+        }
+        let block = entry.path();
+        let real_path = fs::canonicalize(&block).unwrap_or_else(|_| block.clone());
+        let usb = real_path.to_string_lossy().contains("/usb");
+        let removable = read_trimmed(block.join("removable")).as_deref() == Some("1");
+        if !usb && !removable {
+            continue;
+        }
+
+        // Card readers without media report a size of 0
+        let sectors: u64 = read_trimmed(block.join("size")).and_then(|size| size.parse().ok()).unwrap_or(0);
+        if sectors == 0 {
+            continue;
+        }
+
+        let mut mount_points = Vec::new();
+        let mut filesystem = None;
+        for line in mounts.lines() {
+            let mut fields = line.split_whitespace();
+            let (Some(source), Some(target), Some(fs_type)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            let Some(source) = source.strip_prefix("/dev/") else {
+                continue;
+            };
+            if source == name || is_partition_of(source, &name) {
+                mount_points.push(unescape(target));
+                filesystem.get_or_insert_with(|| fs_type.to_string());
+            }
+        }
+
+        let device_path: PathBuf = fs::canonicalize(block.join("device")).unwrap_or_else(|_| block.join("device"));
+        drives.push(RemovableDrive {
+            device: name.clone(),
+            vendor: read_trimmed(block.join("device/vendor")),
+            model: read_trimmed(block.join("device/model")),
+            serial: if usb { usb_serial(&device_path) } else { None },
+            volume_serial: None,
+            label: labels
+                .iter()
+                .find(|(device, _)| device == &name || is_partition_of(device, &name))
+                .map(|(_, label)| label.clone()),
+            filesystem,
+            bus: Some(String::from(if usb { "usb" } else { "removable" })),
+            mount_points,
+            capacity_bytes: Some(sectors * 512),
+        });
+    }
+
+    drives
+}
+
+// Wakes the refresher for every block device uevent from the kernel
+pub fn watch(notify: SyncSender<()>) -> io::Result<()> {
+    let socket = NetlinkSocket::open(libc::SOCK_DGRAM, NETLINK_KOBJECT_UEVENT, KERNEL_UEVENT_GROUP)?;
+    socket.set_read_timeout(Duration::from_secs(1))?;
+
+    thread::Builder::new()
+        .name(String::from("usb-uevent"))
+        .spawn(move || {
+            let mut buffer = vec![0u8; 16 * 1024];
+            loop {
+                let received = match socket.recv(&mut buffer) {
+                    Ok(0) => continue,
+                    Ok(received) => received,
+                    Err(e) => {
+                        warn!("USB uevent socket failed, stopping: {}", e);
+                        return;
+                    }
+                };
+                // "add@/devices/...\0ACTION=add\0SUBSYSTEM=block\0..."
+                let block = buffer[..received]
+                    .split(|byte| *byte == 0)
+                    .any(|field| field == b"SUBSYSTEM=block");
+                if !block {
+                    continue;
+                }
+                match notify.try_send(()) {
+                    Ok(()) | Err(TrySendError::Full(_)) => {}
+                    Err(TrySendError::Disconnected(_)) => return,
+                }
+            }
+        })?;
+
+    Ok(())
+}
@@ -0,0 +1,9 @@
+mod collector;
+mod models;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(windows)]
+mod win32;
+
+pub use collector::{UsbCollector, UsbConfig};
+pub use models::{RemovableDrive, UsbAction, UsbEvent, UsbEventBuilder};
@@ -0,0 +1,201 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsbAction {
+    Inserted,
+    Removed,
+    ExecutableWritten,
+}
+
+// A removable drive as enumerated from the OS: a whole disk on Linux, a drive letter on Windows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemovableDrive {
+    pub device: String,
+    pub vendor: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub volume_serial: Option<String>,
+    pub label: Option<String>,
+    pub filesystem: Option<String>,
+    pub bus: Option<String>,
+    pub mount_points: Vec<String>,
+    pub capacity_bytes: Option<u64>,
+}
+
+impl RemovableDrive {
+    // Device names get reused, so a different stick on the same name counts as a swap
+    pub fn key(&self) -> String {
+        format!(
+            "{}|{}",
+            self.device,
+            self.serial.as_deref().or(self.volume_serial.as_deref()).unwrap_or_default()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsbEvent {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub action: UsbAction,
+    pub device: String,
+    pub vendor: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub volume_serial: Option<String>,
+    pub label: Option<String>,
+    pub filesystem: Option<String>,
+    pub bus: Option<String>,
+    pub mount_points: Vec<String>,
+    pub capacity_bytes: Option<u64>,
+    pub file_path: Option<String>,
+    pub file_size: Option<u64>,
+    pub file_sha256: Option<String>,
+}
+
+impl Event for UsbEvent {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.action {
+            UsbAction::Inserted => "usb_inserted",
+            UsbAction::Removed => "usb_removed",
+            UsbAction::ExecutableWritten => "usb_executable_written",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        match self.action {
+            UsbAction::ExecutableWritten => Severity::High,
+            UsbAction::Inserted => Severity::Medium,
+            UsbAction::Removed => Severity::Low,
+        }
+    }
+}
+
+impl Identifiable for UsbEvent {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for UsbEvent {
+    fn validate(&self) -> Result<(), String> {
+        if self.device.is_empty() {
+            return Err("Device cannot be empty".to_string());
+        }
+
+        if self.action == UsbAction::ExecutableWritten && self.file_path.is_none() {
+            return Err("Executable write events must carry a file path".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct UsbEventBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    action: Option<UsbAction>,
+    drive: Option<RemovableDrive>,
+    file_path: Option<String>,
+    file_size: Option<u64>,
+    file_sha256: Option<String>,
+}
+
+impl UsbEventBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn action(mut self, action: UsbAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn drive(mut self, drive: RemovableDrive) -> Self {
+        self.drive = Some(drive);
+        self
+    }
+
+    pub fn file_path(mut self, file_path: String) -> Self {
+        self.file_path = Some(file_path);
+        self
+    }
+
+    pub fn file_size(mut self, file_size: u64) -> Self {
+        self.file_size = Some(file_size);
+        self
+    }
+
+    pub fn file_sha256(mut self, file_sha256: String) -> Self {
+        self.file_sha256 = Some(file_sha256);
+        self
+    }
+
+    pub fn build(self) -> Result<UsbEvent, String> {
+        let drive = self.drive.ok_or("drive is required")?;
+        let event = UsbEvent {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.ok_or("category is required")?,
+            action: self.action.ok_or("action is required")?,
+            device: drive.device,
+            vendor: drive.vendor,
+            model: drive.model,
+            serial: drive.serial,
+            volume_serial: drive.volume_serial,
+            label: drive.label,
+            filesystem: drive.filesystem,
+            bus: drive.bus,
+            mount_points: drive.mount_points,
+            capacity_bytes: drive.capacity_bytes,
+            file_path: self.file_path,
+            file_size: self.file_size,
+            file_sha256: self.file_sha256,
+        };
+
+        event.validate()?;
+        Ok(event)
+    }
+}
@@ -0,0 +1,232 @@
+use crate::features::usb::models::RemovableDrive;
+use log::warn;
+use std::io;
+use std::sync::mpsc::SyncSender;
+use std::sync::OnceLock;
+use std::thread;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{CloseHandle, HANDLE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::Storage::FileSystem::{
+    BusTypeUsb, CreateFileW, GetDiskFreeSpaceExW, GetDriveTypeW, GetLogicalDrives, GetVolumeInformationW,
+    FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+};
+use windows::Win32::System::IO::DeviceIoControl;
+use windows::Win32::System::Ioctl::{
+    PropertyStandardQuery, StorageDeviceProperty, IOCTL_STORAGE_QUERY_PROPERTY, STORAGE_DEVICE_DESCRIPTOR,
+    STORAGE_PROPERTY_QUERY,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::WindowsProgramming::{DRIVE_FIXED, DRIVE_REMOVABLE};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW, DBT_DEVICEARRIVAL,
+    DBT_DEVICEREMOVECOMPLETE, HMENU, MSG, WINDOW_EX_STYLE, WM_DEVICECHANGE, WNDCLASSW, WS_OVERLAPPED,
+};
+
+// The window procedure has no way to carry state, so the watcher's sender lives here
+static DEVICE_CHANGES: OnceLock<SyncSender<()>> = OnceLock::new();
+
+fn wide(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+fn from_wide(buffer: &[u16]) -> Option<String> {
+    let length = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+    let value = String::from_utf16_lossy(&buffer[..length]).trim().to_string();
+    if value.is_empty() { None } else { Some(value) }
+}
+
+struct DeviceDescriptor {
+    vendor: Option<String>,
+    product: Option<String>,
+    serial: Option<String>,
+    usb: bool,
+}
+
+fn device_descriptor(letter: char) -> Option<DeviceDescriptor> {
+    let path = wide(&format!(r"\\.\{}:", letter));
+    unsafe {
+        // No access rights are needed to query storage properties
+        let handle = CreateFileW(
+            PCWSTR(path.as_ptr()),
+            0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAGS_AND_ATTRIBUTES(0),
+            HANDLE::default(),
+        )
+        .ok()?;
+
+        let query = STORAGE_PROPERTY_QUERY {
+            PropertyId: StorageDeviceProperty,
+            QueryType: PropertyStandardQuery,
+            AdditionalParameters: [0],
+        };
+        let mut buffer = vec![0u8; 1024];
+        let mut returned = 0u32;
+        let ok = DeviceIoControl(
+            handle,
+            IOCTL_STORAGE_QUERY_PROPERTY,
+            Some(&query as *const STORAGE_PROPERTY_QUERY as *const std::ffi::c_void),
+            std::mem::size_of::<STORAGE_PROPERTY_QUERY>() as u32,
+            Some(buffer.as_mut_ptr() as *mut std::ffi::c_void),
+            buffer.len() as u32,
+            Some(&mut returned),
+            None,
+        );
+        CloseHandle(handle);
+
+        let returned = returned as usize;
+        if !ok.as_bool() || returned < std::mem::size_of::<STORAGE_DEVICE_DESCRIPTOR>() {
+            return None;
+        }
+        let descriptor = std::ptr::read_unaligned(buffer.as_ptr() as *const STORAGE_DEVICE_DESCRIPTOR);
+
+        // Offsets point at NUL-terminated ASCII strings inside the same buffer, 0 when absent
+        let text = |offset: u32| -> Option<String> {
+            let offset = offset as usize;
+            if offset == 0 || offset >= returned {
+                return None;
+            }
+            let bytes = &buffer[offset..returned];
+            let length = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+            let value = String::from_utf8_lossy(&bytes[..length]).trim().to_string();
+            if value.is_empty() { None } else { Some(value) }
+        };
+
+        Some(DeviceDescriptor {
+            vendor: text(descriptor.VendorIdOffset),
+            product: text(descriptor.ProductIdOffset),
+            serial: text(descriptor.SerialNumberOffset),
+            usb: descriptor.BusType == BusTypeUsb,
+        })
+    }
+}
+
+pub fn enumerate() -> Vec<RemovableDrive> {
+    let mut drives = Vec::new();
+    let mask = unsafe { GetLogicalDrives() };
+
+    for index in 0..26u32 {
+        if mask & (1 << index) == 0 {
+            continue;
+        }
+        let letter = (b'A' + index as u8) as char;
+        let root = wide(&format!(r"{}:\", letter));
+
+        let drive_type = unsafe { GetDriveTypeW(PCWSTR(root.as_ptr())) };
+        if drive_type != DRIVE_REMOVABLE && drive_type != DRIVE_FIXED {
+            continue;
+        }
+        // USB hard disks report DRIVE_FIXED, the bus type tells them apart from internal disks
+        let descriptor = device_descriptor(letter);
+        let usb = descriptor.as_ref().map_or(false, |descriptor| descriptor.usb);
+        if drive_type != DRIVE_REMOVABLE && !usb {
+            continue;
+        }
+
+        let mut label = [0u16; 261];
+        let mut filesystem = [0u16; 261];
+        let mut volume_serial = 0u32;
+        let has_media = unsafe {
+            GetVolumeInformationW(
+                PCWSTR(root.as_ptr()),
+                Some(&mut label),
+                Some(&mut volume_serial),
+                None,
+                None,
+                Some(&mut filesystem),
+            )
+        };
+        // Empty card reader slots still have a drive letter
+        if !has_media.as_bool() {
+            continue;
+        }
+
+        let mut capacity = 0u64;
+        let has_capacity = unsafe { GetDiskFreeSpaceExW(PCWSTR(root.as_ptr()), None, Some(&mut capacity), None) };
+
+        let (vendor, model, serial) = match descriptor {
+            Some(descriptor) => (descriptor.vendor, descriptor.product, descriptor.serial),
+            None => (None, None, None),
+        };
+        drives.push(RemovableDrive {
+            device: format!("{}:", letter),
+            vendor,
+            model,
+            serial,
+            volume_serial: Some(format!("{:04X}-{:04X}", volume_serial >> 16, volume_serial & 0xffff)),
+            label: from_wide(&label),
+            filesystem: from_wide(&filesystem),
+            bus: Some(String::from(if usb { "usb" } else { "removable" })),
+            mount_points: vec![format!(r"{}:\", letter)],
+            capacity_bytes: if has_capacity.as_bool() { Some(capacity) } else { None },
+        });
+    }
+
+    drives
+}
+
+unsafe extern "system" fn window_proc(window: HWND, message: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if message == WM_DEVICECHANGE
+        && (wparam.0 as u32 == DBT_DEVICEARRIVAL || wparam.0 as u32 == DBT_DEVICEREMOVECOMPLETE)
+    {
+        if let Some(notify) = DEVICE_CHANGES.get() {
+            let _ = notify.try_send(());
+        }
+    }
+    DefWindowProcW(window, message, wparam, lparam)
+}
+
+// Wakes the refresher on WM_DEVICECHANGE. Volume arrivals are broadcast to top-level
+// windows only, so this is a hidden top-level window rather than a message-only one.
+// Agents running as a service in session 0 may not see the broadcast; collection still
+// picks up changes by polling.
+pub fn watch(notify: SyncSender<()>) -> io::Result<()> {
+    if DEVICE_CHANGES.set(notify).is_err() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, "device change watcher already running"));
+    }
+
+    thread::Builder::new()
+        .name(String::from("usb-devicechange"))
+        .spawn(|| unsafe {
+            let instance = GetModuleHandleW(PCWSTR::null()).unwrap_or_default();
+            let class_name = wide("LsedrRemovableMediaWatcher");
+            let class = WNDCLASSW {
+                lpfnWndProc: Some(window_proc),
+                hInstance: instance,
+                lpszClassName: PCWSTR(class_name.as_ptr()),
+                ..Default::default()
+            };
+            if RegisterClassW(&class) == 0 {
+                warn!("Failed to register device change window class: {}", io::Error::last_os_error());
+                return;
+            }
+
+            let window = CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                PCWSTR(class_name.as_ptr()),
+                PCWSTR(class_name.as_ptr()),
+                WS_OVERLAPPED,
+                0,
+                0,
+                0,
+                0,
+                HWND(0),
+                HMENU(0),
+                instance,
+                None,
+            );
+            if window.0 == 0 {
+                warn!("Failed to create device change window: {}", io::Error::last_os_error());
+                return;
+            }
+
+            let mut message = MSG::default();
+            while GetMessageW(&mut message, HWND(0), 0, 0).as_bool() {
+                DispatchMessageW(&message);
+            }
+        })?;
+
+    Ok(())
+}
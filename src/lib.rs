@@ -1,10 +1,26 @@
+pub mod agent;
+pub mod prelude;
+
+// Internal modules, public only with the `unstable` feature (on by default for the lsedr
+// binary); they carry no compatibility promise, see prelude
+#[cfg(feature = "unstable")]
 pub mod features;
+#[cfg(not(feature = "unstable"))]
+#[allow(unused)]
+mod features;
+#[cfg(feature = "unstable")]
 pub mod shared;
+#[cfg(not(feature = "unstable"))]
+#[allow(unused)]
+mod shared;
 
 // Re-export commonly used items from features
-pub use features::network::{NetworkCollector, NetworkInformation};
-pub use features::process::{ProcessCollector, ProcessInformation};
-pub use features::service::{ServiceCollector, ServiceInformation};
+pub use features::network::{ListeningPortEvent, NetworkCollector, NetworkConfig, NetworkInformation, ProcessNetworkUsage};
+pub use features::process::{
+    ProcessCollector, ProcessConfig, ProcessInformation, ProcessLifecycleAction, ProcessLifecycleEvent,
+    ProcessReportMode,
+};
+pub use features::service::{ServiceChange, ServiceChangeEvent, ServiceCollector, ServiceInformation};
 pub use features::system_metrics::{
     SystemMetricsCollector,
     SystemMetrics,
@@ -24,6 +40,120 @@ pub use features::registry::{
     RegistryEventType,
     SuspiciousRegistryOperation,
 };
+pub use features::exec_trace::{
+    ExecTraceCollector,
+    ExecEvent,
+    ExecEventType,
+};
+pub use features::ingest::{
+    IngestCollector,
+    IngestSubmission,
+    IngestedEvent,
+};
+pub use features::scheduled_tasks::{
+    ScheduledTaskCollector,
+    ScheduledTaskEvent,
+    ScheduledTaskChange,
+};
+pub use features::osquery::{
+    OsqueryCollector,
+    OsqueryResultEvent,
+    OsqueryAction,
+};
+pub use features::honeypot::{
+    HoneypotCollector,
+    HoneypotEvent,
+};
+pub use features::usb::{
+    UsbCollector,
+    UsbEvent,
+    UsbAction,
+};
+pub use features::deception::{
+    DeceptionCollector,
+    DeceptionEvent,
+    DeceptionAction,
+};
+pub use features::capture::{
+    CaptureCollector,
+    CaptureEvent,
+    CaptureKind,
+};
+pub use features::keylogging::{
+    KeyloggingCollector,
+    KeyloggingEvent,
+    KeyloggingIndicator,
+};
+pub use features::print_spooler::{
+    PrintSpoolerCollector,
+    PrintSpoolerConfig,
+    PrintSpoolerEvent,
+    PrintSpoolerEventBuilder,
+    SpoolerAction,
+};
+pub use features::boot::{
+    BootChange,
+    BootCollector,
+    BootConfig,
+    BootEvent,
+    BootEventBuilder,
+};
+pub use features::persistence::{
+    PersistenceChange,
+    PersistenceCollector,
+    PersistenceConfig,
+    PersistenceEvent,
+    PersistenceEventBuilder,
+    PersistenceKind,
+};
+pub use features::log_tail::{
+    LogEvent,
+    LogEventBuilder,
+    LogTailCollector,
+    LogTailConfig,
+};
+pub use features::snmp_trap::{
+    SnmpTrapCollector,
+    SnmpTrapConfig,
+    SnmpTrapEvent,
+    SnmpTrapEventBuilder,
+};
+pub use features::directory::{
+    DirectoryChange,
+    DirectoryChangeEvent,
+    DirectoryChangeEventBuilder,
+    DirectoryCollector,
+    DirectoryConfig,
+};
+pub use features::tls_probe::{
+    parse_certificate,
+    CertificateInfo,
+    TlsEndpoint,
+    TlsFinding,
+    TlsProbeCollector,
+    TlsProbeConfig,
+    TlsProbeEvent,
+    TlsProbeEventBuilder,
+};
+pub use features::detection::{
+    Alert,
+    AlertBuilder,
+    DetectionConfig,
+    DetectionSource,
+    FieldMatcher,
+    Rule,
+    RuleEngine,
+    Threshold,
+};
+pub use features::response::{
+    FileRetriever,
+    FileRetrievalRequest,
+    RetrievedFile,
+    ResponseActionType,
+    ResponseActionExecutor,
+    SignedActionRequest,
+    ActionAuditEvent,
+};
 
 // Re-export shared functionality
 pub use shared::traits::{
@@ -38,8 +168,22 @@ pub use shared::error::{
     CollectionError,
     ProcessingError,
     StorageError,
+    ActionError,
+    ConfigError,
 };
-pub use shared::storage::{ElasticsearchStorage, SystemInformation};
+pub use shared::storage::{ElasticsearchStorage, StorageBackend, StorageDocument, SystemInformation};
+pub use shared::alerting::{AlertRouter, LocalAlertHandler};
+pub use shared::config::AgentConfig;
+pub use shared::host_identity::{HostIdentity, HostIdentityService};
+pub use shared::ioc::{IocConfig, IocMatch, IocMatcher};
+pub use shared::process_resolver::{ProcessDetails, ProcessResolver};
+pub use shared::pseudonymizer::{PrivacyConfig, Pseudonymizer};
+pub use shared::signature::SignatureStatus;
+pub use shared::user_resolver::{UserContext, UserNameFormat, UserResolver};
 
 // Utils module will be moved to shared in future refactoring
+#[cfg(feature = "unstable")]
 pub mod utils;
+#[cfg(not(feature = "unstable"))]
+#[allow(unused)]
+mod utils;
@@ -1,175 +1,1928 @@
+mod cli;
+mod service;
+
 use env_logger;
+use clap::Parser;
+use cli::{Cli, Command, MaintenanceAction, OutputFormat, StateAction};
+use service::RunState;
 use lsedr::{
     shared::{
-        storage::{ElasticsearchStorage, SystemInformation},
-        traits::{AsyncDataCollector, DataCollector},
-        error::CollectionError,
+        alerting::AlertRouter,
+        baseline::Baseline,
+        capabilities::{Capabilities, Degradation, DegradedModes},
+        config::AgentConfig,
+        config_watcher::ConfigWatcher,
+        host_identity::HostIdentityService,
+        instance::InstanceLock,
+        ioc::{IocMatch, IocMatcher},
+        maintenance::{MaintenanceMode, MaintenanceSource},
+        metrics::AgentMetrics,
+        pipeline::{Deduplicator, EventPipeline},
+        remote_config::RemoteConfigClient,
+        state_archive::{export_state, import_state},
+        storage::{
+            create_backend, create_direct_backend, split_snapshot, BackendKind, DocumentFormat, ElasticsearchConfig, ElasticsearchStorage, SnapshotDeltaEncoder,
+            SnapshotLayout, StdoutStorage, StorageBackend, StorageDocument, SystemInformation,
+        },
+        vdi::VdiMode,
+        traits::{AsyncDataCollector, DataCollector, DataStorage, Event, EventEnvelope, Identifiable, Severity},
+        error::{CollectionError, InstanceLockError, ServiceError, StorageError},
     },
     features::{
-        network::NetworkCollector,
+        network::{NetworkCollector, NetworkConnectionInformation, NetworkMetrics},
         process::{ProcessCollector, ProcessInformation},
         service::{ServiceCollector, ServiceInformation},
-        system_metrics::SystemMetricsCollector,
-        filesystem::FileSystemCollector,
-        registry::{RegistryCollector, RegistryEvent},
+        system_metrics::{SystemMetrics, SystemMetricsCollector},
+        filesystem::{FileEvent, FileSystemCollector},
+        registry::{RegistryCollector, RegistryEvent, PERSISTENCE_AUDIT_INDEX},
+        exec_trace::{ExecEvent, ExecTraceCollector},
+        ingest::{IngestCollector, IngestedEvent},
+        scheduled_tasks::{ScheduledTaskCollector, ScheduledTaskEvent},
+        osquery::{OsqueryCollector, OsqueryResultEvent},
+        honeypot::{HoneypotCollector, HoneypotEvent},
+        usb::{UsbCollector, UsbEvent},
+        deception::{DeceptionCollector, DeceptionEvent},
+        capture::{CaptureCollector, CaptureEvent},
+        keylogging::{KeyloggingCollector, KeyloggingEvent},
+        print_spooler::{PrintSpoolerCollector, PrintSpoolerEvent},
+        boot::{BootCollector, BootEvent},
+        persistence::{PersistenceCollector, PersistenceEvent},
+        log_tail::{LogEvent, LogTailCollector},
+        snmp_trap::{SnmpTrapCollector, SnmpTrapEvent},
+        directory::{DirectoryChangeEvent, DirectoryCollector},
+        tls_probe::{TlsProbeCollector, TlsProbeEvent},
+        detection::{Alert, AlertBuilder, DetectionSource, EgressPolicy, LateralMovementDetector, OutboundVolumeDetector, RuleEngine},
+        control::{AgentControl, ControlServer, EventBuffer},
+        incident::IncidentTracker,
+        response::ResponseActionExecutor,
     },
 };
-use log::{error, info, warn};
-use std::time::{Duration, SystemTime};
-use tokio::time;
+use chrono::{Local, Utc};
+use log::{debug, error, info, warn};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::runtime::Handle;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
 
-#[tokio::main]
-async fn main() {
-    // Initialize logger with more detailed output
-    std::env::set_var("RUST_LOG", "debug");
-    env_logger::init();
+// Latest output of the collectors that make up the periodic system snapshot
+#[derive(Default)]
+struct LatestCollections {
+    network: Option<NetworkMetrics>,
+    processes: Option<Vec<ProcessInformation>>,
+    services: Option<Vec<ServiceInformation>>,
+}
 
-    // Initialize Elasticsearch storage
-    info!("Connecting to Elasticsearch at localhost:9200");
-    let storage = match ElasticsearchStorage::new("localhost", 9200, None, None) {
-        Ok(storage) => {
-            info!("Successfully connected to Elasticsearch");
-            storage
+// Storage and analysis shared by the collector tasks
+struct Pipeline {
+    storage: Box<dyn StorageBackend>,
+    alert_handler: AlertRouter,
+    rule_engine: Option<RuleEngine>,
+    egress_policy: Option<EgressPolicy>,
+    lateral_movement: Option<LateralMovementDetector>,
+    outbound_volume: Option<OutboundVolumeDetector>,
+    ioc_matcher: Option<Mutex<IocMatcher>>,
+    dedup: Option<Deduplicator>,
+    processors: EventPipeline,
+    latest: Mutex<LatestCollections>,
+}
+
+impl Pipeline {
+    fn update_latest(&self, update: impl FnOnce(&mut LatestCollections)) {
+        if let Ok(mut latest) = self.latest.lock() {
+            update(&mut latest);
         }
-        Err(e) => {
-            error!("Failed to initialize Elasticsearch storage: {}", e);
+    }
+
+    fn latest_snapshot(&self) -> Option<(NetworkMetrics, Vec<ProcessInformation>, Vec<ServiceInformation>)> {
+        let latest = self.latest.lock().ok()?;
+        Some((latest.network.clone()?, latest.processes.clone()?, latest.services.clone()?))
+    }
+
+    fn report(&self, label: &str, count: usize, result: Result<(), StorageError>) {
+        AgentMetrics::global().record_storage(label, count, result.is_ok());
+        match result {
+            Ok(_) => {
+                info!("Successfully stored {} {} in {} storage", count, label, self.storage.name());
+            }
+            Err(e) => {
+                error!("Failed to store {} in {} storage: {}", label, self.storage.name(), e);
+            }
+        }
+    }
+
+    // Maintenance windows and the first-run learning phase hold back all but the most
+    // severe alerts
+    fn raises(severity: Severity) -> bool {
+        MaintenanceMode::global().raises(severity) && Baseline::global().raises(severity)
+    }
+
+    async fn raise(&self, label: &str, mut alerts: Vec<Alert>) {
+        let raised = alerts.len();
+        alerts.retain(|alert| Self::raises(alert.severity()));
+        if alerts.len() < raised {
+            info!("Held back {} {} during maintenance or baseline learning", raised - alerts.len(), label);
+        }
+        if alerts.is_empty() {
             return;
         }
-    };
+        EventBuffer::global().push("alerts", &alerts);
+        self.alert_handler.handle_all(&alerts);
+        let result = self.storage.store_events("alerts", &alerts).await;
+        self.report(label, alerts.len(), result);
+        IncidentTracker::global().record(&alerts);
+    }
 
-    // Create collectors
-    let mut metrics_collector = SystemMetricsCollector::new();
-    let mut network_collector = NetworkCollector::new();
-    let mut process_collector = ProcessCollector::new();
-    let mut service_collector = ServiceCollector::new();
-    let mut filesystem_collector = match FileSystemCollector::new() {
-        Ok(collector) => {
-            info!("Successfully initialized filesystem collector");
-            collector
+    // Runs the detection rules over one batch of events
+    async fn detect<T: Serialize + Sync>(&self, source: DetectionSource, events: &[T]) {
+        if let Some(engine) = self.rule_engine.as_ref() {
+            self.raise("detection alerts", engine.evaluate(source, events)).await;
         }
-        Err(e) => {
-            error!("Failed to initialize filesystem collector: {}", e);
+    }
+
+    // Checks outbound connections against the egress allow-list of this host's role
+    async fn check_egress(&self, connections: &[NetworkConnectionInformation]) {
+        if let Some(policy) = self.egress_policy.as_ref() {
+            self.raise("egress policy violations", policy.evaluate(connections).await).await;
+        }
+    }
+
+    // Looks for this host sweeping or port-scanning internal addresses
+    async fn check_lateral_movement(&self, connections: &[NetworkConnectionInformation]) {
+        if let Some(detector) = self.lateral_movement.as_ref() {
+            self.raise("lateral movement alerts", detector.evaluate(connections)).await;
+        }
+    }
+
+    // Compares what each process sends to new external destinations with its usual volume
+    async fn check_outbound_volume(&self, connections: &[NetworkConnectionInformation]) {
+        if let Some(detector) = self.outbound_volume.as_ref() {
+            self.raise("outbound volume alerts", detector.evaluate(connections)).await;
+        }
+    }
+
+    fn scan_iocs<T: Serialize>(&self, index: &str, events: &[T]) -> Vec<IocMatch> {
+        match self.ioc_matcher.as_ref().map(Mutex::lock) {
+            Some(Ok(mut matcher)) => matcher.scan(index, events),
+            _ => Vec::new(),
+        }
+    }
+
+    // Runs the IOC matcher, when enabled, over one batch of events written to `index`
+    async fn match_iocs<T: Serialize + Sync>(&self, index: &str, events: &[T]) {
+        let matches = self.scan_iocs(index, events);
+        if matches.is_empty() {
             return;
         }
-    };
-    let mut registry_collector = match RegistryCollector::new() {
-        Ok(collector) => {
-            info!("Successfully initialized registry collector");
-            collector
+        EventBuffer::global().push("ioc_matches", &matches);
+        self.alert_handler.handle_all(&matches);
+        let result = self.storage.store_events("ioc_matches", &matches).await;
+        self.report("IOC matches", matches.len(), result);
+    }
+
+    // The path every event collector without detection of its own takes: the category's
+    // processor chain, local inspection, then storage as envelopes of whatever type
+    async fn handle_events<E>(&self, index: &str, label: &str, events: Vec<E>)
+    where
+        E: Event + Identifiable + Serialize + DeserializeOwned + Sync,
+    {
+        let events = self.processors.process(index, events);
+        if events.is_empty() {
+            return;
+        }
+        self.inspect(index, &events).await;
+        let result = self.storage.store_events(index, &events).await;
+        self.report(label, events.len(), result);
+    }
+
+    // Local escalation and IOC matching for a batch about to be stored; during maintenance
+    // and baseline learning only events severe enough to alert on are escalated. The batch
+    // also goes to the control server's buffer of recent events.
+    async fn inspect<E: Event + Identifiable + Serialize + Sync>(&self, index: &str, events: &[E]) {
+        EventBuffer::global().push(index, events);
+        for event in events.iter().filter(|event| Self::raises(event.severity())) {
+            self.alert_handler.handle(event);
         }
+        self.match_iocs(index, events).await;
+    }
+}
+
+// How many events one collection produced, for the agent metrics
+trait EventCount {
+    fn event_count(&self) -> usize;
+}
+
+impl<T> EventCount for Vec<T> {
+    fn event_count(&self) -> usize {
+        self.len()
+    }
+}
+
+impl EventCount for SystemMetrics {
+    fn event_count(&self) -> usize {
+        1
+    }
+}
+
+impl EventCount for NetworkMetrics {
+    fn event_count(&self) -> usize {
+        self.connections.len()
+    }
+}
+
+// Runs one collector on its own task at the given interval, passing every collection to
+// `handle`, until the service manager or a signal asks the agent to stop. While paused the
+// collector keeps its state but is not run, and so are the collectors maintenance mode
+// pauses by their interval `key`. A control client can run it right away by that key.
+fn spawn_collector<C, T, F, Fut>(
+    name: &'static str,
+    key: &'static str,
+    period: Duration,
+    mut control: watch::Receiver<RunState>,
+    mut collector: C,
+    mut handle: F,
+) -> JoinHandle<()>
+where
+    C: AsyncDataCollector<T> + Send + 'static,
+    T: EventCount + Send + 'static,
+    F: FnMut(&mut C, T) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    info!("Collecting {} every {}s", name, period.as_secs());
+    let mut trigger = AgentControl::global().subscribe(key);
+    tokio::spawn(async move {
+        let mut interval = time::interval(period);
+        // A slow backend delays the next collection rather than causing a burst of catch-up runs
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = trigger.requested() => debug!("Collecting {} on request of a control client", name),
+                changed = control.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                }
+            }
+            let state = *control.borrow();
+            match state {
+                RunState::Stopping => break,
+                RunState::Paused => continue,
+                RunState::Running => {}
+            }
+            if !MaintenanceMode::global().runs(key) {
+                debug!("Skipping {} during maintenance", name);
+                continue;
+            }
+            let started = Instant::now();
+            let collected = AsyncDataCollector::<T>::collect(&mut collector).await;
+            let events = collected.as_ref().map_or(0, |output| output.event_count());
+            AgentMetrics::global().record_collection(key, events, started.elapsed(), collected.is_ok());
+            match collected {
+                Ok(output) => handle(&mut collector, output).await,
+                Err(e) => error!("Error collecting {}: {}", name, e),
+            }
+        }
+        info!("Stopped collecting {}", name);
+    })
+}
+
+// Every collector enabled by the configuration, created once per command
+struct Collectors {
+    metrics: SystemMetricsCollector,
+    network: NetworkCollector,
+    process: ProcessCollector,
+    service: ServiceCollector,
+    filesystem: Option<FileSystemCollector>,
+    registry: Option<RegistryCollector>,
+    exec_trace: Option<ExecTraceCollector>,
+    ingest: Option<IngestCollector>,
+    scheduled_tasks: Option<ScheduledTaskCollector>,
+    osquery: Option<OsqueryCollector>,
+    honeypot: Option<HoneypotCollector>,
+    usb: Option<UsbCollector>,
+    deception: Option<DeceptionCollector>,
+    capture: Option<CaptureCollector>,
+    keylogging: Option<KeyloggingCollector>,
+    print_spooler: Option<PrintSpoolerCollector>,
+    boot: Option<BootCollector>,
+    persistence: Option<PersistenceCollector>,
+    log_tail: Option<LogTailCollector>,
+    snmp_trap: Option<SnmpTrapCollector>,
+    directory: Option<DirectoryCollector>,
+    tls_probe: Option<TlsProbeCollector>,
+    // Collectors running with less than configured on this host
+    degraded: Vec<Degradation>,
+}
+
+impl Collectors {
+    // Every enabled collector, on what this host can provide; one that fails to start is
+    // left out rather than keeping the others from running
+    fn new(config: &AgentConfig) -> Self {
+        Self::build(config, true)
+    }
+
+    // For one-shot commands: the collectors that bind a socket, pipe or port are left out so
+    // a running agent keeps its endpoints
+    fn without_listeners(config: &AgentConfig) -> Self {
+        Self::build(config, false)
+    }
+
+    // Enabled collectors that accept connections or datagrams rather than poll
+    fn listeners(config: &AgentConfig) -> Vec<&'static str> {
+        [
+            ("ingest", config.ingest.enabled),
+            ("honeypot", config.honeypot.enabled),
+            ("snmp_trap", config.snmp_trap.enabled),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect()
+    }
+
+    fn build(config: &AgentConfig, listeners: bool) -> Self {
+        let (config, degraded) = Capabilities::global().degrade(config);
+        DegradedModes::global().set(&degraded);
+        let config = &config;
+        let metrics_collector = SystemMetricsCollector::new();
+        let network_collector = NetworkCollector::with_config(config.network.clone());
+        let process_collector = ProcessCollector::with_config(config.process.clone());
+        let service_collector = ServiceCollector::new();
+        let filesystem_collector = match FileSystemCollector::with_config(config.filesystem.clone()) {
+            Ok(collector) => {
+                info!("Successfully initialized filesystem collector");
+                Some(collector)
+            }
+            Err(e) => {
+                error!("Failed to initialize filesystem collector, running without it: {}", e);
+                None
+            }
+        };
+        let registry_collector = match RegistryCollector::with_config(config.registry.clone()) {
+            Ok(collector) => {
+                info!("Successfully initialized registry collector");
+                Some(collector)
+            }
+            Err(e) => {
+                error!("Failed to initialize registry collector, running without it: {}", e);
+                None
+            }
+        };
+
+        // Process execution tracing is Linux-only and optional
+        let exec_trace_config = config.exec_trace.clone();
+        let exec_trace_collector = if exec_trace_config.enabled && cfg!(target_os = "linux") {
+            match ExecTraceCollector::with_config(exec_trace_config) {
+                Ok(collector) => {
+                    info!("Successfully initialized exec trace collector");
+                    Some(collector)
+                }
+                Err(e) => {
+                    warn!("Exec trace collector unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Local endpoint for events submitted by other on-host tools
+        let ingest_config = config.ingest.clone();
+        let ingest_collector = if listeners && ingest_config.enabled {
+            match IngestCollector::with_config(ingest_config) {
+                Ok(collector) => {
+                    info!("Successfully initialized ingestion endpoint");
+                    Some(collector)
+                }
+                Err(e) => {
+                    warn!("Ingestion endpoint unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let scheduled_task_config = config.scheduled_tasks.clone();
+        let scheduled_task_collector = if scheduled_task_config.enabled {
+            Some(ScheduledTaskCollector::with_config(scheduled_task_config))
+        } else {
+            None
+        };
+
+        let osquery_config = config.osquery.clone();
+        let osquery_collector = if osquery_config.enabled {
+            Some(OsqueryCollector::with_config(osquery_config))
+        } else {
+            None
+        };
+
+        let honeypot_config = config.honeypot.clone();
+        let honeypot_collector = if listeners && honeypot_config.enabled {
+            match HoneypotCollector::with_config(honeypot_config) {
+                Ok(collector) => {
+                    info!("Successfully initialized honeypot listeners");
+                    Some(collector)
+                }
+                Err(e) => {
+                    warn!("Honeypot listeners unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let usb_config = config.usb.clone();
+        let usb_collector = if usb_config.enabled {
+            Some(UsbCollector::with_config(usb_config))
+        } else {
+            None
+        };
+
+        let deception_config = config.deception.clone();
+        let deception_collector = if deception_config.enabled {
+            Some(DeceptionCollector::with_config(deception_config))
+        } else {
+            None
+        };
+
+        let capture_config = config.capture.clone();
+        let capture_collector = if capture_config.enabled {
+            Some(CaptureCollector::with_config(capture_config))
+        } else {
+            None
+        };
+
+        let keylogging_config = config.keylogging.clone();
+        let keylogging_collector = if keylogging_config.enabled {
+            Some(KeyloggingCollector::with_config(keylogging_config))
+        } else {
+            None
+        };
+
+        let print_spooler_config = config.print_spooler.clone();
+        let print_spooler_collector = if print_spooler_config.enabled && cfg!(target_os = "windows") {
+            Some(PrintSpoolerCollector::with_config(print_spooler_config))
+        } else {
+            None
+        };
+
+        let boot_config = config.boot.clone();
+        let boot_collector = if boot_config.enabled {
+            Some(BootCollector::with_config(boot_config))
+        } else {
+            None
+        };
+
+        let persistence_config = config.persistence.clone();
+        let persistence_collector = if persistence_config.enabled {
+            Some(PersistenceCollector::with_config(persistence_config))
+        } else {
+            None
+        };
+
+        let log_tail_config = config.log_tail.clone();
+        let log_tail_collector = if log_tail_config.enabled {
+            Some(LogTailCollector::with_config(log_tail_config))
+        } else {
+            None
+        };
+
+        let snmp_trap_config = config.snmp_trap.clone();
+        let snmp_trap_collector = if listeners && snmp_trap_config.enabled {
+            match SnmpTrapCollector::with_config(snmp_trap_config) {
+                Ok(collector) => {
+                    info!("Successfully initialized SNMP trap listener");
+                    Some(collector)
+                }
+                Err(e) => {
+                    warn!("SNMP trap listener unavailable: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let directory_config = config.directory.clone();
+        let directory_collector = if directory_config.enabled {
+            Some(DirectoryCollector::with_config(directory_config))
+        } else {
+            None
+        };
+
+        let tls_probe_config = config.tls_probe.clone();
+        let tls_probe_collector = if tls_probe_config.enabled {
+            Some(TlsProbeCollector::with_config(tls_probe_config))
+        } else {
+            None
+        };
+
+        Self {
+            metrics: metrics_collector,
+            network: network_collector,
+            process: process_collector,
+            service: service_collector,
+            filesystem: filesystem_collector,
+            registry: registry_collector,
+            exec_trace: exec_trace_collector,
+            ingest: ingest_collector,
+            scheduled_tasks: scheduled_task_collector,
+            osquery: osquery_collector,
+            honeypot: honeypot_collector,
+            usb: usb_collector,
+            deception: deception_collector,
+            capture: capture_collector,
+            keylogging: keylogging_collector,
+            print_spooler: print_spooler_collector,
+            boot: boot_collector,
+            persistence: persistence_collector,
+            log_tail: log_tail_collector,
+            snmp_trap: snmp_trap_collector,
+            directory: directory_collector,
+            tls_probe: tls_probe_collector,
+            degraded,
+        }
+    }
+
+    // Result of every collector's health check, by collector name
+    async fn health(&self) -> Vec<(&'static str, bool)> {
+        let mut results = vec![
+            ("system_metrics", AsyncDataCollector::health_check(&self.metrics).await),
+            ("network", AsyncDataCollector::health_check(&self.network).await),
+            ("process", AsyncDataCollector::health_check(&self.process).await),
+            ("service", AsyncDataCollector::health_check(&self.service).await),
+        ];
+        // Both always run, so one that failed to start is unhealthy
+        match &self.filesystem {
+            Some(collector) => results.push(("filesystem", AsyncDataCollector::health_check(collector).await)),
+            None => results.push(("filesystem", false)),
+        }
+        match &self.registry {
+            Some(collector) => results.push(("registry", AsyncDataCollector::health_check(collector).await)),
+            None => results.push(("registry", false)),
+        }
+        if let Some(collector) = &self.exec_trace {
+            results.push(("exec_trace", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.ingest {
+            results.push(("ingest", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.scheduled_tasks {
+            results.push(("scheduled_tasks", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.osquery {
+            results.push(("osquery", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.honeypot {
+            results.push(("honeypot", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.usb {
+            results.push(("usb", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.deception {
+            results.push(("deception", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.capture {
+            results.push(("capture", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.keylogging {
+            results.push(("keylogging", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.print_spooler {
+            results.push(("print_spooler", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.boot {
+            results.push(("boot", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.persistence {
+            results.push(("persistence", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.log_tail {
+            results.push(("log_tail", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.snmp_trap {
+            results.push(("snmp_trap", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.directory {
+            results.push(("directory", AsyncDataCollector::health_check(collector).await));
+        }
+        if let Some(collector) = &self.tls_probe {
+            results.push(("tls_probe", AsyncDataCollector::health_check(collector).await));
+        }
+        results
+    }
+
+    // Runs every collector once and writes what it found to `storage`, without detection
+    // or IOC matching. Collectors that report changes only emit what happened since they
+    // started, so a single pass mostly shows the current state.
+    async fn collect_once(mut self, storage: &dyn StorageBackend) {
+        let metrics = collect_once("system metrics", &mut self.metrics).await;
+        let network = collect_once("network information", &mut self.network).await;
+        report_once("listening port changes", storage.store_events("listening_port_events", &self.network.take_port_changes()).await);
+        report_once("process network usage", storage.store_events("process_network_usage", &self.network.take_process_usage()).await);
+        let processes = collect_once("process information", &mut self.process).await;
+        let services = collect_once("service information", &mut self.service).await;
+        if let (Some(metrics), Some(network), Some(processes), Some(services)) = (metrics, network, processes, services) {
+            report_once("system metrics", storage.store_system_info(&system_snapshot(metrics, network, processes, services)).await);
+        }
+        report_once("process events", storage.store_events("process_events", &self.process.take_lifecycle_events()).await);
+
+        if let Some(collector) = self.filesystem.as_mut() {
+            if let Some(events) = collect_once("filesystem events", collector).await {
+                report_once("file events", storage.store_events("file_events", &events).await);
+            }
+            report_once("YARA alerts", storage.store_events("alerts", &collector.take_alerts()).await);
+        }
+        if let Some(collector) = self.registry.as_mut() {
+            if let Some(events) = collect_once("registry events", collector).await {
+                report_once("registry events", storage.store_events("registry_events", &events).await);
+            }
+            report_once(
+                "suspicious registry operations",
+                storage.store_events("suspicious_registry_operations", &collector.take_suspicious_operations()).await,
+            );
+        }
+        if let Some(collector) = self.exec_trace.as_mut() {
+            if let Some(events) = collect_once("exec trace events", collector).await {
+                report_once("exec events", storage.store_events("exec_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.ingest.as_mut() {
+            if let Some(events) = collect_once("ingested events", collector).await {
+                report_once("ingested events", storage.store_events("ingested_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.scheduled_tasks.as_mut() {
+            if let Some(events) = collect_once("scheduled tasks", collector).await {
+                report_once("scheduled task events", storage.store_events("scheduled_tasks", &events).await);
+            }
+        }
+        if let Some(collector) = self.osquery.as_mut() {
+            if let Some(events) = collect_once("osquery results", collector).await {
+                report_once("osquery results", storage.store_events("osquery_results", &events).await);
+            }
+        }
+        if let Some(collector) = self.honeypot.as_mut() {
+            if let Some(events) = collect_once("honeypot events", collector).await {
+                report_once("honeypot events", storage.store_events("honeypot_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.usb.as_mut() {
+            if let Some(events) = collect_once("USB events", collector).await {
+                report_once("USB events", storage.store_events("usb_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.deception.as_mut() {
+            if let Some(events) = collect_once("deception events", collector).await {
+                report_once("deception events", storage.store_events("deception_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.capture.as_mut() {
+            if let Some(events) = collect_once("clipboard / screen capture events", collector).await {
+                report_once("capture events", storage.store_events("capture_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.keylogging.as_mut() {
+            if let Some(events) = collect_once("keylogging indicators", collector).await {
+                report_once("keylogging events", storage.store_events("keylogging_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.print_spooler.as_mut() {
+            if let Some(events) = collect_once("print spooler events", collector).await {
+                report_once("print spooler events", storage.store_events("print_spooler_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.boot.as_mut() {
+            if let Some(events) = collect_once("boot configuration events", collector).await {
+                report_once("boot events", storage.store_events("boot_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.persistence.as_mut() {
+            if let Some(events) = collect_once::<_, Vec<PersistenceEvent>>("persistence entries", collector).await {
+                report_once("persistence events", storage.store_events("persistence_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.log_tail.as_mut() {
+            if let Some(events) = collect_once::<_, Vec<LogEvent>>("log records", collector).await {
+                report_once("log records", storage.store_events("log_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.snmp_trap.as_mut() {
+            if let Some(events) = collect_once::<_, Vec<SnmpTrapEvent>>("SNMP traps", collector).await {
+                report_once("SNMP traps", storage.store_events("snmp_trap_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.directory.as_mut() {
+            if let Some(events) = collect_once::<_, Vec<DirectoryChangeEvent>>("directory changes", collector).await {
+                report_once("directory changes", storage.store_events("directory_events", &events).await);
+            }
+        }
+        if let Some(collector) = self.tls_probe.as_mut() {
+            if let Some(events) = collect_once::<_, Vec<TlsProbeEvent>>("TLS probes", collector).await {
+                report_once("TLS probes", storage.store_events("tls_probe_events", &events).await);
+            }
+        }
+    }
+}
+
+async fn collect_once<C, T>(name: &str, collector: &mut C) -> Option<T>
+where
+    C: AsyncDataCollector<T> + Send,
+    T: Send,
+{
+    match AsyncDataCollector::<T>::collect(collector).await {
+        Ok(output) => Some(output),
         Err(e) => {
-            error!("Failed to initialize registry collector: {}", e);
-            return;
+            error!("Error collecting {}: {}", name, e);
+            None
         }
-    };
+    }
+}
+
+fn report_once(label: &str, result: Result<(), StorageError>) {
+    if let Err(e) = result {
+        error!("Failed to write {}: {}", label, e);
+    }
+}
 
-    let hostname = whoami::hostname();
+// Combines one collection of the metrics, network, process and service collectors
+// into a system_metrics document
+fn system_snapshot(
+    metrics: SystemMetrics,
+    network: NetworkMetrics,
+    processes: Vec<ProcessInformation>,
+    services: Vec<ServiceInformation>,
+) -> SystemInformation {
+    let identity = HostIdentityService::global().identity();
     let os_name = whoami::distro();
-    let os_version = os_name.clone(); // For now, we'll use distro as version
-    let kernel_version = whoami::platform().to_string();
-    
-    info!("Starting system metrics collection...");
-    
-    let mut interval = time::interval(Duration::from_secs(60));
-    
+    SystemInformation {
+        timestamp: SystemTime::now(),
+        hostname: identity.name,
+        fqdn: identity.fqdn,
+        os_version: os_name.clone(), // For now, we'll use distro as version
+        os_name,
+        kernel_version: whoami::platform().to_string(),
+        cpu_info: metrics.cpu_info,
+        memory_info: metrics.memory_info,
+        disk_info: metrics.disk_info,
+        network_info: network.interfaces,
+        process_info: processes,
+        system_load: metrics.system_load,
+        network_connections: network.connections,
+        services,
+        services_unchanged: false,
+    }
+}
+
+// Passes the agent's run state on to the collectors until it stops, or stops them when a
+// control client reloads the configuration and returns the new one
+async fn supervise(mut state: watch::Receiver<RunState>, collectors: watch::Sender<RunState>) -> Option<AgentConfig> {
+    let mut reloads = AgentControl::global().reload_requests();
     loop {
-        interval.tick().await;
-
-        // Collect metrics from all collectors
-        let metrics_result = AsyncDataCollector::collect(&mut metrics_collector).await;
-        let network_result = AsyncDataCollector::collect(&mut network_collector).await;
-        let process_result = AsyncDataCollector::collect(&mut process_collector).await;
-        let service_result = AsyncDataCollector::collect(&mut service_collector).await;
-        let filesystem_result = AsyncDataCollector::collect(&mut filesystem_collector).await;
-        let registry_result = AsyncDataCollector::collect(&mut registry_collector).await;
-
-        match (metrics_result, network_result, process_result, service_result, filesystem_result, registry_result) {
-            (Ok(metrics), Ok(network), Ok(processes), Ok(services), Ok(file_events), Ok(registry_events)) => {
-                // Create system information
-                let system_info = SystemInformation {
-                    timestamp: SystemTime::now(),
-                    hostname: hostname.clone(),
-                    os_name: os_name.clone(),
-                    os_version: os_version.clone(),
-                    kernel_version: kernel_version.clone(),
-                    cpu_info: metrics.cpu_info,
-                    memory_info: metrics.memory_info,
-                    disk_info: metrics.disk_info,
-                    network_info: network.interfaces,
-                    process_info: processes,
-                    system_load: metrics.system_load,
-                    network_connections: network.connections,
-                    services,
-                };
-                
-                // Log collection details
-                info!("Collected system information:");
-                info!("- {} network interfaces", system_info.network_info.len());
-                info!("- {} network connections", system_info.network_connections.len());
-                info!("- {} services", system_info.services.len());
-                info!("- {} processes", system_info.process_info.len());
-                info!("- {} disks", system_info.disk_info.len());
-                info!("- {} file events", file_events.len());
-                info!("- {} registry events", registry_events.len());
-                
-                // Store metrics in Elasticsearch
-                if let Err(e) = storage.store_system_info(&system_info).await {
-                    error!("Failed to store system metrics in Elasticsearch: {}", e);
-                    error!("Error details: {:?}", e);
-                    
-                    if e.to_string().contains("connection") {
-                        warn!("Elasticsearch connection might be lost. Please check if Elasticsearch is running.");
-                    }
-                } else {
-                    info!("Successfully stored system metrics in Elasticsearch");
+        tokio::select! {
+            changed = state.changed() => {
+                let state = if changed.is_ok() { *state.borrow() } else { RunState::Stopping };
+                AgentControl::global().set_paused(state == RunState::Paused);
+                collectors.send_replace(state);
+                if state == RunState::Stopping {
+                    return None;
+                }
+            }
+            Ok(()) = reloads.changed() => {
+                if let Some(config) = reloads.borrow_and_update().clone() {
+                    collectors.send_replace(RunState::Stopping);
+                    return Some(config);
                 }
+            }
+        }
+    }
+}
 
-                // Store file events
-                if !file_events.is_empty() {
-                    match storage.store_file_events(&file_events).await {
-                        Ok(_) => {
-                            info!("Successfully stored {} file events in Elasticsearch", file_events.len());
-                        }
-                        Err(e) => {
-                            error!("Failed to store file events in Elasticsearch: {}", e);
-                            error!("Error details: {:?}", e);
+// Runs every enabled collector on its own task until `state` says to stop. Returns the new
+// configuration when a control client reloaded it, None when the agent is stopping.
+async fn run(config: AgentConfig, storage: Box<dyn StorageBackend>, state: watch::Receiver<RunState>) -> Option<AgentConfig> {
+    let collectors = Collectors::new(&config);
+    AgentControl::global().running(&config, storage.name(), &collectors.degraded);
+    let (collectors_state, control) = watch::channel(*state.borrow());
+    let supervisor = tokio::spawn(supervise(state, collectors_state));
+    let Collectors {
+        metrics: metrics_collector,
+        network: network_collector,
+        process: process_collector,
+        service: service_collector,
+        filesystem: filesystem_collector,
+        registry: registry_collector,
+        exec_trace: exec_trace_collector,
+        ingest: ingest_collector,
+        scheduled_tasks: scheduled_task_collector,
+        osquery: osquery_collector,
+        honeypot: honeypot_collector,
+        usb: usb_collector,
+        deception: deception_collector,
+        capture: capture_collector,
+        keylogging: keylogging_collector,
+        print_spooler: print_spooler_collector,
+        boot: boot_collector,
+        persistence: persistence_collector,
+        log_tail: log_tail_collector,
+        snmp_trap: snmp_trap_collector,
+        directory: directory_collector,
+        tls_probe: tls_probe_collector,
+        degraded: _,
+    } = collectors;
+
+    let detection_config = config.detection.clone();
+    let rule_engine = if detection_config.enabled {
+        Some(RuleEngine::with_config(detection_config))
+    } else {
+        None
+    };
+
+    let egress_policy = if config.egress.enabled {
+        EgressPolicy::for_role(config.egress.clone(), config.role)
+    } else {
+        None
+    };
+
+    let lateral_movement = if config.lateral_movement.enabled {
+        Some(LateralMovementDetector::new(config.lateral_movement.clone()))
+    } else {
+        None
+    };
+
+    let outbound_volume = if config.outbound_volume.enabled {
+        Some(OutboundVolumeDetector::new(config.outbound_volume.clone()))
+    } else {
+        None
+    };
+
+    let ioc_config = config.ioc.clone();
+    let ioc_matcher = if ioc_config.enabled {
+        Some(Mutex::new(IocMatcher::with_config(ioc_config)))
+    } else {
+        None
+    };
+
+    let dedup = config.dedup.enabled.then(|| Deduplicator::new(config.dedup.clone()));
+
+    let pipeline = Arc::new(Pipeline {
+        storage,
+        alert_handler: AlertRouter::with_config(config.alerting.clone()),
+        rule_engine,
+        egress_policy,
+        lateral_movement,
+        outbound_volume,
+        ioc_matcher,
+        dedup,
+        processors: EventPipeline::new(&config.pipeline),
+        latest: Mutex::new(LatestCollections::default()),
+    });
+
+    let snapshot_encoder = SnapshotDeltaEncoder::new(config.storage.snapshot_delta.clone());
+    // ECS documents already split the snapshot into one document per process and service,
+    // and OTLP turns it into metrics
+    let native = config.storage.format == DocumentFormat::Native && config.storage.backend != BackendKind::Otlp;
+    // The split layout leaves no snapshot to encode
+    let split_snapshots = native && config.storage.system_metrics_layout == SnapshotLayout::Split;
+    let encode_deltas = native && !split_snapshots && snapshot_encoder.enabled();
+
+    let metrics_server = config.metrics.enabled.then(|| AgentMetrics::serve(config.metrics.clone()));
+
+    let intervals = &config.intervals;
+    let mut tasks = Vec::new();
+
+    // The system snapshot combines the metrics with the latest network, process and service collections
+    {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "system metrics",
+            "system_metrics",
+            intervals.interval("system_metrics"),
+            control.clone(),
+            metrics_collector,
+            move |_: &mut SystemMetricsCollector, metrics: SystemMetrics| {
+                let pipeline = pipeline.clone();
+                let latest = pipeline.latest_snapshot();
+                let snapshot = latest.map(|(network, processes, services)| {
+                    let mut snapshot = system_snapshot(metrics, network, processes, services);
+                    // An unchanged service list is left out until the dedup window runs out
+                    if pipeline.dedup.as_ref().is_some_and(|dedup| !dedup.services_changed(&snapshot.services)) {
+                        snapshot.services.clear();
+                        snapshot.services_unchanged = true;
+                    }
+                    snapshot
+                });
+                let documents = snapshot.as_ref().and_then(|system_info| {
+                    if split_snapshots {
+                        Some(split_snapshot(system_info))
+                    } else {
+                        encode_deltas.then(|| snapshot_encoder.encode("system_metrics", system_info).map(|document| vec![document]))
+                    }
+                });
+
+                async move {
+                    let Some(system_info) = snapshot else {
+                        info!("Waiting for the first network, process and service collections before storing a system snapshot");
+                        return;
+                    };
+
+                    // Log collection details
+                    info!("Collected system information:");
+                    info!("- {} network interfaces", system_info.network_info.len());
+                    info!("- {} network connections", system_info.network_connections.len());
+                    info!("- {} services", system_info.services.len());
+                    info!("- {} processes", system_info.process_info.len());
+                    info!("- {} disks", system_info.disk_info.len());
+
+                    // Store metrics in the configured backend
+                    let stored = match documents {
+                        Some(Ok(documents)) => pipeline.storage.batch_store(documents).await,
+                        Some(Err(e)) => Err(e),
+                        None => pipeline.storage.store_system_info(&system_info).await,
+                    };
+                    let storage = &pipeline.storage;
+                    AgentMetrics::global().record_storage("system metrics", 1, stored.is_ok());
+                    if let Err(e) = stored {
+                        error!("Failed to store system metrics in {} storage: {}", storage.name(), e);
+                        error!("Error details: {:?}", e);
+
+                        if e.to_string().to_lowercase().contains("connection") {
+                            warn!("{} storage connection might be lost. Please check if it is reachable.", storage.name());
                         }
+                    } else {
+                        info!("Successfully stored system metrics in {} storage", storage.name());
                     }
                 }
+            },
+        ));
+    }
 
-                // Store registry events
-                if !registry_events.is_empty() {
-                    match storage.store_registry_events(&registry_events).await {
-                        Ok(_) => {
-                            info!("Successfully stored {} registry events in Elasticsearch", registry_events.len());
-                        }
-                        Err(e) => {
-                            error!("Failed to store registry events in Elasticsearch: {}", e);
-                            error!("Error details: {:?}", e);
-                        }
+    {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "network information",
+            "network",
+            intervals.interval("network"),
+            control.clone(),
+            network_collector,
+            move |collector: &mut NetworkCollector, network: NetworkMetrics| {
+                let pipeline = pipeline.clone();
+                let port_changes = collector.take_port_changes();
+                let process_usage = collector.take_process_usage();
+                async move {
+                    pipeline.handle_events("listening_port_events", "listening port changes", port_changes).await;
+                    pipeline.handle_events("process_network_usage", "process network usage", process_usage).await;
+                    pipeline.detect(DetectionSource::Network, &network.connections).await;
+                    pipeline.check_egress(&network.connections).await;
+                    pipeline.check_lateral_movement(&network.connections).await;
+                    pipeline.check_outbound_volume(&network.connections).await;
+                    pipeline.match_iocs("system_metrics", &network.connections).await;
+                    pipeline.update_latest(|latest| latest.network = Some(network));
+                }
+            },
+        ));
+    }
+
+    {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "process information",
+            "process",
+            intervals.interval("process"),
+            control.clone(),
+            process_collector,
+            move |collector: &mut ProcessCollector, processes: Vec<ProcessInformation>| {
+                let pipeline = pipeline.clone();
+                // Started / stopped processes, only produced in delta or both mode
+                let process_events = collector.take_lifecycle_events();
+                async move {
+                    pipeline.detect(DetectionSource::Process, &processes).await;
+                    pipeline.match_iocs("system_metrics", &processes).await;
+                    pipeline.update_latest(|latest| latest.processes = Some(processes));
+
+                    let process_events = pipeline.processors.process("process_events", process_events);
+                    if !process_events.is_empty() {
+                        pipeline.inspect("process_events", &process_events).await;
+                        let result = pipeline.storage.store_events("process_events", &process_events).await;
+                        pipeline.report("process events", process_events.len(), result);
+                    }
+                }
+            },
+        ));
+    }
+
+    {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "service information",
+            "service",
+            intervals.interval("service"),
+            control.clone(),
+            service_collector,
+            move |collector: &mut ServiceCollector, services: Vec<ServiceInformation>| {
+                let pipeline = pipeline.clone();
+                let changes = collector.take_changes();
+                pipeline.update_latest(|latest| latest.services = Some(services));
+                async move { pipeline.handle_events("service_events", "service changes", changes).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = filesystem_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "filesystem events",
+            "filesystem",
+            intervals.interval("filesystem"),
+            control.clone(),
+            collector,
+            move |collector: &mut FileSystemCollector, file_events: Vec<FileEvent>| {
+                let pipeline = pipeline.clone();
+                // YARA matches on files seen in this collection
+                let yara_alerts = collector.take_alerts();
+                async move {
+                    pipeline.raise("YARA alerts", yara_alerts).await;
+                    let file_events = match pipeline.dedup.as_ref() {
+                        Some(dedup) => dedup.filter_file_events(file_events),
+                        None => file_events,
+                    };
+                    let file_events = pipeline.processors.process("file_events", file_events);
+                    if file_events.is_empty() {
+                        return;
+                    }
+                    info!("- {} file events", file_events.len());
+                    pipeline.detect(DetectionSource::File, &file_events).await;
+                    // Escalate critical events locally before shipping so they survive a backend outage
+                    pipeline.inspect("file_events", &file_events).await;
+                    let result = pipeline.storage.store_events("file_events", &file_events).await;
+                    pipeline.report("file events", file_events.len(), result);
+                }
+            },
+        ));
+    }
+
+    if let Some(collector) = registry_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "registry events",
+            "registry",
+            intervals.interval("registry"),
+            control.clone(),
+            collector,
+            move |collector: &mut RegistryCollector, registry_events: Vec<RegistryEvent>| {
+                let pipeline = pipeline.clone();
+                let suspicious_operations = collector.take_suspicious_operations();
+                async move {
+                    let suspicious_operations =
+                        pipeline.processors.process("suspicious_registry_operations", suspicious_operations);
+                    if !suspicious_operations.is_empty() {
+                        pipeline.inspect("suspicious_registry_operations", &suspicious_operations).await;
+                        let result = pipeline.storage.store_events("suspicious_registry_operations", &suspicious_operations).await;
+                        pipeline.report("suspicious registry operations", suspicious_operations.len(), result);
+                    }
+                    let registry_events = match pipeline.dedup.as_ref() {
+                        Some(dedup) => dedup.filter_registry_events(registry_events),
+                        None => registry_events,
+                    };
+                    let registry_events = pipeline.processors.process("registry_events", registry_events);
+                    if registry_events.is_empty() {
+                        return;
+                    }
+                    info!("- {} registry events", registry_events.len());
+                    pipeline.detect(DetectionSource::Registry, &registry_events).await;
+                    pipeline.inspect("registry_events", &registry_events).await;
+                    let result = pipeline.storage.store_events("registry_events", &registry_events).await;
+                    pipeline.report("registry events", registry_events.len(), result);
+                }
+            },
+        ));
+    }
+
+    if let Some(collector) = exec_trace_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "exec trace events",
+            "exec_trace",
+            intervals.interval("exec_trace"),
+            control.clone(),
+            collector,
+            move |_: &mut ExecTraceCollector, events: Vec<ExecEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("exec_events", "exec events", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = ingest_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "ingested events",
+            "ingest",
+            intervals.interval("ingest"),
+            control.clone(),
+            collector,
+            move |_: &mut IngestCollector, events: Vec<IngestedEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("ingested_events", "ingested events", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = scheduled_task_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "scheduled tasks",
+            "scheduled_tasks",
+            intervals.interval("scheduled_tasks"),
+            control.clone(),
+            collector,
+            move |_: &mut ScheduledTaskCollector, events: Vec<ScheduledTaskEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("scheduled_tasks", "scheduled task events", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = osquery_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "osquery results",
+            "osquery",
+            intervals.interval("osquery"),
+            control.clone(),
+            collector,
+            move |_: &mut OsqueryCollector, events: Vec<OsqueryResultEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("osquery_results", "osquery results", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = honeypot_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "honeypot events",
+            "honeypot",
+            intervals.interval("honeypot"),
+            control.clone(),
+            collector,
+            move |_: &mut HoneypotCollector, events: Vec<HoneypotEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("honeypot_events", "honeypot events", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = usb_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "USB events",
+            "usb",
+            intervals.interval("usb"),
+            control.clone(),
+            collector,
+            move |_: &mut UsbCollector, events: Vec<UsbEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("usb_events", "USB events", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = deception_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "deception events",
+            "deception",
+            intervals.interval("deception"),
+            control.clone(),
+            collector,
+            move |_: &mut DeceptionCollector, events: Vec<DeceptionEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("deception_events", "deception events", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = capture_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "clipboard / screen capture events",
+            "capture",
+            intervals.interval("capture"),
+            control.clone(),
+            collector,
+            move |_: &mut CaptureCollector, events: Vec<CaptureEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("capture_events", "capture events", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = keylogging_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "keylogging indicators",
+            "keylogging",
+            intervals.interval("keylogging"),
+            control.clone(),
+            collector,
+            move |_: &mut KeyloggingCollector, events: Vec<KeyloggingEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("keylogging_events", "keylogging events", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = print_spooler_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "print spooler events",
+            "print_spooler",
+            intervals.interval("print_spooler"),
+            control.clone(),
+            collector,
+            move |_: &mut PrintSpoolerCollector, events: Vec<PrintSpoolerEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("print_spooler_events", "print spooler events", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = boot_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "boot configuration events",
+            "boot",
+            intervals.interval("boot"),
+            control.clone(),
+            collector,
+            move |_: &mut BootCollector, events: Vec<BootEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("boot_events", "boot events", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = persistence_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "persistence entries",
+            "persistence",
+            intervals.interval("persistence"),
+            control.clone(),
+            collector,
+            move |_: &mut PersistenceCollector, events: Vec<PersistenceEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("persistence_events", "persistence events", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = log_tail_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "log records",
+            "log_tail",
+            intervals.interval("log_tail"),
+            control.clone(),
+            collector,
+            move |_: &mut LogTailCollector, events: Vec<LogEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("log_events", "log records", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = snmp_trap_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "SNMP traps",
+            "snmp_trap",
+            intervals.interval("snmp_trap"),
+            control.clone(),
+            collector,
+            move |_: &mut SnmpTrapCollector, events: Vec<SnmpTrapEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("snmp_trap_events", "SNMP traps", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = directory_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "directory changes",
+            "directory",
+            intervals.interval("directory"),
+            control.clone(),
+            collector,
+            move |_: &mut DirectoryCollector, events: Vec<DirectoryChangeEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("directory_events", "directory changes", events).await }
+            },
+        ));
+    }
+
+    if let Some(collector) = tls_probe_collector {
+        let pipeline = pipeline.clone();
+        tasks.push(spawn_collector(
+            "TLS probes",
+            "tls_probe",
+            intervals.interval("tls_probe"),
+            control.clone(),
+            collector,
+            move |_: &mut TlsProbeCollector, events: Vec<TlsProbeEvent>| {
+                let pipeline = pipeline.clone();
+                async move { pipeline.handle_events("tls_probe_events", "TLS probes", events).await }
+            },
+        ));
+    }
+
+    // Incidents change with new alerts and through the control channel; every change is
+    // stored as a new revision
+    IncidentTracker::global().configure(config.incidents.clone());
+    let incident_writer = config.incidents.enabled.then(|| {
+        let pipeline = pipeline.clone();
+        let mut control = control.clone();
+        tokio::spawn(async move {
+            let tracker = IncidentTracker::global();
+            loop {
+                let stopping = tokio::select! {
+                    _ = tracker.changed() => false,
+                    changed = control.changed() => changed.is_err() || *control.borrow() == RunState::Stopping,
+                };
+                let incidents = tracker.take_pending();
+                if !incidents.is_empty() {
+                    let result = pipeline.storage.store_events("incidents", &incidents).await;
+                    pipeline.report("incidents", incidents.len(), result);
+                }
+                if stopping {
+                    break;
+                }
+            }
+        })
+    });
+
+    // Response actions arrive through the control channel; their audit records are written
+    // with op_type create as soon as an action ran, and retrieved files next to them
+    let response_writer = config.control.enabled.then(|| {
+        let pipeline = pipeline.clone();
+        let mut control = control.clone();
+        tokio::spawn(async move {
+            let executor = ResponseActionExecutor::global();
+            loop {
+                let stopping = tokio::select! {
+                    _ = executor.changed() => false,
+                    changed = control.changed() => changed.is_err() || *control.borrow() == RunState::Stopping,
+                };
+                let audit_events = executor.drain_audit_events();
+                if !audit_events.is_empty() {
+                    let result = pipeline.storage.store_events("action_audit", &audit_events).await;
+                    pipeline.report("action audit records", audit_events.len(), result);
+                }
+                let files = executor.drain_retrieved_files();
+                if !files.is_empty() {
+                    let result = pipeline.storage.store_events("retrieved_files", &files).await;
+                    pipeline.report("retrieved files", files.len(), result);
+                }
+                if stopping {
+                    break;
+                }
+            }
+        })
+    });
+
+    info!("Started {} collector tasks", tasks.len());
+    for task in tasks {
+        if let Err(e) = task.await {
+            error!("Collector task stopped: {}", e);
+        }
+    }
+    info!("All collector tasks stopped");
+    if let Some(writer) = incident_writer {
+        writer.await.ok();
+    }
+    if let Some(writer) = response_writer {
+        writer.await.ok();
+    }
+    if let Some(server) = metrics_server {
+        server.abort();
+    }
+    // Already finished unless the collector tasks ended on their own
+    supervisor.abort();
+    supervisor.await.ok().flatten()
+}
+
+// Pulls the remote configuration once before anything starts, so the agent comes up on it;
+// an unreachable server or a rejected document leaves the local file in charge
+async fn pull_remote_config(config: AgentConfig) -> (AgentConfig, Option<RemoteConfigClient>) {
+    if !config.remote_config.enabled {
+        return (config, None);
+    }
+    let mut client = match RemoteConfigClient::new(config.remote_config.clone()) {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Remote configuration disabled: {}", e);
+            return (config, None);
+        }
+    };
+    match client.pull().await {
+        Ok(true) => match AgentConfig::load() {
+            Ok(remote) => {
+                info!("Running on the configuration pulled from {}", client.url());
+                (remote, Some(client))
+            }
+            Err(e) => {
+                warn!("Failed to load the configuration pulled from {}: {}", client.url(), e);
+                (config, Some(client))
+            }
+        },
+        Ok(false) => (config, Some(client)),
+        Err(e) => {
+            warn!("{}, running on the local configuration", e);
+            (config, Some(client))
+        }
+    }
+}
+
+const SECOND_INSTANCE_RULE_ID: &str = "second_agent_instance";
+
+// A second agent would store every event twice and fight the running one over the spool and
+// queues. It reports itself once, straight to the configured backend so none of that state is
+// touched, and exits.
+async fn refuse_second_instance(config: &AgentConfig, pid: u32, lock_file: &str) -> ! {
+    error!(
+        "Another agent instance (PID {}) is already running from this directory, holding {}; not starting a second one",
+        pid, lock_file
+    );
+    let matched_fields = BTreeMap::from([
+        (String::from("process_id"), std::process::id().to_string()),
+        (String::from("running_process_id"), pid.to_string()),
+        (String::from("lock_file"), lock_file.to_string()),
+    ]);
+    let alert = AlertBuilder::new()
+        .timestamp(Utc::now())
+        .source(HostIdentityService::global().name())
+        .category(String::from("agent"))
+        .rule_id(String::from(SECOND_INSTANCE_RULE_ID))
+        .rule_name(String::from("Second agent instance refused"))
+        .description(Some(format!(
+            "An agent instance (PID {}) tried to start while PID {} holds {}, and exited without collecting",
+            std::process::id(),
+            pid,
+            lock_file
+        )))
+        .severity(Severity::Medium)
+        .detection_source(DetectionSource::Process)
+        .matched_fields(matched_fields)
+        .tags(vec![String::from("agent_health")])
+        .build();
+    match (alert, create_direct_backend(&config.storage, &config.elasticsearch)) {
+        (Ok(alert), Ok(storage)) => {
+            if let Err(e) = storage.store_events("alerts", &[alert]).await {
+                warn!("Failed to report the refused instance: {}", e);
+            }
+        }
+        (Err(e), _) => warn!("Failed to report the refused instance: {}", e),
+        (_, Err(e)) => warn!("Failed to report the refused instance: {}", e),
+    }
+    std::process::exit(1);
+}
+
+// The agent proper: storage, ILM and every collector, until `control` says to stop. A
+// configuration reload from a control client or the management server restarts storage and
+// the collectors.
+async fn start(config: AgentConfig, control: watch::Receiver<RunState>) {
+    // Held until the agent stops
+    let _instance_lock = if config.instance.enabled {
+        match InstanceLock::acquire(&config.instance) {
+            Ok(lock) => Some(lock),
+            Err(InstanceLockError::Held { pid, path }) => refuse_second_instance(&config, pid, &path).await,
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    if config.capabilities.require_elevation && !Capabilities::global().elevated() {
+        error!(
+            "The agent runs without {} and capabilities.require_elevation is set; not starting",
+            if cfg!(windows) { "administrator rights" } else { "root" }
+        );
+        std::process::exit(1);
+    }
+    let (config, remote_config) = pull_remote_config(config).await;
+
+    // Initialize the configured storage backend
+    let storage = match create_backend(&config.storage, &config.elasticsearch) {
+        Ok(storage) => storage,
+        Err(e) => {
+            error!("Failed to initialize storage backend: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let es_config = &config.elasticsearch;
+    let install_templates = es_config.templates.install_on_startup && config.storage.backend == BackendKind::Elasticsearch;
+    if es_config.ilm.apply_on_startup || install_templates {
+        match ElasticsearchStorage::from_config(es_config) {
+            Ok(es_storage) => {
+                // Templates reference the policies, so the policies go first
+                if es_config.ilm.apply_on_startup {
+                    if let Err(e) = es_storage.apply_ilm_policies(&es_config.ilm).await {
+                        warn!("Failed to apply ILM policies on startup: {}", e);
+                    }
+                }
+                if install_templates {
+                    if let Err(e) = es_storage.install_index_templates(&es_config.templates).await {
+                        warn!("Failed to install index templates on startup: {}", e);
                     }
                 }
             }
-            (Err(e), _, _, _, _, _) => {
-                error!("Error collecting system metrics: {}", e);
+            Err(e) => warn!("Failed to provision Elasticsearch on startup: {}", e),
+        }
+    }
+
+    // Outlives reloads; a new listen address or TLS setting needs a restart
+    let control_server = config.control.enabled.then(|| ControlServer::serve(config.control.clone()));
+    let remote_config = remote_config.map(RemoteConfigClient::spawn);
+    let config_watcher = match (&config.source, config.agent.watch_config) {
+        (Some(path), true) => match ConfigWatcher::new(path.clone()) {
+            Ok(watcher) => Some(watcher.spawn()),
+            Err(e) => {
+                warn!("Configuration changes need a reload or restart: {}", e);
+                None
             }
-            (_, Err(e), _, _, _, _) => {
-                error!("Error collecting network information: {}", e);
+        },
+        _ => None,
+    };
+
+    let mut config = config;
+    let mut storage = storage;
+    while let Some(reloaded) = run(config.clone(), storage, control.clone()).await {
+        storage = match create_backend(&reloaded.storage, &reloaded.elasticsearch) {
+            Ok(storage) => {
+                info!("Restarting the collectors on the reloaded configuration");
+                config = reloaded;
+                storage
             }
-            (_, _, Err(e), _, _, _) => {
-                error!("Error collecting process information: {}", e);
+            Err(e) => {
+                error!("Failed to initialize storage backend from the reloaded configuration, restarting on the previous one: {}", e);
+                match create_backend(&config.storage, &config.elasticsearch) {
+                    Ok(storage) => storage,
+                    Err(e) => {
+                        error!("Failed to initialize storage backend: {}", e);
+                        std::process::exit(1);
+                    }
+                }
             }
-            (_, _, _, Err(e), _, _) => {
-                error!("Error collecting service information: {}", e);
+        };
+    }
+
+    if let Some(server) = control_server {
+        server.abort();
+    }
+    if let Some(puller) = remote_config {
+        puller.abort();
+    }
+    if let Some(watcher) = config_watcher {
+        watcher.abort();
+    }
+}
+
+// `lsedr es-setup` provisions the cluster and exits without collecting
+async fn es_setup(es_config: &ElasticsearchConfig) -> Result<(), StorageError> {
+    let es_storage = ElasticsearchStorage::from_config(es_config)?;
+    if es_config.pipeline.install_default {
+        es_storage.install_default_pipeline().await?;
+    }
+    let count = es_storage.apply_ilm_policies(&es_config.ilm).await?;
+    info!("Provisioned {} ILM policies", count);
+    let count = es_storage.install_index_templates(&es_config.templates).await?;
+    info!("Provisioned {} index templates", count);
+    Ok(())
+}
+
+fn check_config(config: &AgentConfig) {
+    match &config.source {
+        Some(path) => println!("Configuration {} is valid", path.display()),
+        None => println!("No configuration file found, the built-in profile and environment overrides are valid"),
+    }
+    match config.role {
+        Some(role) => println!("Policy profile: {} ({})", role, config.agent.profile),
+        None => println!("Policy profile: none"),
+    }
+    if config.vdi.mode != VdiMode::Off {
+        let clone = if config.vdi_clone { "yes" } else { "no" };
+        println!("VDI clone: {} ({}), reporting as {}", clone, config.vdi.mode, HostIdentityService::global().name());
+    }
+    println!("Storage backend: {:?}", config.storage.backend);
+    if config.storage.failover.enabled {
+        println!("Failover backend: {:?}", config.storage.failover.secondary);
+    }
+    if config.metrics.enabled {
+        println!("Metrics endpoint: http://{}{}", config.metrics.listen, config.metrics.path);
+    }
+    if config.control.enabled {
+        let scheme = if config.control.tls.cert_file.is_some() { "https" } else { "http" };
+        println!("Control server: {}://{}", scheme, config.control.listen);
+    }
+    if config.instance.enabled {
+        println!("Instance lock: {}", config.instance.lock_file);
+    }
+    if config.remote_config.enabled {
+        println!("Remote configuration: {} every {}s", config.remote_config.url, config.remote_config.interval_secs);
+    }
+    let optional = [
+        ("exec_trace", config.exec_trace.enabled),
+        ("ingest", config.ingest.enabled),
+        ("scheduled_tasks", config.scheduled_tasks.enabled),
+        ("osquery", config.osquery.enabled),
+        ("honeypot", config.honeypot.enabled),
+        ("usb", config.usb.enabled),
+        ("deception", config.deception.enabled),
+        ("capture", config.capture.enabled),
+        ("keylogging", config.keylogging.enabled),
+        ("print_spooler", config.print_spooler.enabled),
+        ("boot", config.boot.enabled),
+        ("persistence", config.persistence.enabled),
+        ("log_tail", config.log_tail.enabled),
+        ("snmp_trap", config.snmp_trap.enabled),
+        ("directory", config.directory.enabled),
+        ("tls_probe", config.tls_probe.enabled),
+        ("detection", config.detection.enabled),
+        ("egress", config.egress.enabled),
+        ("lateral_movement", config.lateral_movement.enabled),
+        ("outbound_volume", config.outbound_volume.enabled),
+        ("incidents", config.incidents.enabled),
+        ("ioc", config.ioc.enabled),
+        ("dedup", config.dedup.enabled),
+    ];
+    let enabled: Vec<&str> = optional.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| *name).collect();
+    println!("Optional collectors enabled: {}", if enabled.is_empty() { String::from("none") } else { enabled.join(", ") });
+}
+
+// Prints one line per check and returns whether all of them passed
+async fn health(config: &AgentConfig) -> bool {
+    let mut results = Vec::new();
+    match create_backend(&config.storage, &config.elasticsearch) {
+        Ok(storage) => results.push((format!("storage ({})", storage.name()), storage.health_check().await)),
+        Err(e) => {
+            error!("Failed to initialize storage backend: {}", e);
+            results.push((String::from("storage"), false));
+        }
+    }
+    for capability in &Capabilities::global().capabilities {
+        let state = if capability.available { "yes" } else { "no" };
+        println!("{:<6} capability {}: {}", state, capability.name, capability.detail);
+    }
+    let collectors = Collectors::without_listeners(config);
+    for degradation in &collectors.degraded {
+        println!("{:<6} collector {}", "degr", degradation);
+    }
+    for name in Collectors::listeners(config) {
+        println!("{:<6} collector {}: listener, not started by a one-shot check", "skip", name);
+    }
+    for (name, healthy) in collectors.health().await {
+        results.push((format!("collector {}", name), healthy));
+    }
+
+    for (name, healthy) in &results {
+        println!("{:<6} {}", if *healthy { "ok" } else { "FAIL" }, name);
+    }
+    results.iter().all(|(_, healthy)| *healthy)
+}
+
+// Sweeps the autorun baseline and inventory once and prints the report, or writes it to the
+// configured storage
+async fn persistence_audit(config: &AgentConfig, store: bool, output: OutputFormat) -> Result<(), StorageError> {
+    let audit = RegistryCollector::persistence_audit(&config.registry);
+    info!(
+        "Persistence audit found {} suspicious entries among {} present and {} removed autoruns",
+        audit.findings.len(),
+        audit.present_entries,
+        audit.removed_entries
+    );
+    let document = StorageDocument::new(PERSISTENCE_AUDIT_INDEX, &audit)?;
+    if store {
+        create_backend(&config.storage, &config.elasticsearch)?.store(document).await
+    } else {
+        StdoutStorage::new().pretty(output == OutputFormat::Pretty).store(document).await
+    }
+}
+
+fn maintenance(action: MaintenanceAction) {
+    let mode = MaintenanceMode::global();
+    let result = match action {
+        MaintenanceAction::Start { minutes, reason } => mode.start(minutes, reason).map(|state| {
+            println!("Maintenance mode on until {}", state.until.with_timezone(&Local).to_rfc3339());
+        }),
+        MaintenanceAction::Stop => mode.stop().map(|stopped| {
+            if stopped {
+                println!("Maintenance mode off");
+            } else {
+                println!("Maintenance mode was not started manually");
             }
-            (_, _, _, _, Err(e), _) => {
-                error!("Error collecting filesystem events: {}", e);
+        }),
+        MaintenanceAction::Status => {
+            match mode.current() {
+                Some(MaintenanceSource::Manual { until, reason }) => println!(
+                    "Maintenance mode on until {} ({})",
+                    until.with_timezone(&Local).to_rfc3339(),
+                    reason.as_deref().unwrap_or("manual")
+                ),
+                Some(MaintenanceSource::Window) => println!("Maintenance mode on (scheduled window)"),
+                None => println!("Maintenance mode off"),
             }
-            (_, _, _, _, _, Err(e)) => {
-                error!("Error collecting registry events: {}", e);
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        error!("Failed to update maintenance state: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn state(config: &AgentConfig, action: StateAction) {
+    let key_env = match &action {
+        StateAction::Export { key_env, .. } | StateAction::Import { key_env, .. } => key_env,
+    };
+    let passphrase = match std::env::var(key_env) {
+        Ok(passphrase) if !passphrase.is_empty() => passphrase,
+        _ => {
+            error!("Set the archive passphrase in the {} environment variable", key_env);
+            std::process::exit(1);
+        }
+    };
+    // Files the running agent rewrites would be replaced underneath it
+    if let (StateAction::Import { .. }, Some(holder)) = (&action, InstanceLock::holder(&config.instance)) {
+        error!("Stop the agent (PID {}) before importing state", holder.pid);
+        std::process::exit(1);
+    }
+    let result = match action {
+        StateAction::Export { output, .. } => export_state(config, &output, &passphrase).map(|summary| {
+            println!(
+                "Exported {} files ({} bytes) of {} to {}",
+                summary.files,
+                summary.bytes,
+                summary.identity.name,
+                output.display()
+            );
+        }),
+        StateAction::Import { input, replace, .. } => import_state(config, &input, &passphrase, replace).map(|summary| {
+            println!(
+                "Imported {} files ({} bytes) exported from {} at {}",
+                summary.files,
+                summary.bytes,
+                summary.identity.name,
+                summary.exported_at.with_timezone(&Local).to_rfc3339()
+            );
+            let name = HostIdentityService::global().name();
+            // Baselines follow the host name the events are stamped with
+            if name != summary.identity.name {
+                println!(
+                    "This machine reports as {}; set host_identity.override_name to {} to keep reporting as the old host",
+                    name, summary.identity.name
+                );
             }
+        }),
+    };
+    if let Err(e) = result {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let command = Cli::parse().command.unwrap_or(Command::Run);
+    // Service managers start the agent in their own directory; relative paths in the
+    // configuration are resolved against the one given at install time
+    if let Command::Service { working_dir: Some(dir) } = &command {
+        if let Err(e) = std::env::set_current_dir(dir) {
+            eprintln!("Failed to enter working directory {}: {}", dir.display(), e);
+            std::process::exit(1);
         }
+    }
+    if let Command::Version = command {
+        println!(
+            "lsedr {} ({}/{})",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+        return;
+    }
+
+    // Every setting comes from one file plus LSEDR__ environment overrides; a broken
+    // configuration stops the agent before anything is collected
+    let config = match AgentConfig::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    // Logs go to stderr, so `collect` output on stdout stays parseable
+    env_logger::Builder::new().filter_level(config.log_level()).init();
+    match &config.source {
+        Some(path) => info!("Loaded configuration from {}", path.display()),
+        None => info!("No configuration file found, running on the built-in monitoring profile and environment overrides"),
+    }
+    match config.role {
+        Some(role) => info!("Applied the {} policy profile ({})", role, config.agent.profile),
+        None => info!("No policy profile applied"),
+    }
+    if config.vdi_clone {
+        info!("Running as a VDI clone ({}), with high-volume collection trimmed", config.vdi.mode);
+    }
 
-        info!("Waiting 60 seconds before next collection...");
+    match command {
+        Command::Run => {
+            let (control, state) = watch::channel(RunState::Running);
+            service::stop_on_signal(control);
+            start(config, state).await;
+        }
+        Command::Service { .. } => {
+            let handle = Handle::current();
+            // The service manager calls back on a thread of its own, which drives the agent
+            // on this runtime
+            let result = tokio::task::spawn_blocking(move || {
+                service::run(move |control| handle.block_on(start(config, control)))
+            })
+            .await
+            .unwrap_or_else(|e| Err(ServiceError::Manager(e.to_string())));
+            if let Err(e) = result {
+                error!("Service failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::InstallService => {
+            let working_dir = match std::env::current_dir() {
+                Ok(dir) => dir,
+                Err(e) => {
+                    error!("Failed to read the working directory: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            match service::install(&working_dir) {
+                Ok(()) => println!(
+                    "Installed and started service {}, working directory {}",
+                    service::SERVICE_NAME,
+                    working_dir.display()
+                ),
+                Err(e) => {
+                    error!("Failed to install service: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::UninstallService => match service::uninstall() {
+            Ok(()) => println!("Removed service {}", service::SERVICE_NAME),
+            Err(e) => {
+                error!("Failed to uninstall service: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Command::CheckConfig => check_config(&config),
+        Command::Collect { once, output } => {
+            let storage = StdoutStorage::new().pretty(output == OutputFormat::Pretty);
+            if once {
+                Collectors::without_listeners(&config).collect_once(&storage).await;
+            } else {
+                let (control, state) = watch::channel(RunState::Running);
+                service::stop_on_signal(control);
+                run(config, Box::new(storage), state).await;
+            }
+        }
+        Command::Health => {
+            if !health(&config).await {
+                std::process::exit(1);
+            }
+        }
+        Command::EsSetup => {
+            if let Err(e) = es_setup(&config.elasticsearch).await {
+                error!("Elasticsearch setup failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::PersistenceAudit { store, output } => {
+            if let Err(e) = persistence_audit(&config, store, output).await {
+                error!("Persistence audit failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Maintenance { action } => maintenance(action),
+        Command::State { action } => state(&config, action),
+        Command::Version => {}
     }
 }
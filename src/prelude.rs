@@ -0,0 +1,36 @@
+// The API programs embedding lsedr build against. Names here only change in a major
+// release; the `features`, `shared` and `utils` modules behind the `unstable` feature may
+// change in any release.
+pub use crate::agent::{Agent, AgentBuilder};
+
+pub use crate::shared::traits::{
+    AsyncDataCollector,
+    DataCollector,
+    DataStorage,
+    Enricher,
+    Event,
+    Identifiable,
+    Severity,
+    Validatable,
+};
+pub use crate::shared::error::{CollectionError, ConfigError, StorageError};
+pub use crate::shared::config::AgentConfig;
+pub use crate::shared::enrichment::EnrichmentChain;
+pub use crate::shared::storage::{create_backend, StdoutStorage, StorageBackend, StorageDocument};
+
+pub use crate::features::filesystem::{FileEvent, FileEventType, FileSystemCollector, FileSystemConfig};
+pub use crate::features::registry::{
+    RegistryCollector,
+    RegistryConfig,
+    RegistryEvent,
+    RegistryEventType,
+    SuspiciousRegistryOperation,
+};
+pub use crate::features::process::{ProcessCollector, ProcessConfig, ProcessInformation, ProcessLifecycleEvent};
+pub use crate::features::network::{NetworkCollector, NetworkConnectionInformation, NetworkMetrics};
+pub use crate::features::service::{ServiceCollector, ServiceInformation};
+pub use crate::features::exec_trace::{ExecEvent, ExecEventType, ExecTraceCollector, ExecTraceConfig};
+pub use crate::features::scheduled_tasks::{ScheduledTaskCollector, ScheduledTaskConfig, ScheduledTaskEvent};
+pub use crate::features::usb::{UsbCollector, UsbConfig, UsbEvent};
+pub use crate::features::boot::{BootCollector, BootConfig, BootEvent};
+pub use crate::features::detection::{Alert, DetectionSource};
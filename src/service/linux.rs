@@ -0,0 +1,86 @@
+use super::{stop_on_signal, RunState, DESCRIPTION, SERVICE_NAME};
+use log::warn;
+use lsedr::shared::command;
+use lsedr::shared::config::CONFIG_PATH_ENV;
+use lsedr::shared::error::ServiceError;
+use std::fs;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::path::Path;
+use tokio::sync::watch;
+
+const UNIT_PATH: &str = "/etc/systemd/system/lsedr.service";
+
+// systemd stops the unit with SIGTERM, which ends the collection loop. With Type=notify
+// the unit only counts as started once the collectors are about to run.
+pub fn run<F>(agent: F) -> Result<(), ServiceError>
+where
+    F: FnOnce(watch::Receiver<RunState>) + Send + 'static,
+{
+    let (control, state) = watch::channel(RunState::Running);
+    stop_on_signal(control);
+    notify("READY=1");
+    agent(state);
+    notify("STOPPING=1");
+    Ok(())
+}
+
+// sd_notify(3) without libsystemd: one datagram to the socket systemd passes in NOTIFY_SOCKET
+fn notify(message: &str) {
+    let Ok(path) = std::env::var("NOTIFY_SOCKET") else {
+        return;
+    };
+    let result = UnixDatagram::unbound().and_then(|socket| match path.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes())
+            .and_then(|address| socket.send_to_addr(message.as_bytes(), &address)),
+        None => socket.send_to(message.as_bytes(), &path),
+    });
+    if let Err(e) = result {
+        warn!("Failed to notify systemd: {}", e);
+    }
+}
+
+fn systemctl(args: &[&str]) -> Result<(), ServiceError> {
+    let output = command::run("systemctl", args).map_err(|e| ServiceError::Io(String::from("systemctl"), e))?;
+    if !output.success {
+        return Err(ServiceError::Manager(format!("systemctl {}: {}", args.join(" "), output.stderr.trim())));
+    }
+    Ok(())
+}
+
+pub fn install(working_dir: &Path) -> Result<(), ServiceError> {
+    let executable = std::env::current_exe().map_err(|e| ServiceError::Io(String::from("current executable"), e))?;
+
+    let mut unit = format!(
+        "[Unit]\n\
+         Description={}\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         ExecStart=\"{}\" service\n\
+         WorkingDirectory={}\n",
+        DESCRIPTION,
+        executable.display(),
+        working_dir.display()
+    );
+    // A configuration outside the working directory has to reach the service too
+    if let Ok(path) = std::env::var(CONFIG_PATH_ENV) {
+        unit.push_str(&format!("Environment=\"{}={}\"\n", CONFIG_PATH_ENV, path));
+    }
+    unit.push_str("Restart=on-failure\nRestartSec=5\n\n[Install]\nWantedBy=multi-user.target\n");
+
+    fs::write(UNIT_PATH, unit).map_err(|e| ServiceError::Io(String::from(UNIT_PATH), e))?;
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", "--now", SERVICE_NAME])
+}
+
+pub fn uninstall() -> Result<(), ServiceError> {
+    if !Path::new(UNIT_PATH).exists() {
+        return Err(ServiceError::Manager(format!("{} is not installed", UNIT_PATH)));
+    }
+    systemctl(&["disable", "--now", SERVICE_NAME])?;
+    fs::remove_file(UNIT_PATH).map_err(|e| ServiceError::Io(String::from(UNIT_PATH), e))?;
+    systemctl(&["daemon-reload"])
+}
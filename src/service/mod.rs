@@ -0,0 +1,85 @@
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(windows)]
+mod win32;
+
+use log::info;
+use lsedr::shared::error::ServiceError;
+use std::path::Path;
+use tokio::sync::watch;
+
+// Name of the Windows service and of the systemd unit
+pub const SERVICE_NAME: &str = "lsedr";
+pub const DESCRIPTION: &str = "SpathaX endpoint detection and response agent";
+
+// What the service manager, or a signal in the foreground, asks the collectors to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+    Running,
+    // Only the Windows service control manager pauses a service
+    #[cfg_attr(not(windows), allow(dead_code))]
+    Paused,
+    Stopping,
+}
+
+// Runs `agent` under the platform's service manager and returns once it has stopped
+pub fn run<F>(agent: F) -> Result<(), ServiceError>
+where
+    F: FnOnce(watch::Receiver<RunState>) + Send + 'static,
+{
+    #[cfg(windows)]
+    return win32::run(agent);
+    #[cfg(target_os = "linux")]
+    return linux::run(agent);
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        let _ = agent;
+        Err(ServiceError::Unsupported)
+    }
+}
+
+pub fn install(working_dir: &Path) -> Result<(), ServiceError> {
+    #[cfg(windows)]
+    return win32::install(working_dir);
+    #[cfg(target_os = "linux")]
+    return linux::install(working_dir);
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        let _ = working_dir;
+        Err(ServiceError::Unsupported)
+    }
+}
+
+pub fn uninstall() -> Result<(), ServiceError> {
+    #[cfg(windows)]
+    return win32::uninstall();
+    #[cfg(target_os = "linux")]
+    return linux::uninstall();
+    #[cfg(not(any(windows, target_os = "linux")))]
+    Err(ServiceError::Unsupported)
+}
+
+// Stops the collectors on Ctrl+C, and on SIGTERM from systemd or `kill`
+pub fn stop_on_signal(control: watch::Sender<RunState>) {
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        let terminate = async {
+            use tokio::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::terminate()) {
+                Ok(mut terminate) => {
+                    terminate.recv().await;
+                }
+                Err(_) => std::future::pending::<()>().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate => {}
+        }
+        info!("Stopping collectors");
+        control.send_replace(RunState::Stopping);
+    });
+}
@@ -0,0 +1,161 @@
+use super::{RunState, DESCRIPTION, SERVICE_NAME};
+use log::{error, info};
+use lsedr::shared::error::ServiceError;
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::watch;
+use windows_service::service::{
+    ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode, ServiceInfo,
+    ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+
+const DISPLAY_NAME: &str = "SpathaX EDR Agent";
+
+type Agent = Box<dyn FnOnce(watch::Receiver<RunState>) + Send>;
+
+// The dispatcher calls service_main without any arguments of ours, so the agent waits here
+static AGENT: Mutex<Option<Agent>> = Mutex::new(None);
+
+define_windows_service!(ffi_service_main, service_main);
+
+fn manager_error(e: windows_service::Error) -> ServiceError {
+    ServiceError::Manager(e.to_string())
+}
+
+// Blocks until the service control manager has stopped the service
+pub fn run<F>(agent: F) -> Result<(), ServiceError>
+where
+    F: FnOnce(watch::Receiver<RunState>) + Send + 'static,
+{
+    if let Ok(mut slot) = AGENT.lock() {
+        *slot = Some(Box::new(agent));
+    }
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main).map_err(manager_error)
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_agent() {
+        error!("Service failed: {}", e);
+    }
+}
+
+fn status(state: ServiceState) -> ServiceStatus {
+    let pending = matches!(state, ServiceState::StartPending | ServiceState::StopPending);
+    let controls_accepted = if pending || state == ServiceState::Stopped {
+        ServiceControlAccept::empty()
+    } else {
+        ServiceControlAccept::STOP | ServiceControlAccept::PAUSE_CONTINUE | ServiceControlAccept::SHUTDOWN
+    };
+    ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted,
+        exit_code: ServiceExitCode::Win32(0),
+        checkpoint: 0,
+        // Collectors finish their current collection before stopping
+        wait_hint: if pending { Duration::from_secs(30) } else { Duration::default() },
+        process_id: None,
+    }
+}
+
+fn run_agent() -> Result<(), ServiceError> {
+    let agent = AGENT
+        .lock()
+        .ok()
+        .and_then(|mut slot| slot.take())
+        .ok_or_else(|| ServiceError::Manager(String::from("service started without an agent")))?;
+
+    let (control, state) = watch::channel(RunState::Running);
+    let handler_control = control.clone();
+    let status_handle = service_control_handler::register(SERVICE_NAME, move |event| match event {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            handler_control.send_replace(RunState::Stopping);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Pause => {
+            handler_control.send_replace(RunState::Paused);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Continue => {
+            handler_control.send_replace(RunState::Running);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })
+    .map_err(manager_error)?;
+    status_handle.set_service_status(status(ServiceState::Running)).map_err(manager_error)?;
+    info!("Running as Windows service {}", SERVICE_NAME);
+
+    let mut reported = state.clone();
+    let worker = std::thread::spawn(move || {
+        agent(state);
+        // The agent can also end on its own, e.g. when no storage backend could be created
+        control.send_replace(RunState::Stopping);
+    });
+
+    // Confirms pause, continue and stop to the service control manager
+    while futures::executor::block_on(reported.changed()).is_ok() {
+        let current = *reported.borrow_and_update();
+        let service_state = match current {
+            RunState::Running => ServiceState::Running,
+            RunState::Paused => ServiceState::Paused,
+            RunState::Stopping => ServiceState::StopPending,
+        };
+        status_handle.set_service_status(status(service_state)).map_err(manager_error)?;
+        if current == RunState::Stopping {
+            break;
+        }
+    }
+    let _ = worker.join();
+    status_handle.set_service_status(status(ServiceState::Stopped)).map_err(manager_error)
+}
+
+pub fn install(working_dir: &Path) -> Result<(), ServiceError> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .map_err(manager_error)?;
+    let executable = std::env::current_exe().map_err(|e| ServiceError::Io(String::from("current executable"), e))?;
+
+    let info = ServiceInfo {
+        name: OsString::from(SERVICE_NAME),
+        display_name: OsString::from(DISPLAY_NAME),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: executable,
+        launch_arguments: vec![
+            OsString::from("service"),
+            OsString::from("--working-dir"),
+            working_dir.as_os_str().to_owned(),
+        ],
+        dependencies: Vec::new(),
+        // LocalSystem, which can read every user's profile and the whole registry
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager
+        .create_service(&info, ServiceAccess::CHANGE_CONFIG | ServiceAccess::START)
+        .map_err(manager_error)?;
+    service.set_description(DESCRIPTION).map_err(manager_error)?;
+    service.start::<&str>(&[]).map_err(manager_error)
+}
+
+pub fn uninstall() -> Result<(), ServiceError> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT).map_err(manager_error)?;
+    let service = manager
+        .open_service(SERVICE_NAME, ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE)
+        .map_err(manager_error)?;
+    if service.query_status().map_err(manager_error)?.current_state != ServiceState::Stopped {
+        service.stop().map_err(manager_error)?;
+    }
+    // The service is removed once the last handle to it closes
+    service.delete().map_err(manager_error)
+}
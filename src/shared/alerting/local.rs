@@ -0,0 +1,177 @@
+use crate::shared::alerting::notification::{NotificationConfig, RateLimit};
+use crate::shared::traits::{Event, Identifiable, Severity};
+use crate::utils::load_config_section;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::Mutex;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use which::which;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertingConfig {
+    pub local: LocalAlertConfig,
+    pub notifications: NotificationConfig,
+}
+
+impl AlertingConfig {
+    pub fn load() -> Self {
+        load_config_section("alerting")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalAlertConfig {
+    pub enabled: bool,
+    pub min_severity: Severity,
+    pub system_log: bool,
+    pub notify_user: bool,
+    pub source_name: String,
+}
+
+impl Default for LocalAlertConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_severity: Severity::Critical,
+            system_log: true,
+            notify_user: false,
+            source_name: String::from("SpathaX"),
+        }
+    }
+}
+
+// Local alerts waiting for their commands; more are dropped rather than holding up the
+// pipeline when the commands are slow
+const MAX_QUEUED: usize = 100;
+
+// Second alerting tier: events at or above min_severity are also surfaced on the
+// host itself so they are visible even when the storage backend is unreachable.
+// The system log and user notification commands run from a task of their own, at most
+// notifications.max_per_minute a minute; the agent's own log always gets the alert.
+pub struct LocalAlertHandler {
+    config: LocalAlertConfig,
+    queue: Sender<String>,
+    rate: Mutex<RateLimit>,
+}
+
+impl LocalAlertHandler {
+    // Must be called within the Tokio runtime, which runs the commands
+    pub fn new() -> Self {
+        let config = AlertingConfig::load();
+        Self::with_config(config.local, config.notifications.max_per_minute)
+    }
+
+    pub fn with_config(config: LocalAlertConfig, max_per_minute: u32) -> Self {
+        let (queue, pending) = channel(MAX_QUEUED);
+        tokio::spawn(Self::deliver(config.clone(), pending));
+        Self {
+            config,
+            queue,
+            rate: Mutex::new(RateLimit::new(max_per_minute)),
+        }
+    }
+
+    pub fn should_escalate(&self, severity: Severity) -> bool {
+        self.config.enabled && severity >= self.config.min_severity
+    }
+
+    pub fn handle<E: Event + Identifiable>(&self, event: &E) -> bool {
+        if !self.should_escalate(event.severity()) {
+            return false;
+        }
+
+        let message = format!(
+            "[{:?}] {} ({}) on {} at {} - event id {}",
+            event.severity(),
+            event.event_type(),
+            event.category(),
+            event.source(),
+            event.timestamp().to_rfc3339(),
+            event.id(),
+        );
+        warn!("Local alert: {}", message);
+
+        if !self.config.system_log && !self.config.notify_user {
+            return true;
+        }
+        if !self.rate.lock().is_ok_and(|mut rate| rate.admit("local alerts")) {
+            return true;
+        }
+        if self.queue.try_send(message).is_err() {
+            warn!("Local alert queue full, dropping the local alert for event {}", event.id());
+        }
+        true
+    }
+
+    pub fn handle_all<E: Event + Identifiable>(&self, events: &[E]) -> usize {
+        events.iter().filter(|event| self.handle(*event)).count()
+    }
+
+    async fn deliver(config: LocalAlertConfig, mut pending: Receiver<String>) {
+        while let Some(message) = pending.recv().await {
+            let config = config.clone();
+            // Runs helper programs, kept off the runtime's worker threads
+            let delivered = tokio::task::spawn_blocking(move || {
+                if config.system_log {
+                    write_system_log(&config.source_name, &message);
+                }
+                if config.notify_user {
+                    desktop_notification(&format!("{} alert", config.source_name), &message);
+                }
+            });
+            delivered.await.ok();
+        }
+    }
+}
+
+fn write_system_log(source_name: &str, message: &str) {
+    let result = if cfg!(target_os = "windows") {
+        Command::new("eventcreate")
+            .args(["/L", "APPLICATION", "/T", "ERROR", "/ID", "1000"])
+            .args(["/SO", source_name, "/D", message])
+            .output()
+    } else if let Ok(logger_path) = which("logger") {
+        Command::new(logger_path)
+            .args(["-p", "auth.crit", "-t", source_name, "--", message])
+            .output()
+    } else {
+        debug!("logger command not found, skipping syslog write");
+        return;
+    };
+
+    match result {
+        Ok(output) if output.status.success() => info!("Wrote local alert to system log"),
+        Ok(output) => warn!(
+            "System log write exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => warn!("Failed to write local alert to system log: {}", e),
+    }
+}
+
+// Shows a message to the logged-in users: `msg` on Windows, which also reaches their
+// desktops from the service session, notify-send elsewhere
+pub(crate) fn desktop_notification(title: &str, message: &str) {
+    let result = if cfg!(target_os = "windows") {
+        Command::new("msg").args(["*", "/TIME:60", message]).output()
+    } else if let Ok(notify_path) = which("notify-send") {
+        Command::new(notify_path).args(["-u", "critical", title, message]).output()
+    } else {
+        debug!("notify-send command not found, skipping user notification");
+        return;
+    };
+
+    if let Err(e) = result {
+        warn!("Failed to show local alert notification: {}", e);
+    }
+}
+
+impl Default for LocalAlertHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,8 @@
+mod local;
+mod notification;
+mod router;
+mod smtp;
+
+pub use local::{AlertingConfig, LocalAlertConfig, LocalAlertHandler};
+pub use notification::{EmailConfig, NotificationConfig, Notifier, SmtpSecurity, WebhookConfig, WebhookFormat};
+pub use router::AlertRouter;
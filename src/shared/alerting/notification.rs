@@ -0,0 +1,377 @@
+use crate::shared::alerting::local::desktop_notification;
+use crate::shared::alerting::smtp;
+use crate::shared::metrics::AgentMetrics;
+use crate::shared::pseudonymizer::{PseudonymKind, Pseudonymizer};
+use crate::shared::traits::{Event, Identifiable, Severity};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+// Notifications waiting for the sender task; more are dropped rather than holding up
+// collection when a channel is slow
+const MAX_QUEUED: usize = 1000;
+const SEND_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    Slack,
+    Teams,
+    // The notification fields as a flat JSON object
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub format: WebhookFormat,
+    // Overrides notifications.min_severity for this webhook
+    pub min_severity: Option<Severity>,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            format: WebhookFormat::Json,
+            min_severity: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    None,
+    Starttls,
+    // Implicit TLS from the first byte, usually port 465
+    Tls,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmailConfig {
+    pub enabled: bool,
+    pub server: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub accept_invalid_certs: bool,
+    // Name given in EHLO
+    pub hello_name: String,
+    // Authenticates with AUTH PLAIN when set
+    pub username: Option<String>,
+    // Environment variable holding the password, kept out of the configuration file
+    pub password_env: String,
+    pub from: String,
+    pub to: Vec<String>,
+    // Overrides notifications.min_severity for email
+    pub min_severity: Option<Severity>,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            server: String::from("localhost"),
+            port: 587,
+            security: SmtpSecurity::Starttls,
+            accept_invalid_certs: false,
+            hello_name: String::from("lsedr"),
+            username: None,
+            password_env: String::from("LSEDR_SMTP_PASSWORD"),
+            from: String::from("lsedr@localhost"),
+            to: Vec::new(),
+            min_severity: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub enabled: bool,
+    pub min_severity: Severity,
+    // Notifications sent per minute across all channels; the rest are dropped and logged
+    pub max_per_minute: u32,
+    // The same kind of event from the same host is notified once within this many seconds
+    pub dedup_secs: u64,
+    // Desktop notification on the host itself
+    pub desktop: bool,
+    pub webhooks: Vec<WebhookConfig>,
+    pub email: EmailConfig,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_severity: Severity::High,
+            max_per_minute: 10,
+            dedup_secs: 300,
+            desktop: false,
+            webhooks: Vec::new(),
+            email: EmailConfig::default(),
+        }
+    }
+}
+
+impl NotificationConfig {
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.enabled {
+            return problems;
+        }
+        if self.max_per_minute == 0 {
+            problems.push(String::from("alerting.notifications.max_per_minute must not be 0"));
+        }
+        for webhook in &self.webhooks {
+            match url::Url::parse(&webhook.url) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => {}
+                _ => problems.push(format!("alerting.notifications.webhooks url '{}' is not an http:// or https:// URL", webhook.url)),
+            }
+        }
+        let email = &self.email;
+        if email.enabled {
+            if email.server.trim().is_empty() {
+                problems.push(String::from("alerting.notifications.email.server is empty"));
+            }
+            if email.port == 0 {
+                problems.push(String::from("alerting.notifications.email.port must not be 0"));
+            }
+            if email.from.trim().is_empty() {
+                problems.push(String::from("alerting.notifications.email.from is empty"));
+            }
+            if email.to.is_empty() {
+                problems.push(String::from("alerting.notifications.email.to is empty"));
+            }
+        }
+        problems
+    }
+}
+
+// What every channel is told about one event
+#[derive(Debug, Clone, Serialize)]
+struct Notification {
+    severity: Severity,
+    event_type: String,
+    category: String,
+    source: String,
+    timestamp: String,
+    id: String,
+}
+
+impl Notification {
+    fn title(&self) -> String {
+        format!("[{:?}] {} on {}", self.severity, self.event_type, self.source)
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "{} ({}) on {} at {}, event id {}",
+            self.event_type, self.category, self.source, self.timestamp, self.id
+        )
+    }
+
+    fn webhook_body(&self, format: WebhookFormat) -> Value {
+        match format {
+            WebhookFormat::Slack => json!({ "text": format!("*{}*\n{}", self.title(), self.text()) }),
+            WebhookFormat::Teams => json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "summary": self.title(),
+                "title": self.title(),
+                "text": self.text(),
+                "themeColor": match self.severity {
+                    Severity::Critical => "8B0000",
+                    Severity::High => "D83B01",
+                    Severity::Medium => "FFB900",
+                    Severity::Low => "0078D7",
+                },
+            }),
+            WebhookFormat::Json => json!(self),
+        }
+    }
+}
+
+// At most max_per_minute sends a minute; the ones over it are counted and reported when
+// the next minute starts
+pub(crate) struct RateLimit {
+    max_per_minute: u32,
+    window_started: Instant,
+    window_count: u32,
+    dropped: u32,
+}
+
+impl RateLimit {
+    pub(crate) fn new(max_per_minute: u32) -> Self {
+        Self {
+            max_per_minute,
+            window_started: Instant::now(),
+            window_count: 0,
+            dropped: 0,
+        }
+    }
+
+    pub(crate) fn admit(&mut self, what: &str) -> bool {
+        if self.window_started.elapsed() >= Duration::from_secs(60) {
+            if self.dropped > 0 {
+                warn!("Dropped {} {} over the limit of {} per minute", self.dropped, what, self.max_per_minute);
+            }
+            self.window_started = Instant::now();
+            self.window_count = 0;
+            self.dropped = 0;
+        }
+        if self.window_count >= self.max_per_minute {
+            self.dropped += 1;
+            return false;
+        }
+        self.window_count += 1;
+        true
+    }
+}
+
+// Dedup and rate limit state, kept where events are handed over so dropped notifications
+// never reach the queue
+struct Throttle {
+    sent: HashMap<String, Instant>,
+    rate: RateLimit,
+}
+
+// Sends High and Critical events to webhooks, email and the desktop, from a task of its own
+// so a slow channel never holds up collection
+pub struct Notifier {
+    config: NotificationConfig,
+    queue: Sender<Notification>,
+    throttle: std::sync::Mutex<Throttle>,
+}
+
+impl Notifier {
+    // Must be called within the Tokio runtime, which runs the sender task
+    pub fn start(config: NotificationConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let (queue, pending) = channel(MAX_QUEUED);
+        let client = match reqwest::Client::builder().timeout(Duration::from_secs(SEND_TIMEOUT_SECS)).build() {
+            Ok(client) => client,
+            Err(e) => {
+                warn!("Failed to build webhook client, notifications disabled: {}", e);
+                return None;
+            }
+        };
+        tokio::spawn(Self::deliver(config.clone(), client, pending));
+        info!(
+            "Notifying {} webhooks{}{} of {:?} and worse events",
+            config.webhooks.len(),
+            if config.email.enabled { ", email" } else { "" },
+            if config.desktop { ", desktop" } else { "" },
+            config.min_severity
+        );
+        Some(Self {
+            throttle: std::sync::Mutex::new(Throttle {
+                sent: HashMap::new(),
+                rate: RateLimit::new(config.max_per_minute),
+            }),
+            config,
+            queue,
+        })
+    }
+
+    // The lowest severity any channel takes
+    fn floor(&self) -> Severity {
+        self.config
+            .webhooks
+            .iter()
+            .filter_map(|webhook| webhook.min_severity)
+            .chain(self.config.email.enabled.then_some(self.config.email.min_severity).flatten())
+            .chain(std::iter::once(self.config.min_severity))
+            .min()
+            .unwrap_or(self.config.min_severity)
+    }
+
+    fn admit(&self, key: String) -> bool {
+        let Ok(mut throttle) = self.throttle.lock() else {
+            return false;
+        };
+        let dedup = Duration::from_secs(self.config.dedup_secs);
+        throttle.sent.retain(|_, sent| sent.elapsed() < dedup);
+        if throttle.sent.contains_key(&key) {
+            debug!("Skipping repeated notification for {}", key);
+            return false;
+        }
+        if !throttle.rate.admit("notifications") {
+            return false;
+        }
+        throttle.sent.insert(key, Instant::now());
+        true
+    }
+
+    pub fn notify<E: Event + Identifiable>(&self, event: &E) -> bool {
+        if event.severity() < self.floor() {
+            return false;
+        }
+        let key = format!("{}|{}|{}", event.event_type(), event.category(), event.source());
+        if !self.admit(key) {
+            return false;
+        }
+        let notification = Notification {
+            severity: event.severity(),
+            event_type: event.event_type().to_string(),
+            category: event.category().to_string(),
+            // Leaves the agent like a stored document, so it carries the host pseudonym
+            source: Pseudonymizer::global().pseudonymize(PseudonymKind::Host, event.source()),
+            timestamp: event.timestamp().to_rfc3339(),
+            id: event.id().to_string(),
+        };
+        if self.queue.try_send(notification).is_err() {
+            warn!("Notification queue full, dropping notification for event {}", event.id());
+            return false;
+        }
+        AgentMetrics::global().set_queue_depth("notifications", (MAX_QUEUED - self.queue.capacity()) as u64);
+        true
+    }
+
+    async fn deliver(config: NotificationConfig, client: reqwest::Client, mut pending: Receiver<Notification>) {
+        let password = std::env::var(&config.email.password_env).ok();
+        while let Some(notification) = pending.recv().await {
+            AgentMetrics::global().set_queue_depth("notifications", pending.len() as u64);
+            let takes = |min_severity: Option<Severity>| notification.severity >= min_severity.unwrap_or(config.min_severity);
+
+            for webhook in config.webhooks.iter().filter(|webhook| takes(webhook.min_severity)) {
+                let result = client
+                    .post(&webhook.url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(notification.webhook_body(webhook.format).to_string())
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                if let Err(e) = result {
+                    warn!("Failed to send {:?} webhook notification: {}", webhook.format, e);
+                }
+            }
+
+            if config.email.enabled && !config.email.to.is_empty() && takes(config.email.min_severity) {
+                let sent = tokio::time::timeout(
+                    Duration::from_secs(SEND_TIMEOUT_SECS),
+                    smtp::send(&config.email, password.as_deref(), &notification.title(), &notification.text()),
+                )
+                .await;
+                match sent {
+                    Ok(Ok(())) => debug!("Emailed notification for event {}", notification.id),
+                    Ok(Err(e)) => warn!("Failed to email notification: {}", e),
+                    Err(_) => warn!("Timed out emailing notification through {}", config.email.server),
+                }
+            }
+
+            if config.desktop && takes(None) {
+                let (title, text) = (notification.title(), notification.text());
+                // Runs a helper program, kept off the runtime's worker threads
+                tokio::task::spawn_blocking(move || desktop_notification(&title, &text)).await.ok();
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::shared::alerting::local::{AlertingConfig, LocalAlertHandler};
+use crate::shared::alerting::notification::Notifier;
+use crate::shared::traits::{Event, Identifiable};
+
+// Hands every escalated event to the local tier and, at notifications.min_severity and
+// above, to the webhook, email and desktop channels
+pub struct AlertRouter {
+    local: LocalAlertHandler,
+    notifier: Option<Notifier>,
+}
+
+impl AlertRouter {
+    // Must be called within the Tokio runtime, which sends the notifications
+    pub fn new() -> Self {
+        let config = AlertingConfig::load();
+        Self::with_config(config)
+    }
+
+    pub fn with_config(config: AlertingConfig) -> Self {
+        Self {
+            local: LocalAlertHandler::with_config(config.local, config.notifications.max_per_minute),
+            notifier: Notifier::start(config.notifications),
+        }
+    }
+
+    pub fn handle<E: Event + Identifiable>(&self, event: &E) -> bool {
+        let local = self.local.handle(event);
+        let notified = self.notifier.as_ref().is_some_and(|notifier| notifier.notify(event));
+        local || notified
+    }
+
+    pub fn handle_all<E: Event + Identifiable>(&self, events: &[E]) -> usize {
+        events.iter().filter(|event| self.handle(*event)).count()
+    }
+}
+
+impl Default for AlertRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
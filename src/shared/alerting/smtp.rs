@@ -0,0 +1,147 @@
+use crate::shared::alerting::notification::{EmailConfig, SmtpSecurity};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use std::io;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+// Just enough SMTP to hand one plain-text message to a relay: EHLO, optional STARTTLS or
+// implicit TLS, AUTH PLAIN, one sender and a list of recipients
+struct Session {
+    stream: BufStream<Box<dyn Stream>>,
+}
+
+impl Session {
+    // Code and text of the next reply, joining the lines of a multi-line one
+    async fn reply(&mut self) -> io::Result<(u16, String)> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "SMTP server closed the connection"));
+            }
+            let line = line.trim_end();
+            let code = line
+                .get(..3)
+                .and_then(|code| code.parse::<u16>().ok())
+                .ok_or_else(|| io::Error::other(format!("Malformed SMTP reply: {}", line)))?;
+            text.push_str(line.get(4..).unwrap_or(""));
+            text.push('\n');
+            // "250-" continues the reply, "250 " ends it
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok((code, text));
+            }
+        }
+    }
+
+    async fn expect(&mut self, expected: u16) -> io::Result<String> {
+        let (code, text) = self.reply().await?;
+        if code != expected {
+            return Err(io::Error::other(format!("SMTP server replied {}: {}", code, text.trim_end())));
+        }
+        Ok(text)
+    }
+
+    async fn command(&mut self, line: &str, expected: u16) -> io::Result<String> {
+        self.stream.write_all(format!("{}\r\n", line).as_bytes()).await?;
+        self.stream.flush().await?;
+        self.expect(expected).await
+    }
+
+    fn into_inner(self) -> Box<dyn Stream> {
+        self.stream.into_inner()
+    }
+}
+
+async fn tls(config: &EmailConfig, stream: Box<dyn Stream>) -> io::Result<Box<dyn Stream>> {
+    let mut builder = native_tls::TlsConnector::builder();
+    if config.accept_invalid_certs {
+        builder.danger_accept_invalid_certs(true);
+    }
+    let connector = builder.build().map_err(io::Error::other)?;
+    let stream = tokio_native_tls::TlsConnector::from(connector)
+        .connect(&config.server, stream)
+        .await
+        .map_err(io::Error::other)?;
+    Ok(Box::new(stream))
+}
+
+// RFC 2047 encoded word for header values that are not plain ASCII
+fn header_value(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", STANDARD.encode(value))
+    }
+}
+
+fn message(config: &EmailConfig, subject: &str, body: &str) -> String {
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        config.from,
+        config.to.join(", "),
+        header_value(subject),
+        Utc::now().to_rfc2822(),
+    );
+    for line in body.lines() {
+        // Dot-stuffing, so a line of "." cannot end the message early
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message.push_str(".\r\n");
+    message
+}
+
+pub async fn send(config: &EmailConfig, password: Option<&str>, subject: &str, body: &str) -> io::Result<()> {
+    let tcp: Box<dyn Stream> = Box::new(TcpStream::connect((config.server.as_str(), config.port)).await?);
+    let stream = match config.security {
+        SmtpSecurity::Tls => tls(config, tcp).await?,
+        SmtpSecurity::None | SmtpSecurity::Starttls => tcp,
+    };
+    let mut session = Session { stream: BufStream::new(stream) };
+    session.expect(220).await?;
+    let hello = format!("EHLO {}", config.hello_name);
+    let mut extensions = session.command(&hello, 250).await?;
+
+    if config.security == SmtpSecurity::Starttls {
+        session.command("STARTTLS", 220).await?;
+        session = Session {
+            stream: BufStream::new(tls(config, session.into_inner()).await?),
+        };
+        extensions = session.command(&hello, 250).await?;
+    }
+
+    if let Some(username) = &config.username {
+        if !extensions.lines().any(|line| line.to_ascii_uppercase().starts_with("AUTH")) {
+            return Err(io::Error::other("SMTP server does not offer authentication"));
+        }
+        let credentials = STANDARD.encode(format!("\0{}\0{}", username, password.unwrap_or_default()));
+        session.command(&format!("AUTH PLAIN {}", credentials), 235).await?;
+    }
+
+    session.command(&format!("MAIL FROM:<{}>", config.from), 250).await?;
+    for recipient in &config.to {
+        let (code, text) = {
+            session.stream.write_all(format!("RCPT TO:<{}>\r\n", recipient).as_bytes()).await?;
+            session.stream.flush().await?;
+            session.reply().await?
+        };
+        // 251: not local, will forward
+        if code != 250 && code != 251 {
+            return Err(io::Error::other(format!("SMTP server refused {}: {} {}", recipient, code, text.trim_end())));
+        }
+    }
+    session.command("DATA", 354).await?;
+    session.stream.write_all(message(config, subject, body).as_bytes()).await?;
+    session.stream.flush().await?;
+    session.expect(250).await?;
+    // The message is accepted; a failed goodbye changes nothing
+    session.command("QUIT", 221).await.ok();
+    Ok(())
+}
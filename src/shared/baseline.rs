@@ -0,0 +1,170 @@
+use crate::shared::traits::Severity;
+use crate::utils::load_config_section;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+// Records when this host first ran the agent; removing the directory starts learning over
+const FIRST_RUN_FILE: &str = "first_run.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BaselineConfig {
+    pub enabled: bool,
+    // First-run marker and the collector state kept across restarts
+    pub directory: String,
+    // How long after the first run the initial state is recorded without alerting
+    pub learning_minutes: u64,
+    // Alerts at or above this severity are still raised while learning
+    pub min_alert_severity: Severity,
+}
+
+impl Default for BaselineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: String::from("data/baseline"),
+            learning_minutes: 60,
+            min_alert_severity: Severity::Critical,
+        }
+    }
+}
+
+impl BaselineConfig {
+    pub fn load() -> Self {
+        load_config_section("baseline")
+    }
+
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.enabled && self.directory.trim().is_empty() {
+            problems.push(String::from("baseline.directory is empty"));
+        }
+        problems
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FirstRun {
+    started_at: DateTime<Utc>,
+}
+
+// The learning phase after the agent first runs on a host, when everything already installed
+// shows up as new, and the collector state that keeps it from showing up again after a restart
+pub struct Baseline {
+    config: BaselineConfig,
+    learning_until: Option<DateTime<Utc>>,
+}
+
+impl Baseline {
+    pub fn new() -> Self {
+        Self::with_config(BaselineConfig::load())
+    }
+
+    pub fn with_config(config: BaselineConfig) -> Self {
+        let learning_until = if config.enabled {
+            let started_at = Self::first_run(&config);
+            let until = started_at + Duration::minutes(config.learning_minutes as i64);
+            if until > Utc::now() {
+                info!("Learning the host baseline until {}, alerts are held back", until.to_rfc3339());
+            }
+            Some(until)
+        } else {
+            None
+        };
+        Self { config, learning_until }
+    }
+
+    pub fn global() -> Arc<Baseline> {
+        static GLOBAL: OnceLock<Arc<Baseline>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(Baseline::new())).clone()
+    }
+
+    fn first_run(config: &BaselineConfig) -> DateTime<Utc> {
+        let path = PathBuf::from(&config.directory).join(FIRST_RUN_FILE);
+        if let Some(first_run) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<FirstRun>(&content).ok())
+        {
+            return first_run.started_at;
+        }
+
+        let first_run = FirstRun { started_at: Utc::now() };
+        let written = fs::create_dir_all(&config.directory)
+            .and_then(|_| fs::write(&path, json!(first_run).to_string()));
+        if let Err(e) = written {
+            warn!("Failed to record the first run in {}: {}", path.display(), e);
+        }
+        first_run.started_at
+    }
+
+    pub fn learning(&self) -> bool {
+        self.learning_until.is_some_and(|until| Utc::now() < until)
+    }
+
+    // Whether an alert or escalation of this severity goes out right now
+    pub fn raises(&self, severity: Severity) -> bool {
+        severity >= self.config.min_alert_severity || !self.learning()
+    }
+
+    // Tags documents recorded while learning, so the initial inventory can be told apart
+    // from later changes
+    pub fn annotate(&self, document: &mut Value) {
+        if !self.learning() {
+            return;
+        }
+        if let Value::Object(map) = document {
+            map.insert(String::from("baseline"), json!(true));
+        }
+    }
+
+    fn state_path(&self, name: &str) -> PathBuf {
+        PathBuf::from(&self.config.directory).join(format!("{}.json", name))
+    }
+
+    // Collector state saved by `save`; None on the first run or when the baseline is disabled
+    pub fn load<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        if !self.config.enabled {
+            return None;
+        }
+        let path = self.state_path(name);
+        let content = fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(state) => Some(state),
+            Err(e) => {
+                warn!("Ignoring unreadable baseline {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn save<T: Serialize>(&self, name: &str, state: &T) {
+        if !self.config.enabled {
+            return;
+        }
+        let path = self.state_path(name);
+        let written = serde_json::to_string(state)
+            .map_err(std::io::Error::other)
+            .and_then(|content| {
+                fs::create_dir_all(&self.config.directory)?;
+                // Written aside and renamed, so a crash never leaves half a baseline
+                let partial = path.with_extension("json.tmp");
+                fs::write(&partial, content)?;
+                fs::rename(&partial, &path)
+            });
+        if let Err(e) = written {
+            warn!("Failed to save baseline {}: {}", path.display(), e);
+        }
+    }
+}
+
+impl Default for Baseline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
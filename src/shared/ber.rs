@@ -0,0 +1,120 @@
+// BER / DER as SNMP and X.509 use it: single-byte tags and definite lengths
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    // Offset of the next element in the data
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    // Tag of the next element, for optional ones
+    pub fn peek(&self) -> Option<u8> {
+        self.data.get(self.position).copied()
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.data.get(self.position).ok_or("truncated message")?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    // Tag and contents of the next element
+    pub fn element(&mut self) -> Result<(u8, &'a [u8]), String> {
+        let tag = self.byte()?;
+        let first = self.byte()?;
+        let length = if first & 0x80 == 0 {
+            first as usize
+        } else {
+            let count = (first & 0x7F) as usize;
+            if count == 0 || count > 4 {
+                return Err(String::from("unsupported length encoding"));
+            }
+            let mut length = 0usize;
+            for _ in 0..count {
+                length = (length << 8) | self.byte()? as usize;
+            }
+            length
+        };
+        let end = self.position.checked_add(length).filter(|end| *end <= self.data.len()).ok_or("truncated message")?;
+        let contents = &self.data[self.position..end];
+        self.position = end;
+        Ok((tag, contents))
+    }
+
+    pub fn expect(&mut self, expected: u8, what: &str) -> Result<&'a [u8], String> {
+        let (tag, contents) = self.element()?;
+        if tag != expected {
+            return Err(format!("expected {} (tag {:#04x}), found tag {:#04x}", what, expected, tag));
+        }
+        Ok(contents)
+    }
+
+    pub fn integer(&mut self, what: &str) -> Result<i64, String> {
+        integer(self.expect(0x02, what)?)
+    }
+}
+
+pub fn integer(contents: &[u8]) -> Result<i64, String> {
+    if contents.is_empty() || contents.len() > 8 {
+        return Err(String::from("invalid integer"));
+    }
+    let negative = contents[0] & 0x80 != 0;
+    let mut value: i64 = if negative { -1 } else { 0 };
+    for byte in contents {
+        value = (value << 8) | *byte as i64;
+    }
+    Ok(value)
+}
+
+// Counters, gauges and time ticks; a leading zero byte keeps the top bit of Counter64 clear
+pub fn unsigned(contents: &[u8]) -> Result<u64, String> {
+    let contents = match contents {
+        [0, rest @ ..] if !rest.is_empty() => rest,
+        _ => contents,
+    };
+    if contents.is_empty() || contents.len() > 8 {
+        return Err(String::from("invalid unsigned integer"));
+    }
+    Ok(contents.iter().fold(0u64, |value, byte| (value << 8) | *byte as u64))
+}
+
+pub fn object_identifier(contents: &[u8]) -> Result<String, String> {
+    let mut identifiers = Vec::new();
+    let mut value: u64 = 0;
+    for (index, byte) in contents.iter().enumerate() {
+        if value > u64::MAX >> 7 {
+            return Err(String::from("object identifier component too large"));
+        }
+        value = (value << 7) | (byte & 0x7F) as u64;
+        if byte & 0x80 != 0 {
+            if index == contents.len() - 1 {
+                return Err(String::from("truncated object identifier"));
+            }
+            continue;
+        }
+        if identifiers.is_empty() {
+            // The first component packs the first two arcs
+            let first = (value / 40).min(2);
+            identifiers.push(first);
+            identifiers.push(value - first * 40);
+        } else {
+            identifiers.push(value);
+        }
+        value = 0;
+    }
+    if identifiers.is_empty() {
+        return Err(String::from("empty object identifier"));
+    }
+    Ok(identifiers.iter().map(u64::to_string).collect::<Vec<_>>().join("."))
+}
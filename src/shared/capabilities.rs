@@ -0,0 +1,385 @@
+use crate::features::exec_trace::ExecTraceMode;
+use crate::shared::config::AgentConfig;
+use crate::utils::load_config_section;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::sync::{Arc, OnceLock, RwLock};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CapabilitiesConfig {
+    // Refuse to start without administrator rights on Windows or root elsewhere, instead of
+    // collecting partial data
+    pub require_elevation: bool,
+}
+
+impl CapabilitiesConfig {
+    pub fn load() -> Self {
+        load_config_section("capabilities")
+    }
+}
+
+// One thing the agent needs from the host, as found at startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capability {
+    pub name: String,
+    pub available: bool,
+    // What was found, or what is missing
+    pub detail: String,
+}
+
+impl Capability {
+    fn new(name: &str, available: bool, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            available,
+            detail: detail.into(),
+        }
+    }
+}
+
+// A collector running with less than it was configured for, and why
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Degradation {
+    // Interval key of the collector
+    pub collector: String,
+    pub reason: String,
+}
+
+impl Degradation {
+    fn new(collector: &str, reason: impl Into<String>) -> Self {
+        Self {
+            collector: collector.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for Degradation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.collector, self.reason)
+    }
+}
+
+// What this host lets the agent do, probed once at startup. Collectors whose requirements are
+// missing run in a degraded mode instead of failing, and the control channel reports the set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub probed_at: DateTime<Utc>,
+    pub capabilities: Vec<Capability>,
+}
+
+impl Capabilities {
+    pub fn probe() -> Self {
+        let capabilities = probe_host();
+        for capability in &capabilities {
+            info!(
+                "Capability {}: {} ({})",
+                capability.name,
+                if capability.available { "available" } else { "missing" },
+                capability.detail
+            );
+        }
+        Self {
+            probed_at: Utc::now(),
+            capabilities,
+        }
+    }
+
+    pub fn global() -> Arc<Capabilities> {
+        static GLOBAL: OnceLock<Arc<Capabilities>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(Capabilities::probe())).clone()
+    }
+
+    // Capabilities not probed on this OS count as missing
+    pub fn available(&self, name: &str) -> bool {
+        self.capabilities
+            .iter()
+            .any(|capability| capability.name == name && capability.available)
+    }
+
+    // Administrator rights on Windows, root elsewhere
+    pub fn elevated(&self) -> bool {
+        self.available(if cfg!(windows) { "administrator" } else { "root" })
+    }
+
+    // The configuration with every setting this host cannot honour turned down to what it
+    // can, and every collector that runs with less than configured, for the log, the health
+    // check, the control channel and the documents those collectors store
+    pub fn degrade(&self, config: &AgentConfig) -> (AgentConfig, Vec<Degradation>) {
+        let mut config = config.clone();
+        let mut fallbacks = Vec::new();
+
+        // ETW on Windows, fanotify on Linux; neither exists elsewhere
+        let file_attribution = if cfg!(windows) { "etw" } else { "fanotify" };
+        if config.filesystem.settings.process_attribution && !self.available(file_attribution) {
+            config.filesystem.settings.process_attribution = false;
+            fallbacks.push(Degradation::new(
+                "filesystem",
+                format!("file events are not attributed to processes ({} is missing)", file_attribution),
+            ));
+        }
+        if cfg!(windows) && config.registry.settings.process_attribution && !self.available("etw") {
+            config.registry.settings.process_attribution = false;
+            fallbacks.push(Degradation::new(
+                "registry",
+                "registry events are not attributed to processes (etw is missing)",
+            ));
+        }
+
+        if cfg!(target_os = "linux") && config.exec_trace.enabled {
+            let audit = self.available("audit");
+            let connector = self.available("proc_connector");
+            match config.exec_trace.mode {
+                ExecTraceMode::Audit if !audit && connector => {
+                    config.exec_trace.mode = ExecTraceMode::ProcConnector;
+                    fallbacks.push(Degradation::new("exec_trace", "exec tracing reads the process connector (audit is missing)"));
+                }
+                ExecTraceMode::ProcConnector if !connector && audit => {
+                    config.exec_trace.mode = ExecTraceMode::Audit;
+                    fallbacks.push(Degradation::new("exec_trace", "exec tracing reads audit records (proc_connector is missing)"));
+                }
+                _ if !audit && !connector => {
+                    config.exec_trace.enabled = false;
+                    fallbacks.push(Degradation::new("exec_trace", "exec tracing is off (audit and proc_connector are missing)"));
+                }
+                _ => {}
+            }
+        }
+
+        // Hook tracing is all the keylogging collector does on Windows
+        if cfg!(windows) && config.keylogging.enabled && !self.available("etw") {
+            config.keylogging.enabled = false;
+            fallbacks.push(Degradation::new("keylogging", "keylogging detection is off (etw is missing)"));
+        }
+
+        if !self.elevated() {
+            fallbacks.extend(unprivileged(&config));
+        }
+
+        for fallback in &fallbacks {
+            warn!("Degraded collection: {}", fallback);
+        }
+        (config, fallbacks)
+    }
+}
+
+// What the collectors this configuration runs leave out without administrator rights or root.
+// They still run, on what the operating system shows an unprivileged user.
+fn unprivileged(config: &AgentConfig) -> Vec<Degradation> {
+    let mut limits = if cfg!(windows) {
+        vec![
+            Degradation::new("process", "paths and command lines of protected and other users' processes are missing"),
+            Degradation::new("registry", "keys below HKLM\\SAM and HKLM\\SECURITY cannot be read"),
+            Degradation::new("filesystem", "files only administrators can read are not hashed or scanned"),
+        ]
+    } else {
+        vec![
+            Degradation::new("process", "paths, command lines and open files of other users' processes are missing"),
+            Degradation::new("network", "sockets of other users' processes are not attributed to them"),
+            Degradation::new("filesystem", "files only root can read are not hashed or scanned"),
+        ]
+    };
+    if config.scheduled_tasks.enabled {
+        limits.push(Degradation::new(
+            "scheduled_tasks",
+            if cfg!(windows) { "tasks of other users are hidden" } else { "crontabs of other users cannot be read" },
+        ));
+    }
+    if config.persistence.enabled {
+        limits.push(Degradation::new(
+            "persistence",
+            "startup folders and browser profiles of other users cannot be read",
+        ));
+    }
+    limits
+}
+
+// The collectors running degraded at the moment, kept for the documents they store. Set
+// each time the collectors start.
+pub struct DegradedModes {
+    current: RwLock<Vec<Degradation>>,
+}
+
+impl DegradedModes {
+    pub fn global() -> Arc<DegradedModes> {
+        static GLOBAL: OnceLock<Arc<DegradedModes>> = OnceLock::new();
+        GLOBAL
+            .get_or_init(|| {
+                Arc::new(DegradedModes {
+                    current: RwLock::new(Vec::new()),
+                })
+            })
+            .clone()
+    }
+
+    pub fn set(&self, degradations: &[Degradation]) {
+        if let Ok(mut current) = self.current.write() {
+            *current = degradations.to_vec();
+        }
+    }
+
+    // Why the collector runs degraded, empty when it does not
+    pub fn reasons(&self, collector: &str) -> Vec<String> {
+        self.current
+            .read()
+            .map(|current| {
+                current
+                    .iter()
+                    .filter(|degradation| degradation.collector == collector)
+                    .map(|degradation| degradation.reason.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Tags a document stored by a degraded collector, so partial data can be told apart from
+    // a quiet host
+    pub fn annotate(&self, index: &str, document: &mut Value) {
+        let Some(collector) = collector_of(index) else {
+            return;
+        };
+        let reasons = self.reasons(collector);
+        if reasons.is_empty() {
+            return;
+        }
+        if let Value::Object(map) = document {
+            map.insert(String::from("degraded"), json!(true));
+            map.insert(String::from("degraded_reasons"), json!(reasons));
+        }
+    }
+}
+
+// The collector whose documents go to `index`, for the collectors that can run degraded
+fn collector_of(index: &str) -> Option<&'static str> {
+    Some(match index {
+        "system_processes" | "process_events" => "process",
+        "system_connections" | "listening_port_events" | "process_network_usage" => "network",
+        "file_events" => "filesystem",
+        "registry_events" | "suspicious_registry_operations" => "registry",
+        "exec_events" => "exec_trace",
+        "scheduled_tasks" => "scheduled_tasks",
+        "keylogging_events" => "keylogging",
+        "persistence_events" => "persistence",
+        _ => return None,
+    })
+}
+
+#[cfg(windows)]
+fn probe_host() -> Vec<Capability> {
+    let elevated = elevated();
+    vec![
+        Capability::new(
+            "administrator",
+            elevated,
+            if elevated { "the agent runs elevated" } else { "the agent runs without an elevated token" },
+        ),
+        // Real-time trace sessions for the kernel and Win32k providers need an elevated token
+        Capability::new(
+            "etw",
+            elevated,
+            if elevated { "real-time trace sessions can be started" } else { "real-time trace sessions need administrator rights" },
+        ),
+    ]
+}
+
+#[cfg(windows)]
+fn elevated() -> bool {
+    use windows::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows::Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
+    use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    unsafe {
+        let mut token = HANDLE::default();
+        if !OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token).as_bool() {
+            return false;
+        }
+        let mut elevation = TOKEN_ELEVATION::default();
+        let mut returned = 0u32;
+        let queried = GetTokenInformation(
+            token,
+            TokenElevation,
+            Some(&mut elevation as *mut _ as *mut _),
+            std::mem::size_of::<TOKEN_ELEVATION>() as u32,
+            &mut returned,
+        )
+        .as_bool();
+        CloseHandle(token);
+        queried && elevation.TokenIsElevated != 0
+    }
+}
+
+// Bits of the effective capability set in /proc/self/status
+#[cfg(target_os = "linux")]
+const CAP_NET_ADMIN: u32 = 12;
+#[cfg(target_os = "linux")]
+const CAP_SYS_ADMIN: u32 = 21;
+#[cfg(target_os = "linux")]
+const CAP_AUDIT_READ: u32 = 37;
+
+// A root agent in a container may still lack capabilities, so they are read rather than
+// inferred from the uid
+#[cfg(target_os = "linux")]
+fn probe_host() -> Vec<Capability> {
+    use std::fs;
+
+    let effective = fs::read_to_string("/proc/self/status").ok().and_then(|status| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix("CapEff:"))
+            .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+    });
+    let has = |bit: u32| effective.is_some_and(|mask| mask & (1 << bit) != 0);
+    let root = unsafe { libc::geteuid() } == 0;
+    let read_limit = |name: &str| {
+        fs::read_to_string(format!("/proc/sys/fs/inotify/{}", name))
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+    let auditctl = which::which("auditctl").is_ok();
+
+    let mut capabilities = vec![
+        Capability::new("root", root, if root { "the agent runs as root" } else { "the agent runs as an unprivileged user" }),
+        Capability::new(
+            "fanotify",
+            has(CAP_SYS_ADMIN),
+            if has(CAP_SYS_ADMIN) { "CAP_SYS_ADMIN is effective" } else { "needs CAP_SYS_ADMIN" },
+        ),
+        Capability::new(
+            "proc_connector",
+            has(CAP_NET_ADMIN),
+            if has(CAP_NET_ADMIN) { "CAP_NET_ADMIN is effective" } else { "needs CAP_NET_ADMIN" },
+        ),
+        Capability::new(
+            "audit",
+            has(CAP_AUDIT_READ) && auditctl,
+            match (has(CAP_AUDIT_READ), auditctl) {
+                (true, true) => "CAP_AUDIT_READ is effective and auditctl is installed",
+                (true, false) => "auditctl is not installed",
+                (false, _) => "needs CAP_AUDIT_READ",
+            },
+        ),
+    ];
+    capabilities.push(match (read_limit("max_user_watches"), read_limit("max_user_instances")) {
+        (Some(watches), Some(instances)) => Capability::new(
+            "inotify",
+            watches > 0 && instances > 0,
+            format!("{} watches and {} instances per user", watches, instances),
+        ),
+        _ => Capability::new("inotify", false, "the inotify limits cannot be read"),
+    });
+    capabilities
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn probe_host() -> Vec<Capability> {
+    let root = unsafe { libc::geteuid() } == 0;
+    vec![Capability::new(
+        "root",
+        root,
+        if root { "the agent runs as root" } else { "the agent runs as an unprivileged user" },
+    )]
+}
@@ -0,0 +1,178 @@
+use log::warn;
+use std::ffi::OsStr;
+use std::io::{self, Read};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+// Output of an external command, decoded to UTF-8
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+// Records parsed from command or kernel output, with the lines that could not be used, so a
+// malformed line shows up in the log instead of silently dropping or corrupting a record
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedOutput<T> {
+    pub records: Vec<T>,
+    // 1-based line number and why the line was rejected
+    pub rejected: Vec<(usize, &'static str)>,
+}
+
+impl<T> ParsedOutput<T> {
+    pub fn new() -> Self {
+        Self {
+            records: Vec::new(),
+            rejected: Vec::new(),
+        }
+    }
+
+    pub fn reject(&mut self, line: usize, reason: &'static str) {
+        self.rejected.push((line, reason));
+    }
+
+    pub fn log_rejected(&self, source: &str) {
+        if let Some((line, reason)) = self.rejected.first() {
+            warn!(
+                "Skipped {} unusable lines of {} output, the first at line {}: {}",
+                self.rejected.len(),
+                source,
+                line,
+                reason
+            );
+        }
+    }
+}
+
+impl<T> Default for ParsedOutput<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A command whose output does not depend on the user's locale. On Unix the C locale
+// keeps tools such as systemctl and efibootmgr from translating their output while
+// UTF-8 keeps non-ASCII names intact; Windows tools cannot be switched, so their
+// output is decoded from the console code page instead.
+pub fn command<S: AsRef<OsStr>>(program: S) -> Command {
+    let mut command = Command::new(program);
+    if cfg!(unix) {
+        command.env("LC_ALL", "C.UTF-8").env("LANG", "C.UTF-8").env_remove("LANGUAGE");
+    }
+    command
+}
+
+pub fn run<S, I, A>(program: S, args: I) -> io::Result<CommandOutput>
+where
+    S: AsRef<OsStr>,
+    I: IntoIterator<Item = A>,
+    A: AsRef<OsStr>,
+{
+    let output = command(program).args(args).output()?;
+    Ok(CommandOutput {
+        success: output.status.success(),
+        stdout: decode(&output.stdout),
+        stderr: decode(&output.stderr),
+    })
+}
+
+// Like `run`, but the command is killed once it has run for `timeout`
+pub fn run_with_timeout<S, I, A>(program: S, args: I, timeout: Duration) -> io::Result<CommandOutput>
+where
+    S: AsRef<OsStr>,
+    I: IntoIterator<Item = A>,
+    A: AsRef<OsStr>,
+{
+    let mut child = command(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Read on their own threads so a chatty command cannot fill a pipe and stall
+    let read = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut bytes = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut bytes);
+            }
+            bytes
+        })
+    };
+    let stdout = read(child.stdout.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>));
+    let stderr = read(child.stderr.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>));
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("killed after {} seconds", timeout.as_secs()),
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    Ok(CommandOutput {
+        success: status.success(),
+        stdout: decode(&stdout.join().unwrap_or_default()),
+        stderr: decode(&stderr.join().unwrap_or_default()),
+    })
+}
+
+// Decodes tool output or a file written by one: UTF-16LE with a byte order mark,
+// UTF-8 (which includes plain ASCII), and otherwise the console code page
+pub fn decode(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => decode_code_page(bytes),
+    }
+}
+
+// Console tools write in the console output code page, which follows the system locale:
+// 932 on Japanese, 850 on German, 857 on Turkish, 936 on Simplified Chinese Windows
+#[cfg(windows)]
+pub fn console_code_page() -> u32 {
+    use windows::Win32::Globalization::GetOEMCP;
+    use windows::Win32::System::Console::GetConsoleOutputCP;
+
+    // Running as a service there is no console, and children fall back to the OEM code page
+    match unsafe { GetConsoleOutputCP() } {
+        0 => unsafe { GetOEMCP() },
+        code_page => code_page,
+    }
+}
+
+#[cfg(windows)]
+fn decode_code_page(bytes: &[u8]) -> String {
+    use windows::Win32::Globalization::{MultiByteToWideChar, MULTI_BYTE_TO_WIDE_CHAR_FLAGS};
+
+    let code_page = console_code_page();
+    let flags = MULTI_BYTE_TO_WIDE_CHAR_FLAGS(0);
+    let length = unsafe { MultiByteToWideChar(code_page, flags, bytes, None) };
+    if length <= 0 {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+    let mut wide = vec![0u16; length as usize];
+    let written = unsafe { MultiByteToWideChar(code_page, flags, bytes, Some(&mut wide)) };
+    wide.truncate(written.max(0) as usize);
+    String::from_utf16_lossy(&wide)
+}
+
+#[cfg(not(windows))]
+fn decode_code_page(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
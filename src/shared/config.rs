@@ -0,0 +1,634 @@
+use crate::features::boot::BootConfig;
+use crate::features::persistence::PersistenceConfig;
+use crate::features::log_tail::LogTailConfig;
+use crate::features::network::NetworkConfig;
+use crate::features::snmp_trap::SnmpTrapConfig;
+use crate::features::directory::DirectoryConfig;
+use crate::features::tls_probe::TlsProbeConfig;
+use crate::features::capture::CaptureConfig;
+use crate::features::control::ControlConfig;
+use crate::features::deception::DeceptionConfig;
+use crate::features::detection::{
+    parse_network, DetectionConfig, EgressConfig, LateralMovementConfig, OutboundVolumeConfig, DEFAULT_EGRESS_POLICY,
+};
+use crate::features::exec_trace::ExecTraceConfig;
+use crate::features::filesystem::FileSystemConfig;
+use crate::features::honeypot::HoneypotConfig;
+use crate::features::incident::IncidentConfig;
+use crate::features::ingest::IngestConfig;
+use crate::features::keylogging::KeyloggingConfig;
+use crate::features::osquery::OsqueryConfig;
+use crate::features::print_spooler::PrintSpoolerConfig;
+use crate::features::process::ProcessConfig;
+use crate::features::registry::RegistryConfig;
+use crate::features::response::ResponseConfig;
+use crate::features::scheduled_tasks::ScheduledTaskConfig;
+use crate::features::usb::UsbConfig;
+use crate::shared::error::ConfigError;
+use crate::shared::alerting::AlertingConfig;
+use crate::shared::baseline::BaselineConfig;
+use crate::shared::capabilities::CapabilitiesConfig;
+use crate::shared::enrichment::EnrichmentConfig;
+use crate::shared::host_identity::HostIdentityConfig;
+use crate::shared::instance::InstanceConfig;
+use crate::shared::intervals::IntervalConfig;
+use crate::shared::pipeline::{DedupConfig, PipelineConfig};
+use crate::shared::ioc::IocConfig;
+use crate::shared::maintenance::MaintenanceConfig;
+use crate::shared::metrics::MetricsConfig;
+use crate::shared::policy_profiles::{detect_role, HostRole};
+use crate::shared::pseudonymizer::PrivacyConfig;
+use crate::shared::remote_config::{remote_document, RemoteConfigSettings, RemoteDocument, REMOTE_CONFIG_KEY};
+use crate::shared::storage::{BackendKind, ElasticsearchConfig, IndexNaming, LoggingConfig, StorageConfig};
+use crate::shared::user_resolver::UserResolutionConfig;
+use crate::shared::vdi::{self, VdiConfig};
+use crate::utils::{install_config_document, MONITOR_CONFIG_PATH};
+use chrono::format::{Item, StrftimeItems};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+// Path of the configuration file, defaults to config/monitor.yaml
+pub const CONFIG_PATH_ENV: &str = "LSEDR_CONFIG";
+// LSEDR__<SECTION>__<KEY>=value overrides a setting, e.g. LSEDR__ELASTICSEARCH__HOST=es01
+pub const OVERRIDE_ENV_PREFIX: &str = "LSEDR__";
+// Top-level section holding per-OS settings (windows, linux, macos) merged over the rest
+pub const OS_OVERRIDES_KEY: &str = "os_overrides";
+// Top-level section holding per-role overlays that replace the built-in policy profiles
+pub const PROFILES_KEY: &str = "profiles";
+// Section whose `overlay` replaces the built-in settings of a VDI clone
+pub const VDI_KEY: &str = "vdi";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgentSettings {
+    // error / warn / info / debug / trace / off
+    pub log_level: String,
+    // Policy profile: auto / none / workstation / server / domain_controller / kiosk
+    pub profile: String,
+    // Apply edits of the configuration file to monitored paths, extensions and suspicious
+    // patterns without a restart
+    pub watch_config: bool,
+}
+
+impl Default for AgentSettings {
+    fn default() -> Self {
+        Self {
+            log_level: String::from("info"),
+            profile: String::from("auto"),
+            watch_config: true,
+        }
+    }
+}
+
+// Every runtime setting of the agent, read once at startup from a single YAML file with
+// environment overrides applied. A section of the wrong shape fails the load instead of
+// falling back to defaults. Process-wide services (host identity, privacy, baseline,
+// maintenance, ...) read their section through load_config_section, which only ever sees the
+// document validated here.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgentConfig {
+    pub agent: AgentSettings,
+    pub elasticsearch: ElasticsearchConfig,
+    pub storage: StorageConfig,
+    pub intervals: IntervalConfig,
+    // The filesystem monitor keeps its `paths` and `settings` at the top level
+    #[serde(flatten)]
+    pub filesystem: FileSystemConfig,
+    pub registry: RegistryConfig,
+    pub process: ProcessConfig,
+    // Listening port baseline of the network collector
+    pub network: NetworkConfig,
+    pub exec_trace: ExecTraceConfig,
+    pub ingest: IngestConfig,
+    pub scheduled_tasks: ScheduledTaskConfig,
+    pub osquery: OsqueryConfig,
+    pub honeypot: HoneypotConfig,
+    pub usb: UsbConfig,
+    pub deception: DeceptionConfig,
+    pub capture: CaptureConfig,
+    pub keylogging: KeyloggingConfig,
+    pub print_spooler: PrintSpoolerConfig,
+    pub boot: BootConfig,
+    pub persistence: PersistenceConfig,
+    pub log_tail: LogTailConfig,
+    // Traps from switches, UPSes and other devices nearby
+    pub snmp_trap: SnmpTrapConfig,
+    // Queries a domain controller runs against its own directory
+    pub directory: DirectoryConfig,
+    // Certificates of local and remote TLS endpoints
+    pub tls_probe: TlsProbeConfig,
+    pub detection: DetectionConfig,
+    // Allowed outbound destinations per policy profile
+    pub egress: EgressConfig,
+    // Scanning of the internal network from this host
+    pub lateral_movement: LateralMovementConfig,
+    // Unusual amounts of data sent to new external destinations
+    pub outbound_volume: OutboundVolumeConfig,
+    // Alerts grouped into incidents a responder can contain and resolve
+    pub incidents: IncidentConfig,
+    pub ioc: IocConfig,
+    // Repeated file, registry and service data dropped before storage
+    pub dedup: DedupConfig,
+    // Filter, dedup, enrich and transform steps per event category
+    pub pipeline: PipelineConfig,
+    // Prometheus endpoint for the agent's own health
+    pub metrics: MetricsConfig,
+    // gRPC management channel for the SpathaX console
+    pub control: ControlConfig,
+    // Configuration pulled from the management server, merged over this file
+    pub remote_config: RemoteConfigSettings,
+    // Lock keeping a second agent from running from the same directory
+    pub instance: InstanceConfig,
+    // What to do on a host that does not give the agent everything it needs
+    pub capabilities: CapabilitiesConfig,
+    // Non-persistent virtual desktop clones
+    pub vdi: VdiConfig,
+    // Name reported as the source of every document
+    pub host_identity: HostIdentityConfig,
+    // How account SIDs and uids are turned into names
+    pub user_resolution: UserResolutionConfig,
+    // Fields added to every document before it is stored
+    pub enrichment: EnrichmentConfig,
+    // Pseudonyms for user and host names leaving the agent
+    pub privacy: PrivacyConfig,
+    // Learning phase after the first run
+    pub baseline: BaselineConfig,
+    // Patch windows holding back alerts and collectors
+    pub maintenance: MaintenanceConfig,
+    // Local alerts and notification channels
+    pub alerting: AlertingConfig,
+    // Signed response actions run through the control channel
+    pub response: ResponseConfig,
+    pub logging: LoggingConfig,
+    // File the configuration was read from, None when running on the built-in profile
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+    // Policy profile merged into this configuration, None with `agent.profile: none`
+    #[serde(skip)]
+    pub role: Option<HostRole>,
+    // Whether this host runs as a VDI clone, with the vdi overlay merged in
+    #[serde(skip)]
+    pub vdi_clone: bool,
+}
+
+impl AgentConfig {
+    // Reads, overrides and validates the configuration, then installs it for
+    // load_config_section. A missing default file runs on the built-in profile; a file named
+    // through LSEDR_CONFIG must exist.
+    pub fn load() -> Result<Self, ConfigError> {
+        let (config, document) = Self::read(remote_document().as_ref())?;
+        install_config_document(document);
+        Ok(config)
+    }
+
+    // The configuration load would install, and the merged document behind it. A document
+    // pulled from the management server is merged over the file, before os_overrides,
+    // profiles and environment overrides, as long as the file still pulls from that server.
+    pub fn read(remote: Option<&RemoteDocument>) -> Result<(Self, Value), ConfigError> {
+        let explicit = std::env::var(CONFIG_PATH_ENV).ok().filter(|path| !path.is_empty());
+        let path = PathBuf::from(explicit.as_deref().unwrap_or(MONITOR_CONFIG_PATH));
+
+        let (mut document, source) = match fs::read_to_string(&path) {
+            Ok(content) => {
+                let document: Value = serde_yaml::from_str(&content)
+                    .map_err(|e| ConfigError::Parse(path.display().to_string(), e.to_string()))?;
+                (document, Some(path.clone()))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound && explicit.is_none() => {
+                (Value::Mapping(Mapping::new()), None)
+            }
+            Err(e) => return Err(ConfigError::Read(path.display().to_string(), e)),
+        };
+        if document.is_null() {
+            document = Value::Mapping(Mapping::new());
+        }
+        let mut local = document.clone();
+        apply_os_overrides(&mut local, std::env::consts::OS);
+        apply_env_overrides(&mut local, std::env::vars())?;
+        let remote_config = local.get(REMOTE_CONFIG_KEY).cloned();
+        let settings: RemoteConfigSettings = remote_config
+            .clone()
+            .and_then(|value| serde_yaml::from_value(value).ok())
+            .unwrap_or_default();
+        if let Some(remote) = remote.filter(|remote| settings.enabled && remote.url == settings.url) {
+            merge(&mut document, remote.document.clone());
+        }
+
+        apply_os_overrides(&mut document, std::env::consts::OS);
+        // The profile may itself be chosen through LSEDR__AGENT__PROFILE
+        let mut selection = document.clone();
+        apply_env_overrides(&mut selection, std::env::vars())?;
+        let role = apply_profile(&mut document, &selection, std::env::consts::OS)?;
+        let vdi_clone = apply_vdi(&mut document, &selection, std::env::consts::OS)?;
+        apply_env_overrides(&mut document, std::env::vars())?;
+        // Only the local file decides where configuration is pulled from
+        if let Value::Mapping(mapping) = &mut document {
+            let key = Value::String(String::from(REMOTE_CONFIG_KEY));
+            match remote_config {
+                Some(remote_config) => mapping.insert(key, remote_config),
+                None => mapping.remove(&key),
+            };
+        }
+
+        let mut config: AgentConfig = serde_yaml::from_value(document.clone())
+            .map_err(|e| ConfigError::Parse(path.display().to_string(), e.to_string()))?;
+        config.source = source;
+        config.role = role;
+        config.vdi_clone = vdi_clone;
+        config.validate()?;
+        Ok((config, document))
+    }
+
+    pub fn log_level(&self) -> LevelFilter {
+        LevelFilter::from_str(&self.agent.log_level).unwrap_or(LevelFilter::Info)
+    }
+
+    // Reports every problem at once so a broken deployment is fixed in one pass
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if LevelFilter::from_str(&self.agent.log_level).is_err() {
+            problems.push(format!("agent.log_level '{}' is not a log level", self.agent.log_level));
+        }
+
+        let failover = &self.storage.failover;
+        let uses = |kind: BackendKind| self.storage.backend == kind || (failover.enabled && failover.secondary == kind);
+        let mut check_elasticsearch = |prefix: &str, config: &ElasticsearchConfig| {
+            if config.cloud_id.is_none() {
+                if config.scheme != "http" && config.scheme != "https" {
+                    problems.push(format!("{}.scheme '{}' is not http or https", prefix, config.scheme));
+                }
+                if config.host.trim().is_empty() {
+                    problems.push(format!("{}.host is empty", prefix));
+                }
+                if config.port == 0 {
+                    problems.push(format!("{}.port must not be 0", prefix));
+                }
+            }
+            if config.username.is_some() != config.password.is_some() {
+                problems.push(format!("{0}.username and {0}.password must be set together", prefix));
+            }
+            let credentials = [config.username.is_some(), config.api_key.is_some(), config.bearer_token.is_some()];
+            if credentials.iter().filter(|set| **set).count() > 1 {
+                problems.push(format!("{}: set only one of username / password, api_key and bearer_token", prefix));
+            }
+            if let Some(path) = &config.tls.ca_certificate {
+                if !std::path::Path::new(path).is_file() {
+                    problems.push(format!("{}.tls.ca_certificate {} does not exist", prefix, path));
+                }
+            }
+            if config.retry.initial_backoff_ms > config.retry.max_backoff_ms {
+                problems.push(format!("{0}.retry.initial_backoff_ms is above {0}.retry.max_backoff_ms", prefix));
+            }
+            let naming = &config.index_naming;
+            if naming.prefix != naming.prefix.to_lowercase() || naming.prefix.starts_with(['_', '-', '+', '.']) {
+                problems.push(format!("{}.index_naming.prefix '{}' is not a valid index name prefix", prefix, naming.prefix));
+            }
+            if naming.scheme == IndexNaming::Dated {
+                // An unknown specifier would only fail when the first document is indexed
+                let invalid = StrftimeItems::new(&naming.date_format).any(|item| item == Item::Error);
+                if naming.date_format.trim().is_empty() || invalid {
+                    problems.push(format!("{}.index_naming.date_format '{}' is not a valid date format", prefix, naming.date_format));
+                }
+            }
+        };
+        if self.storage.backend == BackendKind::Elasticsearch {
+            check_elasticsearch("elasticsearch", &self.elasticsearch);
+        }
+        if failover.enabled && failover.secondary == BackendKind::Elasticsearch {
+            check_elasticsearch("storage.failover.elasticsearch", &failover.elasticsearch);
+        }
+
+        if uses(BackendKind::Syslog) {
+            if self.storage.syslog.host.trim().is_empty() {
+                problems.push(String::from("storage.syslog.host is empty"));
+            }
+            if self.storage.syslog.port == 0 {
+                problems.push(String::from("storage.syslog.port must not be 0"));
+            }
+            if self.storage.syslog.facility > 23 {
+                problems.push(format!("storage.syslog.facility {} is not between 0 and 23", self.storage.syslog.facility));
+            }
+        }
+        if uses(BackendKind::Otlp) {
+            let endpoint = &self.storage.otlp.endpoint;
+            match url::Url::parse(endpoint) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => {}
+                _ => problems.push(format!("storage.otlp.endpoint {} is not an http:// or https:// URL", endpoint)),
+            }
+            if self.storage.otlp.timeout_secs == 0 {
+                problems.push(String::from("storage.otlp.timeout_secs must not be 0"));
+            }
+        }
+        if uses(BackendKind::File) && self.storage.file.directory.trim().is_empty() {
+            problems.push(String::from("storage.file.directory is empty"));
+        }
+
+        if self.intervals.default_secs == 0 {
+            problems.push(String::from("intervals.default_secs must not be 0"));
+        }
+        for (collector, secs) in &self.intervals.collectors {
+            if *secs == 0 {
+                problems.push(format!("intervals.collectors.{} must not be 0", collector));
+            }
+        }
+
+        if self.metrics.enabled {
+            if self.metrics.listen.parse::<std::net::SocketAddr>().is_err() {
+                problems.push(format!("metrics.listen '{}' is not an address:port", self.metrics.listen));
+            }
+            if !self.metrics.path.starts_with('/') {
+                problems.push(format!("metrics.path '{}' does not start with /", self.metrics.path));
+            }
+        }
+
+        if self.control.enabled {
+            if self.control.listen.parse::<std::net::SocketAddr>().is_err() {
+                problems.push(format!("control.listen '{}' is not an address:port", self.control.listen));
+            }
+            if self.control.buffered_events == 0 {
+                problems.push(String::from("control.buffered_events must not be 0"));
+            }
+            if self.control.token.as_deref().is_some_and(|token| token.trim().is_empty()) {
+                problems.push(String::from("control.token is empty"));
+            }
+            let tls = &self.control.tls;
+            if tls.cert_file.is_some() != tls.key_file.is_some() {
+                problems.push(String::from("control.tls.cert_file and control.tls.key_file must be set together"));
+            }
+            if tls.client_ca_file.is_some() && tls.cert_file.is_none() {
+                problems.push(String::from("control.tls.client_ca_file needs control.tls.cert_file and key_file"));
+            }
+            for (name, path) in [("cert_file", &tls.cert_file), ("key_file", &tls.key_file), ("client_ca_file", &tls.client_ca_file)] {
+                if let Some(path) = path {
+                    if !std::path::Path::new(path).is_file() {
+                        problems.push(format!("control.tls.{} {} does not exist", name, path));
+                    }
+                }
+            }
+        }
+
+        if self.egress.enabled {
+            if self.egress.resolve_secs == 0 {
+                problems.push(String::from("egress.resolve_secs must not be 0"));
+            }
+            for (policy, rules) in &self.egress.policies {
+                if policy != DEFAULT_EGRESS_POLICY && HostRole::parse(policy).is_none() {
+                    problems.push(format!("egress.policies.{} is not a policy profile or {}", policy, DEFAULT_EGRESS_POLICY));
+                }
+                for cidr in rules.allowed_cidrs.iter().filter(|cidr| parse_network(cidr).is_none()) {
+                    problems.push(format!("egress.policies.{}.allowed_cidrs '{}' is not an address or CIDR block", policy, cidr));
+                }
+                for domain in &rules.allowed_domains {
+                    if domain.trim().is_empty() || domain.contains(['*', '/', ' ', ':']) {
+                        problems.push(format!("egress.policies.{}.allowed_domains '{}' is not a host name", policy, domain));
+                    }
+                }
+            }
+        }
+
+        let lateral = &self.lateral_movement;
+        if lateral.enabled {
+            if lateral.window_secs == 0 {
+                problems.push(String::from("lateral_movement.window_secs must not be 0"));
+            }
+            let thresholds = [
+                ("max_hosts", lateral.max_hosts),
+                ("max_ports", lateral.max_ports),
+                ("max_admin_hosts", lateral.max_admin_hosts),
+            ];
+            for (name, threshold) in thresholds {
+                if threshold < 2 {
+                    problems.push(format!("lateral_movement.{} must be at least 2", name));
+                }
+            }
+            for cidr in lateral.internal_cidrs.iter().filter(|cidr| parse_network(cidr).is_none()) {
+                problems.push(format!("lateral_movement.internal_cidrs '{}' is not an address or CIDR block", cidr));
+            }
+        }
+
+        let outbound = &self.outbound_volume;
+        if outbound.enabled {
+            if outbound.window_secs == 0 {
+                problems.push(String::from("outbound_volume.window_secs must not be 0"));
+            }
+            if outbound.factor.is_nan() || outbound.factor < 1.0 {
+                problems.push(format!("outbound_volume.factor {} must be at least 1", outbound.factor));
+            }
+            for cidr in outbound.internal_cidrs.iter().filter(|cidr| parse_network(cidr).is_none()) {
+                problems.push(format!("outbound_volume.internal_cidrs '{}' is not an address or CIDR block", cidr));
+            }
+        }
+
+        let incidents = &self.incidents;
+        if incidents.enabled {
+            if incidents.window_secs == 0 {
+                problems.push(String::from("incidents.window_secs must not be 0"));
+            }
+            if incidents.max_incidents == 0 {
+                problems.push(String::from("incidents.max_incidents must not be 0"));
+            }
+        }
+
+        if self.dedup.enabled && self.dedup.max_entries == 0 {
+            problems.push(String::from("dedup.max_entries must not be 0"));
+        }
+        problems.extend(self.pipeline.problems());
+        problems.extend(self.log_tail.problems());
+        problems.extend(self.network.listening_ports.problems());
+        problems.extend(self.network.process_usage.problems());
+        problems.extend(self.snmp_trap.problems());
+        problems.extend(self.directory.problems());
+        problems.extend(self.tls_probe.problems());
+        problems.extend(self.filesystem.yara.problems());
+        problems.extend(self.host_identity.problems());
+        problems.extend(self.enrichment.problems());
+        problems.extend(self.privacy.problems());
+        problems.extend(self.baseline.problems());
+        problems.extend(self.maintenance.problems());
+        problems.extend(self.alerting.notifications.problems());
+        problems.extend(self.response.problems());
+
+        if self.instance.enabled && self.instance.lock_file.trim().is_empty() {
+            problems.push(String::from("instance.lock_file is empty"));
+        }
+
+        if self.vdi.template.as_ref().is_some_and(|template| template.trim().is_empty()) {
+            problems.push(String::from("vdi.template is empty"));
+        }
+
+        if self.remote_config.enabled {
+            let remote = &self.remote_config;
+            match url::Url::parse(&remote.url) {
+                Ok(url) if url.scheme() == "https" && url.host_str().is_some() => {}
+                _ => problems.push(format!("remote_config.url '{}' is not an https:// URL", remote.url)),
+            }
+            if remote.interval_secs == 0 {
+                problems.push(String::from("remote_config.interval_secs must not be 0"));
+            }
+            if remote.timeout_secs == 0 {
+                problems.push(String::from("remote_config.timeout_secs must not be 0"));
+            }
+            if remote.signing_key_env.trim().is_empty() {
+                problems.push(String::from("remote_config.signing_key_env is empty"));
+            }
+            if remote.version_file.trim().is_empty() {
+                problems.push(String::from("remote_config.version_file is empty"));
+            }
+            if let Some(path) = &remote.ca_file {
+                if !std::path::Path::new(path).is_file() {
+                    problems.push(format!("remote_config.ca_file {} does not exist", path));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems.join("; ")))
+        }
+    }
+}
+
+// Merges `os_overrides.<os>` over the document, so one file can serve a mixed fleet.
+// Mappings merge key by key; lists and scalars, `paths` included, are replaced.
+fn apply_os_overrides(document: &mut Value, os: &str) {
+    let Value::Mapping(mapping) = document else {
+        return;
+    };
+    let Some(Value::Mapping(mut overrides)) = mapping.remove(OS_OVERRIDES_KEY) else {
+        return;
+    };
+    if let Some(overlay) = overrides.remove(os) {
+        merge(document, overlay);
+    }
+}
+
+// Merges the policy profile named by `agent.profile` in `selection` over the document, after
+// its own os_overrides. `auto` picks the role this host appears to have.
+fn apply_profile(document: &mut Value, selection: &Value, os: &str) -> Result<Option<HostRole>, ConfigError> {
+    let setting = selection
+        .get("agent")
+        .and_then(|agent| agent.get("profile"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| AgentSettings::default().profile);
+
+    let mut profiles = match document.as_mapping_mut().and_then(|mapping| mapping.remove(PROFILES_KEY)) {
+        Some(Value::Mapping(profiles)) => profiles,
+        _ => Mapping::new(),
+    };
+    let role = match setting.trim().to_lowercase().as_str() {
+        "none" | "" => return Ok(None),
+        "auto" => detect_role(),
+        name => HostRole::parse(name)
+            .ok_or_else(|| ConfigError::Invalid(format!("agent.profile '{}' is not a policy profile", setting)))?,
+    };
+
+    let mut overlay = match profiles.remove(role.name()) {
+        Some(overlay) => overlay,
+        None => serde_yaml::from_str(role.builtin_overlay())
+            .map_err(|e| ConfigError::Parse(format!("built-in {} profile", role), e.to_string()))?,
+    };
+    // `profiles.<role>: {}` or an empty entry turns the built-in overlay off
+    if overlay.is_null() {
+        return Ok(Some(role));
+    }
+    if !overlay.is_mapping() {
+        return Err(ConfigError::Invalid(format!("{}.{} is not a mapping", PROFILES_KEY, role)));
+    }
+    apply_os_overrides(&mut overlay, os);
+    merge(document, overlay);
+    Ok(Some(role))
+}
+
+// Merges the settings of a VDI clone over the document when `vdi` in `selection` finds this
+// host to be one, after its own os_overrides. `vdi.overlay` replaces the built-in settings.
+fn apply_vdi(document: &mut Value, selection: &Value, os: &str) -> Result<bool, ConfigError> {
+    let settings: VdiConfig = selection
+        .get(VDI_KEY)
+        .cloned()
+        .map(|value| serde_yaml::from_value(value).map_err(|e| ConfigError::Invalid(format!("{}: {}", VDI_KEY, e))))
+        .transpose()?
+        .unwrap_or_default();
+    let replacement = document
+        .get_mut(VDI_KEY)
+        .and_then(Value::as_mapping_mut)
+        .and_then(|vdi| vdi.remove("overlay"));
+    if !settings.active() {
+        return Ok(false);
+    }
+
+    let mut overlay = match replacement {
+        Some(overlay) => overlay,
+        None => serde_yaml::from_str(vdi::OVERLAY)
+            .map_err(|e| ConfigError::Parse(String::from("built-in vdi overlay"), e.to_string()))?,
+    };
+    // `vdi.overlay:` left empty keeps the configuration as it is
+    if overlay.is_null() {
+        return Ok(true);
+    }
+    if !overlay.is_mapping() {
+        return Err(ConfigError::Invalid(format!("{}.overlay is not a mapping", VDI_KEY)));
+    }
+    apply_os_overrides(&mut overlay, os);
+    merge(document, overlay);
+    Ok(true)
+}
+
+fn merge(target: &mut Value, overlay: Value) {
+    match (target, overlay) {
+        (Value::Mapping(target), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match target.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, overlay) => *target = overlay,
+    }
+}
+
+// Applies LSEDR__SECTION__KEY=value variables onto the document. Keys are matched in lower
+// case and values are read as YAML, so numbers, booleans and [a, b] lists keep their type.
+fn apply_env_overrides(
+    document: &mut Value,
+    variables: impl IntoIterator<Item = (String, String)>,
+) -> Result<(), ConfigError> {
+    for (name, value) in variables {
+        let Some(key) = name.strip_prefix(OVERRIDE_ENV_PREFIX) else {
+            continue;
+        };
+        let path: Vec<String> = key.split("__").map(str::to_lowercase).collect();
+        if path.iter().any(String::is_empty) {
+            return Err(ConfigError::Invalid(format!("malformed override variable {}", name)));
+        }
+
+        let value = match serde_yaml::from_str::<Value>(&value) {
+            Ok(parsed @ (Value::Bool(_) | Value::Number(_) | Value::Null | Value::Sequence(_))) => parsed,
+            _ => Value::String(value),
+        };
+
+        let mut target = &mut *document;
+        for segment in path {
+            if !target.is_mapping() {
+                *target = Value::Mapping(Mapping::new());
+            }
+            let Value::Mapping(mapping) = target else {
+                continue;
+            };
+            target = mapping.entry(Value::String(segment)).or_insert(Value::Null);
+        }
+        *target = value;
+    }
+    Ok(())
+}
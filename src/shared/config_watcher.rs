@@ -0,0 +1,86 @@
+use crate::shared::config::AgentConfig;
+use log::{debug, info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::{channel, Receiver};
+use tokio::task::JoinHandle;
+
+// Editors save in bursts (truncate, write, rename); changes are read once this long after the last
+const SETTLE_MS: u64 = 500;
+
+// Installs the configuration again whenever its file is edited. Monitored paths, extensions
+// and suspicious patterns are picked up by the filesystem and registry collectors on their
+// next run; other settings wait for a reload or restart. An edit that does not validate is
+// logged and the installed configuration is kept.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    // Kept alive for as long as the file is watched
+    _watcher: RecommendedWatcher,
+    changes: Receiver<()>,
+    digest: Option<Vec<u8>>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf) -> Result<Self, String> {
+        let file_name = path.file_name().map(|name| name.to_os_string());
+        let (tx, rx) = channel(16);
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let touches_file = event
+                .map(|event| event.paths.iter().any(|changed| changed.file_name().map(|name| name.to_os_string()) == file_name))
+                .unwrap_or(false);
+            if touches_file {
+                // A full queue already holds a pending change
+                let _ = tx.try_send(());
+            }
+        })
+        .map_err(|e| format!("Failed to watch {}: {}", path.display(), e))?;
+
+        // The directory rather than the file, which editors replace instead of rewriting
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        watcher
+            .watch(&directory, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("Failed to watch {}: {}", directory.display(), e))?;
+
+        Ok(Self {
+            digest: Self::digest(&path),
+            path,
+            _watcher: watcher,
+            changes: rx,
+        })
+    }
+
+    fn digest(path: &Path) -> Option<Vec<u8>> {
+        fs::read(path).ok().map(|content| Sha256::digest(content).to_vec())
+    }
+
+    async fn run(&mut self) {
+        while self.changes.recv().await.is_some() {
+            tokio::time::sleep(Duration::from_millis(SETTLE_MS)).await;
+            while self.changes.try_recv().is_ok() {}
+
+            let digest = Self::digest(&self.path);
+            if digest.is_none() || digest == self.digest {
+                debug!("{} is unchanged or unreadable, not reloading", self.path.display());
+                continue;
+            }
+            self.digest = digest;
+            match AgentConfig::load() {
+                Ok(_) => info!("Reloaded {}, applying monitored paths and patterns", self.path.display()),
+                Err(e) => warn!("Ignoring the edit of {}: {}", self.path.display(), e),
+            }
+        }
+    }
+
+    // Reloads on every edit until the task is aborted, which also stops watching. Must be
+    // called within the Tokio runtime.
+    pub fn spawn(mut self) -> JoinHandle<()> {
+        info!("Watching {} for configuration changes", self.path.display());
+        tokio::spawn(async move { self.run().await })
+    }
+}
@@ -0,0 +1,151 @@
+use crate::shared::baseline::Baseline;
+use crate::shared::capabilities::DegradedModes;
+use crate::shared::maintenance::MaintenanceMode;
+use crate::shared::pseudonymizer::Pseudonymizer;
+use crate::shared::traits::Enricher;
+use crate::utils::load_config_section;
+use crate::utils::time::annotate_local_time;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::{Arc, OnceLock, RwLock};
+
+// Names of the built-in enrichers
+const ENRICHERS: &[&str] = &["local_time", "maintenance", "baseline", "degraded"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnrichmentConfig {
+    // Built-in enrichers in the order they run: local_time, maintenance, baseline, degraded
+    pub enrichers: Vec<String>,
+}
+
+impl Default for EnrichmentConfig {
+    fn default() -> Self {
+        Self {
+            enrichers: vec![
+                String::from("local_time"),
+                String::from("maintenance"),
+                String::from("baseline"),
+                String::from("degraded"),
+            ],
+        }
+    }
+}
+
+impl EnrichmentConfig {
+    pub fn load() -> Self {
+        load_config_section("enrichment")
+    }
+
+    pub fn problems(&self) -> Vec<String> {
+        self.enrichers
+            .iter()
+            .filter(|name| !ENRICHERS.contains(&name.as_str()))
+            .map(|name| format!("enrichment.enrichers '{}' is not one of {}", name, ENRICHERS.join(", ")))
+            .collect()
+    }
+}
+
+// `timestamp_local`, `timezone` and `utc_offset` next to the UTC timestamp
+pub struct LocalTime;
+
+impl Enricher for LocalTime {
+    fn name(&self) -> &str {
+        "local_time"
+    }
+
+    fn enrich(&self, _index: &str, document: &mut Value) {
+        annotate_local_time(document);
+    }
+}
+
+impl Enricher for MaintenanceMode {
+    fn name(&self) -> &str {
+        "maintenance"
+    }
+
+    fn enrich(&self, _index: &str, document: &mut Value) {
+        self.annotate(document);
+    }
+}
+
+impl Enricher for DegradedModes {
+    fn name(&self) -> &str {
+        "degraded"
+    }
+
+    fn enrich(&self, index: &str, document: &mut Value) {
+        self.annotate(index, document);
+    }
+}
+
+impl Enricher for Baseline {
+    fn name(&self) -> &str {
+        "baseline"
+    }
+
+    fn enrich(&self, _index: &str, document: &mut Value) {
+        self.annotate(document);
+    }
+}
+
+// The enrichers every stored document passes through, in order. Pseudonymization always
+// runs after the last of them, so nothing an enricher adds escapes it.
+pub struct EnrichmentChain {
+    enrichers: RwLock<Vec<Arc<dyn Enricher>>>,
+}
+
+impl EnrichmentChain {
+    pub fn new() -> Self {
+        Self::with_config(EnrichmentConfig::load())
+    }
+
+    pub fn with_config(config: EnrichmentConfig) -> Self {
+        let enrichers = config
+            .enrichers
+            .iter()
+            .filter_map(|name| match name.as_str() {
+                "local_time" => Some(Arc::new(LocalTime) as Arc<dyn Enricher>),
+                "maintenance" => Some(MaintenanceMode::global() as Arc<dyn Enricher>),
+                "baseline" => Some(Baseline::global() as Arc<dyn Enricher>),
+                "degraded" => Some(DegradedModes::global() as Arc<dyn Enricher>),
+                _ => {
+                    warn!("Ignoring unknown enricher {}", name);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            enrichers: RwLock::new(enrichers),
+        }
+    }
+
+    pub fn global() -> Arc<EnrichmentChain> {
+        static GLOBAL: OnceLock<Arc<EnrichmentChain>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(EnrichmentChain::new())).clone()
+    }
+
+    // Appends an enricher, which then runs after the configured ones
+    pub fn add(&self, enricher: Arc<dyn Enricher>) {
+        if let Ok(mut enrichers) = self.enrichers.write() {
+            enrichers.push(enricher);
+        }
+    }
+
+    // Runs every enricher over a document about to be written to `index`, then pseudonymizes it
+    pub fn enrich(&self, index: &str, document: &mut Value) {
+        if let Ok(enrichers) = self.enrichers.read() {
+            for enricher in enrichers.iter() {
+                enricher.enrich(index, document);
+            }
+        }
+        Pseudonymizer::global().apply(document);
+    }
+}
+
+impl Default for EnrichmentChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,3 +1,4 @@
+use crate::shared::storage::backend::StorageDocument;
 use thiserror::Error;
 use std::io;
 
@@ -11,6 +12,12 @@ pub enum XdrError {
     
     #[error("Storage failed: {0}")]
     Storage(#[from] StorageError),
+
+    #[error("Response action failed: {0}")]
+    Action(#[from] ActionError),
+
+    #[error("Configuration error: {0}")]
+    Config(#[from] ConfigError),
 }
 
 #[derive(Error, Debug)]
@@ -53,4 +60,84 @@ pub enum StorageError {
     
     #[error("Read operation failed: {0}")]
     Read(String),
+
+    // The server asked to come back later (429) or is briefly unavailable (502-504)
+    #[error("Storage is throttled or unavailable: {0}")]
+    Throttled(String),
+
+    // The rest of the batch was stored; these documents were turned away for now and can be
+    // written again without repeating the others
+    #[error("{} documents were turned away for now: {reason}", .remaining.len())]
+    Partial { reason: String, remaining: Vec<StorageDocument> },
+}
+
+impl StorageError {
+    // Worth retrying after a backoff, unlike a rejected document or a wrong configuration
+    pub fn is_transient(&self) -> bool {
+        matches!(self, StorageError::Throttled(_) | StorageError::Partial { .. })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ActionError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Invalid action request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Action denied by policy: {0}")]
+    PolicyViolation(String),
+
+    #[error("Integrity check failed: {0}")]
+    Integrity(String),
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read {0}: {1}")]
+    Read(String, io::Error),
+
+    #[error("Failed to parse {0}: {1}")]
+    Parse(String, String),
+
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
+
+#[derive(Error, Debug)]
+pub enum ServiceError {
+    #[error("Service manager error: {0}")]
+    Manager(String),
+
+    #[error("Failed to write {0}: {1}")]
+    Io(String, io::Error),
+
+    #[error("Running as a service is not supported on this platform")]
+    Unsupported,
+}
+
+#[derive(Error, Debug)]
+pub enum StateArchiveError {
+    #[error("Failed to read {0}: {1}")]
+    Read(String, io::Error),
+
+    #[error("Failed to write {0}: {1}")]
+    Write(String, io::Error),
+
+    // Wrong passphrase, a damaged file or not a state archive at all
+    #[error("Cannot open the state archive: {0}")]
+    Archive(String),
+
+    #[error("{0}")]
+    Refused(String),
+}
+
+#[derive(Error, Debug)]
+pub enum InstanceLockError {
+    #[error("Another agent instance (PID {pid}) is already running from this directory, holding {path}")]
+    Held { pid: u32, path: String },
+
+    #[error("Failed to take the instance lock {0}: {1}")]
+    Io(String, io::Error),
 }
@@ -0,0 +1,327 @@
+use chrono::{DateTime, Utc};
+#[cfg(windows)]
+use log::{debug, warn};
+use std::collections::HashMap;
+#[cfg(windows)]
+use std::ffi::c_void;
+use std::io;
+use std::sync::mpsc::SyncSender;
+#[cfg(windows)]
+use std::thread;
+use windows::core::GUID;
+#[cfg(windows)]
+use windows::core::PCWSTR;
+#[cfg(windows)]
+use windows::Win32::Foundation::{ERROR_ALREADY_EXISTS, ERROR_SUCCESS, WIN32_ERROR};
+#[cfg(windows)]
+use windows::Win32::System::Diagnostics::Etw::{
+    CloseTrace, ControlTraceW, EnableTraceEx2, OpenTraceW, ProcessTrace, StartTraceW, TdhGetEventInformation,
+    TdhGetProperty, TdhGetPropertySize, CONTROLTRACE_HANDLE, EVENT_CONTROL_CODE_ENABLE_PROVIDER,
+    EVENT_PROPERTY_INFO, EVENT_RECORD, EVENT_TRACE_CONTROL_STOP, EVENT_TRACE_LOGFILEW, EVENT_TRACE_PROPERTIES,
+    EVENT_TRACE_REAL_TIME_MODE, PROCESS_TRACE_MODE_EVENT_RECORD,
+    PROCESS_TRACE_MODE_REAL_TIME, PROPERTY_DATA_DESCRIPTOR, PropertyStruct, TRACE_EVENT_INFO,
+    WNODE_FLAG_TRACED_GUID,
+};
+
+// OpenTraceW signals failure with INVALID_PROCESSTRACE_HANDLE
+#[cfg(windows)]
+const INVALID_PROCESSTRACE_HANDLE: u64 = u64::MAX;
+
+// Seconds between 1601-01-01 and 1970-01-01
+#[cfg(windows)]
+const FILETIME_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+#[derive(Debug, Clone)]
+pub struct EtwProvider {
+    pub guid: GUID,
+    pub level: u8,
+    pub keywords: u64,
+}
+
+impl EtwProvider {
+    // Accepts "8c416c79-d49b-4f01-a467-e56d3aa8234c" with or without braces
+    pub fn parse_guid(value: &str) -> Option<GUID> {
+        let hex: String = value.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+        if hex.len() != 32 {
+            return None;
+        }
+        u128::from_str_radix(&hex, 16).ok().map(GUID::from_u128)
+    }
+}
+
+// One decoded event. Properties are the top-level scalar fields from the provider manifest,
+// rendered as strings.
+#[derive(Debug, Clone)]
+pub struct EtwEvent {
+    pub provider: GUID,
+    pub event_id: u16,
+    pub opcode: u8,
+    pub process_id: u32,
+    pub thread_id: u32,
+    pub timestamp: DateTime<Utc>,
+    pub task_name: Option<String>,
+    pub event_name: Option<String>,
+    pub properties: HashMap<String, String>,
+}
+
+// A real-time ETW session delivering decoded events to a channel. Stopping the session on
+// drop ends the consumer thread. Requires administrator rights.
+pub struct EtwSession {
+    #[cfg_attr(not(windows), allow(dead_code))]
+    name: Vec<u16>,
+}
+
+#[cfg(windows)]
+fn wide(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(windows)]
+fn check(error: WIN32_ERROR, what: &str) -> io::Result<()> {
+    if error == ERROR_SUCCESS {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("{} failed: {}", what, io::Error::from_raw_os_error(error.0 as i32)),
+        ))
+    }
+}
+
+// EVENT_TRACE_PROPERTIES followed by room for the session name, as StartTrace expects
+#[cfg(windows)]
+fn session_properties(name_length: usize) -> Vec<u8> {
+    let size = std::mem::size_of::<EVENT_TRACE_PROPERTIES>() + name_length * 2;
+    let mut buffer = vec![0u8; size];
+    let properties = buffer.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES;
+    unsafe {
+        (*properties).Wnode.BufferSize = size as u32;
+        (*properties).Wnode.Flags = WNODE_FLAG_TRACED_GUID;
+        (*properties).Wnode.ClientContext = 1;
+        (*properties).LogFileMode = EVENT_TRACE_REAL_TIME_MODE;
+        (*properties).LoggerNameOffset = std::mem::size_of::<EVENT_TRACE_PROPERTIES>() as u32;
+    }
+    buffer
+}
+
+#[cfg(windows)]
+fn stop_session(name: &[u16]) -> WIN32_ERROR {
+    let mut properties = session_properties(name.len());
+    unsafe {
+        ControlTraceW(
+            CONTROLTRACE_HANDLE(0),
+            PCWSTR(name.as_ptr()),
+            properties.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES,
+            EVENT_TRACE_CONTROL_STOP,
+        )
+    }
+}
+
+impl EtwSession {
+    #[cfg(not(windows))]
+    pub fn start(_name: &str, _providers: &[EtwProvider], _events: SyncSender<EtwEvent>) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "ETW is only available on Windows"))
+    }
+
+    #[cfg(windows)]
+    pub fn start(name: &str, providers: &[EtwProvider], events: SyncSender<EtwEvent>) -> io::Result<Self> {
+        let wide_name = wide(name);
+        let mut handle = CONTROLTRACE_HANDLE(0);
+        let mut properties = session_properties(wide_name.len());
+        let mut status = unsafe {
+            StartTraceW(&mut handle, PCWSTR(wide_name.as_ptr()), properties.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES)
+        };
+        // A session left behind by a previous run that did not shut down cleanly
+        if status == ERROR_ALREADY_EXISTS {
+            debug!("Stopping stale ETW session {}", name);
+            stop_session(&wide_name);
+            let mut properties = session_properties(wide_name.len());
+            status = unsafe {
+                StartTraceW(&mut handle, PCWSTR(wide_name.as_ptr()), properties.as_mut_ptr() as *mut EVENT_TRACE_PROPERTIES)
+            };
+        }
+        check(status, "StartTrace")?;
+        let session = Self { name: wide_name };
+
+        for provider in providers {
+            let status = unsafe {
+                EnableTraceEx2(
+                    handle,
+                    &provider.guid,
+                    EVENT_CONTROL_CODE_ENABLE_PROVIDER.0,
+                    provider.level,
+                    provider.keywords,
+                    0,
+                    0,
+                    None,
+                )
+            };
+            check(status, "EnableTraceEx2")?;
+        }
+
+        let context = Box::into_raw(Box::new(events)) as usize;
+        let mut logger_name = session.name.clone();
+        thread::Builder::new()
+            .name(format!("etw-{}", name))
+            .spawn(move || unsafe {
+                let mut logfile: EVENT_TRACE_LOGFILEW = std::mem::zeroed();
+                logfile.LoggerName = windows::core::PWSTR(logger_name.as_mut_ptr());
+                logfile.Anonymous1.ProcessTraceMode = PROCESS_TRACE_MODE_REAL_TIME | PROCESS_TRACE_MODE_EVENT_RECORD;
+                logfile.Anonymous2.EventRecordCallback = Some(event_record_callback);
+                logfile.Context = context as *mut c_void;
+
+                let trace = OpenTraceW(&mut logfile);
+                if trace.0 == INVALID_PROCESSTRACE_HANDLE {
+                    warn!("OpenTrace failed: {}", io::Error::last_os_error());
+                } else {
+                    // Blocks until the session is stopped
+                    let status = ProcessTrace(&[trace], None, None);
+                    if status != ERROR_SUCCESS {
+                        debug!("ProcessTrace returned {}", status.0);
+                    }
+                    CloseTrace(trace);
+                }
+                drop(Box::from_raw(context as *mut SyncSender<EtwEvent>));
+            })?;
+
+        Ok(session)
+    }
+}
+
+#[cfg(windows)]
+impl Drop for EtwSession {
+    fn drop(&mut self) {
+        stop_session(&self.name);
+    }
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn event_record_callback(record: *mut EVENT_RECORD) {
+    if record.is_null() || (*record).UserContext.is_null() {
+        return;
+    }
+    let events = &*((*record).UserContext as *const SyncSender<EtwEvent>);
+    // A full channel drops the event rather than stalling the ETW buffers
+    let _ = events.try_send(decode(&*record));
+}
+
+#[cfg(windows)]
+fn wide_at(buffer: &[u8], offset: u32) -> Option<String> {
+    let offset = offset as usize;
+    if offset == 0 || offset >= buffer.len() {
+        return None;
+    }
+    let units: Vec<u16> = buffer[offset..]
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|unit| *unit != 0)
+        .collect();
+    let value = String::from_utf16_lossy(&units).trim().to_string();
+    if value.is_empty() { None } else { Some(value) }
+}
+
+#[cfg(windows)]
+fn render(in_type: u16, data: &[u8]) -> String {
+    let integer = |size: usize| -> Option<u64> {
+        let mut bytes = [0u8; 8];
+        bytes[..size].copy_from_slice(data.get(..size)?);
+        Some(u64::from_le_bytes(bytes))
+    };
+    let rendered = match in_type {
+        // UNICODESTRING
+        1 => {
+            let units: Vec<u16> = data.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+            let length = units.iter().position(|unit| *unit == 0).unwrap_or(units.len());
+            Some(String::from_utf16_lossy(&units[..length]))
+        }
+        // ANSISTRING
+        2 => {
+            let length = data.iter().position(|byte| *byte == 0).unwrap_or(data.len());
+            Some(String::from_utf8_lossy(&data[..length]).into_owned())
+        }
+        3 => data.first().map(|byte| (*byte as i8).to_string()),
+        4 => data.first().map(|byte| byte.to_string()),
+        5 => integer(2).map(|value| (value as i16).to_string()),
+        6 => integer(2).map(|value| value.to_string()),
+        7 => integer(4).map(|value| (value as i32).to_string()),
+        // UINT32, BOOLEAN
+        8 | 13 => integer(4).map(|value| value.to_string()),
+        9 => integer(8).map(|value| (value as i64).to_string()),
+        10 => integer(8).map(|value| value.to_string()),
+        // POINTER, SIZET, HEXINT32, HEXINT64
+        16 | 20 | 21 | 23 => integer(data.len().min(8)).map(|value| format!("0x{:x}", value)),
+        // GUID
+        15 if data.len() >= 16 => {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(&data[..16]);
+            let guid = GUID::from_values(
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                u16::from_le_bytes([bytes[4], bytes[5]]),
+                u16::from_le_bytes([bytes[6], bytes[7]]),
+                [bytes[8], bytes[9], bytes[10], bytes[11], bytes[12], bytes[13], bytes[14], bytes[15]],
+            );
+            Some(format!("{:?}", guid))
+        }
+        _ => None,
+    };
+    rendered.unwrap_or_else(|| hex::encode(data))
+}
+
+#[cfg(windows)]
+unsafe fn decode(record: &EVENT_RECORD) -> EtwEvent {
+    let header = &record.EventHeader;
+    let seconds = header.TimeStamp / 10_000_000 - FILETIME_EPOCH_OFFSET;
+    let nanos = (header.TimeStamp % 10_000_000) as u32 * 100;
+    let mut event = EtwEvent {
+        provider: header.ProviderId,
+        event_id: header.EventDescriptor.Id,
+        opcode: header.EventDescriptor.Opcode,
+        process_id: header.ProcessId,
+        thread_id: header.ThreadId,
+        timestamp: DateTime::from_timestamp(seconds, nanos).unwrap_or_else(Utc::now),
+        task_name: None,
+        event_name: None,
+        properties: HashMap::new(),
+    };
+
+    let mut size = 0u32;
+    TdhGetEventInformation(record, None, None, &mut size);
+    if size == 0 {
+        return event;
+    }
+    let mut buffer = vec![0u8; size as usize];
+    if TdhGetEventInformation(record, None, Some(buffer.as_mut_ptr() as *mut TRACE_EVENT_INFO), &mut size) != 0 {
+        return event;
+    }
+    let info = &*(buffer.as_ptr() as *const TRACE_EVENT_INFO);
+    event.task_name = wide_at(&buffer, info.TaskNameOffset);
+    event.event_name = wide_at(&buffer, info.Anonymous1.EventNameOffset);
+
+    let properties = std::slice::from_raw_parts(
+        info.EventPropertyInfoArray.as_ptr() as *const EVENT_PROPERTY_INFO,
+        info.TopLevelPropertyCount as usize,
+    );
+    for property in properties {
+        if property.Flags.0 & PropertyStruct.0 != 0 {
+            continue;
+        }
+        let Some(name) = wide_at(&buffer, property.NameOffset) else {
+            continue;
+        };
+        let descriptor = [PROPERTY_DATA_DESCRIPTOR {
+            PropertyName: buffer.as_ptr().add(property.NameOffset as usize) as u64,
+            ArrayIndex: u32::MAX,
+            Reserved: 0,
+        }];
+        let mut length = 0u32;
+        if TdhGetPropertySize(record, None, &descriptor, &mut length) != 0 || length == 0 {
+            continue;
+        }
+        let mut data = vec![0u8; length as usize];
+        if TdhGetProperty(record, None, &descriptor, &mut data) != 0 {
+            continue;
+        }
+        event.properties.insert(name, render(property.Anonymous1.nonStructType.InType, &data));
+    }
+    event
+}
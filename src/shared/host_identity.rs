@@ -0,0 +1,235 @@
+use crate::shared::command;
+use crate::shared::vdi::{session_user, VdiConfig};
+use crate::utils::load_config_section;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use windows::core::PWSTR;
+use windows::Win32::System::SystemInformation::{
+    ComputerNameDnsFullyQualified, ComputerNameDnsHostname, GetComputerNameExW, COMPUTER_NAME_FORMAT,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostIdentityConfig {
+    // Report the FQDN as the event source when one can be resolved
+    pub use_fqdn: bool,
+    pub lowercase: bool,
+    // How often to re-read the host name so renames are picked up without a restart
+    pub refresh_secs: u64,
+    // Fixed name to report instead of the OS host name
+    pub override_name: Option<String>,
+}
+
+impl Default for HostIdentityConfig {
+    fn default() -> Self {
+        Self {
+            use_fqdn: true,
+            lowercase: true,
+            refresh_secs: 300,
+            override_name: None,
+        }
+    }
+}
+
+impl HostIdentityConfig {
+    pub fn load() -> Self {
+        load_config_section("host_identity")
+    }
+
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.override_name.as_ref().is_some_and(|name| name.trim().is_empty()) {
+            problems.push(String::from("host_identity.override_name is empty"));
+        }
+        problems
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostIdentity {
+    // Short host name, without the domain
+    pub hostname: String,
+    pub fqdn: Option<String>,
+    pub domain: Option<String>,
+    // Name stamped on every event as `source`
+    pub name: String,
+}
+
+struct CachedIdentity {
+    resolved_at: Instant,
+    identity: HostIdentity,
+}
+
+// Single place the agent learns its own name from. Collectors ask for the name on
+// every event instead of caching it at construction time, so a rename shows up in
+// the data within one refresh interval.
+pub struct HostIdentityService {
+    config: HostIdentityConfig,
+    // Set on a VDI clone that reports its template and user instead of its own name
+    vdi: Option<VdiConfig>,
+    cached: RwLock<CachedIdentity>,
+}
+
+impl HostIdentityService {
+    pub fn new() -> Self {
+        Self::with_config(HostIdentityConfig::load())
+    }
+
+    pub fn with_config(config: HostIdentityConfig) -> Self {
+        let vdi = Some(VdiConfig::load()).filter(|vdi| vdi.session_identity && vdi.active());
+        let identity = Self::resolve(&config, vdi.as_ref());
+        info!("Host identity: {} (fqdn: {:?})", identity.name, identity.fqdn);
+        Self {
+            config,
+            vdi,
+            cached: RwLock::new(CachedIdentity {
+                resolved_at: Instant::now(),
+                identity,
+            }),
+        }
+    }
+
+    pub fn global() -> Arc<HostIdentityService> {
+        static GLOBAL: OnceLock<Arc<HostIdentityService>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(HostIdentityService::new())).clone()
+    }
+
+    pub fn identity(&self) -> HostIdentity {
+        let refresh = Duration::from_secs(self.config.refresh_secs);
+        if let Ok(cached) = self.cached.read() {
+            if cached.resolved_at.elapsed() < refresh {
+                return cached.identity.clone();
+            }
+        }
+        self.refresh()
+    }
+
+    pub fn name(&self) -> String {
+        self.identity().name
+    }
+
+    // Re-resolves the host name immediately, logging when it changed
+    pub fn refresh(&self) -> HostIdentity {
+        let identity = Self::resolve(&self.config, self.vdi.as_ref());
+        match self.cached.write() {
+            Ok(mut cached) => {
+                if cached.identity != identity {
+                    info!(
+                        "Host identity changed: {} -> {}",
+                        cached.identity.name, identity.name
+                    );
+                }
+                cached.resolved_at = Instant::now();
+                cached.identity = identity.clone();
+            }
+            Err(e) => warn!("Host identity cache poisoned: {}", e),
+        }
+        identity
+    }
+
+    fn resolve(config: &HostIdentityConfig, vdi: Option<&VdiConfig>) -> HostIdentity {
+        let normalize = |value: &str| {
+            let value = value.trim().trim_end_matches('.');
+            if config.lowercase {
+                value.to_lowercase()
+            } else {
+                value.to_string()
+            }
+        };
+
+        let raw_hostname = Self::os_hostname().unwrap_or_else(|| "unknown".to_string());
+        let raw_hostname = normalize(&raw_hostname);
+
+        // Some systems already report a dotted name; split it rather than querying DNS
+        let (hostname, mut fqdn) = match raw_hostname.split_once('.') {
+            Some((short, _)) => (short.to_string(), Some(raw_hostname.clone())),
+            None => (raw_hostname.clone(), None),
+        };
+
+        if fqdn.is_none() {
+            fqdn = Self::os_fqdn()
+                .map(|value| normalize(&value))
+                .filter(|value| value.contains('.') && value.starts_with(&hostname));
+        }
+
+        let domain = fqdn
+            .as_deref()
+            .and_then(|value| value.split_once('.'))
+            .map(|(_, domain)| domain.to_string());
+
+        // A clone's name and machine GUID change with every logon; the golden image and the
+        // user it was handed to do not
+        let session = vdi.map(|vdi| {
+            let template = normalize(&vdi.template_of(&hostname));
+            match session_user() {
+                Some(user) => format!("{}@{}", normalize(&user), template),
+                None => template,
+            }
+        });
+        let name = match (&config.override_name, session) {
+            (Some(name), _) if !name.trim().is_empty() => normalize(name),
+            (_, Some(session)) => session,
+            _ if config.use_fqdn => fqdn.clone().unwrap_or_else(|| hostname.clone()),
+            _ => hostname.clone(),
+        };
+
+        HostIdentity {
+            hostname,
+            fqdn,
+            domain,
+            name,
+        }
+    }
+
+    fn os_hostname() -> Option<String> {
+        if cfg!(target_os = "windows") {
+            if let Some(name) = Self::computer_name(ComputerNameDnsHostname) {
+                return Some(name);
+            }
+        }
+        whoami::fallible::hostname()
+            .map_err(|e| debug!("Failed to read host name: {}", e))
+            .ok()
+    }
+
+    fn os_fqdn() -> Option<String> {
+        if cfg!(target_os = "windows") {
+            return Self::computer_name(ComputerNameDnsFullyQualified);
+        }
+
+        let output = command::run("hostname", ["-f"]).ok()?;
+        if !output.success {
+            return None;
+        }
+        let fqdn = output.stdout.trim().to_string();
+        if fqdn.is_empty() {
+            None
+        } else {
+            Some(fqdn)
+        }
+    }
+
+    fn computer_name(format: COMPUTER_NAME_FORMAT) -> Option<String> {
+        let mut buffer = vec![0u16; 256];
+        let mut size = buffer.len() as u32;
+        unsafe {
+            if !GetComputerNameExW(format, PWSTR(buffer.as_mut_ptr()), &mut size).as_bool() {
+                return None;
+            }
+        }
+        let name = String::from_utf16_lossy(&buffer[..size as usize]);
+        if name.is_empty() {
+            None
+        } else {
+            Some(name)
+        }
+    }
+}
+
+impl Default for HostIdentityService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
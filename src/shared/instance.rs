@@ -0,0 +1,138 @@
+use crate::shared::error::InstanceLockError;
+use crate::utils::load_config_section;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstanceConfig {
+    // Refuse to start while another agent holds the lock file
+    pub enabled: bool,
+    pub lock_file: String,
+}
+
+impl Default for InstanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            lock_file: String::from("data/lsedr.lock"),
+        }
+    }
+}
+
+impl InstanceConfig {
+    pub fn load() -> Self {
+        load_config_section("instance")
+    }
+}
+
+// Written to the lock file by the instance holding it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    pub executable: Option<PathBuf>,
+}
+
+impl LockHolder {
+    fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            started_at: Utc::now(),
+            executable: std::env::current_exe().ok(),
+        }
+    }
+
+    // Whether the process that wrote the lock still runs; a PID since reused by another
+    // program does not count
+    fn alive(&self) -> bool {
+        let pid = Pid::from_u32(self.pid);
+        let mut sys = System::new();
+        sys.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            true,
+            ProcessRefreshKind::nothing().with_exe(UpdateKind::OnlyIfNotSet),
+        );
+        let Some(process) = sys.process(pid) else {
+            return false;
+        };
+        match (process.exe(), &self.executable) {
+            (Some(running), Some(recorded)) => running == recorded,
+            _ => true,
+        }
+    }
+}
+
+// Keeps a second agent from running against the same spool, queues and baselines. The lock
+// is a file naming the holder's PID; a lock left behind by an instance that died is taken
+// over. Dropping the lock removes the file.
+pub struct InstanceLock {
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    pub fn acquire(config: &InstanceConfig) -> Result<Self, InstanceLockError> {
+        let path = PathBuf::from(&config.lock_file);
+        let io_error = |e| InstanceLockError::Io(config.lock_file.clone(), e);
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        // A second attempt after clearing a stale lock; losing that race to another
+        // starting instance leaves the lock to it
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let holder = serde_json::to_vec(&LockHolder::current()).unwrap_or_default();
+                    file.write_all(&holder).map_err(io_error)?;
+                    info!("Took the instance lock {}", path.display());
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(io_error(e)),
+            }
+            match Self::holder_at(&path) {
+                Some(holder) if holder.pid != std::process::id() && holder.alive() => {
+                    return Err(InstanceLockError::Held {
+                        pid: holder.pid,
+                        path: config.lock_file.clone(),
+                    });
+                }
+                _ => {
+                    warn!("Taking over the instance lock {} left by an agent that is no longer running", path.display());
+                    match fs::remove_file(&path) {
+                        Ok(()) => {}
+                        Err(e) if e.kind() == ErrorKind::NotFound => {}
+                        Err(e) => return Err(io_error(e)),
+                    }
+                }
+            }
+        }
+        let holder = Self::holder_at(&path).map_or(0, |holder| holder.pid);
+        Err(InstanceLockError::Held {
+            pid: holder,
+            path: config.lock_file.clone(),
+        })
+    }
+
+    // The running instance holding the lock, if any
+    pub fn holder(config: &InstanceConfig) -> Option<LockHolder> {
+        Self::holder_at(Path::new(&config.lock_file)).filter(LockHolder::alive)
+    }
+
+    fn holder_at(path: &Path) -> Option<LockHolder> {
+        fs::read(path).ok().and_then(|contents| serde_json::from_slice(&contents).ok())
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Failed to release the instance lock {}: {}", self.path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::utils::load_config_section;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IntervalConfig {
+    // Interval for collectors not listed below
+    pub default_secs: u64,
+    // Per-collector interval in seconds, keyed by collector name (process, filesystem, ...)
+    pub collectors: HashMap<String, u64>,
+}
+
+impl Default for IntervalConfig {
+    fn default() -> Self {
+        Self {
+            default_secs: 60,
+            collectors: HashMap::from([
+                (String::from("system_metrics"), 60),
+                (String::from("process"), 30),
+                (String::from("filesystem"), 5),
+            ]),
+        }
+    }
+}
+
+impl IntervalConfig {
+    pub fn load() -> Self {
+        load_config_section("intervals")
+    }
+
+    pub fn interval(&self, collector: &str) -> Duration {
+        let secs = self.collectors.get(collector).copied().unwrap_or(self.default_secs);
+        Duration::from_secs(secs.max(1))
+    }
+}
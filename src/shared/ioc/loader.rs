@@ -0,0 +1,280 @@
+use crate::shared::ioc::models::{Indicator, IndicatorType};
+use crate::shared::traits::Severity;
+use log::{info, warn};
+use regex::Regex;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+fn indicator_files(directory: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            indicator_files(&path, files);
+        } else if path.extension().is_some_and(|extension| extension == "csv" || extension == "json") {
+            files.push(path);
+        }
+    }
+}
+
+// Loads every .csv (plain or MISP CSV export) and .json (STIX 2 bundle or MISP event
+// export) file under the given directories
+pub fn load_indicators(directories: &[String]) -> Vec<Indicator> {
+    let mut files = Vec::new();
+    for directory in directories {
+        indicator_files(Path::new(directory), &mut files);
+    }
+    files.sort();
+
+    let mut indicators = Vec::new();
+    for path in files {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Failed to read indicator file {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let feed = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let loaded = if path.extension().is_some_and(|extension| extension == "csv") {
+            parse_csv(&content, &feed)
+        } else {
+            match serde_json::from_str::<Value>(&content) {
+                Ok(document) if document.get("type").and_then(Value::as_str) == Some("bundle") => {
+                    parse_stix(&document, &feed)
+                }
+                Ok(document) => parse_misp(&document, &feed),
+                Err(e) => {
+                    warn!("Failed to parse indicator file {}: {}", path.display(), e);
+                    continue;
+                }
+            }
+        };
+        info!("Loaded {} indicators from {}", loaded.len(), path.display());
+        indicators.extend(loaded);
+    }
+    indicators
+}
+
+// Maps the type names used by our CSV format, MISP and STIX onto indicator types
+fn indicator_type(kind: &str) -> Option<IndicatorType> {
+    match kind.trim().to_lowercase().as_str() {
+        "hash" | "md5" | "sha1" | "sha256" | "sha512" | "imphash" => Some(IndicatorType::Hash),
+        "domain" | "hostname" | "fqdn" | "domain-name" => Some(IndicatorType::Domain),
+        "ip" | "ip-dst" | "ip-src" | "ipv4-addr" | "ipv6-addr" => Some(IndicatorType::Ip),
+        "path" | "filename" | "file-path" | "file" => Some(IndicatorType::Path),
+        _ => None,
+    }
+}
+
+fn parse_severity(value: &str) -> Option<Severity> {
+    match value.trim().to_lowercase().as_str() {
+        "low" => Some(Severity::Low),
+        "medium" => Some(Severity::Medium),
+        "high" => Some(Severity::High),
+        "critical" => Some(Severity::Critical),
+        _ => None,
+    }
+}
+
+fn normalize(indicator_type: IndicatorType, value: &str) -> Option<String> {
+    let value = value.trim().to_lowercase();
+    let normalized = match indicator_type {
+        IndicatorType::Hash => value.chars().all(|c| c.is_ascii_hexdigit()).then_some(value),
+        IndicatorType::Domain => Some(value.trim_start_matches("*.").trim_end_matches('.').to_string()),
+        IndicatorType::Ip => Some(value),
+        IndicatorType::Path => Some(value.replace('\\', "/")),
+    };
+    normalized.filter(|value| !value.is_empty())
+}
+
+fn indicator(
+    kind: &str,
+    value: &str,
+    description: Option<String>,
+    severity: Option<Severity>,
+    feed: &str,
+) -> Vec<Indicator> {
+    // MISP composite attributes such as filename|sha256 carry one value per type
+    let values: Vec<&str> = if kind.contains('|') { value.split('|').collect() } else { vec![value] };
+    kind.split('|')
+        .zip(values)
+        .filter_map(|(kind, value)| {
+            let indicator_type = indicator_type(kind)?;
+            Some(Indicator {
+                indicator_type,
+                value: normalize(indicator_type, value)?,
+                description: description.clone().filter(|description| !description.is_empty()),
+                severity,
+                feed: feed.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+// Either a header row naming `type` and `value` (plus optional description / comment and
+// severity columns), as in MISP CSV exports, or headerless type,value,description,severity rows
+fn parse_csv(content: &str, feed: &str) -> Vec<Indicator> {
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .peekable();
+
+    let header: Vec<String> = lines
+        .peek()
+        .map(|line| csv_fields(line).iter().map(|field| field.trim().to_lowercase()).collect())
+        .unwrap_or_default();
+    let column = |names: &[&str]| header.iter().position(|field| names.contains(&field.as_str()));
+    let (kind, value, description, severity, to_ids) = match (column(&["type"]), column(&["value"])) {
+        (Some(kind), Some(value)) => {
+            lines.next();
+            (kind, value, column(&["description", "comment"]), column(&["severity"]), column(&["to_ids"]))
+        }
+        _ => (0, 1, Some(2), Some(3), None),
+    };
+
+    let mut indicators = Vec::new();
+    for line in lines {
+        let fields = csv_fields(line);
+        let get = |index: Option<usize>| index.and_then(|index| fields.get(index)).map(|field| field.trim().to_string());
+        if get(to_ids).is_some_and(|to_ids| to_ids == "0" || to_ids.eq_ignore_ascii_case("false")) {
+            continue;
+        }
+        let (Some(kind), Some(value)) = (get(Some(kind)), get(Some(value))) else {
+            continue;
+        };
+        let severity = get(severity).and_then(|severity| parse_severity(&severity));
+        indicators.extend(indicator(&kind, &value, get(description), severity, feed));
+    }
+    indicators
+}
+
+fn misp_attributes<'a>(event: &'a Value, attributes: &mut Vec<&'a Value>) {
+    if let Some(list) = event.get("Attribute").and_then(Value::as_array) {
+        attributes.extend(list);
+    }
+    for object in event.get("Object").and_then(Value::as_array).into_iter().flatten() {
+        misp_attributes(object, attributes);
+    }
+}
+
+// MISP JSON export: a single {"Event": {...}}, a {"response": [{"Event": ...}]} search
+// result or a bare list of events
+fn parse_misp(document: &Value, feed: &str) -> Vec<Indicator> {
+    let events: Vec<&Value> = match document {
+        Value::Array(events) => events.iter().collect(),
+        Value::Object(fields) if fields.contains_key("response") => {
+            document["response"].as_array().map(|events| events.iter().collect()).unwrap_or_default()
+        }
+        _ => vec![document],
+    };
+
+    let mut indicators = Vec::new();
+    for event in events {
+        let event = event.get("Event").unwrap_or(event);
+        let info = event.get("info").and_then(Value::as_str);
+        // threat_level_id: 1 high, 2 medium, 3 low, 4 undefined
+        let threat_level = event.get("threat_level_id").map(|level| match level {
+            Value::String(level) => level.clone(),
+            other => other.to_string(),
+        });
+        let severity = match threat_level.as_deref() {
+            Some("1") => Some(Severity::High),
+            Some("2") => Some(Severity::Medium),
+            Some("3") => Some(Severity::Low),
+            _ => None,
+        };
+
+        let mut attributes = Vec::new();
+        misp_attributes(event, &mut attributes);
+        for attribute in attributes {
+            if attribute.get("to_ids").and_then(Value::as_bool) == Some(false) {
+                continue;
+            }
+            let (Some(kind), Some(value)) = (
+                attribute.get("type").and_then(Value::as_str),
+                attribute.get("value").and_then(Value::as_str),
+            ) else {
+                continue;
+            };
+            let description = attribute
+                .get("comment")
+                .and_then(Value::as_str)
+                .filter(|comment| !comment.is_empty())
+                .or(info)
+                .map(String::from);
+            indicators.extend(indicator(kind, value, description, severity, feed));
+        }
+    }
+    indicators
+}
+
+fn stix_comparison() -> &'static Regex {
+    static COMPARISON: OnceLock<Regex> = OnceLock::new();
+    COMPARISON.get_or_init(|| {
+        Regex::new(r"([a-z0-9-]+):([A-Za-z0-9_.'-]+)\s*=\s*'((?:[^'\\]|\\.)*)'").expect("valid STIX comparison regex")
+    })
+}
+
+// STIX 2.x bundle; each equality comparison in an indicator pattern becomes one indicator
+fn parse_stix(document: &Value, feed: &str) -> Vec<Indicator> {
+    let mut indicators = Vec::new();
+    let objects = document.get("objects").and_then(Value::as_array).into_iter().flatten();
+    for object in objects.filter(|object| object.get("type").and_then(Value::as_str) == Some("indicator")) {
+        if object.get("revoked").and_then(Value::as_bool) == Some(true) {
+            continue;
+        }
+        if object.get("pattern_type").and_then(Value::as_str).is_some_and(|pattern_type| pattern_type != "stix") {
+            continue;
+        }
+        let Some(pattern) = object.get("pattern").and_then(Value::as_str) else {
+            continue;
+        };
+        let description = object
+            .get("name")
+            .or(object.get("description"))
+            .and_then(Value::as_str)
+            .map(String::from);
+
+        for comparison in stix_comparison().captures_iter(pattern) {
+            let kind = match (&comparison[1], &comparison[2]) {
+                ("file", property) if property.starts_with("hashes.") => "hash",
+                ("file", "name") => "filename",
+                ("domain-name", "value") => "domain",
+                ("ipv4-addr" | "ipv6-addr", "value") => "ip",
+                _ => continue,
+            };
+            let value = comparison[3].replace("\\'", "'").replace("\\\\", "\\");
+            indicators.extend(indicator(kind, &value, description.clone(), None, feed));
+        }
+    }
+    indicators
+}
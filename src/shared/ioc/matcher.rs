@@ -0,0 +1,319 @@
+use crate::shared::baseline::Baseline;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::ioc::loader::load_indicators;
+use crate::shared::ioc::models::{Indicator, IndicatorType, IocMatch, IocMatchBuilder};
+use crate::shared::traits::Severity;
+use crate::utils::load_config_section;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IocConfig {
+    pub enabled: bool,
+    // Searched recursively for .csv and .json indicator files
+    pub directories: Vec<String>,
+    // Severity of matches whose indicator does not carry one
+    pub default_severity: Severity,
+    // A repeat of the same indicator on the same value is not reported again within this window
+    pub suppress_secs: u64,
+    // Event fields, at any depth, compared against each indicator type
+    pub hash_fields: Vec<String>,
+    pub domain_fields: Vec<String>,
+    pub ip_fields: Vec<String>,
+    pub path_fields: Vec<String>,
+}
+
+impl Default for IocConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directories: vec![String::from("rules/ioc")],
+            default_severity: Severity::High,
+            suppress_secs: 3600,
+            hash_fields: vec![
+                String::from("sha256"),
+                String::from("sha1"),
+                String::from("md5"),
+                String::from("hash"),
+            ],
+            domain_fields: vec![
+                String::from("query"),
+                String::from("query_name"),
+                String::from("domain"),
+                String::from("remote_host"),
+            ],
+            ip_fields: vec![
+                String::from("remote_address"),
+                String::from("remote_ip"),
+                String::from("source_ip"),
+                String::from("destination_ip"),
+            ],
+            path_fields: vec![
+                String::from("path"),
+                String::from("new_path"),
+                String::from("executable"),
+                String::from("image_path"),
+                String::from("loader"),
+            ],
+        }
+    }
+}
+
+impl IocConfig {
+    pub fn load() -> Self {
+        load_config_section("ioc")
+    }
+}
+
+struct Network {
+    address: IpAddr,
+    prefix: u32,
+    indicator: Indicator,
+}
+
+impl Network {
+    fn contains(&self, address: &IpAddr) -> bool {
+        match (self.address, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix).unwrap_or(0);
+                u32::from(network) & mask == u32::from(*address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix).unwrap_or(0);
+                u128::from(network) & mask == u128::from(*address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+// Accepts a bare address, an address with a port (1.2.3.4:443, [::1]:443) or a CIDR block
+fn parse_address(value: &str) -> Option<(IpAddr, Option<u32>)> {
+    let value = value.trim();
+    if let Some((address, prefix)) = value.split_once('/') {
+        return Some((address.parse().ok()?, Some(prefix.parse().ok()?)));
+    }
+    value
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|socket| socket.ip()))
+        .map(|address| (address, None))
+}
+
+// Matches hash, domain, IP and path indicators against any serialized event. Fields
+// are found by name at any depth, so the same matcher covers every collector's events.
+pub struct IocMatcher {
+    config: IocConfig,
+    host: Arc<HostIdentityService>,
+    hashes: HashMap<String, Indicator>,
+    domains: HashMap<String, Indicator>,
+    addresses: HashMap<IpAddr, Indicator>,
+    networks: Vec<Network>,
+    // Full paths, and bare file names matched against the last path component
+    paths: HashMap<String, Indicator>,
+    file_names: HashMap<String, Indicator>,
+    // Kept across restarts, so a long-lived connection to a known-bad address is not
+    // reported again every time the agent starts
+    reported: HashMap<String, DateTime<Utc>>,
+}
+
+impl IocMatcher {
+    const REPORTED_BASELINE: &'static str = "ioc_reported";
+
+    pub fn new() -> Self {
+        Self::with_config(IocConfig::load())
+    }
+
+    pub fn with_config(config: IocConfig) -> Self {
+        let mut matcher = Self {
+            host: HostIdentityService::global(),
+            hashes: HashMap::new(),
+            domains: HashMap::new(),
+            addresses: HashMap::new(),
+            networks: Vec::new(),
+            paths: HashMap::new(),
+            file_names: HashMap::new(),
+            reported: Baseline::global().load(Self::REPORTED_BASELINE).unwrap_or_default(),
+            config,
+        };
+        for indicator in load_indicators(&matcher.config.directories) {
+            matcher.add(indicator);
+        }
+        info!("IOC matching enabled with {} indicators", matcher.indicator_count());
+        matcher
+    }
+
+    fn add(&mut self, indicator: Indicator) {
+        match indicator.indicator_type {
+            IndicatorType::Hash => {
+                self.hashes.insert(indicator.value.clone(), indicator);
+            }
+            IndicatorType::Domain => {
+                self.domains.insert(indicator.value.clone(), indicator);
+            }
+            IndicatorType::Ip => match parse_address(&indicator.value) {
+                Some((address, None)) => {
+                    self.addresses.insert(address, indicator);
+                }
+                Some((address, Some(prefix))) if prefix <= if address.is_ipv4() { 32 } else { 128 } => {
+                    self.networks.push(Network { address, prefix, indicator });
+                }
+                _ => warn!("Ignoring invalid IP indicator '{}' from {}", indicator.value, indicator.feed),
+            },
+            IndicatorType::Path if indicator.value.contains('/') => {
+                self.paths.insert(indicator.value.clone(), indicator);
+            }
+            IndicatorType::Path => {
+                self.file_names.insert(indicator.value.clone(), indicator);
+            }
+        }
+    }
+
+    pub fn indicator_count(&self) -> usize {
+        self.hashes.len()
+            + self.domains.len()
+            + self.addresses.len()
+            + self.networks.len()
+            + self.paths.len()
+            + self.file_names.len()
+    }
+
+    fn match_hash(&self, value: &str) -> Option<&Indicator> {
+        // Some collectors prefix the digest with its algorithm, e.g. sha256:ab12...
+        let digest = value.rsplit(':').next().unwrap_or(value).trim().to_lowercase();
+        self.hashes.get(&digest)
+    }
+
+    fn match_domain(&self, value: &str) -> Option<&Indicator> {
+        // An indicator domain also covers its subdomains
+        let mut domain = value.trim().trim_end_matches('.').to_lowercase();
+        loop {
+            if let Some(indicator) = self.domains.get(&domain) {
+                return Some(indicator);
+            }
+            let (_, parent) = domain.split_once('.')?;
+            domain = parent.to_string();
+        }
+    }
+
+    fn match_ip(&self, value: &str) -> Option<&Indicator> {
+        let (address, _) = parse_address(value)?;
+        self.addresses.get(&address).or_else(|| {
+            self.networks
+                .iter()
+                .find(|network| network.contains(&address))
+                .map(|network| &network.indicator)
+        })
+    }
+
+    fn match_path(&self, value: &str) -> Option<&Indicator> {
+        let path = value.trim().to_lowercase().replace('\\', "/");
+        self.paths.get(&path).or_else(|| {
+            let file_name = path.rsplit('/').next()?;
+            self.file_names.get(file_name)
+        })
+    }
+
+    fn check(&self, field: &str, value: &str) -> Option<&Indicator> {
+        if value.is_empty() {
+            return None;
+        }
+        let listed = |fields: &[String]| fields.iter().any(|name| name == field);
+        let mut found = None;
+        if listed(&self.config.hash_fields) {
+            found = found.or_else(|| self.match_hash(value));
+        }
+        if listed(&self.config.domain_fields) {
+            found = found.or_else(|| self.match_domain(value));
+        }
+        if listed(&self.config.ip_fields) {
+            found = found.or_else(|| self.match_ip(value));
+        }
+        if listed(&self.config.path_fields) {
+            found = found.or_else(|| self.match_path(value));
+        }
+        found
+    }
+
+    fn walk(&self, value: &Value, field: &str, hits: &mut Vec<(String, String, Indicator)>) {
+        match value {
+            Value::String(text) => {
+                if let Some(indicator) = self.check(field, text) {
+                    hits.push((field.to_string(), text.clone(), indicator.clone()));
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.walk(value, field, hits);
+                }
+            }
+            Value::Object(fields) => {
+                for (name, value) in fields {
+                    self.walk(value, name, hits);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Matches a batch of events written to `index` and returns the new matches
+    pub fn scan<T: Serialize>(&mut self, index: &str, events: &[T]) -> Vec<IocMatch> {
+        if self.indicator_count() == 0 {
+            return Vec::new();
+        }
+        let suppress_since = Utc::now() - Duration::seconds(self.config.suppress_secs as i64);
+        self.reported.retain(|_, reported_at| *reported_at > suppress_since);
+
+        let mut matches = Vec::new();
+        for event in events {
+            let Ok(document) = serde_json::to_value(event) else {
+                continue;
+            };
+            let mut hits = Vec::new();
+            self.walk(&document, "", &mut hits);
+            let event_id = document.get("id").and_then(Value::as_str).map(String::from);
+
+            for (field, value, indicator) in hits {
+                let key = format!("{}|{}|{}", indicator.indicator_type.as_str(), indicator.value, value);
+                if self.reported.contains_key(&key) {
+                    continue;
+                }
+                self.reported.insert(key, Utc::now());
+                warn!(
+                    "IOC match: {} indicator {} ({}) in {}.{} = {}",
+                    indicator.indicator_type.as_str(), indicator.value, indicator.feed, index, field, value
+                );
+                let built = IocMatchBuilder::new()
+                    .timestamp(Utc::now())
+                    .source(self.host.name())
+                    .indicator(indicator)
+                    .default_severity(self.config.default_severity)
+                    .event_index(index.to_string())
+                    .event_id(event_id.clone())
+                    .matched_field(field)
+                    .matched_value(value)
+                    .build();
+                match built {
+                    Ok(ioc_match) => matches.push(ioc_match),
+                    Err(e) => warn!("Failed to build IOC match: {}", e),
+                }
+            }
+        }
+        if !matches.is_empty() {
+            Baseline::global().save(Self::REPORTED_BASELINE, &self.reported);
+        }
+        matches
+    }
+}
+
+impl Default for IocMatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
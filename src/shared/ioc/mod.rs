@@ -0,0 +1,7 @@
+mod loader;
+mod matcher;
+mod models;
+
+pub use loader::load_indicators;
+pub use matcher::{IocConfig, IocMatcher};
+pub use models::{Indicator, IndicatorType, IocMatch, IocMatchBuilder};
@@ -0,0 +1,191 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::shared::traits::{Event, Severity, Validatable, Identifiable};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndicatorType {
+    Hash,
+    Domain,
+    Ip,
+    Path,
+}
+
+impl IndicatorType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndicatorType::Hash => "hash",
+            IndicatorType::Domain => "domain",
+            IndicatorType::Ip => "ip",
+            IndicatorType::Path => "path",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Indicator {
+    pub indicator_type: IndicatorType,
+    // Normalized: lowercase, forward slashes for paths
+    pub value: String,
+    pub description: Option<String>,
+    pub severity: Option<Severity>,
+    // Indicator file the entry was loaded from
+    pub feed: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IocMatch {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub category: String,
+    pub indicator_type: IndicatorType,
+    pub indicator: String,
+    pub feed: String,
+    pub description: Option<String>,
+    pub severity: Severity,
+    // Index of the matching event, e.g. file_events
+    pub event_index: String,
+    pub event_id: Option<String>,
+    pub matched_field: String,
+    pub matched_value: String,
+}
+
+impl Event for IocMatch {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        match self.indicator_type {
+            IndicatorType::Hash => "ioc_hash_match",
+            IndicatorType::Domain => "ioc_domain_match",
+            IndicatorType::Ip => "ioc_ip_match",
+            IndicatorType::Path => "ioc_path_match",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl Identifiable for IocMatch {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+impl Validatable for IocMatch {
+    fn validate(&self) -> Result<(), String> {
+        if self.indicator.is_empty() {
+            return Err("IOC match requires an indicator".to_string());
+        }
+        if self.matched_value.is_empty() {
+            return Err("IOC match requires the matched value".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct IocMatchBuilder {
+    id: Option<String>,
+    timestamp: Option<DateTime<Utc>>,
+    source: Option<String>,
+    category: Option<String>,
+    indicator: Option<Indicator>,
+    default_severity: Option<Severity>,
+    event_index: Option<String>,
+    event_id: Option<String>,
+    matched_field: Option<String>,
+    matched_value: Option<String>,
+}
+
+impl IocMatchBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(mut self, id: String) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn source(mut self, source: String) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn category(mut self, category: String) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn indicator(mut self, indicator: Indicator) -> Self {
+        self.indicator = Some(indicator);
+        self
+    }
+
+    // Used when the indicator itself carries no severity
+    pub fn default_severity(mut self, severity: Severity) -> Self {
+        self.default_severity = Some(severity);
+        self
+    }
+
+    pub fn event_index(mut self, event_index: String) -> Self {
+        self.event_index = Some(event_index);
+        self
+    }
+
+    pub fn event_id(mut self, event_id: Option<String>) -> Self {
+        self.event_id = event_id;
+        self
+    }
+
+    pub fn matched_field(mut self, matched_field: String) -> Self {
+        self.matched_field = Some(matched_field);
+        self
+    }
+
+    pub fn matched_value(mut self, matched_value: String) -> Self {
+        self.matched_value = Some(matched_value);
+        self
+    }
+
+    pub fn build(self) -> Result<IocMatch, String> {
+        let indicator = self.indicator.ok_or("indicator is required")?;
+        let ioc_match = IocMatch {
+            id: self.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            timestamp: self.timestamp.ok_or("timestamp is required")?,
+            source: self.source.ok_or("source is required")?,
+            category: self.category.unwrap_or_else(|| String::from("ioc")),
+            indicator_type: indicator.indicator_type,
+            severity: indicator.severity.or(self.default_severity).unwrap_or(Severity::High),
+            indicator: indicator.value,
+            feed: indicator.feed,
+            description: indicator.description,
+            event_index: self.event_index.ok_or("event_index is required")?,
+            event_id: self.event_id,
+            matched_field: self.matched_field.ok_or("matched_field is required")?,
+            matched_value: self.matched_value.ok_or("matched_value is required")?,
+        };
+
+        ioc_match.validate()?;
+        Ok(ioc_match)
+    }
+}
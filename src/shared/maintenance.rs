@@ -0,0 +1,216 @@
+use crate::shared::storage::ShippingWindow;
+use crate::shared::traits::Severity;
+use crate::utils::load_config_section;
+use chrono::{DateTime, Duration, Local, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+// How long the running agent trusts its last read of the state file
+const STATE_REFRESH_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    // Recurring patch windows, in local time like the shipping windows
+    pub windows: Vec<ShippingWindow>,
+    // Written by `lsedr maintenance start` and removed by `lsedr maintenance stop`; the
+    // running agent polls it, so it doubles as the control channel
+    pub state_file: String,
+    // Alerts at or above this severity are still raised during maintenance
+    pub min_alert_severity: Severity,
+    // Collectors (by interval key) not run during maintenance
+    pub paused_collectors: Vec<String>,
+    // Skip YARA scans of created and modified files during maintenance
+    pub skip_yara: bool,
+    // Length of a manual window started without --minutes
+    pub default_minutes: u64,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            windows: Vec::new(),
+            state_file: String::from("data/maintenance.json"),
+            min_alert_severity: Severity::Critical,
+            paused_collectors: vec![String::from("osquery"), String::from("boot")],
+            skip_yara: true,
+            default_minutes: 120,
+        }
+    }
+}
+
+impl MaintenanceConfig {
+    pub fn load() -> Self {
+        load_config_section("maintenance")
+    }
+
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.state_file.trim().is_empty() {
+            problems.push(String::from("maintenance.state_file is empty"));
+        }
+        if self.default_minutes == 0 {
+            problems.push(String::from("maintenance.default_minutes must not be 0"));
+        }
+        problems
+    }
+}
+
+// A manual maintenance window, as stored in the state file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceState {
+    pub started_at: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+// Why maintenance is active right now
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaintenanceSource {
+    Manual { until: DateTime<Utc>, reason: Option<String> },
+    Window,
+}
+
+impl MaintenanceSource {
+    pub fn reason(&self) -> String {
+        match self {
+            MaintenanceSource::Manual { reason: Some(reason), .. } => reason.clone(),
+            MaintenanceSource::Manual { reason: None, .. } => String::from("manual"),
+            MaintenanceSource::Window => String::from("scheduled window"),
+        }
+    }
+}
+
+pub struct MaintenanceMode {
+    config: MaintenanceConfig,
+    state: Mutex<Option<(Instant, Option<MaintenanceState>)>>,
+}
+
+impl MaintenanceMode {
+    pub fn new() -> Self {
+        Self::with_config(MaintenanceConfig::load())
+    }
+
+    pub fn with_config(config: MaintenanceConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(None),
+        }
+    }
+
+    pub fn global() -> Arc<MaintenanceMode> {
+        static GLOBAL: OnceLock<Arc<MaintenanceMode>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(MaintenanceMode::new())).clone()
+    }
+
+    fn read_state(&self) -> Option<MaintenanceState> {
+        let content = fs::read_to_string(&self.config.state_file).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(state) => Some(state),
+            Err(e) => {
+                warn!("Ignoring unreadable maintenance state {}: {}", self.config.state_file, e);
+                None
+            }
+        }
+    }
+
+    fn manual_state(&self) -> Option<MaintenanceState> {
+        let Ok(mut cached) = self.state.lock() else {
+            return self.read_state();
+        };
+        match cached.as_ref() {
+            Some((read_at, state)) if read_at.elapsed().as_secs() < STATE_REFRESH_SECS => state.clone(),
+            _ => {
+                let state = self.read_state();
+                *cached = Some((Instant::now(), state.clone()));
+                state
+            }
+        }
+    }
+
+    pub fn current(&self) -> Option<MaintenanceSource> {
+        if let Some(state) = self.manual_state().filter(|state| state.until > Utc::now()) {
+            return Some(MaintenanceSource::Manual {
+                until: state.until,
+                reason: state.reason,
+            });
+        }
+        let now = Local::now();
+        self.config
+            .windows
+            .iter()
+            .any(|window| window.contains(now))
+            .then_some(MaintenanceSource::Window)
+    }
+
+    pub fn active(&self) -> bool {
+        self.current().is_some()
+    }
+
+    // Whether an alert or escalation of this severity goes out right now
+    pub fn raises(&self, severity: Severity) -> bool {
+        severity >= self.config.min_alert_severity || !self.active()
+    }
+
+    // Whether the collector with this interval key runs right now
+    pub fn runs(&self, collector: &str) -> bool {
+        !self.config.paused_collectors.iter().any(|name| name == collector) || !self.active()
+    }
+
+    pub fn skips_yara(&self) -> bool {
+        self.config.skip_yara && self.active()
+    }
+
+    // Tags a document recorded during maintenance, so it can be told apart or filtered out
+    pub fn annotate(&self, document: &mut Value) {
+        let Some(source) = self.current() else {
+            return;
+        };
+        if let Value::Object(map) = document {
+            map.insert(String::from("maintenance"), json!(true));
+            map.insert(String::from("maintenance_reason"), json!(source.reason()));
+        }
+    }
+
+    pub fn start(&self, minutes: Option<u64>, reason: Option<String>) -> io::Result<MaintenanceState> {
+        let started_at = Utc::now();
+        let minutes = minutes.unwrap_or(self.config.default_minutes);
+        let state = MaintenanceState {
+            started_at,
+            until: started_at + Duration::minutes(minutes as i64),
+            reason,
+        };
+        let path = Path::new(&self.config.state_file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(&state).map_err(io::Error::other)?;
+        fs::write(path, content)?;
+        info!("Maintenance started until {}", state.until.to_rfc3339());
+        Ok(state)
+    }
+
+    // False when no manual window was running
+    pub fn stop(&self) -> io::Result<bool> {
+        match fs::remove_file(&self.config.state_file) {
+            Ok(()) => {
+                info!("Maintenance stopped");
+                Ok(true)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Default for MaintenanceMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
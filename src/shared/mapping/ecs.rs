@@ -0,0 +1,460 @@
+use crate::features::{
+    filesystem::{FileEvent, FileEventType},
+    network::{NetworkConnectionInformation, NetworkInformation},
+    process::ProcessInformation,
+    registry::{RegistryEvent, RegistryEventType},
+    service::ServiceInformation,
+};
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::pseudonymizer::{PseudonymKind, Pseudonymizer};
+use crate::shared::signature::SignatureStatus;
+use crate::shared::storage::SystemInformation;
+use crate::shared::traits::{Event, Severity};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::OnceLock;
+use sysinfo::System;
+
+// Elastic Common Schema version the documents follow
+pub const ECS_VERSION: &str = "8.11.0";
+
+// The `host` and `agent` fields shared by every document of this host
+#[derive(Debug, Clone)]
+pub struct EcsHost {
+    pub name: String,
+    pub hostname: String,
+    pub domain: Option<String>,
+    pub os_type: &'static str,
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub kernel: Option<String>,
+}
+
+struct OsInfo {
+    name: Option<String>,
+    version: Option<String>,
+    kernel: Option<String>,
+}
+
+impl EcsHost {
+    pub fn current() -> Self {
+        static OS: OnceLock<OsInfo> = OnceLock::new();
+        let os = OS.get_or_init(|| OsInfo {
+            name: System::name(),
+            version: System::os_version(),
+            kernel: System::kernel_version(),
+        });
+        // Re-read on every call so a renamed host shows up like it does in native documents
+        let identity = HostIdentityService::global().identity();
+        Self {
+            name: identity.name,
+            hostname: identity.hostname,
+            domain: identity.domain,
+            os_type: os_type(),
+            os_name: os.name.clone(),
+            os_version: os.version.clone(),
+            kernel: os.kernel.clone(),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        let privacy = Pseudonymizer::global();
+        json!({
+            "name": privacy.pseudonymize(PseudonymKind::Host, &self.name),
+            "hostname": privacy.pseudonymize(PseudonymKind::Host, &self.hostname),
+            "domain": self.domain,
+            "os": {
+                "type": self.os_type,
+                "name": self.os_name,
+                "version": self.os_version,
+                "kernel": self.kernel,
+            },
+        })
+    }
+}
+
+fn os_type() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "macos",
+        "windows" => "windows",
+        _ => "linux",
+    }
+}
+
+// Pseudonymization matches field names, which ECS nests as host.name and user.name, so
+// the names are replaced while mapping instead
+fn user(name: Option<&str>) -> Value {
+    match name.filter(|name| !name.is_empty()) {
+        Some(name) => json!({ "name": Pseudonymizer::global().pseudonymize(PseudonymKind::User, name) }),
+        None => Value::Null,
+    }
+}
+
+// Risk score convention of the Elastic Security app
+fn severity_score(severity: Severity) -> u8 {
+    match severity {
+        Severity::Low => 21,
+        Severity::Medium => 47,
+        Severity::High => 73,
+        Severity::Critical => 99,
+    }
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+// Fields every document carries; `event` is merged into the base event object
+fn base(host: &EcsHost, time: DateTime<Utc>, dataset: &str, event: Value) -> Value {
+    let mut document = json!({
+        "@timestamp": timestamp(time),
+        "ecs": { "version": ECS_VERSION },
+        "agent": { "type": "lsedr", "version": env!("CARGO_PKG_VERSION") },
+        "host": host.to_value(),
+        "event": {
+            "module": "lsedr",
+            "dataset": format!("lsedr.{}", dataset),
+        },
+    });
+    if let (Some(target), Value::Object(fields)) = (document["event"].as_object_mut(), event) {
+        target.extend(fields);
+    }
+    document
+}
+
+// Merges `fields` into `document` and drops nulls and empty objects, which ECS consumers
+// treat as "not present" anyway
+fn finish(mut document: Value, fields: Value) -> Value {
+    if let (Some(target), Value::Object(fields)) = (document.as_object_mut(), fields) {
+        target.extend(fields);
+    }
+    prune(&mut document);
+    document
+}
+
+fn prune(value: &mut Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Object(fields) => {
+            fields.retain(|_, field| prune(field));
+            !fields.is_empty()
+        }
+        Value::Array(items) => {
+            items.retain_mut(prune);
+            !items.is_empty()
+        }
+        Value::String(text) => !text.is_empty(),
+        _ => true,
+    }
+}
+
+fn file_fields(path: &str) -> Map<String, Value> {
+    let path_ref = Path::new(path);
+    let mut file = Map::new();
+    file.insert(String::from("path"), json!(path));
+    file.insert(String::from("name"), json!(path_ref.file_name().map(|name| name.to_string_lossy())));
+    file.insert(String::from("directory"), json!(path_ref.parent().map(|dir| dir.to_string_lossy())));
+    file.insert(
+        String::from("extension"),
+        json!(path_ref.extension().map(|extension| extension.to_string_lossy().to_lowercase())),
+    );
+    file
+}
+
+// Address as ECS wants it in *.ip, or None for wildcard and unparsable addresses
+fn ip(address: &str) -> Option<IpAddr> {
+    let address = address.trim_start_matches('[').trim_end_matches(']');
+    address.parse::<IpAddr>().ok().filter(|ip| !ip.is_unspecified())
+}
+
+// Converts one agent model into an ECS document
+pub trait ToEcs {
+    fn to_ecs(&self, host: &EcsHost) -> Value;
+}
+
+impl ToEcs for FileEvent {
+    fn to_ecs(&self, host: &EcsHost) -> Value {
+        let event_type = match self.event_type {
+            FileEventType::Created => "creation",
+            FileEventType::Deleted => "deletion",
+            FileEventType::Accessed => "access",
+            FileEventType::Modified | FileEventType::Renamed | FileEventType::AttributesModified => "change",
+        };
+        let document = base(
+            host,
+            self.timestamp,
+            "file",
+            json!({
+                "id": self.id,
+                "kind": "event",
+                "category": ["file"],
+                "type": [event_type],
+                "action": self.event_type(),
+                "severity": severity_score(self.severity()),
+            }),
+        );
+
+        // A renamed file is reported under its new path, as Elastic Defend does
+        let mut file = file_fields(self.new_path.as_deref().unwrap_or(&self.path));
+        file.insert(
+            String::from("type"),
+            json!(if self.file_type == "directory" { "dir" } else { self.file_type.as_str() }),
+        );
+        file.insert(String::from("size"), json!(self.file_size));
+        file.insert(String::from("hash"), json!({ "sha256": self.hash }));
+
+        finish(
+            document,
+            json!({
+                "file": file,
+                "process": { "pid": self.process_id, "name": self.process_name },
+                "user": user(self.user.as_deref()),
+                "lsedr": {
+                    "file": {
+                        "original_path": self.new_path.as_ref().map(|_| &self.path),
+                        "permissions": self.permissions,
+                    },
+                    "yara_matches": self.yara_matches,
+                    "related_ids": self.related_ids,
+                },
+            }),
+        )
+    }
+}
+
+// Splits HKEY_LOCAL_MACHINE\SOFTWARE\... into the abbreviated hive and the key below it
+fn registry_hive(key_path: &str) -> (Option<&'static str>, &str) {
+    let (root, key) = key_path.split_once('\\').unwrap_or((key_path, ""));
+    let hive = match root.to_uppercase().as_str() {
+        "HKEY_LOCAL_MACHINE" | "HKLM" => Some("HKLM"),
+        "HKEY_CURRENT_USER" | "HKCU" => Some("HKCU"),
+        "HKEY_USERS" | "HKU" => Some("HKU"),
+        "HKEY_CLASSES_ROOT" | "HKCR" => Some("HKCR"),
+        "HKEY_CURRENT_CONFIG" | "HKCC" => Some("HKCC"),
+        _ => None,
+    };
+    match hive {
+        Some(hive) => (Some(hive), key),
+        None => (None, key_path),
+    }
+}
+
+impl ToEcs for RegistryEvent {
+    fn to_ecs(&self, host: &EcsHost) -> Value {
+        let event_type = match self.event_type {
+            RegistryEventType::Created => "creation",
+            RegistryEventType::Modified => "change",
+            RegistryEventType::Deleted => "deletion",
+        };
+        let document = base(
+            host,
+            self.timestamp,
+            "registry",
+            json!({
+                "id": self.id,
+                "kind": "event",
+                "category": ["registry"],
+                "type": [event_type],
+                "action": self.event_type(),
+                "severity": severity_score(self.severity()),
+            }),
+        );
+
+        let (hive, key) = registry_hive(&self.key_path);
+        let path = match &self.value_name {
+            Some(value) => format!("{}\\{}", self.key_path, value),
+            None => self.key_path.clone(),
+        };
+        finish(
+            document,
+            json!({
+                "registry": {
+                    "hive": hive,
+                    "key": key,
+                    "path": path,
+                    "value": self.value_name,
+                    "data": { "strings": self.new_data.as_ref().map(|data| vec![data]) },
+                },
+                "process": { "pid": self.process_id, "name": self.process_name },
+                "user": user(self.user.as_deref()),
+                "lsedr": { "registry": { "old_data": self.old_data } },
+            }),
+        )
+    }
+}
+
+impl ToEcs for ProcessInformation {
+    fn to_ecs(&self, host: &EcsHost) -> Value {
+        let document = base(
+            host,
+            self.timestamp,
+            "process",
+            json!({
+                "id": self.id,
+                "kind": "state",
+                "category": ["process"],
+                "type": ["info"],
+                "severity": severity_score(self.severity()),
+            }),
+        );
+        let code_signature = self.signature_status.map(|status| {
+            json!({
+                "exists": status != SignatureStatus::Unsigned,
+                "trusted": status == SignatureStatus::Signed,
+                "status": status,
+            })
+        });
+        finish(
+            document,
+            json!({
+                "process": {
+                    "pid": self.pid,
+                    "name": self.name,
+                    "command_line": self.command,
+                    "hash": { "sha256": self.sha256 },
+                    "code_signature": code_signature,
+                },
+                "user": user(Some(&self.user)),
+                "lsedr": {
+                    "process": {
+                        "status": self.status,
+                        "threads": self.threads,
+                        "cpu_usage": self.cpu_usage,
+                        "memory_usage": self.memory_usage,
+                    },
+                },
+            }),
+        )
+    }
+}
+
+impl ToEcs for NetworkConnectionInformation {
+    fn to_ecs(&self, host: &EcsHost) -> Value {
+        let document = base(
+            host,
+            Utc::now(),
+            "network",
+            json!({
+                "kind": "state",
+                "category": ["network"],
+                "type": ["connection", "info"],
+            }),
+        );
+        let local = ip(&self.local_address);
+        let remote = ip(&self.remote_address);
+        let network_type = local.or(remote).map(|ip| if ip.is_ipv4() { "ipv4" } else { "ipv6" });
+        // A listening socket has no remote end
+        let destination = remote.map(|ip| json!({ "ip": ip, "port": self.remote_port, "bytes": self.bytes_received }));
+        finish(
+            document,
+            json!({
+                "source": { "ip": local, "port": self.local_port, "bytes": self.bytes_sent },
+                "destination": destination,
+                "network": { "transport": self.protocol.to_lowercase(), "type": network_type },
+                "process": {
+                    "pid": self.process_id,
+                    "name": self.process_name,
+                    "executable": self.process_path,
+                    "hash": { "sha256": self.process_sha256 },
+                },
+                "user": user(self.user.as_deref()),
+                "lsedr": { "network": { "state": self.state, "first_seen": self.first_seen } },
+            }),
+        )
+    }
+}
+
+impl ToEcs for ServiceInformation {
+    fn to_ecs(&self, host: &EcsHost) -> Value {
+        let document = base(
+            host,
+            self.timestamp,
+            "service",
+            json!({
+                "id": self.id,
+                "kind": "state",
+                "category": ["configuration"],
+                "type": ["info"],
+            }),
+        );
+        finish(
+            document,
+            json!({
+                "service": { "name": self.name, "state": self.status },
+                "process": { "pid": self.process_id },
+                "lsedr": {
+                    "service": {
+                        "display_name": self.display_name,
+                        "startup_type": self.startup_type,
+                        "binary_path": self.binary_path,
+                        "account": self.account,
+                        "unit_file": self.unit_file,
+                        "dependencies": self.dependencies,
+                    },
+                },
+            }),
+        )
+    }
+}
+
+// A system_metrics snapshot becomes one host metrics document followed by one state
+// document per process, connection and service in it
+pub fn system_information(info: &SystemInformation, host: &EcsHost) -> Vec<Value> {
+    let time = DateTime::<Utc>::from(info.timestamp);
+    let addresses: Vec<&String> = info
+        .network_info
+        .iter()
+        .flat_map(|interface| interface.ipv4_addresses.iter().chain(&interface.ipv6_addresses))
+        .collect();
+    let macs: Vec<String> = info
+        .network_info
+        .iter()
+        .map(|interface| interface.mac_address.replace(':', "-").to_uppercase())
+        .filter(|mac| mac != "00-00-00-00-00-00")
+        .collect();
+    let sum = |field: fn(&NetworkInformation) -> u64| -> u64 {
+        info.network_info.iter().map(field).sum()
+    };
+
+    let mut document = base(
+        host,
+        time,
+        "host",
+        json!({
+            "kind": "metric",
+            "category": ["host"],
+            "type": ["info"],
+        }),
+    );
+    let metrics = json!({
+        "ip": addresses,
+        "mac": macs,
+        // ECS scales CPU usage to 0..1
+        "cpu": { "usage": f64::from(info.cpu_info.cpu_usage) / 100.0 },
+        "network": {
+            "ingress": { "bytes": sum(|i| i.received_bytes), "packets": sum(|i| i.received_packets) },
+            "egress": { "bytes": sum(|i| i.transmitted_bytes), "packets": sum(|i| i.transmitted_packets) },
+        },
+    });
+    if let (Some(target), Value::Object(fields)) = (document["host"].as_object_mut(), metrics) {
+        target.extend(fields);
+    }
+    let mut documents = vec![finish(
+        document,
+        json!({
+            "lsedr": {
+                "memory": info.memory_info,
+                "load": info.system_load,
+                "disks": info.disk_info,
+                "services_unchanged": info.services_unchanged,
+            },
+        }),
+    )];
+
+    documents.extend(info.process_info.iter().map(|process| process.to_ecs(host)));
+    documents.extend(info.network_connections.iter().map(|connection| connection.to_ecs(host)));
+    documents.extend(info.services.iter().map(|service| service.to_ecs(host)));
+    documents
+}
@@ -0,0 +1,3 @@
+pub mod ecs;
+
+pub use ecs::{EcsHost, ToEcs, ECS_VERSION};
@@ -0,0 +1,66 @@
+use aho_corasick::AhoCorasick;
+use log::warn;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+// File extensions from the configuration, matched without regard to case or a leading dot
+// (".EXE", "exe"). Built once per configuration; clones share the set.
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionSet {
+    extensions: Arc<HashSet<String>>,
+}
+
+impl ExtensionSet {
+    pub fn new(extensions: &[String]) -> Self {
+        let extensions = extensions
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_lowercase())
+            .filter(|extension| !extension.is_empty())
+            .collect();
+        Self {
+            extensions: Arc::new(extensions),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        let Some(extension) = path.extension().and_then(|extension| extension.to_str()) else {
+            return false;
+        };
+        // Most names are lowercase already and get away without an allocation
+        self.extensions.contains(extension)
+            || (extension.chars().any(char::is_uppercase) && self.extensions.contains(&extension.to_lowercase()))
+    }
+}
+
+// Literal substrings searched for in one pass over the text, e.g. the registry paths a key
+// path may contain. Case-sensitive; clones share the automaton.
+#[derive(Debug, Clone, Default)]
+pub struct SubstringSet {
+    automaton: Option<AhoCorasick>,
+}
+
+impl SubstringSet {
+    pub fn new(substrings: &[String]) -> Self {
+        if substrings.is_empty() {
+            return Self::default();
+        }
+        let automaton = AhoCorasick::new(substrings)
+            .map_err(|e| warn!("Ignoring substring list: {}", e))
+            .ok();
+        Self { automaton }
+    }
+
+    // Position in the configured list of the first substring the text contains
+    pub fn first_in(&self, text: &str) -> Option<usize> {
+        self.automaton
+            .as_ref()?
+            .find_overlapping_iter(text)
+            .map(|found| found.pattern().as_usize())
+            .min()
+    }
+}
@@ -0,0 +1,322 @@
+use crate::utils::load_config_section;
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+// Upper bounds, in seconds, of the collection latency histogram buckets
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0];
+// Longest request head read from a scraper, and how long it may take to send it
+const MAX_REQUEST_BYTES: usize = 8192;
+const REQUEST_TIMEOUT_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    // Address the Prometheus endpoint listens on; 0.0.0.0 exposes it to the network
+    pub listen: String,
+    pub path: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: String::from("127.0.0.1:9184"),
+            path: String::from("/metrics"),
+        }
+    }
+}
+
+impl MetricsConfig {
+    pub fn load() -> Self {
+        load_config_section("metrics")
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    // Observations at or below each of LATENCY_BUCKETS
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; LATENCY_BUCKETS.len()];
+        }
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+// Runs and output of one collector so far, as reported by the control server
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectorStats {
+    pub collector: String,
+    pub runs: u64,
+    pub failures: u64,
+    pub events: u64,
+    pub last_run: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct Counters {
+    events: BTreeMap<String, u64>,
+    collections: BTreeMap<(String, &'static str), u64>,
+    latency: BTreeMap<String, Histogram>,
+    last_run: BTreeMap<String, DateTime<Utc>>,
+    storage_writes: BTreeMap<(String, &'static str), u64>,
+    stored_documents: BTreeMap<String, u64>,
+    queue_depth: BTreeMap<String, u64>,
+}
+
+// The agent's own health in the Prometheus text format: what each collector produced and
+// how long it took, how storage writes went, how much is queued and what the agent itself
+// costs the host
+pub struct AgentMetrics {
+    counters: Mutex<Counters>,
+    system: Mutex<System>,
+    started: Instant,
+}
+
+impl AgentMetrics {
+    pub fn new() -> Self {
+        Self {
+            counters: Mutex::new(Counters::default()),
+            system: Mutex::new(System::new()),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn global() -> Arc<AgentMetrics> {
+        static GLOBAL: OnceLock<Arc<AgentMetrics>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(AgentMetrics::new())).clone()
+    }
+
+    fn update(&self, update: impl FnOnce(&mut Counters)) {
+        if let Ok(mut counters) = self.counters.lock() {
+            update(&mut counters);
+        }
+    }
+
+    // One run of a collector, with the number of events it produced
+    pub fn record_collection(&self, collector: &str, events: usize, elapsed: Duration, succeeded: bool) {
+        self.update(|counters| {
+            *counters.events.entry(collector.to_string()).or_default() += events as u64;
+            let result = if succeeded { "success" } else { "failure" };
+            *counters.collections.entry((collector.to_string(), result)).or_default() += 1;
+            counters.latency.entry(collector.to_string()).or_default().observe(elapsed.as_secs_f64());
+            counters.last_run.insert(collector.to_string(), Utc::now());
+        });
+    }
+
+    pub fn collector_stats(&self) -> Vec<CollectorStats> {
+        let Ok(counters) = self.counters.lock() else {
+            return Vec::new();
+        };
+        counters
+            .latency
+            .iter()
+            .map(|(collector, histogram)| CollectorStats {
+                collector: collector.clone(),
+                runs: histogram.count,
+                failures: counters.collections.get(&(collector.clone(), "failure")).copied().unwrap_or(0),
+                events: counters.events.get(collector).copied().unwrap_or(0),
+                last_run: counters.last_run.get(collector).copied(),
+            })
+            .collect()
+    }
+
+    // One write of `documents` documents of a kind, e.g. "process events"
+    pub fn record_storage(&self, kind: &str, documents: usize, succeeded: bool) {
+        self.update(|counters| {
+            let result = if succeeded { "success" } else { "failure" };
+            *counters.storage_writes.entry((kind.to_string(), result)).or_default() += 1;
+            if succeeded {
+                *counters.stored_documents.entry(kind.to_string()).or_default() += documents as u64;
+            }
+        });
+    }
+
+    pub fn set_queue_depth(&self, queue: &str, depth: u64) {
+        self.update(|counters| {
+            counters.queue_depth.insert(queue.to_string(), depth);
+        });
+    }
+
+    // Memory and CPU of the agent process; CPU is averaged since the previous scrape
+    fn process_usage(&self) -> Option<(u64, u64, f32)> {
+        let pid = sysinfo::get_current_pid().ok()?;
+        let mut system = self.system.lock().ok()?;
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            true,
+            ProcessRefreshKind::nothing().with_cpu().with_memory(),
+        );
+        let process = system.process(pid)?;
+        Some((process.memory(), process.virtual_memory(), process.cpu_usage()))
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if let Ok(counters) = self.counters.lock() {
+            family(&mut out, "lsedr_collector_events_total", "counter", "Events produced by each collector");
+            for (collector, events) in &counters.events {
+                sample(&mut out, "lsedr_collector_events_total", &[("collector", collector)], *events as f64);
+            }
+            family(&mut out, "lsedr_collector_runs_total", "counter", "Collector runs by result");
+            for ((collector, result), runs) in &counters.collections {
+                sample(&mut out, "lsedr_collector_runs_total", &[("collector", collector), ("result", result)], *runs as f64);
+            }
+            family(&mut out, "lsedr_collection_duration_seconds", "histogram", "Time each collector run took");
+            for (collector, histogram) in &counters.latency {
+                for (bound, count) in LATENCY_BUCKETS.iter().zip(&histogram.buckets) {
+                    let le = bound.to_string();
+                    sample(&mut out, "lsedr_collection_duration_seconds_bucket", &[("collector", collector), ("le", &le)], *count as f64);
+                }
+                sample(&mut out, "lsedr_collection_duration_seconds_bucket", &[("collector", collector), ("le", "+Inf")], histogram.count as f64);
+                sample(&mut out, "lsedr_collection_duration_seconds_sum", &[("collector", collector)], histogram.sum);
+                sample(&mut out, "lsedr_collection_duration_seconds_count", &[("collector", collector)], histogram.count as f64);
+            }
+            family(&mut out, "lsedr_storage_writes_total", "counter", "Storage writes by kind of document and result");
+            for ((kind, result), writes) in &counters.storage_writes {
+                sample(&mut out, "lsedr_storage_writes_total", &[("kind", kind), ("result", result)], *writes as f64);
+            }
+            family(&mut out, "lsedr_stored_documents_total", "counter", "Documents written to storage by kind");
+            for (kind, documents) in &counters.stored_documents {
+                sample(&mut out, "lsedr_stored_documents_total", &[("kind", kind)], *documents as f64);
+            }
+            family(&mut out, "lsedr_queue_depth", "gauge", "Items waiting in the agent's queues; the spool in bytes");
+            for (queue, depth) in &counters.queue_depth {
+                sample(&mut out, "lsedr_queue_depth", &[("queue", queue)], *depth as f64);
+            }
+        }
+
+        if let Some((resident, virtual_memory, cpu)) = self.process_usage() {
+            family(&mut out, "lsedr_process_resident_memory_bytes", "gauge", "Resident memory of the agent");
+            sample(&mut out, "lsedr_process_resident_memory_bytes", &[], resident as f64);
+            family(&mut out, "lsedr_process_virtual_memory_bytes", "gauge", "Virtual memory of the agent");
+            sample(&mut out, "lsedr_process_virtual_memory_bytes", &[], virtual_memory as f64);
+            family(&mut out, "lsedr_process_cpu_usage_percent", "gauge", "CPU used by the agent since the previous scrape, 100 per core");
+            sample(&mut out, "lsedr_process_cpu_usage_percent", &[], cpu as f64);
+        }
+        family(&mut out, "lsedr_uptime_seconds", "gauge", "Time since the agent started");
+        sample(&mut out, "lsedr_uptime_seconds", &[], self.started.elapsed().as_secs_f64());
+        out
+    }
+
+    // Serves the metrics over HTTP until the task is aborted. Must be called within the
+    // Tokio runtime.
+    pub fn serve(config: MetricsConfig) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let listener = match TcpListener::bind(&config.listen).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    warn!("Failed to listen for metrics scrapes on {}: {}", config.listen, e);
+                    return;
+                }
+            };
+            info!("Serving agent metrics on http://{}{}", config.listen, config.path);
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        let path = config.path.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = Self::respond(stream, &path).await {
+                                debug!("Metrics request from {} failed: {}", peer, e);
+                            }
+                        });
+                    }
+                    Err(e) => warn!("Failed to accept a metrics connection: {}", e),
+                }
+            }
+        })
+    }
+
+    async fn respond(mut stream: TcpStream, path: &str) -> io::Result<()> {
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 1024];
+        let received = tokio::time::timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS), async {
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < MAX_REQUEST_BYTES {
+                let read = stream.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+            Ok::<_, io::Error>(())
+        })
+        .await;
+        match received {
+            Ok(result) => result?,
+            Err(_) => return Err(io::Error::new(io::ErrorKind::TimedOut, "request not received in time")),
+        }
+
+        let head = String::from_utf8_lossy(&request);
+        let mut request_line = head.lines().next().unwrap_or("").split_whitespace();
+        let method = request_line.next().unwrap_or("");
+        let target = request_line.next().unwrap_or("");
+        let target = target.split('?').next().unwrap_or("");
+
+        let (status, body) = if method != "GET" && method != "HEAD" {
+            ("405 Method Not Allowed", String::from("Only GET is supported\n"))
+        } else if target != path {
+            ("404 Not Found", format!("Metrics are served at {}\n", path))
+        } else {
+            ("200 OK", Self::global().render())
+        };
+        let content_type = if status.starts_with("200") { "text/plain; version=0.0.4; charset=utf-8" } else { "text/plain; charset=utf-8" };
+        let mut response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            content_type,
+            body.len()
+        );
+        if method != "HEAD" {
+            response.push_str(&body);
+        }
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+}
+
+impl Default for AgentMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(label, value)| {
+                let value = value.replace('\\', r"\\").replace('"', "\\\"").replace('\n', r"\n");
+                format!("{}=\"{}\"", label, value)
+            })
+            .collect();
+        out.push_str(&format!("{{{}}}", labels.join(",")));
+    }
+    out.push_str(&format!(" {}\n", value));
+}
@@ -1,7 +1,37 @@
+pub mod alerting;
+pub mod baseline;
+pub mod ber;
+pub mod capabilities;
 pub mod collector;
+pub mod command;
+pub mod config;
+pub mod config_watcher;
+pub mod enrichment;
 pub mod storage;
 pub mod error;
+pub mod etw;
+pub mod host_identity;
+pub mod instance;
+pub mod intervals;
+pub mod ioc;
+pub mod maintenance;
+pub mod mapping;
+pub mod matchers;
+pub mod metrics;
+pub mod paths;
+pub mod pipeline;
+pub mod patterns;
+pub mod policy_profiles;
+pub mod protobuf;
+pub mod process_resolver;
+pub mod pseudonymizer;
+pub mod remote_config;
+pub mod signature;
+pub mod state_archive;
 pub mod traits;
+pub mod user_profiles;
+pub mod user_resolver;
+pub mod vdi;
 
 pub use error::*;
 pub use traits::*;
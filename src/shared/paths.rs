@@ -0,0 +1,141 @@
+use crate::shared::user_profiles::user_profiles;
+use std::fs;
+
+// Expands a configured path template into the concrete paths it names on this host:
+//   ${USERPROFILE}, ${HOME}  once per user profile on the host
+//   ${PROGRAMDATA}           C:/ProgramData
+//   ${WINDIR}                C:/Windows
+//   ${ALLUSERS}              the all-users profile, C:/ProgramData
+//   ${NAME}                  any other environment variable
+// then * and ? wildcards in any component, matched against what exists now. A template
+// whose variable has no value on this OS (${WINDIR} on Linux) expands to nothing, so one
+// configuration can serve a mixed fleet.
+pub fn expand_path(template: &str) -> Vec<String> {
+    let homes = if template.contains("${USERPROFILE}") || template.contains("${HOME}") {
+        profile_homes()
+    } else {
+        vec![String::new()]
+    };
+
+    let mut expanded = Vec::new();
+    for home in homes {
+        let per_user = template.replace("${USERPROFILE}", &home).replace("${HOME}", &home);
+        if let Some(path) = substitute(&per_user) {
+            expanded.extend(expand_wildcards(&path.replace('\\', "/")));
+        }
+    }
+    expanded
+}
+
+fn profile_homes() -> Vec<String> {
+    let mut homes: Vec<String> = user_profiles()
+        .into_iter()
+        .map(|profile| profile.path.to_string_lossy().into_owned())
+        .collect();
+    // Without any profile (a container, a fresh image) the agent's own home is better than nothing
+    if homes.is_empty() {
+        homes.extend(std::env::var("USERPROFILE").or_else(|_| std::env::var("HOME")).ok());
+    }
+    homes
+}
+
+fn variable(name: &str) -> Option<String> {
+    let (names, windows_default): (&[&str], &str) = match name {
+        "PROGRAMDATA" => (&["ProgramData"], "C:\\ProgramData"),
+        "WINDIR" => (&["WINDIR", "SystemRoot"], "C:\\Windows"),
+        "ALLUSERS" => (&["ALLUSERSPROFILE"], "C:\\ProgramData"),
+        _ => return std::env::var(name).ok().filter(|value| !value.is_empty()),
+    };
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+        .or_else(|| cfg!(windows).then(|| windows_default.to_string()))
+}
+
+// Replaces every ${NAME}, or None when one of them has no value here
+fn substitute(template: &str) -> Option<String> {
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        let end = start + rest[start..].find('}')?;
+        result.push_str(&rest[..start]);
+        result.push_str(&variable(&rest[start + 2..end])?);
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Some(result)
+}
+
+fn expand_wildcards(path: &str) -> Vec<String> {
+    if !path.contains(['*', '?']) {
+        return vec![path.to_string()];
+    }
+
+    let mut components = path.split('/');
+    // "C:" on Windows, "" for an absolute Unix path
+    let mut prefixes = vec![components.next().unwrap_or_default().to_string()];
+    for component in components {
+        if component.is_empty() {
+            continue;
+        }
+        if !component.contains(['*', '?']) {
+            for prefix in &mut prefixes {
+                prefix.push('/');
+                prefix.push_str(component);
+            }
+            continue;
+        }
+
+        let mut matched = Vec::new();
+        for prefix in &prefixes {
+            let Ok(entries) = fs::read_dir(format!("{}/", prefix)) else {
+                continue;
+            };
+            let mut names: Vec<String> = entries
+                .flatten()
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| wildcard_match(component, name))
+                .collect();
+            names.sort();
+            matched.extend(names.into_iter().map(|name| format!("{}/{}", prefix, name)));
+        }
+        prefixes = matched;
+    }
+    prefixes
+}
+
+// * matches any run of characters and ? a single one; Windows names compare case-insensitively
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let fold = |text: &str| -> Vec<char> {
+        if cfg!(windows) {
+            text.to_lowercase().chars().collect()
+        } else {
+            text.chars().collect()
+        }
+    };
+    let (pattern, name) = (fold(pattern), fold(name));
+
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,263 @@
+use aho_corasick::AhoCorasick;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::warn;
+use regex::{Regex, RegexSet};
+use std::sync::OnceLock;
+
+// Shipped patterns for command lines and autorun data; collectors add them to their defaults
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    // Encoded PowerShell, in any of the abbreviations PowerShell accepts
+    r"re:powershell(\.exe)?\b.*\s[-/]e(c|n|nc|nco|ncod|ncode|ncoded|ncodedc\w*)?\s+[a-z0-9+/=]{8,}",
+    r"re:\s-enc(odedcommand)?\s",
+    r"re:frombase64string\s*\(",
+    r"re:(iex|invoke-expression)\b.*(downloadstring|downloadfile|invoke-webrequest|\biwr\b)",
+    r"re:new-object\s+(system\.)?net\.webclient",
+    r"re:mshta(\.exe)?\s+.*(https?|ftp)://",
+    r"re:mshta(\.exe)?\s+.*(javascript|vbscript):",
+    r"re:rundll32(\.exe)?\s+.*javascript:",
+    r"re:regsvr32(\.exe)?\s.*/i:\s*https?://",
+    r"re:certutil(\.exe)?\s.*[-/](urlcache|decode|decodehex)\b",
+    r"re:bitsadmin(\.exe)?\s.*/transfer\b",
+];
+
+// Shortest token tried as base64 or hex; shorter ones are mostly ordinary words and numbers
+const MIN_ENCODED_LENGTH: usize = 16;
+// Encodings are unwrapped this many layers deep, e.g. hex inside base64
+const MAX_DECODE_DEPTH: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Base64,
+    Hex,
+}
+
+impl Encoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Base64 => "base64",
+            Encoding::Hex => "hex",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PatternMatch {
+    pub pattern: String,
+    // Set when the pattern only matched after decoding part of the text
+    pub encoding: Option<Encoding>,
+    pub decoded: Option<String>,
+}
+
+impl PatternMatch {
+    pub fn describe(&self) -> String {
+        match self.encoding {
+            Some(encoding) => format!("{} (in {}-decoded content)", self.pattern, encoding.as_str()),
+            None => self.pattern.clone(),
+        }
+    }
+}
+
+// Case-insensitive patterns matched against text and against whatever base64 or hex it
+// carries. An entry starting with `re:` is a regex; others are literal substrings, or regexes
+// too when the set is compiled with `compile_regexes`. Compiled once per configuration into
+// one Aho-Corasick automaton for the literals and one RegexSet for the rest, so a text is
+// scanned twice however many patterns there are; clones share the compiled matchers.
+#[derive(Clone)]
+pub struct PatternSet {
+    // Configured order, which matches are reported in
+    names: Vec<String>,
+    literals: Option<AhoCorasick>,
+    // Position in names of each literal and each regex
+    literal_ids: Vec<usize>,
+    regexes: RegexSet,
+    regex_ids: Vec<usize>,
+}
+
+impl PatternSet {
+    pub fn compile(patterns: &[String]) -> Self {
+        Self::build(patterns, false)
+    }
+
+    pub fn compile_regexes(patterns: &[String]) -> Self {
+        Self::build(patterns, true)
+    }
+
+    fn build(patterns: &[String], bare_regexes: bool) -> Self {
+        let mut names = Vec::new();
+        let mut literals = Vec::new();
+        let mut literal_ids = Vec::new();
+        let mut regexes = Vec::new();
+        let mut regex_ids = Vec::new();
+        for pattern in patterns {
+            let (name, source) = match pattern.strip_prefix("re:") {
+                Some(regex) => (regex, regex.to_string()),
+                None if bare_regexes => (pattern.as_str(), pattern.clone()),
+                // The automaton folds ASCII case only; other literals are left to the regexes
+                None if pattern.is_ascii() => {
+                    literal_ids.push(names.len());
+                    literals.push(pattern.clone());
+                    names.push(pattern.clone());
+                    continue;
+                }
+                None => (pattern.as_str(), regex::escape(pattern)),
+            };
+            let source = format!("(?i){}", source);
+            match Regex::new(&source) {
+                Ok(_) => {
+                    regex_ids.push(names.len());
+                    regexes.push(source);
+                    names.push(name.to_string());
+                }
+                Err(e) => warn!("Ignoring invalid suspicious pattern {}: {}", pattern, e),
+            }
+        }
+
+        let literals = if literals.is_empty() {
+            None
+        } else {
+            AhoCorasick::builder()
+                .ascii_case_insensitive(true)
+                .build(&literals)
+                .map_err(|e| warn!("Ignoring literal suspicious patterns: {}", e))
+                .ok()
+        };
+        // Every regex compiled on its own above, so only a size limit can fail here
+        let regexes = RegexSet::new(&regexes).unwrap_or_else(|e| {
+            warn!("Ignoring regex suspicious patterns: {}", e);
+            RegexSet::empty()
+        });
+        Self {
+            names,
+            literals,
+            literal_ids,
+            regexes,
+            regex_ids,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    // Which patterns match the text, by position in names
+    fn hits(&self, text: &str) -> Vec<bool> {
+        let mut hits = vec![false; self.names.len()];
+        if let Some(literals) = &self.literals {
+            for found in literals.find_overlapping_iter(text) {
+                hits[self.literal_ids[found.pattern().as_usize()]] = true;
+            }
+        }
+        for index in self.regexes.matches(text).iter() {
+            hits[self.regex_ids[index]] = true;
+        }
+        hits
+    }
+
+    // Every pattern matching the text as written or once decoded, each reported once
+    pub fn matches(&self, text: &str) -> Vec<PatternMatch> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let direct = self.hits(text);
+        // Decoding is only worth it for patterns the text did not match as written
+        let decoded: Vec<(Encoding, String, Vec<bool>)> = if direct.iter().all(|hit| *hit) {
+            Vec::new()
+        } else {
+            decoded_variants(text)
+                .into_iter()
+                .map(|(encoding, decoded)| {
+                    let hits = self.hits(&decoded);
+                    (encoding, decoded, hits)
+                })
+                .collect()
+        };
+
+        let mut matches: Vec<PatternMatch> = Vec::new();
+        for (index, name) in self.names.iter().enumerate() {
+            if direct[index] {
+                matches.push(PatternMatch {
+                    pattern: name.clone(),
+                    encoding: None,
+                    decoded: None,
+                });
+            } else if let Some((encoding, decoded, _)) = decoded.iter().find(|(_, _, hits)| hits[index]) {
+                matches.push(PatternMatch {
+                    pattern: name.clone(),
+                    encoding: Some(*encoding),
+                    decoded: Some(decoded.clone()),
+                });
+            }
+        }
+        matches
+    }
+
+    pub fn first_match(&self, text: &str) -> Option<PatternMatch> {
+        self.matches(text).into_iter().next()
+    }
+}
+
+fn encoded_tokens() -> &'static Regex {
+    static TOKENS: OnceLock<Regex> = OnceLock::new();
+    TOKENS.get_or_init(|| Regex::new(r"(0x)?[A-Za-z0-9+/]{16,}={0,2}").expect("valid token regex"))
+}
+
+// Text from decoded bytes: UTF-16LE as PowerShell's -EncodedCommand uses, or UTF-8; None for
+// anything that is not mostly printable
+fn printable(bytes: &[u8]) -> Option<String> {
+    let utf16 = bytes.len() >= 4 && bytes.len() % 2 == 0 && bytes.iter().skip(1).step_by(2).filter(|byte| **byte == 0).count() * 2 >= bytes.len() / 2;
+    let text = if utf16 {
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+        String::from_utf16(&units).ok()?
+    } else {
+        String::from_utf8(bytes.to_vec()).ok()?
+    };
+    let total = text.chars().count();
+    let readable = text
+        .chars()
+        .filter(|c| !c.is_control() || c.is_ascii_whitespace())
+        .count();
+    (total > 0 && readable * 10 >= total * 9).then_some(text)
+}
+
+fn decode_token(token: &str) -> Option<(Encoding, String)> {
+    let hex = token.strip_prefix("0x").unwrap_or(token);
+    if hex.len() >= MIN_ENCODED_LENGTH && hex.len() % 2 == 0 && hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        if let Some(text) = hex::decode(hex).ok().and_then(|bytes| printable(&bytes)) {
+            return Some((Encoding::Hex, text));
+        }
+    }
+    if token.len() % 4 != 0 {
+        return None;
+    }
+    STANDARD
+        .decode(token)
+        .ok()
+        .and_then(|bytes| printable(&bytes))
+        .map(|text| (Encoding::Base64, text))
+}
+
+// Text decoded from the base64 and hex tokens in the input, including tokens found in what
+// was decoded, labelled with the outermost encoding
+pub fn decoded_variants(text: &str) -> Vec<(Encoding, String)> {
+    let mut variants: Vec<(Encoding, String)> = Vec::new();
+    let mut layer: Vec<(Option<Encoding>, String)> = vec![(None, text.to_string())];
+    for _ in 0..MAX_DECODE_DEPTH {
+        let mut next = Vec::new();
+        for (outer, content) in &layer {
+            for token in encoded_tokens().find_iter(content) {
+                if let Some((encoding, decoded)) = decode_token(token.as_str()) {
+                    let encoding = outer.unwrap_or(encoding);
+                    if !variants.iter().any(|(_, known)| *known == decoded) {
+                        variants.push((encoding, decoded.clone()));
+                        next.push((Some(encoding), decoded));
+                    }
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        layer = next;
+    }
+    variants
+}
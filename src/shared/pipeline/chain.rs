@@ -0,0 +1,211 @@
+use crate::features::detection::FieldMatcher;
+use crate::shared::error::ProcessingError;
+use crate::shared::pipeline::processors::{
+    EnrichProcessor, Events, FieldDedupProcessor, FilterProcessor, TransformProcessor,
+};
+use crate::shared::traits::DataProcessor;
+use log::{debug, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+// Event categories a chain can be configured for, by the index their events are stored to
+pub const EVENT_CATEGORIES: &[&str] = &[
+    "file_events",
+    "registry_events",
+    "suspicious_registry_operations",
+    "process_events",
+    "exec_events",
+    "ingested_events",
+    "scheduled_tasks",
+    "service_events",
+    "osquery_results",
+    "honeypot_events",
+    "usb_events",
+    "deception_events",
+    "capture_events",
+    "keylogging_events",
+    "print_spooler_events",
+    "boot_events",
+    "persistence_events",
+    "log_events",
+    "listening_port_events",
+    "process_network_usage",
+    "snmp_trap_events",
+    "directory_events",
+    "tls_probe_events",
+];
+
+fn default_dedup_window() -> u64 {
+    60
+}
+
+fn default_dedup_entries() -> usize {
+    10_000
+}
+
+// One step of a chain, as written in the `pipeline.categories` lists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProcessorConfig {
+    Filter {
+        #[serde(default)]
+        drop: Vec<FieldMatcher>,
+        #[serde(default)]
+        keep: Vec<FieldMatcher>,
+    },
+    Dedup {
+        fields: Vec<String>,
+        #[serde(default = "default_dedup_window")]
+        window_secs: u64,
+        #[serde(default = "default_dedup_entries")]
+        max_entries: usize,
+    },
+    Enrich,
+    Transform {
+        #[serde(default)]
+        set: BTreeMap<String, Value>,
+        #[serde(default)]
+        redact: Vec<String>,
+        #[serde(default)]
+        lowercase: Vec<String>,
+    },
+}
+
+type Processor = Box<dyn DataProcessor<Events, Events> + Send + Sync>;
+
+impl ProcessorConfig {
+    fn name(&self) -> &'static str {
+        match self {
+            ProcessorConfig::Filter { .. } => "filter",
+            ProcessorConfig::Dedup { .. } => "dedup",
+            ProcessorConfig::Enrich => "enrich",
+            ProcessorConfig::Transform { .. } => "transform",
+        }
+    }
+
+    fn build(&self) -> Result<Processor, String> {
+        Ok(match self {
+            ProcessorConfig::Filter { drop, keep } => Box::new(FilterProcessor::new(drop, keep)?),
+            ProcessorConfig::Dedup { fields, .. } if fields.is_empty() => {
+                return Err(String::from("dedup needs at least one field"));
+            }
+            ProcessorConfig::Dedup { max_entries: 0, .. } => return Err(String::from("dedup max_entries must not be 0")),
+            ProcessorConfig::Dedup {
+                fields,
+                window_secs,
+                max_entries,
+            } => Box::new(FieldDedupProcessor::new(fields.clone(), *window_secs, *max_entries)),
+            ProcessorConfig::Enrich => Box::new(EnrichProcessor::new()),
+            ProcessorConfig::Transform { set, redact, lowercase } => {
+                Box::new(TransformProcessor::new(set.clone(), redact.clone(), lowercase.clone()))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipelineConfig {
+    // Processors each event category runs through, in order, between its collector and
+    // detection and storage; categories not listed go through unchanged
+    pub categories: BTreeMap<String, Vec<ProcessorConfig>>,
+}
+
+impl PipelineConfig {
+    // What keeps the chains from being built, for the configuration check
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (category, processors) in &self.categories {
+            if !EVENT_CATEGORIES.contains(&category.as_str()) {
+                problems.push(format!("pipeline.categories.{} is not an event category", category));
+            }
+            for (position, processor) in processors.iter().enumerate() {
+                if let Err(e) = processor.build() {
+                    problems.push(format!("pipeline.categories.{}[{}] ({}): {}", category, position, processor.name(), e));
+                }
+            }
+        }
+        problems
+    }
+}
+
+// The processors of one event category
+pub struct ProcessorChain {
+    processors: Vec<(&'static str, Processor)>,
+}
+
+impl ProcessorChain {
+    pub fn run(&self, mut events: Events) -> Result<Events, ProcessingError> {
+        for (name, processor) in &self.processors {
+            if events.is_empty() {
+                break;
+            }
+            let before = events.len();
+            processor.validate_input(&events)?;
+            events = processor.process(events)?;
+            processor.validate_output(&events)?;
+            if events.len() < before {
+                debug!("The {} processor dropped {} of {} events", name, before - events.len(), before);
+            }
+        }
+        Ok(events)
+    }
+}
+
+// The chains of every configured event category. A batch goes through its chain as
+// documents and comes out typed again; a chain that fails leaves the batch as collected.
+#[derive(Default)]
+pub struct EventPipeline {
+    chains: HashMap<String, ProcessorChain>,
+}
+
+impl EventPipeline {
+    pub fn new(config: &PipelineConfig) -> Self {
+        let mut chains = HashMap::new();
+        for (category, processors) in &config.categories {
+            let processors = processors
+                .iter()
+                .filter_map(|processor| match processor.build() {
+                    Ok(built) => Some((processor.name(), built)),
+                    Err(e) => {
+                        warn!("Skipping the {} processor of {}: {}", processor.name(), category, e);
+                        None
+                    }
+                })
+                .collect::<Vec<_>>();
+            if !processors.is_empty() {
+                chains.insert(category.clone(), ProcessorChain { processors });
+            }
+        }
+        Self { chains }
+    }
+
+    pub fn process<T: Serialize + DeserializeOwned>(&self, category: &str, events: Vec<T>) -> Vec<T> {
+        let Some(chain) = self.chains.get(category) else {
+            return events;
+        };
+        if events.is_empty() {
+            return events;
+        }
+        let documents: Result<Events, _> = events.iter().map(serde_json::to_value).collect();
+        let processed = documents
+            .map_err(|e| ProcessingError::InvalidFormat(e.to_string()))
+            .and_then(|documents| chain.run(documents));
+        match processed {
+            Ok(documents) => documents
+                .into_iter()
+                .filter_map(|document| {
+                    serde_json::from_value(document)
+                        .map_err(|e| warn!("Dropping a {} event the pipeline left unreadable: {}", category, e))
+                        .ok()
+                })
+                .collect(),
+            Err(e) => {
+                warn!("The {} pipeline failed, keeping the batch as collected: {}", category, e);
+                events
+            }
+        }
+    }
+}
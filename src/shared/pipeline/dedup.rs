@@ -0,0 +1,200 @@
+use crate::features::filesystem::{FileEvent, FileEventType};
+use crate::features::registry::RegistryEvent;
+use crate::features::service::ServiceInformation;
+use crate::utils::load_config_section;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DedupConfig {
+    pub enabled: bool,
+    // A file event for a path whose content (hash) was already reported within this many
+    // seconds is dropped; 0 keeps every file event
+    pub file_window_secs: u64,
+    // An identical registry change (key, value, type and new data) within this many seconds
+    // is dropped; 0 keeps every registry event
+    pub registry_window_secs: u64,
+    // A system snapshot whose service list is unchanged leaves it out, but carries it again
+    // at least this often; 0 always carries it
+    pub service_window_secs: u64,
+    // Paths and registry changes remembered at most; past this, events are kept
+    pub max_entries: usize,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            file_window_secs: 60,
+            registry_window_secs: 300,
+            service_window_secs: 3600,
+            max_entries: 50_000,
+        }
+    }
+}
+
+impl DedupConfig {
+    pub fn load() -> Self {
+        load_config_section("dedup")
+    }
+}
+
+#[derive(Default)]
+struct State {
+    // Path -> hash of the last reported content and when it was reported
+    files: HashMap<String, (Option<String>, Instant)>,
+    // Fingerprint of a registry change -> when it was last reported
+    registry: HashMap<u64, Instant>,
+    // Fingerprint of the last service list carried in a snapshot and when
+    services: Option<(u64, Instant)>,
+}
+
+fn fingerprint(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Drops repeats before they reach storage: a file rewritten with the same content, the same
+// registry value set over and over, a service list that did not change. A repeat is dropped
+// only within the window after the event it repeats was kept, so a steady repeat still shows
+// up once per window.
+pub struct Deduplicator {
+    config: DedupConfig,
+    state: Mutex<State>,
+}
+
+impl Deduplicator {
+    pub fn new(config: DedupConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    fn window(secs: u64) -> Option<Duration> {
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+
+    fn full<K, V>(&self, entries: &mut HashMap<K, V>, expired: impl Fn(&V) -> bool) -> bool {
+        if entries.len() >= self.config.max_entries {
+            entries.retain(|_, value| !expired(value));
+        }
+        entries.len() >= self.config.max_entries
+    }
+
+    pub fn filter_file_events(&self, events: Vec<FileEvent>) -> Vec<FileEvent> {
+        let (Some(window), Ok(mut state)) = (Self::window(self.config.file_window_secs), self.state.lock()) else {
+            return events;
+        };
+        let now = Instant::now();
+        let before = events.len();
+        let files = &mut state.files;
+        let kept: Vec<FileEvent> = events
+            .into_iter()
+            .filter(|event| {
+                match event.event_type {
+                    // The next content seen at the path is new again
+                    FileEventType::Deleted => {
+                        files.remove(&event.path);
+                        return true;
+                    }
+                    FileEventType::Renamed => {
+                        files.remove(&event.path);
+                        if let Some(new_path) = &event.new_path {
+                            files.insert(new_path.clone(), (event.hash.clone(), now));
+                        }
+                        return true;
+                    }
+                    _ => {}
+                }
+                // Without a hash on both sides the content cannot be shown to be the same
+                if let (Some((Some(hash), reported)), Some(current)) = (files.get(&event.path), &event.hash) {
+                    if hash == current && now.duration_since(*reported) < window {
+                        return false;
+                    }
+                }
+                if files.contains_key(&event.path) || !self.full(files, |(_, reported)| now.duration_since(*reported) >= window) {
+                    files.insert(event.path.clone(), (event.hash.clone(), now));
+                }
+                true
+            })
+            .collect();
+        if kept.len() < before {
+            debug!("Dropped {} repeated file events", before - kept.len());
+        }
+        kept
+    }
+
+    pub fn filter_registry_events(&self, events: Vec<RegistryEvent>) -> Vec<RegistryEvent> {
+        let (Some(window), Ok(mut state)) = (Self::window(self.config.registry_window_secs), self.state.lock()) else {
+            return events;
+        };
+        let now = Instant::now();
+        let before = events.len();
+        let registry = &mut state.registry;
+        let kept: Vec<RegistryEvent> = events
+            .into_iter()
+            .filter(|event| {
+                let key = fingerprint((
+                    format!("{:?}", event.event_type),
+                    &event.key_path,
+                    &event.value_name,
+                    &event.new_data,
+                ));
+                if registry.get(&key).is_some_and(|reported| now.duration_since(*reported) < window) {
+                    return false;
+                }
+                if registry.contains_key(&key) || !self.full(registry, |reported| now.duration_since(*reported) >= window) {
+                    registry.insert(key, now);
+                }
+                true
+            })
+            .collect();
+        if kept.len() < before {
+            debug!("Dropped {} repeated registry events", before - kept.len());
+        }
+        kept
+    }
+
+    // Whether a snapshot should carry this service list: false while it matches the last
+    // list carried, within the window
+    pub fn services_changed(&self, services: &[ServiceInformation]) -> bool {
+        let (Some(window), Ok(mut state)) = (Self::window(self.config.service_window_secs), self.state.lock()) else {
+            return true;
+        };
+        // Ids and timestamps are new on every collection
+        let mut entries: Vec<_> = services
+            .iter()
+            .map(|service| {
+                (
+                    &service.name,
+                    &service.display_name,
+                    &service.status,
+                    &service.startup_type,
+                    &service.binary_path,
+                    &service.account,
+                    &service.unit_file,
+                    service.process_id,
+                    &service.dependencies,
+                )
+            })
+            .collect();
+        entries.sort();
+        let key = fingerprint(entries);
+        let now = Instant::now();
+        if let Some((carried, when)) = state.services {
+            if carried == key && now.duration_since(when) < window {
+                return false;
+            }
+        }
+        state.services = Some((key, now));
+        true
+    }
+}
@@ -0,0 +1,6 @@
+pub mod chain;
+pub mod dedup;
+pub mod processors;
+
+pub use chain::{EventPipeline, PipelineConfig, ProcessorChain, ProcessorConfig, EVENT_CATEGORIES};
+pub use dedup::{DedupConfig, Deduplicator};
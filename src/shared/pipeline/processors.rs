@@ -0,0 +1,259 @@
+use crate::features::detection::{CompiledMatcher, FieldMatcher};
+use crate::shared::error::ProcessingError;
+use crate::shared::process_resolver::ProcessResolver;
+use crate::shared::traits::DataProcessor;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const REDACTED: &str = "[redacted]";
+
+// Events travel the chain as their serialized documents
+pub type Events = Vec<Value>;
+
+fn objects(events: &Events) -> Result<(), ProcessingError> {
+    match events.iter().position(|event| !event.is_object()) {
+        Some(position) => Err(ProcessingError::InvalidFormat(format!("Event {} of the batch is not an object", position))),
+        None => Ok(()),
+    }
+}
+
+// The value at a dotted path, when every step exists
+fn path_value<'a>(event: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(event, |value, key| value.get(key))
+}
+
+fn field_mut<'a>(event: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    path.split('.').try_fold(event, |value, key| value.get_mut(key))
+}
+
+fn compile(matchers: &[FieldMatcher]) -> Result<Vec<CompiledMatcher>, String> {
+    matchers.iter().map(CompiledMatcher::compile).collect()
+}
+
+// Drops the events that hold every `drop` condition, then, when `keep` has conditions,
+// those that do not hold all of them
+pub struct FilterProcessor {
+    drop: Vec<CompiledMatcher>,
+    keep: Vec<CompiledMatcher>,
+}
+
+impl FilterProcessor {
+    pub fn new(drop: &[FieldMatcher], keep: &[FieldMatcher]) -> Result<Self, String> {
+        Ok(Self {
+            drop: compile(drop)?,
+            keep: compile(keep)?,
+        })
+    }
+}
+
+impl DataProcessor<Events, Events> for FilterProcessor {
+    fn process(&self, events: Events) -> Result<Events, ProcessingError> {
+        Ok(events
+            .into_iter()
+            .filter(|event| self.drop.is_empty() || !self.drop.iter().all(|matcher| matcher.matches(event)))
+            .filter(|event| self.keep.iter().all(|matcher| matcher.matches(event)))
+            .collect())
+    }
+
+    fn validate_input(&self, events: &Events) -> Result<(), ProcessingError> {
+        objects(events)
+    }
+
+    fn validate_output(&self, _events: &Events) -> Result<(), ProcessingError> {
+        Ok(())
+    }
+}
+
+// Drops an event whose `fields` all equal those of an event kept within the window
+pub struct FieldDedupProcessor {
+    fields: Vec<String>,
+    window: Duration,
+    max_entries: usize,
+    seen: Mutex<HashMap<u64, Instant>>,
+}
+
+impl FieldDedupProcessor {
+    pub fn new(fields: Vec<String>, window_secs: u64, max_entries: usize) -> Self {
+        Self {
+            fields,
+            window: Duration::from_secs(window_secs),
+            max_entries,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn key(&self, event: &Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for field in &self.fields {
+            // Missing and null fields compare equal
+            path_value(event, field).unwrap_or(&Value::Null).to_string().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+impl DataProcessor<Events, Events> for FieldDedupProcessor {
+    fn process(&self, events: Events) -> Result<Events, ProcessingError> {
+        let mut seen = self
+            .seen
+            .lock()
+            .map_err(|_| ProcessingError::Transform(String::from("Dedup state is unavailable")))?;
+        let now = Instant::now();
+        if seen.len() >= self.max_entries {
+            seen.retain(|_, kept| now.duration_since(*kept) < self.window);
+        }
+        Ok(events
+            .into_iter()
+            .filter(|event| {
+                let key = self.key(event);
+                if seen.get(&key).is_some_and(|kept| now.duration_since(*kept) < self.window) {
+                    return false;
+                }
+                if seen.contains_key(&key) || seen.len() < self.max_entries {
+                    seen.insert(key, now);
+                }
+                true
+            })
+            .collect())
+    }
+
+    fn validate_input(&self, events: &Events) -> Result<(), ProcessingError> {
+        objects(events)
+    }
+
+    fn validate_output(&self, _events: &Events) -> Result<(), ProcessingError> {
+        Ok(())
+    }
+}
+
+// Fills in process_name, process_path and user where the event has the field but left it
+// empty, from its process_id
+pub struct EnrichProcessor {
+    processes: Arc<ProcessResolver>,
+}
+
+impl EnrichProcessor {
+    pub fn new() -> Self {
+        Self {
+            processes: ProcessResolver::global(),
+        }
+    }
+}
+
+impl Default for EnrichProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DataProcessor<Events, Events> for EnrichProcessor {
+    fn process(&self, mut events: Events) -> Result<Events, ProcessingError> {
+        for event in &mut events {
+            let Some(pid) = event.get("process_id").and_then(Value::as_u64) else {
+                continue;
+            };
+            let Some(fields) = event.as_object_mut() else {
+                continue;
+            };
+            let missing = |name: &str| fields.get(name).is_some_and(Value::is_null);
+            if !(missing("process_name") || missing("process_path") || missing("user")) {
+                continue;
+            }
+            let Some(details) = self.processes.resolve(pid as u32) else {
+                continue;
+            };
+            let resolved = [
+                ("process_name", Some(details.name)),
+                ("process_path", details.path),
+                ("user", details.user),
+            ];
+            for (name, value) in resolved {
+                if let (Some(field), Some(value)) = (fields.get_mut(name), value) {
+                    if field.is_null() {
+                        *field = Value::String(value);
+                    }
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    fn validate_input(&self, events: &Events) -> Result<(), ProcessingError> {
+        objects(events)
+    }
+
+    fn validate_output(&self, _events: &Events) -> Result<(), ProcessingError> {
+        Ok(())
+    }
+}
+
+// Rewrites fields the events already have: `set` replaces values with fixed ones of the
+// same kind, `redact` masks strings and `lowercase` folds them. Fields an event type does not
+// have are left alone, since the typed event could not carry them.
+pub struct TransformProcessor {
+    set: BTreeMap<String, Value>,
+    redact: Vec<String>,
+    lowercase: Vec<String>,
+}
+
+impl TransformProcessor {
+    pub fn new(set: BTreeMap<String, Value>, redact: Vec<String>, lowercase: Vec<String>) -> Self {
+        Self { set, redact, lowercase }
+    }
+}
+
+fn same_kind(current: &Value, new: &Value) -> bool {
+    current.is_null() || std::mem::discriminant(current) == std::mem::discriminant(new)
+}
+
+// Applies `change` to a string field or to every string of an array field
+fn map_strings(field: &mut Value, change: impl Fn(&str) -> String) {
+    match field {
+        Value::String(text) => *text = change(text),
+        Value::Array(items) => {
+            for item in items {
+                if let Value::String(text) = item {
+                    *text = change(text);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+impl DataProcessor<Events, Events> for TransformProcessor {
+    fn process(&self, mut events: Events) -> Result<Events, ProcessingError> {
+        for event in &mut events {
+            for (path, value) in &self.set {
+                if let Some(field) = field_mut(event, path) {
+                    if same_kind(field, value) {
+                        *field = value.clone();
+                    }
+                }
+            }
+            for path in &self.redact {
+                if let Some(field) = field_mut(event, path) {
+                    map_strings(field, |_| String::from(REDACTED));
+                }
+            }
+            for path in &self.lowercase {
+                if let Some(field) = field_mut(event, path) {
+                    map_strings(field, str::to_lowercase);
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    fn validate_input(&self, events: &Events) -> Result<(), ProcessingError> {
+        objects(events)
+    }
+
+    fn validate_output(&self, events: &Events) -> Result<(), ProcessingError> {
+        objects(events)
+    }
+}
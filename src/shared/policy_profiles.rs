@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// What a host is used for, which decides the collectors, paths and detection content it runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostRole {
+    Workstation,
+    Server,
+    DomainController,
+    Kiosk,
+}
+
+impl HostRole {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().replace('-', "_").as_str() {
+            "workstation" => Some(Self::Workstation),
+            "server" => Some(Self::Server),
+            "domain_controller" | "dc" => Some(Self::DomainController),
+            "kiosk" => Some(Self::Kiosk),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Workstation => "workstation",
+            Self::Server => "server",
+            Self::DomainController => "domain_controller",
+            Self::Kiosk => "kiosk",
+        }
+    }
+
+    // Overlay shipped with the agent, merged over the configuration file like os_overrides.
+    // `profiles.<role>` in the file replaces it for that role.
+    pub fn builtin_overlay(&self) -> &'static str {
+        match self {
+            Self::Workstation => WORKSTATION,
+            Self::Server => SERVER,
+            Self::DomainController => DOMAIN_CONTROLLER,
+            Self::Kiosk => KIOSK,
+        }
+    }
+}
+
+impl fmt::Display for HostRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// The configuration and built-in defaults already describe a workstation
+const WORKSTATION: &str = r#"
+usb:
+  enabled: true
+keylogging:
+  enabled: true
+"#;
+
+// No one sits at a server: removable media and keyboard hooks are noise, while web roots,
+// scheduled jobs and service binaries are where intrusions persist
+const SERVER: &str = r#"
+usb:
+  enabled: false
+keylogging:
+  enabled: false
+capture:
+  enabled: false
+paths:
+  - ${WINDIR}/System32
+  - ${WINDIR}/Temp
+  - C:/Program Files
+  - C:/Program Files (x86)
+  - ${PROGRAMDATA}
+  - C:/inetpub/wwwroot
+settings:
+  recursive: true
+  extensions: [.exe, .dll, .sys, .scr, .com, .msi, .bat, .cmd, .ps1, .psm1, .vbs, .vbe, .js, .jse, .wsf, .hta, .jar, .sh, .py, .so, .asp, .aspx, .ashx, .asmx, .php, .jsp, .jspx]
+detection:
+  rules:
+    - id: LSEDR-ROLE-SRV-0001
+      name: Web shell written to a web root
+      severity: High
+      source: file
+      match:
+        - field: path
+          regex: '(inetpub[\\/]wwwroot|[\\/]var[\\/]www)[\\/].*\.(aspx?|ashx|asmx|php|jspx?)$'
+        - field: event_type
+          in: [Created, Modified, Renamed]
+      tags: [persistence, T1505.003]
+os_overrides:
+  linux:
+    paths:
+      - /usr/bin
+      - /usr/sbin
+      - /usr/local/bin
+      - /etc/cron.d
+      - /etc/systemd/system
+      - /var/www
+      - /tmp
+"#;
+
+// A server whose directory database and logon scripts are the prize
+const DOMAIN_CONTROLLER: &str = r#"
+usb:
+  enabled: false
+keylogging:
+  enabled: false
+capture:
+  enabled: false
+directory:
+  enabled: true
+paths:
+  - ${WINDIR}/System32
+  - ${WINDIR}/Temp
+  - ${WINDIR}/NTDS
+  - ${WINDIR}/SYSVOL
+  - C:/Program Files
+  - ${PROGRAMDATA}
+settings:
+  recursive: true
+  extensions: [.exe, .dll, .sys, .scr, .com, .msi, .bat, .cmd, .ps1, .psm1, .vbs, .vbe, .js, .jse, .wsf, .hta, .jar, .dit, .xml, .ini]
+detection:
+  rules:
+    - id: LSEDR-ROLE-DC-0001
+      name: Copy of the Active Directory database
+      description: ntds.dit outside its own directory, e.g. from ntdsutil IFM or a shadow copy
+      severity: Critical
+      source: file
+      match:
+        - field: path
+          ends_with: ntds.dit
+        - field: path
+          regex: '[\\/]Windows[\\/]NTDS[\\/]'
+          negate: true
+      tags: [credential_access, T1003.003]
+    - id: LSEDR-ROLE-DC-0002
+      name: Script or policy file changed in SYSVOL
+      severity: High
+      source: file
+      match:
+        - field: path
+          regex: 'SYSVOL[\\/].*\.(bat|cmd|ps1|vbs|exe|dll|xml|ini)$'
+        - field: event_type
+          in: [Created, Modified, Renamed]
+      tags: [persistence, lateral_movement, T1484.001]
+    - id: LSEDR-ROLE-DC-0003
+      name: Directory database extraction tool
+      severity: Critical
+      source: process
+      match_any:
+        - - field: command
+            regex: 'ntdsutil.*(ifm|create full)'
+        - - field: command
+            regex: 'vssadmin.*create shadow'
+      tags: [credential_access, T1003.003]
+os_overrides:
+  linux:
+    paths:
+      - /usr/bin
+      - /usr/sbin
+      - /etc/samba
+      - /var/lib/samba/sysvol
+"#;
+
+// A locked-down shared device: nothing new should ever run, so every executable written
+// by the signed-in user is an alert
+const KIOSK: &str = r#"
+usb:
+  enabled: true
+  scan_executables: true
+keylogging:
+  enabled: true
+paths:
+  - ${USERPROFILE}/Downloads
+  - ${USERPROFILE}/Desktop
+  - ${USERPROFILE}/AppData/Local/Temp
+  - ${WINDIR}/Temp
+detection:
+  rules:
+    - id: LSEDR-ROLE-KIOSK-0001
+      name: Executable written on a kiosk
+      severity: High
+      source: file
+      match:
+        - field: path
+          regex: '\.(exe|dll|scr|com|msi|bat|cmd|ps1|vbs|hta|js|jar|sh)$'
+        - field: event_type
+          in: [Created, Renamed]
+      tags: [execution]
+os_overrides:
+  linux:
+    paths:
+      - ${HOME}/Downloads
+      - ${HOME}/Desktop
+      - /tmp
+"#;
+
+// ProductType tells workstations from member servers and domain controllers; a configured
+// assigned access (kiosk mode) profile takes precedence
+#[cfg(windows)]
+pub fn detect_role() -> HostRole {
+    use windows::core::HSTRING;
+    use windows::Win32::System::Registry::{
+        RegCloseKey, RegGetValueW, RegOpenKeyExW, HKEY, HKEY_LOCAL_MACHINE, KEY_READ, RRF_RT_REG_SZ,
+    };
+
+    const ASSIGNED_ACCESS: &str = "SOFTWARE\\Microsoft\\Windows\\AssignedAccessConfiguration\\Profiles";
+    const PRODUCT_OPTIONS: &str = "SYSTEM\\CurrentControlSet\\Control\\ProductOptions";
+
+    unsafe {
+        let mut key = HKEY::default();
+        if RegOpenKeyExW(HKEY_LOCAL_MACHINE, &HSTRING::from(ASSIGNED_ACCESS), 0, KEY_READ, &mut key).is_ok() {
+            RegCloseKey(key);
+            return HostRole::Kiosk;
+        }
+
+        let mut data = [0u16; 64];
+        let mut data_len = (data.len() * 2) as u32;
+        let status = RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            &HSTRING::from(PRODUCT_OPTIONS),
+            &HSTRING::from("ProductType"),
+            RRF_RT_REG_SZ,
+            None,
+            Some(data.as_mut_ptr() as *mut _),
+            Some(&mut data_len),
+        );
+        if status.is_err() {
+            return HostRole::Workstation;
+        }
+        let chars = (data_len as usize / 2).min(data.len());
+        match String::from_utf16_lossy(&data[..chars]).trim_end_matches('\0') {
+            "LanmanNT" => HostRole::DomainController,
+            "ServerNT" => HostRole::Server,
+            _ => HostRole::Workstation,
+        }
+    }
+}
+
+// A Samba AD DC is a domain controller; a host that does not boot into a graphical session
+// is a server. Kiosks cannot be told apart and are only selected explicitly.
+#[cfg(not(windows))]
+pub fn detect_role() -> HostRole {
+    use std::fs;
+    use std::path::Path;
+
+    if let Ok(smb_conf) = fs::read_to_string("/etc/samba/smb.conf") {
+        let is_dc = smb_conf.lines().any(|line| {
+            let line = line.trim().to_lowercase();
+            line.starts_with("server role") && line.contains("domain controller")
+        });
+        if is_dc {
+            return HostRole::DomainController;
+        }
+    }
+
+    let graphical = match fs::read_link("/etc/systemd/system/default.target") {
+        Ok(target) => target.ends_with("graphical.target"),
+        Err(_) => ["/usr/share/xsessions", "/usr/share/wayland-sessions"]
+            .iter()
+            .any(|dir| fs::read_dir(dir).map(|mut entries| entries.next().is_some()).unwrap_or(false)),
+    };
+    if graphical || Path::new("/System/Library").is_dir() {
+        HostRole::Workstation
+    } else {
+        HostRole::Server
+    }
+}
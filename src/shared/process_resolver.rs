@@ -0,0 +1,161 @@
+use crate::shared::signature::{self, SignatureStatus};
+use crate::shared::user_resolver::UserResolver;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+const DEFAULT_TTL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessDetails {
+    pub pid: u32,
+    pub name: String,
+    pub path: Option<String>,
+    pub sha256: Option<String>,
+    pub user: Option<String>,
+    pub parent_pid: Option<u32>,
+}
+
+struct ResolverState {
+    sys: System,
+    entries: HashMap<u32, (Instant, ProcessDetails)>,
+    // Executable hashes and signature checks keyed by path and modification time
+    hashes: HashMap<(PathBuf, SystemTime), String>,
+    signatures: HashMap<(PathBuf, SystemTime), SignatureStatus>,
+}
+
+// PID -> process metadata lookups shared by every collector, cached for a short TTL
+// so bursts of events from the same process only hit the OS once.
+pub struct ProcessResolver {
+    ttl: Duration,
+    users: Arc<UserResolver>,
+    state: Mutex<ResolverState>,
+}
+
+impl ProcessResolver {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            users: UserResolver::global(),
+            state: Mutex::new(ResolverState {
+                sys: System::new(),
+                entries: HashMap::new(),
+                hashes: HashMap::new(),
+                signatures: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn global() -> Arc<ProcessResolver> {
+        static GLOBAL: OnceLock<Arc<ProcessResolver>> = OnceLock::new();
+        GLOBAL
+            .get_or_init(|| Arc::new(ProcessResolver::new(DEFAULT_TTL)))
+            .clone()
+    }
+
+    pub fn resolve(&self, pid: u32) -> Option<ProcessDetails> {
+        let mut state = self.state.lock().ok()?;
+        let now = Instant::now();
+
+        if let Some((resolved_at, details)) = state.entries.get(&pid) {
+            if now.duration_since(*resolved_at) < self.ttl {
+                return Some(details.clone());
+            }
+        }
+
+        let sys_pid = Pid::from_u32(pid);
+        state.sys.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[sys_pid]),
+            true,
+            ProcessRefreshKind::nothing()
+                .with_exe(UpdateKind::OnlyIfNotSet)
+                .with_user(UpdateKind::OnlyIfNotSet),
+        );
+
+        let (name, exe, user_id, parent_pid) = match state.sys.process(sys_pid) {
+            Some(process) => (
+                process.name().to_string_lossy().into_owned(),
+                process.exe().map(Path::to_path_buf),
+                process.user_id().cloned(),
+                process.parent().map(|parent| parent.as_u32()),
+            ),
+            None => {
+                state.entries.remove(&pid);
+                return None;
+            }
+        };
+
+        // Token owner on Windows (domain-qualified), uid -> name elsewhere
+        let token_owner = if cfg!(target_os = "windows") {
+            self.users.display_process_owner(pid)
+        } else {
+            None
+        };
+        let user = token_owner.or_else(|| user_id.map(|uid| self.users.display_id(&uid.to_string())));
+
+        let sha256 = exe.as_deref().and_then(|path| Self::hash_executable(&mut state.hashes, path));
+
+        let details = ProcessDetails {
+            pid,
+            name,
+            path: exe.map(|path| path.to_string_lossy().into_owned()),
+            sha256,
+            user,
+            parent_pid,
+        };
+
+        // Drop stale entries so the cache does not grow with every PID ever seen
+        let ttl = self.ttl;
+        state.entries.retain(|_, (resolved_at, _)| now.duration_since(*resolved_at) < ttl);
+        state.entries.insert(pid, (now, details.clone()));
+        Some(details)
+    }
+
+    pub fn executable_sha256(&self, path: &Path) -> Option<String> {
+        let mut state = self.state.lock().ok()?;
+        Self::hash_executable(&mut state.hashes, path)
+    }
+
+    // Authenticode status, Windows only. The lock is not held while WinVerifyTrust runs.
+    pub fn executable_signature(&self, path: &Path) -> Option<SignatureStatus> {
+        if !cfg!(target_os = "windows") {
+            return None;
+        }
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+        let key = (path.to_path_buf(), modified);
+        if let Some(status) = self.state.lock().ok()?.signatures.get(&key) {
+            return Some(*status);
+        }
+
+        let status = signature::verify_file(path)?;
+        debug!("Verified signature of {}: {:?}", path.display(), status);
+        let mut state = self.state.lock().ok()?;
+        state.signatures.retain(|(cached_path, _), _| cached_path != path);
+        state.signatures.insert(key, status);
+        Some(status)
+    }
+
+    fn hash_executable(hashes: &mut HashMap<(PathBuf, SystemTime), String>, path: &Path) -> Option<String> {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?;
+        let key = (path.to_path_buf(), modified);
+        if let Some(hash) = hashes.get(&key) {
+            return Some(hash.clone());
+        }
+
+        let mut file = fs::File::open(path).ok()?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher).ok()?;
+        let hash = format!("{:x}", hasher.finalize());
+
+        debug!("Hashed executable {}", path.display());
+        hashes.retain(|(cached_path, _), _| cached_path != path);
+        hashes.insert(key, hash.clone());
+        Some(hash)
+    }
+}
@@ -0,0 +1,171 @@
+use bytes::{Buf, BufMut};
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::Status;
+
+// Protobuf wire format, encoded and walked by hand for the few gRPC services the agent speaks
+// (OTLP export, the control server) instead of generating code from .proto files
+
+const VARINT: u8 = 0;
+const FIXED64: u8 = 1;
+const LENGTH_DELIMITED: u8 = 2;
+
+#[derive(Debug, Default, Clone)]
+pub struct Message {
+    buf: Vec<u8>,
+}
+
+impl Message {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    fn raw_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.raw_varint(((field as u64) << 3) | wire_type as u64);
+    }
+
+    pub fn varint(mut self, field: u32, value: u64) -> Self {
+        self.key(field, VARINT);
+        self.raw_varint(value);
+        self
+    }
+
+    pub fn int64(self, field: u32, value: i64) -> Self {
+        self.varint(field, value as u64)
+    }
+
+    pub fn bool(self, field: u32, value: bool) -> Self {
+        self.varint(field, value as u64)
+    }
+
+    pub fn fixed64(mut self, field: u32, value: u64) -> Self {
+        self.key(field, FIXED64);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn double(mut self, field: u32, value: f64) -> Self {
+        self.key(field, FIXED64);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn bytes(mut self, field: u32, value: &[u8]) -> Self {
+        self.key(field, LENGTH_DELIMITED);
+        self.raw_varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+        self
+    }
+
+    pub fn string(self, field: u32, value: &str) -> Self {
+        self.bytes(field, value.as_bytes())
+    }
+
+    pub fn message(self, field: u32, value: &Message) -> Self {
+        self.bytes(field, &value.buf)
+    }
+
+    pub fn messages<'a>(self, field: u32, values: impl IntoIterator<Item = &'a Message>) -> Self {
+        values.into_iter().fold(self, |message, value| message.message(field, value))
+    }
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*position)?;
+        *position += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+// Walks the top-level fields of a message, returning each field number with its varint value
+// or length-delimited payload
+pub fn fields(bytes: &[u8]) -> Vec<(u32, u64, &[u8])> {
+    let mut fields = Vec::new();
+    let mut position = 0;
+    while position < bytes.len() {
+        let Some(key) = read_varint(bytes, &mut position) else {
+            break;
+        };
+        let field = (key >> 3) as u32;
+        match (key & 0x7) as u8 {
+            VARINT => match read_varint(bytes, &mut position) {
+                Some(value) => fields.push((field, value, &bytes[..0])),
+                None => break,
+            },
+            FIXED64 => position += 8,
+            LENGTH_DELIMITED => {
+                let Some(length) = read_varint(bytes, &mut position) else {
+                    break;
+                };
+                let end = position.saturating_add(length as usize);
+                if end > bytes.len() {
+                    break;
+                }
+                fields.push((field, length, &bytes[position..end]));
+                position = end;
+            }
+            // fixed32
+            5 => position += 4,
+            _ => break,
+        }
+    }
+    fields
+}
+
+// Passes messages that are already protobuf-encoded straight through to the wire, for
+// clients and servers alike
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EncodedCodec;
+
+impl Codec for EncodedCodec {
+    type Encode = Vec<u8>;
+    type Decode = Vec<u8>;
+    type Encoder = EncodedCodec;
+    type Decoder = EncodedCodec;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        EncodedCodec
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        EncodedCodec
+    }
+}
+
+impl Encoder for EncodedCodec {
+    type Item = Vec<u8>;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        dst.put_slice(&item);
+        Ok(())
+    }
+}
+
+impl Decoder for EncodedCodec {
+    type Item = Vec<u8>;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let mut item = vec![0; src.remaining()];
+        src.copy_to_slice(&mut item);
+        Ok(Some(item))
+    }
+}
@@ -0,0 +1,278 @@
+use crate::utils::load_config_section;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+// Hex characters of the HMAC kept in a pseudonym
+const PSEUDONYM_LENGTH: usize = 16;
+// Written in place of a name when no site key is available, so nothing leaks
+const REDACTED: &str = "redacted";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    pub enabled: bool,
+    // Environment variable holding the site key; the same key on every host keeps
+    // pseudonyms consistent across the fleet
+    pub site_key_env: String,
+    // Document fields holding account names, matched at any depth
+    pub user_fields: Vec<String>,
+    // Document fields holding host names, matched at any depth
+    pub host_fields: Vec<String>,
+    // Built-in accounts left readable, compared case-insensitively
+    pub keep_values: Vec<String>,
+    // Local pseudonym -> name mapping for authorized responders
+    pub mapping_file: String,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            site_key_env: String::from("LSEDR_SITE_KEY"),
+            user_fields: vec![
+                String::from("user"),
+                String::from("username"),
+                String::from("user_sid"),
+                String::from("account"),
+                String::from("user_name"),
+            ],
+            host_fields: vec![
+                String::from("source"),
+                String::from("hostname"),
+                String::from("fqdn"),
+            ],
+            keep_values: vec![
+                String::from("root"),
+                String::from("SYSTEM"),
+                String::from("NT AUTHORITY\\SYSTEM"),
+                String::from("NT AUTHORITY\\LOCAL SERVICE"),
+                String::from("NT AUTHORITY\\NETWORK SERVICE"),
+                String::from("LocalSystem"),
+                String::from("NT AUTHORITY\\LocalService"),
+                String::from("NT AUTHORITY\\NetworkService"),
+            ],
+            mapping_file: String::from("data/privacy/pseudonyms.jsonl"),
+        }
+    }
+}
+
+impl PrivacyConfig {
+    pub fn load() -> Self {
+        load_config_section("privacy")
+    }
+
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.enabled {
+            if self.site_key_env.trim().is_empty() {
+                problems.push(String::from("privacy.site_key_env is empty"));
+            }
+            if self.mapping_file.trim().is_empty() {
+                problems.push(String::from("privacy.mapping_file is empty"));
+            }
+        }
+        problems
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PseudonymKind {
+    User,
+    Host,
+}
+
+impl PseudonymKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PseudonymKind::User => "user",
+            PseudonymKind::Host => "host",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PseudonymEntry {
+    pub pseudonym: String,
+    pub kind: PseudonymKind,
+    pub value: String,
+    pub first_seen: DateTime<Utc>,
+}
+
+// Replaces user and host names in outgoing documents with `user-<hmac>` / `host-<hmac>`,
+// keyed by a site secret so the same name maps to the same pseudonym on every host and
+// across restarts. The names behind the pseudonyms stay on the host in the mapping file
+// and are only handed out through the resolve_pseudonym response action.
+pub struct Pseudonymizer {
+    config: PrivacyConfig,
+    site_key: Option<Vec<u8>>,
+    mapping: Mutex<HashMap<String, PseudonymEntry>>,
+}
+
+impl Pseudonymizer {
+    pub fn new() -> Self {
+        Self::with_config(PrivacyConfig::load())
+    }
+
+    pub fn with_config(config: PrivacyConfig) -> Self {
+        let site_key = std::env::var(&config.site_key_env)
+            .ok()
+            .filter(|key| !key.is_empty())
+            .map(String::into_bytes);
+
+        let mut mapping = HashMap::new();
+        if config.enabled {
+            if site_key.is_none() {
+                error!(
+                    "Privacy mode is enabled but ${} holds no site key, user and host names will be redacted",
+                    config.site_key_env
+                );
+            }
+            mapping = Self::load_mapping(Path::new(&config.mapping_file));
+            info!("Pseudonymizing user and host names ({} known pseudonyms)", mapping.len());
+        }
+
+        Self {
+            config,
+            site_key,
+            mapping: Mutex::new(mapping),
+        }
+    }
+
+    pub fn global() -> Arc<Pseudonymizer> {
+        static GLOBAL: OnceLock<Arc<Pseudonymizer>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(Pseudonymizer::new())).clone()
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    fn load_mapping(path: &Path) -> HashMap<String, PseudonymEntry> {
+        let Ok(content) = fs::read_to_string(path) else {
+            return HashMap::new();
+        };
+        content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<PseudonymEntry>(line).ok())
+            .map(|entry| (entry.pseudonym.clone(), entry))
+            .collect()
+    }
+
+    fn append_mapping(&self, entry: &PseudonymEntry) {
+        let path = PathBuf::from(&self.config.mapping_file);
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let written = options.open(&path).and_then(|mut file| {
+            let line = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+            writeln!(file, "{}", line)
+        });
+        if let Err(e) = written {
+            warn!("Failed to record pseudonym in {}: {}", path.display(), e);
+        }
+    }
+
+    pub fn pseudonym(&self, kind: PseudonymKind, value: &str) -> String {
+        let Some(key) = &self.site_key else {
+            return String::from(REDACTED);
+        };
+        let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(key) else {
+            return String::from(REDACTED);
+        };
+        // Names differ only in case between sources (HOST01 vs host01), so they share a pseudonym
+        mac.update(kind.as_str().as_bytes());
+        mac.update(b":");
+        mac.update(value.to_lowercase().as_bytes());
+        let digest = hex::encode(mac.finalize().into_bytes());
+        let pseudonym = format!("{}-{}", kind.as_str(), &digest[..PSEUDONYM_LENGTH]);
+
+        if let Ok(mut mapping) = self.mapping.lock() {
+            if !mapping.contains_key(&pseudonym) {
+                let entry = PseudonymEntry {
+                    pseudonym: pseudonym.clone(),
+                    kind,
+                    value: value.to_string(),
+                    first_seen: Utc::now(),
+                };
+                self.append_mapping(&entry);
+                mapping.insert(pseudonym.clone(), entry);
+            }
+        }
+        pseudonym
+    }
+
+    // The name to ship for `value`: unchanged when privacy mode is off, for kept values and
+    // for values that already are pseudonyms, e.g. a document re-shipped from the spool
+    pub fn pseudonymize(&self, kind: PseudonymKind, value: &str) -> String {
+        if !self.config.enabled || value.is_empty() || value == REDACTED {
+            return value.to_string();
+        }
+        if self.config.keep_values.iter().any(|keep| keep.eq_ignore_ascii_case(value)) {
+            return value.to_string();
+        }
+        if self.mapping.lock().is_ok_and(|mapping| mapping.contains_key(value)) {
+            return value.to_string();
+        }
+        self.pseudonym(kind, value)
+    }
+
+    fn replace(&self, kind: PseudonymKind, value: &mut Value) {
+        let Value::String(text) = value else {
+            return;
+        };
+        *text = self.pseudonymize(kind, text);
+    }
+
+    // Pseudonymizes the configured fields of a serialized document in place
+    pub fn apply(&self, document: &mut Value) {
+        if !self.config.enabled {
+            return;
+        }
+        match document {
+            Value::Object(fields) => {
+                for (field, value) in fields.iter_mut() {
+                    if self.config.user_fields.contains(field) {
+                        self.replace(PseudonymKind::User, value);
+                    } else if self.config.host_fields.contains(field) {
+                        self.replace(PseudonymKind::Host, value);
+                    } else {
+                        self.apply(value);
+                    }
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.apply(value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn resolve(&self, pseudonym: &str) -> Option<PseudonymEntry> {
+        self.mapping.lock().ok()?.get(pseudonym).cloned()
+    }
+}
+
+impl Default for Pseudonymizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,259 @@
+use crate::features::control::AgentControl;
+use crate::shared::config::AgentConfig;
+use crate::shared::host_identity::HostIdentityService;
+use hmac::{Hmac, Mac};
+use log::{debug, info, warn};
+use reqwest::header::{ETAG, IF_NONE_MATCH, USER_AGENT};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use sha2::Sha256;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
+
+// Top-level section configuring the pull; always taken from the local file, so a served
+// document cannot point the agent at another server
+pub const REMOTE_CONFIG_KEY: &str = "remote_config";
+// Response header with the hex HMAC-SHA256, under the shared signing key, of the version,
+// a newline and the body
+pub const SIGNATURE_HEADER: &str = "x-lsedr-signature";
+// Response header with the document version, an integer the server raises with every change
+// (e.g. the publication time in seconds), so an older signed document cannot be replayed
+pub const VERSION_HEADER: &str = "x-lsedr-config-version";
+// Request header naming the agent, for servers that hand out per-host documents
+pub const HOST_HEADER: &str = "x-lsedr-host";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteConfigSettings {
+    pub enabled: bool,
+    // HTTPS endpoint serving the configuration as YAML
+    pub url: String,
+    pub interval_secs: u64,
+    pub timeout_secs: u64,
+    // Environment variable holding the key the server signs documents with
+    pub signing_key_env: String,
+    // PEM CA for a server certificate that does not chain to the system roots
+    pub ca_file: Option<String>,
+    // Version of the last document taken, kept across restarts
+    pub version_file: String,
+}
+
+impl Default for RemoteConfigSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            interval_secs: 300,
+            timeout_secs: 30,
+            signing_key_env: String::from("LSEDR_CONFIG_KEY"),
+            ca_file: None,
+            version_file: String::from("data/remote_config.version"),
+        }
+    }
+}
+
+// A verified document from the management server and the URL it came from
+#[derive(Debug, Clone)]
+pub struct RemoteDocument {
+    pub url: String,
+    pub version: u64,
+    pub document: Value,
+}
+
+// The last document accepted from the server, merged over the local file by AgentConfig::load
+static REMOTE_DOCUMENT: RwLock<Option<RemoteDocument>> = RwLock::new(None);
+
+pub fn remote_document() -> Option<RemoteDocument> {
+    REMOTE_DOCUMENT.read().ok().and_then(|remote| remote.clone())
+}
+
+fn install_remote_document(remote: RemoteDocument) {
+    match REMOTE_DOCUMENT.write() {
+        Ok(mut installed) => *installed = Some(remote),
+        Err(_) => warn!("Remote configuration lock is poisoned, ignoring the new document"),
+    }
+}
+
+// Pulls the agent configuration from the management server. A document is only taken when
+// its signature verifies and the configuration it produces is valid; until then, and
+// whenever the server is unreachable, the agent keeps what it runs on, the local file first.
+pub struct RemoteConfigClient {
+    settings: RemoteConfigSettings,
+    key: Vec<u8>,
+    client: reqwest::Client,
+    host: String,
+    // Validator of the document last taken, sent back so an unchanged one is not sent again
+    etag: Option<String>,
+    // Version of the document last taken; older ones are refused
+    version: u64,
+}
+
+impl RemoteConfigClient {
+    pub fn new(settings: RemoteConfigSettings) -> Result<Self, String> {
+        let key = std::env::var(&settings.signing_key_env)
+            .ok()
+            .filter(|key| !key.is_empty())
+            .map(String::into_bytes)
+            .ok_or_else(|| format!("No configuration signing key found in ${}", settings.signing_key_env))?;
+        if !url::Url::parse(&settings.url).is_ok_and(|url| url.scheme() == "https") {
+            return Err(format!("Remote configuration URL '{}' is not an https:// URL", settings.url));
+        }
+
+        // Also refuses redirects to plain HTTP
+        let mut builder = reqwest::Client::builder()
+            .https_only(true)
+            .timeout(Duration::from_secs(settings.timeout_secs.max(1)));
+        if let Some(ca_file) = &settings.ca_file {
+            let pem = std::fs::read(ca_file).map_err(|e| format!("Failed to read {}: {}", ca_file, e))?;
+            let certificate = reqwest::Certificate::from_pem(&pem).map_err(|e| format!("Invalid CA file {}: {}", ca_file, e))?;
+            builder = builder.add_root_certificate(certificate);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| format!("Failed to build the remote configuration client: {}", e))?;
+        let version = std::fs::read_to_string(&settings.version_file)
+            .ok()
+            .and_then(|content| content.trim().parse().ok())
+            .unwrap_or(0);
+
+        Ok(Self {
+            settings,
+            key,
+            client,
+            host: HostIdentityService::global().name(),
+            etag: None,
+            version,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.settings.url
+    }
+
+    fn verify(&self, version: u64, body: &[u8], signature: &str) -> Result<(), String> {
+        let signature = hex::decode(signature.trim()).map_err(|_| String::from("Configuration signature is not valid hex"))?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).map_err(|e| e.to_string())?;
+        mac.update(format!("{}\n", version).as_bytes());
+        mac.update(body);
+        mac.verify_slice(&signature)
+            .map_err(|_| format!("Configuration from {} has an invalid signature", self.settings.url))
+    }
+
+    // Fetches the document and installs it for AgentConfig::load when it changed, verifies and
+    // makes a valid configuration; returns whether a new document was installed
+    pub async fn pull(&mut self) -> Result<bool, String> {
+        let url = &self.settings.url;
+        let mut request = self
+            .client
+            .get(url)
+            .header(USER_AGENT, concat!("lsedr/", env!("CARGO_PKG_VERSION")))
+            .header(HOST_HEADER, &self.host);
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await.map_err(|e| format!("Failed to reach {}: {}", url, e))?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(false);
+        }
+        if !response.status().is_success() {
+            return Err(format!("{} answered {}", url, response.status()));
+        }
+
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let signature = response
+            .headers()
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| format!("Configuration from {} is not signed", url))?;
+        let version: u64 = response
+            .headers()
+            .get(VERSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| format!("Configuration from {} has no version", url))?
+            .trim()
+            .parse()
+            .map_err(|_| format!("Configuration from {} has an invalid version", url))?;
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read the configuration from {}: {}", url, e))?;
+        self.verify(version, &body, &signature)?;
+        if version < self.version {
+            return Err(format!(
+                "Configuration from {} is version {}, older than the installed version {}",
+                url, version, self.version
+            ));
+        }
+
+        let document: Value =
+            serde_yaml::from_slice(&body).map_err(|e| format!("Failed to parse the configuration from {}: {}", url, e))?;
+        if !document.is_mapping() {
+            return Err(format!("Configuration from {} is not a mapping", url));
+        }
+        if let Some(installed) = remote_document().filter(|installed| installed.url == *url) {
+            // Same content under a new validator, e.g. after the server restarted
+            if installed.version == version && installed.document == document {
+                self.etag = etag;
+                return Ok(false);
+            }
+            // A version names one document
+            if installed.version == version {
+                return Err(format!("Configuration from {} changed without a new version {}", url, version));
+            }
+        }
+
+        let remote = RemoteDocument {
+            url: url.clone(),
+            version,
+            document,
+        };
+        AgentConfig::read(Some(&remote)).map_err(|e| format!("Rejected the configuration from {}: {}", url, e))?;
+        install_remote_document(remote);
+        self.etag = etag;
+        self.record_version(version);
+        Ok(true)
+    }
+
+    fn record_version(&mut self, version: u64) {
+        self.version = version;
+        let path = std::path::Path::new(&self.settings.version_file);
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Err(e) = std::fs::write(path, version.to_string()) {
+            warn!("Failed to record the configuration version in {}: {}", path.display(), e);
+        }
+    }
+
+    // Pulls every interval_secs, the first time one interval from now, and restarts the
+    // collectors on each new document. Must be called within the Tokio runtime.
+    pub fn spawn(mut self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = time::interval(Duration::from_secs(self.settings.interval_secs.max(1)));
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                match self.pull().await {
+                    Ok(true) => match AgentConfig::load() {
+                        Ok(config) => {
+                            info!("Pulled a new configuration from {}, restarting the collectors", self.settings.url);
+                            AgentControl::global().request_reload(config);
+                        }
+                        Err(e) => warn!("Failed to load the configuration pulled from {}: {}", self.settings.url, e),
+                    },
+                    Ok(false) => debug!("Configuration at {} is unchanged", self.settings.url),
+                    Err(e) => warn!("{}, keeping the current configuration", e),
+                }
+            }
+        })
+    }
+}
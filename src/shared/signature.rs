@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use std::ffi::c_void;
+use std::path::Path;
+use windows::core::{GUID, PCWSTR};
+use windows::Win32::Foundation::{CloseHandle, GENERIC_READ, HANDLE, HWND};
+use windows::Win32::Security::Cryptography::Catalog::{
+    CryptCATAdminAcquireContext2, CryptCATAdminCalcHashFromFileHandle2, CryptCATAdminEnumCatalogFromHash,
+    CryptCATAdminReleaseCatalogContext, CryptCATAdminReleaseContext, CryptCATCatalogInfoFromContext, CATALOG_INFO,
+};
+use windows::Win32::Security::Cryptography::BCRYPT_SHA256_ALGORITHM;
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_READ, OPEN_EXISTING,
+};
+use windows::Win32::Security::WinTrust::{
+    WinVerifyTrust, WINTRUST_ACTION_GENERIC_VERIFY_V2, WINTRUST_CATALOG_INFO, WINTRUST_DATA, WINTRUST_DATA_0,
+    WINTRUST_DATA_PROVIDER_FLAGS, WINTRUST_DATA_UICONTEXT, WINTRUST_FILE_INFO, WTD_CACHE_ONLY_URL_RETRIEVAL,
+    WTD_CHOICE_CATALOG, WTD_CHOICE_FILE, WTD_REVOKE_NONE, WTD_STATEACTION_CLOSE, WTD_STATEACTION_VERIFY,
+    WTD_UI_NONE,
+};
+
+const TRUST_E_NOSIGNATURE: u32 = 0x800B_0100;
+const TRUST_E_SUBJECT_FORM_UNKNOWN: u32 = 0x800B_0003;
+const TRUST_E_PROVIDER_UNKNOWN: u32 = 0x800B_0001;
+const TRUST_E_EXPLICIT_DISTRUST: u32 = 0x800B_0111;
+const TRUST_E_BAD_DIGEST: u32 = 0x8009_6010;
+const CERT_E_EXPIRED: u32 = 0x800B_0101;
+const CERT_E_UNTRUSTEDROOT: u32 = 0x800B_0109;
+const CERT_E_CHAINING: u32 = 0x800B_010A;
+const CERT_E_REVOKED: u32 = 0x800B_010C;
+const CRYPT_E_SECURITY_SETTINGS: u32 = 0x8009_2026;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    // Embedded or catalog signature that chains to a trusted root
+    Signed,
+    Unsigned,
+    // Signed, but by a certificate that is not trusted, was revoked or explicitly distrusted
+    Untrusted,
+    Expired,
+    // The file does not match its signature
+    Invalid,
+    // Verification itself failed
+    Error,
+}
+
+impl SignatureStatus {
+    fn from_trust_result(result: i32) -> Self {
+        match result as u32 {
+            0 => SignatureStatus::Signed,
+            TRUST_E_NOSIGNATURE | TRUST_E_SUBJECT_FORM_UNKNOWN | TRUST_E_PROVIDER_UNKNOWN => SignatureStatus::Unsigned,
+            CERT_E_UNTRUSTEDROOT | CERT_E_CHAINING | CERT_E_REVOKED | TRUST_E_EXPLICIT_DISTRUST
+            | CRYPT_E_SECURITY_SETTINGS => SignatureStatus::Untrusted,
+            CERT_E_EXPIRED => SignatureStatus::Expired,
+            TRUST_E_BAD_DIGEST => SignatureStatus::Invalid,
+            _ => SignatureStatus::Error,
+        }
+    }
+}
+
+fn wide(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+// Runs WinVerifyTrust once for the given subject and releases its state again. Revocation
+// is not checked online so an agent without internet access does not stall on every file.
+unsafe fn verify_trust(union_choice: WINTRUST_DATA_0, catalog: bool) -> i32 {
+    let mut data = WINTRUST_DATA {
+        cbStruct: std::mem::size_of::<WINTRUST_DATA>() as u32,
+        dwUIChoice: WTD_UI_NONE,
+        fdwRevocationChecks: WTD_REVOKE_NONE,
+        dwUnionChoice: if catalog { WTD_CHOICE_CATALOG } else { WTD_CHOICE_FILE },
+        Anonymous: union_choice,
+        dwStateAction: WTD_STATEACTION_VERIFY,
+        dwProvFlags: WINTRUST_DATA_PROVIDER_FLAGS(WTD_CACHE_ONLY_URL_RETRIEVAL.0),
+        dwUIContext: WINTRUST_DATA_UICONTEXT(0),
+        ..std::mem::zeroed()
+    };
+    let mut action: GUID = WINTRUST_ACTION_GENERIC_VERIFY_V2;
+    let result = WinVerifyTrust(HWND(-1), &mut action, &mut data as *mut WINTRUST_DATA as *mut c_void);
+
+    data.dwStateAction = WTD_STATEACTION_CLOSE;
+    WinVerifyTrust(HWND(-1), &mut action, &mut data as *mut WINTRUST_DATA as *mut c_void);
+    result
+}
+
+// Most system binaries carry no embedded signature, their hash is listed in a signed
+// catalog instead. Returns None when no catalog lists the file.
+unsafe fn verify_catalog(wide_path: &[u16]) -> Option<i32> {
+    let handle = CreateFileW(
+        PCWSTR(wide_path.as_ptr()),
+        GENERIC_READ.0,
+        FILE_SHARE_READ,
+        None,
+        OPEN_EXISTING,
+        FILE_FLAGS_AND_ATTRIBUTES(0),
+        HANDLE::default(),
+    )
+    .ok()?;
+
+    let mut admin = 0isize;
+    if !CryptCATAdminAcquireContext2(&mut admin, None, BCRYPT_SHA256_ALGORITHM, None, 0).as_bool() {
+        CloseHandle(handle);
+        return None;
+    }
+
+    let mut length = 0u32;
+    CryptCATAdminCalcHashFromFileHandle2(admin, handle, &mut length, None, 0);
+    let mut hash = vec![0u8; length as usize];
+    let result = if length > 0
+        && CryptCATAdminCalcHashFromFileHandle2(admin, handle, &mut length, Some(hash.as_mut_ptr()), 0).as_bool()
+    {
+        let catalog = CryptCATAdminEnumCatalogFromHash(admin, &hash, 0, None);
+        if catalog == 0 {
+            None
+        } else {
+            let mut info = CATALOG_INFO {
+                cbStruct: std::mem::size_of::<CATALOG_INFO>() as u32,
+                wszCatalogFile: [0; 260],
+            };
+            let result = if CryptCATCatalogInfoFromContext(catalog, &mut info, 0).as_bool() {
+                let tag = wide(&hex::encode_upper(&hash));
+                let mut catalog_info = WINTRUST_CATALOG_INFO {
+                    cbStruct: std::mem::size_of::<WINTRUST_CATALOG_INFO>() as u32,
+                    pcwszCatalogFilePath: PCWSTR(info.wszCatalogFile.as_ptr()),
+                    pcwszMemberTag: PCWSTR(tag.as_ptr()),
+                    pcwszMemberFilePath: PCWSTR(wide_path.as_ptr()),
+                    hMemberFile: handle,
+                    pbCalculatedFileHash: hash.as_mut_ptr(),
+                    cbCalculatedFileHash: hash.len() as u32,
+                    hCatAdmin: admin,
+                    ..std::mem::zeroed()
+                };
+                Some(verify_trust(WINTRUST_DATA_0 { pCatalog: &mut catalog_info }, true))
+            } else {
+                None
+            };
+            CryptCATAdminReleaseCatalogContext(admin, catalog, 0);
+            result
+        }
+    } else {
+        None
+    };
+
+    CryptCATAdminReleaseContext(admin, 0);
+    CloseHandle(handle);
+    result
+}
+
+// Authenticode status of an executable, embedded signature first, then the system catalogs.
+// Always None off Windows.
+pub fn verify_file(path: &Path) -> Option<SignatureStatus> {
+    if !cfg!(target_os = "windows") {
+        return None;
+    }
+
+    let wide_path = wide(&path.to_string_lossy());
+    let embedded = unsafe {
+        let mut file_info = WINTRUST_FILE_INFO {
+            cbStruct: std::mem::size_of::<WINTRUST_FILE_INFO>() as u32,
+            pcwszFilePath: PCWSTR(wide_path.as_ptr()),
+            ..std::mem::zeroed()
+        };
+        verify_trust(WINTRUST_DATA_0 { pFile: &mut file_info }, false)
+    };
+
+    let status = SignatureStatus::from_trust_result(embedded);
+    if status != SignatureStatus::Unsigned {
+        return Some(status);
+    }
+    let catalog = unsafe { verify_catalog(&wide_path) };
+    Some(catalog.map(SignatureStatus::from_trust_result).unwrap_or(status))
+}
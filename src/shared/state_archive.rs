@@ -0,0 +1,258 @@
+use crate::shared::baseline::BaselineConfig;
+use crate::shared::config::AgentConfig;
+use crate::shared::error::StateArchiveError;
+use crate::shared::host_identity::{HostIdentity, HostIdentityService};
+use crate::shared::pseudonymizer::PrivacyConfig;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+// File header, also authenticated with the contents
+const MAGIC: &[u8] = b"LSEDR-STATE1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const PBKDF2_ROUNDS: u32 = 600_000;
+const FORMAT: u32 = 1;
+
+// Where one kind of state lives on this machine, by the configuration
+struct Location {
+    name: &'static str,
+    path: PathBuf,
+    directory: bool,
+}
+
+// Everything the agent keeps across restarts: the baseline store (first run, autoruns, file
+// hashes, connection first-seen times, reported IOCs), the spool and deferred queues holding
+// documents not yet shipped, and the pseudonym mapping
+fn locations(config: &AgentConfig) -> Vec<Location> {
+    let storage = &config.storage;
+    let directory = |name, path: &str| Location {
+        name,
+        path: PathBuf::from(path),
+        directory: true,
+    };
+    vec![
+        directory("baseline", &BaselineConfig::load().directory),
+        directory("spool", &storage.spool.directory),
+        directory("deferred", &storage.shipping.queue.directory),
+        directory("failover_journal", &storage.failover.journal.directory),
+        directory("failover_deferred", &storage.failover.shipping.queue.directory),
+        Location {
+            name: "pseudonyms",
+            path: PathBuf::from(PrivacyConfig::load().mapping_file),
+            directory: false,
+        },
+    ]
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchiveEntry {
+    location: String,
+    // Below the location's directory, '/'-separated; empty for a location that is a file
+    path: String,
+    // Base64
+    data: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StateArchive {
+    format: u32,
+    agent_version: String,
+    exported_at: DateTime<Utc>,
+    // Host the state was exported from
+    identity: HostIdentity,
+    entries: Vec<ArchiveEntry>,
+}
+
+// What an export wrote or an import restored
+#[derive(Debug, Clone)]
+pub struct StateSummary {
+    pub exported_at: DateTime<Utc>,
+    pub identity: HostIdentity,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+fn read_error(path: &Path) -> impl FnOnce(std::io::Error) -> StateArchiveError + '_ {
+    move |e| StateArchiveError::Read(path.display().to_string(), e)
+}
+
+fn write_error(path: &Path) -> impl FnOnce(std::io::Error) -> StateArchiveError + '_ {
+    move |e| StateArchiveError::Write(path.display().to_string(), e)
+}
+
+// Every file below `directory`, as paths relative to it; half-written .tmp files are left out
+fn files_below(directory: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<(), StateArchiveError> {
+    let current = directory.join(relative);
+    for entry in fs::read_dir(&current).map_err(read_error(&current))? {
+        let entry = entry.map_err(read_error(&current))?;
+        let path = relative.join(entry.file_name());
+        let kind = entry.file_type().map_err(read_error(&current))?;
+        if kind.is_dir() {
+            files_below(directory, &path, files)?;
+        } else if kind.is_file() && path.extension().is_none_or(|extension| extension != "tmp") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl Location {
+    // Where the file at `relative` below the location lives; a location that is a file has
+    // only the empty path
+    fn file(&self, relative: &Path) -> PathBuf {
+        if relative.as_os_str().is_empty() {
+            self.path.clone()
+        } else {
+            self.path.join(relative)
+        }
+    }
+}
+
+fn has_state(location: &Location) -> bool {
+    if location.directory {
+        fs::read_dir(&location.path).is_ok_and(|mut entries| entries.next().is_some())
+    } else {
+        location.path.is_file()
+    }
+}
+
+fn key(passphrase: &str, salt: &[u8]) -> Key {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key.into()
+}
+
+fn seal(passphrase: &str, plaintext: &[u8]) -> Result<Vec<u8>, StateArchiveError> {
+    let salt = Uuid::new_v4().into_bytes();
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(&key(passphrase, &salt))
+        .encrypt(&nonce, Payload { msg: plaintext, aad: MAGIC })
+        .map_err(|_| StateArchiveError::Archive(String::from("encryption failed")))?;
+    Ok([MAGIC, &salt, nonce.as_slice(), &ciphertext].concat())
+}
+
+fn open(passphrase: &str, sealed: &[u8]) -> Result<Vec<u8>, StateArchiveError> {
+    let Some(rest) = sealed.strip_prefix(MAGIC) else {
+        return Err(StateArchiveError::Archive(String::from("not an lsedr state archive")));
+    };
+    if rest.len() < SALT_LEN + NONCE_LEN {
+        return Err(StateArchiveError::Archive(String::from("the file is truncated")));
+    }
+    let (salt, rest) = rest.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(&key(passphrase, salt))
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: MAGIC })
+        .map_err(|_| StateArchiveError::Archive(String::from("wrong passphrase or damaged file")))
+}
+
+// Writes this machine's agent state to an archive encrypted under `passphrase`. Run it with
+// the agent stopped, so the files are not changing underneath.
+pub fn export_state(config: &AgentConfig, output: &Path, passphrase: &str) -> Result<StateSummary, StateArchiveError> {
+    let mut entries = Vec::new();
+    let mut bytes = 0;
+    for location in locations(config) {
+        let files = if !has_state(&location) {
+            Vec::new()
+        } else if location.directory {
+            let mut files = Vec::new();
+            files_below(&location.path, Path::new(""), &mut files)?;
+            files
+        } else {
+            vec![PathBuf::new()]
+        };
+        for file in &files {
+            let path = location.file(file);
+            let data = fs::read(&path).map_err(read_error(&path))?;
+            bytes += data.len() as u64;
+            let relative: Vec<String> = file.components().map(|part| part.as_os_str().to_string_lossy().into_owned()).collect();
+            entries.push(ArchiveEntry {
+                location: location.name.to_string(),
+                path: relative.join("/"),
+                data: STANDARD.encode(data),
+            });
+        }
+        info!("Exporting {} files of {} from {}", files.len(), location.name, location.path.display());
+    }
+
+    let archive = StateArchive {
+        format: FORMAT,
+        agent_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: Utc::now(),
+        identity: HostIdentityService::global().identity(),
+        entries,
+    };
+    let plaintext = serde_json::to_vec(&archive).map_err(|e| StateArchiveError::Archive(e.to_string()))?;
+    fs::write(output, seal(passphrase, &plaintext)?).map_err(write_error(output))?;
+    Ok(StateSummary {
+        exported_at: archive.exported_at,
+        identity: archive.identity,
+        files: archive.entries.len(),
+        bytes,
+    })
+}
+
+// Restores an exported state on this machine, at the locations its configuration names.
+// State already present is only replaced with `replace`, and is removed first so nothing of
+// it mixes with the imported state. Run it with the agent stopped.
+pub fn import_state(config: &AgentConfig, input: &Path, passphrase: &str, replace: bool) -> Result<StateSummary, StateArchiveError> {
+    let sealed = fs::read(input).map_err(read_error(input))?;
+    let archive: StateArchive =
+        serde_json::from_slice(&open(passphrase, &sealed)?).map_err(|e| StateArchiveError::Archive(e.to_string()))?;
+    if archive.format != FORMAT {
+        return Err(StateArchiveError::Archive(format!("unsupported format {}", archive.format)));
+    }
+
+    let locations = locations(config);
+    let mut targets = Vec::new();
+    for entry in &archive.entries {
+        let location = locations
+            .iter()
+            .find(|location| location.name == entry.location)
+            .ok_or_else(|| StateArchiveError::Archive(format!("unknown location {}", entry.location)))?;
+        let relative = Path::new(&entry.path);
+        // Entries only ever name files below their location
+        if !relative.components().all(|part| matches!(part, Component::Normal(_))) || (location.directory == entry.path.is_empty()) {
+            return Err(StateArchiveError::Archive(format!("invalid path {} in {}", entry.path, entry.location)));
+        }
+        let data = STANDARD
+            .decode(&entry.data)
+            .map_err(|e| StateArchiveError::Archive(format!("{} in {}: {}", entry.path, entry.location, e)))?;
+        targets.push((location.file(relative), data));
+    }
+
+    let present: Vec<&Location> = locations.iter().filter(|location| has_state(location)).collect();
+    if !present.is_empty() && !replace {
+        let paths: Vec<String> = present.iter().map(|location| location.path.display().to_string()).collect();
+        return Err(StateArchiveError::Refused(format!(
+            "This machine already has agent state in {}; import with --replace to overwrite it",
+            paths.join(", ")
+        )));
+    }
+    for location in present {
+        let removed = if location.directory { fs::remove_dir_all(&location.path) } else { fs::remove_file(&location.path) };
+        removed.map_err(write_error(&location.path))?;
+    }
+
+    let mut bytes = 0;
+    for (path, data) in &targets {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(write_error(parent))?;
+        }
+        fs::write(path, data).map_err(write_error(path))?;
+        bytes += data.len() as u64;
+    }
+    Ok(StateSummary {
+        exported_at: archive.exported_at,
+        identity: archive.identity,
+        files: targets.len(),
+        bytes,
+    })
+}
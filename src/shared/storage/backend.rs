@@ -0,0 +1,193 @@
+use crate::shared::enrichment::EnrichmentChain;
+use crate::shared::error::StorageError;
+use crate::shared::mapping::ecs::ECS_VERSION;
+use crate::shared::storage::delta::SnapshotDeltaConfig;
+use crate::shared::storage::elasticsearch_storage::{ElasticsearchConfig, ElasticsearchStorage, SystemInformation};
+use crate::shared::storage::failover::{FailoverBackend, FailoverConfig};
+use crate::shared::storage::file_storage::{FileStorage, FileStorageConfig};
+use crate::shared::storage::format::{DocumentFormat, EcsBackend};
+use crate::shared::storage::layout::SnapshotLayout;
+use crate::shared::storage::shipping::{ShapedBackend, ShippingConfig};
+use crate::shared::storage::spool::{Spool, SpoolConfig, SpooledBackend};
+use crate::shared::storage::stdout_storage::StdoutStorage;
+use crate::shared::storage::otlp::{OtlpConfig, OtlpStorage};
+use crate::shared::storage::syslog_storage::{SyslogConfig, SyslogStorage};
+use crate::shared::traits::{DataStorage, Event, EventEnvelope, Identifiable};
+use async_trait::async_trait;
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// A serialized document addressed to a logical index, the unit every backend persists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageDocument {
+    pub index: String,
+    pub body: Value,
+}
+
+impl StorageDocument {
+    pub fn new<T: Serialize>(index: &str, document: &T) -> Result<Self, StorageError> {
+        let mut body = serde_json::to_value(document)
+            .map_err(|e| StorageError::Write(format!("Failed to serialize document for {}: {}", index, e)))?;
+        EnrichmentChain::global().enrich(index, &mut body);
+        Ok(Self {
+            index: index.to_string(),
+            body,
+        })
+    }
+
+    pub fn many<T: Serialize>(index: &str, documents: &[T]) -> Result<Vec<Self>, StorageError> {
+        documents.iter().map(|document| Self::new(index, document)).collect()
+    }
+
+    pub fn from_envelopes(envelopes: &[EventEnvelope]) -> Result<Vec<Self>, StorageError> {
+        envelopes.iter().map(|envelope| Self::new(&envelope.index, &envelope.event)).collect()
+    }
+}
+
+// Every event reaches storage as envelopes, whatever its type; system snapshots, which
+// backends such as OTLP export as metrics rather than log records, are the one other write
+#[async_trait]
+pub trait StorageBackend: DataStorage<StorageDocument> + Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn store_system_info(&self, info: &SystemInformation) -> Result<(), StorageError> {
+        self.store(StorageDocument::new("system_metrics", info)?).await
+    }
+
+    // Events of any type, each to the index its envelope names
+    async fn store_envelopes(&self, envelopes: &[EventEnvelope]) -> Result<(), StorageError> {
+        self.batch_store(StorageDocument::from_envelopes(envelopes)?).await
+    }
+}
+
+impl<'a> dyn StorageBackend + 'a {
+    // Wraps a batch of one event type for `index` and stores it
+    pub async fn store_events<E>(&self, index: &str, events: &[E]) -> Result<(), StorageError>
+    where
+        E: Event + Identifiable + Serialize + Sync,
+    {
+        let envelopes = EventEnvelope::wrap_all(index, events)
+            .map_err(|e| StorageError::Write(format!("Failed to serialize events for {}: {}", index, e)))?;
+        self.store_envelopes(&envelopes).await
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    Elasticsearch,
+    File,
+    Stdout,
+    Syslog,
+    Otlp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub backend: BackendKind,
+    pub file: FileStorageConfig,
+    pub syslog: SyslogConfig,
+    pub otlp: OtlpConfig,
+    pub spool: SpoolConfig,
+    pub failover: FailoverConfig,
+    // Schedules and bandwidth caps for the primary backend
+    pub shipping: ShippingConfig,
+    // Reference unchanged process and service entries of system_metrics snapshots by hash
+    pub snapshot_delta: SnapshotDeltaConfig,
+    // native, or ecs for file, registry and system_metrics documents
+    pub format: DocumentFormat,
+    // combined, or split to write system snapshots as one document per process, service,
+    // connection and interface plus one with the host metrics, in separate indices
+    pub system_metrics_layout: SnapshotLayout,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: BackendKind::Elasticsearch,
+            file: FileStorageConfig::default(),
+            syslog: SyslogConfig::default(),
+            otlp: OtlpConfig::default(),
+            spool: SpoolConfig::default(),
+            failover: FailoverConfig::default(),
+            shipping: ShippingConfig::default(),
+            snapshot_delta: SnapshotDeltaConfig::default(),
+            format: DocumentFormat::Native,
+            system_metrics_layout: SnapshotLayout::Combined,
+        }
+    }
+}
+
+impl StorageConfig {
+    pub fn load() -> Self {
+        crate::utils::load_config_section("storage")
+    }
+}
+
+fn create_sink(
+    kind: BackendKind,
+    config: &StorageConfig,
+    es_config: &ElasticsearchConfig,
+    shipping: &ShippingConfig,
+) -> Result<Box<dyn StorageBackend>, StorageError> {
+    let backend: Box<dyn StorageBackend> = match kind {
+        BackendKind::Elasticsearch => {
+            info!("Connecting to Elasticsearch at {}:{}", es_config.host, es_config.port);
+            Box::new(ElasticsearchStorage::from_config(es_config)?)
+        }
+        BackendKind::File => Box::new(FileStorage::new(config.file.clone())?),
+        BackendKind::Stdout => Box::new(StdoutStorage::new()),
+        BackendKind::Syslog => Box::new(SyslogStorage::new(config.syslog.clone())?),
+        BackendKind::Otlp => Box::new(OtlpStorage::new(config.otlp.clone())?),
+    };
+    if shipping.enabled {
+        info!("Shaping bulk telemetry to {} storage, queued in {}", backend.name(), shipping.queue.directory);
+        return Ok(Box::new(ShapedBackend::new(backend, shipping.clone())?));
+    }
+    Ok(backend)
+}
+
+// The configured sink alone, without the shipping queue, failover journal or spool, for a
+// process that must leave the on-disk state of a running agent alone
+pub fn create_direct_backend(
+    config: &StorageConfig,
+    es_config: &ElasticsearchConfig,
+) -> Result<Box<dyn StorageBackend>, StorageError> {
+    create_sink(config.backend, config, es_config, &ShippingConfig::default())
+}
+
+pub fn create_backend(
+    config: &StorageConfig,
+    es_config: &ElasticsearchConfig,
+) -> Result<Box<dyn StorageBackend>, StorageError> {
+    let mut backend = create_sink(config.backend, config, es_config, &config.shipping)?;
+
+    if config.failover.enabled {
+        let secondary = create_sink(
+            config.failover.secondary,
+            config,
+            &config.failover.elasticsearch,
+            &config.failover.shipping,
+        )?;
+        info!("Failing over from {} to {} storage when it is unavailable", backend.name(), secondary.name());
+        backend = Box::new(FailoverBackend::new(backend, secondary, &config.failover)?);
+    }
+
+    info!("Using {} storage backend", backend.name());
+
+    // Stdout never fails in a way a spool could help with
+    if config.spool.enabled && config.backend != BackendKind::Stdout {
+        let spool = Spool::new(config.spool.clone())?;
+        info!("Buffering failed writes in spool {}", config.spool.directory);
+        backend = Box::new(SpooledBackend::new(backend, spool));
+    }
+
+    // Outermost, so the spool and the failover sink hold ECS documents too
+    if config.format == DocumentFormat::Ecs {
+        info!("Writing file, registry and system_metrics documents in ECS {}", ECS_VERSION);
+        backend = Box::new(EcsBackend::new(backend));
+    }
+    Ok(backend)
+}
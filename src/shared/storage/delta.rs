@@ -0,0 +1,171 @@
+use crate::shared::error::StorageError;
+use crate::shared::storage::backend::StorageDocument;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+// Length of the hex entry hash; 64 bits is plenty to tell apart the entries of one host
+const HASH_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeltaSection {
+    // Array field of the snapshot document, e.g. process_info
+    pub field: String,
+    // Left out of the comparison and dropped from references
+    pub ignore_fields: Vec<String>,
+    // Left out of the comparison but kept on references, for values that change every cycle
+    pub carry_fields: Vec<String>,
+}
+
+impl Default for DeltaSection {
+    fn default() -> Self {
+        Self {
+            field: String::new(),
+            ignore_fields: vec![
+                String::from("id"),
+                String::from("timestamp"),
+                String::from("source"),
+                String::from("category"),
+            ],
+            carry_fields: Vec::new(),
+        }
+    }
+}
+
+impl DeltaSection {
+    fn hash(&self, entry: &Value) -> String {
+        let mut stable = entry.clone();
+        if let Value::Object(fields) = &mut stable {
+            for field in self.ignore_fields.iter().chain(&self.carry_fields) {
+                fields.remove(field);
+            }
+        }
+        // serde_json maps are ordered by key, so equal entries serialize identically
+        let digest = Sha256::digest(stable.to_string().as_bytes());
+        hex::encode(digest)[..HASH_LENGTH].to_string()
+    }
+
+    fn reference(&self, entry: &Value, hash: String) -> Value {
+        let mut reference = Map::new();
+        reference.insert(String::from("ref"), Value::String(hash));
+        for field in &self.carry_fields {
+            if let Some(value) = entry.get(field) {
+                reference.insert(field.clone(), value.clone());
+            }
+        }
+        Value::Object(reference)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotDeltaConfig {
+    pub enabled: bool,
+    // Every Nth snapshot is written in full and starts a new reference chain
+    pub keyframe_interval: u32,
+    pub sections: Vec<DeltaSection>,
+}
+
+impl Default for SnapshotDeltaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keyframe_interval: 60,
+            sections: vec![
+                DeltaSection {
+                    field: String::from("process_info"),
+                    carry_fields: vec![String::from("cpu_usage"), String::from("memory_usage")],
+                    ..DeltaSection::default()
+                },
+                DeltaSection {
+                    field: String::from("services"),
+                    ..DeltaSection::default()
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Default)]
+struct DeltaState {
+    keyframe_id: Option<String>,
+    since_keyframe: u32,
+    // Entry hashes written in full since the keyframe, per section
+    known: HashMap<String, HashSet<String>>,
+}
+
+// Delta-encodes periodic snapshot documents. An entry already written in full since the
+// last keyframe is replaced by {"ref": <entry_hash>} plus its carry fields; new or changed
+// entries are written in full with their entry_hash. A reader rebuilds a snapshot by
+// resolving each ref against the documents back to the one named by keyframe_id.
+pub struct SnapshotDeltaEncoder {
+    config: SnapshotDeltaConfig,
+    state: Mutex<DeltaState>,
+}
+
+impl SnapshotDeltaEncoder {
+    pub fn new(config: SnapshotDeltaConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(DeltaState::default()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn encode<T: Serialize>(&self, index: &str, snapshot: &T) -> Result<StorageDocument, StorageError> {
+        let mut document = StorageDocument::new(index, snapshot)?;
+        let Value::Object(fields) = &mut document.body else {
+            return Ok(document);
+        };
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| StorageError::Write(String::from("snapshot delta state poisoned")))?;
+
+        let keyframe = state.keyframe_id.is_none() || state.since_keyframe >= self.config.keyframe_interval.max(1);
+        if keyframe {
+            state.keyframe_id = Some(Uuid::new_v4().to_string());
+            state.since_keyframe = 0;
+            state.known.clear();
+        }
+        state.since_keyframe += 1;
+
+        let (mut full, mut referenced) = (0, 0);
+        for section in &self.config.sections {
+            let Some(Value::Array(entries)) = fields.get_mut(&section.field) else {
+                continue;
+            };
+            let known = state.known.entry(section.field.clone()).or_default();
+            for entry in entries.iter_mut() {
+                let hash = section.hash(entry);
+                if known.contains(&hash) {
+                    *entry = section.reference(entry, hash);
+                    referenced += 1;
+                } else {
+                    if let Value::Object(entry) = entry {
+                        entry.insert(String::from("entry_hash"), Value::String(hash.clone()));
+                    }
+                    known.insert(hash);
+                    full += 1;
+                }
+            }
+        }
+
+        fields.insert(String::from("snapshot_encoding"), Value::String(String::from("delta")));
+        fields.insert(String::from("snapshot_keyframe"), Value::Bool(keyframe));
+        fields.insert(
+            String::from("keyframe_id"),
+            state.keyframe_id.clone().map(Value::String).unwrap_or(Value::Null),
+        );
+        debug!("Delta-encoded {} snapshot: {} entries in full, {} referenced", index, full, referenced);
+        Ok(document)
+    }
+}
@@ -3,19 +3,41 @@ use crate::features::{
     process::ProcessInformation,
     service::ServiceInformation,
     system_metrics::{CpuInformation, MemoryInformation, DiskInformation, SystemLoadInformation},
-    filesystem::FileEvent,
-    registry::{RegistryEvent, SuspiciousRegistryOperation},
 };
 use elasticsearch::{
     auth::Credentials,
-    http::transport::{TransportBuilder, SingleNodeConnectionPool},
-    Elasticsearch, IndexParts,
+    cert::{Certificate, CertificateValidation},
+    http::request::JsonBody,
+    http::transport::{CloudConnectionPool, SingleNodeConnectionPool, Transport, TransportBuilder},
+    ilm::IlmPutLifecycleParts,
+    indices::{IndicesPutIndexTemplateParts, IndicesPutSettingsParts},
+    ingest::IngestPutPipelineParts,
+    params::OpType,
+    BulkParts, Elasticsearch, GetParts, IndexParts, SearchParts,
 };
-use log::{error, info};
-use serde::Serialize;
+use crate::shared::enrichment::EnrichmentChain;
+use crate::shared::error::StorageError as SharedStorageError;
+use crate::shared::storage::backend::{StorageBackend, StorageDocument};
+use crate::shared::traits::{DataStorage, EventEnvelope};
+use crate::utils::time::parse_timestamp;
+use crate::shared::storage::ilm::{IlmConfig, MANAGED_INDICES};
+use crate::shared::storage::query::{extract_hits, EventQuery, POLICY_INDEX};
+use crate::shared::storage::retry::RetryPolicy;
+use crate::shared::storage::pipeline::{default_pipeline_body, PipelineConfig, DEFAULT_PIPELINE_ID};
+use crate::shared::storage::templates::{IndexNaming, IndexNamingConfig, TemplateConfig};
+use crate::shared::storage::truncation::TruncationConfig;
+use crate::utils::load_config_section;
+use async_trait::async_trait;
+use chrono::{SecondsFormat, Utc};
+use log::{debug, error, info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::time::SystemTime;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Instant, SystemTime};
 use thiserror::Error;
+use tokio::io::AsyncWriteExt;
 use url::Url;
 
 #[derive(Debug, Error)]
@@ -24,16 +46,170 @@ pub enum StorageError {
     StoreError(String),
     #[error("Failed to connect to Elasticsearch: {0}")]
     ConnectionError(String),
+    #[error("Failed to query data: {0}")]
+    QueryError(String),
+    #[error("Elasticsearch is throttling or unavailable: {0}")]
+    Throttled(String),
+    // The document itself was refused, such as for not fitting the mapping
+    #[error("Elasticsearch rejected the document: {0}")]
+    Rejected(String),
+}
+
+impl StorageError {
+    fn is_transient(&self) -> bool {
+        matches!(self, StorageError::Throttled(_))
+    }
+}
+
+const AUDIT_INDEX: &str = "action_audit";
+
+// Too many requests, or a node or proxy briefly unable to take them
+fn transient_status(status: u16) -> bool {
+    matches!(status, 429 | 502 | 503 | 504)
+}
+
+// Refusals of the document rather than of the agent: sending it again cannot help, unlike
+// after an authentication failure or a missing index the operator can still fix
+fn rejected_status(status: u16) -> bool {
+    matches!(status, 400 | 409 | 413)
+}
+
+// What became of the documents of one bulk request that were not stored, by position
+#[derive(Default)]
+struct BulkOutcome {
+    rejected: Vec<(usize, String)>,
+    throttled: Vec<usize>,
+}
+
+impl From<StorageError> for SharedStorageError {
+    fn from(error: StorageError) -> Self {
+        match error {
+            StorageError::StoreError(e) => SharedStorageError::Write(e),
+            StorageError::ConnectionError(e) => SharedStorageError::Connection(e),
+            StorageError::QueryError(e) => SharedStorageError::Read(e),
+            StorageError::Throttled(e) => SharedStorageError::Throttled(e),
+            StorageError::Rejected(e) => SharedStorageError::Write(e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub storage: StorageLoggingConfig,
+}
+
+impl LoggingConfig {
+    pub fn load() -> Self {
+        load_config_section("logging")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageLoggingConfig {
+    // Log one summary line per stored batch instead of one per document
+    pub batch_summary: bool,
+    // Log every Nth Elasticsearch response body at debug level, 0 disables sampling
+    pub debug_sample_rate: u64,
+}
+
+impl Default for StorageLoggingConfig {
+    fn default() -> Self {
+        Self {
+            batch_summary: true,
+            debug_sample_rate: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ElasticsearchTlsConfig {
+    // PEM file of the CA that signed the cluster's certificate, trusted besides the system roots
+    pub ca_certificate: Option<String>,
+    // Accept any certificate and host name; only for test clusters with self-signed certificates
+    pub insecure_skip_verify: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ElasticsearchConfig {
+    // http or https
+    pub scheme: String,
+    pub host: String,
+    pub port: u16,
+    // Elastic Cloud deployment, replaces scheme, host and port
+    pub cloud_id: Option<String>,
+    // Only one kind of credentials is used: API key, then bearer token, then username / password
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // Encoded key as shown by Kibana, or id:api_key
+    pub api_key: Option<String>,
+    // Service account or OAuth token
+    pub bearer_token: Option<String>,
+    pub tls: ElasticsearchTlsConfig,
+    pub ilm: IlmConfig,
+    pub pipeline: PipelineConfig,
+    pub index_naming: IndexNamingConfig,
+    pub templates: TemplateConfig,
+    // Backoff for writes answered with 429 or 502-504; a bulk request only sends the
+    // documents that were not taken again
+    pub retry: RetryPolicy,
+    // gzip request bodies; Elasticsearch accepts them as is, proxies in between must too
+    pub compression: bool,
+    // Size limits for documents on their way out; action_audit records are never cut
+    pub truncation: TruncationConfig,
+    // JSON lines file for documents the cluster rejects for good, empty to only log them
+    pub dead_letter: String,
+}
+
+impl Default for ElasticsearchConfig {
+    fn default() -> Self {
+        Self {
+            scheme: String::from("http"),
+            host: String::from("localhost"),
+            port: 9200,
+            cloud_id: None,
+            username: None,
+            password: None,
+            api_key: None,
+            bearer_token: None,
+            tls: ElasticsearchTlsConfig::default(),
+            ilm: IlmConfig::default(),
+            pipeline: PipelineConfig::default(),
+            index_naming: IndexNamingConfig::default(),
+            templates: TemplateConfig::default(),
+            retry: RetryPolicy::default(),
+            compression: false,
+            truncation: TruncationConfig::default(),
+            dead_letter: String::from("data/dead-letter/elasticsearch.jsonl"),
+        }
+    }
+}
+
+impl ElasticsearchConfig {
+    pub fn load() -> Self {
+        load_config_section("elasticsearch")
+    }
 }
 
 pub struct ElasticsearchStorage {
     client: Elasticsearch,
+    logging: StorageLoggingConfig,
+    pipeline: Option<String>,
+    naming: IndexNamingConfig,
+    retry: RetryPolicy,
+    truncation: TruncationConfig,
+    dead_letter: Option<PathBuf>,
+    response_counter: AtomicU64,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct SystemInformation {
     pub timestamp: SystemTime,
     pub hostname: String,
+    pub fqdn: Option<String>,
     pub os_name: String,
     pub os_version: String,
     pub kernel_version: String,
@@ -45,6 +221,65 @@ pub struct SystemInformation {
     pub system_load: SystemLoadInformation,
     pub network_connections: Vec<NetworkConnectionInformation>,
     pub services: Vec<ServiceInformation>,
+    // Set when services was left out because it matched the list last stored
+    #[serde(default)]
+    pub services_unchanged: bool,
+}
+
+fn enriched<T: Serialize>(index: &str, document: &T) -> Value {
+    let mut body = json!(document);
+    EnrichmentChain::global().enrich(index, &mut body);
+    body
+}
+
+fn credentials(config: &ElasticsearchConfig) -> Option<Credentials> {
+    if let Some(api_key) = &config.api_key {
+        return Some(match api_key.split_once(':') {
+            Some((id, key)) => Credentials::ApiKey(id.to_string(), key.to_string()),
+            None => Credentials::EncodedApiKey(api_key.clone()),
+        });
+    }
+    if let Some(token) = &config.bearer_token {
+        return Some(Credentials::Bearer(token.clone()));
+    }
+    match (&config.username, &config.password) {
+        (Some(username), Some(password)) => Some(Credentials::Basic(username.clone(), password.clone())),
+        _ => None,
+    }
+}
+
+fn transport(config: &ElasticsearchConfig) -> Result<Transport, StorageError> {
+    let mut builder = match &config.cloud_id {
+        Some(cloud_id) => TransportBuilder::new(
+            CloudConnectionPool::new(cloud_id).map_err(|e| StorageError::ConnectionError(e.to_string()))?,
+        ),
+        None => {
+            let url = format!("{}://{}:{}", config.scheme, config.host, config.port);
+            let url = Url::parse(&url)
+                .map_err(|e| StorageError::ConnectionError(e.to_string()))?;
+            TransportBuilder::new(SingleNodeConnectionPool::new(url))
+        }
+    };
+
+    if let Some(credentials) = credentials(config) {
+        builder = builder.auth(credentials);
+    }
+    builder = builder.request_body_compression(config.compression);
+
+    if config.tls.insecure_skip_verify {
+        warn!("Elasticsearch certificate verification is disabled");
+        builder = builder.cert_validation(CertificateValidation::None);
+    } else if let Some(path) = &config.tls.ca_certificate {
+        let pem = fs::read(path)
+            .map_err(|e| StorageError::ConnectionError(format!("Failed to read CA certificate {}: {}", path, e)))?;
+        let certificate = Certificate::from_pem(&pem)
+            .map_err(|e| StorageError::ConnectionError(format!("Invalid CA certificate {}: {}", path, e)))?;
+        builder = builder.cert_validation(CertificateValidation::Full(certificate));
+    }
+
+    builder
+        .build()
+        .map_err(|e| StorageError::ConnectionError(e.to_string()))
 }
 
 impl ElasticsearchStorage {
@@ -54,136 +289,572 @@ impl ElasticsearchStorage {
         username: Option<&str>,
         password: Option<&str>,
     ) -> Result<Self, StorageError> {
-        let url = format!("http://{}:{}", host, port);
-        let url = Url::parse(&url)
-            .map_err(|e| StorageError::ConnectionError(e.to_string()))?;
-        
-        let conn_pool = SingleNodeConnectionPool::new(url);
-        let mut builder = TransportBuilder::new(conn_pool);
-
-        if let (Some(username), Some(password)) = (username, password) {
-            builder = builder.auth(Credentials::Basic(
-                username.to_string(),
-                password.to_string(),
-            ));
-        }
+        Self::connect(&ElasticsearchConfig {
+            host: host.to_string(),
+            port,
+            username: username.map(String::from),
+            password: password.map(String::from),
+            ..ElasticsearchConfig::default()
+        })
+    }
 
-        let transport = builder
-            .build()
-            .map_err(|e| StorageError::ConnectionError(e.to_string()))?;
+    fn connect(config: &ElasticsearchConfig) -> Result<Self, StorageError> {
+        let transport = transport(config)?;
+        let logging = LoggingConfig::load();
 
         Ok(Self {
             client: Elasticsearch::new(transport),
+            logging: logging.storage,
+            pipeline: None,
+            naming: config.index_naming.clone(),
+            retry: config.retry.clone(),
+            truncation: config.truncation.clone(),
+            dead_letter: Some(config.dead_letter.trim()).filter(|path| !path.is_empty()).map(PathBuf::from),
+            response_counter: AtomicU64::new(0),
         })
     }
 
-    pub async fn store_system_info(&self, info: &SystemInformation) -> Result<(), StorageError> {
-        let response = self
-            .client
-            .index(IndexParts::Index("system_metrics"))
-            .body(json!(info))
+    pub fn from_config(config: &ElasticsearchConfig) -> Result<Self, StorageError> {
+        let storage = Self::connect(config)?;
+        Ok(storage.with_pipeline(config.pipeline.name.clone()))
+    }
+
+    pub fn with_logging_config(mut self, logging: StorageLoggingConfig) -> Self {
+        self.logging = logging;
+        self
+    }
+
+    pub fn with_pipeline(mut self, pipeline: Option<String>) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_truncation(mut self, truncation: TruncationConfig) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    // Every index shares one time field, the event's own timestamp where it has one: data
+    // streams require it, and queries range and sort on it whatever the naming, since
+    // `timestamp` is an object for some documents. Documents are cut to the truncation
+    // limits before anything is added.
+    fn body_for<T: Serialize>(&self, index: &str, document: &T) -> Value {
+        let mut body = enriched(index, document);
+        if index != AUDIT_INDEX {
+            self.truncation.apply(&mut body);
+        }
+        if let Value::Object(fields) = &mut body {
+            let timestamp = fields.get("timestamp").and_then(parse_timestamp).unwrap_or_else(Utc::now);
+            fields
+                .entry("@timestamp")
+                .or_insert_with(|| json!(timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)));
+        }
+        body
+    }
+
+    async fn index_document<T: Serialize>(&self, index: &str, document: &T) -> Result<(), StorageError> {
+        let body = self.body_for(index, document);
+        let what = format!("Indexing into {}", index);
+        self.retry
+            .run(&what, StorageError::is_transient, || self.index_body(index, &body))
+            .await
+    }
+
+    async fn index_body(&self, index: &str, body: &Value) -> Result<(), StorageError> {
+        let target = self.naming.write_target(index);
+        let audit_id = if index == AUDIT_INDEX { body.get("id").and_then(Value::as_str) } else { None };
+        let parts = match audit_id {
+            Some(id) => IndexParts::IndexId(&target, id),
+            None => IndexParts::Index(&target),
+        };
+        let mut request = self.client.index(parts).body(body);
+        // Data streams are append-only, and audit records are never overwritten
+        if self.naming.scheme == IndexNaming::DataStream || audit_id.is_some() {
+            request = request.op_type(OpType::Create);
+        }
+        if let Some(pipeline) = &self.pipeline {
+            request = request.pipeline(pipeline);
+        }
+
+        let response = request
             .send()
             .await
             .map_err(|e| StorageError::StoreError(e.to_string()))?;
 
-        if !response.status_code().is_success() {
-            error!("Failed to store metrics: {:?}", response);
+        let status = response.status_code();
+        if transient_status(status.as_u16()) {
+            return Err(StorageError::Throttled(format!("Elasticsearch returned status {}", status)));
+        }
+        if status.as_u16() == 409 && audit_id.is_some() {
+            debug!("Action audit record in {} already stored", target);
+            return Ok(());
+        }
+        if rejected_status(status.as_u16()) {
+            return Err(StorageError::Rejected(format!("{}: {}", status, response.text().await.unwrap_or_default())));
+        }
+        if !status.is_success() {
+            error!("Failed to store document in {}: {:?}", target, response);
             return Err(StorageError::StoreError(format!(
                 "Elasticsearch returned error status: {}",
-                response.status_code()
+                status
             )));
         }
 
-        let response_body: Value = response
-            .json()
-            .await
-            .map_err(|e| StorageError::StoreError(e.to_string()))?;
-
-        info!("Successfully stored metrics: {:?}", response_body);
+        if self.sampled() {
+            let response_body: Value = response
+                .json()
+                .await
+                .map_err(|e| StorageError::StoreError(e.to_string()))?;
+            debug!("Sampled response from {}: {:?}", target, response_body);
+        }
         Ok(())
     }
 
-    pub async fn store_file_events(&self, events: &[FileEvent]) -> Result<(), StorageError> {
-        for event in events {
-            let response = self
-                .client
-                .index(IndexParts::Index("file_events"))
-                .body(json!(event))
+    fn sampled(&self) -> bool {
+        let rate = self.logging.debug_sample_rate;
+        rate > 0 && self.response_counter.fetch_add(1, Ordering::Relaxed) % rate == 0
+    }
+
+    // One _bulk request of (index, body) pairs. Documents the cluster turns away with 429 or
+    // 502-504, or all of them when the whole request is, are sent again under the retry
+    // policy. What comes back is which documents were rejected for good and which were
+    // still turned away once the retries ran out.
+    async fn bulk(&self, documents: Vec<(&str, Value)>) -> Result<BulkOutcome, StorageError> {
+        let mut pending: Vec<usize> = (0..documents.len()).collect();
+        let mut outcome = BulkOutcome::default();
+        let mut retry = 0;
+        while !pending.is_empty() {
+            let mut lines: Vec<JsonBody<Value>> = Vec::with_capacity(pending.len() * 2);
+            for position in &pending {
+                let (index, body) = &documents[*position];
+                lines.push(JsonBody::new(self.bulk_action(index, body)));
+                lines.push(JsonBody::new(body.clone()));
+            }
+            let mut request = self.client.bulk(BulkParts::None).body(lines);
+            if let Some(pipeline) = &self.pipeline {
+                request = request.pipeline(pipeline);
+            }
+            let response = request
                 .send()
                 .await
                 .map_err(|e| StorageError::StoreError(e.to_string()))?;
 
-            if !response.status_code().is_success() {
-                error!("Failed to store file event: {:?}", response);
+            let status = response.status_code();
+            let throttled = if transient_status(status.as_u16()) {
+                std::mem::take(&mut pending)
+            } else if !status.is_success() {
+                error!("Bulk request failed: {:?}", response);
                 return Err(StorageError::StoreError(format!(
                     "Elasticsearch returned error status: {}",
-                    response.status_code()
+                    status
                 )));
+            } else {
+                let response_body: Value = response
+                    .json()
+                    .await
+                    .map_err(|e| StorageError::StoreError(e.to_string()))?;
+                if self.sampled() {
+                    debug!("Sampled bulk response: {:?}", response_body);
+                }
+                let items = response_body.get("items").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+                let mut throttled = Vec::new();
+                for (position, item) in pending.drain(..).zip(items.iter()) {
+                    // Each item is keyed by its action, e.g. {"index": {"status": 201, ...}}
+                    let result = item.as_object().and_then(|item| item.values().next());
+                    let item_status = result.and_then(|result| result.get("status")).and_then(Value::as_u64).unwrap_or(0) as u16;
+                    if transient_status(item_status) {
+                        throttled.push(position);
+                    } else if item_status == 409 && documents[position].0 == AUDIT_INDEX {
+                        // The audit record was already written, by an earlier attempt at this batch
+                        debug!("Action audit record in {} already stored", documents[position].0);
+                    } else if !(200..300).contains(&item_status) {
+                        let reason = result
+                            .and_then(|result| result.pointer("/error/reason"))
+                            .and_then(Value::as_str)
+                            .unwrap_or("no reason given");
+                        outcome.rejected.push((position, format!("{}: {}", item_status, reason)));
+                    }
+                }
+                throttled
+            };
+
+            if throttled.is_empty() {
+                break;
+            }
+            if retry >= self.retry.max_retries {
+                warn!(
+                    "{} of {} documents were still turned away after {} retries",
+                    throttled.len(),
+                    documents.len(),
+                    retry
+                );
+                outcome.throttled = throttled;
+                break;
             }
+            let backoff = self.retry.backoff(retry);
+            retry += 1;
+            warn!(
+                "Elasticsearch turned away {} of {} documents, retry {} of {} in {:?}",
+                throttled.len(),
+                documents.len(),
+                retry,
+                self.retry.max_retries,
+                backoff
+            );
+            tokio::time::sleep(backoff).await;
+            pending = throttled;
+        }
+        Ok(outcome)
+    }
 
-            let response_body: Value = response
-                .json()
-                .await
-                .map_err(|e| StorageError::StoreError(e.to_string()))?;
+    // Data streams are append-only. Audit records are created under their own ID so they
+    // can never be overwritten.
+    fn bulk_action(&self, index: &str, body: &Value) -> Value {
+        let mut target = json!({ "_index": self.naming.write_target(index) });
+        if index == AUDIT_INDEX {
+            if let Some(id) = body.get("id").and_then(Value::as_str) {
+                target["_id"] = json!(id);
+            }
+            return json!({ "create": target });
+        }
+        if self.naming.scheme == IndexNaming::DataStream {
+            return json!({ "create": target });
+        }
+        json!({ "index": target })
+    }
+
+    // Documents the cluster will never take, such as ones that do not fit the mapping, are
+    // set aside in the dead-letter file instead of failing the batch, so a spool or
+    // failover journal holding them can still be emptied
+    async fn dead_letter(&self, rejected: &[(&StorageDocument, String)]) {
+        let Some((first, reason)) = rejected.first() else {
+            return;
+        };
+        error!(
+            "Elasticsearch rejected {} documents, first in {}: {}",
+            rejected.len(),
+            first.index,
+            reason
+        );
+        let Some(path) = &self.dead_letter else {
+            return;
+        };
+        let mut buffer = Vec::new();
+        for (document, reason) in rejected {
+            let record = json!({
+                "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                "index": document.index,
+                "reason": reason,
+                "document": document.body,
+            });
+            if serde_json::to_writer(&mut buffer, &record).is_ok() {
+                buffer.push(b'\n');
+            }
+        }
+        if let Some(parent) = path.parent() {
+            let _ = tokio::fs::create_dir_all(parent).await;
+        }
+        let written = match tokio::fs::OpenOptions::new().create(true).append(true).open(path).await {
+            Ok(mut file) => file.write_all(&buffer).await,
+            Err(e) => Err(e),
+        };
+        match written {
+            Ok(()) => info!("Set aside {} rejected documents in {}", rejected.len(), path.display()),
+            Err(e) => error!("Failed to write rejected documents to {}: {}", path.display(), e),
+        }
+    }
+
+    // Every write ends here. Rejected documents go to the dead-letter file; documents still
+    // throttled after the retries come back as a partial failure, so only they are spooled
+    // or sent to the failover sink.
+    async fn write_batch(&self, data: Vec<StorageDocument>) -> Result<(), SharedStorageError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let started = Instant::now();
+        let bodies = data
+            .iter()
+            .map(|document| (document.index.as_str(), self.body_for(&document.index, &document.body)))
+            .collect();
+        let outcome = self.bulk(bodies).await?;
 
-            info!("Successfully stored file event: {:?}", response_body);
+        let rejected: Vec<(&StorageDocument, String)> = outcome
+            .rejected
+            .into_iter()
+            .map(|(position, reason)| (&data[position], reason))
+            .collect();
+        self.dead_letter(&rejected).await;
+        if !outcome.throttled.is_empty() {
+            return Err(SharedStorageError::Partial {
+                reason: format!(
+                    "Elasticsearch still turned away {} of {} documents after {} retries",
+                    outcome.throttled.len(),
+                    data.len(),
+                    self.retry.max_retries
+                ),
+                remaining: outcome.throttled.into_iter().map(|position| data[position].clone()).collect(),
+            });
+        }
+
+        // Batches normally hold one index; the first names the batch in the log
+        let stored = data.len() - rejected.len();
+        if self.logging.batch_summary {
+            info!("Stored {} documents in {} ({:?})", stored, data[0].index, started.elapsed());
+        } else {
+            debug!("Stored {} documents in {} ({:?})", stored, data[0].index, started.elapsed());
         }
         Ok(())
     }
 
-    pub async fn store_registry_events(&self, events: &[RegistryEvent]) -> Result<(), StorageError> {
-        for event in events {
+    pub async fn apply_ilm_policies(&self, config: &IlmConfig) -> Result<usize, StorageError> {
+        let mut applied = 0;
+        for index in MANAGED_INDICES {
+            let policy_name = IlmConfig::policy_name(index);
+            let mut phases = config.phases_for(index);
+            // Rollover needs the alias only data streams have; a static index would stall in the
+            // hot phase on it, and a new dated index starts every day anyway
+            if self.naming.scheme != IndexNaming::DataStream {
+                phases.rollover_max_age = None;
+                phases.rollover_max_primary_shard_size = None;
+            }
+
             let response = self
                 .client
-                .index(IndexParts::Index("registry_events"))
-                .body(json!(event))
+                .ilm()
+                .put_lifecycle(IlmPutLifecycleParts::Policy(&policy_name))
+                .body(phases.policy_body())
                 .send()
                 .await
-                .map_err(|e| StorageError::StoreError(e.to_string()))?;
+                .map_err(|e| StorageError::ConnectionError(e.to_string()))?;
 
             if !response.status_code().is_success() {
-                error!("Failed to store registry event: {:?}", response);
+                error!("Failed to create ILM policy {}: {:?}", policy_name, response);
                 return Err(StorageError::StoreError(format!(
-                    "Elasticsearch returned error status: {}",
-                    response.status_code()
+                    "Elasticsearch returned error status {} for ILM policy {}",
+                    response.status_code(),
+                    policy_name
                 )));
             }
+            info!(
+                "Applied ILM policy {} (warm after {}, delete after {})",
+                policy_name, phases.warm_after, phases.delete_after
+            );
 
-            let response_body: Value = response
-                .json()
-                .await
-                .map_err(|e| StorageError::StoreError(e.to_string()))?;
+            if config.attach_to_existing_indices {
+                let target = self.naming.read_target(index);
+                let response = self
+                    .client
+                    .indices()
+                    .put_settings(IndicesPutSettingsParts::Index(&[&target]))
+                    .body(json!({ "index": { "lifecycle": { "name": policy_name } } }))
+                    .send()
+                    .await
+                    .map_err(|e| StorageError::ConnectionError(e.to_string()))?;
 
-            info!("Successfully stored registry event: {:?}", response_body);
+                if response.status_code().is_success() {
+                    info!("Attached ILM policy {} to index {}", policy_name, target);
+                } else {
+                    // The index may simply not exist yet on a fresh cluster
+                    debug!("Could not attach ILM policy to {}: {}", target, response.status_code());
+                }
+            }
+            applied += 1;
         }
-        Ok(())
+        Ok(applied)
     }
 
-    pub async fn store_suspicious_registry_operations(&self, operations: &[SuspiciousRegistryOperation]) -> Result<(), StorageError> {
-        for operation in operations {
+    // One composable template per managed index, so new indices and data streams get the
+    // mappings and lifecycle policy before the first document arrives
+    pub async fn install_index_templates(&self, config: &TemplateConfig) -> Result<usize, StorageError> {
+        let mut installed = 0;
+        for index in MANAGED_INDICES {
+            let template_name = IndexNamingConfig::template_name(index);
             let response = self
                 .client
-                .index(IndexParts::Index("suspicious_registry_operations"))
-                .body(json!(operation))
+                .indices()
+                .put_index_template(IndicesPutIndexTemplateParts::Name(&template_name))
+                .body(self.naming.template_body(index, config))
                 .send()
                 .await
-                .map_err(|e| StorageError::StoreError(e.to_string()))?;
+                .map_err(|e| StorageError::ConnectionError(e.to_string()))?;
 
             if !response.status_code().is_success() {
-                error!("Failed to store suspicious registry operation: {:?}", response);
+                error!("Failed to install index template {}: {:?}", template_name, response);
                 return Err(StorageError::StoreError(format!(
-                    "Elasticsearch returned error status: {}",
-                    response.status_code()
+                    "Elasticsearch returned error status {} for index template {}",
+                    response.status_code(),
+                    template_name
                 )));
             }
+            debug!("Installed index template {} for {}", template_name, self.naming.read_target(index));
+            installed += 1;
+        }
+        info!("Installed {} index templates ({:?} naming)", installed, self.naming.scheme);
+        Ok(installed)
+    }
 
-            let response_body: Value = response
-                .json()
-                .await
-                .map_err(|e| StorageError::StoreError(e.to_string()))?;
+    pub async fn install_default_pipeline(&self) -> Result<(), StorageError> {
+        let response = self
+            .client
+            .ingest()
+            .put_pipeline(IngestPutPipelineParts::Id(DEFAULT_PIPELINE_ID))
+            .body(default_pipeline_body())
+            .send()
+            .await
+            .map_err(|e| StorageError::ConnectionError(e.to_string()))?;
 
-            info!("Successfully stored suspicious registry operation: {:?}", response_body);
+        if !response.status_code().is_success() {
+            error!("Failed to install ingest pipeline {}: {:?}", DEFAULT_PIPELINE_ID, response);
+            return Err(StorageError::StoreError(format!(
+                "Elasticsearch returned error status {} for pipeline {}",
+                response.status_code(),
+                DEFAULT_PIPELINE_ID
+            )));
         }
+
+        info!("Installed ingest pipeline {}", DEFAULT_PIPELINE_ID);
         Ok(())
     }
+
+    // `target` is a concrete index, pattern or data stream, see IndexNamingConfig::read_target
+    async fn search_raw(&self, target: &str, body: Value, size: i64) -> Result<Vec<Value>, StorageError> {
+        let response = self
+            .client
+            .search(SearchParts::Index(&[target]))
+            .ignore_unavailable(true)
+            .allow_no_indices(true)
+            .size(size)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| StorageError::ConnectionError(e.to_string()))?;
+
+        if !response.status_code().is_success() {
+            error!("Search on {} failed: {:?}", target, response);
+            return Err(StorageError::QueryError(format!(
+                "Elasticsearch returned error status: {}",
+                response.status_code()
+            )));
+        }
+
+        let response_body: Value = response
+            .json()
+            .await
+            .map_err(|e| StorageError::QueryError(e.to_string()))?;
+        Ok(extract_hits(&response_body))
+    }
+
+    pub async fn search_events<T: DeserializeOwned>(&self, query: &EventQuery) -> Result<Vec<T>, StorageError> {
+        let target = self.naming.read_target(&query.index);
+        let hits = self.search_raw(&target, query.to_query_body(), query.size).await?;
+        debug!("Search on {} returned {} hits", query.index, hits.len());
+
+        hits.into_iter()
+            .map(|hit| serde_json::from_value(hit).map_err(|e| StorageError::QueryError(e.to_string())))
+            .collect()
+    }
+
+    // Most recent document written for a host, e.g. the last system_metrics snapshot
+    pub async fn get_latest_baseline(&self, index: &str, host: &str) -> Result<Option<Value>, StorageError> {
+        let query = EventQuery::new(index).host(host).size(1);
+        let target = self.naming.read_target(index);
+        Ok(self.search_raw(&target, query.to_query_body(), 1).await?.into_iter().next())
+    }
+
+    pub async fn fetch_policy_document<T: DeserializeOwned>(&self, policy_id: &str) -> Result<Option<T>, StorageError> {
+        let response = self
+            .client
+            .get(GetParts::IndexId(POLICY_INDEX, policy_id))
+            .send()
+            .await
+            .map_err(|e| StorageError::ConnectionError(e.to_string()))?;
+
+        if response.status_code().as_u16() == 404 {
+            return Ok(None);
+        }
+        if !response.status_code().is_success() {
+            return Err(StorageError::QueryError(format!(
+                "Elasticsearch returned error status {} for policy {}",
+                response.status_code(),
+                policy_id
+            )));
+        }
+
+        let response_body: Value = response
+            .json()
+            .await
+            .map_err(|e| StorageError::QueryError(e.to_string()))?;
+        match response_body.get("_source") {
+            Some(source) => serde_json::from_value(source.clone())
+                .map(Some)
+                .map_err(|e| StorageError::QueryError(e.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    // All policy documents of a kind (e.g. "ioc_list"), newest first
+    pub async fn fetch_policy_documents(&self, kind: &str, size: i64) -> Result<Vec<Value>, StorageError> {
+        let body = json!({
+            "query": { "term": { "kind.keyword": kind } },
+            "sort": [ { "updated_at": { "order": "desc", "unmapped_type": "date" } } ]
+        });
+        self.search_raw(POLICY_INDEX, body, size).await
+    }
+
+    pub async fn store_system_info(&self, info: &SystemInformation) -> Result<(), SharedStorageError> {
+        self.write_batch(vec![StorageDocument::new("system_metrics", info)?]).await
+    }
+
+    // Every run of envelopes bound for one index is written as one batch of their events
+    pub async fn store_envelopes(&self, envelopes: &[EventEnvelope]) -> Result<(), SharedStorageError> {
+        for run in envelopes.chunk_by(|a, b| a.index == b.index) {
+            self.write_batch(StorageDocument::from_envelopes(run)?).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DataStorage<StorageDocument> for ElasticsearchStorage {
+    async fn store(&self, data: StorageDocument) -> Result<(), SharedStorageError> {
+        match self.index_document(&data.index, &data.body).await {
+            Err(StorageError::Rejected(reason)) => {
+                self.dead_letter(&[(&data, reason)]).await;
+                Ok(())
+            }
+            result => result.map_err(Into::into),
+        }
+    }
+
+    async fn batch_store(&self, data: Vec<StorageDocument>) -> Result<(), SharedStorageError> {
+        self.write_batch(data).await
+    }
+
+    async fn health_check(&self) -> bool {
+        match self.client.ping().send().await {
+            Ok(response) => response.status_code().is_success(),
+            Err(_) => false,
+        }
+    }
+}
+
+// Envelopes and snapshots go through the inherent methods for per-index batch logging
+#[async_trait]
+impl StorageBackend for ElasticsearchStorage {
+    fn name(&self) -> &str {
+        "elasticsearch"
+    }
+
+    async fn store_system_info(&self, info: &SystemInformation) -> Result<(), SharedStorageError> {
+        ElasticsearchStorage::store_system_info(self, info).await
+    }
+
+    async fn store_envelopes(&self, envelopes: &[EventEnvelope]) -> Result<(), SharedStorageError> {
+        ElasticsearchStorage::store_envelopes(self, envelopes).await
+    }
 }
@@ -0,0 +1,352 @@
+use crate::shared::error::StorageError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::storage::backend::{BackendKind, StorageBackend, StorageDocument};
+use crate::shared::storage::elasticsearch_storage::{ElasticsearchConfig, SystemInformation};
+use crate::shared::storage::shipping::ShippingConfig;
+use crate::shared::storage::spool::{Spool, SpoolConfig};
+use crate::shared::traits::{DataStorage, EventEnvelope};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+pub const ANNOTATION_INDEX: &str = "sink_annotations";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FailoverConfig {
+    pub enabled: bool,
+    // Hot standby that takes writes while the primary backend fails
+    pub secondary: BackendKind,
+    // Connection for a secondary Elasticsearch cluster; file and syslog secondaries use
+    // the regular storage.file / storage.syslog settings
+    pub elasticsearch: ElasticsearchConfig,
+    // How often the primary is retried while writes go to the secondary
+    pub retry_primary_secs: u64,
+    // Journal documents written to the secondary and replay them to the primary once it is back
+    pub catch_up: bool,
+    pub journal: SpoolConfig,
+    // Schedules and bandwidth caps for the secondary backend
+    pub shipping: ShippingConfig,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            secondary: BackendKind::File,
+            elasticsearch: ElasticsearchConfig::default(),
+            retry_primary_secs: 60,
+            catch_up: true,
+            journal: SpoolConfig {
+                directory: String::from("data/failover"),
+                ..SpoolConfig::default()
+            },
+            shipping: ShippingConfig {
+                queue: SpoolConfig {
+                    directory: String::from("data/failover-deferred"),
+                    ..SpoolConfig::default()
+                },
+                ..ShippingConfig::default()
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkTransition {
+    FailedOver,
+    Restored,
+}
+
+// Written to the sinks on every switch so the split of an outage's data can be traced:
+// which sink took over, when, and how many documents of each index went where
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinkAnnotation {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub source: String,
+    pub transition: SinkTransition,
+    pub primary: String,
+    pub secondary: String,
+    pub active: String,
+    pub reason: Option<String>,
+    pub outage_started: Option<DateTime<Utc>>,
+    pub outage_ended: Option<DateTime<Utc>>,
+    // Documents the secondary received during the outage, per index
+    pub secondary_documents: BTreeMap<String, usize>,
+    // Of those, how many were replayed to the primary
+    pub caught_up: usize,
+}
+
+struct FailoverState {
+    on_secondary: bool,
+    outage_started: Option<DateTime<Utc>>,
+    last_primary_attempt: Instant,
+    secondary_documents: BTreeMap<String, usize>,
+    // A write is replaying the journal to the primary, the others leave it to that one
+    restoring: bool,
+}
+
+// Sends writes to the primary backend and, while it fails, to the secondary instead.
+// The primary is probed again every retry_primary_secs; once it accepts a write the
+// journal of documents the secondary took is replayed to it, so the primary ends up
+// complete and the secondary keeps its copy. Only when both fail does the error reach
+// the caller, and with it the outer spool.
+pub struct FailoverBackend {
+    primary: Box<dyn StorageBackend>,
+    secondary: Box<dyn StorageBackend>,
+    journal: Option<Spool>,
+    retry_interval: Duration,
+    label: String,
+    state: Mutex<FailoverState>,
+    host: Arc<HostIdentityService>,
+}
+
+impl FailoverBackend {
+    pub fn new(
+        primary: Box<dyn StorageBackend>,
+        secondary: Box<dyn StorageBackend>,
+        config: &FailoverConfig,
+    ) -> Result<Self, StorageError> {
+        let journal = if config.catch_up { Some(Spool::new(config.journal.clone())?) } else { None };
+        let label = format!("{} (failover {})", primary.name(), secondary.name());
+        Ok(Self {
+            primary,
+            secondary,
+            journal,
+            retry_interval: Duration::from_secs(config.retry_primary_secs),
+            label,
+            state: Mutex::new(FailoverState {
+                on_secondary: false,
+                outage_started: None,
+                last_primary_attempt: Instant::now(),
+                secondary_documents: BTreeMap::new(),
+                restoring: false,
+            }),
+            host: HostIdentityService::global(),
+        })
+    }
+
+    pub fn on_secondary(&self) -> bool {
+        self.state.lock().map(|state| state.on_secondary).unwrap_or(false)
+    }
+
+    // Whether this write should try the primary: always while it is healthy, and once per
+    // retry interval while failed over
+    fn use_primary(&self) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return true;
+        };
+        if !state.on_secondary {
+            return true;
+        }
+        if state.last_primary_attempt.elapsed() < self.retry_interval {
+            return false;
+        }
+        state.last_primary_attempt = Instant::now();
+        true
+    }
+
+    fn annotation(&self, transition: SinkTransition, state: &FailoverState, reason: Option<String>) -> SinkAnnotation {
+        let active = if state.on_secondary { self.secondary.name() } else { self.primary.name() };
+        SinkAnnotation {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            source: self.host.name(),
+            transition,
+            primary: self.primary.name().to_string(),
+            secondary: self.secondary.name().to_string(),
+            active: active.to_string(),
+            reason,
+            outage_started: state.outage_started,
+            outage_ended: None,
+            secondary_documents: state.secondary_documents.clone(),
+            caught_up: 0,
+        }
+    }
+
+    async fn annotate(&self, target: &dyn StorageBackend, annotation: &SinkAnnotation) {
+        let result = match StorageDocument::new(ANNOTATION_INDEX, annotation) {
+            Ok(document) => target.store(document).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!("Failed to store sink annotation in {} storage: {}", target.name(), e);
+        }
+    }
+
+    // Ok when the primary took the write. A failure switches to the secondary and leaves it
+    // the whole write, or only the documents the primary turned away when it stored the rest.
+    async fn primary_result(&self, result: Result<(), StorageError>) -> Result<(), Option<Vec<StorageDocument>>> {
+        match result {
+            Ok(()) => {
+                let restored = match self.state.lock() {
+                    Ok(mut state) if state.on_secondary && !state.restoring => {
+                        state.restoring = true;
+                        true
+                    }
+                    _ => false,
+                };
+                if restored {
+                    self.restore().await;
+                }
+                Ok(())
+            }
+            Err(e) => {
+                let reason = e.to_string();
+                let remaining = match e {
+                    StorageError::Partial { remaining, .. } => Some(remaining),
+                    _ => None,
+                };
+                let annotation = {
+                    let Ok(mut state) = self.state.lock() else {
+                        return Err(remaining);
+                    };
+                    if state.on_secondary {
+                        return Err(remaining);
+                    }
+                    state.on_secondary = true;
+                    state.outage_started = Some(Utc::now());
+                    state.last_primary_attempt = Instant::now();
+                    state.secondary_documents.clear();
+                    self.annotation(SinkTransition::FailedOver, &state, Some(reason.clone()))
+                };
+                error!("{} storage failed, failing over to {} storage: {}", self.primary.name(), self.secondary.name(), reason);
+                self.annotate(self.secondary.as_ref(), &annotation).await;
+                Err(remaining)
+            }
+        }
+    }
+
+    // The primary is back: replay what the secondary took meanwhile, then record the outage
+    // on both sinks. A failed replay leaves the rest of the journal for the next write.
+    // Only the write that set state.restoring gets here.
+    async fn restore(&self) {
+        let caught_up = match &self.journal {
+            Some(journal) => match journal.replay(self.primary.as_ref()).await {
+                Ok(replayed) => replayed,
+                Err(e) => {
+                    warn!("Catch-up to {} storage interrupted: {}", self.primary.name(), e);
+                    if let Ok(mut state) = self.state.lock() {
+                        state.restoring = false;
+                    }
+                    return;
+                }
+            },
+            None => 0,
+        };
+
+        let annotation = {
+            let Ok(mut state) = self.state.lock() else {
+                return;
+            };
+            state.on_secondary = false;
+            state.restoring = false;
+            let mut annotation = self.annotation(SinkTransition::Restored, &state, None);
+            annotation.outage_ended = Some(Utc::now());
+            annotation.caught_up = caught_up;
+            state.outage_started = None;
+            state.secondary_documents.clear();
+            annotation
+        };
+        info!(
+            "{} storage restored, caught up {} documents written to {} storage",
+            self.primary.name(),
+            caught_up,
+            self.secondary.name()
+        );
+        self.annotate(self.primary.as_ref(), &annotation).await;
+        self.annotate(self.secondary.as_ref(), &annotation).await;
+    }
+
+    async fn secondary_result(
+        &self,
+        result: Result<(), StorageError>,
+        documents: impl FnOnce() -> Result<Vec<StorageDocument>, StorageError>,
+    ) -> Result<(), StorageError> {
+        result?;
+        let documents = documents()?;
+        if let Ok(mut state) = self.state.lock() {
+            for document in &documents {
+                *state.secondary_documents.entry(document.index.clone()).or_default() += 1;
+            }
+        }
+        if let Some(journal) = &self.journal {
+            journal.enqueue(&documents).await?;
+        }
+        Ok(())
+    }
+
+    async fn secondary_documents(&self, documents: Vec<StorageDocument>) -> Result<(), StorageError> {
+        let result = self.secondary.batch_store(documents.clone()).await;
+        self.secondary_result(result, || Ok(documents)).await
+    }
+}
+
+#[async_trait]
+impl DataStorage<StorageDocument> for FailoverBackend {
+    async fn store(&self, data: StorageDocument) -> Result<(), StorageError> {
+        if self.use_primary() {
+            match self.primary_result(self.primary.store(data.clone()).await).await {
+                Ok(()) => return Ok(()),
+                Err(Some(remaining)) => return self.secondary_documents(remaining).await,
+                Err(None) => {}
+            }
+        }
+        let result = self.secondary.store(data.clone()).await;
+        self.secondary_result(result, || Ok(vec![data])).await
+    }
+
+    async fn batch_store(&self, data: Vec<StorageDocument>) -> Result<(), StorageError> {
+        if self.use_primary() {
+            match self.primary_result(self.primary.batch_store(data.clone()).await).await {
+                Ok(()) => return Ok(()),
+                Err(Some(remaining)) => return self.secondary_documents(remaining).await,
+                Err(None) => {}
+            }
+        }
+        let result = self.secondary.batch_store(data.clone()).await;
+        self.secondary_result(result, || Ok(data)).await
+    }
+
+    async fn health_check(&self) -> bool {
+        self.primary.health_check().await || self.secondary.health_check().await
+    }
+}
+
+#[async_trait]
+impl StorageBackend for FailoverBackend {
+    fn name(&self) -> &str {
+        &self.label
+    }
+
+    async fn store_system_info(&self, info: &SystemInformation) -> Result<(), StorageError> {
+        if self.use_primary() {
+            match self.primary_result(self.primary.store_system_info(info).await).await {
+                Ok(()) => return Ok(()),
+                Err(Some(remaining)) => return self.secondary_documents(remaining).await,
+                Err(None) => {}
+            }
+        }
+        let result = self.secondary.store_system_info(info).await;
+        self.secondary_result(result, || Ok(vec![StorageDocument::new("system_metrics", info)?])).await
+    }
+
+    async fn store_envelopes(&self, envelopes: &[EventEnvelope]) -> Result<(), StorageError> {
+        if self.use_primary() {
+            match self.primary_result(self.primary.store_envelopes(envelopes).await).await {
+                Ok(()) => return Ok(()),
+                Err(Some(remaining)) => return self.secondary_documents(remaining).await,
+                Err(None) => {}
+            }
+        }
+        let result = self.secondary.store_envelopes(envelopes).await;
+        self.secondary_result(result, || StorageDocument::from_envelopes(envelopes)).await
+    }
+}
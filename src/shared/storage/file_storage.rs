@@ -0,0 +1,85 @@
+use crate::shared::error::StorageError;
+use crate::shared::storage::backend::{StorageBackend, StorageDocument};
+use crate::shared::traits::DataStorage;
+use async_trait::async_trait;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileStorageConfig {
+    pub directory: String,
+}
+
+impl Default for FileStorageConfig {
+    fn default() -> Self {
+        Self {
+            directory: String::from("data/events"),
+        }
+    }
+}
+
+// Appends each document as one JSON line to `<directory>/<index>.jsonl`
+pub struct FileStorage {
+    directory: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl FileStorage {
+    pub fn new(config: FileStorageConfig) -> Result<Self, StorageError> {
+        let directory = PathBuf::from(&config.directory);
+        std::fs::create_dir_all(&directory)
+            .map_err(|e| StorageError::Connection(format!("Failed to create {}: {}", directory.display(), e)))?;
+        Ok(Self {
+            directory,
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    async fn append(&self, documents: &[StorageDocument]) -> Result<(), StorageError> {
+        let _guard = self.write_lock.lock().await;
+        for document in documents {
+            let path = self.directory.join(format!("{}.jsonl", document.index));
+            let mut line = serde_json::to_vec(&document.body)
+                .map_err(|e| StorageError::Write(e.to_string()))?;
+            line.push(b'\n');
+
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .await
+                .map_err(|e| StorageError::Write(format!("Failed to open {}: {}", path.display(), e)))?;
+            file.write_all(&line)
+                .await
+                .map_err(|e| StorageError::Write(format!("Failed to write {}: {}", path.display(), e)))?;
+        }
+        debug!("Appended {} documents to {}", documents.len(), self.directory.display());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DataStorage<StorageDocument> for FileStorage {
+    async fn store(&self, data: StorageDocument) -> Result<(), StorageError> {
+        self.append(std::slice::from_ref(&data)).await
+    }
+
+    async fn batch_store(&self, data: Vec<StorageDocument>) -> Result<(), StorageError> {
+        self.append(&data).await
+    }
+
+    async fn health_check(&self) -> bool {
+        fs::metadata(&self.directory).await.map(|m| m.is_dir()).unwrap_or(false)
+    }
+}
+
+impl StorageBackend for FileStorage {
+    fn name(&self) -> &str {
+        "file"
+    }
+}
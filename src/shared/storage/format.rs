@@ -0,0 +1,80 @@
+use crate::features::{filesystem::FileEvent, registry::RegistryEvent};
+use crate::shared::error::StorageError;
+use crate::shared::mapping::ecs::{self, EcsHost, ToEcs};
+use crate::shared::storage::backend::{StorageBackend, StorageDocument};
+use crate::shared::storage::elasticsearch_storage::SystemInformation;
+use crate::shared::traits::{DataStorage, EventEnvelope};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+// Shape of the documents written for file, registry and system_metrics data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentFormat {
+    // The agent's own models, serialized as they are
+    Native,
+    // Elastic Common Schema, for the Elastic Security app and its prebuilt rules
+    Ecs,
+}
+
+// Writes file and registry events and system snapshots as ECS documents to the same logical
+// indices; every other kind of document passes through unchanged
+pub struct EcsBackend {
+    inner: Box<dyn StorageBackend>,
+}
+
+impl EcsBackend {
+    pub fn new(inner: Box<dyn StorageBackend>) -> Self {
+        Self { inner }
+    }
+
+    // The envelope with its event in ECS form, for the event types that have one
+    fn ecs_envelope(envelope: &EventEnvelope, host: &EcsHost) -> Result<EventEnvelope, StorageError> {
+        let event = match envelope.index.as_str() {
+            "file_events" => envelope.open::<FileEvent>().map(|event| event.to_ecs(host)),
+            "registry_events" => envelope.open::<RegistryEvent>().map(|event| event.to_ecs(host)),
+            _ => return Ok(envelope.clone()),
+        }
+        .map_err(|e| StorageError::Write(format!("Failed to map {} event to ECS: {}", envelope.index, e)))?;
+        Ok(EventEnvelope {
+            event,
+            ..envelope.clone()
+        })
+    }
+}
+
+#[async_trait]
+impl DataStorage<StorageDocument> for EcsBackend {
+    async fn store(&self, data: StorageDocument) -> Result<(), StorageError> {
+        self.inner.store(data).await
+    }
+
+    async fn batch_store(&self, data: Vec<StorageDocument>) -> Result<(), StorageError> {
+        self.inner.batch_store(data).await
+    }
+
+    async fn health_check(&self) -> bool {
+        self.inner.health_check().await
+    }
+}
+
+#[async_trait]
+impl StorageBackend for EcsBackend {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn store_system_info(&self, info: &SystemInformation) -> Result<(), StorageError> {
+        let bodies = ecs::system_information(info, &EcsHost::current());
+        self.inner.batch_store(StorageDocument::many("system_metrics", &bodies)?).await
+    }
+
+    async fn store_envelopes(&self, envelopes: &[EventEnvelope]) -> Result<(), StorageError> {
+        let host = EcsHost::current();
+        let envelopes = envelopes
+            .iter()
+            .map(|envelope| Self::ecs_envelope(envelope, &host))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.inner.store_envelopes(&envelopes).await
+    }
+}
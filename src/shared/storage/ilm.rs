@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+// Indices written by the agent that get a lifecycle policy of their own
+pub const MANAGED_INDICES: &[&str] = &[
+    "system_metrics",
+    "system_host_metrics",
+    "system_processes",
+    "system_services",
+    "system_connections",
+    "system_interfaces",
+    "file_events",
+    "registry_events",
+    "suspicious_registry_operations",
+    "persistence_audit",
+    "exec_events",
+    "ingested_events",
+    "scheduled_tasks",
+    "service_events",
+    "osquery_results",
+    "honeypot_events",
+    "usb_events",
+    "deception_events",
+    "process_events",
+    "capture_events",
+    "keylogging_events",
+    "print_spooler_events",
+    "boot_events",
+    "persistence_events",
+    "log_events",
+    "listening_port_events",
+    "process_network_usage",
+    "snmp_trap_events",
+    "directory_events",
+    "tls_probe_events",
+    "alerts",
+    "incidents",
+    "ioc_matches",
+    "retrieved_files",
+    "action_audit",
+    "sink_annotations",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IlmConfig {
+    pub apply_on_startup: bool,
+    pub attach_to_existing_indices: bool,
+    pub default: IlmPhases,
+    pub indices: HashMap<String, IlmPhaseOverrides>,
+}
+
+impl Default for IlmConfig {
+    fn default() -> Self {
+        Self {
+            apply_on_startup: false,
+            attach_to_existing_indices: false,
+            default: IlmPhases::default(),
+            indices: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IlmPhases {
+    pub rollover_max_age: Option<String>,
+    pub rollover_max_primary_shard_size: Option<String>,
+    pub warm_after: String,
+    pub delete_after: String,
+}
+
+impl Default for IlmPhases {
+    fn default() -> Self {
+        Self {
+            rollover_max_age: Some(String::from("1d")),
+            rollover_max_primary_shard_size: Some(String::from("50gb")),
+            warm_after: String::from("7d"),
+            delete_after: String::from("30d"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IlmPhaseOverrides {
+    pub rollover_max_age: Option<String>,
+    pub rollover_max_primary_shard_size: Option<String>,
+    pub warm_after: Option<String>,
+    pub delete_after: Option<String>,
+}
+
+impl IlmConfig {
+    pub fn policy_name(index: &str) -> String {
+        format!("lsedr-{}-policy", index.replace('_', "-"))
+    }
+
+    pub fn phases_for(&self, index: &str) -> IlmPhases {
+        let mut phases = self.default.clone();
+        if let Some(overrides) = self.indices.get(index) {
+            if overrides.rollover_max_age.is_some() {
+                phases.rollover_max_age = overrides.rollover_max_age.clone();
+            }
+            if overrides.rollover_max_primary_shard_size.is_some() {
+                phases.rollover_max_primary_shard_size = overrides.rollover_max_primary_shard_size.clone();
+            }
+            if let Some(warm_after) = &overrides.warm_after {
+                phases.warm_after = warm_after.clone();
+            }
+            if let Some(delete_after) = &overrides.delete_after {
+                phases.delete_after = delete_after.clone();
+            }
+        }
+        phases
+    }
+}
+
+impl IlmPhases {
+    pub fn policy_body(&self) -> Value {
+        let mut rollover = serde_json::Map::new();
+        if let Some(max_age) = &self.rollover_max_age {
+            rollover.insert("max_age".to_string(), json!(max_age));
+        }
+        if let Some(max_size) = &self.rollover_max_primary_shard_size {
+            rollover.insert("max_primary_shard_size".to_string(), json!(max_size));
+        }
+
+        let mut hot_actions = json!({ "set_priority": { "priority": 100 } });
+        if !rollover.is_empty() {
+            hot_actions["rollover"] = Value::Object(rollover);
+        }
+
+        json!({
+            "policy": {
+                "_meta": { "managed_by": "lsedr" },
+                "phases": {
+                    "hot": {
+                        "min_age": "0ms",
+                        "actions": hot_actions
+                    },
+                    "warm": {
+                        "min_age": self.warm_after,
+                        "actions": {
+                            "set_priority": { "priority": 50 },
+                            "forcemerge": { "max_num_segments": 1 }
+                        }
+                    },
+                    "delete": {
+                        "min_age": self.delete_after,
+                        "actions": { "delete": {} }
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,69 @@
+use crate::shared::error::StorageError;
+use crate::shared::storage::backend::StorageDocument;
+use crate::shared::storage::elasticsearch_storage::SystemInformation;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+// How a system snapshot is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotLayout {
+    // One system_metrics document holding the whole snapshot
+    Combined,
+    // One document per process, service, connection and interface and one with the host
+    // metrics, each kind in its own index
+    Split,
+}
+
+fn document(index: &str, header: &Map<String, Value>, fields: Value) -> Result<StorageDocument, StorageError> {
+    let mut body = header.clone();
+    if let Value::Object(fields) = fields {
+        body.extend(fields);
+    }
+    StorageDocument::new(index, &body)
+}
+
+// Breaks a snapshot into the documents of the split layout. Every document carries the host
+// and a collection_id shared by all documents of the snapshot, to put it back together.
+pub fn split_snapshot(info: &SystemInformation) -> Result<Vec<StorageDocument>, StorageError> {
+    let mut header = Map::new();
+    header.insert(String::from("collection_id"), json!(Uuid::new_v4().to_string()));
+    header.insert(String::from("timestamp"), json!(DateTime::<Utc>::from(info.timestamp)));
+    header.insert(String::from("hostname"), json!(info.hostname));
+    header.insert(String::from("fqdn"), json!(info.fqdn));
+
+    let mut documents = vec![document(
+        "system_host_metrics",
+        &header,
+        json!({
+            "os_name": info.os_name,
+            "os_version": info.os_version,
+            "kernel_version": info.kernel_version,
+            "cpu_info": info.cpu_info,
+            "memory_info": info.memory_info,
+            "disk_info": info.disk_info,
+            "system_load": info.system_load,
+            // So a partly written collection can be told from a complete one
+            "process_count": info.process_info.len(),
+            "service_count": info.services.len(),
+            "services_unchanged": info.services_unchanged,
+            "connection_count": info.network_connections.len(),
+            "interface_count": info.network_info.len(),
+        }),
+    )?];
+    for process in &info.process_info {
+        documents.push(document("system_processes", &header, json!({ "process": process }))?);
+    }
+    for service in &info.services {
+        documents.push(document("system_services", &header, json!({ "service": service }))?);
+    }
+    for connection in &info.network_connections {
+        documents.push(document("system_connections", &header, json!({ "connection": connection }))?);
+    }
+    for interface in &info.network_info {
+        documents.push(document("system_interfaces", &header, json!({ "interface": interface }))?);
+    }
+    Ok(documents)
+}
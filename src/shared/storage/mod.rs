@@ -1,3 +1,39 @@
 mod elasticsearch_storage;
+mod file_storage;
+mod stdout_storage;
+mod syslog_storage;
+pub mod backend;
+pub mod delta;
+pub mod failover;
+pub mod format;
+pub mod ilm;
+pub mod layout;
+pub mod otlp;
+pub mod pipeline;
+pub mod query;
+pub mod retry;
+pub mod shipping;
+pub mod spool;
+pub mod templates;
+pub mod truncation;
 
-pub use elasticsearch_storage::{ElasticsearchStorage, StorageError, SystemInformation};
+pub use elasticsearch_storage::{
+    ElasticsearchConfig, ElasticsearchStorage, ElasticsearchTlsConfig, LoggingConfig, StorageError, StorageLoggingConfig, SystemInformation,
+};
+pub use file_storage::{FileStorage, FileStorageConfig};
+pub use stdout_storage::StdoutStorage;
+pub use syslog_storage::{SyslogConfig, SyslogStorage, SyslogTlsConfig, SyslogTransport};
+pub use backend::{create_backend, create_direct_backend, BackendKind, StorageBackend, StorageConfig, StorageDocument};
+pub use delta::{DeltaSection, SnapshotDeltaConfig, SnapshotDeltaEncoder};
+pub use failover::{FailoverBackend, FailoverConfig, SinkAnnotation, SinkTransition};
+pub use format::{DocumentFormat, EcsBackend};
+pub use ilm::IlmConfig;
+pub use layout::{split_snapshot, SnapshotLayout};
+pub use otlp::{OtlpConfig, OtlpProtocol, OtlpStorage, OtlpTlsConfig};
+pub use pipeline::{PipelineConfig, DEFAULT_PIPELINE_ID};
+pub use query::{EventQuery, POLICY_INDEX};
+pub use retry::RetryPolicy;
+pub use shipping::{ShapedBackend, ShippingConfig, ShippingWindow};
+pub use spool::{Spool, SpoolConfig, SpooledBackend};
+pub use templates::{IndexNaming, IndexNamingConfig, TemplateConfig};
+pub use truncation::TruncationConfig;
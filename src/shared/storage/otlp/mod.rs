@@ -0,0 +1,379 @@
+mod proto;
+mod transport;
+
+use crate::shared::error::StorageError;
+use crate::shared::mapping::ecs::EcsHost;
+use crate::shared::protobuf::Message;
+use crate::shared::storage::backend::{StorageBackend, StorageDocument};
+use crate::shared::storage::elasticsearch_storage::SystemInformation;
+use crate::shared::traits::{DataStorage, EventEnvelope, Severity};
+use crate::utils::time::parse_timestamp;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use proto::{DataPoint, LogRecord, NumberValue};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use transport::{OtlpTransport, Signal};
+
+const SCOPE_NAME: &str = "lsedr";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OtlpProtocol {
+    // OTLP/gRPC, usually port 4317
+    Grpc,
+    // OTLP/HTTP with protobuf bodies, usually port 4318
+    Http,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OtlpTlsConfig {
+    // PEM bundle of extra CAs to trust besides the system store
+    pub ca_file: Option<String>,
+    // Name to verify the collector certificate against, defaults to the endpoint host
+    pub server_name: Option<String>,
+    // Only honoured over HTTP
+    pub accept_invalid_certs: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OtlpConfig {
+    pub protocol: OtlpProtocol,
+    // Collector base URL; TLS is used for https:// endpoints
+    pub endpoint: String,
+    // Sent with every export, e.g. Authorization for a hosted collector
+    pub headers: BTreeMap<String, String>,
+    pub timeout_secs: u64,
+    // service.name of the exported resource
+    pub service_name: String,
+    // Indices exported as log records, empty exports everything; system snapshots are
+    // always exported as metrics
+    pub indices: Vec<String>,
+    pub tls: OtlpTlsConfig,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self {
+            protocol: OtlpProtocol::Grpc,
+            endpoint: String::from("http://localhost:4317"),
+            headers: BTreeMap::new(),
+            timeout_secs: 10,
+            service_name: String::from("lsedr"),
+            indices: Vec::new(),
+            tls: OtlpTlsConfig::default(),
+        }
+    }
+}
+
+fn unix_nanos(timestamp: DateTime<Utc>) -> u64 {
+    timestamp.timestamp_nanos_opt().unwrap_or_default().max(0) as u64
+}
+
+// Exports system snapshots as OpenTelemetry metrics and every other document as an
+// OpenTelemetry log record, to an OTel Collector, Grafana Alloy or any other OTLP receiver
+pub struct OtlpStorage {
+    config: OtlpConfig,
+    transport: OtlpTransport,
+    // End of the interval covered by the last network counters, the start of the next
+    last_network_export: Mutex<Option<u64>>,
+}
+
+impl OtlpStorage {
+    pub fn new(config: OtlpConfig) -> Result<Self, StorageError> {
+        let transport = OtlpTransport::new(&config)?;
+        info!("Exporting to OTLP collector {} over {:?}", config.endpoint, config.protocol);
+        Ok(Self {
+            config,
+            transport,
+            last_network_export: Mutex::new(None),
+        })
+    }
+
+    // Semantic-convention resource attributes describing this agent and host
+    fn resource(&self) -> Message {
+        let host = EcsHost::current();
+        let mut attributes = vec![
+            proto::string_attribute("service.name", &self.config.service_name),
+            proto::string_attribute("service.version", env!("CARGO_PKG_VERSION")),
+            proto::string_attribute("host.name", &host.name),
+            proto::string_attribute("host.arch", std::env::consts::ARCH),
+            proto::string_attribute("os.type", host.os_type),
+        ];
+        if let Some(os_name) = &host.os_name {
+            attributes.push(proto::string_attribute("os.name", os_name));
+        }
+        if let Some(os_version) = &host.os_version {
+            attributes.push(proto::string_attribute("os.version", os_version));
+        }
+        proto::resource(&attributes)
+    }
+
+    fn scope() -> Message {
+        proto::scope(SCOPE_NAME, env!("CARGO_PKG_VERSION"))
+    }
+
+    fn is_selected(&self, document: &StorageDocument) -> bool {
+        self.config.indices.is_empty() || self.config.indices.iter().any(|index| index == &document.index)
+    }
+
+    // OTel severity numbers: INFO 9, WARN 13, ERROR 17, FATAL 21
+    fn severity(document: &StorageDocument) -> (u64, &'static str) {
+        let severity = document
+            .body
+            .get("severity")
+            .and_then(|value| serde_json::from_value::<Severity>(value.clone()).ok());
+        match severity {
+            Some(Severity::Critical) => (21, "FATAL"),
+            Some(Severity::High) => (17, "ERROR"),
+            Some(Severity::Medium) => (13, "WARN"),
+            Some(Severity::Low) | None => (9, "INFO"),
+        }
+    }
+
+    fn log_record(document: &StorageDocument, observed: u64) -> Message {
+        let time = document
+            .body
+            .get("timestamp")
+            .and_then(parse_timestamp)
+            .map(unix_nanos)
+            .unwrap_or(observed);
+        let (severity_number, severity_text) = Self::severity(document);
+        let mut attributes = vec![
+            proto::string_attribute("event.name", &format!("lsedr.{}", document.index)),
+            proto::string_attribute("lsedr.index", &document.index),
+        ];
+        if let Some(category) = document.body.get("category").and_then(Value::as_str) {
+            attributes.push(proto::string_attribute("lsedr.category", category));
+        }
+        proto::log_record(&LogRecord {
+            time_unix_nano: time,
+            observed_time_unix_nano: observed,
+            severity_number,
+            severity_text,
+            body: &document.body,
+            attributes,
+        })
+    }
+
+    fn metrics(&self, info: &SystemInformation) -> Vec<Message> {
+        let time = unix_nanos(DateTime::<Utc>::from(info.timestamp));
+        let point = |value: NumberValue, attributes: Vec<Message>| DataPoint {
+            attributes,
+            start_time_unix_nano: 0,
+            time_unix_nano: time,
+            value,
+        };
+        let state = |value: &str| vec![proto::string_attribute("state", value)];
+
+        let memory = &info.memory_info;
+        let mut metrics = vec![
+            proto::gauge(
+                "system.cpu.utilization",
+                "Share of CPU time in use across all cores",
+                "1",
+                &[point(NumberValue::Double(info.cpu_info.cpu_usage as f64 / 100.0), Vec::new())],
+            ),
+            proto::gauge(
+                "system.cpu.logical.count",
+                "Logical CPU cores",
+                "{cpu}",
+                &[point(NumberValue::Int(info.cpu_info.cpu_cores as i64), Vec::new())],
+            ),
+            proto::gauge(
+                "system.memory.usage",
+                "Physical memory in use and free",
+                "By",
+                &[
+                    point(NumberValue::Int(memory.used_memory as i64), state("used")),
+                    point(NumberValue::Int(memory.total_memory.saturating_sub(memory.used_memory) as i64), state("free")),
+                ],
+            ),
+            proto::gauge(
+                "system.paging.usage",
+                "Swap space in use and free",
+                "By",
+                &[
+                    point(NumberValue::Int(memory.used_swap as i64), state("used")),
+                    point(NumberValue::Int(memory.total_swap.saturating_sub(memory.used_swap) as i64), state("free")),
+                ],
+            ),
+            proto::gauge(
+                "system.process.count",
+                "Processes by state",
+                "{process}",
+                &[
+                    point(NumberValue::Int(info.system_load.running_processes as i64), state("running")),
+                    point(NumberValue::Int(info.system_load.total_processes as i64), state("all")),
+                ],
+            ),
+        ];
+        if memory.total_memory > 0 {
+            metrics.push(proto::gauge(
+                "system.memory.utilization",
+                "Share of physical memory in use",
+                "1",
+                &[point(NumberValue::Double(memory.used_memory as f64 / memory.total_memory as f64), state("used"))],
+            ));
+        }
+
+        // Windows has no load average and reports zeros
+        let load = &info.system_load;
+        if load.one_minute > 0.0 || load.five_minutes > 0.0 || load.fifteen_minutes > 0.0 {
+            for (name, value) in [
+                ("system.cpu.load_average.1m", load.one_minute),
+                ("system.cpu.load_average.5m", load.five_minutes),
+                ("system.cpu.load_average.15m", load.fifteen_minutes),
+            ] {
+                metrics.push(proto::gauge(name, "Run queue load average", "{thread}", &[point(NumberValue::Double(value as f64), Vec::new())]));
+            }
+        }
+
+        let disk_points = |used: bool| -> Vec<DataPoint> {
+            info.disk_info
+                .iter()
+                .map(|disk| {
+                    let value = if used {
+                        disk.total_space.saturating_sub(disk.available_space)
+                    } else {
+                        disk.available_space
+                    };
+                    point(
+                        NumberValue::Int(value as i64),
+                        vec![
+                            proto::string_attribute("system.device", &disk.name),
+                            proto::string_attribute("system.filesystem.mountpoint", &disk.mount_point),
+                            proto::string_attribute("system.filesystem.type", &disk.file_system),
+                            proto::string_attribute("state", if used { "used" } else { "free" }),
+                        ],
+                    )
+                })
+                .collect()
+        };
+        if !info.disk_info.is_empty() {
+            let mut points = disk_points(true);
+            points.extend(disk_points(false));
+            metrics.push(proto::gauge("system.filesystem.usage", "Filesystem space in use and free", "By", &points));
+        }
+
+        // The collector reports traffic since its previous refresh, which makes these delta sums
+        if !info.network_info.is_empty() {
+            let start = self
+                .last_network_export
+                .lock()
+                .ok()
+                .and_then(|mut last| last.replace(time))
+                .unwrap_or(time);
+            let network_points = |transmit: bool| -> Vec<DataPoint> {
+                info.network_info
+                    .iter()
+                    .map(|interface| {
+                        let value = if transmit { interface.transmitted_bytes } else { interface.received_bytes };
+                        DataPoint {
+                            attributes: vec![
+                                proto::string_attribute("system.device", &interface.interface_name),
+                                proto::string_attribute("network.io.direction", if transmit { "transmit" } else { "receive" }),
+                            ],
+                            start_time_unix_nano: start,
+                            time_unix_nano: time,
+                            value: NumberValue::Int(value as i64),
+                        }
+                    })
+                    .collect()
+            };
+            let mut points = network_points(false);
+            points.extend(network_points(true));
+            metrics.push(proto::monotonic_sum("system.network.io", "Bytes sent and received", "By", proto::DELTA, &points));
+        }
+        metrics
+    }
+
+    async fn export(&self, signal: Signal, request: Vec<u8>, count: usize) -> Result<(), StorageError> {
+        let response = self.transport.export(signal, request).await?;
+        match proto::partial_success(&response) {
+            Some((rejected, message)) => warn!(
+                "OTLP collector rejected {} of {} {:?} items: {}",
+                rejected, count, signal, message
+            ),
+            None => debug!("Exported {} {:?} items over OTLP", count, signal),
+        }
+        Ok(())
+    }
+
+    async fn export_metrics(&self, info: &SystemInformation) -> Result<(), StorageError> {
+        let metrics = self.metrics(info);
+        let request = proto::export_metrics(&self.resource(), &Self::scope(), &metrics);
+        self.export(Signal::Metrics, request, metrics.len()).await
+    }
+
+    async fn send(&self, documents: &[StorageDocument]) -> Result<(), StorageError> {
+        let observed = unix_nanos(Utc::now());
+        let mut records = Vec::new();
+        for document in documents.iter().filter(|document| self.is_selected(document)) {
+            // Snapshots replayed from the spool arrive as documents; delta-encoded ones cannot
+            // be read back and go out as log records
+            if document.index == "system_metrics" {
+                if let Ok(info) = serde_json::from_value::<SystemInformation>(document.body.clone()) {
+                    self.export_metrics(&info).await?;
+                    continue;
+                }
+            }
+            records.push(Self::log_record(document, observed));
+        }
+        if records.is_empty() {
+            return Ok(());
+        }
+        let request = proto::export_logs(&self.resource(), &Self::scope(), &records);
+        self.export(Signal::Logs, request, records.len()).await
+    }
+}
+
+#[async_trait]
+impl DataStorage<StorageDocument> for OtlpStorage {
+    async fn store(&self, data: StorageDocument) -> Result<(), StorageError> {
+        self.send(std::slice::from_ref(&data)).await
+    }
+
+    async fn batch_store(&self, data: Vec<StorageDocument>) -> Result<(), StorageError> {
+        self.send(&data).await
+    }
+
+    // An empty export is a valid request every receiver answers
+    async fn health_check(&self) -> bool {
+        let request = proto::export_logs(&self.resource(), &Self::scope(), &[]);
+        match self.transport.export(Signal::Logs, request).await {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("OTLP health check failed: {}", e);
+                false
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for OtlpStorage {
+    fn name(&self) -> &str {
+        "otlp"
+    }
+
+    async fn store_system_info(&self, info: &SystemInformation) -> Result<(), StorageError> {
+        self.export_metrics(info).await
+    }
+
+    // Envelopes carry their severity into the record's severity number
+    async fn store_envelopes(&self, envelopes: &[EventEnvelope]) -> Result<(), StorageError> {
+        let mut documents = StorageDocument::from_envelopes(envelopes)?;
+        for (document, envelope) in documents.iter_mut().zip(envelopes) {
+            if let Value::Object(fields) = &mut document.body {
+                fields.insert(String::from("severity"), json!(envelope.severity));
+            }
+        }
+        self.send(&documents).await
+    }
+}
@@ -0,0 +1,148 @@
+use crate::shared::protobuf::{fields, Message};
+use serde_json::Value;
+
+// The handful of OTLP protobuf messages the exporter sends, encoded by hand; field numbers
+// follow opentelemetry-proto (common/v1, resource/v1, logs/v1, metrics/v1)
+
+// Aggregation temporality of a sum
+pub const DELTA: u64 = 1;
+
+// common.v1.AnyValue from a JSON value; objects become key-value lists so collectors can
+// address nested fields
+pub fn any_value(value: &Value) -> Message {
+    match value {
+        Value::String(text) => Message::new().string(1, text),
+        Value::Bool(flag) => Message::new().bool(2, *flag),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => Message::new().int64(3, integer),
+            None => Message::new().double(4, number.as_f64().unwrap_or_default()),
+        },
+        Value::Array(items) => {
+            let values: Vec<Message> = items.iter().map(any_value).collect();
+            Message::new().message(5, &Message::new().messages(1, &values))
+        }
+        Value::Object(fields) => {
+            let values: Vec<Message> = fields
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| key_value(key, value))
+                .collect();
+            Message::new().message(6, &Message::new().messages(1, &values))
+        }
+        Value::Null => Message::new(),
+    }
+}
+
+// common.v1.KeyValue
+pub fn key_value(key: &str, value: &Value) -> Message {
+    Message::new().string(1, key).message(2, &any_value(value))
+}
+
+pub fn string_attribute(key: &str, value: &str) -> Message {
+    Message::new().string(1, key).message(2, &Message::new().string(1, value))
+}
+
+// resource.v1.Resource
+pub fn resource(attributes: &[Message]) -> Message {
+    Message::new().messages(1, attributes)
+}
+
+// common.v1.InstrumentationScope
+pub fn scope(name: &str, version: &str) -> Message {
+    Message::new().string(1, name).string(2, version)
+}
+
+pub struct LogRecord<'a> {
+    pub time_unix_nano: u64,
+    pub observed_time_unix_nano: u64,
+    pub severity_number: u64,
+    pub severity_text: &'a str,
+    pub body: &'a Value,
+    pub attributes: Vec<Message>,
+}
+
+// logs.v1.LogRecord
+pub fn log_record(record: &LogRecord) -> Message {
+    Message::new()
+        .fixed64(1, record.time_unix_nano)
+        .varint(2, record.severity_number)
+        .string(3, record.severity_text)
+        .message(5, &any_value(record.body))
+        .messages(6, &record.attributes)
+        .fixed64(11, record.observed_time_unix_nano)
+}
+
+// collector.logs.v1.ExportLogsServiceRequest with one resource and one scope
+pub fn export_logs(resource: &Message, scope: &Message, records: &[Message]) -> Vec<u8> {
+    let scope_logs = Message::new().message(1, scope).messages(2, records);
+    let resource_logs = Message::new().message(1, resource).message(2, &scope_logs);
+    Message::new().message(1, &resource_logs).into_bytes()
+}
+
+pub enum NumberValue {
+    Double(f64),
+    Int(i64),
+}
+
+pub struct DataPoint {
+    pub attributes: Vec<Message>,
+    pub start_time_unix_nano: u64,
+    pub time_unix_nano: u64,
+    pub value: NumberValue,
+}
+
+// metrics.v1.NumberDataPoint
+fn data_point(point: &DataPoint) -> Message {
+    let message = Message::new()
+        .fixed64(2, point.start_time_unix_nano)
+        .fixed64(3, point.time_unix_nano);
+    let message = match point.value {
+        NumberValue::Double(value) => message.double(4, value),
+        // sfixed64
+        NumberValue::Int(value) => message.fixed64(6, value as u64),
+    };
+    message.messages(7, &point.attributes)
+}
+
+// metrics.v1.Metric holding a Gauge
+pub fn gauge(name: &str, description: &str, unit: &str, points: &[DataPoint]) -> Message {
+    let points: Vec<Message> = points.iter().map(data_point).collect();
+    Message::new()
+        .string(1, name)
+        .string(2, description)
+        .string(3, unit)
+        .message(5, &Message::new().messages(1, &points))
+}
+
+// metrics.v1.Metric holding a monotonic Sum
+pub fn monotonic_sum(name: &str, description: &str, unit: &str, temporality: u64, points: &[DataPoint]) -> Message {
+    let points: Vec<Message> = points.iter().map(data_point).collect();
+    let sum = Message::new().messages(1, &points).varint(2, temporality).bool(3, true);
+    Message::new()
+        .string(1, name)
+        .string(2, description)
+        .string(3, unit)
+        .message(7, &sum)
+}
+
+// collector.metrics.v1.ExportMetricsServiceRequest with one resource and one scope
+pub fn export_metrics(resource: &Message, scope: &Message, metrics: &[Message]) -> Vec<u8> {
+    let scope_metrics = Message::new().message(1, scope).messages(2, metrics);
+    let resource_metrics = Message::new().message(1, resource).message(2, &scope_metrics);
+    Message::new().message(1, &resource_metrics).into_bytes()
+}
+
+// Rejected item count and message of the partial_success in an Export*ServiceResponse
+pub fn partial_success(response: &[u8]) -> Option<(u64, String)> {
+    let (_, _, partial) = fields(response).into_iter().find(|(field, _, _)| *field == 1)?;
+    let mut rejected = 0;
+    let mut message = String::new();
+    for (field, value, payload) in fields(partial) {
+        match field {
+            1 => rejected = value,
+            2 => message = String::from_utf8_lossy(payload).to_string(),
+            _ => {}
+        }
+    }
+    (rejected > 0 || !message.is_empty()).then_some((rejected, message))
+}
@@ -0,0 +1,170 @@
+use crate::shared::error::StorageError;
+use crate::shared::protobuf::EncodedCodec;
+use crate::shared::storage::otlp::{OtlpConfig, OtlpProtocol};
+use log::warn;
+use std::time::Duration;
+use tonic::metadata::{MetadataKey, MetadataValue};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint};
+
+// What an export carries, which picks the gRPC method and the HTTP path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Logs,
+    Metrics,
+}
+
+impl Signal {
+    fn grpc_path(&self) -> &'static str {
+        match self {
+            Signal::Logs => "/opentelemetry.proto.collector.logs.v1.LogsService/Export",
+            Signal::Metrics => "/opentelemetry.proto.collector.metrics.v1.MetricsService/Export",
+        }
+    }
+
+    fn http_path(&self) -> &'static str {
+        match self {
+            Signal::Logs => "v1/logs",
+            Signal::Metrics => "v1/metrics",
+        }
+    }
+}
+
+pub enum OtlpTransport {
+    Grpc {
+        channel: Channel,
+        headers: Vec<(MetadataKey<tonic::metadata::Ascii>, MetadataValue<tonic::metadata::Ascii>)>,
+    },
+    Http {
+        client: reqwest::Client,
+        endpoint: String,
+    },
+}
+
+fn connection_error(message: String) -> StorageError {
+    StorageError::Connection(message)
+}
+
+fn read_ca_file(ca_file: &str) -> Result<Vec<u8>, StorageError> {
+    std::fs::read(ca_file).map_err(|e| connection_error(format!("Failed to read OTLP CA file {}: {}", ca_file, e)))
+}
+
+impl OtlpTransport {
+    pub fn new(config: &OtlpConfig) -> Result<Self, StorageError> {
+        let timeout = Duration::from_secs(config.timeout_secs.max(1));
+        match config.protocol {
+            OtlpProtocol::Grpc => {
+                let mut endpoint = Endpoint::from_shared(config.endpoint.clone())
+                    .map_err(|e| connection_error(format!("Invalid OTLP endpoint {}: {}", config.endpoint, e)))?
+                    .timeout(timeout)
+                    .connect_timeout(timeout);
+                if config.endpoint.starts_with("https://") {
+                    let mut tls = ClientTlsConfig::new().with_native_roots();
+                    if let Some(ca_file) = &config.tls.ca_file {
+                        tls = tls.ca_certificate(Certificate::from_pem(read_ca_file(ca_file)?));
+                    }
+                    if let Some(server_name) = &config.tls.server_name {
+                        tls = tls.domain_name(server_name.clone());
+                    }
+                    if config.tls.accept_invalid_certs {
+                        warn!("storage.otlp.tls.accept_invalid_certs is not supported over gRPC, certificates are verified");
+                    }
+                    endpoint = endpoint
+                        .tls_config(tls)
+                        .map_err(|e| connection_error(format!("Invalid OTLP TLS settings: {}", e)))?;
+                }
+
+                let headers = config
+                    .headers
+                    .iter()
+                    .map(|(name, value)| {
+                        let key = MetadataKey::from_bytes(name.to_lowercase().as_bytes())
+                            .map_err(|e| connection_error(format!("Invalid OTLP header name {}: {}", name, e)))?;
+                        let value = MetadataValue::try_from(value.as_str())
+                            .map_err(|e| connection_error(format!("Invalid OTLP header value for {}: {}", name, e)))?;
+                        Ok((key, value))
+                    })
+                    .collect::<Result<Vec<_>, StorageError>>()?;
+
+                // Connects on first use and reconnects after failures on its own
+                Ok(OtlpTransport::Grpc {
+                    channel: endpoint.connect_lazy(),
+                    headers,
+                })
+            }
+            OtlpProtocol::Http => {
+                let mut headers = reqwest::header::HeaderMap::new();
+                for (name, value) in &config.headers {
+                    let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                        .map_err(|e| connection_error(format!("Invalid OTLP header name {}: {}", name, e)))?;
+                    let value = reqwest::header::HeaderValue::from_str(value)
+                        .map_err(|e| connection_error(format!("Invalid OTLP header value for {}: {}", name, e)))?;
+                    headers.insert(name, value);
+                }
+
+                let mut builder = reqwest::Client::builder().timeout(timeout).default_headers(headers);
+                if let Some(ca_file) = &config.tls.ca_file {
+                    let certificate = reqwest::Certificate::from_pem(&read_ca_file(ca_file)?)
+                        .map_err(|e| connection_error(format!("Invalid OTLP CA file {}: {}", ca_file, e)))?;
+                    builder = builder.add_root_certificate(certificate);
+                }
+                if config.tls.accept_invalid_certs {
+                    warn!("OTLP TLS certificate verification is disabled");
+                    builder = builder.danger_accept_invalid_certs(true);
+                }
+                let client = builder
+                    .build()
+                    .map_err(|e| connection_error(format!("Failed to build OTLP HTTP client: {}", e)))?;
+                Ok(OtlpTransport::Http {
+                    client,
+                    endpoint: config.endpoint.trim_end_matches('/').to_string(),
+                })
+            }
+        }
+    }
+
+    // Sends one encoded Export*ServiceRequest and returns the encoded response
+    pub async fn export(&self, signal: Signal, request: Vec<u8>) -> Result<Vec<u8>, StorageError> {
+        match self {
+            OtlpTransport::Grpc { channel, headers } => {
+                let mut grpc = tonic::client::Grpc::new(channel.clone());
+                grpc.ready()
+                    .await
+                    .map_err(|e| connection_error(format!("OTLP collector unavailable: {}", e)))?;
+
+                let mut request = tonic::Request::new(request);
+                for (key, value) in headers {
+                    request.metadata_mut().insert(key.clone(), value.clone());
+                }
+                let path = tonic::codegen::http::uri::PathAndQuery::from_static(signal.grpc_path());
+                grpc.unary(request, path, EncodedCodec)
+                    .await
+                    .map(tonic::Response::into_inner)
+                    .map_err(|status| StorageError::Write(format!("OTLP export failed: {}", status)))
+            }
+            OtlpTransport::Http { client, endpoint } => {
+                let url = format!("{}/{}", endpoint, signal.http_path());
+                let response = client
+                    .post(&url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/x-protobuf")
+                    .body(request)
+                    .send()
+                    .await
+                    .map_err(|e| connection_error(format!("Failed to reach OTLP collector {}: {}", url, e)))?;
+                let status = response.status();
+                let body = response
+                    .bytes()
+                    .await
+                    .map_err(|e| StorageError::Write(format!("Failed to read OTLP response from {}: {}", url, e)))?;
+                if !status.is_success() {
+                    return Err(StorageError::Write(format!(
+                        "OTLP collector {} returned {}: {}",
+                        url,
+                        status,
+                        String::from_utf8_lossy(&body)
+                    )));
+                }
+                Ok(body.to_vec())
+            }
+        }
+    }
+}
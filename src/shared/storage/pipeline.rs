@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+pub const DEFAULT_PIPELINE_ID: &str = "lsedr-default";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipelineConfig {
+    // Ingest pipeline passed on every index request, None sends documents as-is
+    pub name: Option<String>,
+    pub install_default: bool,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            name: None,
+            install_default: true,
+        }
+    }
+}
+
+pub fn default_pipeline_body() -> Value {
+    json!({
+        "description": "Default lsedr enrichment: ingest time, remote address GeoIP and document fingerprint",
+        "_meta": { "managed_by": "lsedr" },
+        "processors": [
+            {
+                "set": {
+                    "field": "event_ingested",
+                    "value": "{{_ingest.timestamp}}"
+                }
+            },
+            {
+                "geoip": {
+                    "field": "remote_address",
+                    "target_field": "remote_geo",
+                    "ignore_missing": true,
+                    "ignore_failure": true
+                }
+            },
+            {
+                "fingerprint": {
+                    "fields": ["source", "category", "event_type", "path", "key_path", "value_name", "hash"],
+                    "target_field": "fingerprint",
+                    "method": "SHA-256",
+                    "ignore_missing": true
+                }
+            }
+        ],
+        "on_failure": [
+            {
+                "set": {
+                    "field": "pipeline_error",
+                    "value": "{{ _ingest.on_failure_message }}"
+                }
+            }
+        ]
+    })
+}
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+pub const POLICY_INDEX: &str = "lsedr_policies";
+
+#[derive(Debug, Clone)]
+pub struct EventQuery {
+    pub index: String,
+    pub host: Option<String>,
+    pub event_type: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub size: i64,
+}
+
+impl EventQuery {
+    pub fn new(index: &str) -> Self {
+        Self {
+            index: index.to_string(),
+            host: None,
+            event_type: None,
+            from: None,
+            to: None,
+            size: 100,
+        }
+    }
+
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = Some(host.to_string());
+        self
+    }
+
+    pub fn event_type(mut self, event_type: &str) -> Self {
+        self.event_type = Some(event_type.to_string());
+        self
+    }
+
+    pub fn between(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self.to = Some(to);
+        self
+    }
+
+    pub fn since(mut self, from: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    pub fn size(mut self, size: i64) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn to_query_body(&self) -> Value {
+        let mut filters = Vec::new();
+
+        if let Some(host) = &self.host {
+            // Event documents carry the host in `source`, snapshots in `hostname`
+            filters.push(json!({
+                "bool": {
+                    "should": [
+                        { "term": { "source.keyword": host } },
+                        { "term": { "hostname.keyword": host } }
+                    ],
+                    "minimum_should_match": 1
+                }
+            }));
+        }
+        if let Some(event_type) = &self.event_type {
+            filters.push(json!({ "term": { "event_type.keyword": event_type } }));
+        }
+        if self.from.is_some() || self.to.is_some() {
+            let mut range = serde_json::Map::new();
+            if let Some(from) = self.from {
+                range.insert("gte".to_string(), json!(from.to_rfc3339()));
+            }
+            if let Some(to) = self.to {
+                range.insert("lte".to_string(), json!(to.to_rfc3339()));
+            }
+            // The time field every stored document gets; `timestamp` is an object for some
+            filters.push(json!({ "range": { "@timestamp": range } }));
+        }
+
+        json!({
+            "query": { "bool": { "filter": filters } },
+            "sort": [ { "@timestamp": { "order": "desc", "unmapped_type": "date" } } ]
+        })
+    }
+}
+
+// Pull `_source` out of every hit in a search response
+pub fn extract_hits(response: &Value) -> Vec<Value> {
+    response["hits"]["hits"]
+        .as_array()
+        .map(|hits| hits.iter().filter_map(|hit| hit.get("_source").cloned()).collect())
+        .unwrap_or_default()
+}
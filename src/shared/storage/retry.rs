@@ -0,0 +1,72 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fmt::Display;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    // Attempts after the first one; 0 fails on the first transient error
+    pub max_retries: u32,
+    // Wait before the first retry, doubled for each one after it up to max_backoff_ms
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    // Wait a random half to all of each backoff, so agents throttled together do not all
+    // come back at the same moment
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            jitter: true,
+        }
+    }
+}
+
+// Good enough for spreading retries; every RandomState is seeded differently
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+impl RetryPolicy {
+    // Wait before the given retry, counted from 0
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u64.checked_shl(retry).unwrap_or(u64::MAX);
+        let ceiling = self.initial_backoff_ms.saturating_mul(factor).min(self.max_backoff_ms);
+        let millis = if self.jitter && ceiling > 1 {
+            ceiling / 2 + random() % (ceiling - ceiling / 2 + 1)
+        } else {
+            ceiling
+        };
+        Duration::from_millis(millis)
+    }
+
+    // Runs the operation until it succeeds, fails with an error `transient` does not accept,
+    // or max_retries are used up; the last error is returned
+    pub async fn run<T, E, F, Fut>(&self, what: &str, transient: fn(&E) -> bool, mut operation: F) -> Result<T, E>
+    where
+        E: Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut retry = 0;
+        loop {
+            match operation().await {
+                Err(e) if transient(&e) && retry < self.max_retries => {
+                    let backoff = self.backoff(retry);
+                    retry += 1;
+                    warn!("{} failed ({}), retry {} of {} in {:?}", what, e, retry, self.max_retries, backoff);
+                    tokio::time::sleep(backoff).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
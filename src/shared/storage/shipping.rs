@@ -0,0 +1,303 @@
+use crate::shared::error::StorageError;
+use crate::shared::storage::backend::{StorageBackend, StorageDocument};
+use crate::shared::storage::elasticsearch_storage::SystemInformation;
+use crate::shared::storage::spool::{Spool, SpoolConfig};
+use crate::shared::traits::{DataStorage, EventEnvelope};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Local, NaiveTime, Weekday};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShippingWindow {
+    // Three-letter weekday names (mon..sun); empty means every day
+    pub days: Vec<String>,
+    // Local wall-clock times as HH:MM. A window whose end is before its start runs
+    // past midnight and belongs to the day it starts on.
+    pub start: String,
+    pub end: String,
+}
+
+impl Default for ShippingWindow {
+    fn default() -> Self {
+        Self {
+            days: Vec::new(),
+            start: String::from("00:00"),
+            end: String::from("00:00"),
+        }
+    }
+}
+
+impl ShippingWindow {
+    fn parse_time(value: &str) -> Option<NaiveTime> {
+        NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+    }
+
+    fn includes_day(&self, day: Weekday) -> bool {
+        self.days.is_empty()
+            || self
+                .days
+                .iter()
+                .any(|name| name.trim().parse::<Weekday>().map(|parsed| parsed == day).unwrap_or(false))
+    }
+
+    pub fn contains(&self, now: DateTime<Local>) -> bool {
+        let (Some(start), Some(end)) = (Self::parse_time(&self.start), Self::parse_time(&self.end)) else {
+            return false;
+        };
+        let time = now.time();
+        let today = now.weekday();
+        if start < end {
+            self.includes_day(today) && time >= start && time < end
+        } else {
+            // Equal start and end is a whole day
+            (self.includes_day(today) && time >= start) || (self.includes_day(today.pred()) && time < end)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShippingConfig {
+    pub enabled: bool,
+    // Indices shipped as soon as they are written, regardless of windows and caps
+    pub immediate_indices: Vec<String>,
+    // When bulk telemetry may be sent; empty means any time
+    pub windows: Vec<ShippingWindow>,
+    // Bandwidth cap for bulk telemetry; 0 means uncapped
+    pub max_bytes_per_sec: u64,
+    // How many seconds of unused bandwidth may accumulate for a burst
+    pub burst_secs: u64,
+    // Where bulk telemetry waits for its window or for bandwidth
+    pub queue: SpoolConfig,
+}
+
+impl Default for ShippingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            immediate_indices: vec![
+                String::from("alerts"),
+                String::from("incidents"),
+                String::from("ioc_matches"),
+                String::from("honeypot_events"),
+                String::from("deception_events"),
+                String::from("keylogging_events"),
+                String::from("action_audit"),
+                String::from("sink_annotations"),
+            ],
+            windows: Vec::new(),
+            max_bytes_per_sec: 0,
+            burst_secs: 10,
+            queue: SpoolConfig {
+                directory: String::from("data/deferred"),
+                ..SpoolConfig::default()
+            },
+        }
+    }
+}
+
+impl ShippingConfig {
+    fn in_window(&self, now: DateTime<Local>) -> bool {
+        self.windows.is_empty() || self.windows.iter().any(|window| window.contains(now))
+    }
+}
+
+struct Allowance {
+    bytes: f64,
+    refilled: Instant,
+}
+
+// Holds back bulk telemetry for a sink with a metered or constrained link. Documents of
+// immediate indices go straight through; everything else is sent only inside a shipping
+// window and within the bandwidth cap, and otherwise waits in an on-disk queue that is
+// drained, oldest first, as windows open and bandwidth frees up.
+pub struct ShapedBackend {
+    inner: Box<dyn StorageBackend>,
+    config: ShippingConfig,
+    queue: Spool,
+    allowance: Mutex<Allowance>,
+    flushing: tokio::sync::Mutex<()>,
+}
+
+impl ShapedBackend {
+    pub fn new(inner: Box<dyn StorageBackend>, config: ShippingConfig) -> Result<Self, StorageError> {
+        let queue = Spool::new(config.queue.clone())?;
+        let allowance = Mutex::new(Allowance {
+            bytes: config.max_bytes_per_sec as f64,
+            refilled: Instant::now(),
+        });
+        Ok(Self {
+            inner,
+            config,
+            queue,
+            allowance,
+            flushing: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    fn immediate(&self, index: &str) -> bool {
+        self.config.immediate_indices.iter().any(|name| name == index)
+    }
+
+    fn capped(&self) -> bool {
+        self.config.max_bytes_per_sec > 0
+    }
+
+    // Bytes that may be sent now, after refilling for the time since the last call.
+    // Goes negative when an oversized segment was let through, delaying the next send.
+    fn available_bytes(&self) -> f64 {
+        if !self.capped() {
+            return f64::MAX;
+        }
+        let Ok(mut allowance) = self.allowance.lock() else {
+            return 0.0;
+        };
+        let rate = self.config.max_bytes_per_sec as f64;
+        let burst = rate * self.config.burst_secs.max(1) as f64;
+        allowance.bytes = (allowance.bytes + allowance.refilled.elapsed().as_secs_f64() * rate).min(burst);
+        allowance.refilled = Instant::now();
+        allowance.bytes
+    }
+
+    fn charge(&self, bytes: u64) {
+        if !self.capped() {
+            return;
+        }
+        if let Ok(mut allowance) = self.allowance.lock() {
+            allowance.bytes -= bytes as f64;
+        }
+    }
+
+    fn encoded_size(documents: &[StorageDocument]) -> u64 {
+        documents
+            .iter()
+            .map(|document| serde_json::to_vec(document).map(|bytes| bytes.len() as u64 + 1).unwrap_or(0))
+            .sum()
+    }
+
+    // Sends queued bulk telemetry while the window is open and bandwidth allows. Writes
+    // arriving during a flush leave the queue to it rather than ship and charge it again.
+    pub async fn flush(&self) -> Result<usize, StorageError> {
+        if !self.config.in_window(Local::now()) || !self.queue.has_pending().await {
+            return Ok(0);
+        }
+        let Ok(_flushing) = self.flushing.try_lock() else {
+            return Ok(0);
+        };
+        let available = self.available_bytes();
+        if available <= 0.0 {
+            return Ok(0);
+        }
+        let budget = if self.capped() { available as u64 } else { u64::MAX };
+        let (sent, bytes) = self.queue.replay_within(self.inner.as_ref(), budget).await?;
+        self.charge(bytes);
+        if sent > 0 {
+            info!("Shipped {} deferred documents ({} bytes) to {} storage", sent, bytes, self.inner.name());
+        }
+        Ok(sent)
+    }
+
+    async fn flush_if_pending(&self) {
+        if let Err(e) = self.flush().await {
+            warn!("Deferred shipping to {} storage stopped: {}", self.inner.name(), e);
+        }
+    }
+
+    // Whether bulk documents can go out now: inside a window, nothing older still queued,
+    // and the cap has room for them
+    async fn admit(&self, documents: &[StorageDocument]) -> bool {
+        if !self.config.in_window(Local::now()) || self.queue.has_pending().await {
+            return false;
+        }
+        if !self.capped() {
+            return true;
+        }
+        let size = Self::encoded_size(documents);
+        if self.available_bytes() < size as f64 {
+            return false;
+        }
+        self.charge(size);
+        true
+    }
+
+    // Routes one write: immediate indices and admitted bulk go to the inner backend
+    // through send, the rest is queued
+    async fn ship<F>(
+        &self,
+        index: &str,
+        send: F,
+        documents: impl FnOnce() -> Result<Vec<StorageDocument>, StorageError>,
+    ) -> Result<(), StorageError>
+    where
+        F: std::future::Future<Output = Result<(), StorageError>>,
+    {
+        if self.immediate(index) {
+            return send.await;
+        }
+        self.flush_if_pending().await;
+        let documents = documents()?;
+        if self.admit(&documents).await {
+            return send.await;
+        }
+        debug!("Deferring {} {} documents for {} storage", documents.len(), index, self.inner.name());
+        self.queue.enqueue(&documents).await
+    }
+
+    // Raw documents may mix indices, so immediate ones are split off first
+    async fn ship_documents(&self, data: Vec<StorageDocument>) -> Result<(), StorageError> {
+        let (immediate, bulk): (Vec<_>, Vec<_>) = data.into_iter().partition(|document| self.immediate(&document.index));
+        if !immediate.is_empty() {
+            self.inner.batch_store(immediate).await?;
+        }
+        if bulk.is_empty() {
+            return Ok(());
+        }
+        self.flush_if_pending().await;
+        if self.admit(&bulk).await {
+            return self.inner.batch_store(bulk).await;
+        }
+        debug!("Deferring {} documents for {} storage", bulk.len(), self.inner.name());
+        self.queue.enqueue(&bulk).await
+    }
+}
+
+#[async_trait]
+impl DataStorage<StorageDocument> for ShapedBackend {
+    async fn store(&self, data: StorageDocument) -> Result<(), StorageError> {
+        self.ship_documents(vec![data]).await
+    }
+
+    async fn batch_store(&self, data: Vec<StorageDocument>) -> Result<(), StorageError> {
+        self.ship_documents(data).await
+    }
+
+    async fn health_check(&self) -> bool {
+        self.inner.health_check().await
+    }
+}
+
+#[async_trait]
+impl StorageBackend for ShapedBackend {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn store_system_info(&self, info: &SystemInformation) -> Result<(), StorageError> {
+        self.ship("system_metrics", self.inner.store_system_info(info), || {
+            Ok(vec![StorageDocument::new("system_metrics", info)?])
+        })
+        .await
+    }
+
+    // Each run of envelopes bound for one index is shaped like a batch of that index
+    async fn store_envelopes(&self, envelopes: &[EventEnvelope]) -> Result<(), StorageError> {
+        for run in envelopes.chunk_by(|a, b| a.index == b.index) {
+            self.ship(&run[0].index, self.inner.store_envelopes(run), || StorageDocument::from_envelopes(run)).await?;
+        }
+        Ok(())
+    }
+}
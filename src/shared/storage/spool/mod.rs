@@ -0,0 +1,291 @@
+mod spooled_backend;
+
+pub use spooled_backend::SpooledBackend;
+
+use crate::shared::error::StorageError;
+use crate::shared::storage::backend::{StorageBackend, StorageDocument};
+use chrono::Utc;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+const SEGMENT_PREFIX: &str = "segment-";
+const SEGMENT_SUFFIX: &str = ".jsonl";
+const PROGRESS_SUFFIX: &str = ".progress";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpoolConfig {
+    pub enabled: bool,
+    pub directory: String,
+    pub max_size_bytes: u64,
+    pub segment_max_bytes: u64,
+    pub retention_hours: u64,
+    pub drain_batch_size: usize,
+    // After a replay fails, writes skip replaying for this long instead of each re-reading a segment
+    pub drain_retry_secs: u64,
+}
+
+impl Default for SpoolConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: String::from("data/spool"),
+            max_size_bytes: 512 * 1024 * 1024,
+            segment_max_bytes: 8 * 1024 * 1024,
+            retention_hours: 72,
+            drain_batch_size: 500,
+            drain_retry_secs: 30,
+        }
+    }
+}
+
+struct ActiveSegment {
+    path: PathBuf,
+    size: u64,
+}
+
+// Append-only on-disk queue of documents that could not be delivered. Documents are
+// written as JSON lines into size-bounded segment files and replayed oldest first.
+pub struct Spool {
+    config: SpoolConfig,
+    directory: PathBuf,
+    active: Mutex<Option<ActiveSegment>>,
+}
+
+impl Spool {
+    pub fn new(config: SpoolConfig) -> Result<Self, StorageError> {
+        let directory = PathBuf::from(&config.directory);
+        std::fs::create_dir_all(&directory)
+            .map_err(|e| StorageError::Connection(format!("Failed to create spool {}: {}", directory.display(), e)))?;
+
+        Ok(Self {
+            config,
+            directory,
+            active: Mutex::new(None),
+        })
+    }
+
+    fn new_segment_path(&self) -> PathBuf {
+        self.directory.join(format!(
+            "{}{:020}-{}{}",
+            SEGMENT_PREFIX,
+            Utc::now().timestamp_millis(),
+            uuid::Uuid::new_v4().simple(),
+            SEGMENT_SUFFIX
+        ))
+    }
+
+    async fn segments(&self) -> Result<Vec<(PathBuf, u64, SystemTime)>, StorageError> {
+        let mut entries = fs::read_dir(&self.directory)
+            .await
+            .map_err(|e| StorageError::Read(e.to_string()))?;
+
+        let mut segments = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(|e| StorageError::Read(e.to_string()))? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with(SEGMENT_PREFIX) || !name.ends_with(SEGMENT_SUFFIX) {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata().await {
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                segments.push((entry.path(), metadata.len(), modified));
+            }
+        }
+        // Segment names start with a zero-padded timestamp so lexical order is age order
+        segments.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(segments)
+    }
+
+    pub async fn pending_bytes(&self) -> u64 {
+        self.segments()
+            .await
+            .map(|segments| segments.iter().map(|(_, size, _)| size).sum())
+            .unwrap_or(0)
+    }
+
+    pub async fn has_pending(&self) -> bool {
+        self.pending_bytes().await > 0
+    }
+
+    pub async fn enqueue(&self, documents: &[StorageDocument]) -> Result<(), StorageError> {
+        if documents.is_empty() {
+            return Ok(());
+        }
+
+        let mut buffer = Vec::new();
+        for document in documents {
+            serde_json::to_writer(&mut buffer, document).map_err(|e| StorageError::Write(e.to_string()))?;
+            buffer.push(b'\n');
+        }
+
+        let mut active = self.active.lock().await;
+        let rotate = match active.as_ref() {
+            Some(segment) => segment.size + buffer.len() as u64 > self.config.segment_max_bytes,
+            None => true,
+        };
+        if rotate {
+            *active = Some(ActiveSegment {
+                path: self.new_segment_path(),
+                size: 0,
+            });
+        }
+
+        if let Some(segment) = active.as_mut() {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&segment.path)
+                .await
+                .map_err(|e| StorageError::Write(format!("Failed to open {}: {}", segment.path.display(), e)))?;
+            file.write_all(&buffer)
+                .await
+                .map_err(|e| StorageError::Write(format!("Failed to write {}: {}", segment.path.display(), e)))?;
+            file.flush().await.map_err(|e| StorageError::Write(e.to_string()))?;
+            segment.size += buffer.len() as u64;
+        }
+        drop(active);
+
+        info!("Spooled {} documents to {}", documents.len(), self.directory.display());
+        self.enforce_limits().await
+    }
+
+    // Remove segments past retention, then the oldest ones until the spool fits max_size_bytes
+    async fn enforce_limits(&self) -> Result<(), StorageError> {
+        let retention = Duration::from_secs(self.config.retention_hours * 3600);
+        let now = SystemTime::now();
+        let mut segments = self.segments().await?;
+
+        let mut kept = Vec::new();
+        for (path, size, modified) in segments.drain(..) {
+            if now.duration_since(modified).unwrap_or_default() > retention {
+                warn!("Dropping spool segment {} past retention", path.display());
+                self.remove_segment(&path).await;
+            } else {
+                kept.push((path, size));
+            }
+        }
+
+        let mut total: u64 = kept.iter().map(|(_, size)| size).sum();
+        for (path, size) in kept {
+            if total <= self.config.max_size_bytes {
+                break;
+            }
+            warn!("Spool exceeds {} bytes, dropping oldest segment {}", self.config.max_size_bytes, path.display());
+            self.remove_segment(&path).await;
+            total -= size;
+        }
+        Ok(())
+    }
+
+    async fn remove_segment(&self, path: &Path) {
+        let mut active = self.active.lock().await;
+        if active.as_ref().map(|segment| segment.path == path).unwrap_or(false) {
+            *active = None;
+        }
+        if let Err(e) = fs::remove_file(path).await {
+            warn!("Failed to remove spool segment {}: {}", path.display(), e);
+        }
+        let _ = fs::remove_file(progress_path(path)).await;
+    }
+
+    // Number of documents at the head of a segment already delivered by an earlier,
+    // interrupted replay
+    async fn delivered(&self, path: &Path) -> usize {
+        fs::read_to_string(progress_path(path))
+            .await
+            .ok()
+            .and_then(|content| content.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    async fn record_delivered(&self, path: &Path, delivered: usize) {
+        if let Err(e) = fs::write(progress_path(path), delivered.to_string()).await {
+            warn!("Failed to record replay progress of {}: {}", path.display(), e);
+        }
+    }
+
+    // Closes the active segment and returns all segments with their size, oldest first, for replay
+    async fn seal_segments(&self) -> Result<Vec<(PathBuf, u64)>, StorageError> {
+        *self.active.lock().await = None;
+        Ok(self.segments().await?.into_iter().map(|(path, size, _)| (path, size)).collect())
+    }
+
+    async fn read_segment(&self, path: &Path) -> Result<Vec<StorageDocument>, StorageError> {
+        let content = fs::read_to_string(path)
+            .await
+            .map_err(|e| StorageError::Read(format!("Failed to read {}: {}", path.display(), e)))?;
+
+        let mut documents = Vec::new();
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(document) => documents.push(document),
+                // A torn write at the end of a segment only loses that one record
+                Err(e) => warn!("Skipping corrupt spool record in {}: {}", path.display(), e),
+            }
+        }
+        debug!("Read {} spooled documents from {}", documents.len(), path.display());
+        Ok(documents)
+    }
+
+    async fn complete_segment(&self, path: &Path) {
+        self.remove_segment(path).await;
+    }
+
+    pub fn drain_batch_size(&self) -> usize {
+        self.config.drain_batch_size.max(1)
+    }
+
+    pub fn drain_retry(&self) -> Duration {
+        Duration::from_secs(self.config.drain_retry_secs)
+    }
+
+    // Writes every spooled document to the target, oldest segment first, removing each
+    // segment once it is fully delivered. Stops at the first failed write; the chunks of
+    // a segment delivered before it are recorded and not sent again by the next replay.
+    // Callers serialize replays, a sealed segment is never appended to.
+    pub(crate) async fn replay(&self, target: &dyn StorageBackend) -> Result<usize, StorageError> {
+        Ok(self.replay_within(target, u64::MAX).await?.0)
+    }
+
+    // Like replay, but stops before the segment that would take the replayed bytes past
+    // max_bytes. The first segment always goes so an oversized one cannot block the queue.
+    // Returns the documents and bytes replayed.
+    pub(crate) async fn replay_within(
+        &self,
+        target: &dyn StorageBackend,
+        max_bytes: u64,
+    ) -> Result<(usize, u64), StorageError> {
+        let mut replayed = 0;
+        let mut bytes = 0u64;
+        for (segment, size) in self.seal_segments().await? {
+            if bytes > 0 && bytes.saturating_add(size) > max_bytes {
+                break;
+            }
+            let documents = self.read_segment(&segment).await?;
+            let mut delivered = self.delivered(&segment).await.min(documents.len());
+            if delivered > 0 {
+                debug!("Resuming {} after {} delivered documents", segment.display(), delivered);
+            }
+            for chunk in documents[delivered..].chunks(self.drain_batch_size()) {
+                target.batch_store(chunk.to_vec()).await?;
+                delivered += chunk.len();
+                replayed += chunk.len();
+                self.record_delivered(&segment, delivered).await;
+            }
+            bytes += size;
+            self.complete_segment(&segment).await;
+        }
+        Ok((replayed, bytes))
+    }
+}
+
+fn progress_path(segment: &Path) -> PathBuf {
+    let mut path = segment.as_os_str().to_owned();
+    path.push(PROGRESS_SUFFIX);
+    PathBuf::from(path)
+}
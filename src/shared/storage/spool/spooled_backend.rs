@@ -0,0 +1,141 @@
+use crate::shared::error::StorageError;
+use crate::shared::metrics::AgentMetrics;
+use crate::shared::storage::backend::{StorageBackend, StorageDocument};
+use crate::shared::storage::elasticsearch_storage::SystemInformation;
+use crate::shared::storage::spool::Spool;
+use crate::shared::traits::{DataStorage, EventEnvelope};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+// Wraps a backend so failed writes are spooled to disk and replayed, oldest first,
+// before the next write once the backend accepts data again. After a failed replay writes
+// leave the spool alone for the spool's drain_retry_secs, so an outage does not cost a
+// segment read per write.
+pub struct SpooledBackend {
+    inner: Box<dyn StorageBackend>,
+    spool: Spool,
+    draining: Mutex<()>,
+    last_failed_drain: std::sync::Mutex<Option<Instant>>,
+}
+
+impl SpooledBackend {
+    pub fn new(inner: Box<dyn StorageBackend>, spool: Spool) -> Self {
+        Self {
+            inner,
+            spool,
+            draining: Mutex::new(()),
+            last_failed_drain: std::sync::Mutex::new(None),
+        }
+    }
+
+    pub fn spool(&self) -> &Spool {
+        &self.spool
+    }
+
+    pub async fn drain(&self) -> Result<usize, StorageError> {
+        let _draining = self.draining.lock().await;
+        self.drain_locked().await
+    }
+
+    async fn drain_locked(&self) -> Result<usize, StorageError> {
+        let drained = self.spool.replay(self.inner.as_ref()).await;
+        self.report_pending().await;
+        if let Ok(mut last_failed_drain) = self.last_failed_drain.lock() {
+            *last_failed_drain = drained.is_err().then(Instant::now);
+        }
+        let drained = drained?;
+        if drained > 0 {
+            info!("Drained {} spooled documents to {} storage", drained, self.inner.name());
+        }
+        Ok(drained)
+    }
+
+    async fn report_pending(&self) {
+        AgentMetrics::global().set_queue_depth("spool_bytes", self.spool.pending_bytes().await);
+    }
+
+    fn drain_backing_off(&self) -> bool {
+        self.last_failed_drain
+            .lock()
+            .ok()
+            .and_then(|last_failed_drain| *last_failed_drain)
+            .is_some_and(|failed| failed.elapsed() < self.spool.drain_retry())
+    }
+
+    async fn drain_if_pending(&self) {
+        if self.drain_backing_off() || !self.spool.has_pending().await {
+            return;
+        }
+        // Concurrent writes would otherwise each replay the same segments
+        let Ok(_draining) = self.draining.try_lock() else {
+            debug!("Spool drain to {} storage already running", self.inner.name());
+            return;
+        };
+        if let Err(e) = self.drain_locked().await {
+            warn!("Spool drain stopped, {} storage still unavailable: {}", self.inner.name(), e);
+        }
+    }
+
+    async fn spool_on_error(
+        &self,
+        result: Result<(), StorageError>,
+        documents: impl FnOnce() -> Result<Vec<StorageDocument>, StorageError>,
+    ) -> Result<(), StorageError> {
+        match result {
+            Ok(()) => Ok(()),
+            // The rest of the batch was stored
+            Err(StorageError::Partial { reason, remaining }) => {
+                warn!("{} storage turned away part of a write, spooling it to disk: {}", self.inner.name(), reason);
+                self.spool.enqueue(&remaining).await?;
+                self.report_pending().await;
+                Ok(())
+            }
+            Err(e) => {
+                warn!("{} storage write failed, spooling to disk: {}", self.inner.name(), e);
+                self.spool.enqueue(&documents()?).await?;
+                self.report_pending().await;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl DataStorage<StorageDocument> for SpooledBackend {
+    async fn store(&self, data: StorageDocument) -> Result<(), StorageError> {
+        self.drain_if_pending().await;
+        let result = self.inner.store(data.clone()).await;
+        self.spool_on_error(result, || Ok(vec![data])).await
+    }
+
+    async fn batch_store(&self, data: Vec<StorageDocument>) -> Result<(), StorageError> {
+        self.drain_if_pending().await;
+        let result = self.inner.batch_store(data.clone()).await;
+        self.spool_on_error(result, || Ok(data)).await
+    }
+
+    async fn health_check(&self) -> bool {
+        self.inner.health_check().await
+    }
+}
+
+#[async_trait]
+impl StorageBackend for SpooledBackend {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn store_system_info(&self, info: &SystemInformation) -> Result<(), StorageError> {
+        self.drain_if_pending().await;
+        let result = self.inner.store_system_info(info).await;
+        self.spool_on_error(result, || Ok(vec![StorageDocument::new("system_metrics", info)?])).await
+    }
+
+    async fn store_envelopes(&self, envelopes: &[EventEnvelope]) -> Result<(), StorageError> {
+        self.drain_if_pending().await;
+        let result = self.inner.store_envelopes(envelopes).await;
+        self.spool_on_error(result, || StorageDocument::from_envelopes(envelopes)).await
+    }
+}
@@ -0,0 +1,64 @@
+use crate::shared::error::StorageError;
+use crate::shared::storage::backend::{StorageBackend, StorageDocument};
+use crate::shared::traits::DataStorage;
+use async_trait::async_trait;
+use std::io::Write;
+
+// Writes `{"index": ..., "document": ...}` JSON lines to standard output
+pub struct StdoutStorage {
+    // Indented multi-line JSON for reading by eye instead of one object per line
+    pretty: bool,
+}
+
+impl StdoutStorage {
+    pub fn new() -> Self {
+        Self { pretty: false }
+    }
+
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    fn write(&self, documents: &[StorageDocument]) -> Result<(), StorageError> {
+        let stdout = std::io::stdout();
+        let mut out = stdout.lock();
+        for document in documents {
+            let line = serde_json::json!({ "index": document.index, "document": document.body });
+            if self.pretty {
+                let text = serde_json::to_string_pretty(&line).map_err(|e| StorageError::Write(e.to_string()))?;
+                writeln!(out, "{}", text).map_err(|e| StorageError::Write(e.to_string()))?;
+            } else {
+                writeln!(out, "{}", line).map_err(|e| StorageError::Write(e.to_string()))?;
+            }
+        }
+        out.flush().map_err(|e| StorageError::Write(e.to_string()))
+    }
+}
+
+impl Default for StdoutStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl DataStorage<StorageDocument> for StdoutStorage {
+    async fn store(&self, data: StorageDocument) -> Result<(), StorageError> {
+        self.write(std::slice::from_ref(&data))
+    }
+
+    async fn batch_store(&self, data: Vec<StorageDocument>) -> Result<(), StorageError> {
+        self.write(&data)
+    }
+
+    async fn health_check(&self) -> bool {
+        true
+    }
+}
+
+impl StorageBackend for StdoutStorage {
+    fn name(&self) -> &str {
+        "stdout"
+    }
+}
@@ -0,0 +1,339 @@
+use crate::shared::error::StorageError;
+use crate::shared::host_identity::HostIdentityService;
+use crate::shared::pseudonymizer::{PseudonymKind, Pseudonymizer};
+use crate::shared::storage::backend::{StorageBackend, StorageDocument};
+use crate::shared::traits::{DataStorage, EventEnvelope, Severity};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::Mutex;
+
+// Default facility: 13 = log audit
+const DEFAULT_FACILITY: u8 = 13;
+// Priority used for documents that carry no severity (system snapshots, file retrievals)
+const INFORMATIONAL: u8 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyslogTransport {
+    Udp,
+    Tcp,
+    Tls,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyslogTlsConfig {
+    // PEM bundle of extra CAs to trust besides the system store
+    pub ca_file: Option<String>,
+    // Name to verify the collector certificate against, defaults to `host`
+    pub server_name: Option<String>,
+    pub accept_invalid_certs: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyslogConfig {
+    pub transport: SyslogTransport,
+    pub host: String,
+    pub port: u16,
+    pub facility: u8,
+    pub app_name: String,
+    // Event categories / indices to forward, empty forwards everything
+    pub categories: Vec<String>,
+    // UDP messages longer than this are truncated
+    pub max_message_bytes: usize,
+    pub tls: SyslogTlsConfig,
+}
+
+impl Default for SyslogConfig {
+    fn default() -> Self {
+        Self {
+            transport: SyslogTransport::Udp,
+            host: String::from("localhost"),
+            port: 514,
+            facility: DEFAULT_FACILITY,
+            app_name: String::from("lsedr"),
+            categories: Vec::new(),
+            max_message_bytes: 8192,
+            tls: SyslogTlsConfig::default(),
+        }
+    }
+}
+
+type SyslogStream = Box<dyn AsyncWrite + Send + Unpin>;
+
+enum Connection {
+    Udp(UdpSocket),
+    Stream(SyslogStream),
+}
+
+// Forwards documents to a syslog collector as RFC 5424 messages. Stream transports
+// use octet-counting framing (RFC 6587 / RFC 5425); the connection is reopened on
+// the next write after a failure.
+pub struct SyslogStorage {
+    config: SyslogConfig,
+    host: Arc<HostIdentityService>,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl SyslogStorage {
+    pub fn new(config: SyslogConfig) -> Result<Self, StorageError> {
+        if config.facility > 23 {
+            return Err(StorageError::Connection(format!(
+                "Invalid syslog facility {}, expected 0-23",
+                config.facility
+            )));
+        }
+        info!(
+            "Forwarding events to syslog {}:{} over {:?}",
+            config.host, config.port, config.transport
+        );
+        Ok(Self {
+            config,
+            host: HostIdentityService::global(),
+            connection: Mutex::new(None),
+        })
+    }
+
+    fn severity_code(severity: Option<Severity>) -> u8 {
+        match severity {
+            Some(Severity::Critical) => 2,
+            Some(Severity::High) => 3,
+            Some(Severity::Medium) => 4,
+            Some(Severity::Low) => 5,
+            None => INFORMATIONAL,
+        }
+    }
+
+    fn category_of(document: &StorageDocument) -> &str {
+        document
+            .body
+            .get("category")
+            .and_then(Value::as_str)
+            .unwrap_or(&document.index)
+    }
+
+    fn is_selected(&self, document: &StorageDocument) -> bool {
+        self.config.categories.is_empty()
+            || self.config.categories.iter().any(|category| {
+                category == &document.index || category == Self::category_of(document)
+            })
+    }
+
+    // PRINTUSASCII without spaces, as required for header fields
+    fn header_field(value: &str, max_len: usize) -> String {
+        let field: String = value
+            .chars()
+            .filter(|c| c.is_ascii_graphic())
+            .take(max_len)
+            .collect();
+        if field.is_empty() {
+            String::from("-")
+        } else {
+            field
+        }
+    }
+
+    fn format_message(&self, document: &StorageDocument) -> String {
+        let severity = document
+            .body
+            .get("severity")
+            .and_then(|value| serde_json::from_value::<Severity>(value.clone()).ok());
+        let priority = self.config.facility * 8 + Self::severity_code(severity);
+
+        let timestamp = document
+            .body
+            .get("timestamp")
+            .and_then(Value::as_str)
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map(|value| value.with_timezone(&Utc))
+            .unwrap_or_else(Utc::now)
+            .to_rfc3339_opts(SecondsFormat::Micros, true);
+
+        // Bodies were pseudonymized by the enrichment chain, the header is not part of them
+        let hostname = Pseudonymizer::global().pseudonymize(PseudonymKind::Host, &self.host.name());
+        format!(
+            "<{}>1 {} {} {} {} {} - {}",
+            priority,
+            timestamp,
+            Self::header_field(&hostname, 255),
+            Self::header_field(&self.config.app_name, 48),
+            std::process::id(),
+            Self::header_field(&document.index, 32),
+            document.body,
+        )
+    }
+
+    fn truncate(&self, message: String) -> String {
+        if message.len() <= self.config.max_message_bytes {
+            return message;
+        }
+        let mut end = self.config.max_message_bytes;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message[..end].to_string()
+    }
+
+    async fn connect(&self) -> Result<Connection, StorageError> {
+        let address = format!("{}:{}", self.config.host, self.config.port);
+        let connect_error = |e: std::io::Error| {
+            StorageError::Connection(format!("Failed to connect to syslog {}: {}", address, e))
+        };
+
+        match self.config.transport {
+            SyslogTransport::Udp => {
+                let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(connect_error)?;
+                socket.connect(&address).await.map_err(connect_error)?;
+                Ok(Connection::Udp(socket))
+            }
+            SyslogTransport::Tcp => {
+                let stream = TcpStream::connect(&address).await.map_err(connect_error)?;
+                Ok(Connection::Stream(Box::new(stream)))
+            }
+            SyslogTransport::Tls => {
+                let stream = TcpStream::connect(&address).await.map_err(connect_error)?;
+                let connector = self.tls_connector()?;
+                let server_name = self
+                    .config
+                    .tls
+                    .server_name
+                    .clone()
+                    .unwrap_or_else(|| self.config.host.clone());
+                let stream = connector.connect(&server_name, stream).await.map_err(|e| {
+                    StorageError::Connection(format!("TLS handshake with {} failed: {}", address, e))
+                })?;
+                Ok(Connection::Stream(Box::new(stream)))
+            }
+        }
+    }
+
+    fn tls_connector(&self) -> Result<tokio_native_tls::TlsConnector, StorageError> {
+        let mut builder = native_tls::TlsConnector::builder();
+        if let Some(ca_file) = &self.config.tls.ca_file {
+            let pem = std::fs::read(ca_file).map_err(|e| {
+                StorageError::Connection(format!("Failed to read syslog CA file {}: {}", ca_file, e))
+            })?;
+            let certificate = native_tls::Certificate::from_pem(&pem).map_err(|e| {
+                StorageError::Connection(format!("Invalid syslog CA file {}: {}", ca_file, e))
+            })?;
+            builder.add_root_certificate(certificate);
+        }
+        if self.config.tls.accept_invalid_certs {
+            warn!("Syslog TLS certificate verification is disabled");
+            builder.danger_accept_invalid_certs(true);
+        }
+        let connector = builder
+            .build()
+            .map_err(|e| StorageError::Connection(format!("Failed to build TLS connector: {}", e)))?;
+        Ok(tokio_native_tls::TlsConnector::from(connector))
+    }
+
+    async fn send(&self, documents: &[StorageDocument]) -> Result<(), StorageError> {
+        let messages: Vec<String> = documents
+            .iter()
+            .filter(|document| self.is_selected(document))
+            .map(|document| self.format_message(document))
+            .collect();
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        let mut connection = self.connection.lock().await;
+        if connection.is_none() {
+            *connection = Some(self.connect().await?);
+        }
+
+        let result = match connection.as_mut() {
+            Some(Connection::Udp(socket)) => {
+                let mut result = Ok(());
+                for message in messages.iter() {
+                    let message = self.truncate(message.clone());
+                    if let Err(e) = socket.send(message.as_bytes()).await {
+                        result = Err(e);
+                        break;
+                    }
+                }
+                result
+            }
+            Some(Connection::Stream(stream)) => {
+                let mut frames = Vec::new();
+                for message in messages.iter() {
+                    frames.extend_from_slice(format!("{} ", message.len()).as_bytes());
+                    frames.extend_from_slice(message.as_bytes());
+                }
+                match stream.write_all(&frames).await {
+                    Ok(()) => stream.flush().await,
+                    Err(e) => Err(e),
+                }
+            }
+            None => unreachable!("syslog connection established above"),
+        };
+
+        match result {
+            Ok(()) => {
+                debug!("Sent {} syslog messages", messages.len());
+                Ok(())
+            }
+            Err(e) => {
+                *connection = None;
+                Err(StorageError::Write(format!("Failed to send syslog messages: {}", e)))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl DataStorage<StorageDocument> for SyslogStorage {
+    async fn store(&self, data: StorageDocument) -> Result<(), StorageError> {
+        self.send(std::slice::from_ref(&data)).await
+    }
+
+    async fn batch_store(&self, data: Vec<StorageDocument>) -> Result<(), StorageError> {
+        self.send(&data).await
+    }
+
+    async fn health_check(&self) -> bool {
+        let mut connection = self.connection.lock().await;
+        if connection.is_some() {
+            return true;
+        }
+        match self.connect().await {
+            Ok(established) => {
+                *connection = Some(established);
+                true
+            }
+            Err(e) => {
+                warn!("Syslog health check failed: {}", e);
+                false
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for SyslogStorage {
+    fn name(&self) -> &str {
+        "syslog"
+    }
+
+    // Envelopes carry their severity into the message so it can drive PRI
+    async fn store_envelopes(&self, envelopes: &[EventEnvelope]) -> Result<(), StorageError> {
+        let mut documents = StorageDocument::from_envelopes(envelopes)?;
+        for (document, envelope) in documents.iter_mut().zip(envelopes) {
+            if let Value::Object(fields) = &mut document.body {
+                fields.insert(
+                    String::from("severity"),
+                    serde_json::to_value(envelope.severity).unwrap_or(Value::Null),
+                );
+            }
+        }
+        self.send(&documents).await
+    }
+}
@@ -0,0 +1,154 @@
+use crate::shared::storage::ilm::IlmConfig;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+// Float fields whose first value is often a whole number, which dynamic mapping would
+// otherwise turn into a long and truncate every later value to
+const FLOAT_FIELDS: &[&str] = &[
+    "cpu_usage",
+    "one_minute",
+    "five_minutes",
+    "fifteen_minutes",
+    "*_per_minute",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexNaming {
+    // One index per logical name, e.g. file_events
+    Static,
+    // A new index per day (or per date_format), e.g. lsedr-file-events-2024.06.01
+    Dated,
+    // One data stream per logical name, e.g. lsedr-file-events
+    DataStream,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexNamingConfig {
+    pub scheme: IndexNaming,
+    // Prepended to dated indices and data streams; Elasticsearch requires lower case
+    pub prefix: String,
+    // chrono format of the date suffix of dated indices, evaluated in UTC
+    pub date_format: String,
+}
+
+impl Default for IndexNamingConfig {
+    fn default() -> Self {
+        Self {
+            scheme: IndexNaming::Static,
+            prefix: String::from("lsedr-"),
+            date_format: String::from("%Y.%m.%d"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemplateConfig {
+    pub install_on_startup: bool,
+    // Composable templates with a higher priority win over ours
+    pub priority: u32,
+    pub number_of_shards: u32,
+    pub number_of_replicas: u32,
+}
+
+impl Default for TemplateConfig {
+    fn default() -> Self {
+        Self {
+            install_on_startup: true,
+            priority: 200,
+            number_of_shards: 1,
+            number_of_replicas: 1,
+        }
+    }
+}
+
+impl IndexNamingConfig {
+    fn base_name(&self, index: &str) -> String {
+        format!("{}{}", self.prefix, index.replace('_', "-"))
+    }
+
+    // Index or data stream a document for `index` is written to right now
+    pub fn write_target(&self, index: &str) -> String {
+        match self.scheme {
+            IndexNaming::Static => index.to_string(),
+            IndexNaming::Dated => format!("{}-{}", self.base_name(index), Utc::now().format(&self.date_format)),
+            IndexNaming::DataStream => self.base_name(index),
+        }
+    }
+
+    // Everything ever written for `index`, for searches and settings updates
+    pub fn read_target(&self, index: &str) -> String {
+        match self.scheme {
+            IndexNaming::Static | IndexNaming::DataStream => self.write_target(index),
+            IndexNaming::Dated => format!("{}-*", self.base_name(index)),
+        }
+    }
+
+    pub fn template_name(index: &str) -> String {
+        format!("lsedr-{}-template", index.replace('_', "-"))
+    }
+
+    pub fn template_body(&self, index: &str, config: &TemplateConfig) -> Value {
+        let mut settings = json!({
+            "number_of_shards": config.number_of_shards,
+            "number_of_replicas": config.number_of_replicas,
+        });
+        // A static index has no rollover alias, so its policy is only attached on request
+        // (ilm.attach_to_existing_indices)
+        if self.scheme != IndexNaming::Static {
+            settings["index.lifecycle.name"] = json!(IlmConfig::policy_name(index));
+        }
+
+        let mut dynamic_templates: Vec<Value> = FLOAT_FIELDS
+            .iter()
+            .map(|field| {
+                json!({
+                    format!("float_{}", field.trim_start_matches("*_")): {
+                        "match": field,
+                        "match_mapping_type": "long",
+                        "mapping": { "type": "double" }
+                    }
+                })
+            })
+            .collect();
+        dynamic_templates.push(json!({
+            "doubles": {
+                "match_mapping_type": "double",
+                "mapping": { "type": "double" }
+            }
+        }));
+        // timestamp_local and the like; the UTC timestamp of a SystemTime is an object
+        dynamic_templates.push(json!({
+            "timestamps": {
+                "match": "*timestamp*",
+                "match_mapping_type": "string",
+                "mapping": { "type": "date" }
+            }
+        }));
+
+        let mut body = json!({
+            "index_patterns": [self.read_target(index)],
+            "priority": config.priority,
+            "_meta": { "managed_by": "lsedr" },
+            "template": {
+                "settings": settings,
+                "mappings": {
+                    "dynamic_templates": dynamic_templates,
+                    "properties": {
+                        "@timestamp": { "type": "date" },
+                        "event_ingested": { "type": "date" },
+                        "timezone": { "type": "keyword" },
+                        "utc_offset": { "type": "keyword" }
+                    }
+                }
+            }
+        });
+        if self.scheme == IndexNaming::DataStream {
+            body["data_stream"] = json!({});
+        }
+        body
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+// Field listing what was cut from a document, as dotted paths without array positions
+pub const TRUNCATED_FIELD: &str = "truncated_fields";
+
+const MARKER: &str = "...";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TruncationConfig {
+    // Strings longer than this many characters, e.g. command lines, are cut; 0 keeps them whole
+    pub max_string_chars: usize,
+    // Arrays longer than this keep their first items, e.g. process_info of a busy server;
+    // 0 keeps them whole
+    pub max_array_items: usize,
+}
+
+impl TruncationConfig {
+    pub fn enabled(&self) -> bool {
+        self.max_string_chars > 0 || self.max_array_items > 0
+    }
+
+    // Cuts the document down to the limits and records what was cut in TRUNCATED_FIELD
+    pub fn apply(&self, document: &mut Value) {
+        if !self.enabled() {
+            return;
+        }
+        let mut truncated = BTreeSet::new();
+        self.truncate(document, "", &mut truncated);
+        if let (Value::Object(fields), false) = (document, truncated.is_empty()) {
+            fields.insert(
+                TRUNCATED_FIELD.to_string(),
+                Value::Array(truncated.into_iter().map(Value::String).collect()),
+            );
+        }
+    }
+
+    fn truncate(&self, value: &mut Value, path: &str, truncated: &mut BTreeSet<String>) {
+        match value {
+            Value::String(text) if self.max_string_chars > 0 => {
+                if let Some((cut, _)) = text.char_indices().nth(self.max_string_chars) {
+                    text.truncate(cut);
+                    text.push_str(MARKER);
+                    truncated.insert(path.to_string());
+                }
+            }
+            Value::Array(items) => {
+                if self.max_array_items > 0 && items.len() > self.max_array_items {
+                    items.truncate(self.max_array_items);
+                    truncated.insert(path.to_string());
+                }
+                for item in items {
+                    self.truncate(item, path, truncated);
+                }
+            }
+            Value::Object(fields) => {
+                for (name, field) in fields {
+                    let path = if path.is_empty() { name.clone() } else { format!("{}.{}", path, name) };
+                    self.truncate(field, &path, truncated);
+                }
+            }
+            _ => {}
+        }
+    }
+}
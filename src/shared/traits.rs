@@ -1,9 +1,12 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Serialize, Deserialize};
+use serde_json::Value;
 use crate::shared::error::{CollectionError, ProcessingError, StorageError};
+use crate::shared::storage::retry::RetryPolicy;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Severity {
     Low,
     Medium,
@@ -35,6 +38,18 @@ pub trait DataStorage<T: Send + Sync> {
     async fn store(&self, data: T) -> Result<(), StorageError>;
     async fn batch_store(&self, data: Vec<T>) -> Result<(), StorageError>;
     async fn health_check(&self) -> bool;
+
+    // batch_store, repeated under the policy while it fails with a transient error. For
+    // backends that write a batch all or nothing; Elasticsearch retries the rejected
+    // documents of a bulk request itself.
+    async fn batch_store_with_retry(&self, data: Vec<T>, policy: &RetryPolicy) -> Result<(), StorageError>
+    where
+        T: Clone + 'async_trait,
+    {
+        policy
+            .run("Batch write", StorageError::is_transient, || self.batch_store(data.clone()))
+            .await
+    }
 }
 
 pub trait MetadataProvider {
@@ -55,9 +70,83 @@ pub trait Identifiable {
     fn category(&self) -> &str;
 }
 
+// Any event together with what every consumer needs to know about it, so one code path can
+// carry a batch mixing event types: the index it is stored to, its identity, where and when it
+// happened and how severe it is. The event travels as its serialized document and is read
+// back as its own type with `open`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub index: String,
+    pub id: String,
+    pub category: String,
+    pub event_type: String,
+    pub source: String,
+    pub timestamp: DateTime<Utc>,
+    pub severity: Severity,
+    pub event: Value,
+}
+
+impl EventEnvelope {
+    pub fn wrap<E: Event + Identifiable + Serialize>(index: &str, event: &E) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            index: index.to_string(),
+            id: event.id().to_string(),
+            category: event.category().to_string(),
+            event_type: event.event_type().to_string(),
+            source: event.source().to_string(),
+            timestamp: event.timestamp(),
+            severity: event.severity(),
+            event: serde_json::to_value(event)?,
+        })
+    }
+
+    pub fn wrap_all<E: Event + Identifiable + Serialize>(index: &str, events: &[E]) -> Result<Vec<Self>, serde_json::Error> {
+        events.iter().map(|event| Self::wrap(index, event)).collect()
+    }
+
+    pub fn open<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        T::deserialize(&self.event)
+    }
+}
+
+impl Event for EventEnvelope {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn event_type(&self) -> &str {
+        &self.event_type
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+}
+
+impl Identifiable for EventEnvelope {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn category(&self) -> &str {
+        &self.category
+    }
+}
+
 pub trait Validatable {
     fn validate(&self) -> Result<(), String>;
     fn is_valid(&self) -> bool {
         self.validate().is_ok()
     }
 }
+
+// Adds context to a serialized event on its way to storage, e.g. local time, GeoIP or a
+// resolved user name. Enrichers run in order, each seeing what the ones before it added.
+pub trait Enricher: Send + Sync {
+    fn name(&self) -> &str;
+    fn enrich(&self, index: &str, document: &mut Value);
+}
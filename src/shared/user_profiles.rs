@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+// The home directory of one local or domain user with a profile on this host
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserProfile {
+    pub user: String,
+    pub path: PathBuf,
+}
+
+// Every user profile on the host, not only the account the agent runs as. A profile
+// appears once its user has logged on for the first time.
+pub fn user_profiles() -> Vec<UserProfile> {
+    let mut profiles = platform_profiles();
+    let mut seen = HashSet::new();
+    profiles.retain(|profile| profile.path.is_dir() && seen.insert(profile.path.clone()));
+    profiles.sort_by(|a, b| a.path.cmp(&b.path));
+    profiles
+}
+
+// ProfileList has one subkey per SID that ever logged on. Only local and domain users
+// (S-1-5-21-...) and Entra ID users (S-1-12-1-...) are interactive; SYSTEM and the
+// service accounts have profiles of their own that nobody downloads files into.
+#[cfg(windows)]
+fn platform_profiles() -> Vec<UserProfile> {
+    use windows::core::{HSTRING, PWSTR};
+    use windows::Win32::Foundation::ERROR_NO_MORE_ITEMS;
+    use windows::Win32::System::Registry::{
+        RegCloseKey, RegEnumKeyExW, RegGetValueW, RegOpenKeyExW, HKEY, HKEY_LOCAL_MACHINE, KEY_READ,
+        RRF_RT_REG_EXPAND_SZ, RRF_RT_REG_SZ,
+    };
+
+    const PROFILE_LIST: &str = "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\ProfileList";
+
+    let mut profiles = Vec::new();
+    unsafe {
+        let mut key = HKEY::default();
+        if RegOpenKeyExW(HKEY_LOCAL_MACHINE, &HSTRING::from(PROFILE_LIST), 0, KEY_READ, &mut key).is_err() {
+            return profiles;
+        }
+
+        let mut index = 0u32;
+        loop {
+            let mut name = [0u16; 256];
+            let mut name_len = name.len() as u32;
+            let status = RegEnumKeyExW(
+                key,
+                index,
+                PWSTR(name.as_mut_ptr()),
+                &mut name_len,
+                None,
+                PWSTR::null(),
+                None,
+                None,
+            );
+            if status == ERROR_NO_MORE_ITEMS || status.is_err() {
+                break;
+            }
+            index += 1;
+
+            let sid = String::from_utf16_lossy(&name[..name_len as usize]);
+            if !sid.starts_with("S-1-5-21-") && !sid.starts_with("S-1-12-1-") {
+                continue;
+            }
+
+            // REG_EXPAND_SZ values such as %SystemDrive%\Users\alice are expanded by RegGetValueW
+            let mut data = [0u16; 512];
+            let mut data_len = (data.len() * 2) as u32;
+            let status = RegGetValueW(
+                key,
+                &HSTRING::from(sid.as_str()),
+                &HSTRING::from("ProfileImagePath"),
+                RRF_RT_REG_SZ | RRF_RT_REG_EXPAND_SZ,
+                None,
+                Some(data.as_mut_ptr() as *mut _),
+                Some(&mut data_len),
+            );
+            if status.is_err() {
+                continue;
+            }
+            let chars = (data_len as usize / 2).min(data.len());
+            let path = String::from_utf16_lossy(&data[..chars]).trim_end_matches('\0').to_string();
+            let path = PathBuf::from(path);
+            let user = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or(sid);
+            profiles.push(UserProfile { user, path });
+        }
+        RegCloseKey(key);
+    }
+    profiles
+}
+
+// macOS keeps its accounts in Directory Services rather than /etc/passwd; every user who
+// logged on has a home under /Users. Shared is a drop box every user can write to, and
+// dot-directories are not homes.
+#[cfg(target_os = "macos")]
+fn platform_profiles() -> Vec<UserProfile> {
+    use std::fs;
+
+    let Ok(entries) = fs::read_dir("/Users") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|user| user != "Shared" && !user.starts_with('.'))
+        .map(|user| UserProfile {
+            path: PathBuf::from("/Users").join(&user),
+            user,
+        })
+        .collect()
+}
+
+// Login accounts from /etc/passwd, plus directories under /home for directory-service
+// users (LDAP, SSSD) that have no local passwd entry
+#[cfg(not(any(windows, target_os = "macos")))]
+fn platform_profiles() -> Vec<UserProfile> {
+    use std::fs;
+
+    let mut profiles = Vec::new();
+
+    if let Ok(passwd) = fs::read_to_string("/etc/passwd") {
+        for line in passwd.lines() {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 7 {
+                continue;
+            }
+            let uid: u32 = fields[2].parse().unwrap_or(0);
+            let shell = fields[6];
+            // Regular users start at 1000; 65534 is nobody
+            if uid < 1000 || uid == 65534 || shell.ends_with("nologin") || shell.ends_with("/false") {
+                continue;
+            }
+            profiles.push(UserProfile {
+                user: fields[0].to_string(),
+                path: PathBuf::from(fields[5]),
+            });
+        }
+    }
+
+    if let Ok(entries) = fs::read_dir("/home") {
+        for entry in entries.flatten() {
+            profiles.push(UserProfile {
+                user: entry.file_name().to_string_lossy().into_owned(),
+                path: entry.path(),
+            });
+        }
+    }
+    profiles
+}
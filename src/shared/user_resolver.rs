@@ -0,0 +1,307 @@
+use crate::utils::load_config_section;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind, Users};
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, HLOCAL, PSID};
+use windows::Win32::Security::Authorization::ConvertStringSidToSidW;
+use windows::Win32::Security::{
+    GetTokenInformation, LookupAccountSidW, TokenUser, SID_NAME_USE, TOKEN_QUERY, TOKEN_USER,
+};
+use windows::Win32::System::Memory::LocalFree;
+use windows::Win32::System::Threading::{
+    OpenProcess, OpenProcessToken, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserNameFormat {
+    // "alice"
+    Name,
+    // "CORP\alice"
+    DomainName,
+    // "alice@CORP"
+    Upn,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserResolutionConfig {
+    pub format: UserNameFormat,
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for UserResolutionConfig {
+    fn default() -> Self {
+        Self {
+            format: UserNameFormat::DomainName,
+            cache_ttl_secs: 600,
+        }
+    }
+}
+
+impl UserResolutionConfig {
+    pub fn load() -> Self {
+        load_config_section("user_resolution")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserContext {
+    // SID on Windows, uid on Unix
+    pub id: String,
+    pub name: String,
+    pub domain: Option<String>,
+}
+
+impl UserContext {
+    pub fn display(&self, format: UserNameFormat) -> String {
+        match (format, self.domain.as_deref()) {
+            (UserNameFormat::DomainName, Some(domain)) => format!("{}\\{}", domain, self.name),
+            (UserNameFormat::Upn, Some(domain)) => format!("{}@{}", self.name, domain),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+struct ResolverState {
+    sys: System,
+    users: Users,
+    accounts: HashMap<String, (Instant, Option<UserContext>)>,
+}
+
+// Turns SIDs / uids and process owners into account names. Lookups (especially
+// LookupAccountSidW against a domain controller) can be slow, so results are cached,
+// including misses, for the configured TTL.
+pub struct UserResolver {
+    config: UserResolutionConfig,
+    state: Mutex<ResolverState>,
+}
+
+impl UserResolver {
+    pub fn new() -> Self {
+        Self::with_config(UserResolutionConfig::load())
+    }
+
+    pub fn with_config(config: UserResolutionConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(ResolverState {
+                sys: System::new(),
+                users: Users::new_with_refreshed_list(),
+                accounts: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn global() -> Arc<UserResolver> {
+        static GLOBAL: OnceLock<Arc<UserResolver>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(UserResolver::new())).clone()
+    }
+
+    pub fn format(&self) -> UserNameFormat {
+        self.config.format
+    }
+
+    // Resolves a SID ("S-1-5-21-...") or uid ("1000") to an account
+    pub fn resolve_id(&self, id: &str) -> Option<UserContext> {
+        let mut state = self.state.lock().ok()?;
+        let now = Instant::now();
+        let ttl = Duration::from_secs(self.config.cache_ttl_secs);
+
+        if let Some((resolved_at, context)) = state.accounts.get(id) {
+            if now.duration_since(*resolved_at) < ttl {
+                return context.clone();
+            }
+        }
+
+        let mut context = Self::lookup_local_user(&mut state.users, id);
+        if context.is_none() && cfg!(target_os = "windows") && id.starts_with("S-") {
+            context = Self::lookup_sid_string(id);
+        }
+
+        if context.is_none() {
+            debug!("Unable to resolve account for id {}", id);
+        }
+
+        state.accounts.retain(|_, (resolved_at, _)| now.duration_since(*resolved_at) < ttl);
+        state.accounts.insert(id.to_string(), (now, context.clone()));
+        context
+    }
+
+    // Resolves the account a process runs as: the token owner on Windows, the real uid elsewhere
+    pub fn resolve_process_owner(&self, pid: u32) -> Option<UserContext> {
+        if cfg!(target_os = "windows") {
+            if let Some(context) = Self::lookup_process_token_user(pid) {
+                return Some(context);
+            }
+        }
+
+        let user_id = {
+            let mut state = self.state.lock().ok()?;
+            let sys_pid = Pid::from_u32(pid);
+            state.sys.refresh_processes_specifics(
+                ProcessesToUpdate::Some(&[sys_pid]),
+                true,
+                ProcessRefreshKind::nothing().with_user(UpdateKind::OnlyIfNotSet),
+            );
+            state.sys.process(sys_pid)?.user_id()?.to_string()
+        };
+
+        self.resolve_id(&user_id).or_else(|| {
+            Some(UserContext {
+                id: user_id.clone(),
+                name: user_id,
+                domain: None,
+            })
+        })
+    }
+
+    // Display name for a SID / uid, falling back to the raw id when it cannot be resolved
+    pub fn display_id(&self, id: &str) -> String {
+        self.resolve_id(id)
+            .map(|context| context.display(self.config.format))
+            .unwrap_or_else(|| id.to_string())
+    }
+
+    pub fn display_process_owner(&self, pid: u32) -> Option<String> {
+        self.resolve_process_owner(pid)
+            .map(|context| context.display(self.config.format))
+    }
+
+    fn lookup_local_user(users: &mut Users, id: &str) -> Option<UserContext> {
+        let find = |users: &Users| {
+            users
+                .list()
+                .iter()
+                .find(|user| user.id().to_string() == id)
+                .map(|user| user.name().to_string())
+        };
+
+        let name = match find(users) {
+            Some(name) => name,
+            None => {
+                users.refresh();
+                find(users)?
+            }
+        };
+
+        // sysinfo only enumerates local accounts, so the machine name is the domain
+        let domain = if cfg!(target_os = "windows") {
+            std::env::var("COMPUTERNAME").ok()
+        } else {
+            None
+        };
+
+        Some(UserContext {
+            id: id.to_string(),
+            name,
+            domain,
+        })
+    }
+
+    fn lookup_sid_string(sid: &str) -> Option<UserContext> {
+        let wide: Vec<u16> = sid.encode_utf16().chain(std::iter::once(0)).collect();
+        let mut psid = PSID::default();
+
+        unsafe {
+            if !ConvertStringSidToSidW(PCWSTR(wide.as_ptr()), &mut psid).as_bool() {
+                return None;
+            }
+            let context = Self::lookup_account_sid(psid, sid.to_string());
+            let _ = LocalFree(HLOCAL(psid.0 as isize));
+            context
+        }
+    }
+
+    fn lookup_process_token_user(pid: u32) -> Option<UserContext> {
+        unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+            let mut token = HANDLE::default();
+            let opened = OpenProcessToken(process, TOKEN_QUERY, &mut token).as_bool();
+            CloseHandle(process);
+            if !opened {
+                return None;
+            }
+
+            let mut needed = 0u32;
+            GetTokenInformation(token, TokenUser, None, 0, &mut needed);
+            if needed == 0 {
+                CloseHandle(token);
+                return None;
+            }
+
+            // u64 backing keeps the TOKEN_USER pointer properly aligned
+            let mut buffer = vec![0u64; (needed as usize).div_ceil(8)];
+            let queried = GetTokenInformation(
+                token,
+                TokenUser,
+                Some(buffer.as_mut_ptr() as *mut _),
+                needed,
+                &mut needed,
+            )
+            .as_bool();
+            CloseHandle(token);
+            if !queried {
+                return None;
+            }
+
+            let token_user = &*(buffer.as_ptr() as *const TOKEN_USER);
+            let sid = token_user.User.Sid;
+            let sid_string = Self::sid_to_string(sid).unwrap_or_default();
+            Self::lookup_account_sid(sid, sid_string)
+        }
+    }
+
+    unsafe fn sid_to_string(sid: PSID) -> Option<String> {
+        use windows::Win32::Security::Authorization::ConvertSidToStringSidW;
+
+        let mut raw = PWSTR::null();
+        if !ConvertSidToStringSidW(sid, &mut raw).as_bool() {
+            return None;
+        }
+        let value = raw.to_string().ok();
+        let _ = LocalFree(HLOCAL(raw.0 as isize));
+        value
+    }
+
+    unsafe fn lookup_account_sid(sid: PSID, id: String) -> Option<UserContext> {
+        let mut name = vec![0u16; 256];
+        let mut domain = vec![0u16; 256];
+        let mut name_len = name.len() as u32;
+        let mut domain_len = domain.len() as u32;
+        let mut sid_type = SID_NAME_USE::default();
+
+        if !LookupAccountSidW(
+            PCWSTR::null(),
+            sid,
+            PWSTR(name.as_mut_ptr()),
+            &mut name_len,
+            PWSTR(domain.as_mut_ptr()),
+            &mut domain_len,
+            &mut sid_type,
+        )
+        .as_bool()
+        {
+            return None;
+        }
+
+        let name = String::from_utf16_lossy(&name[..name_len as usize]);
+        let domain = String::from_utf16_lossy(&domain[..domain_len as usize]);
+
+        Some(UserContext {
+            id,
+            name,
+            domain: if domain.is_empty() { None } else { Some(domain) },
+        })
+    }
+}
+
+impl Default for UserResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
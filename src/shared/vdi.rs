@@ -0,0 +1,164 @@
+use crate::shared::user_resolver::UserResolver;
+use crate::utils::load_config_section;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VdiMode {
+    Off,
+    // On when one of the markers of a provisioned clone is present
+    Auto,
+    On,
+}
+
+impl fmt::Display for VdiMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            VdiMode::Off => "off",
+            VdiMode::Auto => "auto",
+            VdiMode::On => "on",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VdiConfig {
+    pub mode: VdiMode,
+    // Registry keys below HKLM on Windows, paths elsewhere, any of which marks this host as a
+    // non-persistent clone in auto mode
+    pub markers: Vec<String>,
+    // Golden image the clones are made from; by default the host name without the pool's
+    // trailing number, so vdi-win11-042 belongs to vdi-win11
+    pub template: Option<String>,
+    // Report <user>@<template> as the event source, so a user's events line up across the
+    // clones they are handed, instead of the clone's throwaway name
+    pub session_identity: bool,
+}
+
+impl Default for VdiConfig {
+    fn default() -> Self {
+        let markers: &[&str] = if cfg!(windows) {
+            &[
+                // Citrix Provisioning target device
+                "SYSTEM\\CurrentControlSet\\Services\\BNIStack",
+                // Citrix Machine Creation Services
+                "SOFTWARE\\Citrix\\MachineIdentityServiceAgent",
+                // VMware Horizon linked and instant clones
+                "SYSTEM\\CurrentControlSet\\Services\\vmware-viewcomposer-ga",
+            ]
+        } else {
+            // No provisioning stack leaves a common mark on Linux clones
+            &[]
+        };
+        Self {
+            mode: VdiMode::Off,
+            markers: markers.iter().map(|marker| marker.to_string()).collect(),
+            template: None,
+            session_identity: true,
+        }
+    }
+}
+
+impl VdiConfig {
+    pub fn load() -> Self {
+        load_config_section("vdi")
+    }
+
+    // Whether this host runs as a VDI clone
+    pub fn active(&self) -> bool {
+        match self.mode {
+            VdiMode::Off => false,
+            VdiMode::On => true,
+            VdiMode::Auto => self.markers.iter().any(|marker| marker_present(marker)),
+        }
+    }
+
+    pub fn template_of(&self, hostname: &str) -> String {
+        match &self.template {
+            Some(template) if !template.trim().is_empty() => template.trim().to_string(),
+            _ => pool_template(hostname),
+        }
+    }
+}
+
+// Settings merged over the configuration on a clone, before environment overrides; `vdi.overlay`
+// in the file replaces them. Hundreds of sessions share one hypervisor's CPU and storage, so the
+// full process table is only sent as changes, executables are neither hashed nor verified on
+// every collection, and large files are not read through to hash them.
+pub const OVERLAY: &str = r#"
+process:
+  mode: delta
+  inventory_every: 0
+  hash_executables: false
+  verify_signatures: false
+settings:
+  max_hash_bytes: 8388608
+  max_known_hashes: 5000
+usb:
+  max_hash_bytes: 8388608
+intervals:
+  collectors:
+    system_metrics: 300
+    process: 60
+"#;
+
+// vdi-win11-042 and VDIWIN11042 come from the pools vdi-win11 and VDIWIN11
+fn pool_template(hostname: &str) -> String {
+    let template = hostname
+        .trim_end_matches(|c: char| c.is_ascii_digit())
+        .trim_end_matches(['-', '_']);
+    if template.is_empty() {
+        hostname.to_string()
+    } else {
+        template.to_string()
+    }
+}
+
+// Processes that run for as long as a user is signed in to the desktop
+const SESSION_SHELLS: &[&str] = if cfg!(windows) {
+    &["explorer.exe"]
+} else {
+    &["gnome-session-binary", "gnome-shell", "plasmashell", "xfce4-session", "mate-session", "cinnamon-session"]
+};
+
+// The user signed in to this clone's desktop: the owner of the longest running session shell.
+// A VDI clone serves one session at a time.
+pub fn session_user() -> Option<String> {
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+    let shell = sys
+        .processes()
+        .values()
+        .filter(|process| {
+            let name = process.name().to_string_lossy();
+            SESSION_SHELLS.iter().any(|shell| name.eq_ignore_ascii_case(shell))
+        })
+        .min_by_key(|process| process.start_time())?;
+    UserResolver::global()
+        .resolve_process_owner(shell.pid().as_u32())
+        .map(|user| user.name)
+}
+
+#[cfg(windows)]
+fn marker_present(marker: &str) -> bool {
+    use windows::core::HSTRING;
+    use windows::Win32::System::Registry::{RegCloseKey, RegOpenKeyExW, HKEY, HKEY_LOCAL_MACHINE, KEY_READ};
+
+    let key_path = marker.trim_start_matches("HKLM\\").trim_start_matches("HKEY_LOCAL_MACHINE\\");
+    unsafe {
+        let mut key = HKEY::default();
+        if RegOpenKeyExW(HKEY_LOCAL_MACHINE, &HSTRING::from(key_path), 0, KEY_READ, &mut key).is_ok() {
+            RegCloseKey(key);
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(not(windows))]
+fn marker_present(marker: &str) -> bool {
+    std::path::Path::new(marker).exists()
+}
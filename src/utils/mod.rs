@@ -1 +1,82 @@
 // Utility functions will be implemented here
+use log::warn;
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+pub mod time;
+
+pub const MONITOR_CONFIG_PATH: &str = "config/monitor.yaml";
+
+// The merged configuration (file plus environment overrides) installed by AgentConfig::load;
+// a reload replaces it for sections read afterwards
+static CONFIG_DOCUMENT: RwLock<Option<serde_yaml::Value>> = RwLock::new(None);
+// Counts installed documents, so long-running collectors can tell their section may have changed
+static CONFIG_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub fn install_config_document(document: serde_yaml::Value) {
+    match CONFIG_DOCUMENT.write() {
+        Ok(mut installed) => {
+            *installed = Some(document);
+            CONFIG_GENERATION.fetch_add(1, Ordering::Release);
+        }
+        Err(_) => warn!("Configuration lock is poisoned, ignoring the new document"),
+    }
+}
+
+pub fn config_generation() -> u64 {
+    CONFIG_GENERATION.load(Ordering::Acquire)
+}
+
+// The installed configuration, or the monitor config file read directly when the agent
+// configuration has not been loaded (library use, tools)
+fn config_document() -> Option<serde_yaml::Value> {
+    if let Some(document) = CONFIG_DOCUMENT.read().ok().and_then(|installed| installed.clone()) {
+        return Some(document);
+    }
+
+    let content = match fs::read_to_string(MONITOR_CONFIG_PATH) {
+        Ok(content) => content,
+        Err(e) => {
+            warn!("Failed to read config {}: {}, using defaults", MONITOR_CONFIG_PATH, e);
+            return None;
+        }
+    };
+    match serde_yaml::from_str(&content) {
+        Ok(document) => Some(document),
+        Err(e) => {
+            warn!("Failed to parse config {}: {}, using defaults", MONITOR_CONFIG_PATH, e);
+            None
+        }
+    }
+}
+
+// Load an optional top-level section of the monitor config, falling back to
+// defaults when the file or section is missing or malformed.
+pub fn load_config_section<T: DeserializeOwned + Default>(section: &str) -> T {
+    let Some(document) = config_document() else {
+        return T::default();
+    };
+
+    match document.get(section) {
+        Some(value) => serde_yaml::from_value(value.clone()).unwrap_or_else(|e| {
+            warn!("Invalid '{}' config section: {}, using defaults", section, e);
+            T::default()
+        }),
+        None => T::default(),
+    }
+}
+
+// Load settings that live at the top level of the monitor config, such as the
+// filesystem `paths` and `settings`
+pub fn load_config_root<T: DeserializeOwned + Default>() -> T {
+    let Some(document) = config_document() else {
+        return T::default();
+    };
+
+    serde_yaml::from_value(document).unwrap_or_else(|e| {
+        warn!("Invalid config {}: {}, using defaults", MONITOR_CONFIG_PATH, e);
+        T::default()
+    })
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Local, SecondsFormat, TimeZone, Utc};
+use serde_json::Value;
+use std::sync::OnceLock;
+
+// IANA name of the host time zone, e.g. "Asia/Taipei"; falls back to "Local"
+// when the platform does not expose one
+pub fn host_timezone() -> &'static str {
+    static TIMEZONE: OnceLock<String> = OnceLock::new();
+    TIMEZONE.get_or_init(|| iana_time_zone::get_timezone().unwrap_or_else(|_| String::from("Local")))
+}
+
+pub fn parse_timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::String(text) => DateTime::parse_from_rfc3339(text)
+            .ok()
+            .map(|timestamp| timestamp.with_timezone(&Utc)),
+        // SystemTime serializes as {"secs_since_epoch", "nanos_since_epoch"}
+        Value::Object(fields) => {
+            let secs = fields.get("secs_since_epoch")?.as_i64()?;
+            let nanos = fields.get("nanos_since_epoch").and_then(Value::as_u64).unwrap_or(0);
+            Utc.timestamp_opt(secs, nanos as u32).single()
+        }
+        _ => None,
+    }
+}
+
+// Adds `timestamp_local`, `timezone` and `utc_offset` next to the UTC `timestamp`
+// of a serialized document. The offset is the one in effect at the event time, so
+// documents on either side of a DST change stay correct.
+pub fn annotate_local_time(document: &mut Value) {
+    let Value::Object(fields) = document else {
+        return;
+    };
+    if fields.contains_key("timestamp_local") {
+        return;
+    }
+    let Some(timestamp) = fields.get("timestamp").and_then(parse_timestamp) else {
+        return;
+    };
+
+    let local = timestamp.with_timezone(&Local);
+    fields.insert(
+        String::from("timestamp_local"),
+        Value::String(local.to_rfc3339_opts(SecondsFormat::Micros, false)),
+    );
+    fields.insert(String::from("timezone"), Value::String(host_timezone().to_string()));
+    fields.insert(String::from("utc_offset"), Value::String(local.offset().to_string()));
+}
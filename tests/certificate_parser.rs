@@ -0,0 +1,102 @@
+// X.509 certificates through the DER parser of the TLS probe, and DER it must refuse
+
+use chrono::{TimeZone, Utc};
+use lsedr::features::tls_probe::parse_certificate;
+use sha2::{Digest, Sha256};
+
+const SHA256_WITH_RSA: &str = "1.2.840.113549.1.1.11";
+const SHA1_WITH_RSA: &str = "1.2.840.113549.1.1.5";
+
+fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let length = contents.len() as u16;
+    let header = match length {
+        0..=0x7F => vec![tag, length as u8],
+        0x80..=0xFF => vec![tag, 0x81, length as u8],
+        _ => [&[tag, 0x82][..], &length.to_be_bytes()].concat(),
+    };
+    [header, contents.to_vec()].concat()
+}
+
+fn sequence(elements: &[Vec<u8>]) -> Vec<u8> {
+    tlv(0x30, &elements.concat())
+}
+
+fn oid(dotted: &str) -> Vec<u8> {
+    let arcs: Vec<u32> = dotted.split('.').map(|arc| arc.parse().expect("numeric arc")).collect();
+    let mut contents = vec![(arcs[0] * 40 + arcs[1]) as u8];
+    for arc in &arcs[2..] {
+        let mut groups = vec![(arc & 0x7F) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            groups.push((rest & 0x7F) as u8 | 0x80);
+            rest >>= 7;
+        }
+        contents.extend(groups.into_iter().rev());
+    }
+    tlv(0x06, &contents)
+}
+
+fn common_name(name: &str) -> Vec<u8> {
+    sequence(&[tlv(0x31, &sequence(&[oid("2.5.4.3"), tlv(0x0C, name.as_bytes())]))])
+}
+
+fn certificate(signature: &str, modulus_bits: usize, not_before: &[u8]) -> Vec<u8> {
+    let algorithm = sequence(&[oid(signature), tlv(0x05, &[])]);
+    // Top bit of the modulus set, behind the zero byte that keeps the integer positive
+    let mut modulus = vec![0u8; modulus_bits / 8 + 1];
+    modulus[1] = 0x80;
+    let rsa_key = sequence(&[tlv(0x02, &modulus), tlv(0x02, &[0x01, 0x00, 0x01])]);
+    let key = sequence(&[
+        sequence(&[oid("1.2.840.113549.1.1.1"), tlv(0x05, &[])]),
+        tlv(0x03, &[&[0][..], &rsa_key].concat()),
+    ]);
+    let alt_names = sequence(&[oid("2.5.29.17"), tlv(0x04, &sequence(&[tlv(0x82, b"example.com")]))]);
+    let tbs = sequence(&[
+        tlv(0xA0, &tlv(0x02, &[2])),
+        tlv(0x02, &[0x01, 0x23, 0x45]),
+        algorithm.clone(),
+        common_name("Example Root"),
+        sequence(&[tlv(0x17, not_before), tlv(0x17, b"250601120000Z")]),
+        common_name("example.com"),
+        key,
+        tlv(0xA3, &sequence(&[alt_names])),
+    ]);
+    sequence(&[tbs, algorithm, tlv(0x03, &[0, 0xAA, 0xBB])])
+}
+
+#[test]
+fn parses_certificate() {
+    let der = certificate(SHA256_WITH_RSA, 2048, b"240601000000Z");
+    let info = parse_certificate(&der).expect("certificate");
+    assert_eq!(info.subject, "CN=example.com");
+    assert_eq!(info.issuer, "CN=Example Root");
+    assert_eq!(info.serial, "012345");
+    assert_eq!(info.not_before, Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap());
+    assert_eq!(info.not_after, Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap());
+    assert_eq!(info.key_algorithm, "RSA");
+    assert_eq!(info.key_bits, Some(2048));
+    assert_eq!(info.alt_names, ["example.com"]);
+    assert_eq!(info.sha256, format!("{:x}", Sha256::digest(&der)));
+    assert!(!info.self_signed());
+    assert!(info.weaknesses().is_empty());
+}
+
+#[test]
+fn reports_weak_signature_and_key() {
+    let info = parse_certificate(&certificate(SHA1_WITH_RSA, 1024, b"240601000000Z")).expect("certificate");
+    assert_eq!(info.weaknesses(), ["sha1WithRSAEncryption signature", "1024-bit RSA key"]);
+}
+
+#[test]
+fn refuses_malformed_der() {
+    let der = certificate(SHA256_WITH_RSA, 2048, b"240601000000Z");
+    let cases: &[(&str, Vec<u8>)] = &[
+        ("empty", Vec::new()),
+        ("length past the end", vec![0x30, 0x82, 0x10, 0x00, 0x30, 0x00]),
+        ("bad month", certificate(SHA256_WITH_RSA, 2048, b"241301000000Z")),
+        ("truncated", der[..der.len() - 1].to_vec()),
+    ];
+    for (name, der) in cases {
+        assert!(parse_certificate(der).is_err(), "{}: accepted", name);
+    }
+}
@@ -0,0 +1,138 @@
+// Synthetic load through the per-event hot paths, held to the memory and CPU budgets the
+// agent is rolled out with. Run in release mode, as the fleet binary is built:
+//   cargo test --release --test load_budget
+// LSEDR_BUDGET_RSS_MB and LSEDR_BUDGET_CPU_MS override the budgets, e.g. on slow runners.
+// Unoptimized builds skip the budget checks, they are too far from the fleet binary to judge.
+#![cfg(target_os = "linux")]
+
+use chrono::Utc;
+use lsedr::features::process::{ProcessInformation, ProcessInformationBuilder};
+use lsedr::shared::patterns::{PatternSet, DEFAULT_PATTERNS};
+use lsedr::shared::storage::{SnapshotDeltaConfig, SnapshotDeltaEncoder, StorageDocument};
+use lsedr::utils::install_config_document;
+use serde_json::json;
+use std::time::Duration;
+
+// An hour of a busy host at the default intervals: 60 snapshots of a 500 process table
+// and about 100 process events a second
+const SNAPSHOTS: u32 = 60;
+const SNAPSHOT_PROCESSES: u32 = 500;
+const EVENTS: u32 = 360_000;
+
+// Growth of the peak resident memory of the test process over the load
+const RSS_BUDGET_MB: u64 = 64;
+// CPU time, user and system, spent on the load above
+const CPU_BUDGET_MS: u64 = 10_000;
+
+fn budget(variable: &str, default: u64) -> u64 {
+    std::env::var(variable)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+// A resident set size field of /proc/self/status, e.g. VmRSS or VmHWM (the peak)
+fn status_kb(field: &str) -> u64 {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(field)?.strip_prefix(':'))
+                .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+        })
+        .unwrap_or_else(|| panic!("{} in /proc/self/status", field))
+}
+
+fn cpu_time() -> Duration {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    assert_eq!(unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) }, 0);
+    let time = |value: libc::timeval| Duration::new(value.tv_sec as u64, value.tv_usec as u32 * 1000);
+    time(usage.ru_utime) + time(usage.ru_stime)
+}
+
+fn process(pid: u32, cycle: u32) -> ProcessInformation {
+    // Every tenth process changes between snapshots, the rest only in carried fields
+    let generation = if pid % 10 == 1 { cycle } else { 0 };
+    ProcessInformationBuilder::new()
+        .timestamp(Utc::now())
+        .source(String::from("load-host"))
+        .category(String::from("process"))
+        .pid(pid)
+        .name(format!("worker-{}", pid % 40))
+        .cpu_usage((pid + cycle) as f32 % 100.0)
+        .memory_usage(32_000_000 + pid as u64 * 4096)
+        .status(String::from("Run"))
+        .user(String::from("svc-app"))
+        .command(format!("/usr/bin/worker-{} --id {} --generation {}", pid % 40, pid, generation))
+        .threads(8)
+        .build()
+        .expect("valid process")
+}
+
+#[test]
+fn synthetic_load_stays_within_budget() {
+    // Documents go through the global enrichment chain, which keeps baseline, maintenance and
+    // pseudonym state on disk; installing the configuration points that state at a scratch
+    // directory, keeps the run on defaults otherwise and out of the source tree
+    let directory = std::env::temp_dir().join(format!("lsedr-load-budget-{}", std::process::id()));
+    let document = serde_yaml::from_str(&format!(
+        "baseline: {{directory: '{0}/baseline'}}\n\
+         maintenance: {{state_file: '{0}/maintenance.json'}}\n\
+         privacy: {{mapping_file: '{0}/pseudonyms.jsonl'}}\n",
+        directory.display()
+    ))
+    .expect("scratch configuration");
+    install_config_document(document);
+
+    let defaults: Vec<String> = DEFAULT_PATTERNS.iter().map(|pattern| pattern.to_string()).collect();
+    let patterns = PatternSet::compile(&defaults);
+    let encoder = SnapshotDeltaEncoder::new(SnapshotDeltaConfig {
+        enabled: true,
+        ..SnapshotDeltaConfig::default()
+    });
+    let started = cpu_time();
+    let rss_before_kb = status_kb("VmRSS");
+
+    let mut stored_bytes = 0usize;
+    let mut suspicious = 0usize;
+    for cycle in 0..SNAPSHOTS {
+        let snapshot = json!({
+            "source": "load-host",
+            "process_info": (1..=SNAPSHOT_PROCESSES).map(|pid| process(pid, cycle)).collect::<Vec<_>>(),
+        });
+        let document = encoder.encode("system_snapshot", &snapshot).expect("encodable snapshot");
+        stored_bytes += document.body.to_string().len();
+
+        for pid in 0..EVENTS / SNAPSHOTS {
+            let event = process(pid + 1, cycle);
+            if !patterns.matches(&event.command).is_empty() {
+                suspicious += 1;
+            }
+            let document = StorageDocument::new("process_metrics", &event).expect("serializable event");
+            stored_bytes += document.body.to_string().len();
+        }
+    }
+
+    let cpu = cpu_time() - started;
+    let rss_mb = status_kb("VmHWM").saturating_sub(rss_before_kb) / 1024;
+    std::fs::remove_dir_all(&directory).ok();
+    let (rss_budget, cpu_budget) = (budget("LSEDR_BUDGET_RSS_MB", RSS_BUDGET_MB), budget("LSEDR_BUDGET_CPU_MS", CPU_BUDGET_MS));
+    let figures = format!(
+        "{} events and {} snapshots: {} bytes stored, {} ms CPU (budget {}), peak resident memory up {} MB (budget {})",
+        EVENTS,
+        SNAPSHOTS,
+        stored_bytes,
+        cpu.as_millis(),
+        cpu_budget,
+        rss_mb,
+        rss_budget
+    );
+
+    assert_eq!(suspicious, 0, "synthetic worker command lines matched suspicious patterns; {}", figures);
+    if cfg!(debug_assertions) {
+        return;
+    }
+    assert!(rss_mb <= rss_budget, "peak resident memory over budget; {}", figures);
+    assert!(cpu.as_millis() as u64 <= cpu_budget, "CPU time over budget; {}", figures);
+}
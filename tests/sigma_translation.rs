@@ -0,0 +1,324 @@
+// Sigma rules through the translator into agent rules: modifiers, quantifiers, negation
+// pushed down into disjunctive normal form, aggregations, and rules that must be skipped.
+
+use lsedr::features::detection::{load_sigma_rules, FieldMatcher, Rule};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static CASE: AtomicUsize = AtomicUsize::new(0);
+
+// Translates one rule file, in a directory of its own
+fn translate(yaml: &str) -> Vec<Rule> {
+    let directory: PathBuf = std::env::temp_dir().join(format!(
+        "lsedr-sigma-{}-{}",
+        std::process::id(),
+        CASE.fetch_add(1, Ordering::SeqCst)
+    ));
+    std::fs::create_dir_all(&directory).expect("temporary rule directory");
+    std::fs::write(directory.join("rule.yml"), yaml).expect("temporary rule file");
+    let rules = load_sigma_rules(&[directory.to_string_lossy().into_owned()]);
+    std::fs::remove_dir_all(&directory).ok();
+    rules
+}
+
+fn process_rule(detection: &str) -> String {
+    format!("title: case\nid: case\nlogsource:\n  category: process_creation\ndetection:\n{}", detection)
+}
+
+fn describe(matcher: &FieldMatcher) -> String {
+    let mut parts = Vec::new();
+    let comparisons = [
+        ("=", &matcher.equals),
+        ("~", &matcher.contains),
+        ("^=", &matcher.starts_with),
+        ("$=", &matcher.ends_with),
+        ("=~", &matcher.regex),
+    ];
+    for (operator, value) in comparisons {
+        if let Some(value) = value {
+            parts.push(format!("{} {} {}", matcher.field, operator, value));
+        }
+    }
+    if let Some(bound) = matcher.gt {
+        parts.push(format!("{} > {}", matcher.field, bound));
+    }
+    if let Some(bound) = matcher.lt {
+        parts.push(format!("{} < {}", matcher.field, bound));
+    }
+    if let Some(exists) = matcher.exists {
+        parts.push(format!("{} exists {}", matcher.field, exists));
+    }
+    let mut description = parts.join(" & ");
+    if matcher.negate {
+        description = format!("!{}", description);
+    }
+    if matcher.case_sensitive {
+        description.push_str(" (cs)");
+    }
+    description
+}
+
+// The rule's condition as alternatives of conjunctions, the shared matchers in each
+fn dnf(rule: &Rule) -> Vec<Vec<String>> {
+    let shared: Vec<String> = rule.matchers.iter().map(describe).collect();
+    if rule.alternatives.is_empty() {
+        return vec![shared];
+    }
+    rule.alternatives
+        .iter()
+        .map(|alternative| shared.iter().cloned().chain(alternative.iter().map(describe)).collect())
+        .collect()
+}
+
+#[test]
+fn translates_modifiers() {
+    let cases: &[(&str, &str, &[&[&str]])] = &[
+        (
+            "plain value",
+            "  sel:\n    CommandLine: whoami\n  condition: sel",
+            &[&["command = whoami"]],
+        ),
+        (
+            "contains",
+            "  sel:\n    CommandLine|contains: whoami\n  condition: sel",
+            &[&["command ~ whoami"]],
+        ),
+        (
+            "startswith",
+            "  sel:\n    CommandLine|startswith: cmd\n  condition: sel",
+            &[&["command ^= cmd"]],
+        ),
+        (
+            "endswith",
+            "  sel:\n    CommandLine|endswith: .ps1\n  condition: sel",
+            &[&["command $= .ps1"]],
+        ),
+        (
+            "image path to its file name",
+            "  sel:\n    Image|endswith: '\\cmd.exe'\n  condition: sel",
+            &[&["name = cmd.exe"]],
+        ),
+        (
+            "regex is case sensitive",
+            "  sel:\n    CommandLine|re: 'a.*b'\n  condition: sel",
+            &[&["command =~ (?:a.*b) (cs)"]],
+        ),
+        (
+            "regex with i",
+            "  sel:\n    CommandLine|re|i: 'a.*b'\n  condition: sel",
+            &[&["command =~ (?:a.*b)"]],
+        ),
+        (
+            "several values share one regex",
+            "  sel:\n    CommandLine|contains:\n      - mimikatz\n      - 'sekurlsa::'\n  condition: sel",
+            &[&["command =~ (?:mimikatz|sekurlsa::)"]],
+        ),
+        (
+            "all needs every value",
+            "  sel:\n    CommandLine|contains|all:\n      - net\n      - user\n  condition: sel",
+            &[&["command ~ net", "command ~ user"]],
+        ),
+        (
+            "wildcards",
+            "  sel:\n    CommandLine: '*foo?'\n  condition: sel",
+            &[&["command =~ (?:^.*foo.$)"]],
+        ),
+        (
+            "escaped wildcard",
+            "  sel:\n    CommandLine: 'a\\*b'\n  condition: sel",
+            &[&["command = a*b"]],
+        ),
+        (
+            "null means absent",
+            "  sel:\n    User: null\n  condition: sel",
+            &[&["user exists false"]],
+        ),
+        (
+            "gt",
+            "  sel:\n    ProcessId|gt: 4\n  condition: sel",
+            &[&["pid > 4"]],
+        ),
+        (
+            "gte is greater or equal",
+            "  sel:\n    ProcessId|gte: 4\n  condition: sel",
+            &[&["pid > 4"], &["pid = 4"]],
+        ),
+        (
+            "fields of a map all hold",
+            "  sel:\n    Image|endswith: '\\net.exe'\n    CommandLine|contains: user\n  condition: sel",
+            &[&["name = net.exe", "command ~ user"]],
+        ),
+        (
+            "list of maps is a choice",
+            "  sel:\n    - CommandLine|contains: a\n    - User: root\n  condition: sel",
+            &[&["command ~ a"], &["user = root"]],
+        ),
+    ];
+
+    for (name, detection, expected) in cases {
+        let rules = translate(&process_rule(detection));
+        assert_eq!(rules.len(), 1, "{}: rule was skipped", name);
+        assert_eq!(dnf(&rules[0]), *expected, "{}", name);
+    }
+}
+
+#[test]
+fn translates_quantifiers_and_negation() {
+    let selections = "  sel_a:\n    CommandLine|contains: a\n  sel_b:\n    User: root\n  filter:\n    CommandLine|contains: x\n    User: admin\n";
+    let cases: &[(&str, &str, &[&[&str]])] = &[
+        ("and", "sel_a and sel_b", &[&["command ~ a", "user = root"]]),
+        ("or", "sel_a or sel_b", &[&["command ~ a"], &["user = root"]]),
+        ("1 of", "1 of sel_*", &[&["command ~ a"], &["user = root"]]),
+        ("any of", "any of sel_*", &[&["command ~ a"], &["user = root"]]),
+        ("all of", "all of sel_*", &[&["command ~ a", "user = root"]]),
+        (
+            "all of them",
+            "all of them",
+            &[&["command ~ x", "user = admin", "command ~ a", "user = root"]],
+        ),
+        ("not", "not sel_a", &[&["!command ~ a"]]),
+        ("double not", "not not sel_a", &[&["command ~ a"]]),
+        (
+            "not over and",
+            "sel_a and not filter",
+            &[&["command ~ a", "!command ~ x"], &["command ~ a", "!user = admin"]],
+        ),
+        ("not over or", "not (sel_a or sel_b)", &[&["!command ~ a", "!user = root"]]),
+        ("not over 1 of", "not 1 of sel_*", &[&["!command ~ a", "!user = root"]]),
+        (
+            "and binds tighter than or",
+            "sel_a or sel_b and filter",
+            &[&["command ~ a"], &["user = root", "command ~ x", "user = admin"]],
+        ),
+        (
+            "parentheses",
+            "(sel_a or sel_b) and filter",
+            &[&["command ~ a", "command ~ x", "user = admin"], &["user = root", "command ~ x", "user = admin"]],
+        ),
+        (
+            "keywords ignore case",
+            "sel_a AND NOT sel_b",
+            &[&["command ~ a", "!user = root"]],
+        ),
+    ];
+
+    for (name, condition, expected) in cases {
+        let rules = translate(&process_rule(&format!("{}  condition: {}", selections, condition)));
+        assert_eq!(rules.len(), 1, "{}: rule was skipped", name);
+        assert_eq!(dnf(&rules[0]), *expected, "{}", name);
+    }
+
+    // `them` leaves out selections starting with an underscore
+    let rules = translate(&process_rule(
+        "  sel:\n    User: root\n  _helper:\n    User: admin\n  condition: all of them",
+    ));
+    assert_eq!(dnf(&rules[0]), [["user = root"]]);
+}
+
+#[test]
+fn translates_logsources_and_aggregations() {
+    let rules = translate(
+        "title: dropped script\nid: dropped\nlevel: high\nlogsource:\n  category: file_event\ndetection:\n  sel:\n    TargetFilename|endswith: .ps1\n  condition: sel",
+    );
+    assert_eq!(rules.len(), 1);
+    assert_eq!(dnf(&rules[0]), [["event_type = Created", "path $= .ps1"]]);
+    assert_eq!(format!("{:?}", rules[0].severity), "High");
+
+    // Threshold count, window and grouping, or None for a skipped rule
+    type Expected = Option<(u64, u64, &'static [&'static str])>;
+    let cases: &[(&str, &str, Expected)] = &[
+        ("greater than", "sel | count() > 5", Some((6, 60, &[]))),
+        ("at least", "sel | count() >= 5", Some((5, 60, &[]))),
+        ("grouped", "sel | count() by User > 2", Some((3, 60, &["user"]))),
+        ("grouped by two", "sel | count() by User, ProcessId >= 2", Some((2, 60, &["user", "pid"]))),
+        ("count overflow", "sel | count() > 18446744073709551615", None),
+        ("unknown group field", "sel | count() by Hashes > 2", None),
+        ("other aggregation", "sel | max(ProcessId) > 2", None),
+    ];
+    for (name, condition, expected) in cases {
+        let rules = translate(&process_rule(&format!("  sel:\n    User: root\n  condition: {}", condition)));
+        match expected {
+            Some((count, window_secs, group_by)) => {
+                assert_eq!(rules.len(), 1, "{}: rule was skipped", name);
+                let threshold = rules[0].threshold.as_ref().expect("threshold");
+                assert_eq!(threshold.count, *count, "{}", name);
+                assert_eq!(threshold.window_secs, *window_secs, "{}", name);
+                assert_eq!(threshold.group_by, *group_by, "{}", name);
+            }
+            None => assert!(rules.is_empty(), "{}: rule was not skipped", name),
+        }
+    }
+}
+
+#[test]
+fn translates_timeframes() {
+    let cases: &[(&str, Option<u64>)] = &[
+        ("30s", Some(30)),
+        ("5m", Some(300)),
+        ("2h", Some(7200)),
+        ("1d", Some(86400)),
+        (" 1d ", Some(86400)),
+        ("", None),
+        ("d", None),
+        ("5", None),
+        ("5w", None),
+        ("-5m", None),
+        ("5分", None),
+        ("分", None),
+        ("5mé", None),
+        ("213503982334602d", None),
+        ("18446744073709551616s", None),
+    ];
+    for (timeframe, expected) in cases {
+        let rules = translate(&process_rule(&format!(
+            "  sel:\n    User: root\n  timeframe: '{}'\n  condition: sel | count() > 5",
+            timeframe
+        )));
+        match expected {
+            Some(window_secs) => {
+                assert_eq!(rules.len(), 1, "'{}': rule was skipped", timeframe);
+                assert_eq!(rules[0].threshold.as_ref().map(|threshold| threshold.window_secs), Some(*window_secs));
+            }
+            None => assert!(rules.is_empty(), "'{}': rule was not skipped", timeframe),
+        }
+    }
+}
+
+#[test]
+fn skips_unsupported_rules() {
+    let cases: &[(&str, &str)] = &[
+        ("unknown modifier", "  sel:\n    CommandLine|base64offset: x\n  condition: sel"),
+        ("unknown field", "  sel:\n    Hashes: x\n  condition: sel"),
+        ("keyword search", "  keywords:\n    - mimikatz\n  condition: keywords"),
+        ("unknown selection", "  sel:\n    User: root\n  condition: other"),
+        ("unbalanced parentheses", "  sel:\n    User: root\n  condition: (sel"),
+        ("trailing token", "  sel:\n    User: root\n  condition: sel sel"),
+        ("dangling operator", "  sel:\n    User: root\n  condition: sel and"),
+        ("quantifier matching nothing", "  sel:\n    User: root\n  condition: 1 of filter*"),
+        ("unsupported quantifier", "  sel:\n    User: root\n  condition: 2 of sel*"),
+        ("no condition", "  sel:\n    User: root"),
+        ("invalid regex", "  sel:\n    CommandLine|re: '('\n  condition: sel"),
+        ("number modifier on text", "  sel:\n    ProcessId|gt: many\n  condition: sel"),
+        ("path prefix on a file name field", "  sel:\n    Image|startswith: 'C:\\Windows\\'\n  condition: sel"),
+        ("empty value list", "  sel:\n    User: []\n  condition: sel"),
+    ];
+    for (name, detection) in cases {
+        assert!(translate(&process_rule(detection)).is_empty(), "{}: rule was not skipped", name);
+    }
+
+    // Beyond the alternative limit: 9 alternatives, each of 9 values, in a conjunction
+    let mut detection = String::new();
+    for selection in 0..3 {
+        detection.push_str(&format!("  sel{}:\n", selection));
+        for value in 0..9 {
+            detection.push_str(&format!("    - CommandLine|contains: v{}x{}\n", selection, value));
+        }
+    }
+    detection.push_str("  condition: all of sel*");
+    assert!(translate(&process_rule(&detection)).is_empty(), "too many alternatives");
+
+    let rules = translate(
+        "title: other category\nlogsource:\n  category: dns_query\ndetection:\n  sel:\n    User: root\n  condition: sel",
+    );
+    assert!(rules.is_empty(), "unsupported logsource");
+}
@@ -0,0 +1,97 @@
+// SNMP traps and informs through the BER parser, and datagrams it must refuse
+
+use lsedr::features::snmp_trap::{parse_trap, SnmpVariable, SnmpVersion};
+use serde_json::json;
+
+const SNMP_TRAP_OID: &str = "1.3.6.1.6.3.1.1.4.1.0";
+const LINK_DOWN: &str = "1.3.6.1.6.3.1.1.5.3";
+const IF_INDEX: &str = "1.3.6.1.2.1.2.2.1.1.2";
+
+fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    assert!(contents.len() < 0x80, "short length form only");
+    [&[tag, contents.len() as u8][..], contents].concat()
+}
+
+fn sequence(elements: &[Vec<u8>]) -> Vec<u8> {
+    tlv(0x30, &elements.concat())
+}
+
+// Arcs below 128 only, one byte each
+fn oid(dotted: &str) -> Vec<u8> {
+    let arcs: Vec<u8> = dotted.split('.').map(|arc| arc.parse().expect("arc below 128")).collect();
+    tlv(0x06, &[&[arcs[0] * 40 + arcs[1]][..], &arcs[2..]].concat())
+}
+
+fn binding(name: &str, value: Vec<u8>) -> Vec<u8> {
+    sequence(&[oid(name), value])
+}
+
+fn v2_message(tag: u8, variables: &[Vec<u8>]) -> Vec<u8> {
+    let pdu = [tlv(0x02, &[7]), tlv(0x02, &[0]), tlv(0x02, &[0]), sequence(variables)];
+    sequence(&[tlv(0x02, &[1]), tlv(0x04, b"private"), tlv(tag, &pdu.concat())])
+}
+
+#[test]
+fn parses_v1_trap() {
+    let pdu = [
+        oid("1.3.6.1.4.1.9"),
+        tlv(0x40, &[10, 0, 0, 1]),
+        tlv(0x02, &[6]),
+        tlv(0x02, &[42]),
+        tlv(0x43, &[0x01, 0x00]),
+        sequence(&[]),
+    ];
+    let datagram = sequence(&[tlv(0x02, &[0]), tlv(0x04, b"public"), tlv(0xA4, &pdu.concat())]);
+    let trap = parse_trap(&datagram).expect("v1 trap");
+    assert_eq!(trap.version, SnmpVersion::V1);
+    assert_eq!(trap.community, "public");
+    // Enterprise-specific traps are named under the enterprise
+    assert_eq!(trap.trap_oid, "1.3.6.1.4.1.9.0.42");
+    assert_eq!(trap.agent_address.as_deref(), Some("10.0.0.1"));
+    assert_eq!(trap.uptime, Some(256));
+    assert!(trap.acknowledgement(&datagram).is_none());
+}
+
+#[test]
+fn parses_v2c_trap_and_acknowledges_inform() {
+    let variables = [
+        binding("1.3.6.1.2.1.1.3.0", tlv(0x43, &[0x04, 0xD2])),
+        binding(SNMP_TRAP_OID, oid(LINK_DOWN)),
+        binding(IF_INDEX, tlv(0x02, &[2])),
+    ];
+    let trap = parse_trap(&v2_message(0xA7, &variables)).expect("v2c trap");
+    assert_eq!(trap.version, SnmpVersion::V2c);
+    assert_eq!(trap.trap_oid, LINK_DOWN);
+    assert_eq!(trap.uptime, Some(1234));
+    assert_eq!(
+        trap.variables,
+        [SnmpVariable {
+            oid: IF_INDEX.to_string(),
+            kind: String::from("integer"),
+            value: json!(2),
+        }]
+    );
+
+    // The acknowledgement is the same message with the PDU tagged as a response
+    let datagram = v2_message(0xA6, &variables);
+    let inform = parse_trap(&datagram).expect("inform");
+    assert!(inform.inform);
+    assert_eq!(inform.acknowledgement(&datagram), Some(v2_message(0xA2, &variables)));
+}
+
+#[test]
+fn refuses_malformed_datagrams() {
+    let trap = v2_message(0xA7, &[binding(SNMP_TRAP_OID, oid(LINK_DOWN))]);
+    let cases: &[(&str, Vec<u8>)] = &[
+        ("empty", Vec::new()),
+        ("length past the end", vec![0x30, 0x05, 0x02, 0x01, 0x01]),
+        ("huge length", vec![0x30, 0x84, 0xFF, 0xFF, 0xFF, 0xFF, 0x02, 0x01, 0x01]),
+        ("SNMPv3", sequence(&[tlv(0x02, &[3]), sequence(&[])])),
+        ("get request", v2_message(0xA0, &[binding(SNMP_TRAP_OID, oid(LINK_DOWN))])),
+        ("no snmpTrapOID", v2_message(0xA7, &[])),
+        ("truncated", trap[..trap.len() - 1].to_vec()),
+    ];
+    for (name, datagram) in cases {
+        assert!(parse_trap(datagram).is_err(), "{}: accepted", name);
+    }
+}